@@ -0,0 +1,66 @@
+-- SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+--
+-- Copyleft (c) 2024 James Wong. This file is part of James Wong.
+-- is free software: you can redistribute it and/or modify it under
+-- the terms of the GNU General Public License as published by the
+-- Free Software Foundation, either version 3 of the License, or
+-- (at your option) any later version.
+--
+-- James Wong is distributed in the hope that it will be useful,
+-- but WITHOUT ANY WARRANTY; without even the implied warranty of
+-- MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+-- GNU General Public License for more details.
+--
+-- You should have received a copy of the GNU General Public License
+-- along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+--
+-- IMPORTANT: Any software that fully or partially contains or uses materials
+-- covered by this license must also be released under the GNU GPL license.
+-- This includes modifications and derived works.
+
+create table if not exists documents (
+    id integer primary key not null,
+    key varchar(64) null, -- "文档唯一标识, 由客户端生成"
+    name varchar(64) null, -- "文档名称"
+    folder_key varchar(64) null, -- "所属文件夹 key"
+    type varchar(16) not null default 'Board', -- "文档类型: Board|Note"
+    content text null, -- "文档内容, 如 Excalidraw/BlockSuite 快照 json"
+    status integer null default 0,
+    create_by varchar(64) null,
+    create_time integer null,
+    update_by varchar(64) null,
+    update_time integer null,
+    del_flag integer not null default 0
+);
+create unique index if not exists uk_documents_key on documents (key);
+create index if not exists idx_documents_folder_key on documents (folder_key);
+create index if not exists idx_documents_update_time on documents (update_time);
+
+create table if not exists folders (
+    id integer primary key not null,
+    pid integer null, -- "父文件夹 id, 顶级为 0 或空"
+    key varchar(64) null, -- "文件夹唯一标识, 由客户端生成"
+    name varchar(64) null, -- "文件夹名称"
+    status integer null default 0,
+    create_by varchar(64) null,
+    create_time integer null,
+    update_by varchar(64) null,
+    update_time integer null,
+    del_flag integer not null default 0
+);
+create unique index if not exists uk_folders_key on folders (key);
+create index if not exists idx_folders_pid on folders (pid);
+create index if not exists idx_folders_update_time on folders (update_time);
+
+create table if not exists settings (
+    id integer primary key not null,
+    name varchar(64) null, -- "配置项名称"
+    status integer null default 0,
+    create_by varchar(64) null,
+    create_time integer null,
+    update_by varchar(64) null,
+    update_time integer null,
+    del_flag integer not null default 0
+);
+create unique index if not exists uk_settings_name on settings (name);
+create index if not exists idx_settings_update_time on settings (update_time);
@@ -0,0 +1,47 @@
+-- SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+--
+-- Copyleft (c) 2024 James Wong. This file is part of James Wong.
+-- is free software: you can redistribute it and/or modify it under
+-- the terms of the GNU General Public License as published by the
+-- Free Software Foundation, either version 3 of the License, or
+-- (at your option) any later version.
+--
+-- James Wong is distributed in the hope that it will be useful,
+-- but WITHOUT ANY WARRANTY; without even the implied warranty of
+-- MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+-- GNU General Public License for more details.
+--
+-- You should have received a copy of the GNU General Public License
+-- along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+--
+-- IMPORTANT: Any software that fully or partially contains or uses materials
+-- covered by this license must also be released under the GNU GPL license.
+-- This includes modifications and derived works.
+
+-- The owner user id of the modules data, which stamped from the authenticated claims.
+alter table documents add column owner_uid integer null;
+alter table folders add column owner_uid integer null;
+alter table settings add column owner_uid integer null;
+
+-- Backfill the legacy data owner by the audit creator which is the email or name of user.
+update documents set owner_uid = (
+    select u.id from users u where u.email = documents.create_by or u.name = documents.create_by limit 1
+) where owner_uid is null;
+update folders set owner_uid = (
+    select u.id from users u where u.email = folders.create_by or u.name = folders.create_by limit 1
+) where owner_uid is null;
+update settings set owner_uid = (
+    select u.id from users u where u.email = settings.create_by or u.name = settings.create_by limit 1
+) where owner_uid is null;
+
+-- The uniqueness is only within the same owner.
+drop index if exists uk_documents_key;
+create unique index if not exists uk_documents_owner_uid_key on documents (owner_uid, key);
+create index if not exists idx_documents_owner_uid_folder_key on documents (owner_uid, folder_key);
+
+drop index if exists uk_folders_key;
+create unique index if not exists uk_folders_owner_uid_key on folders (owner_uid, key);
+create index if not exists idx_folders_owner_uid_pid on folders (owner_uid, pid);
+
+drop index if exists uk_settings_name;
+create unique index if not exists uk_settings_owner_uid_name on settings (owner_uid, name);
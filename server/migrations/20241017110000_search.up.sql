@@ -0,0 +1,35 @@
+-- SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+--
+-- Copyleft (c) 2024 James Wong. This file is part of James Wong.
+-- is free software: you can redistribute it and/or modify it under
+-- the terms of the GNU General Public License as published by the
+-- Free Software Foundation, either version 3 of the License, or
+-- (at your option) any later version.
+--
+-- James Wong is distributed in the hope that it will be useful,
+-- but WITHOUT ANY WARRANTY; without even the implied warranty of
+-- MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+-- GNU General Public License for more details.
+--
+-- You should have received a copy of the GNU General Public License
+-- along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+--
+-- IMPORTANT: Any software that fully or partially contains or uses materials
+-- covered by this license must also be released under the GNU GPL license.
+-- This includes modifications and derived works.
+
+-- The full text index of documents names and content (the texts extracted from the boards), which
+-- rowid is the document id and kept in sync by the documents repository.
+create virtual table if not exists documents_fts using fts5(
+    name,
+    content,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+insert into documents_fts (rowid, name, content)
+select d.id, d.name, case
+    when lower(d.type) = 'board' and json_valid(d.content) then (
+        select group_concat(t.value, ' ') from json_tree(d.content) t where t.key = 'text' and t.type = 'text'
+    )
+    else d.content
+end
+from documents d;
@@ -0,0 +1,40 @@
+-- SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+--
+-- Copyleft (c) 2024 James Wong. This file is part of James Wong.
+-- is free software: you can redistribute it and/or modify it under
+-- the terms of the GNU General Public License as published by the
+-- Free Software Foundation, either version 3 of the License, or
+-- (at your option) any later version.
+--
+-- James Wong is distributed in the hope that it will be useful,
+-- but WITHOUT ANY WARRANTY; without even the implied warranty of
+-- MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+-- GNU General Public License for more details.
+--
+-- You should have received a copy of the GNU General Public License
+-- along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+--
+-- IMPORTANT: Any software that fully or partially contains or uses materials
+-- covered by this license must also be released under the GNU GPL license.
+-- This includes modifications and derived works.
+
+-- The public links of the read-only document access, the token is unique globally.
+create table if not exists share_links (
+    id integer primary key not null,
+    owner_uid integer null,
+    document_id integer not null,
+    token varchar(64) not null,
+    password varchar(128) null,
+    expire_time integer null,
+    access_count integer not null default 0,
+    last_access_time integer null,
+    status integer null default 0,
+    create_by varchar(64) null,
+    create_time integer null,
+    update_by varchar(64) null,
+    update_time integer null,
+    del_flag integer not null default 0,
+    version integer not null default 0
+);
+create unique index if not exists uk_share_links_token on share_links (token);
+create index if not exists idx_share_links_owner_uid_document_id on share_links (owner_uid, document_id);
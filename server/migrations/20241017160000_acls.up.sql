@@ -0,0 +1,38 @@
+-- SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+--
+-- Copyleft (c) 2024 James Wong. This file is part of James Wong.
+-- is free software: you can redistribute it and/or modify it under
+-- the terms of the GNU General Public License as published by the
+-- Free Software Foundation, either version 3 of the License, or
+-- (at your option) any later version.
+--
+-- James Wong is distributed in the hope that it will be useful,
+-- but WITHOUT ANY WARRANTY; without even the implied warranty of
+-- MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+-- GNU General Public License for more details.
+--
+-- You should have received a copy of the GNU General Public License
+-- along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+--
+-- IMPORTANT: Any software that fully or partially contains or uses materials
+-- covered by this license must also be released under the GNU GPL license.
+-- This includes modifications and derived works.
+
+-- The roles of the documents or the folder subtrees granted to the other users by the owners.
+create table if not exists acls (
+    id integer primary key not null,
+    owner_uid integer null,
+    grantee_uid integer not null,
+    resource_type varchar(16) not null,
+    resource_id integer not null,
+    role varchar(16) not null,
+    status integer null default 0,
+    create_by varchar(64) null,
+    create_time integer null,
+    update_by varchar(64) null,
+    update_time integer null,
+    del_flag integer not null default 0,
+    version integer not null default 0
+);
+create unique index if not exists uk_acls_resource_grantee_uid on acls (resource_type, resource_id, grantee_uid);
+create index if not exists idx_acls_grantee_uid on acls (grantee_uid);
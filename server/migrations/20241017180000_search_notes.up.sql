@@ -0,0 +1,31 @@
+-- SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+--
+-- Copyleft (c) 2024 James Wong. This file is part of James Wong.
+-- is free software: you can redistribute it and/or modify it under
+-- the terms of the GNU General Public License as published by the
+-- Free Software Foundation, either version 3 of the License, or
+-- (at your option) any later version.
+--
+-- James Wong is distributed in the hope that it will be useful,
+-- but WITHOUT ANY WARRANTY; without even the implied warranty of
+-- MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+-- GNU General Public License for more details.
+--
+-- You should have received a copy of the GNU General Public License
+-- along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+--
+-- IMPORTANT: Any software that fully or partially contains or uses materials
+-- covered by this license must also be released under the GNU GPL license.
+-- This includes modifications and derived works.
+
+-- Re-index the notes by the texts of the BlockSuite snapshot (the delta inserts), rather than the raw
+-- JSON with the block ids and flavours.
+delete from documents_fts where rowid in (
+    select d.id from documents d where lower(d.type) = 'note' and json_valid(d.content)
+);
+insert into documents_fts (rowid, name, content)
+select d.id, d.name, (
+    select group_concat(t.value, ' ') from json_tree(d.content) t where t.key = 'insert' and t.type = 'text'
+)
+from documents d
+where lower(d.type) = 'note' and json_valid(d.content);
@@ -0,0 +1,33 @@
+-- SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+--
+-- Copyleft (c) 2024 James Wong. This file is part of James Wong.
+-- is free software: you can redistribute it and/or modify it under
+-- the terms of the GNU General Public License as published by the
+-- Free Software Foundation, either version 3 of the License, or
+-- (at your option) any later version.
+--
+-- James Wong is distributed in the hope that it will be useful,
+-- but WITHOUT ANY WARRANTY; without even the implied warranty of
+-- MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+-- GNU General Public License for more details.
+--
+-- You should have received a copy of the GNU General Public License
+-- along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+--
+-- IMPORTANT: Any software that fully or partially contains or uses materials
+-- covered by this license must also be released under the GNU GPL license.
+-- This includes modifications and derived works.
+
+drop index if exists idx_settings_update_time;
+drop index if exists uk_settings_name;
+drop table if exists settings;
+
+drop index if exists idx_folders_update_time;
+drop index if exists idx_folders_pid;
+drop index if exists uk_folders_key;
+drop table if exists folders;
+
+drop index if exists idx_documents_update_time;
+drop index if exists idx_documents_folder_key;
+drop index if exists uk_documents_key;
+drop table if exists documents;
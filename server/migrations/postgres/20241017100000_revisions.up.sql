@@ -0,0 +1,34 @@
+-- SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+--
+-- Copyleft (c) 2024 James Wong. This file is part of James Wong.
+-- is free software: you can redistribute it and/or modify it under
+-- the terms of the GNU General Public License as published by the
+-- Free Software Foundation, either version 3 of the License, or
+-- (at your option) any later version.
+--
+-- James Wong is distributed in the hope that it will be useful,
+-- but WITHOUT ANY WARRANTY; without even the implied warranty of
+-- MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+-- GNU General Public License for more details.
+--
+-- You should have received a copy of the GNU General Public License
+-- along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+--
+-- IMPORTANT: Any software that fully or partially contains or uses materials
+-- covered by this license must also be released under the GNU GPL license.
+-- This includes modifications and derived works.
+
+create table if not exists document_revisions (
+    id bigint primary key not null,
+    owner_uid bigint null,
+    document_id bigint not null, -- "所属文档 id"
+    content text null, -- "该版本的文档内容快照"
+    size bigint not null default 0, -- "内容字节数"
+    status smallint null default 0,
+    create_by varchar(64) null,
+    create_time bigint null,
+    update_by varchar(64) null,
+    update_time bigint null,
+    del_flag integer not null default 0
+);
+create index if not exists idx_document_revisions_owner_uid_document_id on document_revisions (owner_uid, document_id, create_time);
@@ -0,0 +1,28 @@
+-- SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+--
+-- Copyleft (c) 2024 James Wong. This file is part of James Wong.
+-- is free software: you can redistribute it and/or modify it under
+-- the terms of the GNU General Public License as published by the
+-- Free Software Foundation, either version 3 of the License, or
+-- (at your option) any later version.
+--
+-- James Wong is distributed in the hope that it will be useful,
+-- but WITHOUT ANY WARRANTY; without even the implied warranty of
+-- MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+-- GNU General Public License for more details.
+--
+-- You should have received a copy of the GNU General Public License
+-- along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+--
+-- IMPORTANT: Any software that fully or partially contains or uses materials
+-- covered by this license must also be released under the GNU GPL license.
+-- This includes modifications and derived works.
+
+-- The optimistic lock version, which is increased on every update, the existing records start from 0.
+alter table users add column if not exists version bigint not null default 0;
+alter table documents add column if not exists version bigint not null default 0;
+alter table folders add column if not exists version bigint not null default 0;
+alter table settings add column if not exists version bigint not null default 0;
+alter table document_revisions add column if not exists version bigint not null default 0;
+alter table blobs add column if not exists version bigint not null default 0;
+alter table document_blobs add column if not exists version bigint not null default 0;
@@ -0,0 +1,38 @@
+-- SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+--
+-- Copyleft (c) 2024 James Wong. This file is part of James Wong.
+-- is free software: you can redistribute it and/or modify it under
+-- the terms of the GNU General Public License as published by the
+-- Free Software Foundation, either version 3 of the License, or
+-- (at your option) any later version.
+--
+-- James Wong is distributed in the hope that it will be useful,
+-- but WITHOUT ANY WARRANTY; without even the implied warranty of
+-- MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+-- GNU General Public License for more details.
+--
+-- You should have received a copy of the GNU General Public License
+-- along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+--
+-- IMPORTANT: Any software that fully or partially contains or uses materials
+-- covered by this license must also be released under the GNU GPL license.
+-- This includes modifications and derived works.
+
+-- Re-extract the search text of the notes by the texts of the BlockSuite snapshot (the delta inserts),
+-- rather than the raw JSON with the block ids and flavours, and the invalid (such as the compressed or
+-- the legacy plain text) are skipped.
+do $$
+declare
+    r record;
+begin
+    for r in select id, content from documents where lower(type) = 'note' loop
+        begin
+            update documents set search_text = (
+                select string_agg(v #>> '{}', ' ')
+                from jsonb_path_query(r.content::jsonb, 'strict $.**.insert ? (@.type() == "string")') v
+            ) where id = r.id;
+        exception when others then
+            null;
+        end;
+    end loop;
+end $$;
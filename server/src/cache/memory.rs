@@ -21,22 +21,31 @@
  */
 
 use std::ops::Deref;
-use std::sync::Arc;
+use std::sync::{ Arc, Mutex };
 use std::time::Duration;
 use std::collections::HashMap;
 
 use anyhow::{ Error, Ok };
 use axum::async_trait;
+use futures::stream::{ self, BoxStream, StreamExt };
 use moka::policy::EvictionPolicy;
 use moka::future::Cache;
 use regex::Regex;
+use tokio::sync::broadcast::{ self, error::RecvError };
 
 use crate::config::config_serve::MemoryProperties;
 
 use super::ICache;
 
+// The capacity of the messages of per channel, which are dropped for the lagged subscribers.
+const CHANNEL_CAPACITY: usize = 1024;
+
 pub struct StringMemoryCache {
     cache: Arc<Cache<String, String>>,
+    channels: Arc<Mutex<HashMap<String, broadcast::Sender<String>>>>,
+    // The counters without the expiration, which are kept out of the cache so that never expired by the
+    // ttl, such as the sequences.
+    counters: Arc<Mutex<HashMap<String, i64>>>,
 }
 
 impl StringMemoryCache {
@@ -66,6 +75,8 @@ impl StringMemoryCache {
         }
         StringMemoryCache {
             cache: Arc::new(builder.build()),
+            channels: Arc::new(Mutex::new(HashMap::new())),
+            counters: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -81,6 +92,9 @@ impl StringMemoryCache {
 #[async_trait]
 impl ICache<String> for StringMemoryCache {
     async fn get(&self, key: String) -> Result<Option<String>, Error> {
+        if let Some(count) = self.counters.lock().unwrap().get(&key) {
+            return Ok(Some(count.to_string()));
+        }
         Ok(self.cache.get(&key).await)
     }
 
@@ -211,13 +225,13 @@ impl ICache<String> for StringMemoryCache {
         }
     }
 
-    /// Sets the given key to the specified value.
+    /// Sets the expiration of the given key.
     ///
     /// # Note
-    /// The expire(&self, String, i64) function is deprecated and will be unimplemented in the future.
-    #[allow(unused)]
+    /// The entries are expired by the ttl of the cache, so the `milliseconds` parameter is ignored.
+    #[allow(unused_variables)]
     async fn expire(&self, key: String, milliseconds: i64) -> Result<bool, Error> {
-        unimplemented!()
+        Ok(false)
     }
 
     async fn get_bit(&self, key: String, offset: u64) -> Result<bool, Error> {
@@ -263,9 +277,65 @@ impl ICache<String> for StringMemoryCache {
     }
 
     async fn del(&self, key: String) -> Result<bool, Error> {
+        self.counters.lock().unwrap().remove(&key);
         self.cache.invalidate(&key).await;
         Ok(true)
     }
+
+    async fn incr(&self, key: String, milliseconds: Option<i64>) -> Result<i64, Error> {
+        if milliseconds.is_none() {
+            let mut counters = self.counters.lock().unwrap();
+            let count = counters.entry(key).or_default();
+            *count += 1;
+            return Ok(*count);
+        }
+        // The upserts of the same key are serialized.
+        let entry = self.cache
+            .entry(key)
+            .and_upsert_with(|entry| async move {
+                let count = entry.and_then(|e| e.into_value().parse::<i64>().ok()).unwrap_or_default();
+                (count + 1).to_string()
+            }).await;
+        Ok(entry.into_value().parse::<i64>()?)
+    }
+
+    async fn publish(&self, channel: String, message: String) -> Result<bool, Error> {
+        let mut channels = self.channels.lock().unwrap();
+        let received = match channels.get(&channel) {
+            Some(sender) => sender.send(message).is_ok(),
+            None => false,
+        };
+        // The channel is released after all the subscribers are dropped.
+        if !received {
+            channels.remove(&channel);
+        }
+        Ok(received)
+    }
+
+    async fn subscribe(&self, channel: String) -> Result<BoxStream<'static, String>, Error> {
+        let receiver = self.channels
+            .lock()
+            .unwrap()
+            .entry(channel)
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+            .subscribe();
+        let messages = stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    std::result::Result::Ok(message) => {
+                        return Some((message, receiver));
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Skipped the {} lagged messages of the subscription.", skipped);
+                    }
+                    Err(RecvError::Closed) => {
+                        return None;
+                    }
+                }
+            }
+        });
+        Ok(messages.boxed())
+    }
 }
 
 #[cfg(test)]
@@ -401,4 +471,50 @@ mod tests {
         assert!(cache.del("key4".to_string()).await.unwrap());
         assert_eq!(cache.get("key4".to_string()).await.unwrap(), None);
     }
+
+    #[tokio::test]
+    async fn test_incr() {
+        let cache = create_test_cache();
+        let counts = futures::future::join_all((0..10).map(|_| cache.incr("counter1".to_string(), None))).await;
+        let mut counts: Vec<i64> = counts.into_iter().map(|count| count.unwrap()).collect();
+        counts.sort();
+        assert_eq!(counts, (1..=10).collect::<Vec<i64>>());
+        assert_eq!(cache.get("counter1".to_string()).await.unwrap(), Some("10".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_incr_without_expiration() {
+        let config = MemoryProperties {
+            initial_capacity: None,
+            max_capacity: None,
+            ttl: Some(100),
+            eviction_policy: None,
+        };
+        let cache = StringMemoryCache::new(&config);
+        assert_eq!(cache.incr("sequence1".to_string(), None).await.unwrap(), 1);
+        assert_eq!(cache.incr("counter2".to_string(), Some(100)).await.unwrap(), 1);
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        // Only the counter of the expiration is expired by the ttl of the cache.
+        assert_eq!(cache.incr("sequence1".to_string(), None).await.unwrap(), 2);
+        assert_eq!(cache.incr("counter2".to_string(), Some(100)).await.unwrap(), 1);
+        assert!(cache.del("sequence1".to_string()).await.unwrap());
+        assert_eq!(cache.get("sequence1".to_string()).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_publish_and_subscribe() {
+        let cache = create_test_cache();
+        assert!(!cache.publish("channel1".to_string(), "lost".to_string()).await.unwrap());
+
+        let mut first = cache.subscribe("channel1".to_string()).await.unwrap();
+        let mut second = cache.subscribe("channel1".to_string()).await.unwrap();
+        assert!(cache.publish("channel1".to_string(), "message1".to_string()).await.unwrap());
+        assert!(!cache.publish("channel2".to_string(), "message2".to_string()).await.unwrap());
+        assert_eq!(first.next().await, Some("message1".to_string()));
+        assert_eq!(second.next().await, Some("message1".to_string()));
+
+        drop(first);
+        drop(second);
+        assert!(!cache.publish("channel1".to_string(), "message3".to_string()).await.unwrap());
+    }
 }
@@ -24,6 +24,7 @@ use std::collections::HashMap;
 
 use anyhow::Error;
 use axum::async_trait;
+use futures::stream::BoxStream;
 
 use crate::config::config_serve::{ WebServeProperties, CacheProvider };
 
@@ -65,6 +66,19 @@ pub trait ICache<T>: Send + Sync {
     async fn set_bit(&self, key: String, offset: u64, value: bool) -> Result<bool, Error>;
 
     async fn del(&self, key: String) -> Result<bool, Error>;
+
+    // Increase the counter of the key by 1 atomically, returns the increased. The expiration is set when the
+    // counter is created, which the memory cache expires by the configured ttl instead, and the counter
+    // without the expiration is never expired.
+    async fn incr(&self, key: String, milliseconds: Option<i64>) -> Result<i64, Error>;
+
+    // Publish the message to the subscribers of the channel (of all the instances if distributed),
+    // returns whether any subscriber received it.
+    async fn publish(&self, channel: String, message: String) -> Result<bool, Error>;
+
+    // Subscribe the channel, only the messages published after subscribed are received, and the stream
+    // ends if the subscription is broken.
+    async fn subscribe(&self, channel: String) -> Result<BoxStream<'static, String>, Error>;
 }
 
 pub struct CacheContainer<T> where T: 'static + Send + Sync {
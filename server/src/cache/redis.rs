@@ -22,9 +22,11 @@
 
 use anyhow::Error;
 use axum::async_trait;
+use futures::stream::{ BoxStream, StreamExt };
 use redis::{
     cluster::{ ClusterClient, ClusterClientBuilder },
     cluster_async::ClusterConnection,
+    IntoConnectionInfo,
     RedisResult,
 };
 use std::{ collections::HashMap, sync::Arc, time::Duration };
@@ -35,6 +37,7 @@ use super::ICache;
 
 pub struct StringRedisCache {
     client: Arc<ClusterClient>,
+    config: RedisProperties,
 }
 
 impl StringRedisCache {
@@ -71,12 +74,28 @@ impl StringRedisCache {
             builder = builder.read_from_replicas();
         }
         let client = builder.build().expect("Failed to build redis cluster client");
-        StringRedisCache { client: Arc::new(client) }
+        StringRedisCache { client: Arc::new(client), config: config.clone() }
     }
 
     async fn get_async_connection(&self) -> Result<ClusterConnection, Error> {
         self.client.get_async_connection().await.map_err(Error::from)
     }
+
+    // The cluster connection doesn't support the subscriptions, but the messages published are propagated
+    // to all the nodes of the cluster, so that subscribing to any one node is enough.
+    fn get_pubsub_client(&self) -> Result<redis::Client, Error> {
+        let node = self.config.nodes
+            .first()
+            .ok_or_else(|| Error::msg("The redis nodes are not configured"))?;
+        let mut info = node.as_str().into_connection_info()?;
+        if self.config.username.is_some() {
+            info.redis.username = self.config.username.clone();
+        }
+        if self.config.password.is_some() {
+            info.redis.password = self.config.password.clone();
+        }
+        redis::Client::open(info).map_err(Error::from)
+    }
 }
 
 #[async_trait]
@@ -218,4 +237,27 @@ impl ICache<String> for StringRedisCache {
         let result: RedisResult<i32> = redis::cmd("DEL").arg(key).query_async(&mut con).await;
         Ok(result.map(|n| n > 0).unwrap_or(false))
     }
+
+    async fn incr(&self, key: String, milliseconds: Option<i64>) -> Result<i64, Error> {
+        let mut con = self.get_async_connection().await?;
+        let count: i64 = redis::cmd("INCR").arg(&key).query_async(&mut con).await?;
+        if let Some(milliseconds) = milliseconds.filter(|_| count == 1) {
+            let _: i64 = redis::cmd("PEXPIRE").arg(key).arg(milliseconds).query_async(&mut con).await?;
+        }
+        Ok(count)
+    }
+
+    // Notice: The count of the receivers responded by the cluster is only of the node connected.
+    async fn publish(&self, channel: String, message: String) -> Result<bool, Error> {
+        let mut con = self.get_async_connection().await?;
+        let result: RedisResult<i64> = redis::cmd("PUBLISH").arg(channel).arg(message).query_async(&mut con).await;
+        Ok(result.map(|n| n > 0)?)
+    }
+
+    async fn subscribe(&self, channel: String) -> Result<BoxStream<'static, String>, Error> {
+        let mut pubsub = self.get_pubsub_client()?.get_async_pubsub().await?;
+        pubsub.subscribe(channel).await?;
+        let messages = pubsub.into_on_message().filter_map(|msg| async move { msg.get_payload::<String>().ok() });
+        Ok(messages.boxed())
+    }
 }
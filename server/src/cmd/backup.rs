@@ -0,0 +1,108 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use std::io::BufWriter;
+
+use clap::{ Arg, ArgMatches, Command };
+
+use crate::config::config_serve;
+use crate::context::state::AppState;
+use crate::handler::backup::{ self, BackupManifest };
+
+pub fn build_cli() -> Command {
+    Command::new("backup")
+        .about(
+            "Export all the users, folders, documents, revisions, settings and blobs of the configured DbType \
+            to a portable archive, which can be restored to any DbType."
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .required(true)
+                .help("The file path of the archive to write.")
+        )
+        .arg(
+            Arg::new("user")
+                .short('u')
+                .long("user")
+                .value_parser(clap::value_parser!(i64))
+                .help("Only export the data of the user id, default is of all users.")
+        )
+}
+
+// The state of the offline backup and restore, which the OIDC client is unnecessary, and the provider
+// discovery may be unreachable.
+pub(crate) async fn create_offline_state() -> AppState {
+    let mut properties = config_serve::get_config().inner.clone();
+    properties.auth.oidc.enabled = Some(false);
+    AppState::new(&properties.to_config()).await
+}
+
+fn print_backup_manifest(manifest: &BackupManifest) {
+    println!("{:<20} {:<8} CHECKSUM", "MODULE", "COUNT");
+    for entry in &manifest.entries {
+        println!("{:<20} {:<8} {}", entry.name, entry.count, entry.checksum);
+    }
+    println!("Schema version: {}, format version: {}", manifest.schema_version, manifest.format_version);
+}
+
+#[tokio::main]
+pub async fn handle_cli(matches: &ArgMatches) {
+    let output = matches.get_one::<String>("output").unwrap();
+    let owner_uid = matches.get_one::<i64>("user").copied();
+
+    let state = create_offline_state().await;
+    let result = match std::fs::File::create(output) {
+        Ok(file) => backup::backup(&state, owner_uid, BufWriter::new(file)).await.map(|(manifest, _)| manifest),
+        Err(e) => Err(e.into()),
+    };
+    match result {
+        Ok(manifest) => {
+            print_backup_manifest(&manifest);
+            println!("Backup to the archive: {}", output);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_backup_args() {
+        let app = build_cli();
+        assert!(app.clone().try_get_matches_from(vec!["backup"]).is_err());
+        assert!(app.clone().try_get_matches_from(vec!["backup", "-o", "a.zip", "--user", "abc"]).is_err());
+
+        let matches = app.clone().try_get_matches_from(vec!["backup", "--output", "a.zip"]).unwrap();
+        assert_eq!(matches.get_one::<String>("output").unwrap(), "a.zip");
+        assert_eq!(matches.get_one::<i64>("user"), None);
+
+        let matches = app.try_get_matches_from(vec!["backup", "-o", "a.zip", "-u", "1001"]).unwrap();
+        assert_eq!(*matches.get_one::<i64>("user").unwrap(), 1001);
+    }
+}
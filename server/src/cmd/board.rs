@@ -0,0 +1,208 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use anyhow::Error;
+use clap::{ Arg, ArgMatches, Command };
+
+use crate::handler::board::{ BoardHandler, IBoardHandler };
+use crate::types::board::{ BoardFormat, BulkImportBoardRequest, BulkImportBoardResponse, ImportBoardRequest };
+use crate::utils::auths::{ AuthUserClaims, SecurityContext };
+
+use super::backup::create_offline_state;
+
+pub fn build_cli() -> Command {
+    Command::new("board")
+        .about("Import and export the boards of the Excalidraw and tldraw files.")
+        .arg_required_else_help(true) // When no args are provided, show help.
+        .subcommand(
+            Command::new("import")
+                .about(
+                    "Import the '.excalidraw' or '.tldr' file as the new board, or all the board files of \
+                    the zip to the folder."
+                )
+                .arg(
+                    Arg::new("input")
+                        .short('i')
+                        .long("input")
+                        .required(true)
+                        .help("The file path of the board file or the zip to read.")
+                )
+                .arg(user_arg())
+                .arg(Arg::new("folder").long("folder").help("The key of the folder to import to."))
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_parser(["excalidraw", "tldraw"])
+                        .help("The format of the board file, default is detected from the content.")
+                )
+                .arg(
+                    Arg::new("name")
+                        .long("name")
+                        .help("The name of the board, default is the file name (ignored for the zip).")
+                )
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Export the board as the '.excalidraw' file with the embedded files.")
+                .arg(
+                    Arg::new("id")
+                        .long("id")
+                        .required(true)
+                        .value_parser(clap::value_parser!(i64))
+                        .help("The id of the board document.")
+                )
+                .arg(user_arg())
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .required(true)
+                        .help("The file path of the '.excalidraw' file to write.")
+                )
+        )
+}
+
+fn user_arg() -> Arg {
+    Arg::new("user")
+        .short('u')
+        .long("user")
+        .required(true)
+        .value_parser(clap::value_parser!(i64))
+        .help("The user id of the owner of the boards.")
+}
+
+fn to_board_format(name: &str) -> BoardFormat {
+    match name {
+        "tldraw" => BoardFormat::Tldraw,
+        _ => BoardFormat::Excalidraw,
+    }
+}
+
+fn print_bulk_import_response(response: &BulkImportBoardResponse) {
+    println!("{:<48} RESULT", "FILE");
+    for imported in &response.imported {
+        println!("{:<48} imported (id: {})", imported.file, imported.id);
+    }
+    for failed in &response.failed {
+        println!("{:<48} failed ({})", failed.file, failed.reason);
+    }
+    println!("Imported: {}, failed: {}", response.imported.len(), response.failed.len());
+}
+
+async fn handle_import(matches: &ArgMatches) -> Result<(), Error> {
+    let input = matches.get_one::<String>("input").unwrap();
+    let folder_key = matches.get_one::<String>("folder").cloned();
+    let content = tokio::fs::read(input).await?;
+
+    let state = create_offline_state().await;
+    let handler = BoardHandler::new(&state);
+    if input.to_lowercase().ends_with(".zip") {
+        let param = BulkImportBoardRequest { folder_key };
+        print_bulk_import_response(&handler.import_bulk(param, content).await?);
+    } else {
+        let format = matches
+            .get_one::<String>("format")
+            .map(|name| to_board_format(name))
+            .or_else(|| BoardFormat::from_file_name(input));
+        let name = matches.get_one::<String>("name").cloned().or_else(|| Some(to_file_stem(input)));
+        let param = ImportBoardRequest { format, name, folder_key };
+        let (id, version) = handler.import(param, content).await?;
+        println!("Imported the board: {} (id: {}, version: {})", input, id, version);
+    }
+    Ok(())
+}
+
+async fn handle_export(matches: &ArgMatches) -> Result<(), Error> {
+    let id = *matches.get_one::<i64>("id").unwrap();
+    let output = matches.get_one::<String>("output").unwrap();
+
+    let state = create_offline_state().await;
+    let (name, content) = BoardHandler::new(&state).export(id).await?;
+    tokio::fs::write(output, content).await?;
+    println!("Exported the board: {} to: {}", name, output);
+    Ok(())
+}
+
+// The file name without the directories and the extension.
+fn to_file_stem(path: &str) -> String {
+    std::path::Path
+        ::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+#[tokio::main]
+pub async fn handle_cli(matches: &ArgMatches) {
+    let result = match matches.subcommand() {
+        Some((name, sub_matches)) => {
+            let uid = *sub_matches.get_one::<i64>("user").unwrap();
+            let claims = Some(AuthUserClaims::system(uid));
+            match name {
+                "import" => SecurityContext::scope(claims, handle_import(sub_matches)).await,
+                _ => SecurityContext::scope(claims, handle_export(sub_matches)).await,
+            }
+        }
+        None => Ok(()),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_board_import_args() {
+        let app = build_cli();
+        assert!(app.clone().try_get_matches_from(vec!["board", "import", "-i", "a.tldr"]).is_err());
+        let args = vec!["board", "import", "-i", "a.json", "-u", "1", "--format", "svg"];
+        assert!(app.clone().try_get_matches_from(args).is_err());
+
+        let args = vec!["board", "import", "-i", "a.tldr", "-u", "1001", "--folder", "f1"];
+        let matches = app.try_get_matches_from(args).unwrap();
+        let (name, sub_matches) = matches.subcommand().unwrap();
+        assert_eq!(name, "import");
+        assert_eq!(*sub_matches.get_one::<i64>("user").unwrap(), 1001);
+        assert_eq!(sub_matches.get_one::<String>("folder").unwrap(), "f1");
+        assert_eq!(sub_matches.get_one::<String>("format"), None);
+        assert_eq!(to_board_format("tldraw"), BoardFormat::Tldraw);
+        assert_eq!(to_file_stem("/tmp/boards/My Board.tldr"), "My Board");
+    }
+
+    #[test]
+    fn test_cli_board_export_args() {
+        let app = build_cli();
+        assert!(app.clone().try_get_matches_from(vec!["board", "export", "--id", "1", "-u", "1"]).is_err());
+        assert!(app.clone().try_get_matches_from(vec!["board", "export", "--id", "a", "-u", "1", "-o", "a"]).is_err());
+
+        let args = vec!["board", "export", "--id", "7", "-u", "1001", "-o", "a.excalidraw"];
+        let matches = app.try_get_matches_from(args).unwrap();
+        let (name, sub_matches) = matches.subcommand().unwrap();
+        assert_eq!(name, "export");
+        assert_eq!(*sub_matches.get_one::<i64>("id").unwrap(), 7);
+        assert_eq!(sub_matches.get_one::<String>("output").unwrap(), "a.excalidraw");
+    }
+}
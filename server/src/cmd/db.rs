@@ -0,0 +1,357 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use anyhow::Error;
+use clap::{ Arg, ArgAction, ArgMatches, Command };
+
+use crate::config::config_serve::{ self, DbProperties, DbType };
+use crate::store::{ postgres, sqlite, AsyncRepository };
+use crate::store::data_migration::{ self, DataMigrationOptions, DataMigrationReport };
+use crate::store::{
+    documents_mongo::DocumentMongoRepository,
+    documents_postgres::DocumentPostgresRepository,
+    documents_sqlite::DocumentSQLiteRepository,
+    folders_mongo::FolderMongoRepository,
+    folders_postgres::FolderPostgresRepository,
+    folders_sqlite::FolderSQLiteRepository,
+    settings_mongo::SettingsMongoRepository,
+    settings_postgres::SettingsPostgresRepository,
+    settings_sqlite::SettingsSQLiteRepository,
+    users_mongo::UserMongoRepository,
+    users_postgres::UserPostgresRepository,
+    users_sqlite::UserSQLiteRepository,
+};
+
+const DB_TYPES: [&str; 3] = ["sqlite", "mongo", "postgres"];
+
+pub fn build_cli() -> Command {
+    Command::new("db")
+        .about("My Webnote database management.")
+        .arg_required_else_help(true) // When no args are provided, show help.
+        .subcommand(
+            Command::new("migrate-status").about(
+                "Show the embedded schema versions of the SQLite or Postgres and whether they are applied."
+            )
+        )
+        .subcommand(
+            Command::new("migrate-run").about("Apply all pending schema versions.")
+        )
+        .subcommand(
+            Command::new("migrate-rollback")
+                .about("Revert the applied schema versions newer than the target version.")
+                .arg(
+                    Arg::new("target")
+                        .short('t')
+                        .long("target")
+                        .required(true)
+                        .value_parser(clap::value_parser!(i64))
+                        .help("The target version to keep, 0 means revert all versions.")
+                )
+        )
+        .subcommand(
+            Command::new("migrate-data")
+                .about(
+                    "Copy all the users, folders, documents and settings from the one DbType to another, \
+                    which keeps the ids and timestamps, and verifies the row counts and checksums at last."
+                )
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .required(true)
+                        .value_parser(DB_TYPES)
+                        .help("The source DbType, which is connected by the db configuration.")
+                )
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .required(true)
+                        .value_parser(DB_TYPES)
+                        .help("The target DbType, which is connected by the db configuration.")
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .help("Only report the data to be copied, but nothing is written to the target.")
+                )
+                .arg(
+                    Arg::new("resume")
+                        .long("resume")
+                        .action(ArgAction::SetTrue)
+                        .help("Resume the interrupted migration, which skips the data already in the target.")
+                )
+                .arg(
+                    Arg::new("batch-size")
+                        .long("batch-size")
+                        .default_value("500")
+                        .value_parser(clap::value_parser!(u32).range(1..=10000))
+                        .help("The number of rows to read per batch.")
+                )
+        )
+}
+
+// The migration commands of the backend module (sqlite or postgres), which both provide the
+// same 'connect', 'migration_status', 'migration_rollback' and 'MIGRATOR'.
+macro_rules! handle_migrate {
+    ($backend:ident, $config:expr, $matches:expr) => {
+        match $backend::connect($config).await {
+            Ok(pool) =>
+                match $matches.subcommand() {
+                    Some(("migrate-status", _)) => {
+                        $backend::migration_status(&pool).await.map(|status| {
+                            println!("{:<16} {:<8} {:<10} DESCRIPTION", "VERSION", "APPLIED", "CHECKSUM");
+                            for s in status {
+                                println!(
+                                    "{:<16} {:<8} {:<10} {}",
+                                    s.version,
+                                    s.applied,
+                                    if s.checksum_matched { "ok" } else { "mismatch" },
+                                    s.description
+                                );
+                            }
+                        })
+                    }
+                    Some(("migrate-run", _)) => {
+                        $backend::MIGRATOR.run(&pool).await
+                            .map(|_| println!("Migrations applied."))
+                            .map_err(anyhow::Error::from)
+                    }
+                    Some(("migrate-rollback", sub_matches)) => {
+                        let target = *sub_matches.get_one::<i64>("target").unwrap();
+                        $backend::migration_rollback(&pool, target).await.map(|_|
+                            println!("Rollback to version {} finished.", target)
+                        )
+                    }
+                    _ => Ok(()),
+                }
+            Err(e) => Err(e),
+        }
+    };
+}
+
+// Create the module repository of the DbType, which is the one of the AppState repository containers,
+// and the postgres one is created of the shared pool.
+macro_rules! new_repository {
+    ($config:expr, $sqlite:ty, $mongo:ty, $postgres:expr) => {
+        {
+            let repo: Box<dyn AsyncRepository<_>> = match $config.db_type {
+                DbType::Sqlite => Box::new(<$sqlite>::new($config).await?),
+                DbType::Mongo => Box::new(<$mongo>::new($config).await?),
+                DbType::Postgres => Box::new($postgres),
+            };
+            repo
+        }
+    };
+}
+
+fn to_db_type(name: &str) -> DbType {
+    match name {
+        "mongo" => DbType::Mongo,
+        "postgres" => DbType::Postgres,
+        _ => DbType::Sqlite,
+    }
+}
+
+async fn handle_migrate_data(config: &DbProperties, matches: &ArgMatches) -> Result<bool, Error> {
+    let source = DbProperties {
+        db_type: to_db_type(matches.get_one::<String>("from").unwrap()),
+        ..config.clone()
+    };
+    let target = DbProperties {
+        db_type: to_db_type(matches.get_one::<String>("to").unwrap()),
+        ..config.clone()
+    };
+    if source.db_type == target.db_type {
+        return Err(Error::msg("The source and target of the data migration must be the different DbTypes"));
+    }
+    let options = DataMigrationOptions {
+        dry_run: matches.get_flag("dry-run"),
+        resume: matches.get_flag("resume"),
+        batch_size: *matches.get_one::<u32>("batch-size").unwrap(),
+    };
+
+    let (source_pool, target_pool) = (postgres::create_pool(&source).await?, postgres::create_pool(&target).await?);
+
+    // Notice: The users are migrated first, and then the owned modules data.
+    let reports = vec![
+        data_migration::migrate_data(
+            "users",
+            &*new_repository!(&source, UserSQLiteRepository, UserMongoRepository, {
+                UserPostgresRepository::new(source_pool.clone())
+            }),
+            &*new_repository!(&target, UserSQLiteRepository, UserMongoRepository, {
+                UserPostgresRepository::new(target_pool.clone())
+            }),
+            &options
+        ).await?,
+        data_migration::migrate_data(
+            "folders",
+            &*new_repository!(&source, FolderSQLiteRepository, FolderMongoRepository, {
+                FolderPostgresRepository::new(source_pool.clone())
+            }),
+            &*new_repository!(&target, FolderSQLiteRepository, FolderMongoRepository, {
+                FolderPostgresRepository::new(target_pool.clone())
+            }),
+            &options
+        ).await?,
+        data_migration::migrate_data(
+            "documents",
+            &*new_repository!(&source, DocumentSQLiteRepository, DocumentMongoRepository, {
+                DocumentPostgresRepository::new(&source, source_pool.clone())
+            }),
+            &*new_repository!(&target, DocumentSQLiteRepository, DocumentMongoRepository, {
+                DocumentPostgresRepository::new(&target, target_pool.clone())
+            }),
+            &options
+        ).await?,
+        data_migration::migrate_data(
+            "settings",
+            &*new_repository!(&source, SettingsSQLiteRepository, SettingsMongoRepository, {
+                SettingsPostgresRepository::new(source_pool.clone())
+            }),
+            &*new_repository!(&target, SettingsSQLiteRepository, SettingsMongoRepository, {
+                SettingsPostgresRepository::new(target_pool.clone())
+            }),
+            &options
+        ).await?
+    ];
+    print_migrate_data_reports(&reports, options.dry_run);
+
+    Ok(options.dry_run || reports.iter().all(|report| report.is_matched()))
+}
+
+fn print_migrate_data_reports(reports: &[DataMigrationReport], dry_run: bool) {
+    println!(
+        "{:<10} {:<8} {:<8} {:<8} {:<8} {:<16} {:<16} STATUS",
+        "MODULE",
+        "SOURCE",
+        "TARGET",
+        "COPIED",
+        "SKIPPED",
+        "SOURCE_CHECKSUM",
+        "TARGET_CHECKSUM"
+    );
+    for report in reports {
+        let status = if report.is_matched() {
+            "ok"
+        } else if dry_run {
+            "pending"
+        } else {
+            "mismatch"
+        };
+        println!(
+            "{:<10} {:<8} {:<8} {:<8} {:<8} {:<16} {:<16} {}",
+            report.name,
+            report.source_count,
+            report.target_count,
+            report.copied,
+            report.skipped,
+            &report.source_checksum[..16],
+            &report.target_checksum[..16],
+            status
+        );
+    }
+    if dry_run {
+        println!("Dry run, nothing is written to the target.");
+    }
+}
+
+#[tokio::main]
+pub async fn handle_cli(matches: &ArgMatches) {
+    let config = config_serve::get_config();
+    if let Some(("migrate-data", sub_matches)) = matches.subcommand() {
+        match handle_migrate_data(&config.db, sub_matches).await {
+            Ok(true) => {}
+            Ok(false) => {
+                eprintln!("The migrated data mismatched, please check the report above.");
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let result = match config.db.db_type {
+        DbType::Sqlite => handle_migrate!(sqlite, &config.db, matches),
+        DbType::Postgres => handle_migrate!(postgres, &config.db, matches),
+        _ => {
+            eprintln!("Nothing to do, the schema migrations only available for db.type: sqlite|postgres");
+            return;
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_migrate_rollback_requires_target() {
+        let app = build_cli();
+        assert!(app.clone().try_get_matches_from(vec!["db", "migrate-rollback"]).is_err());
+
+        let matches = app
+            .try_get_matches_from(vec!["db", "migrate-rollback", "--target", "20240723010654"])
+            .unwrap();
+        let sub_matches = matches.subcommand_matches("migrate-rollback").unwrap();
+        assert_eq!(*sub_matches.get_one::<i64>("target").unwrap(), 20240723010654);
+    }
+    #[test]
+    fn test_cli_migrate_data_args() {
+        let app = build_cli();
+        assert!(app.clone().try_get_matches_from(vec!["db", "migrate-data", "--from", "sqlite"]).is_err());
+        assert!(
+            app.clone().try_get_matches_from(vec!["db", "migrate-data", "--from", "sqlite", "--to", "mysql"]).is_err()
+        );
+
+        let matches = app
+            .clone()
+            .try_get_matches_from(vec!["db", "migrate-data", "--from", "sqlite", "--to", "mongo"])
+            .unwrap();
+        let sub_matches = matches.subcommand_matches("migrate-data").unwrap();
+        assert_eq!(to_db_type(sub_matches.get_one::<String>("from").unwrap()), DbType::Sqlite);
+        assert_eq!(to_db_type(sub_matches.get_one::<String>("to").unwrap()), DbType::Mongo);
+        assert!(!sub_matches.get_flag("dry-run"));
+        assert!(!sub_matches.get_flag("resume"));
+        assert_eq!(*sub_matches.get_one::<u32>("batch-size").unwrap(), 500);
+
+        let matches = app
+            .try_get_matches_from(
+                vec![
+                    "db", "migrate-data", "--from", "mongo", "--to", "postgres", "--dry-run", "--resume", "--batch-size", "100"
+                ]
+            )
+            .unwrap();
+        let sub_matches = matches.subcommand_matches("migrate-data").unwrap();
+        assert!(sub_matches.get_flag("dry-run"));
+        assert!(sub_matches.get_flag("resume"));
+        assert_eq!(*sub_matches.get_one::<u32>("batch-size").unwrap(), 100);
+    }
+}
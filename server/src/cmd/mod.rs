@@ -20,6 +20,10 @@
  * This includes modifications and derived works.
  */
 
+pub mod backup;
+pub mod board;
+pub mod db;
+pub mod restore;
 pub mod serve;
 
 use std::{ collections::HashMap, sync::OnceLock };
@@ -55,6 +59,10 @@ pub fn register_subcommand_handles() -> &'static HashMap<
             serve::build_cli as SubcommandBuildFn,
             serve::handle_cli as SubcommandHandleFn,
         ));
+        map.insert("db", (db::build_cli as SubcommandBuildFn, db::handle_cli as SubcommandHandleFn));
+        map.insert("backup", (backup::build_cli as SubcommandBuildFn, backup::handle_cli as SubcommandHandleFn));
+        map.insert("board", (board::build_cli as SubcommandBuildFn, board::handle_cli as SubcommandHandleFn));
+        map.insert("restore", (restore::build_cli as SubcommandBuildFn, restore::handle_cli as SubcommandHandleFn));
         map
     })
 }
@@ -0,0 +1,125 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use std::io::BufReader;
+
+use anyhow::Error;
+use clap::{ Arg, ArgMatches, Command };
+
+use crate::handler::backup::{ self, RestoreMode, RestoreReport };
+
+use super::backup::create_offline_state;
+
+pub fn build_cli() -> Command {
+    Command::new("restore")
+        .about(
+            "Import the archive of the backup to the configured DbType, which verifies the checksums and \
+            schema version of the archive before writing."
+        )
+        .arg(
+            Arg::new("input")
+                .short('i')
+                .long("input")
+                .required(true)
+                .help("The file path of the archive to read.")
+        )
+        .arg(
+            Arg::new("mode")
+                .short('m')
+                .long("mode")
+                .default_value("merge")
+                .value_parser(["merge", "replace"])
+                .help(
+                    "The merge only restores the data absent, and the replace deletes the existing data \
+                    (of the user if specified) before restoring."
+                )
+        )
+        .arg(
+            Arg::new("user")
+                .short('u')
+                .long("user")
+                .value_parser(clap::value_parser!(i64))
+                .help("Only restore the data of the user id, default is of all users.")
+        )
+}
+
+fn to_restore_mode(name: &str) -> RestoreMode {
+    match name {
+        "replace" => RestoreMode::Replace,
+        _ => RestoreMode::Merge,
+    }
+}
+
+fn print_restore_reports(reports: &[RestoreReport]) {
+    println!("{:<20} {:<8} {:<8} {:<8} DELETED", "MODULE", "COUNT", "RESTORED", "SKIPPED");
+    for report in reports {
+        println!(
+            "{:<20} {:<8} {:<8} {:<8} {}",
+            report.name,
+            report.count,
+            report.restored,
+            report.skipped,
+            report.deleted
+        );
+    }
+}
+
+async fn handle_restore(matches: &ArgMatches) -> Result<Vec<RestoreReport>, Error> {
+    let input = matches.get_one::<String>("input").unwrap();
+    let mode = to_restore_mode(matches.get_one::<String>("mode").unwrap());
+    let owner_uid = matches.get_one::<i64>("user").copied();
+
+    let archive = BufReader::new(std::fs::File::open(input)?);
+    let state = create_offline_state().await;
+    backup::restore(&state, archive, mode, owner_uid).await
+}
+
+#[tokio::main]
+pub async fn handle_cli(matches: &ArgMatches) {
+    match handle_restore(matches).await {
+        Ok(reports) => print_restore_reports(&reports),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_restore_args() {
+        let app = build_cli();
+        assert!(app.clone().try_get_matches_from(vec!["restore"]).is_err());
+        assert!(app.clone().try_get_matches_from(vec!["restore", "-i", "a.zip", "--mode", "overwrite"]).is_err());
+
+        let matches = app.clone().try_get_matches_from(vec!["restore", "--input", "a.zip"]).unwrap();
+        assert_eq!(to_restore_mode(matches.get_one::<String>("mode").unwrap()), RestoreMode::Merge);
+        assert_eq!(matches.get_one::<i64>("user"), None);
+
+        let matches = app.try_get_matches_from(vec!["restore", "-i", "a.zip", "-m", "replace", "-u", "1001"]).unwrap();
+        assert_eq!(to_restore_mode(matches.get_one::<String>("mode").unwrap()), RestoreMode::Replace);
+        assert_eq!(*matches.get_one::<i64>("user").unwrap(), 1001);
+    }
+}
@@ -34,6 +34,7 @@ use tokio::net::TcpListener;
 use tokio::sync::oneshot;
 
 use axum::Router;
+use axum::extract::DefaultBodyLimit;
 use axum::routing::get;
 use axum_prometheus::PrometheusMetricLayer;
 
@@ -44,6 +45,7 @@ use crate::config::config_serve::GIT_COMMIT_HASH;
 use crate::config::config_serve::GIT_VERSION;
 use crate::config::swagger;
 use crate::context::state::AppState;
+use crate::handler::{ backup, blob, trash };
 use crate::mgmt::apm;
 use crate::mgmt::apm::metrics::handle_metrics;
 use crate::mgmt::health::init as health_router;
@@ -51,8 +53,16 @@ use crate::route::auths::auth_middleware;
 use crate::route::auths::init as auth_router;
 use crate::route::user::init as user_router;
 use crate::route::document::init as document_router;
+use crate::route::blob::init as blob_router;
+use crate::route::board::init as board_router;
 use crate::route::folder::init as folder_router;
 use crate::route::settings::init as settings_router;
+use crate::route::share::init as share_router;
+use crate::route::tag::init as tag_router;
+use crate::route::acl::init as acl_router;
+use crate::route::room::init as room_router;
+use crate::route::sync::init as sync_router;
+use crate::route::event::init as event_router;
 use crate::route::browser_indexeddb::init as browser_indexeddb_router;
 use crate::route::api_v1::users::init as api_v1_users_router;
 
@@ -86,6 +96,9 @@ async fn start_mgmt_server(
 
 async fn start_server(config: &Arc<WebServeConfig>) {
     let app_state = AppState::new(&config).await;
+    trash::start_purge_task(app_state.clone());
+    blob::start_gc_task(app_state.clone());
+    backup::start_backup_task(app_state.clone());
     tracing::info!("Register Web server middlewares ...");
 
     // 1. Merge the biz modules routes.
@@ -93,8 +106,16 @@ async fn start_server(config: &Arc<WebServeConfig>) {
         .merge(auth_router())
         .merge(user_router())
         .merge(document_router())
+        .merge(blob_router())
+        .merge(board_router())
         .merge(folder_router())
         .merge(settings_router())
+        .merge(tag_router())
+        .merge(share_router())
+        .merge(acl_router())
+        .merge(room_router())
+        .merge(sync_router())
+        .merge(event_router())
         .merge(browser_indexeddb_router())
         .merge(api_v1_users_router());
 
@@ -123,6 +144,7 @@ async fn start_server(config: &Arc<WebServeConfig>) {
     // directly enter handle_root().
     app_routes = app_routes.layer(
         ServiceBuilder::new()
+            .layer(DefaultBodyLimit::max(config.server.max_body_size))
             .layer(axum::middleware::from_fn_with_state(app_state, auth_middleware))
             // Optional: add logs to tracing.
             .layer(
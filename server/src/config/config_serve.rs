@@ -69,6 +69,9 @@ pub struct ServerProperties {
     pub thread_max_pool: u32,
     #[serde(default = "CorsProperties::default")]
     pub cors: CorsProperties,
+    // The max bytes of the request body, such as the large board documents.
+    #[serde(rename = "max-body-size", default = "ServerProperties::default_max_body_size")]
+    pub max_body_size: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -90,6 +93,10 @@ pub struct DbProperties {
     pub db_type: DbType,
     pub sqlite: SqliteProperties,
     pub mongo: MongoProperties,
+    #[serde(default = "PostgresProperties::default")]
+    pub postgres: PostgresProperties,
+    #[serde(default = "CompressionProperties::default")]
+    pub compression: CompressionProperties,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
@@ -97,6 +104,7 @@ pub struct DbProperties {
 pub enum DbType {
     Sqlite,
     Mongo,
+    Postgres,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -110,6 +118,33 @@ pub struct MongoProperties {
     pub database: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PostgresProperties {
+    pub url: String,
+    // The pool settings of per repository.
+    #[serde(rename = "max-connections")]
+    pub max_connections: u32,
+    #[serde(rename = "min-connections")]
+    pub min_connections: u32,
+    // The milliseconds of waiting for a connection from the pool.
+    #[serde(rename = "acquire-timeout")]
+    pub acquire_timeout: u64,
+    // The milliseconds of the idle connection to be closed.
+    #[serde(rename = "idle-timeout")]
+    pub idle_timeout: u64,
+}
+
+// The compression of the large content (such as documents and revisions) on storage, which
+// transparently decompressed on read.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CompressionProperties {
+    pub enabled: bool,
+    // The min bytes of the content to compress.
+    pub threshold: usize,
+    // The zstd compression level (1-22).
+    pub level: i32,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CacheProperties {
     pub provider: CacheProvider,
@@ -289,6 +324,144 @@ pub struct OtelProperties {
 pub struct WebNoteProperties {
     pub indexeddb_name: String,
     pub indexeddb_store_names: Vec<String>,
+    #[serde(default = "RevisionProperties::default")]
+    pub revision: RevisionProperties,
+    #[serde(default = "TrashProperties::default")]
+    pub trash: TrashProperties,
+    #[serde(default = "BlobProperties::default")]
+    pub blob: BlobProperties,
+    #[serde(default = "BackupProperties::default")]
+    pub backup: BackupProperties,
+    #[serde(default = "BoardProperties::default")]
+    pub board: BoardProperties,
+    #[serde(default = "ThumbnailProperties::default")]
+    pub thumbnail: ThumbnailProperties,
+    #[serde(default = "RoomProperties::default")]
+    pub room: RoomProperties,
+    #[serde(default = "SyncProperties::default")]
+    pub sync: SyncProperties,
+    #[serde(default = "EventProperties::default")]
+    pub events: EventProperties,
+    #[serde(default = "ShareProperties::default")]
+    pub share: ShareProperties,
+}
+
+// The content addressed (SHA-256) storage of the attachments, such as the images of boards,
+// and the unreferenced blobs are garbage collected in the background after the grace.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BlobProperties {
+    // The local directory of the blobs.
+    pub dir: String,
+    // The max bytes of per blob.
+    #[serde(rename = "max-size")]
+    pub max_size: u64,
+    // The interval seconds of the background garbage collection.
+    #[serde(rename = "gc-interval")]
+    pub gc_interval: u64,
+    // The grace seconds of the unreferenced blobs, so that the uploaded blob is not collected
+    // before the document referencing it is saved.
+    #[serde(rename = "gc-grace")]
+    pub gc_grace: u64,
+}
+
+// The scheduled backup of the serve, which writes the archives of all the modules data to the local
+// directory periodically, and only the latest archives are kept.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupProperties {
+    pub enabled: bool,
+    // The local directory of the archives.
+    pub dir: String,
+    // The interval seconds of the scheduled backup.
+    pub interval: u64,
+    // The number of the latest archives to keep, the older are deleted.
+    pub keep: u32,
+}
+
+// The import of the boards, the bulk imported zip is limited by the uncompressed, since the body limit
+// only bounds the compressed size.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BoardProperties {
+    // The max uncompressed bytes of per file of the bulk imported zip.
+    #[serde(rename = "import-max-file-size")]
+    pub import_max_file_size: u64,
+    // The max number of the entries of the bulk imported zip.
+    #[serde(rename = "import-max-files")]
+    pub import_max_files: usize,
+}
+
+// The thumbnails of the boards, which are rendered when saved and cached by the version, the missing
+// (such as expired) are rendered again when requested.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ThumbnailProperties {
+    // Whether to render the thumbnails in the background when the boards are saved.
+    pub enabled: bool,
+    // The max width and height of the PNG thumbnails, the aspect ratio is kept.
+    #[serde(rename = "max-width")]
+    pub max_width: u32,
+    #[serde(rename = "max-height")]
+    pub max_height: u32,
+    // The expiration seconds of the cached thumbnails.
+    pub ttl: u32,
+}
+
+// The real-time collaboration rooms of the boards, the merged scene of the room is persisted periodically
+// and when the last client left.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RoomProperties {
+    // The interval seconds of persisting the changed scene of the rooms.
+    #[serde(rename = "persist-interval")]
+    pub persist_interval: u64,
+}
+
+// The yjs sync of the notes, the appended updates of the note are compacted into the one merged update
+// when the count of them reached the threshold, and the merged is written back to the content of the note.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SyncProperties {
+    #[serde(rename = "compact-threshold")]
+    pub compact_threshold: usize,
+}
+
+// The change events of the documents, folders and settings, which are kept in the cache for the resuming
+// of the disconnected clients (by the 'Last-Event-ID') only within the retention.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventProperties {
+    // The retention seconds of the events.
+    pub retention: u64,
+}
+
+// The share links of the documents, the passwords of the links are derived by PBKDF2-SHA256, and the link
+// is locked within the window when the mismatched passwords reached the max failures.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShareProperties {
+    #[serde(rename = "password-rounds")]
+    pub password_rounds: u32,
+    #[serde(rename = "max-password-failures")]
+    pub max_password_failures: i64,
+    // The window milliseconds of counting the mismatched passwords.
+    #[serde(rename = "password-failures-window")]
+    pub password_failures_window: i64,
+}
+
+// The retention of document revisions, the older revisions are purged when appending, and if the both
+// are configured, only the revision out of the latest N and older than D days is purged (i.e. retained
+// if within either), the latest revision always be retained.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RevisionProperties {
+    #[serde(rename = "keep-last")]
+    pub keep_last: Option<u32>,
+    #[serde(rename = "keep-days")]
+    pub keep_days: Option<u32>,
+}
+
+// The trash bin of the deleted documents and folders, which are permanently purged in the
+// background after the retention days.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrashProperties {
+    #[serde(rename = "retention-days")]
+    pub retention_days: u32,
+    // The interval seconds of the background purge.
+    #[serde(rename = "purge-interval")]
+    pub purge_interval: u64,
 }
 
 impl WebServeProperties {
@@ -339,10 +512,17 @@ impl Default for ServerProperties {
             context_path: None,
             thread_max_pool: 4,
             cors: CorsProperties::default(),
+            max_body_size: ServerProperties::default_max_body_size(),
         }
     }
 }
 
+impl ServerProperties {
+    fn default_max_body_size() -> usize {
+        32 * 1024 * 1024
+    }
+}
+
 impl Default for CorsProperties {
     fn default() -> Self {
         CorsProperties {
@@ -368,6 +548,30 @@ impl Default for DbProperties {
             db_type: DbType::Sqlite,
             sqlite: SqliteProperties::default(),
             mongo: MongoProperties::default(),
+            postgres: PostgresProperties::default(),
+            compression: CompressionProperties::default(),
+        }
+    }
+}
+
+impl Default for PostgresProperties {
+    fn default() -> Self {
+        PostgresProperties {
+            url: String::from("postgres://postgres@127.0.0.1:5432/mywebnote"),
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout: 30_000,
+            idle_timeout: 600_000,
+        }
+    }
+}
+
+impl Default for CompressionProperties {
+    fn default() -> Self {
+        CompressionProperties {
+            enabled: true,
+            threshold: 4096,
+            level: 3,
         }
     }
 }
@@ -569,6 +773,8 @@ impl WebServeConfig {
             for path in config.auth.anonymous_paths.as_ref().unwrap() {
                 builder.add(Glob::new(path).unwrap());
             }
+            // The public share links are always anonymous, which are verified by the token itself.
+            builder.add(Glob::new(PUBLIC_SHARE_GLOB).unwrap());
             globset = Some(builder.build().unwrap());
         } else {
             // Add internal components routes to defaults.
@@ -608,6 +814,110 @@ impl Default for WebNoteProperties {
                 String::from("menu"),
                 String::from("blob")
             ],
+            revision: RevisionProperties::default(),
+            trash: TrashProperties::default(),
+            blob: BlobProperties::default(),
+            backup: BackupProperties::default(),
+            board: BoardProperties::default(),
+            thumbnail: ThumbnailProperties::default(),
+            room: RoomProperties::default(),
+            sync: SyncProperties::default(),
+            events: EventProperties::default(),
+            share: ShareProperties::default(),
+        }
+    }
+}
+
+impl Default for RevisionProperties {
+    fn default() -> Self {
+        RevisionProperties {
+            keep_last: Some(50),
+            keep_days: None,
+        }
+    }
+}
+
+impl Default for BlobProperties {
+    fn default() -> Self {
+        BlobProperties {
+            dir: String::from("/tmp/mywebnote/blobs"),
+            max_size: 32 * 1024 * 1024,
+            gc_interval: 3600,
+            gc_grace: 86400,
+        }
+    }
+}
+
+impl Default for BackupProperties {
+    fn default() -> Self {
+        BackupProperties {
+            enabled: false,
+            dir: String::from("/tmp/mywebnote/backups"),
+            interval: 86400,
+            keep: 7,
+        }
+    }
+}
+
+impl Default for BoardProperties {
+    fn default() -> Self {
+        BoardProperties {
+            import_max_file_size: 32 * 1024 * 1024,
+            import_max_files: 1000,
+        }
+    }
+}
+
+impl Default for ThumbnailProperties {
+    fn default() -> Self {
+        ThumbnailProperties {
+            enabled: true,
+            max_width: 320,
+            max_height: 240,
+            ttl: 7 * 86400,
+        }
+    }
+}
+
+impl Default for RoomProperties {
+    fn default() -> Self {
+        RoomProperties {
+            persist_interval: 10,
+        }
+    }
+}
+
+impl Default for SyncProperties {
+    fn default() -> Self {
+        SyncProperties {
+            compact_threshold: 100,
+        }
+    }
+}
+
+impl Default for EventProperties {
+    fn default() -> Self {
+        EventProperties {
+            retention: 600,
+        }
+    }
+}
+
+impl Default for ShareProperties {
+    fn default() -> Self {
+        ShareProperties {
+            password_rounds: 600_000,
+            max_password_failures: 10,
+            password_failures_window: 15 * 60 * 1000,
+        }
+    }
+}
+
+impl Default for TrashProperties {
+    fn default() -> Self {
+        TrashProperties {
+            retention_days: 30,
+            purge_interval: 3600,
         }
     }
 }
@@ -637,6 +947,9 @@ pub fn refresh_config() -> Result<(), anyhow::Error> {
 // see: https://github.com/wl4g-collect/openobserve/blob/v0.10.9/src/config/src/config.rs#L186
 static CONFIG: Lazy<ArcSwap<WebServeConfig>> = Lazy::new(|| ArcSwap::from(init()));
 
+// The public share links of the documents, see: route/share.rs#PUBLIC_SHARE_URI
+pub const PUBLIC_SHARE_GLOB: &str = "/public/share/*";
+
 // Global static resources.
 pub const DEFAULT_INDEX_HTML: &str = include_str!("../../static/index.html");
 pub const DEFAULT_LOGIN_HTML: &str = include_str!("../../static/login.html");
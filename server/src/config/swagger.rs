@@ -52,15 +52,65 @@ use crate::{
             __path_handle_save_user,
         },
         document::{
+            __path_handle_move_documents,
             __path_handle_delete_document,
+            __path_handle_query_trash_documents,
+            __path_handle_restore_trash_document,
+            __path_handle_purge_trash_document,
             __path_handle_query_documents,
+            __path_handle_search_documents,
             __path_handle_save_document,
+            __path_handle_query_document_revisions,
+            __path_handle_get_document_revision,
+            __path_handle_diff_document_revision,
+            __path_handle_restore_document_revision,
+            __path_handle_export_document,
+            __path_handle_get_document_thumbnail,
+        },
+        blob::{
+            __path_handle_upload_blob,
+            __path_handle_download_blob,
+            __path_handle_query_blobs,
+        },
+        board::{
+            __path_handle_import_board,
+            __path_handle_bulk_import_boards,
+            __path_handle_export_board,
         },
         folder::{
             __path_handle_delete_folder,
+            __path_handle_query_trash_folders,
+            __path_handle_restore_trash_folder,
+            __path_handle_purge_trash_folder,
             __path_handle_query_folders,
             __path_handle_save_folder,
+            __path_handle_query_folder_tree,
+            __path_handle_move_folder,
+            __path_handle_export_folder,
+        },
+        tag::{
+            __path_handle_query_tags,
+            __path_handle_save_tag,
+            __path_handle_merge_tags,
+            __path_handle_delete_tag,
+            __path_handle_attach_tags,
+            __path_handle_detach_tags,
+        },
+        share::{
+            __path_handle_query_share_links,
+            __path_handle_create_share_link,
+            __path_handle_revoke_share_link,
+            __path_handle_get_shared_document,
+        },
+        acl::{
+            __path_handle_query_acls,
+            __path_handle_grant_acl,
+            __path_handle_revoke_acl,
+            __path_handle_query_shared,
         },
+        room::__path_handle_join_room,
+        sync::__path_handle_join_sync,
+        event::__path_handle_events,
         settings::{
             __path_handle_delete_settings,
             __path_handle_query_settings,
@@ -75,6 +125,14 @@ use crate::{
             __path_handle_delete_browser_indexeddb,
         },
     },
+    store::{
+        blobs_sqlite::BLOB_COLUMNS,
+        document_revisions_sqlite::DOCUMENT_REVISION_COLUMNS,
+        documents_sqlite::DOCUMENT_COLUMNS,
+        folders_sqlite::FOLDER_COLUMNS,
+        settings_sqlite::SETTINGS_COLUMNS,
+        users_sqlite::USER_COLUMNS,
+    },
     utils::auths,
 };
 
@@ -82,6 +140,7 @@ use crate::types::{
     BaseBean,
     PageRequest,
     PageResponse,
+    DocumentSearchHit,
     auth::{
         CallbackGithubRequest,
         CallbackOidcRequest,
@@ -114,9 +173,49 @@ use crate::types::{
         QueryDocumentResponse,
         SaveDocumentRequest,
         SaveDocumentResponse,
+        MoveDocumentRequest,
+        MoveDocumentResponse,
         DeleteDocumentRequest,
         DeleteDocumentResponse,
+        SearchDocumentRequest,
+        SearchDocumentResponse,
+        RestoreDocumentRequest,
+        RestoreDocumentResponse,
+        PurgeDocumentRequest,
+        PurgeDocumentResponse,
         DocumentType,
+        NoteFormat,
+        ExportDocumentRequest,
+        ThumbnailFormat,
+        GetThumbnailRequest,
+    },
+    blob::{
+        Blob,
+        UploadBlobRequest,
+        UploadBlobResponse,
+        DownloadBlobRequest,
+        QueryBlobRequest,
+        QueryBlobResponse,
+    },
+    board::{
+        BoardFormat,
+        ImportBoardRequest,
+        ImportBoardResponse,
+        BulkImportBoardRequest,
+        BulkImportBoardResponse,
+        ImportedBoard,
+        FailedBoard,
+        ExportBoardRequest,
+    },
+    document_revision::{
+        DocumentRevision,
+        QueryDocumentRevisionRequest,
+        QueryDocumentRevisionResponse,
+        GetDocumentRevisionRequest,
+        DiffDocumentRevisionRequest,
+        DiffDocumentRevisionResponse,
+        RestoreDocumentRevisionRequest,
+        RestoreDocumentRevisionResponse,
     },
     folder::{
         Folder,
@@ -124,9 +223,60 @@ use crate::types::{
         QueryFolderResponse,
         SaveFolderRequest,
         SaveFolderResponse,
+        FolderNode,
+        FolderTreeResponse,
+        MoveFolderRequest,
+        MoveFolderResponse,
+        FolderCascade,
         DeleteFolderRequest,
         DeleteFolderResponse,
+        RestoreFolderRequest,
+        RestoreFolderResponse,
+        PurgeFolderRequest,
+        PurgeFolderResponse,
+        ExportFolderRequest,
+    },
+    tag::{
+        Tag,
+        TagMatch,
+        TagCount,
+        QueryTagRequest,
+        QueryTagResponse,
+        SaveTagRequest,
+        SaveTagResponse,
+        MergeTagRequest,
+        MergeTagResponse,
+        DeleteTagRequest,
+        DeleteTagResponse,
+        TagDocumentsRequest,
+        TagDocumentsResponse,
     },
+    share::{
+        ShareLink,
+        ShareLinkView,
+        QueryShareLinkRequest,
+        QueryShareLinkResponse,
+        CreateShareLinkRequest,
+        CreateShareLinkResponse,
+        RevokeShareLinkRequest,
+        RevokeShareLinkResponse,
+        SharedDocumentResponse,
+    },
+    acl::{
+        Acl,
+        AclResource,
+        AclRole,
+        QueryAclRequest,
+        QueryAclResponse,
+        GrantAclRequest,
+        GrantAclResponse,
+        RevokeAclRequest,
+        RevokeAclResponse,
+        SharedResource,
+        QuerySharedResponse,
+    },
+    room::{ RoomMessage, RoomEvent },
+    event::{ ChangeEvent, ChangeResource, ChangeAction },
     settings::{
         Settings,
         QuerySettingsRequest,
@@ -186,12 +336,60 @@ use crate::types::{
         handle_apiv1_delete_user,
         // Document
         handle_query_documents,
+        handle_search_documents,
         handle_save_document,
+        handle_move_documents,
         handle_delete_document,
+        handle_query_trash_documents,
+        handle_restore_trash_document,
+        handle_purge_trash_document,
+        handle_query_document_revisions,
+        handle_get_document_revision,
+        handle_diff_document_revision,
+        handle_restore_document_revision,
+        handle_export_document,
+        handle_get_document_thumbnail,
+        // Blob
+        handle_upload_blob,
+        handle_download_blob,
+        handle_query_blobs,
+        // Board
+        handle_import_board,
+        handle_bulk_import_boards,
+        handle_export_board,
         // Folder
         handle_query_folders,
         handle_save_folder,
+        handle_query_folder_tree,
+        handle_move_folder,
         handle_delete_folder,
+        handle_query_trash_folders,
+        handle_restore_trash_folder,
+        handle_purge_trash_folder,
+        handle_export_folder,
+        // Tag
+        handle_query_tags,
+        handle_save_tag,
+        handle_merge_tags,
+        handle_delete_tag,
+        handle_attach_tags,
+        handle_detach_tags,
+        // Share
+        handle_query_share_links,
+        handle_create_share_link,
+        handle_revoke_share_link,
+        handle_get_shared_document,
+        // Acl
+        handle_query_acls,
+        handle_grant_acl,
+        handle_revoke_acl,
+        handle_query_shared,
+        // Room
+        handle_join_room,
+        // Sync
+        handle_join_sync,
+        // Events
+        handle_events,
         // Settings
         handle_query_settings,
         handle_save_settings,
@@ -238,17 +436,107 @@ use crate::types::{
             QueryDocumentResponse,
             SaveDocumentRequest,
             SaveDocumentResponse,
+            MoveDocumentRequest,
+            MoveDocumentResponse,
             DeleteDocumentRequest,
             DeleteDocumentResponse,
+            SearchDocumentRequest,
+            SearchDocumentResponse,
+            DocumentSearchHit,
+            RestoreDocumentRequest,
+            RestoreDocumentResponse,
+            PurgeDocumentRequest,
+            PurgeDocumentResponse,
             DocumentType,
+            DocumentRevision,
+            QueryDocumentRevisionRequest,
+            QueryDocumentRevisionResponse,
+            GetDocumentRevisionRequest,
+            DiffDocumentRevisionRequest,
+            DiffDocumentRevisionResponse,
+            RestoreDocumentRevisionRequest,
+            RestoreDocumentRevisionResponse,
+            NoteFormat,
+            ExportDocumentRequest,
+            ThumbnailFormat,
+            GetThumbnailRequest,
+            // Module of Blob
+            Blob,
+            UploadBlobRequest,
+            UploadBlobResponse,
+            DownloadBlobRequest,
+            QueryBlobRequest,
+            QueryBlobResponse,
+            // Module of Board
+            BoardFormat,
+            ImportBoardRequest,
+            ImportBoardResponse,
+            BulkImportBoardRequest,
+            BulkImportBoardResponse,
+            ImportedBoard,
+            FailedBoard,
+            ExportBoardRequest,
             // Module of Folder
             Folder,
             QueryFolderRequest,
             QueryFolderResponse,
             SaveFolderRequest,
             SaveFolderResponse,
+            FolderNode,
+            FolderTreeResponse,
+            MoveFolderRequest,
+            MoveFolderResponse,
+            FolderCascade,
             DeleteFolderRequest,
             DeleteFolderResponse,
+            RestoreFolderRequest,
+            RestoreFolderResponse,
+            PurgeFolderRequest,
+            PurgeFolderResponse,
+            ExportFolderRequest,
+            // Module of Tag
+            Tag,
+            TagMatch,
+            TagCount,
+            QueryTagRequest,
+            QueryTagResponse,
+            SaveTagRequest,
+            SaveTagResponse,
+            MergeTagRequest,
+            MergeTagResponse,
+            DeleteTagRequest,
+            DeleteTagResponse,
+            TagDocumentsRequest,
+            TagDocumentsResponse,
+            // Module of Share
+            ShareLink,
+            ShareLinkView,
+            QueryShareLinkRequest,
+            QueryShareLinkResponse,
+            CreateShareLinkRequest,
+            CreateShareLinkResponse,
+            RevokeShareLinkRequest,
+            RevokeShareLinkResponse,
+            SharedDocumentResponse,
+            // Module of Acl
+            Acl,
+            AclResource,
+            AclRole,
+            QueryAclRequest,
+            QueryAclResponse,
+            GrantAclRequest,
+            GrantAclResponse,
+            RevokeAclRequest,
+            RevokeAclResponse,
+            SharedResource,
+            QuerySharedResponse,
+            // Module of Room
+            RoomMessage,
+            RoomEvent,
+            // Module of Events
+            ChangeEvent,
+            ChangeResource,
+            ChangeAction,
             // Module of Settings
             Settings,
             QuerySettingsRequest,
@@ -271,10 +559,49 @@ use crate::types::{
             DeleteIndexedRecordResponse
         )
     ),
-    modifiers(&ApiPathPrefixer)
+    modifiers(&PageParamsDescriber, &ApiPathPrefixer)
 )]
 struct ApiDoc;
 
+// Append the whitelist columns of the entity to the description of the 'sort' and 'fields' of the
+// paging parameters, see: types::PageRequest
+struct PageParamsDescriber;
+
+impl PageParamsDescriber {
+    // The entity columns of the queried path, the search is ordered by the relevance instead.
+    fn columns(path: &str) -> Option<&'static [&'static str]> {
+        match path {
+            "/modules/document/revision/query" => Some(DOCUMENT_REVISION_COLUMNS),
+            "/modules/document/query" | "/modules/document/trash/query" => Some(DOCUMENT_COLUMNS),
+            "/modules/folder/query" | "/modules/folder/trash/query" => Some(FOLDER_COLUMNS),
+            "/modules/blob/query" => Some(BLOB_COLUMNS),
+            "/sys/settings/query" => Some(SETTINGS_COLUMNS),
+            "/sys/user/query" | "/api/v1/user/query" => Some(USER_COLUMNS),
+            _ => None,
+        }
+    }
+}
+
+impl utoipa::Modify for PageParamsDescriber {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        for (path, item) in openapi.paths.paths.iter_mut() {
+            let columns = match Self::columns(path) {
+                Some(columns) => columns,
+                None => continue,
+            };
+            let params = item.operations
+                .values_mut()
+                .flat_map(|operation| operation.parameters.iter_mut().flatten())
+                .filter(|param| param.name == "sort" || param.name == "fields");
+            for param in params {
+                let description = param.description.take().unwrap_or_default();
+                let description = format!("{} The whitelist columns: {}.", description, columns.join(", "));
+                param.description = Some(description.trim().to_string());
+            }
+        }
+    }
+}
+
 struct ApiPathPrefixer;
 
 impl utoipa::Modify for ApiPathPrefixer {
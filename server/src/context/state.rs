@@ -28,21 +28,59 @@ use crate::cache::memory::StringMemoryCache;
 use crate::cache::redis::StringRedisCache;
 use crate::cache::CacheContainer;
 // use crate::monitoring::health::{ MongoChecker, RedisClusterChecker, SQLiteChecker };
+use crate::types::acl::Acl;
+use crate::types::blob::{ Blob, DocumentBlob };
 use crate::types::document::Document;
+use crate::types::document_revision::DocumentRevision;
+use crate::types::document_update::DocumentUpdate;
 use crate::types::folder::Folder;
 use crate::types::settings::Settings;
+use crate::types::share::ShareLink;
+use crate::types::tag::{ DocumentTag, Tag };
 use crate::types::user::User;
 use crate::config::config_serve::WebServeConfig;
+use crate::handler::room::RoomRegistry;
 use crate::store::{
     RepositoryContainer,
+    postgres,
+    acls_sqlite::AclSQLiteRepository,
+    acls_mongo::AclMongoRepository,
+    acls_postgres::AclPostgresRepository,
+    blobs::BlobStorage,
+    blobs_local::LocalBlobStorage,
+    blobs_sqlite::BlobSQLiteRepository,
+    blobs_mongo::BlobMongoRepository,
+    blobs_postgres::BlobPostgresRepository,
     documents_sqlite::DocumentSQLiteRepository,
     documents_mongo::DocumentMongoRepository,
+    documents_postgres::DocumentPostgresRepository,
+    document_revisions_sqlite::DocumentRevisionSQLiteRepository,
+    document_revisions_mongo::DocumentRevisionMongoRepository,
+    document_revisions_postgres::DocumentRevisionPostgresRepository,
+    document_updates_sqlite::DocumentUpdateSQLiteRepository,
+    document_updates_mongo::DocumentUpdateMongoRepository,
+    document_updates_postgres::DocumentUpdatePostgresRepository,
+    document_blobs_sqlite::DocumentBlobSQLiteRepository,
+    document_blobs_mongo::DocumentBlobMongoRepository,
+    document_blobs_postgres::DocumentBlobPostgresRepository,
+    document_tags_sqlite::DocumentTagSQLiteRepository,
+    document_tags_mongo::DocumentTagMongoRepository,
+    document_tags_postgres::DocumentTagPostgresRepository,
     folders_sqlite::FolderSQLiteRepository,
     folders_mongo::FolderMongoRepository,
+    folders_postgres::FolderPostgresRepository,
     settings_sqlite::SettingsSQLiteRepository,
     settings_mongo::SettingsMongoRepository,
+    settings_postgres::SettingsPostgresRepository,
+    share_links_sqlite::ShareLinkSQLiteRepository,
+    share_links_mongo::ShareLinkMongoRepository,
+    share_links_postgres::ShareLinkPostgresRepository,
+    tags_sqlite::TagSQLiteRepository,
+    tags_mongo::TagMongoRepository,
+    tags_postgres::TagPostgresRepository,
     users_sqlite::UserSQLiteRepository,
     users_mongo::UserMongoRepository,
+    users_postgres::UserPostgresRepository,
 };
 use crate::utils::{ self, httpclients };
 
@@ -54,11 +92,22 @@ pub struct AppState {
     pub oidc_client: Option<Arc<openidconnect::core::CoreClient>>,
     pub github_client: Option<Arc<BasicClient>>,
     pub default_http_client: Arc<reqwest::Client>,
+    pub blob_storage: Arc<dyn BlobStorage>,
+    // The real-time collaboration rooms of this instance.
+    pub rooms: Arc<RoomRegistry>,
     // The modules repositories.
     pub user_repo: Arc<Mutex<RepositoryContainer<User>>>,
     pub document_repo: Arc<Mutex<RepositoryContainer<Document>>>,
+    pub document_revision_repo: Arc<Mutex<RepositoryContainer<DocumentRevision>>>,
+    pub document_update_repo: Arc<Mutex<RepositoryContainer<DocumentUpdate>>>,
     pub folder_repo: Arc<Mutex<RepositoryContainer<Folder>>>,
     pub settings_repo: Arc<Mutex<RepositoryContainer<Settings>>>,
+    pub blob_repo: Arc<Mutex<RepositoryContainer<Blob>>>,
+    pub document_blob_repo: Arc<Mutex<RepositoryContainer<DocumentBlob>>>,
+    pub tag_repo: Arc<Mutex<RepositoryContainer<Tag>>>,
+    pub document_tag_repo: Arc<Mutex<RepositoryContainer<DocumentTag>>>,
+    pub share_link_repo: Arc<Mutex<RepositoryContainer<ShareLink>>>,
+    pub acl_repo: Arc<Mutex<RepositoryContainer<Acl>>>,
     // // The health checker.
     // pub sqlite_checker: SQLiteChecker,
     // pub mongo_checker: MongoChecker,
@@ -90,21 +139,66 @@ impl AppState {
 
         // Build DB repositories.
         let db_config = &config.db;
+        let pg_pool = postgres::create_pool(db_config).await.unwrap();
         let user_repo_container = RepositoryContainer::new(
-            Box::new(UserSQLiteRepository::new(&db_config).await.unwrap()),
-            Box::new(UserMongoRepository::new(&db_config).await.unwrap())
+            Box::new(UserSQLiteRepository::new(db_config).await.unwrap()),
+            Box::new(UserMongoRepository::new(db_config).await.unwrap()),
+            Box::new(UserPostgresRepository::new(pg_pool.clone()))
         );
         let document_repo_container = RepositoryContainer::new(
-            Box::new(DocumentSQLiteRepository::new(&db_config).await.unwrap()),
-            Box::new(DocumentMongoRepository::new(&db_config).await.unwrap())
+            Box::new(DocumentSQLiteRepository::new(db_config).await.unwrap()),
+            Box::new(DocumentMongoRepository::new(db_config).await.unwrap()),
+            Box::new(DocumentPostgresRepository::new(db_config, pg_pool.clone()))
+        );
+        let document_revision_repo_container = RepositoryContainer::new(
+            Box::new(DocumentRevisionSQLiteRepository::new(db_config).await.unwrap()),
+            Box::new(DocumentRevisionMongoRepository::new(db_config).await.unwrap()),
+            Box::new(DocumentRevisionPostgresRepository::new(db_config, pg_pool.clone()))
+        );
+        let document_update_repo_container = RepositoryContainer::new(
+            Box::new(DocumentUpdateSQLiteRepository::new(db_config).await.unwrap()),
+            Box::new(DocumentUpdateMongoRepository::new(db_config).await.unwrap()),
+            Box::new(DocumentUpdatePostgresRepository::new(pg_pool.clone()))
         );
         let folder_repo_container = RepositoryContainer::new(
-            Box::new(FolderSQLiteRepository::new(&db_config).await.unwrap()),
-            Box::new(FolderMongoRepository::new(&db_config).await.unwrap())
+            Box::new(FolderSQLiteRepository::new(db_config).await.unwrap()),
+            Box::new(FolderMongoRepository::new(db_config).await.unwrap()),
+            Box::new(FolderPostgresRepository::new(pg_pool.clone()))
         );
         let settings_repo_container = RepositoryContainer::new(
-            Box::new(SettingsSQLiteRepository::new(&db_config).await.unwrap()),
-            Box::new(SettingsMongoRepository::new(&db_config).await.unwrap())
+            Box::new(SettingsSQLiteRepository::new(db_config).await.unwrap()),
+            Box::new(SettingsMongoRepository::new(db_config).await.unwrap()),
+            Box::new(SettingsPostgresRepository::new(pg_pool.clone()))
+        );
+        let blob_repo_container = RepositoryContainer::new(
+            Box::new(BlobSQLiteRepository::new(db_config).await.unwrap()),
+            Box::new(BlobMongoRepository::new(db_config).await.unwrap()),
+            Box::new(BlobPostgresRepository::new(pg_pool.clone()))
+        );
+        let document_blob_repo_container = RepositoryContainer::new(
+            Box::new(DocumentBlobSQLiteRepository::new(db_config).await.unwrap()),
+            Box::new(DocumentBlobMongoRepository::new(db_config).await.unwrap()),
+            Box::new(DocumentBlobPostgresRepository::new(pg_pool.clone()))
+        );
+        let tag_repo_container = RepositoryContainer::new(
+            Box::new(TagSQLiteRepository::new(db_config).await.unwrap()),
+            Box::new(TagMongoRepository::new(db_config).await.unwrap()),
+            Box::new(TagPostgresRepository::new(pg_pool.clone()))
+        );
+        let document_tag_repo_container = RepositoryContainer::new(
+            Box::new(DocumentTagSQLiteRepository::new(db_config).await.unwrap()),
+            Box::new(DocumentTagMongoRepository::new(db_config).await.unwrap()),
+            Box::new(DocumentTagPostgresRepository::new(pg_pool.clone()))
+        );
+        let share_link_repo_container = RepositoryContainer::new(
+            Box::new(ShareLinkSQLiteRepository::new(db_config).await.unwrap()),
+            Box::new(ShareLinkMongoRepository::new(db_config).await.unwrap()),
+            Box::new(ShareLinkPostgresRepository::new(pg_pool.clone()))
+        );
+        let acl_repo_container = RepositoryContainer::new(
+            Box::new(AclSQLiteRepository::new(db_config).await.unwrap()),
+            Box::new(AclMongoRepository::new(db_config).await.unwrap()),
+            Box::new(AclPostgresRepository::new(pg_pool.clone()))
         );
 
         let app_state = AppState {
@@ -115,11 +209,21 @@ impl AppState {
             oidc_client: auth_clients.0,
             github_client: auth_clients.1,
             default_http_client: Arc::new(http_client),
+            blob_storage: Arc::new(LocalBlobStorage::new(&config.webnote.blob.dir)),
+            rooms: Arc::new(RoomRegistry::default()),
             // The modules repositories.
             user_repo: Arc::new(Mutex::new(user_repo_container)),
             document_repo: Arc::new(Mutex::new(document_repo_container)),
+            document_revision_repo: Arc::new(Mutex::new(document_revision_repo_container)),
+            document_update_repo: Arc::new(Mutex::new(document_update_repo_container)),
             folder_repo: Arc::new(Mutex::new(folder_repo_container)),
             settings_repo: Arc::new(Mutex::new(settings_repo_container)),
+            blob_repo: Arc::new(Mutex::new(blob_repo_container)),
+            document_blob_repo: Arc::new(Mutex::new(document_blob_repo_container)),
+            tag_repo: Arc::new(Mutex::new(tag_repo_container)),
+            document_tag_repo: Arc::new(Mutex::new(document_tag_repo_container)),
+            share_link_repo: Arc::new(Mutex::new(share_link_repo_container)),
+            acl_repo: Arc::new(Mutex::new(acl_repo_container)),
             // // The health checker.
             // sqlite_checker: SQLiteChecker::new(),
             // mongo_checker: MongoChecker::new(),
@@ -0,0 +1,88 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use axum::{ response::{ IntoResponse, Response }, Json };
+use hyper::{ header, StatusCode };
+use serde_json::json;
+use thiserror::Error;
+
+use crate::utils::webs::to_etag;
+
+// The business errors which should be responded with a specific http status rather than 500.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum BizError {
+    #[error("Bad request, {0}")]
+    BadRequest(String),
+    #[error("Unauthenticated")]
+    Unauthenticated,
+    #[error("Forbidden, {0}")]
+    Forbidden(String),
+    #[error("Not found the {0}")]
+    NotFound(String),
+    #[error("Payload too large, {0}")]
+    PayloadTooLarge(String),
+    // The stale write of the optimistic lock, with the current version of the resource.
+    #[error("Conflict the {0} was modified, the current version is {1}")]
+    Conflict(String, i64),
+    #[error("Precondition required, {0}")]
+    PreconditionRequired(String),
+    #[error("Too many requests, {0}")]
+    TooManyRequests(String),
+}
+
+impl BizError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            BizError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            BizError::Unauthenticated => StatusCode::UNAUTHORIZED,
+            BizError::Forbidden(_) => StatusCode::FORBIDDEN,
+            BizError::NotFound(_) => StatusCode::NOT_FOUND,
+            BizError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            BizError::Conflict(_, _) => StatusCode::CONFLICT,
+            BizError::PreconditionRequired(_) => StatusCode::PRECONDITION_REQUIRED,
+            BizError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+}
+
+// Resolve the http status of the handler result error, fallback to internal server error.
+pub fn to_status_code(error: &anyhow::Error) -> StatusCode {
+    match error.downcast_ref::<BizError>() {
+        Some(e) => e.status_code(),
+        None => {
+            tracing::error!("Internal server error: {:?}", error);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+// Resolve the http response of the handler result error, which is same as the status code but the
+// conflict also responds the current version (as the body and ETag), so that the client could merge.
+pub fn to_response(error: &anyhow::Error) -> Response {
+    match error.downcast_ref::<BizError>() {
+        Some(e @ BizError::Conflict(_, version)) => {
+            let body = json!({ "version": version, "message": e.to_string() });
+            (e.status_code(), [(header::ETAG, to_etag(*version))], Json(body)).into_response()
+        }
+        _ => to_status_code(error).into_response(),
+    }
+}
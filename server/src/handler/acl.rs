@@ -0,0 +1,324 @@
+use std::collections::{ BTreeMap, BTreeSet, HashSet };
+
+use anyhow::Error;
+use axum::async_trait;
+
+use crate::context::state::AppState;
+use crate::errors::BizError;
+use crate::store;
+use crate::types::acl::{
+    Acl,
+    AclResource,
+    AclRole,
+    GrantAclRequest,
+    QueryAclRequest,
+    RevokeAclRequest,
+    SharedResource,
+};
+use crate::types::folder::Folder;
+use crate::utils::auths::{ AuthUserClaims, SecurityContext };
+use super::folder::FolderHandler;
+use super::user::{ IUserHandler, UserHandler };
+
+#[async_trait]
+pub trait IAclHandler: Send {
+    // The entries granted by the current user, latest created first.
+    async fn find(&self, param: QueryAclRequest) -> Result<Vec<Acl>, Error>;
+
+    // Grant the user of the email the role on the owned resource, returns the id of the entry.
+    async fn grant(&self, param: GrantAclRequest) -> Result<i64, Error>;
+
+    async fn revoke(&self, param: RevokeAclRequest) -> Result<u64, Error>;
+
+    // The resources shared with the current user by the others, the absent (such as trashed) are ignored.
+    async fn find_shared(&self) -> Result<Vec<SharedResource>, Error>;
+}
+
+pub struct AclHandler<'a> {
+    state: &'a AppState,
+}
+
+impl<'a> AclHandler<'a> {
+    pub fn new(state: &'a AppState) -> Self {
+        Self { state }
+    }
+
+    // Authorize the current user the role on the resource, returns the claims of acting as the owner of
+    // the resource (which keeps the identity of the current user for the audit) if shared by the others,
+    // or none if owned by the current user. The resource not owned nor shared is responded as not found.
+    pub(crate) async fn authorize(
+        &self,
+        resource_type: AclResource,
+        id: i64,
+        role: AclRole
+    ) -> Result<Option<AuthUserClaims>, Error> {
+        match self.select_resource(resource_type, id).await {
+            Ok(_) => {
+                return Ok(None);
+            }
+            Err(e) if !is_not_found(&e) => {
+                return Err(e);
+            }
+            Err(_) => {}
+        }
+
+        for (owner_uid, acls) in self.list_granted_by_owner().await? {
+            let claims = as_owner(owner_uid);
+            let resolved = self.resolve_role(&acls, resource_type, id);
+            match SecurityContext::scope(Some(claims.clone()), resolved).await? {
+                Some(granted) if granted >= role => {
+                    return Ok(Some(claims));
+                }
+                Some(_) => {
+                    return Err(BizError::Forbidden(format!("The {:?} role is required", role)).into());
+                }
+                None => {}
+            }
+        }
+        Err(BizError::NotFound(to_name(resource_type)).into())
+    }
+
+    // The ids of the documents of the owner shared with the current user, which directly or within the
+    // shared folder subtrees, and the claims of acting as the owner to select them.
+    pub(crate) async fn shared_document_ids(&self, owner_uid: i64) -> Result<(AuthUserClaims, Vec<i64>), Error> {
+        let acls = self.list_granted_by_owner().await?.remove(&owner_uid).unwrap_or_default();
+        let claims = as_owner(owner_uid);
+        let ids = SecurityContext::scope(Some(claims.clone()), async {
+            let mut ids: BTreeSet<i64> = acls
+                .iter()
+                .filter(|acl| acl.resource_type == Some(AclResource::Document))
+                .filter_map(|acl| acl.resource_id)
+                .collect();
+            let folders = FolderHandler::new(self.state);
+            for folder in self.collect_shared_folders(&acls).await? {
+                for document in folders.select_documents(&folder, false).await? {
+                    ids.extend(document.base.id);
+                }
+            }
+            Ok::<_, Error>(ids)
+        }).await?;
+        Ok((claims, ids.into_iter().collect()))
+    }
+
+    // The ids of the folders of the owner shared with the current user, which include the sub folders.
+    pub(crate) async fn shared_folder_ids(&self, owner_uid: i64) -> Result<(AuthUserClaims, Vec<i64>), Error> {
+        let acls = self.list_granted_by_owner().await?.remove(&owner_uid).unwrap_or_default();
+        let claims = as_owner(owner_uid);
+        let folders = SecurityContext::scope(Some(claims.clone()), self.collect_shared_folders(&acls)).await?;
+        Ok((claims, folders.into_iter().filter_map(|folder| folder.base.id).collect()))
+    }
+
+    // Delete the entries of the resource, such as the resource is purged.
+    pub(crate) async fn delete_by_resource(&self, resource_type: AclResource, id: i64) -> Result<u64, Error> {
+        let param = Acl::with(None, Some(resource_type), Some(id));
+        let acls = store::select_all(&self.state.acl_repo, &self.state.config, param, false).await?;
+        let repo = self.state.acl_repo.lock().await;
+        let mut deleted = 0;
+        for acl in acls {
+            deleted += repo.get(&self.state.config).delete_by_id(acl.base.id.unwrap_or_default()).await?;
+        }
+        Ok(deleted)
+    }
+
+    // The entries granted to the current user, grouped by the owners.
+    async fn list_granted_by_owner(&self) -> Result<BTreeMap<i64, Vec<Acl>>, Error> {
+        let uid = SecurityContext::get_current_uid().ok_or(BizError::Unauthenticated)?;
+        let param = Acl::with(Some(uid), None, None);
+        let acls = store::select_all_unscoped(&self.state.acl_repo, &self.state.config, param).await?;
+        let mut grouped: BTreeMap<i64, Vec<Acl>> = BTreeMap::new();
+        for acl in acls {
+            grouped.entry(acl.owner_uid.unwrap_or_default()).or_default().push(acl);
+        }
+        Ok(grouped)
+    }
+
+    // Select the name and the key of the resource of the current owner.
+    async fn select_resource(
+        &self,
+        resource_type: AclResource,
+        id: i64
+    ) -> Result<(Option<String>, Option<String>), Error> {
+        match resource_type {
+            AclResource::Document => {
+                let repo = self.state.document_repo.lock().await;
+                let document = repo.get(&self.state.config).select_by_id(id).await?;
+                Ok((document.key, document.name))
+            }
+            AclResource::Folder => {
+                let folder = self.select_folder(id).await?;
+                Ok((folder.key, folder.name))
+            }
+        }
+    }
+
+    async fn select_folder(&self, id: i64) -> Result<Folder, Error> {
+        let repo = self.state.folder_repo.lock().await;
+        repo.get(&self.state.config).select_by_id(id).await
+    }
+
+    // Resolve the highest role of the entries on the resource of the current owner, which is granted on the
+    // resource directly or on any of the ancestor folders, or none if the resource is absent or not shared.
+    async fn resolve_role(
+        &self,
+        acls: &[Acl],
+        resource_type: AclResource,
+        id: i64
+    ) -> Result<Option<AclRole>, Error> {
+        let folder = match resource_type {
+            AclResource::Document => {
+                let repo = self.state.document_repo.lock().await;
+                let document = match repo.get(&self.state.config).select_by_id(id).await {
+                    Ok(document) => document,
+                    Err(e) if is_not_found(&e) => {
+                        return Ok(None);
+                    }
+                    Err(e) => {
+                        return Err(e);
+                    }
+                };
+                drop(repo);
+                match document.folder_key.filter(|key| !key.is_empty()) {
+                    Some(key) => FolderHandler::new(self.state).get_by_key(&key).await?,
+                    None => None,
+                }
+            }
+            AclResource::Folder =>
+                match self.select_folder(id).await {
+                    Ok(folder) => Some(folder),
+                    Err(e) if is_not_found(&e) => {
+                        return Ok(None);
+                    }
+                    Err(e) => {
+                        return Err(e);
+                    }
+                }
+        };
+
+        // The resource itself and the ancestor folders, and stop at the cycle if any.
+        let mut granted = HashSet::from([(resource_type, id)]);
+        let mut visited = BTreeSet::new();
+        let mut current = folder;
+        while let Some(folder) = current {
+            let folder_id = folder.base.id.unwrap_or_default();
+            if !visited.insert(folder_id) {
+                break;
+            }
+            granted.insert((AclResource::Folder, folder_id));
+            current = match folder.pid.filter(|pid| *pid > 0) {
+                Some(pid) => Some(self.select_folder(pid).await?),
+                None => None,
+            };
+        }
+        Ok(
+            acls
+                .iter()
+                .filter(|acl| {
+                    let resource_type = acl.resource_type.unwrap_or(AclResource::Document);
+                    granted.contains(&(resource_type, acl.resource_id.unwrap_or_default()))
+                })
+                .filter_map(|acl| acl.role)
+                .max()
+        )
+    }
+
+    // Collect the shared folders of the current owner and all the sub folders of them.
+    async fn collect_shared_folders(&self, acls: &[Acl]) -> Result<Vec<Folder>, Error> {
+        let folders = FolderHandler::new(self.state);
+        let mut collected = BTreeMap::new();
+        for acl in acls.iter().filter(|acl| acl.resource_type == Some(AclResource::Folder)) {
+            let root = match self.select_folder(acl.resource_id.unwrap_or_default()).await {
+                Ok(root) => root,
+                Err(e) if is_not_found(&e) => {
+                    continue;
+                }
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+            for folder in folders.collect_tree(root, false).await? {
+                collected.insert(folder.base.id.unwrap_or_default(), folder);
+            }
+        }
+        Ok(collected.into_values().collect())
+    }
+}
+
+#[async_trait]
+impl<'a> IAclHandler for AclHandler<'a> {
+    async fn find(&self, param: QueryAclRequest) -> Result<Vec<Acl>, Error> {
+        let param = Acl::with(None, param.resource_type, param.resource_id);
+        store::select_all(&self.state.acl_repo, &self.state.config, param, false).await
+    }
+
+    async fn grant(&self, param: GrantAclRequest) -> Result<i64, Error> {
+        self.select_resource(param.resource_type, param.resource_id).await?;
+        let grantee = UserHandler::new(self.state)
+            .get(None, None, Some(param.email), None, None, None, None, None).await?
+            .ok_or_else(|| BizError::NotFound("user".to_string()))?;
+        let grantee_uid = grantee.base.id.unwrap_or_default();
+        if Some(grantee_uid) == SecurityContext::get_current_uid() {
+            return Err(BizError::BadRequest("The resource could not be shared with the owner".to_string()).into());
+        }
+
+        let existing = Acl::with(Some(grantee_uid), Some(param.resource_type), Some(param.resource_id));
+        let existing = store::select_all(&self.state.acl_repo, &self.state.config, existing, false).await?.pop();
+        let repo = self.state.acl_repo.lock().await;
+        match existing {
+            Some(mut acl) => {
+                let id = acl.base.id.unwrap_or_default();
+                acl.role = Some(param.role);
+                repo.get(&self.state.config).update(acl).await?;
+                Ok(id)
+            }
+            None => {
+                let acl = Acl::new(grantee_uid, param.resource_type, param.resource_id, param.role);
+                repo.get(&self.state.config).insert(acl).await
+            }
+        }
+    }
+
+    async fn revoke(&self, param: RevokeAclRequest) -> Result<u64, Error> {
+        let repo = self.state.acl_repo.lock().await;
+        let revoked = repo.get(&self.state.config).delete_by_id(param.id).await?;
+        if revoked == 0 {
+            return Err(BizError::NotFound("acl".to_string()).into());
+        }
+        Ok(revoked)
+    }
+
+    async fn find_shared(&self) -> Result<Vec<SharedResource>, Error> {
+        let mut shared = Vec::new();
+        for (owner_uid, acls) in self.list_granted_by_owner().await? {
+            for acl in acls {
+                let resource_type = acl.resource_type.unwrap_or(AclResource::Document);
+                let selected = self.select_resource(resource_type, acl.resource_id.unwrap_or_default());
+                match SecurityContext::scope(Some(as_owner(owner_uid)), selected).await {
+                    Ok((key, name)) => shared.push(SharedResource { acl, key, name }),
+                    Err(e) if is_not_found(&e) => {}
+                    Err(e) => {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        Ok(shared)
+    }
+}
+
+// The claims of the current user, but acting as the owner of the shared resources.
+fn as_owner(owner_uid: i64) -> AuthUserClaims {
+    let mut claims = SecurityContext::get().unwrap_or_else(|| AuthUserClaims::system(owner_uid));
+    claims.uid = owner_uid;
+    claims
+}
+
+fn is_not_found(e: &Error) -> bool {
+    matches!(e.downcast_ref::<BizError>(), Some(BizError::NotFound(_)))
+}
+
+fn to_name(resource_type: AclResource) -> String {
+    match resource_type {
+        AclResource::Document => "document".to_string(),
+        AclResource::Folder => "folder".to_string(),
+    }
+}
@@ -43,6 +43,8 @@ pub enum PrincipalType {
     OIDC,
     Github,
     EtherWallet,
+    // The internal principal of the background jobs, which never issued to the clients.
+    System,
 }
 
 #[async_trait]
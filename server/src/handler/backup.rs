@@ -0,0 +1,586 @@
+use std::collections::{ BTreeMap, BTreeSet, HashSet };
+use std::fs::File;
+use std::io::{ BufWriter, Read, Seek, Write };
+use std::path::{ Path, PathBuf };
+use std::time::Duration;
+
+use anyhow::Error;
+use chrono::Utc;
+use futures::StreamExt;
+use serde::{ de::DeserializeOwned, Deserialize, Serialize };
+use sha2::{ Digest, Sha256 };
+use tokio::io::{ AsyncBufReadExt, Lines };
+use tokio::sync::{ mpsc, Mutex };
+use tokio_util::io::ReaderStream;
+use zip::{ read::ZipFile, write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter };
+
+use crate::config::config_serve::DbType;
+use crate::context::state::AppState;
+use crate::store::{ sqlite, RepositoryContainer };
+use crate::types::ModuleBean;
+use crate::types::acl::Acl;
+use crate::types::blob::{ Blob, DocumentBlob };
+use crate::types::document::Document;
+use crate::types::document_revision::DocumentRevision;
+use crate::types::document_update::DocumentUpdate;
+use crate::types::folder::Folder;
+use crate::types::settings::Settings;
+use crate::types::share::ShareLink;
+use crate::types::tag::{ DocumentTag, Tag };
+use crate::types::user::User;
+
+// The version of the archive layout, which is increased on the incompatible changes.
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+pub const BACKUP_FILE_PREFIX: &str = "mywebnote-backup-";
+pub const BACKUP_FILE_SUFFIX: &str = ".zip";
+
+const MANIFEST_FILE: &str = "manifest.json";
+// The contents of the blobs are stored as 'blobs/<hash>' of the archive.
+const BLOBS_DIR: &str = "blobs/";
+const BATCH_SIZE: u32 = 500;
+// The number of the pending writes to the archive, which the reading of the rows waits for when full.
+const WRITE_BUFFER_SIZE: usize = 16;
+
+// The manifest of the archive, which the modules data are stored as the JSON lines files.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BackupManifest {
+    pub format_version: u32,
+    // The latest schema migration version when backup, the archive of the newer can't be restored.
+    pub schema_version: i64,
+    // The DbType of backup from.
+    pub db_type: DbType,
+    pub create_time: i64,
+    // Only the data of the user if present, otherwise of the all users.
+    pub owner_uid: Option<i64>,
+    pub entries: Vec<BackupEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BackupEntry {
+    pub name: String,
+    pub file: String,
+    pub count: u64,
+    // The hex of SHA-256 of the file.
+    pub checksum: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RestoreMode {
+    // Only the data absent in the repository are restored, and the existing are kept as is.
+    Merge,
+    // The existing data (of the user if filtered) are deleted before restoring.
+    Replace,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RestoreReport {
+    pub name: String,
+    // The number of rows of the archive (of the user if filtered).
+    pub count: u64,
+    pub restored: u64,
+    // The rows skipped which already in the repository.
+    pub skipped: u64,
+    pub deleted: u64,
+}
+
+fn schema_version() -> i64 {
+    sqlite::MIGRATOR.iter().map(|m| m.version).max().unwrap_or_default()
+}
+
+fn is_owned<T: ModuleBean>(row: &T, owner_uid: Option<i64>) -> bool {
+    owner_uid.map_or(true, |uid| row.owner_uid() == Some(uid))
+}
+
+// Each of line is the JSON of the row with the 'del_flag' (which is skipped by serde).
+fn to_json_line<T: Serialize + ModuleBean>(row: &T) -> Result<Vec<u8>, Error> {
+    let mut value = serde_json::to_value(row)?;
+    if let Some(obj) = value.as_object_mut() {
+        obj.insert("del_flag".to_string(), row.base().del_flag.unwrap_or(0).into());
+    }
+    let mut line = serde_json::to_vec(&value)?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+fn from_json_line<T: DeserializeOwned + ModuleBean>(line: &str) -> Result<T, Error> {
+    let value: serde_json::Value = serde_json::from_str(line)?;
+    let del_flag = value.get("del_flag").and_then(|v| v.as_i64()).unwrap_or(0);
+    let mut row: T = serde_json::from_value(value)?;
+    if row.base().id.is_none() {
+        return Err(Error::msg("The id is required"));
+    }
+    row.base_mut().del_flag = Some(del_flag as i32);
+    Ok(row)
+}
+
+// The writes of the archive, which are sent to the blocking writer in order, see: write_archive()
+enum ArchiveWrite {
+    File(String),
+    Data(Vec<u8>),
+}
+
+// The sender of the writes of the archive, which fails if the writer is stopped by the error.
+struct ArchiveSender(mpsc::Sender<ArchiveWrite>);
+
+impl ArchiveSender {
+    async fn send(&self, write: ArchiveWrite) -> Result<(), Error> {
+        self.0.send(write).await.map_err(|_| Error::msg("The writer of the archive is stopped"))
+    }
+}
+
+// Write the archive of the received writes until all the senders are dropped, which is run in the
+// blocking thread since the writing of the zip is synchronous.
+fn write_archive<W: Write + Seek>(output: W, mut receiver: mpsc::Receiver<ArchiveWrite>) -> Result<W, Error> {
+    let mut writer = ZipWriter::new(output);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    while let Some(write) = receiver.blocking_recv() {
+        match write {
+            ArchiveWrite::File(name) => writer.start_file(name, options)?,
+            ArchiveWrite::Data(data) => writer.write_all(&data)?,
+        }
+    }
+    let mut output = writer.finish()?;
+    output.flush()?;
+    Ok(output)
+}
+
+// Write the rows (include the trashed) of the configured DbType to the archive in batches, so that the
+// rows are never all loaded, see: AsyncRepository::select_raw
+async fn backup_module<T>(
+    state: &AppState,
+    sender: &ArchiveSender,
+    name: &str,
+    container: &Mutex<RepositoryContainer<T>>,
+    owner_uid: Option<i64>,
+    mut visit: impl FnMut(&T) + Send
+) -> Result<BackupEntry, Error>
+    where T: 'static + Send + Sync + Serialize + ModuleBean
+{
+    let file = format!("{}.jsonl", name);
+    sender.send(ArchiveWrite::File(file.to_owned())).await?;
+    let mut hasher = Sha256::new();
+    let mut count = 0;
+    let repo = container.lock().await;
+    let repo = repo.get(&state.config);
+    let mut after_id = i64::MIN;
+    loop {
+        let rows = repo.select_raw(after_id, BATCH_SIZE).await?;
+        let more = rows.len() >= (BATCH_SIZE as usize);
+        if let Some(last) = rows.last() {
+            after_id = last.base().id.unwrap_or_default();
+        }
+        let mut data = Vec::new();
+        for row in rows.iter().filter(|row| is_owned(*row, owner_uid)) {
+            let line = to_json_line(row)?;
+            hasher.update(&line);
+            data.extend(line);
+            visit(row);
+            count += 1;
+        }
+        if !data.is_empty() {
+            sender.send(ArchiveWrite::Data(data)).await?;
+        }
+        if !more {
+            break;
+        }
+    }
+    Ok(BackupEntry { name: name.to_string(), file, count, checksum: hex::encode(hasher.finalize()) })
+}
+
+// Export all the modules data (include the trashed) and the blobs contents of the configured DbType
+// to the archive written to the output, which only of the user if the owner present. Returns the
+// manifest and the output when finished.
+// Notice: The archive is written by the blocking thread, so that the runtime is never stalled by the
+// writing while the repositories are locked.
+pub async fn backup<W>(state: &AppState, owner_uid: Option<i64>, output: W) -> Result<(BackupManifest, W), Error>
+    where W: Write + Seek + Send + 'static
+{
+    let (sender, receiver) = mpsc::channel(WRITE_BUFFER_SIZE);
+    let writing = tokio::task::spawn_blocking(move || write_archive(output, receiver));
+    let sender = ArchiveSender(sender);
+    let result = backup_modules(state, &sender, owner_uid).await;
+    drop(sender);
+    // The error of the writer is first, since the sending is only failed of the stopped writer.
+    let output = writing.await??;
+    Ok((result?, output))
+}
+
+async fn backup_modules(
+    state: &AppState,
+    sender: &ArchiveSender,
+    owner_uid: Option<i64>
+) -> Result<BackupManifest, Error> {
+    let s = sender;
+    let mut entries = vec![
+        backup_module(state, s, "users", &state.user_repo, owner_uid, |_| {}).await?,
+        backup_module(state, s, "folders", &state.folder_repo, owner_uid, |_| {}).await?,
+        backup_module(state, s, "documents", &state.document_repo, owner_uid, |_| {}).await?,
+        backup_module(state, s, "document_revisions", &state.document_revision_repo, owner_uid, |_| {}).await?,
+        backup_module(state, s, "document_updates", &state.document_update_repo, owner_uid, |_| {}).await?,
+        backup_module(state, s, "settings", &state.settings_repo, owner_uid, |_| {}).await?,
+        backup_module(state, s, "tags", &state.tag_repo, owner_uid, |_| {}).await?,
+        backup_module(state, s, "document_tags", &state.document_tag_repo, owner_uid, |_| {}).await?,
+        backup_module(state, s, "share_links", &state.share_link_repo, owner_uid, |_| {}).await?,
+        backup_module(state, s, "acls", &state.acl_repo, owner_uid, |_| {}).await?,
+        backup_module(state, s, "document_blobs", &state.document_blob_repo, owner_uid, |_| {}).await?
+    ];
+    // The same content of the blobs is stored once.
+    let mut hashes = BTreeSet::new();
+    let visit = |blob: &Blob| {
+        hashes.extend(blob.hash.to_owned());
+    };
+    entries.push(backup_module(state, s, "blobs", &state.blob_repo, owner_uid, visit).await?);
+
+    // The contents are copied by the chunks, rather than read all into memory.
+    for hash in hashes {
+        let Some((_, mut stream)) = state.blob_storage.get(&hash).await? else {
+            tracing::warn!("Skip backup the missing content of blob: {}", hash);
+            continue;
+        };
+        sender.send(ArchiveWrite::File(format!("{}{}", BLOBS_DIR, hash))).await?;
+        while let Some(chunk) = stream.next().await {
+            sender.send(ArchiveWrite::Data(chunk?.to_vec())).await?;
+        }
+    }
+
+    let manifest = BackupManifest {
+        format_version: BACKUP_FORMAT_VERSION,
+        schema_version: schema_version(),
+        db_type: state.config.db.db_type,
+        create_time: Utc::now().timestamp_millis(),
+        owner_uid,
+        entries,
+    };
+    sender.send(ArchiveWrite::File(MANIFEST_FILE.to_string())).await?;
+    sender.send(ArchiveWrite::Data(serde_json::to_vec_pretty(&manifest)?)).await?;
+    Ok(manifest)
+}
+
+// The directory of the files extracted from the archive, which is removed when dropped.
+struct ExtractDir(PathBuf);
+
+impl ExtractDir {
+    fn create() -> Result<Self, Error> {
+        let dir = std::env::temp_dir().join(format!("mywebnote-restore-{}", uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self(dir))
+    }
+}
+
+impl Drop for ExtractDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.0) {
+            tracing::warn!("Failed to remove the extracted directory: {}. reason: {}", self.0.display(), e);
+        }
+    }
+}
+
+// The extracted files of the archive, which the modules data are keyed by the names.
+struct ExtractedFiles {
+    modules: BTreeMap<String, PathBuf>,
+    blobs: Vec<PathBuf>,
+}
+
+fn open_file<'a, R: Read + Seek>(reader: &'a mut ZipArchive<R>, name: &str) -> Result<ZipFile<'a>, Error> {
+    reader
+        .by_name(name)
+        .map_err(|e| Error::msg(format!("The file '{}' of the archive is unreadable. reason: {}", name, e)))
+}
+
+// Copy the file of the archive to the path by the chunks, returns the checksum of the content.
+fn extract_file<R: Read + Seek>(reader: &mut ZipArchive<R>, name: &str, path: &Path) -> Result<String, Error> {
+    let mut file = open_file(reader, name)?;
+    let mut output = BufWriter::new(File::create(path)?);
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        output.write_all(&buf[..n])?;
+    }
+    output.flush()?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+// Extract all the files of the archive to the directory, which are all verified before restoring.
+// Notice: The files are named by the indexes rather than the names of the archive, which are untrusted.
+fn extract<R: Read + Seek>(archive: R, dir: &Path) -> Result<ExtractedFiles, Error> {
+    let mut reader = ZipArchive::new(archive)?;
+    let manifest: BackupManifest = serde_json::from_reader(open_file(&mut reader, MANIFEST_FILE)?)?;
+    if manifest.format_version != BACKUP_FORMAT_VERSION {
+        return Err(Error::msg(format!("Unsupported the archive format version: {}", manifest.format_version)));
+    }
+    if manifest.schema_version > schema_version() {
+        return Err(
+            Error::msg(
+                format!(
+                    "The archive schema version {} is newer than the current {}, please upgrade first",
+                    manifest.schema_version,
+                    schema_version()
+                )
+            )
+        );
+    }
+
+    let mut modules = BTreeMap::new();
+    for (i, entry) in manifest.entries.iter().enumerate() {
+        let path = dir.join(format!("module-{}.jsonl", i));
+        if extract_file(&mut reader, &entry.file, &path)? != entry.checksum {
+            return Err(Error::msg(format!("The checksum of the archive file '{}' mismatched", entry.file)));
+        }
+        modules.insert(entry.name.to_owned(), path);
+    }
+    let names: Vec<String> = reader
+        .file_names()
+        .filter(|name| name.starts_with(BLOBS_DIR))
+        .map(|name| name.to_string())
+        .collect();
+    let mut blobs = Vec::with_capacity(names.len());
+    for (i, name) in names.iter().enumerate() {
+        let path = dir.join(format!("blob-{}", i));
+        if extract_file(&mut reader, name, &path)? != name[BLOBS_DIR.len()..] {
+            return Err(Error::msg(format!("The checksum of the archive file '{}' mismatched", name)));
+        }
+        blobs.push(path);
+    }
+    Ok(ExtractedFiles { modules, blobs })
+}
+
+// The reader of the rows of the extracted file, which is line by line rather than read all into memory.
+struct RowReader {
+    name: String,
+    lines: Lines<tokio::io::BufReader<tokio::fs::File>>,
+    number: usize,
+}
+
+impl RowReader {
+    async fn open(name: &str, path: &Path) -> Result<Self, Error> {
+        let lines = tokio::io::BufReader::new(tokio::fs::File::open(path).await?).lines();
+        Ok(Self { name: name.to_string(), lines, number: 0 })
+    }
+
+    async fn next<T: DeserializeOwned + ModuleBean>(&mut self) -> Result<Option<T>, Error> {
+        while let Some(line) = self.lines.next_line().await? {
+            self.number += 1;
+            if line.is_empty() {
+                continue;
+            }
+            let row = from_json_line(&line).map_err(|e| {
+                Error::msg(format!("The line {} of the archive {} is invalid. reason: {}", self.number, self.name, e))
+            })?;
+            return Ok(Some(row));
+        }
+        Ok(None)
+    }
+}
+
+// Check all the rows of the module are readable, so that nothing is deleted for the invalid archive.
+async fn check_module<T>(name: &str, path: Option<&PathBuf>) -> Result<(), Error>
+    where T: DeserializeOwned + ModuleBean
+{
+    if let Some(path) = path {
+        let mut reader = RowReader::open(name, path).await?;
+        while reader.next::<T>().await?.is_some() {}
+    }
+    Ok(())
+}
+
+async fn restore_module<T>(
+    state: &AppState,
+    name: &str,
+    container: &Mutex<RepositoryContainer<T>>,
+    path: Option<&PathBuf>,
+    mode: RestoreMode,
+    owner_uid: Option<i64>
+) -> Result<RestoreReport, Error>
+    where T: 'static + Send + Sync + DeserializeOwned + ModuleBean
+{
+    let mut report = RestoreReport { name: name.to_string(), ..Default::default() };
+    let Some(path) = path else {
+        return Ok(report);
+    };
+
+    let repo = container.lock().await;
+    let repo = repo.get(&state.config);
+    // The ids of the all existing rows, which the rows of the archive with the same ids are skipped.
+    let mut existing = HashSet::new();
+    let mut after_id = i64::MIN;
+    loop {
+        let rows = repo.select_raw(after_id, BATCH_SIZE).await?;
+        let more = rows.len() >= (BATCH_SIZE as usize);
+        for row in rows {
+            let id = row.base().id.unwrap_or_default();
+            after_id = id;
+            if mode == RestoreMode::Replace && is_owned(&row, owner_uid) {
+                report.deleted += repo.delete_raw(id).await?;
+            } else {
+                existing.insert(id);
+            }
+        }
+        if !more {
+            break;
+        }
+    }
+
+    let mut reader = RowReader::open(name, path).await?;
+    while let Some(row) = reader.next::<T>().await? {
+        if !is_owned(&row, owner_uid) {
+            continue;
+        }
+        report.count += 1;
+        let id = row.base().id.unwrap_or_default();
+        if existing.contains(&id) {
+            report.skipped += 1;
+            continue;
+        }
+        repo
+            .insert_raw(row).await
+            .map_err(|e| Error::msg(format!("Failed to restore the {} of id: {}. reason: {}", name, id, e)))?;
+        report.restored += 1;
+    }
+    Ok(report)
+}
+
+// Import the modules data and the blobs contents of the archive to the configured DbType, which only of
+// the user if the owner present. The files of the archive are all extracted and verified, and the rows
+// are all checked before restoring, so that the existing of the replace are never deleted for the
+// invalid archive. The deletes and inserts of all the modules are in one transaction, so that nothing
+// is changed if any row failed, such as the violation of the unique constraints.
+pub async fn restore<R>(
+    state: &AppState,
+    archive: R,
+    mode: RestoreMode,
+    owner_uid: Option<i64>
+) -> Result<Vec<RestoreReport>, Error>
+    where R: Read + Seek + Send + 'static
+{
+    let dir = ExtractDir::create()?;
+    let path = dir.0.clone();
+    let files = tokio::task::spawn_blocking(move || extract(archive, &path)).await??;
+    let m = |name: &str| files.modules.get(name);
+
+    check_module::<User>("users", m("users")).await?;
+    check_module::<Folder>("folders", m("folders")).await?;
+    check_module::<Document>("documents", m("documents")).await?;
+    check_module::<DocumentRevision>("document_revisions", m("document_revisions")).await?;
+    check_module::<DocumentUpdate>("document_updates", m("document_updates")).await?;
+    check_module::<Settings>("settings", m("settings")).await?;
+    check_module::<Tag>("tags", m("tags")).await?;
+    check_module::<DocumentTag>("document_tags", m("document_tags")).await?;
+    check_module::<ShareLink>("share_links", m("share_links")).await?;
+    check_module::<Acl>("acls", m("acls")).await?;
+    check_module::<DocumentBlob>("document_blobs", m("document_blobs")).await?;
+    check_module::<Blob>("blobs", m("blobs")).await?;
+
+    let tx = {
+        let repo = state.user_repo.lock().await;
+        repo.get(&state.config).begin_raw().await?
+    };
+    let reports = tx.scope(async {
+        Ok(vec![
+            restore_module(state, "users", &state.user_repo, m("users"), mode, owner_uid).await?,
+            restore_module(state, "folders", &state.folder_repo, m("folders"), mode, owner_uid).await?,
+            restore_module(state, "documents", &state.document_repo, m("documents"), mode, owner_uid).await?,
+            restore_module(
+                state,
+                "document_revisions",
+                &state.document_revision_repo,
+                m("document_revisions"),
+                mode,
+                owner_uid
+            ).await?,
+            restore_module(
+                state,
+                "document_updates",
+                &state.document_update_repo,
+                m("document_updates"),
+                mode,
+                owner_uid
+            ).await?,
+            restore_module(state, "settings", &state.settings_repo, m("settings"), mode, owner_uid).await?,
+            restore_module(state, "tags", &state.tag_repo, m("tags"), mode, owner_uid).await?,
+            restore_module(
+                state,
+                "document_tags",
+                &state.document_tag_repo,
+                m("document_tags"),
+                mode,
+                owner_uid
+            ).await?,
+            restore_module(state, "share_links", &state.share_link_repo, m("share_links"), mode, owner_uid).await?,
+            restore_module(state, "acls", &state.acl_repo, m("acls"), mode, owner_uid).await?,
+            restore_module(
+                state,
+                "document_blobs",
+                &state.document_blob_repo,
+                m("document_blobs"),
+                mode,
+                owner_uid
+            ).await?,
+            restore_module(state, "blobs", &state.blob_repo, m("blobs"), mode, owner_uid).await?
+        ])
+    }).await?;
+
+    // The contents are addressed by the hash, so that the existing are stored once.
+    for path in &files.blobs {
+        let file = tokio::fs::File::open(path).await?;
+        state.blob_storage.put(Box::pin(ReaderStream::new(file)), u64::MAX).await?;
+    }
+    Ok(reports)
+}
+
+// Start the background task of periodically writing the archives to the local directory if enabled.
+pub fn start_backup_task(state: AppState) {
+    let config = &state.config.webnote.backup;
+    if !config.enabled {
+        return;
+    }
+    let interval = Duration::from_secs(config.interval.max(1));
+    tokio::spawn(async move {
+        // Notice: The first backup is after the interval, rather than every starting.
+        let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+        loop {
+            ticker.tick().await;
+            match backup_to_dir(&state).await {
+                Ok(path) => tracing::info!("Backup to the archive: {}", path.display()),
+                Err(e) => tracing::warn!("Failed to backup. reason: {}", e),
+            }
+        }
+    });
+}
+
+// Write the archive of the all users to the backup directory, and then delete the older archives
+// which exceeds the number to keep.
+pub async fn backup_to_dir(state: &AppState) -> Result<PathBuf, Error> {
+    let config = &state.config.webnote.backup;
+    tokio::fs::create_dir_all(&config.dir).await?;
+
+    // Write to the temporary file first, so that the partial archive is never rotated.
+    let name = format!("{}{}{}", BACKUP_FILE_PREFIX, Utc::now().format("%Y%m%d%H%M%S%3f"), BACKUP_FILE_SUFFIX);
+    let path = Path::new(&config.dir).join(name);
+    let temp = path.with_extension("tmp");
+    if let Err(e) = backup(state, None, BufWriter::new(File::create(&temp)?)).await {
+        let _ = tokio::fs::remove_file(&temp).await;
+        return Err(e);
+    }
+    tokio::fs::rename(&temp, &path).await?;
+
+    let mut archives = Vec::new();
+    let mut entries = tokio::fs::read_dir(&config.dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with(BACKUP_FILE_PREFIX) && name.ends_with(BACKUP_FILE_SUFFIX) {
+            archives.push(name);
+        }
+    }
+    // The names are in order of the time.
+    archives.sort();
+    let expired = archives.len().saturating_sub(config.keep.max(1) as usize);
+    for name in &archives[..expired] {
+        tokio::fs::remove_file(Path::new(&config.dir).join(name)).await?;
+    }
+    Ok(path)
+}
@@ -0,0 +1,282 @@
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use anyhow::Error;
+use axum::async_trait;
+use chrono::Utc;
+use futures::StreamExt;
+use crate::context::state::AppState;
+use crate::errors::BizError;
+use crate::store::{ self, blobs::{ BlobStream, is_valid_hash } };
+use crate::types::blob::{ Blob, DocumentBlob, QueryBlobRequest };
+use crate::types::{ PageRequest, PageResponse };
+use crate::utils::auths::{ AuthUserClaims, SecurityContext };
+
+// The path of the blob download, which the blobs are referenced by the contents with.
+pub(crate) const BLOB_DOWNLOAD_PATH: &str = "/modules/blob/download?hash=";
+
+#[async_trait]
+pub trait IBlobHandler: Send {
+    // Store the uploaded content, the same content is stored once.
+    async fn upload(&self, mime_type: String, stream: BlobStream) -> Result<Blob, Error>;
+
+    // Open the content of the blob which uploaded by the current user.
+    async fn download(&self, hash: &str) -> Result<(Blob, BlobStream), Error>;
+
+    // The blobs referenced by the document.
+    async fn find(
+        &self,
+        param: QueryBlobRequest,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<Blob>), Error>;
+
+    // Add the references of the document to the owned blobs whose hash present in the content, and the
+    // blobs of the uploaders (such as the editors of the shared document) are owned by the owner as well.
+    // The references are kept as long as the revisions of the content, see: prune_references
+    async fn sync_references(&self, document_id: i64, content: &str, uploaders: &[i64]) -> Result<(), Error>;
+
+    // Remove the references of the document which present in none of the contents, i.e. the retained
+    // revisions, so that the blobs of the purged revisions are collectable.
+    async fn prune_references(&self, document_id: i64, contents: &[&str]) -> Result<u64, Error>;
+
+    async fn delete_references(&self, document_id: i64) -> Result<u64, Error>;
+}
+
+pub struct BlobHandler<'a> {
+    state: &'a AppState,
+}
+
+impl<'a> BlobHandler<'a> {
+    pub fn new(state: &'a AppState) -> Self {
+        Self { state }
+    }
+
+    async fn get_owned(&self, hash: &str) -> Result<Option<Blob>, Error> {
+        let repo = self.state.blob_repo.lock().await;
+        let param = Blob::with_hash(None, Some(hash.to_string()));
+        let page = PageRequest::new(1, 1);
+        Ok(repo.get(&self.state.config).select(param, page).await?.1.pop())
+    }
+
+    // Own the blob uploaded by any of the uploaders, the content addressed storage is shared, so only the
+    // metadata is copied.
+    async fn adopt(&self, hash: &str, uploaders: &[i64]) -> Result<bool, Error> {
+        for uploader in uploaders {
+            let uploaded = {
+                let repo = self.state.blob_repo.lock().await;
+                let param = Blob::with_hash(Some(*uploader), Some(hash.to_string()));
+                repo.get(&self.state.config).select_unscoped(param, PageRequest::new(1, 1)).await?.1.pop()
+            };
+            if let Some(uploaded) = uploaded {
+                let mime_type = uploaded.mime_type.unwrap_or_default();
+                let blob = Blob::new(hash.to_string(), mime_type, uploaded.size.unwrap_or(0));
+                let repo = self.state.blob_repo.lock().await;
+                repo.get(&self.state.config).insert(blob).await?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    async fn list_references(&self, document_id: i64) -> Result<Vec<DocumentBlob>, Error> {
+        let param = DocumentBlob::with(None, Some(document_id), None);
+        store::select_all(&self.state.document_blob_repo, &self.state.config, param, false).await
+    }
+
+    // Read the whole content of the blob which uploaded by the current user.
+    pub(crate) async fn read_all(&self, hash: &str) -> Result<(Blob, Vec<u8>), Error> {
+        let (blob, mut stream) = self.download(hash).await?;
+        let mut data = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            data.extend_from_slice(&chunk?);
+        }
+        Ok((blob, data))
+    }
+
+    // The download URL of the blob, which is prefixed with the context path.
+    pub(crate) fn download_url(&self, hash: &str) -> String {
+        let context_path = self.state.config.server.context_path.as_deref().unwrap_or("");
+        format!("{}{}{}", context_path, BLOB_DOWNLOAD_PATH, hash)
+    }
+}
+
+// The hash of the blob reference, which is the hash itself or the download URL,
+// i.e: '/modules/blob/download?hash=<hash>'
+pub(crate) fn to_blob_hash(reference: &str) -> Option<String> {
+    if is_valid_hash(reference) {
+        return Some(reference.to_string());
+    }
+    if reference.starts_with("data:") {
+        return None;
+    }
+    let (_, query) = reference.split_once('?')?;
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("hash="))
+        .filter(|hash| is_valid_hash(hash))
+        .map(|hash| hash.to_string())
+}
+
+// The hashes of the blobs in the content, such as the image url '/modules/blob/download?hash={hash}'
+// or the file id of the board elements.
+fn to_hashes(content: &str) -> BTreeSet<String> {
+    content
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|token| is_valid_hash(token))
+        .map(|token| token.to_string())
+        .collect()
+}
+
+#[async_trait]
+impl<'a> IBlobHandler for BlobHandler<'a> {
+    async fn upload(&self, mime_type: String, stream: BlobStream) -> Result<Blob, Error> {
+        let max_size = self.state.config.webnote.blob.max_size;
+        let object = self.state.blob_storage.put(stream, max_size).await?;
+        if let Some(blob) = self.get_owned(&object.hash).await? {
+            return Ok(blob);
+        }
+        let blob = Blob::new(object.hash, mime_type, object.size as i64);
+        let repo = self.state.blob_repo.lock().await;
+        repo.get(&self.state.config).insert(blob.clone()).await?;
+        Ok(blob)
+    }
+
+    async fn download(&self, hash: &str) -> Result<(Blob, BlobStream), Error> {
+        if !is_valid_hash(hash) {
+            return Err(BizError::BadRequest(format!("Invalid blob hash: {}", hash)).into());
+        }
+        let blob = self.get_owned(hash).await?.ok_or_else(|| BizError::NotFound("blob".to_string()))?;
+        let (_, stream) = self.state.blob_storage
+            .get(hash).await?
+            .ok_or_else(|| BizError::NotFound("blob".to_string()))?;
+        Ok((blob, stream))
+    }
+
+    async fn find(
+        &self,
+        param: QueryBlobRequest,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<Blob>), Error> {
+        let (page, references) = {
+            let repo = self.state.document_blob_repo.lock().await;
+            let param = DocumentBlob::with(None, Some(param.document_id), None);
+            repo.get(&self.state.config).select(param, page).await?
+        };
+        let mut blobs = Vec::with_capacity(references.len());
+        for reference in references {
+            if let Some(blob) = self.get_owned(&reference.hash.unwrap_or_default()).await? {
+                blobs.push(blob);
+            }
+        }
+        Ok((page, blobs))
+    }
+
+    async fn sync_references(&self, document_id: i64, content: &str, uploaders: &[i64]) -> Result<(), Error> {
+        let existing: BTreeSet<String> = self
+            .list_references(document_id).await?
+            .into_iter()
+            .filter_map(|reference| reference.hash)
+            .collect();
+        // Only the blobs uploaded by the owner (or the uploaders) could be referenced.
+        let mut owned = BTreeSet::new();
+        for hash in to_hashes(content).difference(&existing) {
+            if self.get_owned(hash).await?.is_some() || self.adopt(hash, uploaders).await? {
+                owned.insert(hash.to_owned());
+            }
+        }
+
+        let repo = self.state.document_blob_repo.lock().await;
+        for hash in owned {
+            repo.get(&self.state.config).insert(DocumentBlob::new(document_id, hash)).await?;
+        }
+        Ok(())
+    }
+
+    async fn prune_references(&self, document_id: i64, contents: &[&str]) -> Result<u64, Error> {
+        let retained: BTreeSet<String> = contents
+            .iter()
+            .flat_map(|content| to_hashes(content))
+            .collect();
+        let references = self.list_references(document_id).await?;
+        let repo = self.state.document_blob_repo.lock().await;
+        let mut pruned = 0;
+        let mut existing = BTreeSet::new();
+        for reference in references {
+            let hash = reference.hash.unwrap_or_default();
+            // The duplicated references are pruned as well.
+            if retained.contains(&hash) && existing.insert(hash) {
+                continue;
+            }
+            pruned += repo.get(&self.state.config).delete_by_id(reference.base.id.unwrap_or_default()).await?;
+        }
+        Ok(pruned)
+    }
+
+    async fn delete_references(&self, document_id: i64) -> Result<u64, Error> {
+        let references = self.list_references(document_id).await?;
+        let repo = self.state.document_blob_repo.lock().await;
+        let mut deleted = 0;
+        for reference in references {
+            let id = reference.base.id.unwrap_or_default();
+            deleted += repo.get(&self.state.config).delete_by_id(id).await?;
+        }
+        Ok(deleted)
+    }
+}
+
+// Start the background task of periodically collecting the unreferenced blobs.
+pub fn start_gc_task(state: AppState) {
+    let interval = state.config.webnote.blob.gc_interval.max(1);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval));
+        loop {
+            ticker.tick().await;
+            match gc_unreferenced(&state).await {
+                Ok(collected) => tracing::info!("Collected the unreferenced blobs count: {}", collected),
+                Err(e) => tracing::warn!("Failed to collect the unreferenced blobs. reason: {}", e),
+            }
+        }
+    });
+}
+
+// Delete the blobs of the owners which not referenced by any documents of the owner, and then
+// delete the stored contents which not owned by anyone, both of them only after the grace.
+pub async fn gc_unreferenced(state: &AppState) -> Result<u64, Error> {
+    let config = &state.config;
+    let expired_before = Utc::now().timestamp_millis() - (config.webnote.blob.gc_grace as i64) * 1000;
+    let page = || PageRequest::new(1, 1);
+
+    let blobs = store::select_all_unscoped(&state.blob_repo, config, Blob::with_hash(None, None)).await?;
+    for blob in blobs {
+        if blob.base.create_time.unwrap_or_default() >= expired_before {
+            continue;
+        }
+        let param = DocumentBlob::with(blob.owner_uid, None, blob.hash.to_owned());
+        let referenced = {
+            let repo = state.document_blob_repo.lock().await;
+            !repo.get(config).select_unscoped(param, page()).await?.1.is_empty()
+        };
+        if !referenced {
+            let claims = AuthUserClaims::system(blob.owner_uid.unwrap_or_default());
+            let repo = state.blob_repo.lock().await;
+            let id = blob.base.id.unwrap_or_default();
+            SecurityContext::scope(Some(claims), repo.get(config).delete_by_id(id)).await?;
+        }
+    }
+
+    let mut collected = 0;
+    for object in state.blob_storage.list().await? {
+        if object.modified >= expired_before {
+            continue;
+        }
+        let param = Blob::with_hash(None, Some(object.hash.to_owned()));
+        let owned = {
+            let repo = state.blob_repo.lock().await;
+            !repo.get(config).select_unscoped(param, page()).await?.1.is_empty()
+        };
+        if !owned && state.blob_storage.delete(&object.hash).await? {
+            collected += 1;
+        }
+    }
+    Ok(collected)
+}
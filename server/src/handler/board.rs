@@ -0,0 +1,238 @@
+use std::io::{ self, Cursor, Read };
+
+use anyhow::Error;
+use axum::{ async_trait, body::Bytes };
+use serde_json::Value;
+use zip::ZipArchive;
+
+use crate::context::state::AppState;
+use crate::errors::BizError;
+use crate::types::board::{
+    BoardFormat,
+    BulkImportBoardRequest,
+    BulkImportBoardResponse,
+    FailedBoard,
+    ImportBoardRequest,
+    ImportedBoard,
+};
+use crate::types::document::{ DocumentType, SaveDocumentRequest };
+use crate::utils::boards;
+use super::blob::{ to_blob_hash, BlobHandler, IBlobHandler };
+use super::document::{ DocumentHandler, IDocumentHandler };
+use super::folder::FolderHandler;
+
+const DEFAULT_BOARD_NAME: &str = "Untitled";
+const MAX_BOARD_NAME_LEN: usize = 64;
+
+#[async_trait]
+pub trait IBoardHandler: Send {
+    // Import the excalidraw or tldraw file as the new board document, returns the id and the version.
+    async fn import(&self, param: ImportBoardRequest, content: Vec<u8>) -> Result<(i64, i64), Error>;
+
+    // Import all the '.excalidraw' and '.tldr' files of the zip to the folder, the name of each board
+    // is the file name.
+    async fn import_bulk(
+        &self,
+        param: BulkImportBoardRequest,
+        archive: Vec<u8>
+    ) -> Result<BulkImportBoardResponse, Error>;
+
+    // Export the board document as the '.excalidraw' file with the embedded files, returns the name
+    // and the content.
+    async fn export(&self, id: i64) -> Result<(String, String), Error>;
+}
+
+pub struct BoardHandler<'a> {
+    state: &'a AppState,
+}
+
+impl<'a> BoardHandler<'a> {
+    pub fn new(state: &'a AppState) -> Self {
+        Self { state }
+    }
+
+    async fn check_folder(&self, folder_key: &Option<String>) -> Result<(), Error> {
+        if let Some(folder_key) = folder_key {
+            if FolderHandler::new(self.state).get_by_key(folder_key).await?.is_none() {
+                return Err(BizError::NotFound("folder".to_string()).into());
+            }
+        }
+        Ok(())
+    }
+
+    async fn import_content(
+        &self,
+        name: String,
+        folder_key: Option<String>,
+        format: Option<BoardFormat>,
+        content: &[u8]
+    ) -> Result<(i64, i64), Error> {
+        let mut scene = boards::to_scene(content, format)?;
+        self.store_files(&mut scene).await?;
+        let param = SaveDocumentRequest {
+            id: None,
+            key: Some(uuid::Uuid::new_v4().to_string()),
+            name: Some(name),
+            folder_key,
+            doc_type: Some(DocumentType::Board),
+            content: Some(scene.to_string()),
+            version: None,
+        };
+        DocumentHandler::new(self.state).save(param).await
+    }
+
+    // Store the embedded files (the data URLs) as the blobs, which are replaced with the download URLs,
+    // so that the board content is kept small.
+    async fn store_files(&self, scene: &mut Value) -> Result<(), Error> {
+        let Some(files) = scene.get_mut("files").and_then(Value::as_object_mut) else {
+            return Ok(());
+        };
+        for file in files.values_mut() {
+            let data_url = file.get("dataURL").and_then(Value::as_str).unwrap_or_default();
+            if !data_url.starts_with("data:") {
+                continue;
+            }
+            let (mime_type, data) = boards
+                ::decode_data_url(data_url)
+                .ok_or_else(|| BizError::BadRequest("The embedded file must be the base64 data URL".to_string()))?;
+            let stream = futures::stream::once(async move { Ok::<_, io::Error>(Bytes::from(data)) });
+            let handler = BlobHandler::new(self.state);
+            let blob = handler.upload(mime_type, Box::pin(stream)).await?;
+            file["dataURL"] = Value::String(handler.download_url(&blob.hash.unwrap_or_default()));
+        }
+        Ok(())
+    }
+
+    // Embed the files of the blobs as the data URLs, and the file is kept as it is if the blob is absent.
+    async fn embed_files(&self, scene: &mut Value) -> Result<(), Error> {
+        let Some(files) = scene.get_mut("files").and_then(Value::as_object_mut) else {
+            return Ok(());
+        };
+        for file in files.values_mut() {
+            let Some(hash) = file.get("dataURL").and_then(Value::as_str).and_then(to_blob_hash) else {
+                continue;
+            };
+            let (blob, data) = match BlobHandler::new(self.state).read_all(&hash).await {
+                Ok(read) => read,
+                Err(e) => {
+                    tracing::warn!("Skip embed the file of the blob: {}. reason: {}", hash, e);
+                    continue;
+                }
+            };
+            let mime_type = blob.mime_type.unwrap_or_else(|| file["mimeType"].as_str().unwrap_or_default().to_string());
+            file["dataURL"] = Value::String(boards::encode_data_url(&mime_type, &data));
+        }
+        Ok(())
+    }
+}
+
+// The board file of the zip, as the path, the format and the read content.
+type BoardEntry = (String, Option<BoardFormat>, Result<Vec<u8>, Error>);
+
+// The board file of the zip, none if not a board file.
+fn read_entry(
+    reader: &mut ZipArchive<Cursor<Vec<u8>>>,
+    index: usize,
+    max_size: u64
+) -> Result<Option<BoardEntry>, Error> {
+    let mut file = reader.by_index(index)?;
+    let path = file.name().to_string();
+    let format = BoardFormat::from_file_name(&path);
+    if file.is_dir() || format.is_none() || path.starts_with("__MACOSX/") {
+        return Ok(None);
+    }
+    Ok(Some((path, format, read_limited(&mut file, max_size))))
+}
+
+// Read the file of the zip up to the max uncompressed bytes, the declared size isn't trusted since it
+// could be forged.
+fn read_limited(file: &mut zip::read::ZipFile, max_size: u64) -> Result<Vec<u8>, Error> {
+    let too_large = || BizError::PayloadTooLarge(format!("The file must be at most {} bytes", max_size));
+    if file.size() > max_size {
+        return Err(too_large().into());
+    }
+    let mut content = Vec::new();
+    file.take(max_size + 1).read_to_end(&mut content)?;
+    if content.len() as u64 > max_size {
+        return Err(too_large().into());
+    }
+    Ok(content)
+}
+
+// The name of the board is the file name without the extension.
+fn to_board_name(path: &str) -> String {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    let name = file_name.rsplit_once('.').map(|(name, _)| name).unwrap_or(file_name).trim();
+    let name: String = name.chars().take(MAX_BOARD_NAME_LEN).collect();
+    if name.is_empty() { DEFAULT_BOARD_NAME.to_string() } else { name }
+}
+
+#[async_trait]
+impl<'a> IBoardHandler for BoardHandler<'a> {
+    async fn import(&self, param: ImportBoardRequest, content: Vec<u8>) -> Result<(i64, i64), Error> {
+        self.check_folder(&param.folder_key).await?;
+        let name = param.name.unwrap_or(DEFAULT_BOARD_NAME.to_string());
+        self.import_content(name, param.folder_key, param.format, &content).await
+    }
+
+    async fn import_bulk(
+        &self,
+        param: BulkImportBoardRequest,
+        archive: Vec<u8>
+    ) -> Result<BulkImportBoardResponse, Error> {
+        self.check_folder(&param.folder_key).await?;
+        let reader = tokio::task::spawn_blocking(move || ZipArchive::new(Cursor::new(archive))).await?;
+        let mut reader = reader.map_err(|e|
+            BizError::BadRequest(format!("The bulk import file must be a zip. reason: {}", e))
+        )?;
+
+        let limits = &self.state.config.webnote.board;
+        if reader.len() > limits.import_max_files {
+            let reason = format!("The zip must be at most {} entries", limits.import_max_files);
+            return Err(BizError::PayloadTooLarge(reason).into());
+        }
+
+        // Each file is imported before the next is inflated, so that only one is in the memory, and the zip
+        // reader is moved into the blocking inflating, since the file of it isn't Send across the awaits.
+        let mut response = BulkImportBoardResponse::default();
+        let max_file_size = limits.import_max_file_size;
+        for i in 0..reader.len() {
+            let (returned, entry) = tokio::task::spawn_blocking(move || {
+                let entry = read_entry(&mut reader, i, max_file_size);
+                (reader, entry)
+            }).await?;
+            reader = returned;
+            let Some((path, format, read)) = entry? else {
+                continue;
+            };
+            let result = match read {
+                Ok(content) => {
+                    let name = to_board_name(&path);
+                    self.import_content(name, param.folder_key.to_owned(), format, &content).await
+                }
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok((id, _)) => response.imported.push(ImportedBoard { file: path, id }),
+                Err(e) => response.failed.push(FailedBoard { file: path, reason: e.to_string() }),
+            }
+        }
+        Ok(response)
+    }
+
+    async fn export(&self, id: i64) -> Result<(String, String), Error> {
+        let document = {
+            let repo = self.state.document_repo.lock().await;
+            repo.get(&self.state.config).select_by_id(id).await?
+        };
+        if document.doc_type != Some(DocumentType::Board) {
+            return Err(BizError::BadRequest("The document is not a board".to_string()).into());
+        }
+        let content = document.content.filter(|c| !c.is_empty()).unwrap_or("{}".to_string());
+        let mut scene = boards::to_scene(content.as_bytes(), Some(BoardFormat::Excalidraw))?;
+        self.embed_files(&mut scene).await?;
+        let name = document.name.filter(|n| !n.is_empty()).unwrap_or(DEFAULT_BOARD_NAME.to_string());
+        Ok((name, serde_json::to_string_pretty(&scene)?))
+    }
+}
+
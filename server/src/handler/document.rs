@@ -2,14 +2,36 @@ use std::sync::Arc;
 
 use anyhow::{ Error, Ok };
 use axum::async_trait;
+use chrono::Utc;
+use similar::TextDiff;
 use crate::context::state::AppState;
+use crate::errors::BizError;
+use crate::store::{ self, search };
+use crate::types::{ BaseBean, SearchHit };
+use crate::types::acl::{ AclResource, AclRole };
 use crate::types::document::{
     DeleteDocumentRequest,
+    MoveDocumentRequest,
+    PurgeDocumentRequest,
     QueryDocumentRequest,
+    RestoreDocumentRequest,
     SaveDocumentRequest,
+    SearchDocumentRequest,
     Document,
 };
+use crate::types::document_revision::{ DocumentRevision, QueryDocumentRevisionRequest };
+use crate::types::event::{ ChangeAction, ChangeEvent, ChangeResource };
 use crate::types::{ PageRequest, PageResponse };
+use crate::utils::auths::SecurityContext;
+use crate::utils::types::GenericValue;
+use super::acl::AclHandler;
+use super::blob::{ BlobHandler, IBlobHandler };
+use super::event::EventHandler;
+use super::folder::FolderHandler;
+use super::share::ShareHandler;
+use super::sync::SyncHandler;
+use super::tag::TagHandler;
+use super::thumbnail::ThumbnailHandler;
 
 #[async_trait]
 pub trait IDocumentHandler: Send {
@@ -21,9 +43,48 @@ pub trait IDocumentHandler: Send {
         page: PageRequest
     ) -> Result<(PageResponse, Vec<Document>), Error>;
 
-    async fn save(&self, param: SaveDocumentRequest) -> Result<i64, Error>;
+    // The full text search of the names and contents, ordered by the relevance.
+    async fn search(
+        &self,
+        param: SearchDocumentRequest,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<SearchHit<Document>>), Error>;
+
+    // Returns the id and the current version of the saved document, the update must match the
+    // version of the optimistic lock, otherwise it's rejected as conflict with the current version.
+    async fn save(&self, param: SaveDocumentRequest) -> Result<(i64, i64), Error>;
+
+    // Move the documents to the folder (or the root), returns the count of the moved.
+    async fn move_to_folder(&self, param: MoveDocumentRequest) -> Result<u64, Error>;
 
+    // Move the document to the trash.
     async fn delete(&self, param: DeleteDocumentRequest) -> Result<u64, Error>;
+
+    async fn find_trash(
+        &self,
+        param: QueryDocumentRequest,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<Document>), Error>;
+
+    // Restore the document from the trash, as well as the trashed folder ancestry of it.
+    async fn restore(&self, param: RestoreDocumentRequest) -> Result<u64, Error>;
+
+    // Permanently delete the trashed document and the revisions and blob references of it.
+    async fn purge(&self, param: PurgeDocumentRequest) -> Result<u64, Error>;
+
+    async fn find_revisions(
+        &self,
+        param: QueryDocumentRevisionRequest,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<DocumentRevision>), Error>;
+
+    async fn get_revision(&self, id: i64) -> Result<DocumentRevision, Error>;
+
+    // Diff the revision to another revision or the current document content.
+    async fn diff_revision(&self, from_id: i64, to_id: Option<i64>) -> Result<String, Error>;
+
+    // Restore the revision as the new head, which is also appended as a new revision.
+    async fn restore_revision(&self, id: i64) -> Result<i64, Error>;
 }
 
 pub struct DocumentHandler<'a> {
@@ -34,6 +95,126 @@ impl<'a> DocumentHandler<'a> {
     pub fn new(state: &'a AppState) -> Self {
         Self { state }
     }
+
+    async fn append_revision(&self, document_id: i64, content: Option<String>) -> Result<i64, Error> {
+        let revision = DocumentRevision::new(document_id, content);
+        let revision_id = {
+            let repo = self.state.document_revision_repo.lock().await;
+            repo.get(&self.state.config).insert(revision).await?
+        };
+
+        // Purge the revisions which out of the retention, the latest one is always retained.
+        let retention = &self.state.config.webnote.revision;
+        if retention.keep_last.is_none() && retention.keep_days.is_none() {
+            return Ok(revision_id);
+        }
+        let expired_before = retention.keep_days.map(
+            |days| Utc::now().timestamp_millis() - (days as i64) * 86_400_000
+        );
+        let deleted = {
+            let repo = self.state.document_revision_repo.lock().await;
+            let repo = repo.get(&self.state.config);
+            repo.delete_revisions_before(document_id, retention.keep_last, expired_before).await?
+        };
+        // The blobs are referenced as long as the revisions, see: BlobHandler::sync_references
+        if deleted > 0 {
+            let revisions = self.list_all_revisions(document_id).await?;
+            let contents: Vec<&str> = revisions
+                .iter()
+                .filter_map(|revision| revision.content.as_deref())
+                .collect();
+            BlobHandler::new(self.state).prune_references(document_id, &contents).await?;
+        }
+        Ok(revision_id)
+    }
+
+    // Save the document as the current user (i.e. the owner), the blobs referenced by the content could
+    // be uploaded by the uploaders as well, such as the editors of the shared document.
+    pub(crate) async fn save_as(&self, param: SaveDocumentRequest, uploaders: &[i64]) -> Result<(i64, i64), Error> {
+        let repo = self.state.document_repo.lock().await;
+        let (document_id, version) = match param.id {
+            Some(id) => {
+                let version = param.version.ok_or_else(||
+                    BizError::PreconditionRequired(
+                        "The version or If-Match is required to update the document".to_string()
+                    )
+                )?;
+                // The update is scoped to the current owner, nothing matched means not found or not
+                // owned, or the version is stale.
+                let updated_id = repo.get(&self.state.config).update(param.to_document()).await?;
+                if updated_id < 0 {
+                    let current = repo.get(&self.state.config).select_by_id(id).await?;
+                    let current_version = current.base.version.unwrap_or_default();
+                    return Err(BizError::Conflict("document".to_string(), current_version).into());
+                }
+                (updated_id, version + 1)
+            }
+            None => (repo.get(&self.state.config).insert(param.to_document()).await?, 1),
+        };
+        drop(repo);
+
+        // Sync the references of the blobs (such as the embedded images) in the content.
+        if let Some(content) = &param.content {
+            BlobHandler::new(self.state).sync_references(document_id, content, uploaders).await?;
+        }
+
+        // Append the immutable revision of every saved content.
+        if param.content.is_some() {
+            self.append_revision(document_id, param.content).await?;
+            // Render the thumbnails of the board ahead, so that the menu can preview it quickly.
+            if self.state.config.webnote.thumbnail.enabled {
+                ThumbnailHandler::new(self.state).render_in_background(document_id).await;
+            }
+        }
+
+        let action = if param.id.is_some() { ChangeAction::Updated } else { ChangeAction::Created };
+        let event = ChangeEvent::new(ChangeResource::Document, action, document_id, param.key, Some(version));
+        EventHandler::new(self.state).publish(event).await;
+        Ok((document_id, version))
+    }
+
+    // Find the trashed document by id.
+    pub(crate) async fn get_trash(&self, id: i64) -> Result<Document, Error> {
+        let param = Document {
+            base: BaseBean::new_with_id(Some(id)),
+            owner_uid: None,
+            key: None,
+            name: None,
+            folder_key: None,
+            doc_type: None,
+            content: None,
+        };
+        store
+            ::select_all(&self.state.document_repo, &self.state.config, param, true).await?
+            .pop()
+            .ok_or_else(|| BizError::NotFound("document".to_string()).into())
+    }
+
+    // List all the revisions of the document, newest first.
+    async fn list_all_revisions(&self, document_id: i64) -> Result<Vec<DocumentRevision>, Error> {
+        let repo = self.state.document_revision_repo.lock().await;
+        let param = QueryDocumentRevisionRequest { document_id };
+        let mut revisions = Vec::new();
+        let mut page = PageRequest { with_total: Some(false), ..PageRequest::new(1, 1000) };
+        loop {
+            let (response, data) = repo.get(&self.state.config).select(param.to_revision(), page.clone()).await?;
+            revisions.extend(data);
+            match response.next_cursor {
+                Some(cursor) => page.cursor = Some(cursor),
+                None => return Ok(revisions),
+            }
+        }
+    }
+}
+
+// Pretty the json content (such as the board snapshot) to make the line diff readable.
+fn to_diffable(content: &Option<String>) -> String {
+    let content = content.to_owned().unwrap_or_default();
+    serde_json
+        ::from_str::<serde_json::Value>(&content)
+        .ok()
+        .and_then(|v| serde_json::to_string_pretty(&v).ok())
+        .unwrap_or(content)
 }
 
 #[async_trait]
@@ -44,8 +225,12 @@ impl<'a> IDocumentHandler for DocumentHandler<'a> {
             name,
             folder_key: None,
             doc_type: None,
+            with_content: None,
+            tags: None,
+            tag_match: None,
+            owner_uid: None,
         };
-        let res = self.find(param, PageRequest::default()).await.unwrap().1;
+        let res = self.find(param, PageRequest::default()).await?.1;
         if res.len() > 0 {
             let document = Arc::new(res.get(0).unwrap().clone());
             return Ok(Some(document));
@@ -59,21 +244,209 @@ impl<'a> IDocumentHandler for DocumentHandler<'a> {
         param: QueryDocumentRequest,
         page: PageRequest
     ) -> Result<(PageResponse, Vec<Document>), Error> {
+        if let Some(owner_uid) = param.owner_uid.filter(|uid| Some(*uid) != SecurityContext::get_current_uid()) {
+            let (claims, ids) = AclHandler::new(self.state).shared_document_ids(owner_uid).await?;
+            let summary = !param.with_content.unwrap_or(true);
+            let repo = self.state.document_repo.lock().await;
+            let selected = repo.get(&self.state.config).select_within(ids, param.to_document(), page, summary);
+            return SecurityContext::scope(Some(claims), selected).await;
+        }
+        let tag_ids = param.get_tag_ids()?;
+        if !tag_ids.is_empty() {
+            let tag_match = param.tag_match.unwrap_or_default();
+            let summary = !param.with_content.unwrap_or(true);
+            let repo = self.state.document_repo.lock().await;
+            let document = param.to_document();
+            return repo.get(&self.state.config).select_tagged(tag_ids, tag_match, document, page, summary).await;
+        }
         let repo = self.state.document_repo.lock().await;
-        repo.get(&self.state.config).select(param.to_document(), page).await
+        if param.with_content.unwrap_or(true) {
+            repo.get(&self.state.config).select(param.to_document(), page).await
+        } else {
+            repo.get(&self.state.config).select_summary(param.to_document(), page).await
+        }
     }
 
-    async fn save(&self, param: SaveDocumentRequest) -> Result<i64, Error> {
+    async fn search(
+        &self,
+        param: SearchDocumentRequest,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<SearchHit<Document>>), Error> {
+        if search::to_terms(&param.q).is_empty() {
+            return Err(BizError::BadRequest("The search query is empty".to_string()).into());
+        }
         let repo = self.state.document_repo.lock().await;
-        if param.id.is_some() {
-            repo.get(&self.state.config).update(param.to_document()).await
-        } else {
-            repo.get(&self.state.config).insert(param.to_document()).await
+        repo
+            .get(&self.state.config)
+            .searcher()
+            .ok_or_else(|| Error::msg("The documents search is not supported"))?
+            .search(&param.q, param.to_document(), page).await
+    }
+
+    async fn save(&self, param: SaveDocumentRequest) -> Result<(i64, i64), Error> {
+        // The shared document is saved as the owner if the current user is the editor.
+        if let Some(id) = param.id {
+            let authorized = AclHandler::new(self.state).authorize(AclResource::Document, id, AclRole::Editor).await?;
+            if let Some(claims) = authorized {
+                let editor: Vec<i64> = SecurityContext::get_current_uid().into_iter().collect();
+                return SecurityContext::scope(Some(claims), self.save_as(param, &editor)).await;
+            }
         }
+        self.save_as(param, &[]).await
+    }
+
+    async fn move_to_folder(&self, param: MoveDocumentRequest) -> Result<u64, Error> {
+        if let Some(folder_key) = &param.folder_key {
+            if FolderHandler::new(self.state).get_by_key(folder_key).await?.is_none() {
+                return Err(BizError::NotFound("folder".to_string()).into());
+            }
+        }
+        let value = param.folder_key.map(GenericValue::String).unwrap_or(GenericValue::Null);
+        let mut ids = param.ids;
+        ids.sort_unstable();
+        ids.dedup();
+
+        // Check all the documents before moving, so that none is moved if any is absent.
+        let repo = self.state.document_repo.lock().await;
+        for id in &ids {
+            repo.get(&self.state.config).select_by_id(*id).await?;
+        }
+        // All are moved in one statement, so that none is moved if it fails.
+        let moved = repo
+            .get(&self.state.config)
+            .update_fields_within(ids.clone(), vec![("folder_key".to_string(), value)]).await?;
+        drop(repo);
+        let events = EventHandler::new(self.state);
+        for id in ids {
+            events.publish(ChangeEvent::new(ChangeResource::Document, ChangeAction::Moved, id, None, None)).await;
+        }
+        Ok(moved)
     }
 
     async fn delete(&self, param: DeleteDocumentRequest) -> Result<u64, Error> {
+        let authorized = AclHandler::new(self.state).authorize(AclResource::Document, param.id, AclRole::Editor).await?;
+        if let Some(claims) = authorized {
+            return SecurityContext::scope(Some(claims), self.delete(param)).await;
+        }
         let repo = self.state.document_repo.lock().await;
-        repo.get(&self.state.config).delete_by_id(param.id).await
+        let deleted = repo.get(&self.state.config).delete_by_id(param.id).await?;
+        if deleted == 0 {
+            return Err(BizError::NotFound("document".to_string()).into());
+        }
+        let event = ChangeEvent::new(ChangeResource::Document, ChangeAction::Deleted, param.id, None, None);
+        EventHandler::new(self.state).publish(event).await;
+        Ok(deleted)
+    }
+
+    async fn find_trash(
+        &self,
+        param: QueryDocumentRequest,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<Document>), Error> {
+        let repo = self.state.document_repo.lock().await;
+        repo.get(&self.state.config).select_trash(param.to_document(), page).await
+    }
+
+    async fn restore(&self, param: RestoreDocumentRequest) -> Result<u64, Error> {
+        let document = self.get_trash(param.id).await?;
+        if let Some(folder_key) = document.folder_key.filter(|k| !k.is_empty()) {
+            FolderHandler::new(self.state).restore_ancestry_by_key(&folder_key).await?;
+        }
+        let repo = self.state.document_repo.lock().await;
+        let restored = repo.get(&self.state.config).restore_by_id(param.id).await?;
+        if restored > 0 {
+            let action = ChangeAction::Restored;
+            let event = ChangeEvent::new(ChangeResource::Document, action, param.id, document.key, None);
+            EventHandler::new(self.state).publish(event).await;
+        }
+        Ok(restored)
+    }
+
+    async fn purge(&self, param: PurgeDocumentRequest) -> Result<u64, Error> {
+        let repo = self.state.document_repo.lock().await;
+        let purged = repo.get(&self.state.config).purge_by_id(param.id).await?;
+        if purged == 0 {
+            return Err(BizError::NotFound("document".to_string()).into());
+        }
+        drop(repo);
+
+        let revisions = self.list_all_revisions(param.id).await?;
+        let repo = self.state.document_revision_repo.lock().await;
+        for revision in revisions {
+            repo.get(&self.state.config).delete_by_id(revision.base.id.unwrap_or_default()).await?;
+        }
+        drop(repo);
+
+        BlobHandler::new(self.state).delete_references(param.id).await?;
+        TagHandler::new(self.state).detach_document(param.id).await?;
+        ShareHandler::new(self.state).delete_by_document(param.id).await?;
+        AclHandler::new(self.state).delete_by_resource(AclResource::Document, param.id).await?;
+        SyncHandler::new(self.state).delete_by_document(param.id).await?;
+        let event = ChangeEvent::new(ChangeResource::Document, ChangeAction::Purged, param.id, None, None);
+        EventHandler::new(self.state).publish(event).await;
+        Ok(purged)
+    }
+
+    async fn find_revisions(
+        &self,
+        param: QueryDocumentRevisionRequest,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<DocumentRevision>), Error> {
+        let repo = self.state.document_revision_repo.lock().await;
+        repo.get(&self.state.config).select(param.to_revision(), page).await
+    }
+
+    async fn get_revision(&self, id: i64) -> Result<DocumentRevision, Error> {
+        let repo = self.state.document_revision_repo.lock().await;
+        repo.get(&self.state.config).select_by_id(id).await
+    }
+
+    async fn diff_revision(&self, from_id: i64, to_id: Option<i64>) -> Result<String, Error> {
+        let from = self.get_revision(from_id).await?;
+        let document_id = from.document_id.unwrap_or_default();
+        let (to_name, to_content) = match to_id {
+            Some(id) => {
+                let to = self.get_revision(id).await?;
+                if to.document_id != from.document_id {
+                    return Err(
+                        BizError::BadRequest(
+                            "The revisions are not of the same document".to_string()
+                        ).into()
+                    );
+                }
+                (format!("revision/{}", id), to.content)
+            }
+            None => {
+                let repo = self.state.document_repo.lock().await;
+                let document = repo.get(&self.state.config).select_by_id(document_id).await?;
+                (format!("document/{}", document_id), document.content)
+            }
+        };
+
+        let (old, new) = (to_diffable(&from.content), to_diffable(&to_content));
+        let diff = TextDiff::from_lines(&old, &new)
+            .unified_diff()
+            .header(&format!("revision/{}", from_id), &to_name)
+            .to_string();
+        Ok(diff)
+    }
+
+    async fn restore_revision(&self, id: i64) -> Result<i64, Error> {
+        let revision = self.get_revision(id).await?;
+        // The restoring is based on the current version, which is explicitly acted by the user.
+        let current = {
+            let repo = self.state.document_repo.lock().await;
+            repo.get(&self.state.config).select_by_id(revision.document_id.unwrap_or_default()).await?
+        };
+        let param = SaveDocumentRequest {
+            id: revision.document_id,
+            version: Some(current.base.version.unwrap_or_default()),
+            key: None,
+            name: None,
+            folder_key: None,
+            doc_type: None,
+            content: Some(revision.content.unwrap_or_default()),
+        };
+        Ok(self.save(param).await?.0)
     }
 }
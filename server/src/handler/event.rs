@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+
+use anyhow::Error;
+use axum::async_trait;
+use chrono::Utc;
+use futures::stream::{ self, BoxStream, StreamExt };
+
+use crate::context::state::AppState;
+use crate::errors::BizError;
+use crate::types::event::ChangeEvent;
+use crate::utils::auths::SecurityContext;
+
+// The channel of the change events of per user, which is distributed across the instances if the cache
+// provider is redis.
+const EVENT_CHANNEL_PREFIX: &str = "mywebnote:events:";
+// The recent change events of per user, for resuming the disconnected clients, which are bucketed by the
+// retention, and the buckets are expired by the cache, so that none is trimmed when publishing.
+const EVENT_HISTORY_PREFIX: &str = "mywebnote:events:history:";
+// The sequence of the event ids of per user, which is increased by the cache across the instances, so that
+// the ids are ordered by the publishing.
+const EVENT_SEQUENCE_PREFIX: &str = "mywebnote:events:sequence:";
+
+#[async_trait]
+pub trait IEventHandler: Send {
+    // Subscribe the change events of the current user, the events after the last event id are replayed
+    // first if resuming.
+    async fn subscribe(&self, last_event_id: Option<i64>) -> Result<BoxStream<'static, ChangeEvent>, Error>;
+}
+
+pub struct EventHandler<'a> {
+    state: &'a AppState,
+}
+
+impl<'a> EventHandler<'a> {
+    pub fn new(state: &'a AppState) -> Self {
+        Self { state }
+    }
+
+    // Publish the change of the current user, which is the best effort, since the change is already saved.
+    pub(crate) async fn publish(&self, event: ChangeEvent) {
+        if let Err(e) = self.try_publish(event.clone()).await {
+            tracing::warn!("Failed to publish the change event: {:?}. reason: {}", event, e);
+        }
+    }
+
+    async fn try_publish(&self, mut event: ChangeEvent) -> Result<(), Error> {
+        let uid = SecurityContext::get_current_uid().ok_or(BizError::Unauthenticated)?;
+        let cache = self.state.string_cache.get(&self.state.config);
+        event.id = cache.incr(format!("{}{}", EVENT_SEQUENCE_PREFIX, uid), None).await?;
+        let data = serde_json::to_string(&event)?;
+        let history = self.history_key(uid, event.time);
+        cache.hset(history.to_owned(), Some(vec![(event.id.to_string(), data.to_owned())])).await?;
+        // The bucket is kept until all the events of it are out of the retention.
+        cache.expire(history, self.retention() * 2).await?;
+
+        cache.publish(format!("{}{}", EVENT_CHANNEL_PREFIX, uid), data).await?;
+        Ok(())
+    }
+
+    // The events of the user within the retention, ordered by the ids.
+    async fn history(&self, uid: i64) -> Result<Vec<ChangeEvent>, Error> {
+        let cache = self.state.string_cache.get(&self.state.config);
+        let now = Utc::now().timestamp_millis();
+        let expired_before = self.expired_before();
+        let mut events = Vec::new();
+        // The retention is covered by the current and the previous buckets.
+        for history in [self.history_key(uid, now - self.retention()), self.history_key(uid, now)] {
+            let history = cache.hget_all(history).await?.unwrap_or_default();
+            events.extend(
+                history
+                    .values()
+                    .filter_map(|data| serde_json::from_str::<ChangeEvent>(data).ok())
+                    .filter(|event| event.time >= expired_before)
+            );
+        }
+        events.sort_by_key(|event| event.id);
+        Ok(events)
+    }
+
+    fn history_key(&self, uid: i64, time: i64) -> String {
+        format!("{}{}:{}", EVENT_HISTORY_PREFIX, uid, time / self.retention())
+    }
+
+    // The retention milliseconds of the events.
+    fn retention(&self) -> i64 {
+        (self.state.config.webnote.events.retention.max(1) as i64) * 1000
+    }
+
+    fn expired_before(&self) -> i64 {
+        Utc::now().timestamp_millis() - self.retention()
+    }
+}
+
+#[async_trait]
+impl<'a> IEventHandler for EventHandler<'a> {
+    async fn subscribe(&self, last_event_id: Option<i64>) -> Result<BoxStream<'static, ChangeEvent>, Error> {
+        let uid = SecurityContext::get_current_uid().ok_or(BizError::Unauthenticated)?;
+        // Subscribed before the history is read, so that none is missed, and the replayed are skipped.
+        let cache = self.state.string_cache.get(&self.state.config);
+        let live = cache.subscribe(format!("{}{}", EVENT_CHANNEL_PREFIX, uid)).await?;
+        let replayed: Vec<ChangeEvent> = match last_event_id {
+            Some(last_event_id) => {
+                // The sequence is reset if the last event id is ahead of it, such as the cache restarted, and
+                // then all the events are replayed.
+                let sequence = cache.get(format!("{}{}", EVENT_SEQUENCE_PREFIX, uid)).await?;
+                let sequence = sequence.and_then(|s| s.parse::<i64>().ok()).unwrap_or_default();
+                let last_event_id = if last_event_id > sequence { 0 } else { last_event_id };
+                self.history(uid).await?
+                    .into_iter()
+                    .filter(|event| event.id > last_event_id)
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+        let replayed_ids: HashSet<i64> = replayed.iter().map(|event| event.id).collect();
+        let live = live.filter_map(move |message| {
+            let event = serde_json::from_str::<ChangeEvent>(&message).ok();
+            futures::future::ready(event.filter(|event| !replayed_ids.contains(&event.id)))
+        });
+        Ok(stream::iter(replayed).chain(live).boxed())
+    }
+}
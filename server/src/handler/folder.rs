@@ -1,10 +1,31 @@
+use std::collections::{ HashMap, HashSet };
 use std::sync::Arc;
 
 use anyhow::{ Error, Ok };
 use axum::async_trait;
 use crate::context::state::AppState;
-use crate::types::folder::{ DeleteFolderRequest, QueryFolderRequest, SaveFolderRequest, Folder };
+use crate::errors::BizError;
+use crate::store;
+use crate::types::BaseBean;
+use crate::types::acl::{ AclResource, AclRole };
+use crate::types::document::{ Document, PurgeDocumentRequest };
+use crate::types::event::{ ChangeAction, ChangeEvent, ChangeResource };
+use crate::types::folder::{
+    DeleteFolderRequest,
+    FolderCascade,
+    FolderNode,
+    MoveFolderRequest,
+    PurgeFolderRequest,
+    QueryFolderRequest,
+    RestoreFolderRequest,
+    SaveFolderRequest,
+    Folder,
+};
 use crate::types::{ PageRequest, PageResponse };
+use crate::utils::auths::SecurityContext;
+use super::acl::AclHandler;
+use super::document::{ DocumentHandler, IDocumentHandler };
+use super::event::EventHandler;
 
 #[async_trait]
 pub trait IFolderHandler: Send {
@@ -16,9 +37,31 @@ pub trait IFolderHandler: Send {
         page: PageRequest
     ) -> Result<(PageResponse, Vec<Folder>), Error>;
 
-    async fn save(&self, param: SaveFolderRequest) -> Result<i64, Error>;
+    // Returns the id and the current version of the saved folder, see: IDocumentHandler::save
+    async fn save(&self, param: SaveFolderRequest) -> Result<(i64, i64), Error>;
 
+    // Returns the nested folders of the current user, and the count of the documents in the root.
+    async fn tree(&self) -> Result<(Vec<FolderNode>, u64), Error>;
+
+    // Move the folder under the new parent, returns the id and the current version of the moved.
+    async fn move_folder(&self, param: MoveFolderRequest) -> Result<(i64, i64), Error>;
+
+    // Move the folder to the trash, which cascades to the sub folders and the documents of them
+    // by default, see: FolderCascade
     async fn delete(&self, param: DeleteFolderRequest) -> Result<u64, Error>;
+
+    async fn find_trash(
+        &self,
+        param: QueryFolderRequest,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<Folder>), Error>;
+
+    // Restore the folder from the trash, as well as the trashed ancestry of it, and the sub folders
+    // and documents which trashed together with it.
+    async fn restore(&self, param: RestoreFolderRequest) -> Result<u64, Error>;
+
+    // Permanently delete the trashed folder, as well as the trashed sub folders and documents of it.
+    async fn purge(&self, param: PurgeFolderRequest) -> Result<u64, Error>;
 }
 
 pub struct FolderHandler<'a> {
@@ -29,6 +72,182 @@ impl<'a> FolderHandler<'a> {
     pub fn new(state: &'a AppState) -> Self {
         Self { state }
     }
+
+    // Get the active folder of the key, which is none if not exists or trashed.
+    pub(crate) async fn get_by_key(&self, key: &str) -> Result<Option<Folder>, Error> {
+        Ok(self.select_all(to_param(None, None, Some(key.to_string())), false).await?.pop())
+    }
+
+    // Restore the trashed folder of the key, and the trashed ancestors of it.
+    pub(crate) async fn restore_ancestry_by_key(&self, key: &str) -> Result<u64, Error> {
+        let param = to_param(None, None, Some(key.to_string()));
+        if !self.select_all(param.clone(), false).await?.is_empty() {
+            return Ok(0);
+        }
+        match self.select_all(param, true).await?.pop() {
+            Some(folder) => self.restore_ancestry(folder).await,
+            None => Ok(0),
+        }
+    }
+
+    // Restore the trashed folder and walk up restoring until the active or root ancestor.
+    async fn restore_ancestry(&self, folder: Folder) -> Result<u64, Error> {
+        let mut restored = 0;
+        let mut current = Some(folder);
+        while let Some(folder) = current {
+            {
+                let repo = self.state.folder_repo.lock().await;
+                restored += repo
+                    .get(&self.state.config)
+                    .restore_by_id(folder.base.id.unwrap_or_default()).await?;
+            }
+            current = match folder.pid.filter(|pid| *pid > 0) {
+                Some(pid) => self.select_all(to_param(Some(pid), None, None), true).await?.pop(),
+                None => None,
+            };
+        }
+        Ok(restored)
+    }
+
+    // Collect the folder and all the sub folders of it, which are trashed or not.
+    pub(crate) async fn collect_tree(&self, root: Folder, trash: bool) -> Result<Vec<Folder>, Error> {
+        let mut tree = vec![root];
+        let mut i = 0;
+        while i < tree.len() {
+            let children = self.select_all(to_param(None, tree[i].base.id, None), trash).await?;
+            tree.extend(children);
+            i += 1;
+        }
+        Ok(tree)
+    }
+
+    // Move the folder to the trash, as well as the sub folders and the documents of them, all or nothing.
+    async fn delete_tree(&self, root: Folder) -> Result<u64, Error> {
+        let tree = self.collect_tree(root, false).await?;
+        let ids = tree.iter().filter_map(|folder| folder.base.id).collect();
+        let keys = tree.into_iter().filter_map(|folder| folder.key.filter(|key| !key.is_empty())).collect();
+        let repo = self.state.folder_repo.lock().await;
+        repo.get(&self.state.config).trash_tree(ids, keys).await
+    }
+
+    // Move the sub folders and the documents of the folder to the parent of it, then trash the folder,
+    // all or nothing, and the folder changed since read is the conflict.
+    async fn delete_reparent(&self, root: Folder) -> Result<u64, Error> {
+        let id = root.base.id.unwrap_or_default();
+        let parent_key = match root.pid.unwrap_or_default() {
+            pid if pid > 0 => self.select_all(to_param(Some(pid), None, None), false).await?.pop(),
+            _ => None,
+        }.and_then(|parent| parent.key);
+
+        let repo = self.state.folder_repo.lock().await;
+        if repo.get(&self.state.config).trash_reparent(root, parent_key).await? < 0 {
+            let current = repo.get(&self.state.config).select_by_id(id).await?;
+            let current_version = current.base.version.unwrap_or_default();
+            return Err(BizError::Conflict("folder".to_string(), current_version).into());
+        }
+        Ok(1)
+    }
+
+    // Only trash the folder which has no active sub folders or documents.
+    async fn delete_restrict(&self, root: Folder) -> Result<u64, Error> {
+        let children = self.select_all(to_param(None, root.base.id, None), false).await?;
+        if !children.is_empty() || !self.select_documents(&root, false).await?.is_empty() {
+            return Err(BizError::BadRequest("The folder is not empty".to_string()).into());
+        }
+        let repo = self.state.folder_repo.lock().await;
+        repo.get(&self.state.config).delete_by_id(root.base.id.unwrap_or_default()).await
+    }
+
+    async fn select_all(&self, param: Folder, trash: bool) -> Result<Vec<Folder>, Error> {
+        store::select_all(&self.state.folder_repo, &self.state.config, param, trash).await
+    }
+
+    pub(crate) async fn select_documents(&self, folder: &Folder, trash: bool) -> Result<Vec<Document>, Error> {
+        let param = to_document_param(folder.key.to_owned().filter(|k| !k.is_empty()));
+        if param.folder_key.is_none() {
+            return Ok(vec![]);
+        }
+        store::select_all(&self.state.document_repo, &self.state.config, param, trash).await
+    }
+}
+
+// Transform the flat folders to the trees, the folders whose parent is absent (such as trashed) are
+// listed at the root, as well as the documents whose folder is absent.
+pub(crate) fn build_tree(folders: Vec<Folder>, documents: &[Document]) -> (Vec<FolderNode>, u64) {
+    let ids: HashSet<i64> = folders.iter().filter_map(|f| f.base.id).collect();
+    let key_ids: HashMap<&str, i64> = folders
+        .iter()
+        .filter_map(|f| Some((f.key.as_deref()?, f.base.id?)))
+        .collect();
+
+    let mut counts: HashMap<i64, u64> = HashMap::new();
+    let mut root_count = 0;
+    for document in documents {
+        match document.folder_key.as_deref().and_then(|key| key_ids.get(key)) {
+            Some(id) => *counts.entry(*id).or_default() += 1,
+            None => root_count += 1,
+        }
+    }
+
+    let mut children: HashMap<i64, Vec<Folder>> = HashMap::new();
+    let mut roots = vec![];
+    for folder in folders.iter().cloned() {
+        match folder.pid.filter(|pid| ids.contains(pid) && Some(*pid) != folder.base.id) {
+            Some(pid) => children.entry(pid).or_default().push(folder),
+            None => roots.push(folder),
+        }
+    }
+
+    let mut nodes: Vec<FolderNode> = roots
+        .into_iter()
+        .map(|folder| to_node(folder, &mut children, &counts))
+        .collect();
+    // The remaining are the cycled (unreachable from the root) folders, which are broken at the root.
+    while let Some(pid) = children.keys().next().copied() {
+        for folder in children.remove(&pid).unwrap_or_default() {
+            nodes.push(to_node(folder, &mut children, &counts));
+        }
+    }
+    sort_nodes(&mut nodes);
+    (nodes, root_count)
+}
+
+fn to_node(folder: Folder, children: &mut HashMap<i64, Vec<Folder>>, counts: &HashMap<i64, u64>) -> FolderNode {
+    let id = folder.base.id.unwrap_or_default();
+    let mut node = FolderNode::new(folder);
+    node.document_count = counts.get(&id).copied().unwrap_or_default();
+    // The visited children are removed, so that the cycled folders are not walked infinitely.
+    for child in children.remove(&id).unwrap_or_default() {
+        node.children.push(to_node(child, children, counts));
+    }
+    sort_nodes(&mut node.children);
+    node
+}
+
+fn sort_nodes(nodes: &mut [FolderNode]) {
+    nodes.sort_by(|a, b| a.folder.name.cmp(&b.folder.name).then(a.folder.base.id.cmp(&b.folder.base.id)));
+}
+
+fn to_param(id: Option<i64>, pid: Option<i64>, key: Option<String>) -> Folder {
+    Folder {
+        base: BaseBean::new_with_id(id),
+        owner_uid: None,
+        pid,
+        key,
+        name: None,
+    }
+}
+
+fn to_document_param(folder_key: Option<String>) -> Document {
+    Document {
+        base: BaseBean::new_with_id(None),
+        owner_uid: None,
+        key: None,
+        name: None,
+        folder_key,
+        doc_type: None,
+        content: None,
+    }
 }
 
 #[async_trait]
@@ -38,8 +257,9 @@ impl<'a> IFolderHandler for FolderHandler<'a> {
             pid: None,
             key: None,
             name,
+            owner_uid: None,
         };
-        let res = self.find(param, PageRequest::default()).await.unwrap().1;
+        let res = self.find(param, PageRequest::default()).await?.1;
         if res.len() > 0 {
             let folder = Arc::new(res.get(0).unwrap().clone());
             return Ok(Some(folder));
@@ -53,21 +273,189 @@ impl<'a> IFolderHandler for FolderHandler<'a> {
         param: QueryFolderRequest,
         page: PageRequest
     ) -> Result<(PageResponse, Vec<Folder>), Error> {
+        if let Some(owner_uid) = param.owner_uid.filter(|uid| Some(*uid) != SecurityContext::get_current_uid()) {
+            let (claims, ids) = AclHandler::new(self.state).shared_folder_ids(owner_uid).await?;
+            let repo = self.state.folder_repo.lock().await;
+            let selected = repo.get(&self.state.config).select_within(ids, param.to_folder(), page, false);
+            return SecurityContext::scope(Some(claims), selected).await;
+        }
         let repo = self.state.folder_repo.lock().await;
         repo.get(&self.state.config).select(param.to_folder(), page).await
     }
 
-    async fn save(&self, param: SaveFolderRequest) -> Result<i64, Error> {
+    async fn save(&self, param: SaveFolderRequest) -> Result<(i64, i64), Error> {
+        // The shared folder is saved as the owner if the current user is the editor.
+        if let Some(id) = param.id {
+            let authorized = AclHandler::new(self.state).authorize(AclResource::Folder, id, AclRole::Editor).await?;
+            if let Some(claims) = authorized {
+                return SecurityContext::scope(Some(claims), self.save(param)).await;
+            }
+        }
         let repo = self.state.folder_repo.lock().await;
-        if param.id.is_some() {
-            repo.get(&self.state.config).update(param.to_folder()).await
-        } else {
-            repo.get(&self.state.config).insert(param.to_folder()).await
+        let (folder_id, action, version) = match param.id {
+            Some(id) => {
+                let version = param.version.ok_or_else(||
+                    BizError::PreconditionRequired(
+                        "The version or If-Match is required to update the folder".to_string()
+                    )
+                )?;
+                let current = repo.get(&self.state.config).select_by_id(id).await?;
+                // The documents refer to the folder by key, which follow the renamed key together.
+                let renamed = param.key.is_some() && param.key != current.key;
+                let from_key = current.key.filter(|key| renamed && !key.is_empty());
+                // The update is scoped to the current owner, nothing matched means not found or not
+                // owned, or the version is stale.
+                let updated_id = match from_key {
+                    Some(from_key) => repo.get(&self.state.config).update_rekey(param.to_folder(), from_key).await?,
+                    None => repo.get(&self.state.config).update(param.to_folder()).await?,
+                };
+                if updated_id < 0 {
+                    let current = repo.get(&self.state.config).select_by_id(id).await?;
+                    let current_version = current.base.version.unwrap_or_default();
+                    return Err(BizError::Conflict("folder".to_string(), current_version).into());
+                }
+                (updated_id, ChangeAction::Updated, version + 1)
+            }
+            None => (repo.get(&self.state.config).insert(param.to_folder()).await?, ChangeAction::Created, 1),
+        };
+        let event = ChangeEvent::new(ChangeResource::Folder, action, folder_id, param.key, Some(version));
+        EventHandler::new(self.state).publish(event).await;
+        Ok((folder_id, version))
+    }
+
+    async fn tree(&self) -> Result<(Vec<FolderNode>, u64), Error> {
+        let folders = self.select_all(to_param(None, None, None), false).await?;
+        let documents = store::select_all_summary(
+            &self.state.document_repo,
+            &self.state.config,
+            to_document_param(None)
+        ).await?;
+        Ok(build_tree(folders, &documents))
+    }
+
+    async fn move_folder(&self, param: MoveFolderRequest) -> Result<(i64, i64), Error> {
+        let version = param.version.ok_or_else(||
+            BizError::PreconditionRequired("The version or If-Match is required to move the folder".to_string())
+        )?;
+        // Hold the lock while checking and moving, so that the concurrent moves could not make a cycle.
+        let repo = self.state.folder_repo.lock().await;
+        let folder = repo.get(&self.state.config).select_by_id(param.id).await?;
+
+        // Walk up from the new parent, the folder itself as an ancestor means the cycle.
+        let mut visited = HashSet::new();
+        let mut ancestor = Some(param.pid).filter(|pid| *pid > 0);
+        while let Some(id) = ancestor {
+            if id == param.id {
+                return Err(
+                    BizError::BadRequest(
+                        "The folder could not be moved under itself or its sub folders".to_string()
+                    ).into()
+                );
+            }
+            if !visited.insert(id) {
+                break;
+            }
+            ancestor = repo.get(&self.state.config).select_by_id(id).await?.pid.filter(|pid| *pid > 0);
+        }
+
+        let mut update = to_param(Some(param.id), Some(param.pid), None);
+        update.base.version = Some(version);
+        if repo.get(&self.state.config).update(update).await? < 0 {
+            let current = repo.get(&self.state.config).select_by_id(param.id).await?;
+            let current_version = current.base.version.unwrap_or_default();
+            return Err(BizError::Conflict("folder".to_string(), current_version).into());
         }
+        drop(repo);
+        let action = ChangeAction::Moved;
+        let event = ChangeEvent::new(ChangeResource::Folder, action, param.id, folder.key, Some(version + 1));
+        EventHandler::new(self.state).publish(event).await;
+        Ok((param.id, version + 1))
     }
 
     async fn delete(&self, param: DeleteFolderRequest) -> Result<u64, Error> {
+        let authorized = AclHandler::new(self.state).authorize(AclResource::Folder, param.id, AclRole::Editor).await?;
+        if let Some(claims) = authorized {
+            return SecurityContext::scope(Some(claims), self.delete(param)).await;
+        }
+        let root = {
+            let repo = self.state.folder_repo.lock().await;
+            repo.get(&self.state.config).select_by_id(param.id).await?
+        };
+        let key = root.key.clone();
+        let deleted = match param.cascade.unwrap_or_default() {
+            FolderCascade::Trash => self.delete_tree(root).await?,
+            FolderCascade::Reparent => self.delete_reparent(root).await?,
+            FolderCascade::Restrict => self.delete_restrict(root).await?,
+        };
+        let event = ChangeEvent::new(ChangeResource::Folder, ChangeAction::Deleted, param.id, key, None);
+        EventHandler::new(self.state).publish(event).await;
+        Ok(deleted)
+    }
+
+    async fn find_trash(
+        &self,
+        param: QueryFolderRequest,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<Folder>), Error> {
         let repo = self.state.folder_repo.lock().await;
-        repo.get(&self.state.config).delete_by_id(param.id).await
+        repo.get(&self.state.config).select_trash(param.to_folder(), page).await
+    }
+
+    async fn restore(&self, param: RestoreFolderRequest) -> Result<u64, Error> {
+        let root = self
+            .select_all(to_param(Some(param.id), None, None), true).await?
+            .pop()
+            .ok_or_else(|| BizError::NotFound("folder".to_string()))?;
+
+        // Only the descendants trashed together with (or after) the folder are restored, the earlier
+        // trashed ones are left in the trash as they were deleted individually.
+        let trashed_time = root.base.update_time.unwrap_or_default();
+        let trashed_with = |time: Option<i64>| time.unwrap_or_default() >= trashed_time;
+
+        let key = root.key.clone();
+        let mut restored = self.restore_ancestry(root.clone()).await?;
+        let tree = self.collect_tree(root, true).await?;
+        for (i, folder) in tree.iter().enumerate() {
+            if i > 0 && trashed_with(folder.base.update_time) {
+                let repo = self.state.folder_repo.lock().await;
+                restored += repo
+                    .get(&self.state.config)
+                    .restore_by_id(folder.base.id.unwrap_or_default()).await?;
+            }
+            for document in self.select_documents(folder, true).await? {
+                if trashed_with(document.base.update_time) {
+                    let repo = self.state.document_repo.lock().await;
+                    restored += repo
+                        .get(&self.state.config)
+                        .restore_by_id(document.base.id.unwrap_or_default()).await?;
+                }
+            }
+        }
+        let event = ChangeEvent::new(ChangeResource::Folder, ChangeAction::Restored, param.id, key, None);
+        EventHandler::new(self.state).publish(event).await;
+        Ok(restored)
+    }
+
+    async fn purge(&self, param: PurgeFolderRequest) -> Result<u64, Error> {
+        let root = self
+            .select_all(to_param(Some(param.id), None, None), true).await?
+            .pop()
+            .ok_or_else(|| BizError::NotFound("folder".to_string()))?;
+
+        let mut purged = 0;
+        let documents = DocumentHandler::new(self.state);
+        for folder in self.collect_tree(root, true).await? {
+            for document in self.select_documents(&folder, true).await? {
+                let param = PurgeDocumentRequest { id: document.base.id.unwrap_or_default() };
+                purged += documents.purge(param).await?;
+            }
+            let fid = folder.base.id.unwrap_or_default();
+            AclHandler::new(self.state).delete_by_resource(AclResource::Folder, fid).await?;
+            let repo = self.state.folder_repo.lock().await;
+            purged += repo.get(&self.state.config).purge_by_id(fid).await?;
+        }
+        let event = ChangeEvent::new(ChangeResource::Folder, ChangeAction::Purged, param.id, None, None);
+        EventHandler::new(self.state).publish(event).await;
+        Ok(purged)
     }
 }
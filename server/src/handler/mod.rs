@@ -1,7 +1,19 @@
 pub mod api_v1;
+pub mod acl;
 pub mod auth;
 pub mod user;
 pub mod browser_indexeddb_v2;
+pub mod blob;
+pub mod board;
+pub mod note;
+pub mod thumbnail;
 pub mod document;
+pub mod event;
 pub mod settings;
+pub mod room;
+pub mod share;
+pub mod sync;
+pub mod tag;
 pub mod folder;
+pub mod trash;
+pub mod backup;
@@ -0,0 +1,189 @@
+use std::collections::{ BTreeMap, HashMap, HashSet };
+use std::io::{ Cursor, Write };
+
+use anyhow::Error;
+use axum::async_trait;
+use zip::{ write::SimpleFileOptions, CompressionMethod, ZipWriter };
+
+use crate::context::state::AppState;
+use crate::errors::BizError;
+use crate::types::document::{ Document, DocumentType, NoteFormat };
+use crate::utils::{ boards, notes };
+use super::blob::{ to_blob_hash, BlobHandler };
+use super::folder::FolderHandler;
+
+const DEFAULT_NOTE_NAME: &str = "Untitled";
+// The directory of the zip which the blobs of the notes are stored in when export as the markdown.
+const ASSETS_DIR: &str = "assets/";
+
+#[async_trait]
+pub trait INoteHandler: Send {
+    // Export the note document as the markdown or the standalone HTML, returns the file name and the content.
+    async fn export(&self, id: i64, format: NoteFormat) -> Result<(String, String), Error>;
+
+    // Export all the notes of the folder and the sub folders as the zip which keeps the hierarchy of the
+    // folders, returns the file name and the zip.
+    async fn export_folder(&self, id: i64, format: NoteFormat) -> Result<(String, Vec<u8>), Error>;
+}
+
+pub struct NoteHandler<'a> {
+    state: &'a AppState,
+}
+
+impl<'a> NoteHandler<'a> {
+    pub fn new(state: &'a AppState) -> Self {
+        Self { state }
+    }
+
+    // Render the note, the blobs are embedded as the data URLs for the HTML, and for the markdown they are
+    // linked to the downloads, or to the files of the zip if the assets present, which are relative to
+    // the directory of the note.
+    async fn render(
+        &self,
+        document: &Document,
+        format: NoteFormat,
+        assets: Option<(&mut BTreeMap<String, Vec<u8>>, &str)>
+    ) -> Result<String, Error> {
+        let root = notes::to_root_block(document.content.as_deref().unwrap_or_default())?;
+        let title = Some(notes::title_of(&root))
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| to_note_name(document));
+
+        let handler = BlobHandler::new(self.state);
+        let mut links = HashMap::new();
+        let mut assets = assets;
+        for source in notes::blob_sources(&root) {
+            let Some(hash) = to_blob_hash(&source) else {
+                continue;
+            };
+            let link = if format == NoteFormat::Md && assets.is_none() {
+                handler.download_url(&hash)
+            } else {
+                let (blob, data) = match handler.read_all(&hash).await {
+                    Ok(read) => read,
+                    Err(e) => {
+                        tracing::warn!("Skip export the blob of the note: {}. reason: {}", hash, e);
+                        continue;
+                    }
+                };
+                let mime_type = blob.mime_type.unwrap_or_default();
+                match (format, assets.as_mut()) {
+                    (NoteFormat::Md, Some((assets, relative))) => {
+                        let path = format!("{}{}{}", ASSETS_DIR, hash, to_extension(&mime_type));
+                        let link = format!("{}{}", relative, path);
+                        assets.insert(path, data);
+                        link
+                    }
+                    _ => boards::encode_data_url(&mime_type, &data),
+                }
+            };
+            links.insert(source, link);
+        }
+
+        Ok(match format {
+            NoteFormat::Md => notes::to_markdown(&root, &title, &links),
+            NoteFormat::Html => notes::to_html(&root, &title, &links),
+        })
+    }
+}
+
+fn to_note_name(document: &Document) -> String {
+    document.name
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .unwrap_or(DEFAULT_NOTE_NAME)
+        .to_string()
+}
+
+// The extension of the file of the mime type, such as '.png', and empty if unknown.
+fn to_extension(mime_type: &str) -> String {
+    mime_guess
+        ::get_mime_extensions_str(mime_type)
+        .and_then(|extensions| extensions.first())
+        .map(|extension| format!(".{}", extension))
+        .unwrap_or_default()
+}
+
+// The name of the file or the directory of the zip, which the reserved characters of the file systems are
+// replaced.
+fn to_file_name(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_control() || "/\\:*?\"<>|".contains(c) { '_' } else { c })
+        .collect();
+    if name.trim_matches('.').is_empty() { DEFAULT_NOTE_NAME.to_string() } else { name }
+}
+
+// The unique path of the name in the directory, such as 'a (2).md' if the 'a.md' is taken.
+fn to_unique_path(taken: &mut HashSet<String>, dir: &str, name: &str, extension: &str) -> String {
+    let mut path = format!("{}{}{}", dir, name, extension);
+    let mut i = 1;
+    while !taken.insert(path.to_lowercase()) {
+        i += 1;
+        path = format!("{}{} ({}){}", dir, name, i, extension);
+    }
+    path
+}
+
+#[async_trait]
+impl<'a> INoteHandler for NoteHandler<'a> {
+    async fn export(&self, id: i64, format: NoteFormat) -> Result<(String, String), Error> {
+        let document = {
+            let repo = self.state.document_repo.lock().await;
+            repo.get(&self.state.config).select_by_id(id).await?
+        };
+        if document.doc_type != Some(DocumentType::Note) {
+            return Err(BizError::BadRequest("The document is not a note".to_string()).into());
+        }
+        let content = self.render(&document, format, None).await?;
+        Ok((format!("{}.{}", to_file_name(&to_note_name(&document)), format.extension()), content))
+    }
+
+    async fn export_folder(&self, id: i64, format: NoteFormat) -> Result<(String, Vec<u8>), Error> {
+        let root = {
+            let repo = self.state.folder_repo.lock().await;
+            repo.get(&self.state.config).select_by_id(id).await?
+        };
+        let root_name = to_file_name(root.name.as_deref().unwrap_or_default());
+
+        let folder_handler = FolderHandler::new(self.state);
+        let mut taken = HashSet::new();
+        // The directories of the folders, the folders of the tree are in order of the parents first.
+        let mut dirs: HashMap<i64, String> = HashMap::new();
+        let mut files: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        let mut assets = BTreeMap::new();
+        for folder in folder_handler.collect_tree(root, false).await? {
+            let parent_dir = folder.pid.and_then(|pid| dirs.get(&pid)).cloned().unwrap_or_default();
+            let name = to_file_name(folder.name.as_deref().unwrap_or_default());
+            let dir = format!("{}/", to_unique_path(&mut taken, &parent_dir, &name, ""));
+            // The relative path from the directory to the root of the zip.
+            let relative = "../".repeat(dir.matches('/').count());
+
+            for document in folder_handler.select_documents(&folder, false).await? {
+                if document.doc_type != Some(DocumentType::Note) {
+                    continue;
+                }
+                let extension = format!(".{}", format.extension());
+                let path = to_unique_path(&mut taken, &dir, &to_file_name(&to_note_name(&document)), &extension);
+                match self.render(&document, format, Some((&mut assets, &relative))).await {
+                    Ok(content) => {
+                        files.insert(path, content.into_bytes());
+                    }
+                    Err(e) => tracing::warn!("Skip export the note: {}. reason: {}", path, e),
+                }
+            }
+            dirs.insert(folder.base.id.unwrap_or_default(), dir);
+        }
+        files.extend(assets);
+
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        for (path, data) in files {
+            writer.start_file(path, options)?;
+            writer.write_all(&data)?;
+        }
+        Ok((format!("{}.zip", root_name), writer.finish()?.into_inner()))
+    }
+}
@@ -0,0 +1,359 @@
+use std::collections::{ BTreeSet, HashMap };
+use std::sync::atomic::{ AtomicBool, AtomicUsize, Ordering };
+use std::sync::{ Arc, Mutex as StdMutex };
+use std::time::Duration;
+
+use anyhow::Error;
+use axum::async_trait;
+use futures::stream::{ BoxStream, StreamExt };
+use serde_json::{ json, Value };
+use tokio::sync::{ broadcast, Mutex, Notify };
+use tokio::task::JoinHandle;
+
+use crate::context::state::AppState;
+use crate::errors::BizError;
+use crate::types::acl::{ AclResource, AclRole };
+use crate::types::board::BoardFormat;
+use crate::types::document::{ Document, DocumentType, SaveDocumentRequest };
+use crate::types::room::{ RoomEvent, RoomMessage };
+use crate::utils::auths::{ AuthUserClaims, SecurityContext };
+use crate::utils::boards;
+use super::acl::AclHandler;
+use super::document::DocumentHandler;
+
+// The channel of the room, which is distributed across the instances if the cache provider is redis.
+const ROOM_CHANNEL_PREFIX: &str = "mywebnote:room:";
+// The capacity of the events of per room, which are dropped for the lagged clients.
+const ROOM_EVENT_CAPACITY: usize = 1024;
+
+#[async_trait]
+pub trait IRoomHandler: Send {
+    // Authorize the current user to join the room of the board, which is done before the websocket upgraded,
+    // so that the failures are responded as the status codes.
+    async fn authorize(&self, document_id: i64) -> Result<RoomTicket, Error>;
+
+    // Join the room of the board, which is opened with the persisted scene if absent on this instance.
+    async fn join(&self, ticket: RoomTicket) -> Result<RoomSession, Error>;
+
+    // Relay the message of the client to the room on all the instances.
+    async fn relay(&self, session: &RoomSession, message: RoomMessage) -> Result<(), Error>;
+
+    // Leave the room, which is persisted and closed if the last client of this instance left.
+    async fn leave(&self, session: RoomSession) -> Result<(), Error>;
+}
+
+// The rooms of the boards being edited on this instance.
+#[derive(Default)]
+pub struct RoomRegistry {
+    rooms: Mutex<HashMap<i64, Arc<Room>>>,
+}
+
+struct Room {
+    document_id: i64,
+    // The claims of the owner of the board, which the scene is persisted as.
+    owner: AuthUserClaims,
+    scene: StdMutex<RoomScene>,
+    events: broadcast::Sender<RoomEvent>,
+    clients: AtomicUsize,
+    tasks: StdMutex<Vec<JoinHandle<()>>>,
+    // Whether the last client has left, the room is kept in the registry until persisted, so that the
+    // joining waits for it rather than opens the earlier scene.
+    closing: AtomicBool,
+    closed: Notify,
+}
+
+impl Room {
+    fn stop(&self) {
+        for task in self.tasks.lock().unwrap().drain(..) {
+            task.abort();
+        }
+    }
+}
+
+struct RoomScene {
+    value: Value,
+    // The version of the document persisted, for the optimistic locking.
+    version: i64,
+    // The counts of the merged and the persisted changes, the scene is changed if they are different.
+    changes: u64,
+    persisted: u64,
+    // The users whose updates are merged, the blobs of the images uploaded by them could be referenced.
+    editors: BTreeSet<i64>,
+}
+
+// The user authorized to join the room.
+pub struct RoomTicket {
+    document_id: i64,
+    uid: i64,
+    editable: bool,
+    // The claims of the owner of the board.
+    owner: AuthUserClaims,
+}
+
+// The client joined the room.
+pub struct RoomSession {
+    pub id: String,
+    pub uid: i64,
+    // Whether the client could update the scene, the viewers only receive the updates and the cursors.
+    pub editable: bool,
+    // The merged scene when joined.
+    pub scene: Value,
+    pub events: broadcast::Receiver<RoomEvent>,
+    room: Arc<Room>,
+}
+
+pub struct RoomHandler<'a> {
+    state: &'a AppState,
+}
+
+impl<'a> RoomHandler<'a> {
+    pub fn new(state: &'a AppState) -> Self {
+        Self { state }
+    }
+
+    // Open the room with the persisted scene, and the messages of the room are subscribed before any
+    // client joined, so that none is missed.
+    async fn open(&self, document_id: i64, owner: AuthUserClaims) -> Result<Arc<Room>, Error> {
+        let selected = async {
+            let repo = self.state.document_repo.lock().await;
+            repo.get(&self.state.config).select_by_id(document_id).await
+        };
+        let document = SecurityContext::scope(Some(owner.clone()), selected).await?;
+        if document.doc_type != Some(DocumentType::Board) {
+            return Err(BizError::BadRequest("The collaboration room is only for the boards".to_string()).into());
+        }
+        let room = Arc::new(Room {
+            document_id,
+            owner,
+            scene: StdMutex::new(RoomScene {
+                value: to_scene(&document)?,
+                version: document.base.version.unwrap_or_default(),
+                changes: 0,
+                persisted: 0,
+                editors: BTreeSet::new(),
+            }),
+            events: broadcast::channel(ROOM_EVENT_CAPACITY).0,
+            clients: AtomicUsize::new(0),
+            tasks: StdMutex::new(Vec::new()),
+            closing: AtomicBool::new(false),
+            closed: Notify::new(),
+        });
+
+        let cache = self.state.string_cache.get(&self.state.config);
+        let messages = cache.subscribe(to_channel(document_id)).await?;
+        let receiving = tokio::spawn(receive(room.clone(), messages));
+        let persisting = tokio::spawn(persist_periodically(self.state.clone(), room.clone()));
+        room.tasks.lock().unwrap().extend([receiving, persisting]);
+        Ok(room)
+    }
+
+    async fn publish(&self, session: &RoomSession, message: RoomMessage) -> Result<(), Error> {
+        let event = RoomEvent { sender: session.id.to_owned(), uid: session.uid, message };
+        let cache = self.state.string_cache.get(&self.state.config);
+        cache.publish(to_channel(session.room.document_id), serde_json::to_string(&event)?).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<'a> IRoomHandler for RoomHandler<'a> {
+    async fn authorize(&self, document_id: i64) -> Result<RoomTicket, Error> {
+        let claims = SecurityContext::get().ok_or(BizError::Unauthenticated)?;
+        // The viewers of the shared board could also join, but not update.
+        let acls = AclHandler::new(self.state);
+        let (authorized, editable) = match acls.authorize(AclResource::Document, document_id, AclRole::Editor).await {
+            Ok(authorized) => (authorized, true),
+            Err(e) if matches!(e.downcast_ref::<BizError>(), Some(BizError::Forbidden(_))) => {
+                (acls.authorize(AclResource::Document, document_id, AclRole::Viewer).await?, false)
+            }
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        let owner = authorized.unwrap_or_else(|| claims.clone());
+        let selected = async {
+            let repo = self.state.document_repo.lock().await;
+            repo.get(&self.state.config).select_by_id(document_id).await
+        };
+        let document = SecurityContext::scope(Some(owner.clone()), selected).await?;
+        if document.doc_type != Some(DocumentType::Board) {
+            return Err(BizError::BadRequest("The collaboration room is only for the boards".to_string()).into());
+        }
+        Ok(RoomTicket { document_id, uid: claims.uid, editable, owner })
+    }
+
+    async fn join(&self, ticket: RoomTicket) -> Result<RoomSession, Error> {
+        // The client is counted within the registry lock, so that the room is not closed by the leaving meanwhile.
+        let room = loop {
+            let rooms = self.state.rooms.rooms.lock().await;
+            if let Some(room) = rooms.get(&ticket.document_id).cloned() {
+                if !room.closing.load(Ordering::SeqCst) {
+                    room.clients.fetch_add(1, Ordering::SeqCst);
+                    break room;
+                }
+                // The closing room is opened again after persisted, so that the latest scene is loaded.
+                let closed = room.closed.notified();
+                drop(rooms);
+                closed.await;
+                continue;
+            }
+            drop(rooms);
+
+            // Opened outside the registry lock, so that the other rooms are not blocked by the loading.
+            let opened = self.open(ticket.document_id, ticket.owner.clone()).await?;
+            let mut rooms = self.state.rooms.rooms.lock().await;
+            let room = rooms.entry(ticket.document_id).or_insert_with(|| opened.clone()).clone();
+            if Arc::ptr_eq(&room, &opened) {
+                room.clients.fetch_add(1, Ordering::SeqCst);
+                break room;
+            }
+            // The room is opened by the other joining meanwhile.
+            opened.stop();
+        };
+        // Subscribed before the scene is cloned, so that no update is missed, and the updates merged twice
+        // are harmless.
+        let events = room.events.subscribe();
+        let scene = room.scene.lock().unwrap().value.clone();
+        let session = RoomSession {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            uid: ticket.uid,
+            editable: ticket.editable,
+            scene,
+            events,
+            room,
+        };
+        self.publish(&session, RoomMessage::Joined).await?;
+        Ok(session)
+    }
+
+    async fn relay(&self, session: &RoomSession, message: RoomMessage) -> Result<(), Error> {
+        if !message.is_acceptable() {
+            return Err(BizError::BadRequest("Only the scene updates and the cursors are accepted".to_string()).into());
+        }
+        if !session.editable && matches!(message, RoomMessage::SceneUpdate { .. }) {
+            return Err(BizError::Forbidden("The viewer could not update the board".to_string()).into());
+        }
+        self.publish(session, message).await
+    }
+
+    async fn leave(&self, session: RoomSession) -> Result<(), Error> {
+        let published = self.publish(&session, RoomMessage::Left).await;
+        let room = session.room;
+        {
+            let _rooms = self.state.rooms.rooms.lock().await;
+            if room.clients.fetch_sub(1, Ordering::SeqCst) > 1 {
+                return published;
+            }
+            room.closing.store(true, Ordering::SeqCst);
+        }
+        // Persisted after the registry unlocked, so that the other rooms are not blocked, and the room is
+        // removed when finished, so that the joining meanwhile waits for the latest scene.
+        room.stop();
+        let persisted = persist(self.state, &room).await;
+        {
+            let mut rooms = self.state.rooms.rooms.lock().await;
+            rooms.remove(&room.document_id);
+            room.closed.notify_waiters();
+        }
+        persisted?;
+        published
+    }
+}
+
+// Receive the messages of the room from all the instances, the scene updates are merged and only the
+// accepted elements are relayed to the clients.
+async fn receive(room: Arc<Room>, mut messages: BoxStream<'static, String>) {
+    while let Some(message) = messages.next().await {
+        let mut event: RoomEvent = match serde_json::from_str(&message) {
+            Ok(event) => event,
+            Err(e) => {
+                tracing::warn!("Invalid the message of the room: {}. reason: {}", room.document_id, e);
+                continue;
+            }
+        };
+        if let RoomMessage::SceneUpdate { elements } = &mut event.message {
+            let mut scene = room.scene.lock().unwrap();
+            *elements = boards::reconcile_elements(&mut scene.value, std::mem::take(elements));
+            if elements.is_empty() {
+                continue;
+            }
+            scene.changes += 1;
+            scene.editors.insert(event.uid);
+        }
+        // None is receiving if all the clients of this instance have left.
+        let _ = room.events.send(event);
+    }
+}
+
+async fn persist_periodically(state: AppState, room: Arc<Room>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(state.config.webnote.room.persist_interval.max(1)));
+    // The first tick completes immediately.
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        if let Err(e) = persist(&state, &room).await {
+            tracing::warn!("Failed to persist the scene of the room: {}. reason: {}", room.document_id, e);
+        }
+    }
+}
+
+// Persist the changed scene as the owner. On the conflict, such as saved by the others outside the room,
+// the persisted scene is merged and the changes are persisted by the next.
+async fn persist(state: &AppState, room: &Room) -> Result<(), Error> {
+    let (content, version, changes, editors) = {
+        let scene = room.scene.lock().unwrap();
+        if scene.changes == scene.persisted {
+            return Ok(());
+        }
+        (scene.value.to_string(), scene.version, scene.changes, scene.editors.iter().copied().collect::<Vec<_>>())
+    };
+    let param = SaveDocumentRequest {
+        id: Some(room.document_id),
+        key: None,
+        name: None,
+        folder_key: None,
+        doc_type: None,
+        content: Some(content),
+        version: Some(version),
+    };
+    let documents = DocumentHandler::new(state);
+    let saved = SecurityContext::scope(Some(room.owner.clone()), documents.save_as(param, &editors)).await;
+    match saved {
+        Ok((_, version)) => {
+            let mut scene = room.scene.lock().unwrap();
+            scene.version = version;
+            scene.persisted = changes;
+            Ok(())
+        }
+        Err(e) if matches!(e.downcast_ref::<BizError>(), Some(BizError::Conflict(..))) => {
+            let selected = async {
+                let repo = state.document_repo.lock().await;
+                repo.get(&state.config).select_by_id(room.document_id).await
+            };
+            let document = SecurityContext::scope(Some(room.owner.clone()), selected).await?;
+            let stored = to_scene(&document)?["elements"].as_array().cloned().unwrap_or_default();
+            let accepted = {
+                let mut scene = room.scene.lock().unwrap();
+                scene.version = document.base.version.unwrap_or_default();
+                boards::reconcile_elements(&mut scene.value, stored)
+            };
+            if !accepted.is_empty() {
+                let message = RoomMessage::SceneUpdate { elements: accepted };
+                let _ = room.events.send(RoomEvent { sender: String::new(), uid: room.owner.uid, message });
+            }
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn to_scene(document: &Document) -> Result<Value, Error> {
+    match document.content.as_deref().filter(|content| !content.trim().is_empty()) {
+        Some(content) => boards::to_scene(content.as_bytes(), Some(BoardFormat::Excalidraw)),
+        None => boards::normalize_excalidraw(json!({})),
+    }
+}
+
+fn to_channel(document_id: i64) -> String {
+    format!("{}{}", ROOM_CHANNEL_PREFIX, document_id)
+}
@@ -3,6 +3,8 @@ use std::sync::Arc;
 use anyhow::{ Error, Ok };
 use axum::async_trait;
 use crate::context::state::AppState;
+use crate::errors::BizError;
+use crate::types::event::{ ChangeAction, ChangeEvent, ChangeResource };
 use crate::types::settings::{
     DeleteSettingsRequest,
     QuerySettingsRequest,
@@ -10,6 +12,7 @@ use crate::types::settings::{
     Settings,
 };
 use crate::types::{ PageRequest, PageResponse };
+use super::event::EventHandler;
 
 #[async_trait]
 pub trait ISettingsHandler: Send {
@@ -42,7 +45,7 @@ impl<'a> ISettingsHandler for SettingsHandler<'a> {
         let param = QuerySettingsRequest {
             name,
         };
-        let res = self.find(param, PageRequest::default()).await.unwrap().1;
+        let res = self.find(param, PageRequest::default()).await?.1;
         if res.len() > 0 {
             let settings = Arc::new(res.get(0).unwrap().clone());
             return Ok(Some(settings));
@@ -62,15 +65,31 @@ impl<'a> ISettingsHandler for SettingsHandler<'a> {
 
     async fn save(&self, param: SaveSettingsRequest) -> Result<i64, Error> {
         let repo = self.state.settings_repo.lock().await;
-        if param.id.is_some() {
-            repo.get(&self.state.config).update(param.to_settings()).await
+        let (settings_id, action) = if param.id.is_some() {
+            // The update is scoped to the current owner, nothing matched means not found or not owned.
+            let updated_id = repo.get(&self.state.config).update(param.to_settings()).await?;
+            if updated_id < 0 {
+                return Err(BizError::NotFound("settings".to_string()).into());
+            }
+            (updated_id, ChangeAction::Updated)
         } else {
-            repo.get(&self.state.config).insert(param.to_settings()).await
-        }
+            (repo.get(&self.state.config).insert(param.to_settings()).await?, ChangeAction::Created)
+        };
+        drop(repo);
+        let event = ChangeEvent::new(ChangeResource::Settings, action, settings_id, param.name, None);
+        EventHandler::new(self.state).publish(event).await;
+        Ok(settings_id)
     }
 
     async fn delete(&self, param: DeleteSettingsRequest) -> Result<u64, Error> {
         let repo = self.state.settings_repo.lock().await;
-        repo.get(&self.state.config).delete_by_id(param.id).await
+        let deleted = repo.get(&self.state.config).delete_by_id(param.id).await?;
+        if deleted == 0 {
+            return Err(BizError::NotFound("settings".to_string()).into());
+        }
+        drop(repo);
+        let event = ChangeEvent::new(ChangeResource::Settings, ChangeAction::Deleted, param.id, None, None);
+        EventHandler::new(self.state).publish(event).await;
+        Ok(deleted)
     }
 }
@@ -0,0 +1,188 @@
+use anyhow::Error;
+use axum::async_trait;
+use chrono::Utc;
+use sha2::Sha256;
+
+use crate::context::state::AppState;
+use crate::errors::BizError;
+use crate::store;
+use crate::types::document::Document;
+use crate::types::share::{
+    CreateShareLinkRequest,
+    QueryShareLinkRequest,
+    RevokeShareLinkRequest,
+    ShareLink,
+    ShareLinkView,
+};
+use crate::types::PageRequest;
+use crate::utils::auths::{ self, AuthUserClaims, SecurityContext };
+use crate::utils::types::GenericValue;
+
+// The mismatched passwords of per link are counted within the window, over the max of which the link
+// is locked until the next window.
+const PASSWORD_FAILURES_PREFIX: &str = "mywebnote:share:failures:";
+// The passwords are derived by the PBKDF2-SHA256, which the iterations are stored with the hashed.
+const PASSWORD_SCHEME: &str = "pbkdf2-sha256";
+
+#[async_trait]
+pub trait IShareHandler: Send {
+    // The share links of the owner, latest created first.
+    async fn find(&self, param: QueryShareLinkRequest) -> Result<Vec<ShareLinkView>, Error>;
+
+    // Create the share link of the document, returns the id and the token of the link.
+    async fn create(&self, param: CreateShareLinkRequest) -> Result<(i64, String), Error>;
+
+    // Revoke the share link, which is no longer accessible.
+    async fn revoke(&self, param: RevokeShareLinkRequest) -> Result<u64, Error>;
+
+    // Access the document of the public link without an account, and the access is recorded.
+    async fn access(&self, token: String, password: Option<String>) -> Result<Document, Error>;
+}
+
+pub struct ShareHandler<'a> {
+    state: &'a AppState,
+}
+
+impl<'a> ShareHandler<'a> {
+    pub fn new(state: &'a AppState) -> Self {
+        Self { state }
+    }
+
+    // Delete all the share links of the document, such as the document is purged.
+    pub(crate) async fn delete_by_document(&self, document_id: i64) -> Result<u64, Error> {
+        let param = ShareLink::with(Some(document_id), None);
+        let links = store::select_all(&self.state.share_link_repo, &self.state.config, param, false).await?;
+        let repo = self.state.share_link_repo.lock().await;
+        let mut deleted = 0;
+        for link in links {
+            deleted += repo.get(&self.state.config).delete_by_id(link.base.id.unwrap_or_default()).await?;
+        }
+        Ok(deleted)
+    }
+
+    async fn select_by_token(&self, token: String) -> Result<ShareLink, Error> {
+        let repo = self.state.share_link_repo.lock().await;
+        let param = ShareLink::with(None, Some(token));
+        let (_, links) = repo.get(&self.state.config).select_unscoped(param, PageRequest::new(1, 1)).await?;
+        links.into_iter().next().ok_or_else(|| BizError::NotFound("share link".to_string()).into())
+    }
+
+    // Select the document and record the access as the owner of the link, since the public
+    // request is anonymous.
+    async fn access_as_owner(&self, link: ShareLink) -> Result<Document, Error> {
+        let document = {
+            let repo = self.state.document_repo.lock().await;
+            repo.get(&self.state.config).select_by_id(link.document_id.unwrap_or_default()).await?
+        };
+        // Increased by the store, so that the concurrent accesses are all counted.
+        let fields = vec![("last_access_time".to_string(), GenericValue::Int64(Utc::now().timestamp_millis()))];
+        let repo = self.state.share_link_repo.lock().await;
+        repo.get(&self.state.config).increase_field(link.base.id.unwrap_or_default(), "access_count", fields).await?;
+        Ok(document)
+    }
+
+    // Verify the password of the link, which is rejected without deriving if too many mismatched recently.
+    async fn verify(&self, link: &ShareLink, hashed: String, password: Option<String>) -> Result<(), Error> {
+        let share = &self.state.config.webnote.share;
+        let cache = self.state.string_cache.get(&self.state.config);
+        let window = Utc::now().timestamp_millis() / share.password_failures_window;
+        let key = format!("{}{}:{}", PASSWORD_FAILURES_PREFIX, link.base.id.unwrap_or_default(), window);
+        let failures = cache.get(key.to_owned()).await?.and_then(|v| v.parse::<i64>().ok()).unwrap_or_default();
+        if failures >= share.max_password_failures {
+            return Err(BizError::TooManyRequests("the password of the share link".to_string()).into());
+        }
+        // The missing password is asked by the client, which isn't counted as the failure.
+        let Some(password) = password else {
+            return Err(BizError::Unauthenticated.into());
+        };
+        if !tokio::task::spawn_blocking(move || verify_password(&password, &hashed)).await? {
+            cache.incr(key, Some(share.password_failures_window)).await?;
+            return Err(BizError::Unauthenticated.into());
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<'a> IShareHandler for ShareHandler<'a> {
+    async fn find(&self, param: QueryShareLinkRequest) -> Result<Vec<ShareLinkView>, Error> {
+        let param = ShareLink::with(param.document_id, None);
+        let links = store::select_all(&self.state.share_link_repo, &self.state.config, param, false).await?;
+        Ok(links.into_iter().map(ShareLinkView::from).collect())
+    }
+
+    async fn create(&self, param: CreateShareLinkRequest) -> Result<(i64, String), Error> {
+        if param.expire_time.is_some_and(|time| time <= Utc::now().timestamp_millis()) {
+            return Err(BizError::BadRequest("The expire time of the share link is passed".to_string()).into());
+        }
+        {
+            let repo = self.state.document_repo.lock().await;
+            repo.get(&self.state.config).select_by_id(param.document_id).await?;
+        }
+
+        let token = new_token();
+        let rounds = self.state.config.webnote.share.password_rounds;
+        let password = match param.password {
+            Some(password) => Some(tokio::task::spawn_blocking(move || hash_password(&password, rounds)).await?),
+            None => None,
+        };
+        let link = ShareLink::new(param.document_id, token.to_owned(), password, param.expire_time);
+        let repo = self.state.share_link_repo.lock().await;
+        let id = repo.get(&self.state.config).insert(link).await?;
+        Ok((id, token))
+    }
+
+    async fn revoke(&self, param: RevokeShareLinkRequest) -> Result<u64, Error> {
+        let repo = self.state.share_link_repo.lock().await;
+        let revoked = repo.get(&self.state.config).delete_by_id(param.id).await?;
+        if revoked == 0 {
+            return Err(BizError::NotFound("share link".to_string()).into());
+        }
+        Ok(revoked)
+    }
+
+    async fn access(&self, token: String, password: Option<String>) -> Result<Document, Error> {
+        let link = self.select_by_token(token).await?;
+        // The expired is the same as the revoked, so that the existence isn't leaked.
+        if link.expire_time.is_some_and(|time| time <= Utc::now().timestamp_millis()) {
+            return Err(BizError::NotFound("share link".to_string()).into());
+        }
+        if let Some(hashed) = link.password.to_owned().filter(|p| !p.is_empty()) {
+            self.verify(&link, hashed, password).await?;
+        }
+
+        let claims = AuthUserClaims::system(link.owner_uid.unwrap_or_default());
+        SecurityContext::scope(Some(claims), self.access_as_owner(link)).await
+    }
+}
+
+// The random token of 256 bits, which is url safe.
+fn new_token() -> String {
+    format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple())
+}
+
+fn hash_password(password: &str, rounds: u32) -> String {
+    let salt = uuid::Uuid::new_v4().simple().to_string();
+    let derived = derive_password(password, &salt, rounds);
+    format!("{}${}${}${}", PASSWORD_SCHEME, rounds, salt, derived)
+}
+
+fn derive_password(password: &str, salt: &str, rounds: u32) -> String {
+    let mut derived = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt.as_bytes(), rounds, &mut derived);
+    hex::encode(derived)
+}
+
+fn verify_password(password: &str, hashed: &str) -> bool {
+    let parts: Vec<&str> = hashed.split('$').collect();
+    let (rounds, salt, digest) = match parts.as_slice() {
+        [PASSWORD_SCHEME, rounds, salt, digest] => (rounds, salt, digest),
+        _ => {
+            return false;
+        }
+    };
+    match rounds.parse::<u32>() {
+        Ok(rounds) => auths::constant_time_eq(derive_password(password, salt, rounds).as_bytes(), digest.as_bytes()),
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Error;
+use axum::async_trait;
+use base64::{ engine::general_purpose::STANDARD, Engine as _ };
+use futures::stream::{ BoxStream, StreamExt };
+use serde::{ Deserialize, Serialize };
+use serde_json::Value;
+use yrs::sync::awareness::AwarenessUpdateEntry;
+use yrs::sync::{ AwarenessUpdate, Message, SyncMessage };
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::{ StateVector, Update };
+
+use crate::context::state::AppState;
+use crate::errors::BizError;
+use crate::store;
+use crate::types::acl::{ AclResource, AclRole };
+use crate::types::document::{ DocumentType, SaveDocumentRequest };
+use crate::types::document_update::DocumentUpdate;
+use crate::types::PageRequest;
+use crate::utils::auths::{ AuthUserClaims, SecurityContext };
+use crate::utils::notes;
+use super::acl::AclHandler;
+use super::document::DocumentHandler;
+
+// The channel of the sync, which is distributed across the instances if the cache provider is redis.
+const SYNC_CHANNEL_PREFIX: &str = "mywebnote:sync:";
+
+#[async_trait]
+pub trait ISyncHandler: Send {
+    // Join the sync of the note, returns the session and the messages of the other clients, which are
+    // subscribed before the updates are loaded, so that none is missed.
+    async fn join(&self, document_id: i64) -> Result<(SyncSession, BoxStream<'static, Vec<u8>>), Error>;
+
+    // Handle the message of the client, and returns the reply to the client if any.
+    async fn handle(&self, session: &mut SyncSession, message: &[u8]) -> Result<Option<Message>, Error>;
+
+    // Leave the sync, the awareness states of the client are removed from the others.
+    async fn leave(&self, session: SyncSession) -> Result<(), Error>;
+}
+
+// The client joined the sync of the note.
+pub struct SyncSession {
+    pub id: String,
+    pub document_id: i64,
+    // Whether the client could update the note, the viewers only receive the updates and the awareness.
+    pub editable: bool,
+    // The state vector of the merged updates when joined, which is sent as the sync step 1.
+    pub state_vector: StateVector,
+    // The user joined, and the claims of the owner of the note, which the updates are persisted as.
+    uid: i64,
+    owner: AuthUserClaims,
+    // The clocks of the awareness clients relayed from the client.
+    awareness: HashMap<u64, u32>,
+}
+
+// The message relayed to the other clients, the sender is the id of the session.
+#[derive(Serialize, Deserialize)]
+struct SyncEvent {
+    sender: String,
+    // The base64 of the encoded message.
+    data: String,
+}
+
+pub struct SyncHandler<'a> {
+    state: &'a AppState,
+}
+
+impl<'a> SyncHandler<'a> {
+    pub fn new(state: &'a AppState) -> Self {
+        Self { state }
+    }
+
+    // Delete all the updates of the document, which is called when the document is purged.
+    pub(crate) async fn delete_by_document(&self, document_id: i64) -> Result<u64, Error> {
+        let param = DocumentUpdate::of_document(document_id);
+        let updates = store::select_all(&self.state.document_update_repo, &self.state.config, param, false).await?;
+        let repo = self.state.document_update_repo.lock().await;
+        let mut deleted = 0;
+        for update in updates {
+            deleted += repo.get(&self.state.config).delete_by_id(update.base.id.unwrap_or_default()).await?;
+        }
+        Ok(deleted)
+    }
+
+    async fn load(&self, session: &SyncSession) -> Result<Vec<DocumentUpdate>, Error> {
+        let param = DocumentUpdate::of_document(session.document_id);
+        let selected = store::select_all(&self.state.document_update_repo, &self.state.config, param, false);
+        SecurityContext::scope(Some(session.owner.clone()), selected).await
+    }
+
+    // Append the update to the log of the note, and the log is compacted if the count reached the threshold.
+    async fn append(&self, session: &SyncSession, update: Vec<u8>) -> Result<(), Error> {
+        let appended = async {
+            let repo = self.state.document_update_repo.lock().await;
+            let repo = repo.get(&self.state.config);
+            repo.insert(DocumentUpdate::new(session.document_id, Some(STANDARD.encode(&update)))).await?;
+            let page = PageRequest { with_total: Some(true), ..PageRequest::new(1, 1) };
+            let (page, _) = repo.select(DocumentUpdate::of_document(session.document_id), page).await?;
+            Ok::<_, Error>(page.total.unwrap_or_default())
+        };
+        let count = SecurityContext::scope(Some(session.owner.clone()), appended).await?;
+        self.publish(session, Message::Sync(SyncMessage::Update(update)).encode_v1()).await?;
+
+        if count as usize >= self.state.config.webnote.sync.compact_threshold.max(2) {
+            self.compact(session).await?;
+        }
+        Ok(())
+    }
+
+    // Compact the log into the one merged update, the updates appended meanwhile are kept, and the
+    // concurrent compactions are harmless since the merging is idempotent.
+    async fn compact(&self, session: &SyncSession) -> Result<(), Error> {
+        let updates = self.load(session).await?;
+        let merged = merge(&updates)?;
+        let compacted = async {
+            let repo = self.state.document_update_repo.lock().await;
+            let repo = repo.get(&self.state.config);
+            repo.insert(DocumentUpdate::new(session.document_id, Some(STANDARD.encode(&merged)))).await?;
+            for update in &updates {
+                repo.delete_by_id(update.base.id.unwrap_or_default()).await?;
+            }
+            Ok::<_, Error>(())
+        };
+        SecurityContext::scope(Some(session.owner.clone()), compacted).await?;
+        tracing::info!("Compacted the {} updates of the note: {}", updates.len(), session.document_id);
+
+        // The content of the note follows the merged, so that the exports, the search and the revisions are
+        // up to date, and the failed (such as saved by the others meanwhile) is written by the next compaction.
+        if let Err(e) = self.write_back(session, &merged).await {
+            tracing::warn!("Failed to write back the note: {}. reason: {}", session.document_id, e);
+        }
+        Ok(())
+    }
+
+    // Save the BlockSuite snapshot of the merged update as the content of the note, the meta of the page
+    // in the content is kept.
+    async fn write_back(&self, session: &SyncSession, merged: &[u8]) -> Result<(), Error> {
+        let selected = async {
+            let repo = self.state.document_repo.lock().await;
+            repo.get(&self.state.config).select_by_id(session.document_id).await
+        };
+        let document = SecurityContext::scope(Some(session.owner.clone()), selected).await?;
+        let mut snapshot = notes::snapshot_of_update(merged)?;
+        let content = document.content.as_deref().and_then(|content| serde_json::from_str::<Value>(content).ok());
+        if let Some(meta) = content.as_ref().and_then(|content| content.get("meta")) {
+            snapshot["meta"] = meta.clone();
+        }
+        let param = SaveDocumentRequest {
+            id: Some(session.document_id),
+            key: None,
+            name: None,
+            folder_key: None,
+            doc_type: None,
+            content: Some(snapshot.to_string()),
+            version: document.base.version,
+        };
+        let editors: Vec<i64> = Some(session.uid).filter(|uid| *uid != session.owner.uid).into_iter().collect();
+        let documents = DocumentHandler::new(self.state);
+        SecurityContext::scope(Some(session.owner.clone()), documents.save_as(param, &editors)).await?;
+        Ok(())
+    }
+
+    async fn publish(&self, session: &SyncSession, data: Vec<u8>) -> Result<(), Error> {
+        let event = SyncEvent { sender: session.id.to_owned(), data: STANDARD.encode(data) };
+        let cache = self.state.string_cache.get(&self.state.config);
+        cache.publish(to_channel(session.document_id), serde_json::to_string(&event)?).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<'a> ISyncHandler for SyncHandler<'a> {
+    async fn join(&self, document_id: i64) -> Result<(SyncSession, BoxStream<'static, Vec<u8>>), Error> {
+        let claims = SecurityContext::get().ok_or(BizError::Unauthenticated)?;
+        // The viewers of the shared note could also join, but not update.
+        let acls = AclHandler::new(self.state);
+        let (authorized, editable) = match acls.authorize(AclResource::Document, document_id, AclRole::Editor).await {
+            Ok(authorized) => (authorized, true),
+            Err(e) if matches!(e.downcast_ref::<BizError>(), Some(BizError::Forbidden(_))) => {
+                (acls.authorize(AclResource::Document, document_id, AclRole::Viewer).await?, false)
+            }
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        let owner = authorized.unwrap_or_else(|| claims.clone());
+        let selected = async {
+            let repo = self.state.document_repo.lock().await;
+            repo.get(&self.state.config).select_by_id(document_id).await
+        };
+        let document = SecurityContext::scope(Some(owner.clone()), selected).await?;
+        if document.doc_type != Some(DocumentType::Note) {
+            return Err(BizError::BadRequest("The yjs sync is only for the notes".to_string()).into());
+        }
+
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let sender = id.to_owned();
+        let cache = self.state.string_cache.get(&self.state.config);
+        let messages = cache
+            .subscribe(to_channel(document_id)).await?
+            .filter_map(move |message| {
+                let event = serde_json::from_str::<SyncEvent>(&message).ok().filter(|event| event.sender != sender);
+                futures::future::ready(event.and_then(|event| STANDARD.decode(event.data).ok()))
+            })
+            .boxed();
+        let mut session = SyncSession {
+            id,
+            document_id,
+            editable,
+            state_vector: StateVector::default(),
+            uid: claims.uid,
+            owner,
+            awareness: HashMap::new(),
+        };
+        let merged = merge(&self.load(&session).await?)?;
+        session.state_vector = StateVector::decode_v1(&yrs::encode_state_vector_from_update_v1(&merged)?)?;
+        // Query the awareness states of the others for the client joined.
+        self.publish(&session, Message::AwarenessQuery.encode_v1()).await?;
+        Ok((session, messages))
+    }
+
+    async fn handle(&self, session: &mut SyncSession, message: &[u8]) -> Result<Option<Message>, Error> {
+        match Message::decode_v1(message).map_err(invalid)? {
+            Message::Sync(SyncMessage::SyncStep1(state_vector)) => {
+                let merged = merge(&self.load(session).await?)?;
+                let missing = yrs::diff_updates_v1(&merged, &state_vector.encode_v1())?;
+                Ok(Some(Message::Sync(SyncMessage::SyncStep2(missing))))
+            }
+            Message::Sync(SyncMessage::SyncStep2(update) | SyncMessage::Update(update)) => {
+                // The empty is answered by every client (including the viewer) for the step 1 of the server.
+                if is_empty_update(&update)? {
+                    return Ok(None);
+                }
+                if !session.editable {
+                    return Err(BizError::Forbidden("The viewer could not update the note".to_string()).into());
+                }
+                self.append(session, update).await?;
+                Ok(None)
+            }
+            Message::Awareness(update) => {
+                for (client, entry) in update.clients {
+                    // The 'null' state is the removed client, see: y-protocols/awareness.js
+                    if &*entry.json == "null" {
+                        session.awareness.remove(&client);
+                    } else {
+                        session.awareness.insert(client, entry.clock);
+                    }
+                }
+                self.publish(session, message.to_vec()).await?;
+                Ok(None)
+            }
+            Message::AwarenessQuery => {
+                self.publish(session, message.to_vec()).await?;
+                Ok(None)
+            }
+            Message::Auth(_) => {
+                Err(BizError::BadRequest("The auth message is only sent by the server".to_string()).into())
+            }
+            Message::Custom(tag, _) => {
+                Err(BizError::BadRequest(format!("Unsupported the message type: {}", tag)).into())
+            }
+        }
+    }
+
+    async fn leave(&self, session: SyncSession) -> Result<(), Error> {
+        let null: Arc<str> = Arc::from("null");
+        let clients: HashMap<_, _> = session.awareness
+            .iter()
+            .map(|(client, clock)| (*client, AwarenessUpdateEntry { clock: clock + 1, json: null.clone() }))
+            .collect();
+        if clients.is_empty() {
+            return Ok(());
+        }
+        self.publish(&session, Message::Awareness(AwarenessUpdate { clients }).encode_v1()).await
+    }
+}
+
+// Merge the logged updates of the note, which are the same regardless of the order.
+fn merge(updates: &[DocumentUpdate]) -> Result<Vec<u8>, Error> {
+    let decoded = updates
+        .iter()
+        .map(|update| STANDARD.decode(update.data.as_deref().unwrap_or_default()))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(yrs::merge_updates_v1(&decoded)?)
+}
+
+// The update of nothing, such as the sync step 2 of the client without the missing, which is not logged.
+fn is_empty_update(update: &[u8]) -> Result<bool, Error> {
+    let update = Update::decode_v1(update).map_err(invalid)?;
+    Ok(update.state_vector().is_empty() && update.delete_set().is_empty())
+}
+
+fn invalid(e: impl std::fmt::Display) -> Error {
+    BizError::BadRequest(format!("The yjs message is invalid. reason: {}", e)).into()
+}
+
+fn to_channel(document_id: i64) -> String {
+    format!("{}{}", SYNC_CHANNEL_PREFIX, document_id)
+}
@@ -0,0 +1,256 @@
+use std::collections::{ BTreeSet, HashMap, HashSet };
+
+use anyhow::Error;
+use axum::async_trait;
+
+use crate::context::state::AppState;
+use crate::errors::BizError;
+use crate::store;
+use crate::types::document::QueryDocumentRequest;
+use crate::types::tag::{
+    DeleteTagRequest,
+    DocumentTag,
+    MergeTagRequest,
+    QueryTagRequest,
+    SaveTagRequest,
+    Tag,
+    TagCount,
+    TagDocumentsRequest,
+};
+
+#[async_trait]
+pub trait ITagHandler: Send {
+    // The tags in order of the name, with the count of the tagged documents.
+    async fn find(&self, param: QueryTagRequest) -> Result<Vec<TagCount>, Error>;
+
+    // Create the tag, or rename it if the id present, returns the id of the tag.
+    async fn save(&self, param: SaveTagRequest) -> Result<i64, Error>;
+
+    // Merge the source tags into the target, returns the count of the merged sources.
+    async fn merge(&self, param: MergeTagRequest) -> Result<u64, Error>;
+
+    // Delete the tag, as well as detach it from all the documents.
+    async fn delete(&self, param: DeleteTagRequest) -> Result<u64, Error>;
+
+    // Attach the tags to the documents, returns the count of the newly attached.
+    async fn attach(&self, param: TagDocumentsRequest) -> Result<u64, Error>;
+
+    // Detach the tags from the documents, returns the count of the detached.
+    async fn detach(&self, param: TagDocumentsRequest) -> Result<u64, Error>;
+}
+
+pub struct TagHandler<'a> {
+    state: &'a AppState,
+}
+
+impl<'a> TagHandler<'a> {
+    pub fn new(state: &'a AppState) -> Self {
+        Self { state }
+    }
+
+    async fn get_by_name(&self, name: &str) -> Result<Option<Tag>, Error> {
+        let param = Tag::new(None, Some(name.to_string()));
+        let tags = store::select_all(&self.state.tag_repo, &self.state.config, param, false).await?;
+        Ok(tags.into_iter().next())
+    }
+
+    async fn list_relations(&self, document_id: Option<i64>, tag_id: Option<i64>) -> Result<Vec<DocumentTag>, Error> {
+        let param = DocumentTag::with(document_id, tag_id);
+        store::select_all(&self.state.document_tag_repo, &self.state.config, param, false).await
+    }
+
+    async fn delete_relations(&self, relations: Vec<DocumentTag>) -> Result<u64, Error> {
+        let repo = self.state.document_tag_repo.lock().await;
+        let mut deleted = 0;
+        for relation in relations {
+            deleted += repo.get(&self.state.config).delete_by_id(relation.base.id.unwrap_or_default()).await?;
+        }
+        Ok(deleted)
+    }
+
+    // Check all the tags are owned, so that nothing is changed if any is absent.
+    async fn check_tags(&self, ids: &BTreeSet<i64>) -> Result<(), Error> {
+        let repo = self.state.tag_repo.lock().await;
+        for id in ids {
+            repo.get(&self.state.config).select_by_id(*id).await?;
+        }
+        Ok(())
+    }
+
+    // The ids of the owned and not trashed documents within the ids.
+    async fn select_live_ids(&self, ids: Vec<i64>) -> Result<HashSet<i64>, Error> {
+        if ids.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let param = QueryDocumentRequest {
+            key: None,
+            name: None,
+            folder_key: None,
+            doc_type: None,
+            with_content: None,
+            tags: None,
+            tag_match: None,
+            owner_uid: None,
+        }.to_document();
+        let documents = store::select_all_within(&self.state.document_repo, &self.state.config, ids, param).await?;
+        Ok(documents.into_iter().filter_map(|document| document.base.id).collect())
+    }
+
+    // Detach all the tags from the document, such as the document is purged.
+    pub(crate) async fn detach_document(&self, document_id: i64) -> Result<u64, Error> {
+        let relations = self.list_relations(Some(document_id), None).await?;
+        self.delete_relations(relations).await
+    }
+}
+
+#[async_trait]
+impl<'a> ITagHandler for TagHandler<'a> {
+    async fn find(&self, param: QueryTagRequest) -> Result<Vec<TagCount>, Error> {
+        let param_tag = Tag::new(None, param.name);
+        let mut tags = store::select_all(&self.state.tag_repo, &self.state.config, param_tag, false).await?;
+        if let Some(document_id) = param.document_id {
+            {
+                let repo = self.state.document_repo.lock().await;
+                repo.get(&self.state.config).select_by_id(document_id).await?;
+            }
+            let tag_ids: HashSet<i64> = self
+                .list_relations(Some(document_id), None).await?
+                .into_iter()
+                .filter_map(|relation| relation.tag_id)
+                .collect();
+            tags.retain(|tag| tag.base.id.is_some_and(|id| tag_ids.contains(&id)));
+        }
+
+        // Only the not trashed documents are counted, which is the same as the filtered by the tags.
+        let relations = self.list_relations(None, None).await?;
+        let document_ids: BTreeSet<i64> = relations.iter().filter_map(|relation| relation.document_id).collect();
+        let live_ids = self.select_live_ids(document_ids.into_iter().collect()).await?;
+        let mut counts: HashMap<i64, u64> = HashMap::new();
+        for relation in relations {
+            if relation.document_id.is_some_and(|id| live_ids.contains(&id)) {
+                *counts.entry(relation.tag_id.unwrap_or_default()).or_default() += 1;
+            }
+        }
+
+        tags.sort_by_key(|tag| tag.name.to_owned().unwrap_or_default().to_lowercase());
+        Ok(
+            tags
+                .into_iter()
+                .map(|tag| {
+                    let document_count = counts.get(&tag.base.id.unwrap_or_default()).copied().unwrap_or_default();
+                    TagCount { tag, document_count }
+                })
+                .collect()
+        )
+    }
+
+    async fn save(&self, param: SaveTagRequest) -> Result<i64, Error> {
+        let name = param.name.trim().to_string();
+        if name.is_empty() {
+            return Err(BizError::BadRequest("The tag name is empty".to_string()).into());
+        }
+        if let Some(existing) = self.get_by_name(&name).await? {
+            if existing.base.id != param.id {
+                return Err(BizError::BadRequest(format!("The tag '{}' already exists", name)).into());
+            }
+        }
+
+        let repo = self.state.tag_repo.lock().await;
+        match param.id {
+            Some(id) => {
+                let updated_id = repo.get(&self.state.config).update(Tag::new(Some(id), Some(name))).await?;
+                if updated_id < 0 {
+                    return Err(BizError::NotFound("tag".to_string()).into());
+                }
+                Ok(updated_id)
+            }
+            None => repo.get(&self.state.config).insert(Tag::new(None, Some(name))).await,
+        }
+    }
+
+    async fn merge(&self, param: MergeTagRequest) -> Result<u64, Error> {
+        let mut source_ids: BTreeSet<i64> = param.source_ids.into_iter().collect();
+        source_ids.remove(&param.target_id);
+        self.check_tags(&source_ids).await?;
+        self.check_tags(&BTreeSet::from([param.target_id])).await?;
+
+        let mut tagged: HashSet<i64> = self
+            .list_relations(None, Some(param.target_id)).await?
+            .into_iter()
+            .filter_map(|relation| relation.document_id)
+            .collect();
+        let mut merged = 0;
+        for source_id in source_ids {
+            let relations = self.list_relations(None, Some(source_id)).await?;
+            {
+                let repo = self.state.document_tag_repo.lock().await;
+                for relation in &relations {
+                    let document_id = relation.document_id.unwrap_or_default();
+                    if tagged.insert(document_id) {
+                        let relation = DocumentTag::new(document_id, param.target_id);
+                        repo.get(&self.state.config).insert(relation).await?;
+                    }
+                }
+            }
+            self.delete_relations(relations).await?;
+            let repo = self.state.tag_repo.lock().await;
+            merged += repo.get(&self.state.config).delete_by_id(source_id).await?;
+        }
+        Ok(merged)
+    }
+
+    async fn delete(&self, param: DeleteTagRequest) -> Result<u64, Error> {
+        let deleted = {
+            let repo = self.state.tag_repo.lock().await;
+            repo.get(&self.state.config).delete_by_id(param.id).await?
+        };
+        if deleted == 0 {
+            return Err(BizError::NotFound("tag".to_string()).into());
+        }
+        let relations = self.list_relations(None, Some(param.id)).await?;
+        self.delete_relations(relations).await?;
+        Ok(deleted)
+    }
+
+    async fn attach(&self, param: TagDocumentsRequest) -> Result<u64, Error> {
+        let tag_ids: BTreeSet<i64> = param.tag_ids.into_iter().collect();
+        let document_ids: BTreeSet<i64> = param.document_ids.into_iter().collect();
+        self.check_tags(&tag_ids).await?;
+        {
+            let repo = self.state.document_repo.lock().await;
+            for id in &document_ids {
+                repo.get(&self.state.config).select_by_id(*id).await?;
+            }
+        }
+
+        let mut attached = 0;
+        for document_id in document_ids {
+            let existing: HashSet<i64> = self
+                .list_relations(Some(document_id), None).await?
+                .into_iter()
+                .filter_map(|relation| relation.tag_id)
+                .collect();
+            let repo = self.state.document_tag_repo.lock().await;
+            for tag_id in tag_ids.iter().filter(|id| !existing.contains(id)) {
+                repo.get(&self.state.config).insert(DocumentTag::new(document_id, *tag_id)).await?;
+                attached += 1;
+            }
+        }
+        Ok(attached)
+    }
+
+    async fn detach(&self, param: TagDocumentsRequest) -> Result<u64, Error> {
+        let tag_ids: HashSet<i64> = param.tag_ids.into_iter().collect();
+        let document_ids: BTreeSet<i64> = param.document_ids.into_iter().collect();
+        let mut detached = 0;
+        for document_id in document_ids {
+            let relations = self
+                .list_relations(Some(document_id), None).await?
+                .into_iter()
+                .filter(|relation| relation.tag_id.is_some_and(|id| tag_ids.contains(&id)))
+                .collect();
+            detached += self.delete_relations(relations).await?;
+        }
+        Ok(detached)
+    }
+}
@@ -0,0 +1,127 @@
+use anyhow::Error;
+use axum::async_trait;
+use base64::{ engine::general_purpose::STANDARD, Engine as _ };
+
+use crate::context::state::AppState;
+use crate::errors::BizError;
+use crate::types::board::BoardFormat;
+use crate::types::document::{ Document, DocumentType, ThumbnailFormat };
+use crate::utils::{ boards, thumbnails };
+
+pub const THUMBNAIL_KEY_PREFIX: &str = "thumbnail:";
+
+#[async_trait]
+pub trait IThumbnailHandler: Send {
+    // Get the thumbnail of the board, which is rendered if not cached (such as expired), returns the
+    // version of the board and the content.
+    async fn get(&self, id: i64, format: ThumbnailFormat) -> Result<(i64, Vec<u8>), Error>;
+}
+
+pub struct ThumbnailHandler<'a> {
+    state: &'a AppState,
+}
+
+impl<'a> ThumbnailHandler<'a> {
+    pub fn new(state: &'a AppState) -> Self {
+        Self { state }
+    }
+
+    // Render the thumbnails of the saved board in the background, the others are ignored. The board is
+    // selected before spawning, since the security context of the owner isn't inherited by the task.
+    pub async fn render_in_background(&self, id: i64) {
+        let document = {
+            let repo = self.state.document_repo.lock().await;
+            repo.get(&self.state.config).select_by_id(id).await
+        };
+        let document = match document {
+            Ok(document) if document.doc_type == Some(DocumentType::Board) => document,
+            Ok(_) => {
+                return;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to select the board of the thumbnails: {}. reason: {}", id, e);
+                return;
+            }
+        };
+        let state = self.state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = render(&state, &document, &ThumbnailFormat::ALL).await {
+                tracing::warn!("Failed to render the thumbnails of the board: {}. reason: {}", id, e);
+                return;
+            }
+            // The thumbnails of the previous version are stale.
+            let version = document.base.version.unwrap_or_default();
+            let cache = state.string_cache.get(&state.config);
+            for format in ThumbnailFormat::ALL {
+                let _ = cache.del(to_cache_key(id, version - 1, format)).await;
+            }
+        });
+    }
+}
+
+fn to_cache_key(id: i64, version: i64, format: ThumbnailFormat) -> String {
+    format!("{}{}:{}:{}", THUMBNAIL_KEY_PREFIX, id, version, format.extension())
+}
+
+// Render the thumbnails of the board in the formats and cache them by the version, the PNG is cached as
+// the base64 since the cache only holds the strings.
+async fn render(state: &AppState, document: &Document, formats: &[ThumbnailFormat]) -> Result<Vec<Vec<u8>>, Error> {
+    let content = document.content.as_deref().filter(|c| !c.is_empty()).unwrap_or("{}");
+    let scene = boards::to_scene(content.as_bytes(), Some(BoardFormat::Excalidraw))?;
+    let svg = thumbnails::to_svg(&scene);
+
+    let config = &state.config.webnote.thumbnail;
+    let cache = state.string_cache.get(&state.config);
+    let (id, version) = (document.base.id.unwrap_or_default(), document.base.version.unwrap_or_default());
+    let mut rendered = Vec::with_capacity(formats.len());
+    for format in formats {
+        let (data, value) = match format {
+            ThumbnailFormat::Svg => (svg.clone().into_bytes(), svg.clone()),
+            ThumbnailFormat::Png => {
+                // The rasterization is CPU bound, which shouldn't block the workers of the runtime.
+                let (svg, width, height) = (svg.clone(), config.max_width, config.max_height);
+                let png = tokio::task::spawn_blocking(move || thumbnails::to_png(&svg, width, height)).await??;
+                let value = STANDARD.encode(&png);
+                (png, value)
+            }
+        };
+        let key = to_cache_key(id, version, *format);
+        if let Err(e) = cache.set(key.to_owned(), value, Some(config.ttl as i32)).await {
+            tracing::warn!("Failed to cache the thumbnail: {}. reason: {}", key, e);
+        }
+        rendered.push(data);
+    }
+    Ok(rendered)
+}
+
+#[async_trait]
+impl<'a> IThumbnailHandler for ThumbnailHandler<'a> {
+    async fn get(&self, id: i64, format: ThumbnailFormat) -> Result<(i64, Vec<u8>), Error> {
+        let document = {
+            let repo = self.state.document_repo.lock().await;
+            repo.get(&self.state.config).select_by_id(id).await?
+        };
+        if document.doc_type != Some(DocumentType::Board) {
+            return Err(BizError::BadRequest("The document is not a board".to_string()).into());
+        }
+        let version = document.base.version.unwrap_or_default();
+
+        let cache = self.state.string_cache.get(&self.state.config);
+        let cached = match cache.get(to_cache_key(id, version, format)).await {
+            Ok(cached) => cached,
+            Err(e) => {
+                tracing::warn!("Failed to get the cached thumbnail of the board: {}. reason: {}", id, e);
+                None
+            }
+        };
+        let data = match (cached, format) {
+            (Some(svg), ThumbnailFormat::Svg) => Some(svg.into_bytes()),
+            (Some(png), ThumbnailFormat::Png) => STANDARD.decode(png).ok(),
+            (None, _) => None,
+        };
+        match data {
+            Some(data) => Ok((version, data)),
+            None => Ok((version, render(self.state, &document, &[format]).await?.remove(0))),
+        }
+    }
+}
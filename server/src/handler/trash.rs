@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use anyhow::Error;
+use chrono::Utc;
+use crate::context::state::AppState;
+use crate::types::document::PurgeDocumentRequest;
+use crate::types::folder::PurgeFolderRequest;
+use crate::utils::auths::{ AuthUserClaims, SecurityContext };
+use super::document::{ DocumentHandler, IDocumentHandler };
+use super::folder::{ FolderHandler, IFolderHandler };
+
+// The max count of per kind of the expired trashed data to purge at a round.
+const PURGE_BATCH_SIZE: u32 = 1000;
+
+// Start the background task of periodically purging the expired trashed documents and folders.
+pub fn start_purge_task(state: AppState) {
+    let interval = state.config.webnote.trash.purge_interval.max(1);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval));
+        loop {
+            ticker.tick().await;
+            match purge_expired(&state).await {
+                Ok(purged) => tracing::info!("Purged the expired trash count: {}", purged),
+                Err(e) => tracing::warn!("Failed to purge the expired trash. reason: {}", e),
+            }
+        }
+    });
+}
+
+// Permanently delete the trashed documents and folders which deleted before the retention days,
+// each of which is purged as the owner of it.
+pub async fn purge_expired(state: &AppState) -> Result<u64, Error> {
+    let retention_days = state.config.webnote.trash.retention_days as i64;
+    let expired_before = Utc::now().timestamp_millis() - retention_days * 86_400_000;
+
+    let mut purged = 0;
+    let folders = {
+        let repo = state.folder_repo.lock().await;
+        repo.get(&state.config).select_trash_before(expired_before, PURGE_BATCH_SIZE).await?
+    };
+    for folder in folders {
+        let param = PurgeFolderRequest { id: folder.base.id.unwrap_or_default() };
+        let claims = AuthUserClaims::system(folder.owner_uid.unwrap_or_default());
+        // The sub folder may have been purged together with the parent, then it's not found.
+        let result = SecurityContext::scope(Some(claims), FolderHandler::new(state).purge(param)).await;
+        purged += result.unwrap_or_else(|e| {
+            tracing::debug!("Skip purge the expired folder. reason: {}", e);
+            0
+        });
+    }
+
+    let documents = {
+        let repo = state.document_repo.lock().await;
+        repo.get(&state.config).select_trash_before(expired_before, PURGE_BATCH_SIZE).await?
+    };
+    for document in documents {
+        let param = PurgeDocumentRequest { id: document.base.id.unwrap_or_default() };
+        let claims = AuthUserClaims::system(document.owner_uid.unwrap_or_default());
+        let result = SecurityContext::scope(Some(claims), DocumentHandler::new(state).purge(param)).await;
+        purged += result.unwrap_or_else(|e| {
+            tracing::debug!("Skip purge the expired document. reason: {}", e);
+            0
+        });
+    }
+    Ok(purged)
+}
@@ -124,6 +124,44 @@ impl HealthChecker for MongoChecker {
     }
 }
 
+#[derive(Clone, Debug)]
+pub(crate) struct PostgresChecker {}
+
+impl PostgresChecker {
+    pub fn new() -> Self {
+        PostgresChecker {}
+    }
+
+    async fn is_postgres_connected(&self, state: &AppState) -> bool {
+        match &state.config.db.db_type {
+            DbType::Postgres => {
+                let repo = state.user_repo.lock().await;
+                match
+                    repo.get(&state.config).select(User::default(), PageRequest::default()).await
+                {
+                    Ok(_) => true,
+                    Err(e) => {
+                        tracing::error!("Postgres connection check failed: {}", e);
+                        false
+                    }
+                }
+            }
+            _ => true, // If not enabled, it is considered healthy.
+        }
+    }
+}
+
+#[async_trait]
+impl HealthChecker for PostgresChecker {
+    async fn check(&self, state: &AppState) -> HealthCheckResult {
+        let status = if self.is_postgres_connected(state).await { "UP" } else { "DOWN" };
+        HealthCheckResult {
+            status: "postgres".to_string(),
+            details: HashMap::from([("postgres".to_string(), status.to_string())]),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct RedisClusterChecker {}
 
@@ -185,6 +223,12 @@ async fn handle_healthz(State(state): State<AppState>) -> impl IntoResponse {
         result.status = "DOWN".to_string();
     }
 
+    let postgres_check = PostgresChecker::new().check(&state).await;
+    result.details.extend(postgres_check.details);
+    if postgres_check.status == "DOWN" {
+        result.status = "DOWN".to_string();
+    }
+
     let redis_cluster_check = RedisClusterChecker::new().check(&state).await;
     result.details.extend(redis_cluster_check.details);
     if redis_cluster_check.status == "DOWN" {
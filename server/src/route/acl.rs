@@ -0,0 +1,128 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+use axum::{
+    extract::{ Json, Query, State },
+    response::IntoResponse,
+    routing::{ get, post },
+    Router,
+};
+
+use crate::{
+    context::state::AppState,
+    errors,
+    handler::acl::{ AclHandler, IAclHandler },
+    types::acl::{
+        GrantAclRequest,
+        GrantAclResponse,
+        QueryAclRequest,
+        QueryAclResponse,
+        QuerySharedResponse,
+        RevokeAclRequest,
+        RevokeAclResponse,
+    },
+};
+
+use super::ValidatedJson;
+
+pub fn init() -> Router<AppState> {
+    Router::new()
+        .route("/modules/acl/query", get(handle_query_acls))
+        .route("/modules/acl/grant", post(handle_grant_acl))
+        .route("/modules/acl/revoke", post(handle_revoke_acl))
+        .route("/modules/acl/shared", get(handle_query_shared))
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/acl/query",
+    params(QueryAclRequest),
+    responses((status = 200, description = "Getting for the granted acls.", body = QueryAclResponse)),
+    tag = "Acl"
+)]
+pub async fn handle_query_acls(
+    State(state): State<AppState>,
+    Query(param): Query<QueryAclRequest>
+) -> impl IntoResponse {
+    match get_acl_handler(&state).find(param).await {
+        Ok(data) => Ok(Json(QueryAclResponse::new(data))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/modules/acl/grant",
+    request_body = GrantAclRequest,
+    responses(
+        (status = 200, description = "Grant the user the role on the document or folder.", body = GrantAclResponse),
+        (status = 400, description = "The grantee is the current user."),
+        (status = 404, description = "The resource or the user of the email is not found.")
+    ),
+    tag = "Acl"
+)]
+pub async fn handle_grant_acl(
+    State(state): State<AppState>,
+    ValidatedJson(param): ValidatedJson<GrantAclRequest>
+) -> impl IntoResponse {
+    match get_acl_handler(&state).grant(param).await {
+        Ok(id) => Ok(Json(GrantAclResponse::new(id))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/modules/acl/revoke",
+    request_body = RevokeAclRequest,
+    responses((status = 200, description = "Revoke the acl.", body = RevokeAclResponse)),
+    tag = "Acl"
+)]
+pub async fn handle_revoke_acl(
+    State(state): State<AppState>,
+    ValidatedJson(param): ValidatedJson<RevokeAclRequest>
+) -> impl IntoResponse {
+    match get_acl_handler(&state).revoke(param).await {
+        Ok(count) => Ok(Json(RevokeAclResponse::new(count))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/acl/shared",
+    responses((
+        status = 200,
+        description = "Getting for the documents and folders shared with the current user.",
+        body = QuerySharedResponse,
+    )),
+    tag = "Acl"
+)]
+pub async fn handle_query_shared(State(state): State<AppState>) -> impl IntoResponse {
+    match get_acl_handler(&state).find_shared().await {
+        Ok(data) => Ok(Json(QuerySharedResponse::new(data))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+fn get_acl_handler(state: &AppState) -> Box<dyn IAclHandler + '_> {
+    Box::new(AclHandler::new(state))
+}
@@ -30,6 +30,7 @@ use axum::{
 
 use crate::{
     context::state::AppState,
+    errors,
     handler::api_v1::user::{ ApiV1Handler, IApiV1Handler },
     route::ValidatedJson,
     types::{
@@ -70,7 +71,7 @@ pub async fn handle_apiv1_get_users(
 ) -> impl IntoResponse {
     match get_apiv1_handler(&state).find(param, page).await {
         Ok((page, data)) => Ok(Json(QueryUserApiV1Response::new(page, data))),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(errors::to_status_code(&e)),
     }
 }
 
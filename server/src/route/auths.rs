@@ -164,9 +164,8 @@ pub async fn auth_middleware(
     };
 
     if is_authenticated {
-        // 3. Bind authenticated info to context.
+        // 3. Authenticated, the info will be bound to request scoped context.
         tracing::info!("Authenticated user: {:?}", claims);
-        SecurityContext::get_instance().bind(claims).await;
 
         // If logged in, and redirect to home page
         if path == ROOT_URI {
@@ -180,8 +179,8 @@ pub async fn auth_middleware(
             );
         }
 
-        // 4. Pass to call next routes.
-        return next.run(req).await;
+        // 4. Pass to call next routes within the request scoped security context.
+        return SecurityContext::scope(claims, next.run(req)).await;
     }
 
     // 5. Unauthenticated Response.
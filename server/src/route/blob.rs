@@ -0,0 +1,172 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use std::io;
+
+use axum::{
+    body::Body,
+    extract::{ Json, Query, State },
+    http::{ header, HeaderMap, StatusCode },
+    response::{ IntoResponse, Response },
+    routing::{ get, post },
+    Router,
+};
+use futures::TryStreamExt;
+use validator::Validate;
+
+use crate::{
+    context::state::AppState,
+    errors::{ self, BizError },
+    handler::blob::{ BlobHandler, IBlobHandler },
+    types::{
+        blob::{
+            DownloadBlobRequest,
+            QueryBlobRequest,
+            QueryBlobResponse,
+            UploadBlobRequest,
+            UploadBlobResponse,
+        },
+        PageRequest,
+    },
+};
+
+const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+// The raster images, which are the only served inline, since the others (e.g. the SVG and HTML) may run
+// the scripts on the origin of the app.
+const INLINE_MIME_TYPES: [&str; 6] = ["image/png", "image/jpeg", "image/gif", "image/webp", "image/avif", "image/bmp"];
+
+pub fn init() -> Router<AppState> {
+    Router::new()
+        .route("/modules/blob/upload", post(handle_upload_blob))
+        .route("/modules/blob/download", get(handle_download_blob))
+        .route("/modules/blob/query", get(handle_query_blobs))
+}
+
+#[utoipa::path(
+    post,
+    path = "/modules/blob/upload",
+    params(UploadBlobRequest),
+    request_body(content = String, content_type = "application/octet-stream", description = "The raw content."),
+    responses((status = 200, description = "Upload the blob, which addressed by the SHA-256 hash.", body = UploadBlobResponse)),
+    tag = "Blob"
+)]
+pub async fn handle_upload_blob(
+    State(state): State<AppState>,
+    Query(param): Query<UploadBlobRequest>,
+    headers: HeaderMap,
+    body: Body
+) -> impl IntoResponse {
+    if let Err(e) = param.validate() {
+        return Err(errors::to_status_code(&BizError::BadRequest(e.to_string()).into()));
+    }
+    let mime_type = to_mime_type(&headers, param.name.as_deref());
+    let stream = Box::pin(body.into_data_stream().map_err(io::Error::other));
+    match get_blob_handler(&state).upload(mime_type, stream).await {
+        Ok(blob) => Ok(Json(UploadBlobResponse::new(blob))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/blob/download",
+    params(DownloadBlobRequest),
+    responses(
+        (status = 200, description = "Download the blob content, which is the attachment unless the raster image.",
+            content_type = "application/octet-stream"),
+        (status = 304, description = "The blob is not modified (matched the 'If-None-Match').")
+    ),
+    tag = "Blob"
+)]
+pub async fn handle_download_blob(
+    State(state): State<AppState>,
+    Query(param): Query<DownloadBlobRequest>,
+    headers: HeaderMap
+) -> Result<Response, StatusCode> {
+    if let Err(e) = param.validate() {
+        return Err(errors::to_status_code(&BizError::BadRequest(e.to_string()).into()));
+    }
+    match get_blob_handler(&state).download(&param.hash).await {
+        Ok((blob, stream)) => {
+            // The content addressed blob is immutable, so the hash is the strong ETag.
+            let etag = format!("\"{}\"", param.hash);
+            let matched = headers
+                .get(header::IF_NONE_MATCH)
+                .map(|v| v.as_bytes() == etag.as_bytes())
+                .unwrap_or(false);
+            if matched {
+                return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+            }
+            // The not allowed (e.g. stored before) are downloaded as the attachment.
+            let (mime_type, disposition) = match blob.mime_type.filter(|m| INLINE_MIME_TYPES.contains(&m.as_str())) {
+                Some(mime_type) => (mime_type, "inline"),
+                None => (DEFAULT_MIME_TYPE.to_string(), "attachment"),
+            };
+            let headers = [
+                (header::CONTENT_TYPE, mime_type),
+                (header::CONTENT_DISPOSITION, disposition.to_string()),
+                (header::CONTENT_LENGTH, blob.size.unwrap_or_default().to_string()),
+                (header::ETAG, etag),
+                (header::CACHE_CONTROL, "private, max-age=31536000, immutable".to_string()),
+                (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+                (header::CONTENT_SECURITY_POLICY, "sandbox".to_string()),
+            ];
+            Ok((headers, Body::from_stream(stream)).into_response())
+        }
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/blob/query",
+    params(QueryBlobRequest, PageRequest),
+    responses((status = 200, description = "Getting for the blobs referenced by the document.", body = QueryBlobResponse)),
+    tag = "Blob"
+)]
+pub async fn handle_query_blobs(
+    State(state): State<AppState>,
+    Query(param): Query<QueryBlobRequest>,
+    Query(page): Query<PageRequest>
+) -> impl IntoResponse {
+    match get_blob_handler(&state).find(param, page).await {
+        Ok((page, data)) => Ok(Json(QueryBlobResponse::new(page, data))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+// The MIME type of the 'Content-Type', or guessed by the file name if absent, and the not allowed
+// are stored as the default.
+fn to_mime_type(headers: &HeaderMap, name: Option<&str>) -> String {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.split(';').next().unwrap_or_default().trim().to_lowercase())
+        .filter(|v| !v.is_empty() && *v != DEFAULT_MIME_TYPE)
+        .or_else(|| name.and_then(|n| mime_guess::from_path(n).first()).map(|m| m.essence_str().to_string()))
+        .filter(|v| INLINE_MIME_TYPES.contains(&v.as_str()))
+        .unwrap_or(DEFAULT_MIME_TYPE.to_string())
+}
+
+fn get_blob_handler(state: &AppState) -> Box<dyn IBlobHandler + '_> {
+    Box::new(BlobHandler::new(state))
+}
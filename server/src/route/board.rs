@@ -0,0 +1,136 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use axum::{
+    body::Bytes,
+    extract::{ Json, Query, State },
+    http::{ header, StatusCode },
+    response::{ IntoResponse, Response },
+    routing::{ get, post },
+    Router,
+};
+use validator::Validate;
+
+use crate::{
+    context::state::AppState,
+    errors::{ self, BizError },
+    handler::board::{ BoardHandler, IBoardHandler },
+    types::board::{
+        BulkImportBoardRequest,
+        ExportBoardRequest,
+        ImportBoardRequest,
+        ImportBoardResponse,
+    },
+    utils::boards::EXCALIDRAW_MIME_TYPE,
+};
+
+use super::to_content_disposition;
+
+pub fn init() -> Router<AppState> {
+    Router::new()
+        .route("/modules/board/import", post(handle_import_board))
+        .route("/modules/board/import/bulk", post(handle_bulk_import_boards))
+        .route("/modules/board/export", get(handle_export_board))
+}
+
+#[utoipa::path(
+    post,
+    path = "/modules/board/import",
+    params(ImportBoardRequest),
+    request_body(
+        content = String,
+        content_type = "application/json",
+        description = "The '.excalidraw' or '.tldr' file."
+    ),
+    responses((status = 200, description = "Import the file as the new board document.", body = ImportBoardResponse)),
+    tag = "Board"
+)]
+pub async fn handle_import_board(
+    State(state): State<AppState>,
+    Query(param): Query<ImportBoardRequest>,
+    body: Bytes
+) -> impl IntoResponse {
+    if let Err(e) = param.validate() {
+        return Err(errors::to_status_code(&BizError::BadRequest(e.to_string()).into()));
+    }
+    match get_board_handler(&state).import(param, body.to_vec()).await {
+        Ok((id, version)) => Ok(Json(ImportBoardResponse::new(id, version))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/modules/board/import/bulk",
+    params(BulkImportBoardRequest),
+    request_body(content = String, content_type = "application/zip", description = "The zip of the board files."),
+    responses((
+        status = 200,
+        description = "Import all the '.excalidraw' and '.tldr' files of the zip to the folder.",
+        body = BulkImportBoardResponse,
+    )),
+    tag = "Board"
+)]
+pub async fn handle_bulk_import_boards(
+    State(state): State<AppState>,
+    Query(param): Query<BulkImportBoardRequest>,
+    body: Bytes
+) -> impl IntoResponse {
+    if let Err(e) = param.validate() {
+        return Err(errors::to_status_code(&BizError::BadRequest(e.to_string()).into()));
+    }
+    match get_board_handler(&state).import_bulk(param, body.to_vec()).await {
+        Ok(result) => Ok(Json(result)),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/board/export",
+    params(ExportBoardRequest),
+    responses((
+        status = 200,
+        description = "Export the board as the '.excalidraw' file.",
+        content_type = "application/vnd.excalidraw+json",
+    )),
+    tag = "Board"
+)]
+pub async fn handle_export_board(
+    State(state): State<AppState>,
+    Query(param): Query<ExportBoardRequest>
+) -> Result<Response, StatusCode> {
+    match get_board_handler(&state).export(param.id).await {
+        Ok((name, content)) => {
+            let headers = [
+                (header::CONTENT_TYPE, EXCALIDRAW_MIME_TYPE.to_string()),
+                (header::CONTENT_DISPOSITION, to_content_disposition(&format!("{}.excalidraw", name))),
+            ];
+            Ok((headers, content).into_response())
+        }
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+fn get_board_handler(state: &AppState) -> Box<dyn IBoardHandler + '_> {
+    Box::new(BoardHandler::new(state))
+}
@@ -22,31 +22,75 @@
 
 use axum::{
     extract::{ Json, Query, State },
-    http::StatusCode,
-    response::IntoResponse,
+    response::{ IntoResponse, Response },
     routing::{ get, post },
     Router,
 };
+use hyper::{ header, HeaderMap, StatusCode };
+
+use validator::Validate;
 
 use crate::{
     context::state::AppState,
+    errors::{ self, BizError },
     handler::document::IDocumentHandler,
+    handler::note::{ INoteHandler, NoteHandler },
+    handler::thumbnail::{ IThumbnailHandler, ThumbnailHandler },
     types::{
-        document::{ DeleteDocumentResponse, QueryDocumentResponse, SaveDocumentResponse },
+        document::{
+            DeleteDocumentResponse,
+            ExportDocumentRequest,
+            GetThumbnailRequest,
+            QueryDocumentResponse,
+            SaveDocumentResponse,
+            ThumbnailFormat,
+        },
+        document_revision::{
+            DiffDocumentRevisionRequest,
+            DiffDocumentRevisionResponse,
+            GetDocumentRevisionRequest,
+            QueryDocumentRevisionRequest,
+            QueryDocumentRevisionResponse,
+            RestoreDocumentRevisionRequest,
+            RestoreDocumentRevisionResponse,
+        },
         PageRequest,
     },
-    utils::auths::SecurityContext,
+    utils::{ auths::SecurityContext, webs::to_etag },
 };
 use crate::handler::document::DocumentHandler;
-use crate::types::document::{ QueryDocumentRequest, SaveDocumentRequest, DeleteDocumentRequest };
+use crate::types::document::{
+    QueryDocumentRequest,
+    SaveDocumentRequest,
+    MoveDocumentRequest,
+    MoveDocumentResponse,
+    DeleteDocumentRequest,
+    RestoreDocumentRequest,
+    RestoreDocumentResponse,
+    PurgeDocumentRequest,
+    PurgeDocumentResponse,
+    SearchDocumentRequest,
+    SearchDocumentResponse,
+};
 
-use super::ValidatedJson;
+use super::{ resolve_if_match, to_content_disposition, ValidatedJson };
 
 pub fn init() -> Router<AppState> {
     Router::new()
         .route("/modules/document/query", get(handle_query_documents))
+        .route("/modules/document/search", get(handle_search_documents))
         .route("/modules/document/save", post(handle_save_document))
+        .route("/modules/document/move", post(handle_move_documents))
         .route("/modules/document/delete", post(handle_delete_document))
+        .route("/modules/document/trash/query", get(handle_query_trash_documents))
+        .route("/modules/document/trash/restore", post(handle_restore_trash_document))
+        .route("/modules/document/trash/purge", post(handle_purge_trash_document))
+        .route("/modules/document/revision/query", get(handle_query_document_revisions))
+        .route("/modules/document/revision/get", get(handle_get_document_revision))
+        .route("/modules/document/revision/diff", get(handle_diff_document_revision))
+        .route("/modules/document/revision/restore", post(handle_restore_document_revision))
+        .route("/modules/document/export", get(handle_export_document))
+        .route("/modules/document/thumbnail", get(handle_get_document_thumbnail))
 }
 
 #[utoipa::path(
@@ -65,12 +109,37 @@ pub async fn handle_query_documents(
     Query(param): Query<QueryDocumentRequest>,
     Query(page): Query<PageRequest>
 ) -> impl IntoResponse {
-    let cur_document = SecurityContext::get_instance().get().await;
+    let cur_document = SecurityContext::get();
     tracing::info!("current document: {:?}", cur_document);
 
     match get_document_handler(&state).find(param, page).await {
         Ok((page, data)) => Ok(Json(QueryDocumentResponse::new(page, data))),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/document/search",
+    params(SearchDocumentRequest, PageRequest),
+    responses((
+        status = 200,
+        description = "Full text search for documents names and contents, the most relevant first.",
+        body = SearchDocumentResponse,
+    )),
+    tag = "Document"
+)]
+pub async fn handle_search_documents(
+    State(state): State<AppState>,
+    Query(param): Query<SearchDocumentRequest>,
+    Query(page): Query<PageRequest>
+) -> impl IntoResponse {
+    if let Err(e) = param.validate() {
+        return Err(errors::to_status_code(&BizError::BadRequest(e.to_string()).into()));
+    }
+    match get_document_handler(&state).search(param, page).await {
+        Ok((page, data)) => Ok(Json(SearchDocumentResponse::new(page, data))),
+        Err(e) => Err(errors::to_status_code(&e)),
     }
 }
 
@@ -78,16 +147,43 @@ pub async fn handle_query_documents(
     post,
     path = "/modules/document/save",
     request_body = SaveDocumentRequest,
-    responses((status = 200, description = "Save for document.", body = SaveDocumentResponse)),
+    params(("If-Match" = Option<String>, Header, description = "The version (ETag) of the document to update.")),
+    responses(
+        (status = 200, description = "Save for document.", body = SaveDocumentResponse),
+        (status = 409, description = "The version of document is stale, responds the current version."),
+        (status = 428, description = "The version of document to update is required.")
+    ),
     tag = "Document"
 )]
 async fn handle_save_document(
     State(state): State<AppState>,
-    ValidatedJson(param): ValidatedJson<SaveDocumentRequest>
-) -> impl IntoResponse {
+    headers: HeaderMap,
+    ValidatedJson(mut param): ValidatedJson<SaveDocumentRequest>
+) -> Result<Response, Response> {
+    param.version = resolve_if_match(&headers, param.version).map_err(|e| errors::to_response(&e))?;
     match get_document_handler(&state).save(param).await {
-        Ok(result) => Ok(Json(SaveDocumentResponse::new(result))),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Ok((id, version)) => {
+            let headers = [(header::ETAG, to_etag(version))];
+            Ok((headers, Json(SaveDocumentResponse::new(id, version))).into_response())
+        }
+        Err(e) => Err(errors::to_response(&e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/modules/document/move",
+    request_body = MoveDocumentRequest,
+    responses((status = 200, description = "Move the documents to the folder or the root.", body = MoveDocumentResponse)),
+    tag = "Document"
+)]
+async fn handle_move_documents(
+    State(state): State<AppState>,
+    ValidatedJson(param): ValidatedJson<MoveDocumentRequest>
+) -> impl IntoResponse {
+    match get_document_handler(&state).move_to_folder(param).await {
+        Ok(result) => Ok(Json(MoveDocumentResponse::new(result))),
+        Err(e) => Err(errors::to_status_code(&e)),
     }
 }
 
@@ -104,10 +200,220 @@ async fn handle_delete_document(
 ) -> impl IntoResponse {
     match get_document_handler(&state).delete(param).await {
         Ok(result) => Ok(Json(DeleteDocumentResponse::new(result))),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/document/revision/query",
+    params(QueryDocumentRevisionRequest, PageRequest),
+    responses((
+        status = 200,
+        description = "Getting for the revisions of document, newest first.",
+        body = QueryDocumentRevisionResponse,
+    )),
+    tag = "Document"
+)]
+pub async fn handle_query_document_revisions(
+    State(state): State<AppState>,
+    Query(param): Query<QueryDocumentRevisionRequest>,
+    Query(page): Query<PageRequest>
+) -> impl IntoResponse {
+    match get_document_handler(&state).find_revisions(param, page).await {
+        Ok((page, data)) => Ok(Json(QueryDocumentRevisionResponse::new(page, data))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/document/revision/get",
+    params(GetDocumentRevisionRequest),
+    responses((status = 200, description = "Getting for the document revision.", body = DocumentRevision)),
+    tag = "Document"
+)]
+pub async fn handle_get_document_revision(
+    State(state): State<AppState>,
+    Query(param): Query<GetDocumentRevisionRequest>
+) -> impl IntoResponse {
+    match get_document_handler(&state).get_revision(param.id).await {
+        Ok(result) => Ok(Json(result)),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/document/revision/diff",
+    params(DiffDocumentRevisionRequest),
+    responses((
+        status = 200,
+        description = "Diff the document revision to another revision or the current content.",
+        body = DiffDocumentRevisionResponse,
+    )),
+    tag = "Document"
+)]
+pub async fn handle_diff_document_revision(
+    State(state): State<AppState>,
+    Query(param): Query<DiffDocumentRevisionRequest>
+) -> impl IntoResponse {
+    match get_document_handler(&state).diff_revision(param.from_id, param.to_id).await {
+        Ok(diff) => Ok(Json(DiffDocumentRevisionResponse::new(param.from_id, param.to_id, diff))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/modules/document/revision/restore",
+    request_body = RestoreDocumentRevisionRequest,
+    responses((
+        status = 200,
+        description = "Restore the document revision as the new head.",
+        body = RestoreDocumentRevisionResponse,
+    )),
+    tag = "Document"
+)]
+async fn handle_restore_document_revision(
+    State(state): State<AppState>,
+    Json(param): Json<RestoreDocumentRevisionRequest>
+) -> impl IntoResponse {
+    match get_document_handler(&state).restore_revision(param.id).await {
+        Ok(result) => Ok(Json(RestoreDocumentRevisionResponse::new(result))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/document/trash/query",
+    params(QueryDocumentRequest, PageRequest),
+    responses((
+        status = 200,
+        description = "Getting for the trashed documents, latest deleted first.",
+        body = QueryDocumentResponse,
+    )),
+    tag = "Document"
+)]
+pub async fn handle_query_trash_documents(
+    State(state): State<AppState>,
+    Query(param): Query<QueryDocumentRequest>,
+    Query(page): Query<PageRequest>
+) -> impl IntoResponse {
+    match get_document_handler(&state).find_trash(param, page).await {
+        Ok((page, data)) => Ok(Json(QueryDocumentResponse::new(page, data))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/modules/document/trash/restore",
+    request_body = RestoreDocumentRequest,
+    responses((status = 200, description = "Restore the document from the trash.", body = RestoreDocumentResponse)),
+    tag = "Document"
+)]
+async fn handle_restore_trash_document(
+    State(state): State<AppState>,
+    Json(param): Json<RestoreDocumentRequest>
+) -> impl IntoResponse {
+    match get_document_handler(&state).restore(param).await {
+        Ok(result) => Ok(Json(RestoreDocumentResponse::new(result))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/modules/document/trash/purge",
+    request_body = PurgeDocumentRequest,
+    responses((status = 200, description = "Permanently delete the trashed document.", body = PurgeDocumentResponse)),
+    tag = "Document"
+)]
+async fn handle_purge_trash_document(
+    State(state): State<AppState>,
+    Json(param): Json<PurgeDocumentRequest>
+) -> impl IntoResponse {
+    match get_document_handler(&state).purge(param).await {
+        Ok(result) => Ok(Json(PurgeDocumentResponse::new(result))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/document/export",
+    params(ExportDocumentRequest),
+    responses((
+        status = 200,
+        description = "Export the note as the markdown or the standalone HTML.",
+        content_type = "text/markdown",
+    )),
+    tag = "Document"
+)]
+pub async fn handle_export_document(
+    State(state): State<AppState>,
+    Query(param): Query<ExportDocumentRequest>
+) -> impl IntoResponse {
+    match get_note_handler(&state).export(param.id, param.format).await {
+        Ok((name, content)) => {
+            let headers = [
+                (header::CONTENT_TYPE, param.format.mime_type().to_string()),
+                (header::CONTENT_DISPOSITION, to_content_disposition(&name)),
+            ];
+            Ok((headers, content).into_response())
+        }
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/document/thumbnail",
+    params(GetThumbnailRequest),
+    responses(
+        (status = 200, description = "Getting the SVG or PNG thumbnail of the board.", content_type = "image/png"),
+        (status = 304, description = "The thumbnail is not modified since the version of If-None-Match."),
+    ),
+    tag = "Document"
+)]
+pub async fn handle_get_document_thumbnail(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(param): Query<GetThumbnailRequest>
+) -> Result<Response, StatusCode> {
+    let format = param.format.unwrap_or(ThumbnailFormat::Png);
+    match get_thumbnail_handler(&state).get(param.id, format).await {
+        Ok((version, content)) => {
+            // The thumbnail changes with the version of the board, which must be revalidated.
+            let etag = to_etag(version);
+            let matched = headers
+                .get(header::IF_NONE_MATCH)
+                .map(|v| v.as_bytes() == etag.as_bytes())
+                .unwrap_or(false);
+            if matched {
+                return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+            }
+            let headers = [
+                (header::CONTENT_TYPE, format.mime_type().to_string()),
+                (header::ETAG, etag),
+                (header::CACHE_CONTROL, "private, no-cache".to_string()),
+            ];
+            Ok((headers, content).into_response())
+        }
+        Err(e) => Err(errors::to_status_code(&e)),
     }
 }
 
 fn get_document_handler(state: &AppState) -> Box<dyn IDocumentHandler + '_> {
     Box::new(DocumentHandler::new(state))
 }
+
+fn get_note_handler(state: &AppState) -> Box<dyn INoteHandler + '_> {
+    Box::new(NoteHandler::new(state))
+}
+
+fn get_thumbnail_handler(state: &AppState) -> Box<dyn IThumbnailHandler + '_> {
+    Box::new(ThumbnailHandler::new(state))
+}
@@ -0,0 +1,73 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    response::{ sse::{ Event, KeepAlive, Sse }, IntoResponse },
+    routing::get,
+    Router,
+};
+use futures::stream::StreamExt;
+
+use crate::{
+    context::state::AppState,
+    errors,
+    handler::event::{ EventHandler, IEventHandler },
+};
+
+pub fn init() -> Router<AppState> {
+    Router::new().route("/modules/events", get(handle_events))
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/events",
+    params(
+        ("Last-Event-ID" = Option<i64>, Header, description = "The id of the last received event for resuming.")
+    ),
+    responses(
+        (status = 200, description = "The server-sent events 'change' of the ChangeEvent."),
+        (status = 401, description = "Unauthenticated.")
+    ),
+    tag = "Events"
+)]
+pub async fn handle_events(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    let last_event_id = headers
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<i64>().ok());
+    // Subscribed before responding, since the stream is polled outside the security context of the request.
+    let subscribed = get_event_handler(&state).subscribe(last_event_id).await;
+    match subscribed {
+        Ok(events) => {
+            let stream = events.map(|event| {
+                Event::default().id(event.id.to_string()).event("change").json_data(&event)
+            });
+            Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+        }
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+fn get_event_handler(state: &AppState) -> Box<dyn IEventHandler + '_> {
+    Box::new(EventHandler::new(state))
+}
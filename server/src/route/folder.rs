@@ -1,22 +1,40 @@
 use axum::{
     extract::{ Json, Query, State },
-    http::StatusCode,
-    response::IntoResponse,
+    response::{ IntoResponse, Response },
     routing::{ get, post },
     Router,
 };
+use hyper::{ header, HeaderMap };
 
 use crate::{
     context::state::AppState,
+    errors,
     handler::folder::IFolderHandler,
+    handler::note::{ INoteHandler, NoteHandler },
     types::{
-        folder::{ DeleteFolderResponse, QueryFolderResponse, SaveFolderResponse },
+        folder::{
+            DeleteFolderResponse,
+            FolderTreeResponse,
+            MoveFolderResponse,
+            QueryFolderResponse,
+            SaveFolderResponse,
+        },
         PageRequest,
     },
-    utils::auths::SecurityContext,
+    utils::{ auths::SecurityContext, webs::to_etag },
 };
 use crate::handler::folder::FolderHandler;
-use crate::types::folder::{ QueryFolderRequest, SaveFolderRequest, DeleteFolderRequest };
+use crate::types::folder::{
+    ExportFolderRequest,
+    QueryFolderRequest,
+    SaveFolderRequest,
+    MoveFolderRequest,
+    DeleteFolderRequest,
+    RestoreFolderRequest,
+    RestoreFolderResponse,
+    PurgeFolderRequest,
+    PurgeFolderResponse,
+};
 
 /*
  * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
@@ -40,13 +58,19 @@ use crate::types::folder::{ QueryFolderRequest, SaveFolderRequest, DeleteFolderR
  * This includes modifications and derived works.
  */
 
-use super::ValidatedJson;
+use super::{ resolve_if_match, to_content_disposition, ValidatedJson };
 
 pub fn init() -> Router<AppState> {
     Router::new()
         .route("/modules/folder/query", get(handle_query_folders))
+        .route("/modules/folder/tree", get(handle_query_folder_tree))
         .route("/modules/folder/save", post(handle_save_folder))
+        .route("/modules/folder/move", post(handle_move_folder))
         .route("/modules/folder/delete", post(handle_delete_folder))
+        .route("/modules/folder/trash/query", get(handle_query_trash_folders))
+        .route("/modules/folder/trash/restore", post(handle_restore_trash_folder))
+        .route("/modules/folder/trash/purge", post(handle_purge_trash_folder))
+        .route("/modules/folder/export", get(handle_export_folder))
 }
 
 #[utoipa::path(
@@ -61,12 +85,29 @@ pub async fn handle_query_folders(
     Query(param): Query<QueryFolderRequest>,
     Query(page): Query<PageRequest>
 ) -> impl IntoResponse {
-    let cur_folder = SecurityContext::get_instance().get().await;
+    let cur_folder = SecurityContext::get();
     tracing::info!("current folder: {:?}", cur_folder);
 
     match get_folder_handler(&state).find(param, page).await {
         Ok((page, data)) => Ok(Json(QueryFolderResponse::new(page, data))),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/folder/tree",
+    responses((
+        status = 200,
+        description = "Getting for the nested folders with the document counts.",
+        body = FolderTreeResponse,
+    )),
+    tag = "Folder"
+)]
+pub async fn handle_query_folder_tree(State(state): State<AppState>) -> impl IntoResponse {
+    match get_folder_handler(&state).tree().await {
+        Ok((data, root_document_count)) => Ok(Json(FolderTreeResponse::new(data, root_document_count))),
+        Err(e) => Err(errors::to_status_code(&e)),
     }
 }
 
@@ -74,16 +115,54 @@ pub async fn handle_query_folders(
     post,
     path = "/modules/folder/save",
     request_body = SaveFolderRequest,
-    responses((status = 200, description = "Save for folder.", body = SaveFolderResponse)),
+    params(("If-Match" = Option<String>, Header, description = "The version (ETag) of the folder to update.")),
+    responses(
+        (status = 200, description = "Save for folder.", body = SaveFolderResponse),
+        (status = 409, description = "The version of folder is stale, responds the current version."),
+        (status = 428, description = "The version of folder to update is required.")
+    ),
     tag = "Folder"
 )]
 async fn handle_save_folder(
     State(state): State<AppState>,
-    ValidatedJson(param): ValidatedJson<SaveFolderRequest>
-) -> impl IntoResponse {
+    headers: HeaderMap,
+    ValidatedJson(mut param): ValidatedJson<SaveFolderRequest>
+) -> Result<Response, Response> {
+    param.version = resolve_if_match(&headers, param.version).map_err(|e| errors::to_response(&e))?;
     match get_folder_handler(&state).save(param).await {
-        Ok(result) => Ok(Json(SaveFolderResponse::new(result))),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Ok((id, version)) => {
+            let headers = [(header::ETAG, to_etag(version))];
+            Ok((headers, Json(SaveFolderResponse::new(id, version))).into_response())
+        }
+        Err(e) => Err(errors::to_response(&e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/modules/folder/move",
+    request_body = MoveFolderRequest,
+    params(("If-Match" = Option<String>, Header, description = "The version (ETag) of the folder to move.")),
+    responses(
+        (status = 200, description = "Move the folder under the new parent.", body = MoveFolderResponse),
+        (status = 400, description = "The new parent is the folder itself or the sub folder of it."),
+        (status = 409, description = "The version of folder is stale, responds the current version."),
+        (status = 428, description = "The version of folder to move is required.")
+    ),
+    tag = "Folder"
+)]
+async fn handle_move_folder(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(mut param): Json<MoveFolderRequest>
+) -> Result<Response, Response> {
+    param.version = resolve_if_match(&headers, param.version).map_err(|e| errors::to_response(&e))?;
+    match get_folder_handler(&state).move_folder(param).await {
+        Ok((id, version)) => {
+            let headers = [(header::ETAG, to_etag(version))];
+            Ok((headers, Json(MoveFolderResponse::new(id, version))).into_response())
+        }
+        Err(e) => Err(errors::to_response(&e)),
     }
 }
 
@@ -91,7 +170,10 @@ async fn handle_save_folder(
     post,
     path = "/modules/folder/delete",
     request_body = DeleteFolderRequest,
-    responses((status = 200, description = "Delete for folder.", body = DeleteFolderResponse)),
+    responses(
+        (status = 200, description = "Delete for folder, cascades to the whole subtree by default.", body = DeleteFolderResponse),
+        (status = 400, description = "The folder is not empty for the restrict cascade.")
+    ),
     tag = "Folder"
 )]
 async fn handle_delete_folder(
@@ -100,10 +182,97 @@ async fn handle_delete_folder(
 ) -> impl IntoResponse {
     match get_folder_handler(&state).delete(param).await {
         Ok(result) => Ok(Json(DeleteFolderResponse::new(result))),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/folder/trash/query",
+    params(QueryFolderRequest, PageRequest),
+    responses((
+        status = 200,
+        description = "Getting for the trashed folders, latest deleted first.",
+        body = QueryFolderResponse,
+    )),
+    tag = "Folder"
+)]
+pub async fn handle_query_trash_folders(
+    State(state): State<AppState>,
+    Query(param): Query<QueryFolderRequest>,
+    Query(page): Query<PageRequest>
+) -> impl IntoResponse {
+    match get_folder_handler(&state).find_trash(param, page).await {
+        Ok((page, data)) => Ok(Json(QueryFolderResponse::new(page, data))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/modules/folder/trash/restore",
+    request_body = RestoreFolderRequest,
+    responses((status = 200, description = "Restore the folder from the trash.", body = RestoreFolderResponse)),
+    tag = "Folder"
+)]
+async fn handle_restore_trash_folder(
+    State(state): State<AppState>,
+    Json(param): Json<RestoreFolderRequest>
+) -> impl IntoResponse {
+    match get_folder_handler(&state).restore(param).await {
+        Ok(result) => Ok(Json(RestoreFolderResponse::new(result))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/modules/folder/trash/purge",
+    request_body = PurgeFolderRequest,
+    responses((status = 200, description = "Permanently delete the trashed folder.", body = PurgeFolderResponse)),
+    tag = "Folder"
+)]
+async fn handle_purge_trash_folder(
+    State(state): State<AppState>,
+    Json(param): Json<PurgeFolderRequest>
+) -> impl IntoResponse {
+    match get_folder_handler(&state).purge(param).await {
+        Ok(result) => Ok(Json(PurgeFolderResponse::new(result))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/folder/export",
+    params(ExportFolderRequest),
+    responses((
+        status = 200,
+        description = "Export all the notes of the folder and the sub folders as the zip.",
+        content_type = "application/zip",
+    )),
+    tag = "Folder"
+)]
+pub async fn handle_export_folder(
+    State(state): State<AppState>,
+    Query(param): Query<ExportFolderRequest>
+) -> impl IntoResponse {
+    match get_note_handler(&state).export_folder(param.id, param.format).await {
+        Ok((name, archive)) => {
+            let headers = [
+                (header::CONTENT_TYPE, "application/zip".to_string()),
+                (header::CONTENT_DISPOSITION, to_content_disposition(&name)),
+            ];
+            Ok((headers, archive).into_response())
+        }
+        Err(e) => Err(errors::to_status_code(&e)),
     }
 }
 
 fn get_folder_handler(state: &AppState) -> Box<dyn IFolderHandler + '_> {
     Box::new(FolderHandler::new(state))
 }
+
+fn get_note_handler(state: &AppState) -> Box<dyn INoteHandler + '_> {
+    Box::new(NoteHandler::new(state))
+}
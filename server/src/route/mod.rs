@@ -25,17 +25,70 @@ use axum::extract::rejection::{ JsonRejection, QueryRejection };
 use axum::response::{ IntoResponse, Response };
 use axum::extract::{ FromRequest, Request };
 use serde::de::DeserializeOwned;
-use hyper::StatusCode;
+use hyper::{ header, HeaderMap, StatusCode };
 use validator::Validate;
 
+pub mod acl;
 pub mod api_v1;
 pub mod auths;
+pub mod blob;
+pub mod board;
 pub mod document;
+pub mod event;
 pub mod folder;
 pub mod settings;
+pub mod room;
+pub mod share;
+pub mod sync;
+pub mod tag;
 pub mod user;
 pub mod browser_indexeddb;
 
+use crate::errors::BizError;
+
+// Resolve the expected version of the optimistic lock from the 'If-Match' header (such as '"3"'
+// or 'W/"3"') or the version field of body, which must be the same if both present.
+pub fn resolve_if_match(headers: &HeaderMap, version: Option<i64>) -> Result<Option<i64>, anyhow::Error> {
+    let if_match = match headers.get(header::IF_MATCH) {
+        Some(value) => {
+            let value = value.to_str().unwrap_or_default().trim();
+            let parsed = value.strip_prefix("W/").unwrap_or(value).trim_matches('"').parse::<i64>();
+            match parsed {
+                Ok(v) => Some(v),
+                Err(_) => {
+                    return Err(BizError::BadRequest(format!("Invalid the If-Match '{}'", value)).into());
+                }
+            }
+        }
+        None => None,
+    };
+    match (if_match, version) {
+        (Some(a), Some(b)) if a != b => {
+            Err(BizError::BadRequest("The If-Match is mismatched with the version".to_string()).into())
+        }
+        (a, b) => Ok(a.or(b)),
+    }
+}
+
+// The attachment of the file name, which the non ASCII is encoded as the 'filename*' (RFC 6266).
+pub fn to_content_disposition(file_name: &str) -> String {
+    let fallback: String = file_name
+        .chars()
+        .map(|c| if c.is_ascii_graphic() && c != '"' && c != '\\' || c == ' ' { c } else { '_' })
+        .collect();
+    let encoded: String = file_name
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect();
+    format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", fallback, encoded)
+}
+
 pub struct ValidatedJson<T>(pub T);
 
 #[async_trait]
@@ -54,9 +107,14 @@ impl<T, S> FromRequest<S>
     ) -> Result<Self, Self::Rejection> {
         let Json(value) = Json::<T>
             ::from_request(req, state).await
-            .map_err(|e|
-                (StatusCode::BAD_REQUEST, format!("Json parsing error: {}", e)).into_response()
-            )?;
+            .map_err(|e| {
+                // Keep the status of the body exceeds the limit, see: 'server.max-body-size'
+                let status = match e.status() {
+                    StatusCode::PAYLOAD_TOO_LARGE => StatusCode::PAYLOAD_TOO_LARGE,
+                    _ => StatusCode::BAD_REQUEST,
+                };
+                (status, format!("Json parsing error: {}", e)).into_response()
+            })?;
 
         value
             .validate()
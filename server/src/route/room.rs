@@ -0,0 +1,130 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+use axum::{
+    extract::{ ws::{ Message, WebSocket, WebSocketUpgrade }, Path, State },
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use futures::{ sink::SinkExt, stream::{ SplitSink, StreamExt } };
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    context::state::AppState,
+    errors,
+    handler::room::{ IRoomHandler, RoomHandler, RoomTicket },
+    types::room::{ RoomEvent, RoomMessage },
+};
+
+pub fn init() -> Router<AppState> {
+    Router::new().route("/modules/room/:id", get(handle_join_room))
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/room/{id}",
+    params(("id" = i64, Path, description = "The id of the board document.")),
+    responses(
+        (status = 101, description = "Switching to the websocket of the collaboration room.", body = RoomEvent),
+        (status = 400, description = "The document isn't a board."),
+        (status = 404, description = "The board is not found or not shared.")
+    ),
+    tag = "Room"
+)]
+pub async fn handle_join_room(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ws: WebSocketUpgrade
+) -> impl IntoResponse {
+    // Authorized before upgrading, so that the failures are responded as the status codes, and the socket is
+    // served in the spawned task which doesn't inherit the security context of the request. The room is joined
+    // after upgraded, so that it's never left open by the failed upgrade.
+    let authorized = get_room_handler(&state).authorize(id).await;
+    match authorized {
+        Ok(ticket) => {
+            let ws = ws.max_message_size(state.config.server.max_body_size);
+            Ok(ws.on_upgrade(move |socket| serve_room(state, ticket, socket)))
+        }
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+async fn serve_room(state: AppState, ticket: RoomTicket, socket: WebSocket) {
+    let handler = get_room_handler(&state);
+    let mut session = match handler.join(ticket).await {
+        Ok(session) => session,
+        Err(e) => {
+            tracing::warn!("Failed to join the room. reason: {}", e);
+            return;
+        }
+    };
+    let (mut sender, mut receiver) = socket.split();
+    let scene = std::mem::take(&mut session.scene);
+    let init = RoomEvent { sender: session.id.to_owned(), uid: session.uid, message: RoomMessage::SceneInit { scene } };
+    let mut connected = send_event(&mut sender, &init).await;
+    while connected {
+        tokio::select! {
+            incoming = receiver.next() => match incoming {
+                Some(Ok(Message::Text(text))) => {
+                    let relayed = match serde_json::from_str::<RoomMessage>(&text) {
+                        Ok(message) => handler.relay(&session, message).await,
+                        Err(e) => Err(e.into()),
+                    };
+                    if let Err(e) = relayed {
+                        tracing::warn!("Dropped the message of the room client: {}. reason: {}", session.id, e);
+                    }
+                }
+                // The pings are responded automatically.
+                Some(Ok(Message::Binary(_) | Message::Ping(_) | Message::Pong(_))) => {}
+                Some(Ok(Message::Close(_)) | Err(_)) | None => {
+                    connected = false;
+                }
+            },
+            event = session.events.recv() => match event {
+                Ok(event) if event.sender == session.id => {}
+                Ok(event) => {
+                    connected = send_event(&mut sender, &event).await;
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Skipped the {} lagged events of the room client: {}", skipped, session.id);
+                }
+                Err(RecvError::Closed) => {
+                    connected = false;
+                }
+            },
+        }
+    }
+    if let Err(e) = handler.leave(session).await {
+        tracing::warn!("Failed to leave the room. reason: {}", e);
+    }
+}
+
+async fn send_event(sender: &mut SplitSink<WebSocket, Message>, event: &RoomEvent) -> bool {
+    match serde_json::to_string(event) {
+        Ok(text) => sender.send(Message::Text(text)).await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+fn get_room_handler(state: &AppState) -> Box<dyn IRoomHandler + '_> {
+    Box::new(RoomHandler::new(state))
+}
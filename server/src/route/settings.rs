@@ -22,7 +22,6 @@
 
 use axum::{
     extract::{ Json, Query, State },
-    http::StatusCode,
     response::IntoResponse,
     routing::{ get, post },
     Router,
@@ -30,6 +29,7 @@ use axum::{
 
 use crate::{
     context::state::AppState,
+    errors,
     handler::settings::ISettingsHandler,
     types::{
         settings::{ DeleteSettingsResponse, QuerySettingsResponse, SaveSettingsResponse },
@@ -65,12 +65,12 @@ pub async fn handle_query_settings(
     Query(param): Query<QuerySettingsRequest>,
     Query(page): Query<PageRequest>
 ) -> impl IntoResponse {
-    let cur_settings = SecurityContext::get_instance().get().await;
+    let cur_settings = SecurityContext::get();
     tracing::info!("current settings: {:?}", cur_settings);
 
     match get_settings_handler(&state).find(param, page).await {
         Ok((page, data)) => Ok(Json(QuerySettingsResponse::new(page, data))),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(errors::to_status_code(&e)),
     }
 }
 
@@ -87,7 +87,7 @@ async fn handle_save_settings(
 ) -> impl IntoResponse {
     match get_settings_handler(&state).save(param).await {
         Ok(result) => Ok(Json(SaveSettingsResponse::new(result))),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(errors::to_status_code(&e)),
     }
 }
 
@@ -104,7 +104,7 @@ async fn handle_delete_settings(
 ) -> impl IntoResponse {
     match get_settings_handler(&state).delete(param).await {
         Ok(result) => Ok(Json(DeleteSettingsResponse::new(result))),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(errors::to_status_code(&e)),
     }
 }
 
@@ -0,0 +1,149 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+use axum::{
+    extract::{ Json, Path, Query, State },
+    http::{ header, HeaderMap },
+    response::IntoResponse,
+    routing::{ get, post },
+    Router,
+};
+
+use crate::{
+    context::state::AppState,
+    errors,
+    handler::share::{ IShareHandler, ShareHandler },
+    types::share::{
+        CreateShareLinkRequest,
+        CreateShareLinkResponse,
+        QueryShareLinkRequest,
+        QueryShareLinkResponse,
+        RevokeShareLinkRequest,
+        RevokeShareLinkResponse,
+        SharedDocumentResponse,
+    },
+};
+
+use super::ValidatedJson;
+
+// The public path of the share links, which is anonymous, see: config_serve.rs#WebServeConfig::new()
+pub const PUBLIC_SHARE_URI: &str = "/public/share/:token";
+// The header of the password of the protected share links.
+pub const SHARE_PASSWORD_HEADER: &str = "X-Share-Password";
+
+pub fn init() -> Router<AppState> {
+    Router::new()
+        .route("/modules/share/query", get(handle_query_share_links))
+        .route("/modules/share/create", post(handle_create_share_link))
+        .route("/modules/share/revoke", post(handle_revoke_share_link))
+        .route(PUBLIC_SHARE_URI, get(handle_get_shared_document))
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/share/query",
+    params(QueryShareLinkRequest),
+    responses((status = 200, description = "Getting for the share links.", body = QueryShareLinkResponse)),
+    tag = "Share"
+)]
+pub async fn handle_query_share_links(
+    State(state): State<AppState>,
+    Query(param): Query<QueryShareLinkRequest>
+) -> impl IntoResponse {
+    match get_share_handler(&state).find(param).await {
+        Ok(data) => Ok(Json(QueryShareLinkResponse::new(data))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/modules/share/create",
+    request_body = CreateShareLinkRequest,
+    responses(
+        (status = 200, description = "Create the share link of the document.", body = CreateShareLinkResponse),
+        (status = 400, description = "The expire time is passed.")
+    ),
+    tag = "Share"
+)]
+pub async fn handle_create_share_link(
+    State(state): State<AppState>,
+    ValidatedJson(param): ValidatedJson<CreateShareLinkRequest>
+) -> impl IntoResponse {
+    match get_share_handler(&state).create(param).await {
+        Ok((id, token)) => Ok(Json(CreateShareLinkResponse::new(id, token))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/modules/share/revoke",
+    request_body = RevokeShareLinkRequest,
+    responses((status = 200, description = "Revoke the share link.", body = RevokeShareLinkResponse)),
+    tag = "Share"
+)]
+pub async fn handle_revoke_share_link(
+    State(state): State<AppState>,
+    Json(param): Json<RevokeShareLinkRequest>
+) -> impl IntoResponse {
+    match get_share_handler(&state).revoke(param).await {
+        Ok(count) => Ok(Json(RevokeShareLinkResponse::new(count))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/public/share/{token}",
+    params(
+        ("token" = String, Path, description = "The token of the share link."),
+        ("X-Share-Password" = Option<String>, Header, description = "The password of the protected share link.")
+    ),
+    responses(
+        (status = 200, description = "Getting for the read-only shared document.", body = SharedDocumentResponse),
+        (status = 401, description = "The password is required or mismatched."),
+        (status = 404, description = "The share link is revoked or expired."),
+        (status = 429, description = "Too many mismatched passwords of the share link, please retry later.")
+    ),
+    tag = "Share"
+)]
+pub async fn handle_get_shared_document(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    headers: HeaderMap
+) -> impl IntoResponse {
+    let password = headers
+        .get(SHARE_PASSWORD_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+    match get_share_handler(&state).access(token, password).await {
+        Ok(document) => {
+            let headers = [(header::CACHE_CONTROL, "no-store")];
+            Ok((headers, Json(SharedDocumentResponse::from(document))))
+        }
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+fn get_share_handler(state: &AppState) -> Box<dyn IShareHandler + '_> {
+    Box::new(ShareHandler::new(state))
+}
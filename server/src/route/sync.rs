@@ -0,0 +1,121 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+use axum::{
+    extract::{ ws::{ self, WebSocket, WebSocketUpgrade }, Path, State },
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use futures::{ sink::SinkExt, stream::{ BoxStream, StreamExt } };
+use yrs::{ sync::{ Message, SyncMessage }, updates::encoder::Encode };
+
+use crate::{
+    context::state::AppState,
+    errors::{ self, BizError },
+    handler::sync::{ ISyncHandler, SyncHandler, SyncSession },
+};
+
+pub fn init() -> Router<AppState> {
+    Router::new().route("/modules/sync/:id", get(handle_join_sync))
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/sync/{id}",
+    params(("id" = i64, Path, description = "The id of the note document.")),
+    responses(
+        (status = 101, description = "Switching to the websocket of the y-protocols sync (binary frames)."),
+        (status = 400, description = "The document isn't a note."),
+        (status = 404, description = "The note is not found or not shared.")
+    ),
+    tag = "Sync"
+)]
+pub async fn handle_join_sync(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ws: WebSocketUpgrade
+) -> impl IntoResponse {
+    // Joined before upgrading, so that the failures are responded as the status codes, and the socket is
+    // served in the spawned task which doesn't inherit the security context of the request.
+    let joined = get_sync_handler(&state).join(id).await;
+    match joined {
+        Ok((session, messages)) => {
+            let ws = ws.max_message_size(state.config.server.max_body_size);
+            Ok(ws.on_upgrade(move |socket| serve_sync(state, session, messages, socket)))
+        }
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+async fn serve_sync(
+    state: AppState,
+    mut session: SyncSession,
+    mut messages: BoxStream<'static, Vec<u8>>,
+    socket: WebSocket
+) {
+    let handler = get_sync_handler(&state);
+    let (mut sender, mut receiver) = socket.split();
+    let init = Message::Sync(SyncMessage::SyncStep1(std::mem::take(&mut session.state_vector)));
+    let mut connected = sender.send(ws::Message::Binary(init.encode_v1())).await.is_ok();
+    while connected {
+        tokio::select! {
+            incoming = receiver.next() => match incoming {
+                Some(Ok(ws::Message::Binary(data))) => {
+                    let reply = match handler.handle(&mut session, &data).await {
+                        Ok(reply) => reply,
+                        // The permission denied of the y-protocols, i.e: the viewer updated the note.
+                        Err(e) if matches!(e.downcast_ref::<BizError>(), Some(BizError::Forbidden(_))) => {
+                            Some(Message::Auth(Some(e.to_string())))
+                        }
+                        Err(e) => {
+                            tracing::warn!("Dropped the message of the sync client: {}. reason: {}", session.id, e);
+                            None
+                        }
+                    };
+                    if let Some(reply) = reply {
+                        connected = sender.send(ws::Message::Binary(reply.encode_v1())).await.is_ok();
+                    }
+                }
+                // The pings are responded automatically.
+                Some(Ok(ws::Message::Text(_) | ws::Message::Ping(_) | ws::Message::Pong(_))) => {}
+                Some(Ok(ws::Message::Close(_)) | Err(_)) | None => {
+                    connected = false;
+                }
+            },
+            message = messages.next() => match message {
+                Some(data) => {
+                    connected = sender.send(ws::Message::Binary(data)).await.is_ok();
+                }
+                None => {
+                    connected = false;
+                }
+            },
+        }
+    }
+    if let Err(e) = handler.leave(session).await {
+        tracing::warn!("Failed to leave the sync. reason: {}", e);
+    }
+}
+
+fn get_sync_handler(state: &AppState) -> Box<dyn ISyncHandler + '_> {
+    Box::new(SyncHandler::new(state))
+}
@@ -0,0 +1,174 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+use axum::{
+    extract::{ Json, Query, State },
+    response::IntoResponse,
+    routing::{ get, post },
+    Router,
+};
+
+use crate::{
+    context::state::AppState,
+    errors,
+    handler::tag::{ ITagHandler, TagHandler },
+    types::tag::{
+        DeleteTagRequest,
+        DeleteTagResponse,
+        MergeTagRequest,
+        MergeTagResponse,
+        QueryTagRequest,
+        QueryTagResponse,
+        SaveTagRequest,
+        SaveTagResponse,
+        TagDocumentsRequest,
+        TagDocumentsResponse,
+    },
+};
+
+use super::ValidatedJson;
+
+pub fn init() -> Router<AppState> {
+    Router::new()
+        .route("/modules/tag/query", get(handle_query_tags))
+        .route("/modules/tag/save", post(handle_save_tag))
+        .route("/modules/tag/merge", post(handle_merge_tags))
+        .route("/modules/tag/delete", post(handle_delete_tag))
+        .route("/modules/tag/attach", post(handle_attach_tags))
+        .route("/modules/tag/detach", post(handle_detach_tags))
+}
+
+#[utoipa::path(
+    get,
+    path = "/modules/tag/query",
+    params(QueryTagRequest),
+    responses((
+        status = 200,
+        description = "Getting for all tags with the counts of the tagged documents.",
+        body = QueryTagResponse,
+    )),
+    tag = "Tag"
+)]
+pub async fn handle_query_tags(
+    State(state): State<AppState>,
+    Query(param): Query<QueryTagRequest>
+) -> impl IntoResponse {
+    match get_tag_handler(&state).find(param).await {
+        Ok(data) => Ok(Json(QueryTagResponse::new(data))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/modules/tag/save",
+    request_body = SaveTagRequest,
+    responses(
+        (status = 200, description = "Create the tag, or rename it if the id present.", body = SaveTagResponse),
+        (status = 400, description = "The tag name is empty or already exists.")
+    ),
+    tag = "Tag"
+)]
+pub async fn handle_save_tag(
+    State(state): State<AppState>,
+    ValidatedJson(param): ValidatedJson<SaveTagRequest>
+) -> impl IntoResponse {
+    match get_tag_handler(&state).save(param).await {
+        Ok(id) => Ok(Json(SaveTagResponse::new(id))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/modules/tag/merge",
+    request_body = MergeTagRequest,
+    responses((status = 200, description = "Merge the source tags into the target tag.", body = MergeTagResponse)),
+    tag = "Tag"
+)]
+pub async fn handle_merge_tags(
+    State(state): State<AppState>,
+    ValidatedJson(param): ValidatedJson<MergeTagRequest>
+) -> impl IntoResponse {
+    match get_tag_handler(&state).merge(param).await {
+        Ok(count) => Ok(Json(MergeTagResponse::new(count))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/modules/tag/delete",
+    request_body = DeleteTagRequest,
+    responses((
+        status = 200,
+        description = "Delete the tag and detach it from the documents.",
+        body = DeleteTagResponse,
+    )),
+    tag = "Tag"
+)]
+pub async fn handle_delete_tag(
+    State(state): State<AppState>,
+    ValidatedJson(param): ValidatedJson<DeleteTagRequest>
+) -> impl IntoResponse {
+    match get_tag_handler(&state).delete(param).await {
+        Ok(count) => Ok(Json(DeleteTagResponse::new(count))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/modules/tag/attach",
+    request_body = TagDocumentsRequest,
+    responses((status = 200, description = "Attach the tags to the documents.", body = TagDocumentsResponse)),
+    tag = "Tag"
+)]
+pub async fn handle_attach_tags(
+    State(state): State<AppState>,
+    ValidatedJson(param): ValidatedJson<TagDocumentsRequest>
+) -> impl IntoResponse {
+    match get_tag_handler(&state).attach(param).await {
+        Ok(count) => Ok(Json(TagDocumentsResponse::new(count))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/modules/tag/detach",
+    request_body = TagDocumentsRequest,
+    responses((status = 200, description = "Detach the tags from the documents.", body = TagDocumentsResponse)),
+    tag = "Tag"
+)]
+pub async fn handle_detach_tags(
+    State(state): State<AppState>,
+    ValidatedJson(param): ValidatedJson<TagDocumentsRequest>
+) -> impl IntoResponse {
+    match get_tag_handler(&state).detach(param).await {
+        Ok(count) => Ok(Json(TagDocumentsResponse::new(count))),
+        Err(e) => Err(errors::to_status_code(&e)),
+    }
+}
+
+fn get_tag_handler(state: &AppState) -> Box<dyn ITagHandler + '_> {
+    Box::new(TagHandler::new(state))
+}
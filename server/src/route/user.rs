@@ -30,6 +30,7 @@ use axum::{
 
 use crate::{
     context::state::AppState,
+    errors,
     handler::user::IUserHandler,
     types::{
         user::{ DeleteUserResponse, QueryUserResponse, SaveUserRequestWith, SaveUserResponse },
@@ -59,7 +60,7 @@ pub fn init() -> Router<AppState> {
     tag = "User"
 )]
 async fn handle_get_current_user(State(state): State<AppState>) -> impl IntoResponse {
-    let cur_user = SecurityContext::get_instance().get().await;
+    let cur_user = SecurityContext::get();
     tracing::info!("Getting for current user: {:?}", cur_user);
 
     let cur_user_uid = cur_user.map(|u| u.uid);
@@ -87,7 +88,7 @@ async fn handle_post_current_user(
     State(state): State<AppState>,
     ValidatedJson(param): ValidatedJson<SaveUserRequestWith>
 ) -> impl IntoResponse {
-    let cur_user = SecurityContext::get_instance().get().await;
+    let cur_user = SecurityContext::get();
     tracing::info!("Configure for current user: {:?}", cur_user);
 
     let cur_user_uid = cur_user.map(|u| u.uid);
@@ -123,7 +124,7 @@ async fn handle_query_users(
 ) -> impl IntoResponse {
     match get_user_handler(&state).find(param, page).await {
         Ok((page, data)) => Ok(Json(QueryUserResponse::new(page, data))),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => Err(errors::to_status_code(&e)),
     }
 }
 
@@ -0,0 +1,130 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use anyhow::{ Error, Ok };
+use axum::async_trait;
+use sqlx::PgPool;
+
+use crate::errors::BizError;
+use crate::types::acl::Acl;
+use crate::types::PageRequest;
+use crate::types::PageResponse;
+use crate::utils::types::GenericValue;
+use super::{ AsyncRepository, current_owner_uid };
+use super::query::{ Operator, QueryBuilder };
+use super::postgres::PostgresRepository;
+use super::acls_sqlite::ACL_COLUMNS;
+
+pub struct AclPostgresRepository {
+    inner: PostgresRepository<Acl>,
+}
+
+impl AclPostgresRepository {
+    pub fn new(pool: PgPool) -> Self {
+        AclPostgresRepository {
+            inner: PostgresRepository::new(pool),
+        }
+    }
+
+    // The query builder scoped to the owner (current user).
+    async fn owned_builder(&self) -> Result<QueryBuilder, Error> {
+        QueryBuilder::new("acls", ACL_COLUMNS).and("owner_uid", Operator::Eq, current_owner_uid().await?)
+    }
+}
+
+#[async_trait]
+impl AsyncRepository<Acl> for AclPostgresRepository {
+    async fn select(&self, acl: Acl, page: PageRequest) -> Result<(PageResponse, Vec<Acl>), Error> {
+        let builder = self.owned_builder().await?.and_bean(&acl)?.order_by("create_time", true)?;
+        let result = self.inner.select_page(&builder, &page).await?;
+
+        tracing::info!("query acls: {:?}", result.0);
+        Ok((result.0, result.1))
+    }
+
+    async fn select_by_id(&self, id: i64) -> Result<Acl, Error> {
+        let builder = self.owned_builder().await?.and("id", Operator::Eq, id)?;
+        let acl = self.inner
+            .select_one(&builder).await?
+            .ok_or_else(|| BizError::NotFound("acl".to_string()))?;
+        Ok(acl)
+    }
+
+    async fn insert(&self, mut acl: Acl) -> Result<i64, Error> {
+        acl.owner_uid = Some(current_owner_uid().await?);
+        acl.base.pre_insert(None).await;
+        let builder = QueryBuilder::new("acls", ACL_COLUMNS);
+        let inserted_id = self.inner.insert_bean(&builder, &acl).await?;
+        tracing::info!("Inserted acl.id: {:?}", inserted_id);
+        Ok(inserted_id)
+    }
+
+    async fn update(&self, mut acl: Acl) -> Result<i64, Error> {
+        acl.base.pre_update(None).await;
+        let id = acl.base.id.ok_or_else(|| Error::msg("The acl id is required for update"))?;
+        let builder = self.owned_builder().await?.and("id", Operator::Eq, id)?;
+        let updated_id = if self.inner.update_bean(&builder, &acl).await? > 0 { id } else { -1 };
+        tracing::info!("Updated acl.id: {:?}", updated_id);
+        Ok(updated_id)
+    }
+
+    async fn delete_all(&self) -> Result<u64, Error> {
+        let deleted = self.inner.delete(&self.owned_builder().await?).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    async fn delete_by_id(&self, id: i64) -> Result<u64, Error> {
+        let builder = self.owned_builder().await?.and("id", Operator::Eq, id)?;
+        let deleted = self.inner.delete(&builder).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    // Notice: It's the system wide for resolving the acls granted to the current user.
+    async fn select_unscoped(
+        &self,
+        acl: Acl,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<Acl>), Error> {
+        let builder = QueryBuilder::new("acls", ACL_COLUMNS)
+            .and_bean(&acl)?
+            .order_by("id", false)?;
+        self.inner.select_page(&builder, &page).await
+    }
+
+    async fn select_raw(&self, after_id: i64, limit: u32) -> Result<Vec<Acl>, Error> {
+        let builder = QueryBuilder::new("acls", ACL_COLUMNS);
+        self.inner.select_after(&builder, after_id, limit).await
+    }
+
+    async fn insert_raw(&self, acl: Acl) -> Result<i64, Error> {
+        let builder = QueryBuilder::new("acls", ACL_COLUMNS);
+        let del_flag = GenericValue::Int32(acl.base.del_flag.unwrap_or(0));
+        self.inner.insert_bean_with(&builder, &acl, vec![("del_flag".to_string(), del_flag)]).await
+    }
+
+    async fn delete_raw(&self, id: i64) -> Result<u64, Error> {
+        let builder = QueryBuilder::new("acls", ACL_COLUMNS).and("id", Operator::Eq, id)?;
+        self.inner.delete(&builder).await
+    }
+}
@@ -0,0 +1,54 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use std::{ io, pin::Pin };
+
+use anyhow::Error;
+use axum::{ async_trait, body::Bytes };
+use futures::Stream;
+
+pub type BlobStream = Pin<Box<dyn Stream<Item = Result<Bytes, io::Error>> + Send>>;
+
+// The stored object of the blob storage.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlobObject {
+    // The hex of SHA-256 of the content, which is the address of the object.
+    pub hash: String,
+    pub size: u64,
+    // The last modified millis.
+    pub modified: i64,
+}
+
+// The content addressed storage of the blobs (attachments), the same content is stored once.
+#[async_trait]
+pub trait BlobStorage: Send + Sync {
+    // Store the content stream of at most the max size bytes, the 'PayloadTooLarge' if exceeded.
+    async fn put(&self, stream: BlobStream, max_size: u64) -> Result<BlobObject, Error>;
+    async fn get(&self, hash: &str) -> Result<Option<(BlobObject, BlobStream)>, Error>;
+    async fn delete(&self, hash: &str) -> Result<bool, Error>;
+    async fn list(&self) -> Result<Vec<BlobObject>, Error>;
+}
+
+// The hash is the lowercase hex of SHA-256.
+pub fn is_valid_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
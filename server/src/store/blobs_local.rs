@@ -0,0 +1,202 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use anyhow::Error;
+use axum::async_trait;
+use futures::StreamExt;
+use sha2::{ Digest, Sha256 };
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
+
+use crate::errors::BizError;
+use super::blobs::{ BlobObject, BlobStorage, BlobStream, is_valid_hash };
+
+// The blobs storage of the local file system, which stored as '{dir}/{hash[0..2]}/{hash}'.
+pub struct LocalBlobStorage {
+    dir: PathBuf,
+}
+
+impl LocalBlobStorage {
+    pub fn new(dir: &str) -> Self {
+        LocalBlobStorage { dir: PathBuf::from(dir) }
+    }
+
+    fn path_of(&self, hash: &str) -> Result<PathBuf, Error> {
+        if !is_valid_hash(hash) {
+            return Err(BizError::BadRequest(format!("Invalid blob hash: {}", hash)).into());
+        }
+        Ok(self.dir.join(&hash[0..2]).join(hash))
+    }
+
+    async fn to_object(&self, hash: &str, path: &PathBuf) -> Result<BlobObject, Error> {
+        let metadata = fs::metadata(path).await?;
+        let modified = metadata
+            .modified()?
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_millis() as i64;
+        Ok(BlobObject { hash: hash.to_string(), size: metadata.len(), modified })
+    }
+}
+
+#[async_trait]
+impl BlobStorage for LocalBlobStorage {
+    async fn put(&self, mut stream: BlobStream, max_size: u64) -> Result<BlobObject, Error> {
+        // Write to the temporary file while hashing, and then move to the addressed path.
+        let tmp_dir = self.dir.join("tmp");
+        fs::create_dir_all(&tmp_dir).await?;
+        let tmp_path = tmp_dir.join(uuid::Uuid::new_v4().to_string());
+        let mut file = fs::File::create(&tmp_path).await?;
+
+        let mut hasher = Sha256::new();
+        let mut size: u64 = 0;
+        let written: Result<(), Error> = async {
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                size += chunk.len() as u64;
+                if size > max_size {
+                    let msg = format!("The blob exceeds the max size {} bytes", max_size);
+                    return Err(BizError::PayloadTooLarge(msg).into());
+                }
+                hasher.update(&chunk);
+                file.write_all(&chunk).await?;
+            }
+            file.flush().await?;
+            Ok(())
+        }.await;
+        drop(file);
+        if let Err(e) = written {
+            let _ = fs::remove_file(&tmp_path).await;
+            return Err(e);
+        }
+
+        let hash = hex::encode(hasher.finalize());
+        let path = self.path_of(&hash)?;
+        if fs::try_exists(&path).await? {
+            // The same content is already stored, touch it to avoid collecting by the concurrent GC.
+            fs::remove_file(&tmp_path).await?;
+            let file = std::fs::File::options().append(true).open(&path)?;
+            file.set_modified(SystemTime::now())?;
+        } else {
+            fs::create_dir_all(path.parent().unwrap()).await?;
+            fs::rename(&tmp_path, &path).await?;
+        }
+        self.to_object(&hash, &path).await
+    }
+
+    async fn get(&self, hash: &str) -> Result<Option<(BlobObject, BlobStream)>, Error> {
+        let path = self.path_of(hash)?;
+        if !fs::try_exists(&path).await? {
+            return Ok(None);
+        }
+        let object = self.to_object(hash, &path).await?;
+        let file = fs::File::open(&path).await?;
+        Ok(Some((object, Box::pin(ReaderStream::new(file)))))
+    }
+
+    async fn delete(&self, hash: &str) -> Result<bool, Error> {
+        match fs::remove_file(self.path_of(hash)?).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn list(&self) -> Result<Vec<BlobObject>, Error> {
+        let mut objects = Vec::new();
+        if !fs::try_exists(&self.dir).await? {
+            return Ok(objects);
+        }
+        let mut dirs = fs::read_dir(&self.dir).await?;
+        while let Some(dir) = dirs.next_entry().await? {
+            if !dir.file_type().await?.is_dir() || dir.file_name().len() != 2 {
+                continue;
+            }
+            let mut files = fs::read_dir(dir.path()).await?;
+            while let Some(file) = files.next_entry().await? {
+                let name = file.file_name().to_string_lossy().to_string();
+                if is_valid_hash(&name) {
+                    objects.push(self.to_object(&name, &file.path()).await?);
+                }
+            }
+        }
+        Ok(objects)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Bytes;
+
+    fn to_stream(chunks: Vec<&'static str>) -> BlobStream {
+        let chunks = chunks.into_iter().map(|c| Ok(Bytes::from(c)));
+        Box::pin(futures::stream::iter(chunks))
+    }
+
+    async fn read_all(stream: BlobStream) -> String {
+        let chunks: Vec<Bytes> = stream.map(|c| c.unwrap()).collect().await;
+        chunks.iter().map(|c| String::from_utf8_lossy(c).to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_put_get_delete_and_list() {
+        let dir = std::env::temp_dir().join(format!("mywebnote-{}", uuid::Uuid::new_v4()));
+        let storage = LocalBlobStorage::new(&dir.to_string_lossy());
+
+        let object = storage.put(to_stream(vec!["hello", " world"]), 1024).await.unwrap();
+        // echo -n 'hello world' | sha256sum
+        assert_eq!(object.hash, "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9");
+        assert_eq!(object.size, 11);
+
+        // The same content is stored once.
+        let again = storage.put(to_stream(vec!["hello world"]), 1024).await.unwrap();
+        assert_eq!(again.hash, object.hash);
+        assert_eq!(storage.list().await.unwrap().len(), 1);
+
+        let (got, stream) = storage.get(&object.hash).await.unwrap().unwrap();
+        assert_eq!(got.size, 11);
+        assert_eq!(read_all(stream).await, "hello world");
+
+        assert!(storage.delete(&object.hash).await.unwrap());
+        assert!(!storage.delete(&object.hash).await.unwrap());
+        assert!(storage.get(&object.hash).await.unwrap().is_none());
+        assert!(storage.list().await.unwrap().is_empty());
+        assert!(storage.get("../../etc/passwd").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_put_exceeds_max_size() {
+        let dir = std::env::temp_dir().join(format!("mywebnote-{}", uuid::Uuid::new_v4()));
+        let storage = LocalBlobStorage::new(&dir.to_string_lossy());
+
+        let result = storage.put(to_stream(vec!["0123456789", "0123456789"]), 15).await;
+        let error = result.unwrap_err();
+        assert!(matches!(error.downcast_ref::<BizError>(), Some(BizError::PayloadTooLarge(_))));
+        assert!(storage.list().await.unwrap().is_empty());
+        let mut tmp = fs::read_dir(dir.join("tmp")).await.unwrap();
+        assert!(tmp.next_entry().await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,125 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use anyhow::{ Error, Ok };
+use axum::async_trait;
+use sqlx::PgPool;
+
+use crate::errors::BizError;
+use crate::types::blob::Blob;
+use crate::types::PageRequest;
+use crate::types::PageResponse;
+use crate::utils::types::GenericValue;
+use super::{ AsyncRepository, current_owner_uid };
+use super::query::{ Operator, QueryBuilder };
+use super::postgres::PostgresRepository;
+use super::blobs_sqlite::BLOB_COLUMNS;
+
+pub struct BlobPostgresRepository {
+    inner: PostgresRepository<Blob>,
+}
+
+impl BlobPostgresRepository {
+    pub fn new(pool: PgPool) -> Self {
+        BlobPostgresRepository {
+            inner: PostgresRepository::new(pool),
+        }
+    }
+
+    // The query builder scoped to the owner (current user).
+    async fn owned_builder(&self) -> Result<QueryBuilder, Error> {
+        QueryBuilder::new("blobs", BLOB_COLUMNS).and("owner_uid", Operator::Eq, current_owner_uid().await?)
+    }
+}
+
+#[async_trait]
+impl AsyncRepository<Blob> for BlobPostgresRepository {
+    async fn select(&self, blob: Blob, page: PageRequest) -> Result<(PageResponse, Vec<Blob>), Error> {
+        let builder = self.owned_builder().await?
+            .and_bean(&blob)?
+            .order_by("create_time", true)?
+            .order_by("id", true)?;
+        let result = self.inner.select_page(&builder, &page).await?;
+
+        tracing::info!("query blobs: {:?}", result.0);
+        Ok((result.0, result.1))
+    }
+
+    async fn select_by_id(&self, id: i64) -> Result<Blob, Error> {
+        let builder = self.owned_builder().await?.and("id", Operator::Eq, id)?;
+        let blob = self.inner
+            .select_one(&builder).await?
+            .ok_or_else(|| BizError::NotFound("blob".to_string()))?;
+        Ok(blob)
+    }
+
+    async fn insert(&self, mut blob: Blob) -> Result<i64, Error> {
+        blob.owner_uid = Some(current_owner_uid().await?);
+        blob.base.pre_insert(None).await;
+        let builder = QueryBuilder::new("blobs", BLOB_COLUMNS);
+        let inserted_id = self.inner.insert_bean(&builder, &blob).await?;
+        tracing::info!("Inserted blob.id: {:?}", inserted_id);
+        Ok(inserted_id)
+    }
+
+    async fn update(&self, _: Blob) -> Result<i64, Error> {
+        Err(Error::msg("The blob is immutable"))
+    }
+
+    async fn delete_all(&self) -> Result<u64, Error> {
+        let deleted = self.inner.delete(&self.owned_builder().await?).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    async fn delete_by_id(&self, id: i64) -> Result<u64, Error> {
+        let builder = self.owned_builder().await?.and("id", Operator::Eq, id)?;
+        let deleted = self.inner.delete(&builder).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    async fn select_unscoped(
+        &self,
+        blob: Blob,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<Blob>), Error> {
+        let builder = QueryBuilder::new("blobs", BLOB_COLUMNS).and_bean(&blob)?.order_by("id", false)?;
+        self.inner.select_page(&builder, &page).await
+    }
+
+    async fn select_raw(&self, after_id: i64, limit: u32) -> Result<Vec<Blob>, Error> {
+        let builder = QueryBuilder::new("blobs", BLOB_COLUMNS);
+        self.inner.select_after(&builder, after_id, limit).await
+    }
+
+    async fn insert_raw(&self, blob: Blob) -> Result<i64, Error> {
+        let builder = QueryBuilder::new("blobs", BLOB_COLUMNS);
+        let del_flag = GenericValue::Int32(blob.base.del_flag.unwrap_or(0));
+        self.inner.insert_bean_with(&builder, &blob, vec![("del_flag".to_string(), del_flag)]).await
+    }
+
+    async fn delete_raw(&self, id: i64) -> Result<u64, Error> {
+        let builder = QueryBuilder::new("blobs", BLOB_COLUMNS).and("id", Operator::Eq, id)?;
+        self.inner.delete(&builder).await
+    }
+}
@@ -0,0 +1,286 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use std::collections::HashSet;
+
+use anyhow::Error;
+use serde::Serialize;
+use sha2::{ Digest, Sha256 };
+
+use crate::types::{ BaseBean, ModuleBean };
+use super::AsyncRepository;
+
+#[derive(Clone, Debug)]
+pub struct DataMigrationOptions {
+    // Only scan and report, but nothing is written to the target.
+    pub dry_run: bool,
+    // Skip the data which already in the target, such as the interrupted migration before.
+    pub resume: bool,
+    pub batch_size: u32,
+}
+
+// The verification report of the module data migration, which is matched if the row counts and
+// checksums of the source and target are both same.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DataMigrationReport {
+    pub name: String,
+    pub source_count: u64,
+    pub source_checksum: String,
+    pub target_count: u64,
+    pub target_checksum: String,
+    // The number of rows copied (or to be copied if dry run), and skipped which already in the target.
+    pub copied: u64,
+    pub skipped: u64,
+}
+
+impl DataMigrationReport {
+    pub fn is_matched(&self) -> bool {
+        self.source_count == self.target_count && self.source_checksum == self.target_checksum
+    }
+}
+
+// The checksum of all the rows in ascending order of the id, the row is digested by the serialized
+// fields and the 'del_flag' (which is skipped by serde), so it's same for the all DbTypes.
+struct Checksum {
+    hasher: Sha256,
+    count: u64,
+}
+
+impl Checksum {
+    fn new() -> Self {
+        Self { hasher: Sha256::new(), count: 0 }
+    }
+
+    fn update<T: Serialize + ModuleBean>(&mut self, row: &T) -> Result<(), Error> {
+        self.hasher.update(serde_json::to_vec(row)?);
+        self.hasher.update(row.base().del_flag.unwrap_or(0).to_le_bytes());
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(self) -> (u64, String) {
+        (self.count, hex::encode(self.hasher.finalize()))
+    }
+}
+
+fn id_of(base: &BaseBean) -> Result<i64, Error> {
+    base.id.ok_or_else(|| Error::msg("The id of migrating data is missing"))
+}
+
+// Scan all the rows of the repository for the ids and checksum.
+async fn scan<T>(repo: &dyn AsyncRepository<T>, batch_size: u32) -> Result<(HashSet<i64>, u64, String), Error>
+    where T: 'static + Send + Sync + Serialize + ModuleBean
+{
+    let mut ids = HashSet::new();
+    let mut checksum = Checksum::new();
+    let mut after_id = i64::MIN;
+    loop {
+        let rows = repo.select_raw(after_id, batch_size).await?;
+        for row in &rows {
+            checksum.update(row)?;
+            ids.insert(id_of(row.base())?);
+        }
+        match rows.last() {
+            Some(last) if rows.len() >= (batch_size as usize) => {
+                after_id = id_of(last.base())?;
+            }
+            _ => break,
+        }
+    }
+    let (count, checksum) = checksum.finish();
+    Ok((ids, count, checksum))
+}
+
+// Copy all the rows (include the trashed) of the module from the source to the target repository in
+// batches, which the ids, owners, audit fields and versions are kept as is, see: AsyncRepository::insert_raw
+// Notice: The target must be empty unless resuming, so that the checksums are comparable.
+pub async fn migrate_data<T>(
+    name: &str,
+    source: &dyn AsyncRepository<T>,
+    target: &dyn AsyncRepository<T>,
+    options: &DataMigrationOptions
+) -> Result<DataMigrationReport, Error>
+    where T: 'static + Send + Sync + Serialize + ModuleBean
+{
+    let batch_size = options.batch_size.max(1);
+    let (target_ids, target_count, target_checksum) = scan(target, batch_size).await?;
+    if !target_ids.is_empty() && !options.resume {
+        return Err(
+            Error::msg(
+                format!(
+                    "The target of '{}' already has {} rows, use the resume to skip the migrated rows",
+                    name,
+                    target_ids.len()
+                )
+            )
+        );
+    }
+
+    let mut report = DataMigrationReport { name: name.to_string(), ..Default::default() };
+    let mut checksum = Checksum::new();
+    let mut after_id = i64::MIN;
+    loop {
+        let rows = source.select_raw(after_id, batch_size).await?;
+        let more = rows.len() >= (batch_size as usize);
+        for row in rows {
+            let id = id_of(row.base())?;
+            checksum.update(&row)?;
+            after_id = id;
+            if target_ids.contains(&id) {
+                report.skipped += 1;
+                continue;
+            }
+            if !options.dry_run {
+                target.insert_raw(row).await?;
+            }
+            report.copied += 1;
+        }
+        if !more {
+            break;
+        }
+    }
+    (report.source_count, report.source_checksum) = checksum.finish();
+
+    // Verify the target after copied, or it's as is for the dry run.
+    (report.target_count, report.target_checksum) = if options.dry_run {
+        (target_count, target_checksum)
+    } else {
+        let (_, count, checksum) = scan(target, batch_size).await?;
+        (count, checksum)
+    };
+    tracing::info!("Migrated data report: {:?}", report);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::config_serve::DbProperties;
+    use crate::handler::auth::PrincipalType;
+    use crate::store::documents_sqlite::DocumentSQLiteRepository;
+    use crate::store::documents_postgres::DocumentPostgresRepository;
+    use crate::store::postgres::{ create_pool, create_test_config };
+    use crate::types::PageRequest;
+    use crate::types::document::{ Document, SaveDocumentRequest, SearchDocumentRequest };
+    use crate::utils::auths::{ AuthUserClaims, SecurityContext };
+
+    fn create_sqlite_config() -> DbProperties {
+        let dir = std::env::temp_dir().join(format!("mywebnote-{}", uuid::Uuid::new_v4()));
+        let mut config = DbProperties::default();
+        config.sqlite.dir = Some(dir.to_string_lossy().to_string());
+        config
+    }
+
+    fn user(uid: i64) -> Option<AuthUserClaims> {
+        Some(AuthUserClaims {
+            ptype: PrincipalType::Password,
+            uid,
+            uname: format!("user{}", uid),
+            email: format!("user{}@mywebnote.local", uid),
+            exp: 0,
+            ext: None,
+        })
+    }
+
+    fn options(dry_run: bool, resume: bool) -> DataMigrationOptions {
+        DataMigrationOptions { dry_run, resume, batch_size: 2 }
+    }
+
+    // Insert the documents of the owner, the last of which is trashed, returns the ids.
+    async fn insert_documents(repo: &dyn AsyncRepository<Document>, count: usize) -> Vec<i64> {
+        SecurityContext::scope(user(1), async {
+            let mut ids = Vec::new();
+            for i in 0..count {
+                let save = SaveDocumentRequest {
+                    id: None,
+                    key: Some(format!("k{}", i)),
+                    name: Some(format!("migrated note {}", i)),
+                    folder_key: None,
+                    doc_type: None,
+                    content: Some("hello ".repeat(1024)),
+                    version: None,
+                };
+                ids.push(repo.insert(save.to_document()).await.unwrap());
+            }
+            assert_eq!(repo.delete_by_id(*ids.last().unwrap()).await.unwrap(), 1);
+            ids
+        }).await
+    }
+
+    async fn assert_migrated(
+        source: &dyn AsyncRepository<Document>,
+        target: &dyn AsyncRepository<Document>,
+        ids: &[i64]
+    ) {
+        let report = migrate_data("documents", source, target, &options(false, false)).await.unwrap();
+        assert!(report.is_matched());
+        assert_eq!((report.source_count, report.copied, report.skipped), (ids.len() as u64, ids.len() as u64, 0));
+
+        SecurityContext::scope(user(1), async {
+            // The ids, owners, timestamps and the trashed are kept.
+            for id in &ids[..ids.len() - 1] {
+                assert_eq!(source.select_by_id(*id).await.unwrap(), target.select_by_id(*id).await.unwrap());
+            }
+            let param = SearchDocumentRequest { q: String::new(), folder_key: None, doc_type: None }.to_document();
+            let trash = target.select_trash(param, PageRequest::default()).await.unwrap().1;
+            assert_eq!(trash.iter().map(|d| d.base.id).collect::<Vec<_>>(), vec![ids.last().copied()]);
+
+            // The migrated documents are searchable.
+            let param = SearchDocumentRequest { q: "migrated".to_string(), folder_key: None, doc_type: None };
+            let hits = target.searcher().unwrap().search(&param.q, param.to_document(), PageRequest::default()).await;
+            assert_eq!(hits.unwrap().1.len(), ids.len() - 1);
+        }).await;
+
+        // The non empty target is refused unless resuming, which skips all the migrated.
+        assert!(migrate_data("documents", source, target, &options(false, false)).await.is_err());
+        let report = migrate_data("documents", source, target, &options(false, true)).await.unwrap();
+        assert!(report.is_matched());
+        assert_eq!((report.copied, report.skipped), (0, ids.len() as u64));
+    }
+
+    #[tokio::test]
+    async fn test_migrate_documents_between_sqlite() {
+        let source = DocumentSQLiteRepository::new(&create_sqlite_config()).await.unwrap();
+        let target = DocumentSQLiteRepository::new(&create_sqlite_config()).await.unwrap();
+        let ids = insert_documents(&source, 5).await;
+
+        // Nothing is written for the dry run.
+        let report = migrate_data("documents", &source, &target, &options(true, false)).await.unwrap();
+        assert_eq!((report.source_count, report.target_count, report.copied), (5, 0, 5));
+        assert!(!report.is_matched());
+        assert!(target.select_raw(i64::MIN, 10).await.unwrap().is_empty());
+
+        assert_migrated(&source, &target, &ids).await;
+    }
+
+    #[tokio::test]
+    async fn test_migrate_documents_from_sqlite_to_postgres() {
+        let config = match create_test_config().await {
+            Some(config) => config,
+            None => return,
+        };
+        let source = DocumentSQLiteRepository::new(&create_sqlite_config()).await.unwrap();
+        let target = DocumentPostgresRepository::new(&config, create_pool(&config).await.unwrap());
+        let ids = insert_documents(&source, 3).await;
+        assert_migrated(&source, &target, &ids).await;
+    }
+}
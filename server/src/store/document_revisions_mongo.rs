@@ -0,0 +1,180 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use std::sync::Arc;
+
+use anyhow::Error;
+use axum::async_trait;
+
+use futures::stream::TryStreamExt;
+use mongodb::Collection;
+use mongodb::bson::{ self, doc };
+
+use crate::config::config_serve::{ CompressionProperties, DbProperties };
+use crate::utils::compress;
+use crate::errors::BizError;
+use crate::types::document_revision::DocumentRevision;
+use crate::types::{ PageRequest, PageResponse };
+use super::{ AsyncRepository, current_owner_uid };
+use super::mongo::{ MongoRepository, delete_by_id, find_after, insert_with };
+use super::document_revisions_sqlite::DOCUMENT_REVISION_COLUMNS;
+use crate::{ dynamic_mongo_query, dynamic_mongo_insert };
+
+pub struct DocumentRevisionMongoRepository {
+    #[allow(unused)]
+    inner: Arc<MongoRepository<DocumentRevision>>,
+    collection: Collection<DocumentRevision>,
+    compression: CompressionProperties,
+}
+
+impl DocumentRevisionMongoRepository {
+    pub async fn new(config: &DbProperties) -> Result<Self, Error> {
+        let inner = Arc::new(MongoRepository::new(config).await?);
+        let collection = inner.get_database().collection("document_revisions");
+        let compression = config.compression.to_owned();
+        Ok(DocumentRevisionMongoRepository { inner, collection, compression })
+    }
+}
+
+#[async_trait]
+impl AsyncRepository<DocumentRevision> for DocumentRevisionMongoRepository {
+    async fn select(
+        &self,
+        revision: DocumentRevision,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<DocumentRevision>), Error> {
+        match
+            dynamic_mongo_query!(
+                revision,
+                self.collection,
+                Some(current_owner_uid().await?),
+                None,
+                None,
+                "create_time",
+                DOCUMENT_REVISION_COLUMNS,
+                page,
+                DocumentRevision
+            )
+        {
+            Ok(result) => {
+                tracing::info!("query document revisions: {:?}", result.0);
+                Ok((result.0, result.1))
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    async fn select_by_id(&self, id: i64) -> Result<DocumentRevision, Error> {
+        let filter = doc! { "id": id, "owner_uid": current_owner_uid().await? };
+        let revision = self.collection
+            .find_one(filter).await?
+            .ok_or_else(|| BizError::NotFound("document revision".to_string()))?;
+        Ok(revision)
+    }
+
+    async fn insert(&self, mut revision: DocumentRevision) -> Result<i64, Error> {
+        revision.owner_uid = Some(current_owner_uid().await?);
+        revision.content = compress::encode_content(revision.content, &self.compression)?;
+        dynamic_mongo_insert!(revision, self.collection)
+    }
+
+    async fn update(&self, _: DocumentRevision) -> Result<i64, Error> {
+        Err(Error::msg("The document revision is immutable"))
+    }
+
+    async fn delete_all(&self) -> Result<u64, Error> {
+        let result = self.collection.delete_many(
+            doc! { "owner_uid": current_owner_uid().await? }
+        ).await?;
+        Ok(result.deleted_count)
+    }
+
+    async fn delete_by_id(&self, id: i64) -> Result<u64, Error> {
+        let filter = doc! { "id": id, "owner_uid": current_owner_uid().await? };
+        let result = self.collection.delete_one(filter).await?;
+        Ok(result.deleted_count)
+    }
+
+    async fn delete_revisions_before(
+        &self,
+        document_id: i64,
+        keep_last: Option<u32>,
+        expired_before: Option<i64>
+    ) -> Result<u64, Error> {
+        if keep_last.is_none() && expired_before.is_none() {
+            return Ok(0);
+        }
+        let filter = doc! { "owner_uid": current_owner_uid().await?, "document_id": document_id };
+        let latest: Vec<bson::Document> = self.collection
+            .clone_with_type::<bson::Document>()
+            .find(filter.clone())
+            .projection(doc! { "id": 1 })
+            .sort(doc! { "create_time": -1, "id": -1 })
+            .limit(keep_last.unwrap_or(1).max(1) as i64).await?
+            .try_collect().await?;
+        let latest: Vec<i64> = latest
+            .iter()
+            .filter_map(|row| row.get_i64("id").ok())
+            .collect();
+        let newest = match latest.first() {
+            Some(newest) => *newest,
+            None => return Ok(0),
+        };
+
+        let mut retentions = Vec::new();
+        if keep_last.is_some() {
+            retentions.push(doc! { "id": { "$nin": &latest } });
+        }
+        if let Some(time) = expired_before {
+            retentions.push(doc! { "create_time": { "$lt": time } });
+        }
+        let mut filter = filter;
+        filter.insert("id", doc! { "$ne": newest });
+        filter.insert("$and", retentions);
+        let result = self.collection.delete_many(filter).await?;
+        Ok(result.deleted_count)
+    }
+
+    async fn select_raw(&self, after_id: i64, limit: u32) -> Result<Vec<DocumentRevision>, Error> {
+        let revisions = find_after(&self.collection, after_id, limit).await?;
+        Ok(
+            revisions
+                .into_iter()
+                .map(|(mut revision, del_flag)| {
+                    revision.base.del_flag = Some(del_flag);
+                    revision
+                })
+                .collect()
+        )
+    }
+
+    async fn insert_raw(&self, mut revision: DocumentRevision) -> Result<i64, Error> {
+        let del_flag = revision.base.del_flag.unwrap_or(0);
+        revision.content = compress::encode_content(revision.content, &self.compression)?;
+        insert_with(&self.collection, &revision, doc! { "del_flag": del_flag }).await?;
+        Ok(revision.base.id.unwrap_or(-1))
+    }
+
+    async fn delete_raw(&self, id: i64) -> Result<u64, Error> {
+        delete_by_id(&self.collection, id).await
+    }
+}
@@ -0,0 +1,152 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use anyhow::{ Error, Ok };
+use axum::async_trait;
+use sqlx::PgPool;
+
+use crate::config::config_serve::{ CompressionProperties, DbProperties };
+use crate::utils::compress;
+use crate::errors::BizError;
+use crate::types::document_revision::DocumentRevision;
+use crate::types::PageRequest;
+use crate::types::PageResponse;
+use crate::utils::types::GenericValue;
+use super::{ AsyncRepository, current_owner_uid };
+use super::query::{ Dialect, Operator, QueryBuilder };
+use super::postgres::PostgresRepository;
+use super::document_revisions_sqlite::{ to_retention_delete, DOCUMENT_REVISION_COLUMNS };
+
+pub struct DocumentRevisionPostgresRepository {
+    inner: PostgresRepository<DocumentRevision>,
+    compression: CompressionProperties,
+}
+
+impl DocumentRevisionPostgresRepository {
+    pub fn new(config: &DbProperties, pool: PgPool) -> Self {
+        DocumentRevisionPostgresRepository {
+            inner: PostgresRepository::new(pool),
+            compression: config.compression.to_owned(),
+        }
+    }
+
+    // The query builder scoped to the owner (current user).
+    async fn owned_builder(&self) -> Result<QueryBuilder, Error> {
+        QueryBuilder::new("document_revisions", DOCUMENT_REVISION_COLUMNS).and(
+            "owner_uid",
+            Operator::Eq,
+            current_owner_uid().await?
+        )
+    }
+}
+
+#[async_trait]
+impl AsyncRepository<DocumentRevision> for DocumentRevisionPostgresRepository {
+    async fn select(
+        &self,
+        revision: DocumentRevision,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<DocumentRevision>), Error> {
+        // Newest first, the id is the tiebreaker of the same millis.
+        let builder = self.owned_builder().await?
+            .and_bean(&revision)?
+            .order_by("create_time", true)?
+            .order_by("id", true)?;
+        let result = self.inner.select_page(&builder, &page).await?;
+
+        tracing::info!("query document revisions: {:?}", result.0);
+        Ok((result.0, result.1))
+    }
+
+    async fn select_by_id(&self, id: i64) -> Result<DocumentRevision, Error> {
+        let builder = self.owned_builder().await?.and("id", Operator::Eq, id)?;
+        let revision = self.inner
+            .select_one(&builder).await?
+            .ok_or_else(|| BizError::NotFound("document revision".to_string()))?;
+        Ok(revision)
+    }
+
+    async fn insert(&self, mut revision: DocumentRevision) -> Result<i64, Error> {
+        revision.owner_uid = Some(current_owner_uid().await?);
+        revision.base.pre_insert(None).await;
+        revision.content = compress::encode_content(revision.content, &self.compression)?;
+        let builder = QueryBuilder::new("document_revisions", DOCUMENT_REVISION_COLUMNS);
+        let inserted_id = self.inner.insert_bean(&builder, &revision).await?;
+        tracing::info!("Inserted document revision.id: {:?}", inserted_id);
+        Ok(inserted_id)
+    }
+
+    async fn update(&self, _: DocumentRevision) -> Result<i64, Error> {
+        Err(Error::msg("The document revision is immutable"))
+    }
+
+    async fn delete_all(&self) -> Result<u64, Error> {
+        let deleted = self.inner.delete(&self.owned_builder().await?).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    async fn delete_by_id(&self, id: i64) -> Result<u64, Error> {
+        let builder = self.owned_builder().await?.and("id", Operator::Eq, id)?;
+        let deleted = self.inner.delete(&builder).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    async fn delete_revisions_before(
+        &self,
+        document_id: i64,
+        keep_last: Option<u32>,
+        expired_before: Option<i64>
+    ) -> Result<u64, Error> {
+        let owner_uid = current_owner_uid().await?;
+        let retention = to_retention_delete(Dialect::Postgres, owner_uid, document_id, keep_last, expired_before);
+        let (sql, params) = match retention {
+            Some(retention) => retention,
+            None => return Ok(0),
+        };
+        let mut query = sqlx::query(&sql);
+        for param in params {
+            query = query.bind(param);
+        }
+        let deleted = query.execute(self.inner.get_pool()).await?.rows_affected();
+        tracing::info!("Deleted the revisions of document: {}, result: {:?}", document_id, deleted);
+        Ok(deleted)
+    }
+
+    async fn select_raw(&self, after_id: i64, limit: u32) -> Result<Vec<DocumentRevision>, Error> {
+        let builder = QueryBuilder::new("document_revisions", DOCUMENT_REVISION_COLUMNS);
+        self.inner.select_after(&builder, after_id, limit).await
+    }
+
+    async fn insert_raw(&self, mut revision: DocumentRevision) -> Result<i64, Error> {
+        revision.content = compress::encode_content(revision.content, &self.compression)?;
+        let builder = QueryBuilder::new("document_revisions", DOCUMENT_REVISION_COLUMNS);
+        let del_flag = GenericValue::Int32(revision.base.del_flag.unwrap_or(0));
+        self.inner.insert_bean_with(&builder, &revision, vec![("del_flag".to_string(), del_flag)]).await
+    }
+
+    async fn delete_raw(&self, id: i64) -> Result<u64, Error> {
+        let builder = QueryBuilder::new("document_revisions", DOCUMENT_REVISION_COLUMNS).and("id", Operator::Eq, id)?;
+        self.inner.delete(&builder).await
+    }
+}
@@ -0,0 +1,273 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use anyhow::{ Error, Ok };
+use axum::async_trait;
+
+use crate::config::config_serve::{ CompressionProperties, DbProperties };
+use crate::utils::compress;
+use crate::errors::BizError;
+use crate::types::document_revision::DocumentRevision;
+use crate::types::PageRequest;
+use crate::types::PageResponse;
+use crate::utils::types::GenericValue;
+use super::{ AsyncRepository, current_owner_uid };
+use super::query::{ Dialect, Operator, QueryBuilder };
+use super::sqlite::SQLiteRepository;
+
+// The whitelist columns of the table 'document_revisions'.
+pub const DOCUMENT_REVISION_COLUMNS: &[&str] = &[
+    "id", "status", "create_by", "create_time", "update_by", "update_time", "del_flag", "version",
+    "owner_uid", "document_id", "content", "size",
+];
+
+// The statement deleting the revisions of the document out of the retention and the parameters, which
+// is none if no retention, see: AsyncRepository::delete_revisions_before
+pub(crate) fn to_retention_delete(
+    dialect: Dialect,
+    owner_uid: i64,
+    document_id: i64,
+    keep_last: Option<u32>,
+    expired_before: Option<i64>
+) -> Option<(String, Vec<i64>)> {
+    if keep_last.is_none() && expired_before.is_none() {
+        return None;
+    }
+    let mut params = vec![owner_uid, document_id];
+    let next = |params: &mut Vec<i64>, value: i64| {
+        params.push(value);
+        dialect.placeholder(params.len())
+    };
+    let latest = |params: &mut Vec<i64>, n: i64| {
+        format!(
+            "SELECT id FROM document_revisions WHERE owner_uid = {} AND document_id = {} \
+             ORDER BY create_time DESC, id DESC LIMIT {}",
+            next(params, owner_uid),
+            next(params, document_id),
+            next(params, n)
+        )
+    };
+    let mut sql = format!(
+        "DELETE FROM document_revisions WHERE owner_uid = {} AND document_id = {} AND id NOT IN ({})",
+        dialect.placeholder(1),
+        dialect.placeholder(2),
+        latest(&mut params, 1)
+    );
+    let mut retentions = Vec::new();
+    if let Some(n) = keep_last {
+        retentions.push(format!("id NOT IN ({})", latest(&mut params, n.max(1) as i64)));
+    }
+    if let Some(time) = expired_before {
+        params.push(time);
+        retentions.push(format!("create_time < {}", dialect.placeholder(params.len())));
+    }
+    sql.push_str(&format!(" AND {}", retentions.join(" AND ")));
+    Some((sql, params))
+}
+
+pub struct DocumentRevisionSQLiteRepository {
+    inner: SQLiteRepository<DocumentRevision>,
+    compression: CompressionProperties,
+}
+
+impl DocumentRevisionSQLiteRepository {
+    pub async fn new(config: &DbProperties) -> Result<Self, Error> {
+        Ok(DocumentRevisionSQLiteRepository {
+            inner: SQLiteRepository::new(config).await?,
+            compression: config.compression.to_owned(),
+        })
+    }
+
+    // The query builder scoped to the owner (current user).
+    async fn owned_builder(&self) -> Result<QueryBuilder, Error> {
+        QueryBuilder::new("document_revisions", DOCUMENT_REVISION_COLUMNS).and(
+            "owner_uid",
+            Operator::Eq,
+            current_owner_uid().await?
+        )
+    }
+}
+
+#[async_trait]
+impl AsyncRepository<DocumentRevision> for DocumentRevisionSQLiteRepository {
+    async fn select(
+        &self,
+        revision: DocumentRevision,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<DocumentRevision>), Error> {
+        // Newest first, the id is the tiebreaker of the same millis.
+        let builder = self.owned_builder().await?
+            .and_bean(&revision)?
+            .order_by("create_time", true)?
+            .order_by("id", true)?;
+        let result = self.inner.select_page(&builder, &page).await?;
+
+        tracing::info!("query document revisions: {:?}", result.0);
+        Ok((result.0, result.1))
+    }
+
+    async fn select_by_id(&self, id: i64) -> Result<DocumentRevision, Error> {
+        let builder = self.owned_builder().await?.and("id", Operator::Eq, id)?;
+        let revision = self.inner
+            .select_one(&builder).await?
+            .ok_or_else(|| BizError::NotFound("document revision".to_string()))?;
+        Ok(revision)
+    }
+
+    async fn insert(&self, mut revision: DocumentRevision) -> Result<i64, Error> {
+        revision.owner_uid = Some(current_owner_uid().await?);
+        revision.base.pre_insert(None).await;
+        revision.content = compress::encode_content(revision.content, &self.compression)?;
+        let builder = QueryBuilder::new("document_revisions", DOCUMENT_REVISION_COLUMNS);
+        let inserted_id = self.inner.insert_bean(&builder, &revision).await?;
+        tracing::info!("Inserted document revision.id: {:?}", inserted_id);
+        Ok(inserted_id)
+    }
+
+    async fn update(&self, _: DocumentRevision) -> Result<i64, Error> {
+        Err(Error::msg("The document revision is immutable"))
+    }
+
+    async fn delete_all(&self) -> Result<u64, Error> {
+        let deleted = self.inner.delete(&self.owned_builder().await?).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    async fn delete_by_id(&self, id: i64) -> Result<u64, Error> {
+        let builder = self.owned_builder().await?.and("id", Operator::Eq, id)?;
+        let deleted = self.inner.delete(&builder).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    async fn delete_revisions_before(
+        &self,
+        document_id: i64,
+        keep_last: Option<u32>,
+        expired_before: Option<i64>
+    ) -> Result<u64, Error> {
+        let owner_uid = current_owner_uid().await?;
+        let retention = to_retention_delete(Dialect::SQLite, owner_uid, document_id, keep_last, expired_before);
+        let (sql, params) = match retention {
+            Some(retention) => retention,
+            None => return Ok(0),
+        };
+        let mut query = sqlx::query(&sql);
+        for param in params {
+            query = query.bind(param);
+        }
+        let deleted = query.execute(self.inner.get_pool()).await?.rows_affected();
+        tracing::info!("Deleted the revisions of document: {}, result: {:?}", document_id, deleted);
+        Ok(deleted)
+    }
+
+    async fn select_raw(&self, after_id: i64, limit: u32) -> Result<Vec<DocumentRevision>, Error> {
+        let builder = QueryBuilder::new("document_revisions", DOCUMENT_REVISION_COLUMNS);
+        self.inner.select_after(&builder, after_id, limit).await
+    }
+
+    async fn insert_raw(&self, mut revision: DocumentRevision) -> Result<i64, Error> {
+        revision.content = compress::encode_content(revision.content, &self.compression)?;
+        let builder = QueryBuilder::new("document_revisions", DOCUMENT_REVISION_COLUMNS);
+        let del_flag = GenericValue::Int32(revision.base.del_flag.unwrap_or(0));
+        self.inner.insert_bean_with(&builder, &revision, vec![("del_flag".to_string(), del_flag)]).await
+    }
+
+    async fn delete_raw(&self, id: i64) -> Result<u64, Error> {
+        let builder = QueryBuilder::new("document_revisions", DOCUMENT_REVISION_COLUMNS).and("id", Operator::Eq, id)?;
+        self.inner.delete(&builder).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::auth::PrincipalType;
+    use crate::types::BaseBean;
+    use crate::types::document_revision::QueryDocumentRevisionRequest;
+    use crate::utils::auths::{ AuthUserClaims, SecurityContext };
+
+    fn create_test_config() -> DbProperties {
+        let dir = std::env::temp_dir().join(format!("mywebnote-{}", uuid::Uuid::new_v4()));
+        let mut config = DbProperties::default();
+        config.sqlite.dir = Some(dir.to_string_lossy().to_string());
+        config
+    }
+
+    fn user(uid: i64) -> Option<AuthUserClaims> {
+        Some(AuthUserClaims {
+            ptype: PrincipalType::Password,
+            uid,
+            uname: format!("user{}", uid),
+            email: format!("user{}@mywebnote.local", uid),
+            exp: 0,
+            ext: None,
+        })
+    }
+
+    fn revision(id: i64, owner_uid: i64, document_id: i64, create_time: i64) -> DocumentRevision {
+        let mut revision = DocumentRevision::new(document_id, Some(format!("v{}", id)));
+        revision.base = BaseBean { create_time: Some(create_time), ..BaseBean::new_default(Some(id)) };
+        revision.owner_uid = Some(owner_uid);
+        revision
+    }
+
+    #[tokio::test]
+    async fn test_delete_revisions_before() {
+        let repo = DocumentRevisionSQLiteRepository::new(&create_test_config()).await.unwrap();
+        for (id, owner_uid, document_id, create_time) in [
+            (1, 1, 10, 100),
+            (2, 1, 10, 200),
+            (3, 1, 10, 300),
+            (4, 1, 10, 400),
+            (5, 1, 11, 100),
+            (6, 2, 10, 100),
+        ] {
+            repo.insert_raw(revision(id, owner_uid, document_id, create_time)).await.unwrap();
+        }
+        let remaining = |revisions: Vec<DocumentRevision>| -> Vec<i64> {
+            let mut ids: Vec<i64> = revisions.iter().map(|r| r.base.id.unwrap()).collect();
+            ids.sort();
+            ids
+        };
+
+        SecurityContext::scope(user(1), async {
+            assert_eq!(repo.delete_revisions_before(10, None, None).await.unwrap(), 0);
+            // Both out of the latest 3 and expired, the expired within the latest 3 is retained.
+            assert_eq!(repo.delete_revisions_before(10, Some(3), Some(250)).await.unwrap(), 1);
+            let param = QueryDocumentRevisionRequest { document_id: 10 }.to_revision();
+            let (_, revisions) = repo.select(param.clone(), PageRequest::default()).await.unwrap();
+            assert_eq!(remaining(revisions), vec![2, 3, 4]);
+            // And the not expired out of the latest 1 is retained as well.
+            assert_eq!(repo.delete_revisions_before(10, Some(1), Some(250)).await.unwrap(), 1);
+            let (_, revisions) = repo.select(param, PageRequest::default()).await.unwrap();
+            assert_eq!(remaining(revisions), vec![3, 4]);
+            assert_eq!(repo.delete_revisions_before(10, None, Some(i64::MAX)).await.unwrap(), 1);
+            assert_eq!(repo.delete_revisions_before(10, Some(0), None).await.unwrap(), 0);
+        }).await;
+
+        // The others are untouched.
+        let revisions = repo.select_raw(0, 100).await.unwrap();
+        assert_eq!(remaining(revisions), vec![4, 5, 6]);
+    }
+}
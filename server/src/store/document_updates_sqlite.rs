@@ -0,0 +1,129 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use anyhow::{ Error, Ok };
+use axum::async_trait;
+
+use crate::config::config_serve::DbProperties;
+use crate::errors::BizError;
+use crate::types::document_update::DocumentUpdate;
+use crate::types::PageRequest;
+use crate::types::PageResponse;
+use crate::utils::types::GenericValue;
+use super::{ AsyncRepository, current_owner_uid };
+use super::query::{ Operator, QueryBuilder };
+use super::sqlite::SQLiteRepository;
+
+// The whitelist columns of the table 'document_updates'.
+pub const DOCUMENT_UPDATE_COLUMNS: &[&str] = &[
+    "id", "status", "create_by", "create_time", "update_by", "update_time", "del_flag", "version",
+    "owner_uid", "document_id", "data",
+];
+
+pub struct DocumentUpdateSQLiteRepository {
+    inner: SQLiteRepository<DocumentUpdate>,
+}
+
+impl DocumentUpdateSQLiteRepository {
+    pub async fn new(config: &DbProperties) -> Result<Self, Error> {
+        Ok(DocumentUpdateSQLiteRepository {
+            inner: SQLiteRepository::new(config).await?,
+        })
+    }
+
+    // The query builder scoped to the owner (current user).
+    async fn owned_builder(&self) -> Result<QueryBuilder, Error> {
+        QueryBuilder::new("document_updates", DOCUMENT_UPDATE_COLUMNS).and(
+            "owner_uid",
+            Operator::Eq,
+            current_owner_uid().await?
+        )
+    }
+}
+
+#[async_trait]
+impl AsyncRepository<DocumentUpdate> for DocumentUpdateSQLiteRepository {
+    async fn select(
+        &self,
+        update: DocumentUpdate,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<DocumentUpdate>), Error> {
+        // Oldest first, the order of the appended.
+        let builder = self.owned_builder().await?
+            .and_bean(&update)?
+            .order_by("id", false)?;
+        let result = self.inner.select_page(&builder, &page).await?;
+
+        tracing::info!("query document updates: {:?}", result.0);
+        Ok((result.0, result.1))
+    }
+
+    async fn select_by_id(&self, id: i64) -> Result<DocumentUpdate, Error> {
+        let builder = self.owned_builder().await?.and("id", Operator::Eq, id)?;
+        let update = self.inner
+            .select_one(&builder).await?
+            .ok_or_else(|| BizError::NotFound("document update".to_string()))?;
+        Ok(update)
+    }
+
+    async fn insert(&self, mut update: DocumentUpdate) -> Result<i64, Error> {
+        update.owner_uid = Some(current_owner_uid().await?);
+        update.base.pre_insert(None).await;
+        let builder = QueryBuilder::new("document_updates", DOCUMENT_UPDATE_COLUMNS);
+        let inserted_id = self.inner.insert_bean(&builder, &update).await?;
+        tracing::info!("Inserted document update.id: {:?}", inserted_id);
+        Ok(inserted_id)
+    }
+
+    async fn update(&self, _: DocumentUpdate) -> Result<i64, Error> {
+        Err(Error::msg("The document update is immutable"))
+    }
+
+    async fn delete_all(&self) -> Result<u64, Error> {
+        let deleted = self.inner.delete(&self.owned_builder().await?).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    async fn delete_by_id(&self, id: i64) -> Result<u64, Error> {
+        let builder = self.owned_builder().await?.and("id", Operator::Eq, id)?;
+        let deleted = self.inner.delete(&builder).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    async fn select_raw(&self, after_id: i64, limit: u32) -> Result<Vec<DocumentUpdate>, Error> {
+        let builder = QueryBuilder::new("document_updates", DOCUMENT_UPDATE_COLUMNS);
+        self.inner.select_after(&builder, after_id, limit).await
+    }
+
+    async fn insert_raw(&self, update: DocumentUpdate) -> Result<i64, Error> {
+        let builder = QueryBuilder::new("document_updates", DOCUMENT_UPDATE_COLUMNS);
+        let del_flag = GenericValue::Int32(update.base.del_flag.unwrap_or(0));
+        self.inner.insert_bean_with(&builder, &update, vec![("del_flag".to_string(), del_flag)]).await
+    }
+
+    async fn delete_raw(&self, id: i64) -> Result<u64, Error> {
+        let builder = QueryBuilder::new("document_updates", DOCUMENT_UPDATE_COLUMNS).and("id", Operator::Eq, id)?;
+        self.inner.delete(&builder).await
+    }
+}
@@ -20,32 +20,105 @@
  * This includes modifications and derived works.
  */
 
+use std::collections::BTreeSet;
 use std::sync::Arc;
 
 use anyhow::Error;
 use axum::async_trait;
 
-use mongodb::Collection;
-use mongodb::bson::doc;
+use futures::stream::TryStreamExt;
+use mongodb::{ Collection, IndexModel };
+use mongodb::bson::{ self, doc };
+use mongodb::options::IndexOptions;
+use tokio::sync::OnceCell;
 
-use crate::config::config_serve::DbProperties;
+use crate::config::config_serve::{ CompressionProperties, DbProperties };
+use crate::utils::compress;
+use crate::errors::BizError;
 use crate::types::document::Document;
-use crate::types::{ PageRequest, PageResponse };
-use super::AsyncRepository;
-use super::mongo::MongoRepository;
+use crate::types::tag::TagMatch;
+use crate::types::{ PageRequest, PageResponse, SearchHit };
+use crate::utils::types::GenericValue;
+use super::{ AsyncRepository, AsyncSearchRepository, current_owner_uid, with_audit_values };
+use super::search;
+use super::mongo::{
+    MongoRepository,
+    bean_filter,
+    del_flag_filter,
+    del_flag_update,
+    delete_by_id,
+    find_after,
+    find_page,
+    insert_with,
+    to_bson_value,
+};
+use super::documents_sqlite::DOCUMENT_COLUMNS;
 use crate::{ dynamic_mongo_query, dynamic_mongo_insert, dynamic_mongo_update };
 
 pub struct DocumentMongoRepository {
     #[allow(unused)]
     inner: Arc<MongoRepository<Document>>,
     collection: Collection<Document>,
+    // The text index is lazily created on first searching or indexing, because of the
+    // repository is created even if the mongo isn't the configured DbType.
+    text_index: OnceCell<()>,
+    compression: CompressionProperties,
 }
 
 impl DocumentMongoRepository {
     pub async fn new(config: &DbProperties) -> Result<Self, Error> {
         let inner = Arc::new(MongoRepository::new(config).await?);
         let collection = inner.get_database().collection("documents");
-        Ok(DocumentMongoRepository { inner, collection })
+        Ok(DocumentMongoRepository {
+            inner,
+            collection,
+            text_index: OnceCell::new(),
+            compression: config.compression.to_owned(),
+        })
+    }
+
+    async fn ensure_text_index(&self) -> Result<(), Error> {
+        self.text_index.get_or_try_init(|| async {
+            let options = IndexOptions::builder()
+                .name("documents_text".to_string())
+                .weights(doc! { "name": 10, "search_text": 1 })
+                .build();
+            let index = IndexModel::builder()
+                .keys(doc! { "name": "text", "search_text": "text" })
+                .options(options)
+                .build();
+            self.collection.create_index(index).await?;
+            Ok::<(), Error>(())
+        }).await?;
+        Ok(())
+    }
+
+    // Sync the searchable text of the saved document, which re-read for the partial updated.
+    async fn reindex(&self, id: i64) -> Result<(), Error> {
+        self.ensure_text_index().await?;
+        let filter = doc! { "id": id, "owner_uid": current_owner_uid().await? };
+        if let Some(document) = self.collection.find_one(filter.clone()).await? {
+            let search_text = search::extract_text(&document.doc_type, &document.content);
+            self.collection.update_one(filter, doc! { "$set": { "search_text": search_text } }).await?;
+        }
+        Ok(())
+    }
+
+    // The ids of the documents tagged with any or all of the tags, which are grouped by the pipeline of
+    // the 'document_tags', so that only the matched ids are fetched instead of all the relations.
+    async fn tagged_ids(&self, owner_uid: i64, tag_ids: Vec<i64>, tag_match: TagMatch) -> Result<Vec<i64>, Error> {
+        let tag_ids: Vec<i64> = tag_ids.into_iter().collect::<BTreeSet<i64>>().into_iter().collect();
+        let count = tag_ids.len() as i64;
+        let mut pipeline = vec![
+            doc! { "$match": { "owner_uid": owner_uid, "tag_id": { "$in": tag_ids } } },
+            doc! { "$group": { "_id": "$document_id", "tags": { "$addToSet": "$tag_id" } } }
+        ];
+        if tag_match == TagMatch::All {
+            pipeline.push(doc! { "$match": { "tags": { "$size": count } } });
+        }
+        let relations = self.inner.get_database().collection::<bson::Document>("document_tags");
+        let grouped: Vec<bson::Document> = relations.aggregate(pipeline).await?.try_collect().await?;
+        Ok(grouped.iter().filter_map(|group| group.get_i64("_id").ok()).collect())
     }
 }
 
@@ -56,7 +129,17 @@ impl AsyncRepository<Document> for DocumentMongoRepository {
         document: Document,
         page: PageRequest
     ) -> Result<(PageResponse, Vec<Document>), Error> {
-        match dynamic_mongo_query!(document, self.collection, "update_time", page, Document) {
+        match dynamic_mongo_query!(
+            document,
+            self.collection,
+            Some(current_owner_uid().await?),
+            Some(0),
+            None,
+            "update_time",
+            DOCUMENT_COLUMNS,
+            page,
+            Document
+        ) {
             Ok(result) => {
                 tracing::info!("query documents: {:?}", result);
                 Ok((result.0, result.1))
@@ -65,30 +148,264 @@ impl AsyncRepository<Document> for DocumentMongoRepository {
         }
     }
 
+    async fn select_summary(
+        &self,
+        document: Document,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<Document>), Error> {
+        dynamic_mongo_query!(
+            document,
+            self.collection,
+            Some(current_owner_uid().await?),
+            Some(0),
+            Some(doc! { "content": 0, "search_text": 0 }),
+            "update_time",
+            DOCUMENT_COLUMNS,
+            page,
+            Document
+        )
+    }
+
+    async fn select_within(
+        &self,
+        ids: Vec<i64>,
+        document: Document,
+        page: PageRequest,
+        summary: bool
+    ) -> Result<(PageResponse, Vec<Document>), Error> {
+        let mut filter = bean_filter(&document);
+        filter.insert("id", doc! { "$in": ids });
+        filter.insert("owner_uid", current_owner_uid().await?);
+        filter.extend(del_flag_filter(0));
+        let projection = if summary { Some(doc! { "content": 0, "search_text": 0 }) } else { None };
+        find_page(&self.collection, filter, projection, "update_time", DOCUMENT_COLUMNS, &page).await
+    }
+
+    async fn select_tagged(
+        &self,
+        tag_ids: Vec<i64>,
+        tag_match: TagMatch,
+        document: Document,
+        page: PageRequest,
+        summary: bool
+    ) -> Result<(PageResponse, Vec<Document>), Error> {
+        let ids = self.tagged_ids(current_owner_uid().await?, tag_ids, tag_match).await?;
+        self.select_within(ids, document, page, summary).await
+    }
+
     async fn select_by_id(&self, id: i64) -> Result<Document, Error> {
-        let filter = doc! { "id": id };
+        let mut filter = doc! { "id": id, "owner_uid": current_owner_uid().await? };
+        filter.extend(del_flag_filter(0));
         let document = self.collection
             .find_one(filter).await?
-            .ok_or_else(|| Error::msg("Document not found"))?;
+            .ok_or_else(|| BizError::NotFound("document".to_string()))?;
         Ok(document)
     }
 
     async fn insert(&self, mut document: Document) -> Result<i64, Error> {
-        dynamic_mongo_insert!(document, self.collection)
+        document.owner_uid = Some(current_owner_uid().await?);
+        document.content = compress::encode_content(document.content, &self.compression)?;
+        let inserted_id: Result<i64, Error> = dynamic_mongo_insert!(document, self.collection);
+        let inserted_id = inserted_id?;
+        self.reindex(inserted_id).await?;
+        Ok(inserted_id)
     }
 
     async fn update(&self, mut document: Document) -> Result<i64, Error> {
-        dynamic_mongo_update!(document, self.collection)
+        document.content = compress::encode_content(document.content, &self.compression)?;
+        let updated_id: Result<i64, Error> = dynamic_mongo_update!(
+            document,
+            self.collection,
+            Some(current_owner_uid().await?),
+            Some(0)
+        );
+        let updated_id = updated_id?;
+        if updated_id > 0 {
+            self.reindex(updated_id).await?;
+        }
+        Ok(updated_id)
     }
 
     async fn delete_all(&self) -> Result<u64, Error> {
-        let result = self.collection.delete_many(doc! {}).await?;
+        let result = self.collection.delete_many(
+            doc! { "owner_uid": current_owner_uid().await? }
+        ).await?;
         Ok(result.deleted_count)
     }
 
+    // Soft delete to the trash.
     async fn delete_by_id(&self, id: i64) -> Result<u64, Error> {
-        let filter = doc! { "id": id };
+        let mut filter = doc! { "id": id, "owner_uid": current_owner_uid().await? };
+        filter.extend(del_flag_filter(0));
+        let result = self.collection.update_one(filter, del_flag_update(1).await).await?;
+        Ok(result.matched_count)
+    }
+
+    async fn select_trash(
+        &self,
+        document: Document,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<Document>), Error> {
+        dynamic_mongo_query!(
+            document,
+            self.collection,
+            Some(current_owner_uid().await?),
+            Some(1),
+            None,
+            "update_time",
+            DOCUMENT_COLUMNS,
+            page,
+            Document
+        )
+    }
+
+    async fn select_trash_before(&self, time: i64, limit: u32) -> Result<Vec<Document>, Error> {
+        let filter = doc! { "del_flag": 1, "update_time": { "$lt": time } };
+        let cursor = self.collection
+            .find(filter)
+            .sort(doc! { "update_time": 1 })
+            .limit(limit as i64).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn restore_by_id(&self, id: i64) -> Result<u64, Error> {
+        let filter = doc! { "id": id, "owner_uid": current_owner_uid().await?, "del_flag": 1 };
+        let result = self.collection.update_one(filter, del_flag_update(0).await).await?;
+        Ok(result.matched_count)
+    }
+
+    async fn purge_by_id(&self, id: i64) -> Result<u64, Error> {
+        let filter = doc! { "id": id, "owner_uid": current_owner_uid().await?, "del_flag": 1 };
         let result = self.collection.delete_one(filter).await?;
         Ok(result.deleted_count)
     }
+
+    // Notice: The content isn't supported, which is compressed by the 'update'.
+    async fn update_fields(&self, id: i64, fields: Vec<(String, GenericValue)>) -> Result<u64, Error> {
+        if fields.iter().any(|(key, _)| key == "content") {
+            return Err(Error::msg("The content of document must be updated by the 'update'"));
+        }
+        let reindex = fields.iter().any(|(key, _)| key == "name");
+        let mut filter = doc! { "id": id, "owner_uid": current_owner_uid().await? };
+        filter.extend(del_flag_filter(0));
+        let mut set = bson::Document::new();
+        for (key, value) in with_audit_values(fields).await {
+            set.insert(key, to_bson_value(value));
+        }
+        let update = doc! { "$set": set, "$inc": { "version": 1_i64 } };
+        let result = self.collection.update_one(filter, update).await?;
+        if result.matched_count > 0 && reindex {
+            self.reindex(id).await?;
+        }
+        Ok(result.matched_count)
+    }
+
+    async fn update_fields_within(&self, ids: Vec<i64>, fields: Vec<(String, GenericValue)>) -> Result<u64, Error> {
+        if fields.iter().any(|(key, _)| key == "content") {
+            return Err(Error::msg("The content of document must be updated by the 'update'"));
+        }
+        let reindex = fields.iter().any(|(key, _)| key == "name");
+        let mut filter = doc! { "id": { "$in": ids.clone() }, "owner_uid": current_owner_uid().await? };
+        filter.extend(del_flag_filter(0));
+        let mut set = bson::Document::new();
+        for (key, value) in with_audit_values(fields).await {
+            set.insert(key, to_bson_value(value));
+        }
+        let update = doc! { "$set": set, "$inc": { "version": 1_i64 } };
+        let result = self.collection.update_many(filter, update).await?;
+        if result.matched_count > 0 && reindex {
+            for id in ids {
+                self.reindex(id).await?;
+            }
+        }
+        Ok(result.matched_count)
+    }
+
+    async fn select_raw(&self, after_id: i64, limit: u32) -> Result<Vec<Document>, Error> {
+        let documents = find_after(&self.collection, after_id, limit).await?;
+        Ok(
+            documents
+                .into_iter()
+                .map(|(mut document, del_flag)| {
+                    document.base.del_flag = Some(del_flag);
+                    document
+                })
+                .collect()
+        )
+    }
+
+    async fn insert_raw(&self, mut document: Document) -> Result<i64, Error> {
+        self.ensure_text_index().await?;
+        let search_text = search::extract_text(&document.doc_type, &document.content);
+        let del_flag = document.base.del_flag.unwrap_or(0);
+        document.content = compress::encode_content(document.content, &self.compression)?;
+        insert_with(&self.collection, &document, doc! { "del_flag": del_flag, "search_text": search_text }).await?;
+        Ok(document.base.id.unwrap_or(-1))
+    }
+
+    async fn delete_raw(&self, id: i64) -> Result<u64, Error> {
+        delete_by_id(&self.collection, id).await
+    }
+
+    fn searcher(&self) -> Option<&dyn AsyncSearchRepository<Document>> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl AsyncSearchRepository<Document> for DocumentMongoRepository {
+    async fn search(
+        &self,
+        query: &str,
+        document: Document,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<SearchHit<Document>>), Error> {
+        self.ensure_text_index().await?;
+        let terms = search::to_terms(query);
+        let mut filter = doc! {
+            "$text": { "$search": search::to_mongo_text_query(&terms) },
+            "owner_uid": current_owner_uid().await?,
+        };
+        filter.extend(del_flag_filter(0));
+        if let Some(folder_key) = document.folder_key.filter(|k| !k.is_empty()) {
+            filter.insert("folder_key", folder_key);
+        }
+        if let Some(doc_type) = document.doc_type {
+            filter.insert("type", bson::to_bson(&doc_type)?);
+        }
+
+        // Queries to get total count.
+        let total_count = self.collection.count_documents(filter.clone()).await?;
+
+        // Queries to get data, with the relevance score of text search.
+        let cursor = self.collection
+            .clone_with_type::<bson::Document>()
+            .find(filter)
+            .projection(doc! { "score": { "$meta": "textScore" } })
+            .sort(doc! { "score": { "$meta": "textScore" } })
+            .skip(page.get_offset() as u64)
+            .limit(page.get_limit() as i64).await?;
+        let rows: Vec<bson::Document> = cursor.try_collect().await?;
+
+        let mut hits = Vec::with_capacity(rows.len());
+        for row in rows {
+            let score = row.get_f64("score").unwrap_or_default();
+            let search_text = row.get_str("search_text").unwrap_or_default().to_string();
+            let mut data: Document = bson::from_document(row)?;
+            // The content is omitted from the hits, which could be fetched by id.
+            data.content = None;
+            let highlights = [data.name.to_owned().unwrap_or_default(), search_text]
+                .iter()
+                .filter_map(|text| search::highlight(text, &terms))
+                .collect();
+            hits.push(SearchHit { data, score, highlights });
+        }
+
+        let page = PageResponse::new(
+            Some(total_count as i64),
+            Some(page.get_num()),
+            Some(page.get_limit())
+        );
+        Ok((page, hits))
+    }
 }
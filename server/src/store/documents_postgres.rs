@@ -0,0 +1,483 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use anyhow::{ Error, Ok };
+use axum::async_trait;
+use sqlx::{ PgPool, Row };
+
+use crate::config::config_serve::{ CompressionProperties, DbProperties };
+use crate::errors::BizError;
+use crate::types::document::Document;
+use crate::types::tag::TagMatch;
+use crate::types::{ PageRequest, PageResponse, SearchHit };
+use crate::utils::{ compress, types::GenericValue };
+use super::{ AsyncRepository, AsyncSearchRepository, current_owner_uid, with_audit_values };
+use super::query::{ Dialect, Operator, QueryBuilder };
+use super::search;
+use super::sqlite::del_flag_values;
+use super::postgres::{ PostgresRepository, to_arguments };
+use super::documents_sqlite::{ DOCUMENT_COLUMNS, and_tagged };
+
+pub struct DocumentPostgresRepository {
+    inner: PostgresRepository<Document>,
+    compression: CompressionProperties,
+}
+
+impl DocumentPostgresRepository {
+    pub fn new(config: &DbProperties, pool: PgPool) -> Self {
+        DocumentPostgresRepository {
+            inner: PostgresRepository::new(pool),
+            compression: config.compression.to_owned(),
+        }
+    }
+
+    // The query builder scoped to the owner (current user).
+    async fn owned_builder(&self) -> Result<QueryBuilder, Error> {
+        QueryBuilder::new("documents", DOCUMENT_COLUMNS).and("owner_uid", Operator::Eq, current_owner_uid().await?)
+    }
+
+    // Select the page of the documents matched by the builder, see: 'select_within'
+    async fn select_matched(
+        &self,
+        mut builder: QueryBuilder,
+        document: Document,
+        page: PageRequest,
+        summary: bool
+    ) -> Result<(PageResponse, Vec<Document>), Error> {
+        if summary {
+            builder = builder.select_without(&["content"])?;
+        }
+        let builder = builder
+            .and("del_flag", Operator::Eq, 0)?
+            .and_bean(&document)?
+            .order_by("update_time", true)?;
+        self.inner.select_page(&builder, &page).await
+    }
+
+    // Sync the searchable text of the saved document, which re-read for the partial updated.
+    async fn reindex(&self, id: i64) -> Result<(), Error> {
+        let builder = self.owned_builder().await?.and("id", Operator::Eq, id)?;
+        let document = match self.inner.select_one(&builder).await? {
+            Some(document) => document,
+            None => return Ok(()),
+        };
+        self.index(id, &document).await
+    }
+
+    // Notice: The content of document must be the plain (not compressed).
+    async fn index(&self, id: i64, document: &Document) -> Result<(), Error> {
+        let query = sqlx
+            ::query("UPDATE documents SET search_text = $1 WHERE id = $2")
+            .bind(search::extract_text(&document.doc_type, &document.content))
+            .bind(id);
+        self.inner.execute(query).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AsyncRepository<Document> for DocumentPostgresRepository {
+    async fn select(
+        &self,
+        document: Document,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<Document>), Error> {
+        let builder = self.owned_builder().await?
+            .and("del_flag", Operator::Eq, 0)?
+            .and_bean(&document)?
+            .order_by("update_time", true)?;
+        let result = self.inner.select_page(&builder, &page).await?;
+
+        tracing::info!("query documents: {:?}", result);
+        Ok((result.0, result.1))
+    }
+
+    async fn select_summary(
+        &self,
+        document: Document,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<Document>), Error> {
+        let builder = self.owned_builder().await?
+            .select_without(&["content"])?
+            .and("del_flag", Operator::Eq, 0)?
+            .and_bean(&document)?
+            .order_by("update_time", true)?;
+        self.inner.select_page(&builder, &page).await
+    }
+
+    async fn select_within(
+        &self,
+        ids: Vec<i64>,
+        document: Document,
+        page: PageRequest,
+        summary: bool
+    ) -> Result<(PageResponse, Vec<Document>), Error> {
+        let builder = self.owned_builder().await?
+            .and_in_array("id", ids.into_iter().map(GenericValue::Int64).collect())?;
+        self.select_matched(builder, document, page, summary).await
+    }
+
+    async fn select_tagged(
+        &self,
+        tag_ids: Vec<i64>,
+        tag_match: TagMatch,
+        document: Document,
+        page: PageRequest,
+        summary: bool
+    ) -> Result<(PageResponse, Vec<Document>), Error> {
+        let owner_uid = current_owner_uid().await?;
+        let builder = and_tagged(self.owned_builder().await?, owner_uid, tag_ids, tag_match)?;
+        self.select_matched(builder, document, page, summary).await
+    }
+
+    async fn select_by_id(&self, id: i64) -> Result<Document, Error> {
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?;
+        let document = self.inner
+            .select_one(&builder).await?
+            .ok_or_else(|| BizError::NotFound("document".to_string()))?;
+
+        tracing::info!("query document: {:?}", document);
+        Ok(document)
+    }
+
+    async fn insert(&self, mut document: Document) -> Result<i64, Error> {
+        document.owner_uid = Some(current_owner_uid().await?);
+        document.base.pre_insert(None).await;
+        document.content = compress::encode_content(document.content, &self.compression)?;
+        let builder = QueryBuilder::new("documents", DOCUMENT_COLUMNS);
+        let inserted_id = self.inner.insert_bean(&builder, &document).await?;
+        self.reindex(inserted_id).await?;
+        tracing::info!("Inserted document.id: {:?}", inserted_id);
+        Ok(inserted_id)
+    }
+
+    async fn update(&self, mut document: Document) -> Result<i64, Error> {
+        document.base.pre_update(None).await;
+        document.content = compress::encode_content(document.content, &self.compression)?;
+        let id = document.base.id.ok_or_else(|| Error::msg("The document id is required for update"))?;
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?;
+        let updated_id = if self.inner.update_bean(&builder, &document).await? > 0 {
+            self.reindex(id).await?;
+            id
+        } else {
+            -1
+        };
+        tracing::info!("Updated document.id: {:?}", updated_id);
+        Ok(updated_id)
+    }
+
+    async fn delete_all(&self) -> Result<u64, Error> {
+        let deleted = self.inner.delete(&self.owned_builder().await?).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    // Soft delete to the trash.
+    async fn delete_by_id(&self, id: i64) -> Result<u64, Error> {
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?;
+        let deleted = self.inner.update_values(&builder, del_flag_values(1).await).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    async fn select_trash(
+        &self,
+        document: Document,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<Document>), Error> {
+        let builder = self.owned_builder().await?
+            .and("del_flag", Operator::Eq, 1)?
+            .and_bean(&document)?
+            .order_by("update_time", true)?;
+        self.inner.select_page(&builder, &page).await
+    }
+
+    async fn select_trash_before(&self, time: i64, limit: u32) -> Result<Vec<Document>, Error> {
+        let builder = QueryBuilder::new("documents", DOCUMENT_COLUMNS)
+            .and("del_flag", Operator::Eq, 1)?
+            .and("update_time", Operator::Lt, time)?
+            .order_by("update_time", false)?;
+        let page = PageRequest::new(1, limit);
+        Ok(self.inner.select_page(&builder, &page).await?.1)
+    }
+
+    async fn restore_by_id(&self, id: i64) -> Result<u64, Error> {
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 1)?;
+        let restored = self.inner.update_values(&builder, del_flag_values(0).await).await?;
+        tracing::info!("Restored result: {:?}", restored);
+        Ok(restored)
+    }
+
+    async fn purge_by_id(&self, id: i64) -> Result<u64, Error> {
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 1)?;
+        let purged = self.inner.delete(&builder).await?;
+        tracing::info!("Purged result: {:?}", purged);
+        Ok(purged)
+    }
+
+    // Notice: The content isn't supported, which is compressed by the 'update'.
+    async fn update_fields(&self, id: i64, fields: Vec<(String, GenericValue)>) -> Result<u64, Error> {
+        if fields.iter().any(|(key, _)| key == "content") {
+            return Err(Error::msg("The content of document must be updated by the 'update'"));
+        }
+        let reindex = fields.iter().any(|(key, _)| key == "name");
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?;
+        let updated = self.inner.update_values(&builder, with_audit_values(fields).await).await?;
+        if updated > 0 && reindex {
+            self.reindex(id).await?;
+        }
+        Ok(updated)
+    }
+
+    async fn update_fields_within(&self, ids: Vec<i64>, fields: Vec<(String, GenericValue)>) -> Result<u64, Error> {
+        if fields.iter().any(|(key, _)| key == "content") {
+            return Err(Error::msg("The content of document must be updated by the 'update'"));
+        }
+        let reindex = fields.iter().any(|(key, _)| key == "name");
+        let builder = self.owned_builder().await?
+            .and_in_array("id", ids.iter().copied().map(GenericValue::Int64).collect())?
+            .and("del_flag", Operator::Eq, 0)?;
+        let updated = self.inner.update_values(&builder, with_audit_values(fields).await).await?;
+        if updated > 0 && reindex {
+            for id in ids {
+                self.reindex(id).await?;
+            }
+        }
+        Ok(updated)
+    }
+
+    async fn select_raw(&self, after_id: i64, limit: u32) -> Result<Vec<Document>, Error> {
+        let builder = QueryBuilder::new("documents", DOCUMENT_COLUMNS);
+        self.inner.select_after(&builder, after_id, limit).await
+    }
+
+    async fn insert_raw(&self, mut document: Document) -> Result<i64, Error> {
+        let plain = document.clone();
+        document.content = compress::encode_content(document.content, &self.compression)?;
+        let builder = QueryBuilder::new("documents", DOCUMENT_COLUMNS);
+        let del_flag = GenericValue::Int32(document.base.del_flag.unwrap_or(0));
+        let values = vec![("del_flag".to_string(), del_flag)];
+        let inserted_id = self.inner.insert_bean_with(&builder, &document, values).await?;
+        self.index(inserted_id, &plain).await?;
+        Ok(inserted_id)
+    }
+
+    async fn delete_raw(&self, id: i64) -> Result<u64, Error> {
+        let builder = QueryBuilder::new("documents", DOCUMENT_COLUMNS).and("id", Operator::Eq, id)?;
+        self.inner.delete(&builder).await
+    }
+
+    fn searcher(&self) -> Option<&dyn AsyncSearchRepository<Document>> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl AsyncSearchRepository<Document> for DocumentPostgresRepository {
+    async fn search(
+        &self,
+        query: &str,
+        document: Document,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<SearchHit<Document>>), Error> {
+        let terms = search::to_terms(query);
+        let builder = self.owned_builder().await?
+            .and("del_flag", Operator::Eq, 0)?
+            .and_bean(&document)?
+            .dialect(Dialect::Postgres);
+        let (ids_sql, mut params) = builder.build_select_ids();
+        // All the terms must be matched, each of which is matched as a phrase.
+        let mut queries = Vec::with_capacity(terms.len());
+        for term in &terms {
+            params.push(GenericValue::String(term.to_owned()));
+            queries.push(format!("phraseto_tsquery('simple', {})", Dialect::Postgres.placeholder(params.len())));
+        }
+        let tsquery = if queries.is_empty() { "''::tsquery".to_string() } else { queries.join(" && ") };
+        let where_clause = format!(
+            "to_tsvector('simple', coalesce(name, '') || ' ' || coalesce(search_text, '')) @@ ({}) AND id IN ({})",
+            tsquery,
+            ids_sql
+        );
+
+        // Queries to get total count.
+        let count_sql = format!("SELECT COUNT(1) FROM documents WHERE {}", where_clause);
+        let total_count: i64 = sqlx
+            ::query_scalar_with(&count_sql, to_arguments(params.clone()))
+            .fetch_one(self.inner.get_pool()).await?;
+
+        // Queries to get data, the names are weighted higher than the content for ranking.
+        let columns = DOCUMENT_COLUMNS.iter()
+            .filter(|c| **c != "content")
+            .copied()
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sql = format!(
+            "SELECT {}, search_text, ts_rank(\
+             setweight(to_tsvector('simple', coalesce(name, '')), 'A') || \
+             setweight(to_tsvector('simple', coalesce(search_text, '')), 'D'), ({}))::float8 AS score \
+             FROM documents WHERE {} ORDER BY score DESC LIMIT {} OFFSET {}",
+            columns,
+            tsquery,
+            where_clause,
+            page.get_limit(),
+            page.get_offset()
+        );
+        let rows = sqlx::query_with(&sql, to_arguments(params)).fetch_all(self.inner.get_pool()).await?;
+        let mut hits = Vec::with_capacity(rows.len());
+        for row in rows {
+            // The content is omitted from the hits, which could be fetched by id.
+            let data: Document = sqlx::FromRow::from_row(&row)?;
+            let search_text: Option<String> = row.try_get("search_text")?;
+            let highlights = [data.name.to_owned().unwrap_or_default(), search_text.unwrap_or_default()]
+                .iter()
+                .filter_map(|text| search::highlight(text, &terms))
+                .collect();
+            hits.push(SearchHit { data, score: row.try_get("score")?, highlights });
+        }
+
+        let page = PageResponse::new(Some(total_count), Some(page.get_num()), Some(page.get_limit()));
+        Ok((page, hits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::auth::PrincipalType;
+    use crate::store::postgres::{ create_pool, create_test_config };
+    use crate::types::document::{ DocumentType, SaveDocumentRequest, SearchDocumentRequest };
+    use crate::utils::auths::{ AuthUserClaims, SecurityContext };
+
+    fn user(uid: i64) -> Option<AuthUserClaims> {
+        Some(AuthUserClaims {
+            ptype: PrincipalType::Password,
+            uid,
+            uname: format!("user{}", uid),
+            email: format!("user{}@mywebnote.local", uid),
+            exp: 0,
+            ext: None,
+        })
+    }
+
+    fn search_request(q: &str) -> (String, Document, PageRequest) {
+        let param = SearchDocumentRequest { q: q.to_string(), folder_key: None, doc_type: None };
+        (param.q.to_owned(), param.to_document(), PageRequest::default())
+    }
+
+    fn save_request(key: &str, name: &str, content: &str) -> SaveDocumentRequest {
+        SaveDocumentRequest {
+            id: None,
+            key: Some(key.to_string()),
+            name: Some(name.to_string()),
+            folder_key: None,
+            doc_type: None,
+            content: Some(content.to_string()),
+            version: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_documents_isolated_by_owner() {
+        let config = match create_test_config().await {
+            Some(config) => config,
+            None => return,
+        };
+        let repo = DocumentPostgresRepository::new(&config, create_pool(&config).await.unwrap());
+        let save = save_request("k1", "n1", "c1");
+
+        let id = SecurityContext::scope(user(1), async {
+            let id = repo.insert(save.to_document()).await.unwrap();
+            assert_eq!(repo.select_by_id(id).await.unwrap().owner_uid, Some(1));
+            id
+        }).await;
+
+        // The other user can neither read nor write.
+        SecurityContext::scope(user(2), async {
+            let err = repo.select_by_id(id).await.unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<BizError>(),
+                Some(&BizError::NotFound("document".to_string()))
+            );
+            let mut update = save.clone();
+            update.id = Some(id);
+            assert_eq!(repo.update(update.to_document()).await.unwrap(), -1);
+            assert_eq!(repo.delete_by_id(id).await.unwrap(), 0);
+            // The same key is allowed for the different owners.
+            assert!(repo.insert(save.to_document()).await.is_ok());
+        }).await;
+
+        SecurityContext::scope(user(1), async {
+            assert_eq!(repo.delete_by_id(id).await.unwrap(), 1);
+            assert_eq!(repo.restore_by_id(id).await.unwrap(), 1);
+        }).await;
+    }
+
+    #[tokio::test]
+    async fn test_search_documents() {
+        let config = match create_test_config().await {
+            Some(config) => config,
+            None => return,
+        };
+        let repo = DocumentPostgresRepository::new(&config, create_pool(&config).await.unwrap());
+
+        SecurityContext::scope(user(1), async {
+            let mut board = save_request("k1", "Roadmap", r#"{"elements":[{"type":"text","text":"quarterly planning"}]}"#);
+            board.doc_type = Some(DocumentType::Board);
+            let board_id = repo.insert(board.to_document()).await.unwrap();
+            repo.insert(save_request("k2", "Notes", "the planning of the roadmap").to_document()).await.unwrap();
+            repo.insert(save_request("k3", "Others", "nothing matched").to_document()).await.unwrap();
+
+            let searcher = repo.searcher().unwrap();
+            let (q, document, page) = search_request("roadmap");
+            let (page, hits) = searcher.search(&q, document, page).await.unwrap();
+            assert_eq!(page.total, Some(2));
+            // The name matched is ranked higher.
+            assert_eq!(hits[0].data.base.id, Some(board_id));
+            assert!(hits[0].data.content.is_none());
+            assert!(hits[0].highlights.iter().any(|h| h.contains("<mark>Roadmap</mark>")));
+
+            let (q, document, page) = search_request("quarterly planning");
+            let (page, hits) = searcher.search(&q, document, page).await.unwrap();
+            assert_eq!(page.total, Some(1));
+            assert_eq!(hits[0].data.base.id, Some(board_id));
+        }).await;
+
+        // The other user's documents are never matched.
+        SecurityContext::scope(user(2), async {
+            let (q, document, page) = search_request("roadmap");
+            let (page, _) = repo.searcher().unwrap().search(&q, document, page).await.unwrap();
+            assert_eq!(page.total, Some(0));
+        }).await;
+    }
+}
@@ -20,26 +20,112 @@
  * This includes modifications and derived works.
  */
 
+use std::collections::BTreeSet;
+
 use anyhow::{ Error, Ok };
 use axum::async_trait;
+use sqlx::Row;
 
-use crate::config::config_serve::DbProperties;
+use crate::config::config_serve::{ CompressionProperties, DbProperties };
+use crate::errors::BizError;
 use crate::types::document::Document;
-use crate::types::PageRequest;
-use crate::types::PageResponse;
-use super::AsyncRepository;
-use super::sqlite::SQLiteRepository;
+use crate::types::tag::TagMatch;
+use crate::types::{ PageRequest, PageResponse, SearchHit };
+use crate::utils::{ compress, types::GenericValue };
+use super::{ AsyncRepository, AsyncSearchRepository, current_owner_uid, with_audit_values };
+use super::query::{ Operator, QueryBuilder };
+use super::document_tags_sqlite::DOCUMENT_TAG_COLUMNS;
+use super::search::{ self, HIGHLIGHT_ELLIPSIS, HIGHLIGHT_END, HIGHLIGHT_START };
+use super::sqlite::{ SQLiteRepository, del_flag_values, to_arguments };
+
+// The whitelist columns of the table 'documents'.
+pub const DOCUMENT_COLUMNS: &[&str] = &[
+    "id", "status", "create_by", "create_time", "update_by", "update_time", "del_flag", "version",
+    "owner_uid", "key", "name", "folder_key", "type", "content",
+];
+
+// The condition of the documents tagged with any or all of the tags, which is matched by the sub query of
+// the 'document_tags', so that the tagged ids are never loaded.
+pub fn and_tagged(
+    builder: QueryBuilder,
+    owner_uid: i64,
+    tag_ids: Vec<i64>,
+    tag_match: TagMatch
+) -> Result<QueryBuilder, Error> {
+    let tag_ids: BTreeSet<i64> = tag_ids.into_iter().collect();
+    let having = match tag_match {
+        TagMatch::Any => None,
+        TagMatch::All => Some(("tag_id", tag_ids.len() as i64)),
+    };
+    let tagged = QueryBuilder::new("document_tags", DOCUMENT_TAG_COLUMNS)
+        .and("owner_uid", Operator::Eq, owner_uid)?
+        .and_in("tag_id", tag_ids.into_iter().map(GenericValue::Int64).collect())?;
+    builder.and_in_select("id", tagged, "document_id", having)
+}
 
 pub struct DocumentSQLiteRepository {
     inner: SQLiteRepository<Document>,
+    compression: CompressionProperties,
 }
 
 impl DocumentSQLiteRepository {
     pub async fn new(config: &DbProperties) -> Result<Self, Error> {
         Ok(DocumentSQLiteRepository {
             inner: SQLiteRepository::new(config).await?,
+            compression: config.compression.to_owned(),
         })
     }
+
+    // The query builder scoped to the owner (current user).
+    async fn owned_builder(&self) -> Result<QueryBuilder, Error> {
+        QueryBuilder::new("documents", DOCUMENT_COLUMNS).and("owner_uid", Operator::Eq, current_owner_uid().await?)
+    }
+
+    // Select the page of the documents matched by the builder, see: 'select_within'
+    async fn select_matched(
+        &self,
+        mut builder: QueryBuilder,
+        document: Document,
+        page: PageRequest,
+        summary: bool
+    ) -> Result<(PageResponse, Vec<Document>), Error> {
+        if summary {
+            builder = builder.select_without(&["content"])?;
+        }
+        let builder = builder
+            .and("del_flag", Operator::Eq, 0)?
+            .and_bean(&document)?
+            .order_by("update_time", true)?;
+        self.inner.select_page(&builder, &page).await
+    }
+
+    // Sync the full text index of the saved document, which re-read for the partial updated.
+    async fn reindex(&self, id: i64) -> Result<(), Error> {
+        let builder = self.owned_builder().await?.and("id", Operator::Eq, id)?;
+        let document = match self.inner.select_one(&builder).await? {
+            Some(document) => document,
+            None => return Ok(()),
+        };
+        self.index(id, &document).await
+    }
+
+    // Notice: The content of document must be the plain (not compressed).
+    async fn index(&self, id: i64, document: &Document) -> Result<(), Error> {
+        self.unindex(id).await?;
+        let query = sqlx
+            ::query("INSERT INTO documents_fts (rowid, name, content) VALUES (?, ?, ?)")
+            .bind(id)
+            .bind(document.name.to_owned().unwrap_or_default())
+            .bind(search::extract_text(&document.doc_type, &document.content));
+        self.inner.execute(query).await?;
+        Ok(())
+    }
+
+    async fn unindex(&self, id: i64) -> Result<(), Error> {
+        let query = sqlx::query("DELETE FROM documents_fts WHERE rowid = ?").bind(id);
+        self.inner.execute(query).await?;
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -49,68 +135,378 @@ impl AsyncRepository<Document> for DocumentSQLiteRepository {
         document: Document,
         page: PageRequest
     ) -> Result<(PageResponse, Vec<Document>), Error> {
-        let result = dynamic_sqlite_query!(
-            document,
-            "documents",
-            self.inner.get_pool(),
-            "update_time",
-            page,
-            Document
-        ).unwrap();
+        let builder = self.owned_builder().await?
+            .and("del_flag", Operator::Eq, 0)?
+            .and_bean(&document)?
+            .order_by("update_time", true)?;
+        let result = self.inner.select_page(&builder, &page).await?;
 
         tracing::info!("query documents: {:?}", result);
         Ok((result.0, result.1))
     }
 
+    async fn select_summary(
+        &self,
+        document: Document,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<Document>), Error> {
+        let builder = self.owned_builder().await?
+            .select_without(&["content"])?
+            .and("del_flag", Operator::Eq, 0)?
+            .and_bean(&document)?
+            .order_by("update_time", true)?;
+        self.inner.select_page(&builder, &page).await
+    }
+
+    async fn select_within(
+        &self,
+        ids: Vec<i64>,
+        document: Document,
+        page: PageRequest,
+        summary: bool
+    ) -> Result<(PageResponse, Vec<Document>), Error> {
+        let builder = self.owned_builder().await?
+            .and_in_array("id", ids.into_iter().map(GenericValue::Int64).collect())?;
+        self.select_matched(builder, document, page, summary).await
+    }
+
+    async fn select_tagged(
+        &self,
+        tag_ids: Vec<i64>,
+        tag_match: TagMatch,
+        document: Document,
+        page: PageRequest,
+        summary: bool
+    ) -> Result<(PageResponse, Vec<Document>), Error> {
+        let owner_uid = current_owner_uid().await?;
+        let builder = and_tagged(self.owned_builder().await?, owner_uid, tag_ids, tag_match)?;
+        self.select_matched(builder, document, page, summary).await
+    }
+
     async fn select_by_id(&self, id: i64) -> Result<Document, Error> {
-        let document = sqlx
-            ::query_as::<_, Document>("SELECT * FROM documents WHERE id = $1")
-            .bind(id)
-            .fetch_one(self.inner.get_pool()).await
-            .unwrap();
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?;
+        let document = self.inner
+            .select_one(&builder).await?
+            .ok_or_else(|| BizError::NotFound("document".to_string()))?;
 
         tracing::info!("query document: {:?}", document);
         Ok(document)
     }
 
     async fn insert(&self, mut document: Document) -> Result<i64, Error> {
-        let inserted_id = dynamic_sqlite_insert!(
-            document,
-            "documents",
-            self.inner.get_pool()
-        ).unwrap();
+        document.owner_uid = Some(current_owner_uid().await?);
+        document.base.pre_insert(None).await;
+        document.content = compress::encode_content(document.content, &self.compression)?;
+        let builder = QueryBuilder::new("documents", DOCUMENT_COLUMNS);
+        let inserted_id = self.inner.insert_bean(&builder, &document).await?;
+        self.reindex(inserted_id).await?;
         tracing::info!("Inserted document.id: {:?}", inserted_id);
         Ok(inserted_id)
     }
 
     async fn update(&self, mut document: Document) -> Result<i64, Error> {
-        let updated_id = dynamic_sqlite_update!(
-            document,
-            "documents",
-            self.inner.get_pool()
-        ).unwrap();
+        document.base.pre_update(None).await;
+        document.content = compress::encode_content(document.content, &self.compression)?;
+        let id = document.base.id.ok_or_else(|| Error::msg("The document id is required for update"))?;
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?;
+        let updated_id = if self.inner.update_bean(&builder, &document).await? > 0 {
+            self.reindex(id).await?;
+            id
+        } else {
+            -1
+        };
         tracing::info!("Updated document.id: {:?}", updated_id);
         Ok(updated_id)
     }
 
     async fn delete_all(&self) -> Result<u64, Error> {
-        let delete_result = sqlx
-            ::query("DELETE FROM documents")
-            .execute(self.inner.get_pool()).await
-            .unwrap();
-
-        tracing::info!("Deleted result: {:?}", delete_result);
-        Ok(delete_result.rows_affected())
+        let (ids_sql, params) = self.owned_builder().await?.build_select_ids();
+        sqlx
+            ::query_with(&format!("DELETE FROM documents_fts WHERE rowid IN ({})", ids_sql), to_arguments(params))
+            .execute(self.inner.get_pool()).await?;
+        let deleted = self.inner.delete(&self.owned_builder().await?).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
     }
 
+    // Soft delete to the trash.
     async fn delete_by_id(&self, id: i64) -> Result<u64, Error> {
-        let delete_result = sqlx
-            ::query("DELETE FROM documents WHERE id = $1")
-            .bind(id)
-            .execute(self.inner.get_pool()).await
-            .unwrap();
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?;
+        let deleted = self.inner.update_values(&builder, del_flag_values(1).await).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    async fn select_trash(
+        &self,
+        document: Document,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<Document>), Error> {
+        let builder = self.owned_builder().await?
+            .and("del_flag", Operator::Eq, 1)?
+            .and_bean(&document)?
+            .order_by("update_time", true)?;
+        self.inner.select_page(&builder, &page).await
+    }
+
+    async fn select_trash_before(&self, time: i64, limit: u32) -> Result<Vec<Document>, Error> {
+        let builder = QueryBuilder::new("documents", DOCUMENT_COLUMNS)
+            .and("del_flag", Operator::Eq, 1)?
+            .and("update_time", Operator::Lt, time)?
+            .order_by("update_time", false)?;
+        let page = PageRequest::new(1, limit);
+        Ok(self.inner.select_page(&builder, &page).await?.1)
+    }
+
+    async fn restore_by_id(&self, id: i64) -> Result<u64, Error> {
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 1)?;
+        let restored = self.inner.update_values(&builder, del_flag_values(0).await).await?;
+        tracing::info!("Restored result: {:?}", restored);
+        Ok(restored)
+    }
+
+    async fn purge_by_id(&self, id: i64) -> Result<u64, Error> {
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 1)?;
+        let purged = self.inner.delete(&builder).await?;
+        if purged > 0 {
+            self.unindex(id).await?;
+        }
+        tracing::info!("Purged result: {:?}", purged);
+        Ok(purged)
+    }
+
+    // Notice: The content isn't supported, which is compressed by the 'update'.
+    async fn update_fields(&self, id: i64, fields: Vec<(String, GenericValue)>) -> Result<u64, Error> {
+        if fields.iter().any(|(key, _)| key == "content") {
+            return Err(Error::msg("The content of document must be updated by the 'update'"));
+        }
+        let reindex = fields.iter().any(|(key, _)| key == "name");
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?;
+        let updated = self.inner.update_values(&builder, with_audit_values(fields).await).await?;
+        if updated > 0 && reindex {
+            self.reindex(id).await?;
+        }
+        Ok(updated)
+    }
+
+    async fn update_fields_within(&self, ids: Vec<i64>, fields: Vec<(String, GenericValue)>) -> Result<u64, Error> {
+        if fields.iter().any(|(key, _)| key == "content") {
+            return Err(Error::msg("The content of document must be updated by the 'update'"));
+        }
+        let reindex = fields.iter().any(|(key, _)| key == "name");
+        let builder = self.owned_builder().await?
+            .and_in_array("id", ids.iter().copied().map(GenericValue::Int64).collect())?
+            .and("del_flag", Operator::Eq, 0)?;
+        let updated = self.inner.update_values(&builder, with_audit_values(fields).await).await?;
+        if updated > 0 && reindex {
+            for id in ids {
+                self.reindex(id).await?;
+            }
+        }
+        Ok(updated)
+    }
+
+    async fn select_raw(&self, after_id: i64, limit: u32) -> Result<Vec<Document>, Error> {
+        let builder = QueryBuilder::new("documents", DOCUMENT_COLUMNS);
+        self.inner.select_after(&builder, after_id, limit).await
+    }
+
+    async fn insert_raw(&self, mut document: Document) -> Result<i64, Error> {
+        let plain = document.clone();
+        document.content = compress::encode_content(document.content, &self.compression)?;
+        let builder = QueryBuilder::new("documents", DOCUMENT_COLUMNS);
+        let del_flag = GenericValue::Int32(document.base.del_flag.unwrap_or(0));
+        let values = vec![("del_flag".to_string(), del_flag)];
+        let inserted_id = self.inner.insert_bean_with(&builder, &document, values).await?;
+        self.index(inserted_id, &plain).await?;
+        Ok(inserted_id)
+    }
+
+    async fn delete_raw(&self, id: i64) -> Result<u64, Error> {
+        let builder = QueryBuilder::new("documents", DOCUMENT_COLUMNS).and("id", Operator::Eq, id)?;
+        let deleted = self.inner.delete(&builder).await?;
+        self.unindex(id).await?;
+        Ok(deleted)
+    }
+
+    fn searcher(&self) -> Option<&dyn AsyncSearchRepository<Document>> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl AsyncSearchRepository<Document> for DocumentSQLiteRepository {
+    async fn search(
+        &self,
+        query: &str,
+        document: Document,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<SearchHit<Document>>), Error> {
+        let terms = search::to_terms(query);
+        let builder = self.owned_builder().await?
+            .and("del_flag", Operator::Eq, 0)?
+            .and_bean(&document)?;
+        let (ids_sql, ids_params) = builder.build_select_ids();
+        let mut params = vec![GenericValue::String(search::to_fts5_query(&terms))];
+        params.extend(ids_params);
+        let where_clause = format!("documents_fts MATCH ? AND documents_fts.rowid IN ({})", ids_sql);
+
+        // Queries to get total count.
+        let count_sql = format!("SELECT COUNT(1) FROM documents_fts WHERE {}", where_clause);
+        let total_count: i64 = sqlx
+            ::query_scalar_with(&count_sql, to_arguments(params.clone()))
+            .fetch_one(self.inner.get_pool()).await?;
+
+        // Queries to get data, the names are weighted higher than the content for ranking.
+        let snippet = |column: i32| {
+            format!(
+                "snippet(documents_fts, {}, '{}', '{}', '{}', 16)",
+                column,
+                HIGHLIGHT_START,
+                HIGHLIGHT_END,
+                HIGHLIGHT_ELLIPSIS
+            )
+        };
+        let sql = format!(
+            "SELECT d.*, -bm25(documents_fts, 10.0, 1.0) AS score, {} AS name_highlight, {} AS content_highlight \
+             FROM documents_fts JOIN documents d ON d.id = documents_fts.rowid \
+             WHERE {} ORDER BY score DESC LIMIT {} OFFSET {}",
+            snippet(0),
+            snippet(1),
+            where_clause,
+            page.get_limit(),
+            page.get_offset()
+        );
+        let rows = sqlx::query_with(&sql, to_arguments(params)).fetch_all(self.inner.get_pool()).await?;
+        let mut hits = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut data: Document = sqlx::FromRow::from_row(&row)?;
+            // The content is omitted from the hits, which could be fetched by id.
+            data.content = None;
+            let highlights = ["name_highlight", "content_highlight"]
+                .iter()
+                .filter_map(|c| row.try_get::<Option<String>, _>(*c).ok().flatten())
+                .filter(|h| h.contains(HIGHLIGHT_START))
+                .collect();
+            hits.push(SearchHit { data, score: row.try_get("score")?, highlights });
+        }
+
+        let page = PageResponse::new(Some(total_count), Some(page.get_num()), Some(page.get_limit()));
+        Ok((page, hits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::auth::PrincipalType;
+    use crate::types::BaseBean;
+    use crate::types::document::SaveDocumentRequest;
+    use crate::utils::auths::{ AuthUserClaims, SecurityContext };
+
+    fn create_test_config() -> DbProperties {
+        let dir = std::env::temp_dir().join(format!("mywebnote-{}", uuid::Uuid::new_v4()));
+        let mut config = DbProperties::default();
+        config.sqlite.dir = Some(dir.to_string_lossy().to_string());
+        config
+    }
+
+    fn user(uid: i64) -> Option<AuthUserClaims> {
+        Some(AuthUserClaims {
+            ptype: PrincipalType::Password,
+            uid,
+            uname: format!("user{}", uid),
+            email: format!("user{}@mywebnote.local", uid),
+            exp: 0,
+            ext: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_documents_isolated_by_owner() {
+        let repo = DocumentSQLiteRepository::new(&create_test_config()).await.unwrap();
+        let save = SaveDocumentRequest {
+            id: None,
+            key: Some("k1".to_string()),
+            name: Some("n1".to_string()),
+            folder_key: None,
+            doc_type: None,
+            content: Some("c1".to_string()),
+            version: None,
+        };
+
+        let id = SecurityContext::scope(user(1), async {
+            let id = repo.insert(save.to_document()).await.unwrap();
+            assert_eq!(repo.select_by_id(id).await.unwrap().owner_uid, Some(1));
+            id
+        }).await;
+
+        // The other user can neither read nor write.
+        SecurityContext::scope(user(2), async {
+            let err = repo.select_by_id(id).await.unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<BizError>(),
+                Some(&BizError::NotFound("document".to_string()))
+            );
+            let mut update = save.clone();
+            update.id = Some(id);
+            assert_eq!(repo.update(update.to_document()).await.unwrap(), -1);
+            assert_eq!(repo.delete_by_id(id).await.unwrap(), 0);
+            // The same key is allowed for the different owners.
+            assert!(repo.insert(save.to_document()).await.is_ok());
+        }).await;
+
+        SecurityContext::scope(user(1), async {
+            assert_eq!(repo.delete_by_id(id).await.unwrap(), 1);
+        }).await;
+
+        // Without the authenticated user.
+        let err = repo.select_by_id(id).await.unwrap_err();
+        assert_eq!(err.downcast_ref::<BizError>(), Some(&BizError::Unauthenticated));
+    }
 
-        tracing::info!("Deleted result: {:?}", delete_result);
-        Ok(delete_result.rows_affected())
+    #[tokio::test]
+    async fn test_select_within_large_ids() {
+        let repo = DocumentSQLiteRepository::new(&create_test_config()).await.unwrap();
+        let save = SaveDocumentRequest {
+            id: None,
+            key: Some("k1".to_string()),
+            name: Some("n1".to_string()),
+            folder_key: None,
+            doc_type: None,
+            content: Some("c1".to_string()),
+            version: None,
+        };
+        SecurityContext::scope(user(1), async {
+            let id = repo.insert(save.to_document()).await.unwrap();
+            // More than the limit of the parameters of SQLite (32766 by default).
+            let mut ids: Vec<i64> = (1..=40000).map(|i| -i).collect();
+            ids.push(id);
+            let any = Document {
+                base: BaseBean::new_with_id(None),
+                owner_uid: None,
+                key: None,
+                name: None,
+                folder_key: None,
+                doc_type: None,
+                content: None,
+            };
+            let (_, documents) = repo
+                .select_within(ids, any, PageRequest::default(), true).await
+                .unwrap();
+            assert_eq!(documents.into_iter().filter_map(|d| d.base.id).collect::<Vec<_>>(), vec![id]);
+        }).await;
     }
 }
@@ -25,14 +25,27 @@ use std::sync::Arc;
 use anyhow::Error;
 use axum::async_trait;
 
-use mongodb::Collection;
-use mongodb::bson::doc;
+use futures::stream::TryStreamExt;
+use mongodb::{ ClientSession, Collection };
+use mongodb::bson::{ self, doc };
 
 use crate::config::config_serve::DbProperties;
+use crate::errors::BizError;
 use crate::types::folder::Folder;
 use crate::types::{ PageRequest, PageResponse };
-use super::AsyncRepository;
-use super::mongo::MongoRepository;
+use super::{ AsyncRepository, current_owner_uid, with_audit_values };
+use super::mongo::{
+    MongoRepository,
+    bean_filter,
+    del_flag_filter,
+    del_flag_update,
+    delete_by_id,
+    find_after,
+    find_page,
+    insert_with,
+    to_bson_value,
+};
+use super::folders_sqlite::FOLDER_COLUMNS;
 use crate::{ dynamic_mongo_query, dynamic_mongo_insert, dynamic_mongo_update };
 
 pub struct FolderMongoRepository {
@@ -47,6 +60,36 @@ impl FolderMongoRepository {
         let collection = inner.get_database().collection("folders");
         Ok(FolderMongoRepository { inner, collection })
     }
+
+    // Start the session of the transaction, which is aborted when dropped without commit.
+    async fn start_transaction(&self) -> Result<ClientSession, Error> {
+        let mut session = self.inner.get_database().client().start_session().await?;
+        session.start_transaction().await?;
+        Ok(session)
+    }
+
+    // Move the owned and not trashed documents in the folder of the key to the folder of the 'folder_key'.
+    async fn move_documents(
+        &self,
+        session: &mut ClientSession,
+        key: String,
+        folder_key: Option<String>
+    ) -> Result<u64, Error> {
+        let mut filter = doc! { "owner_uid": current_owner_uid().await?, "folder_key": key };
+        filter.extend(del_flag_filter(0));
+        let folder_key = folder_key.map(bson::Bson::String).unwrap_or(bson::Bson::Null);
+        let mut set = doc! { "folder_key": folder_key };
+        for (key, value) in with_audit_values(vec![]).await {
+            set.insert(key, to_bson_value(value));
+        }
+        let update = doc! { "$set": set, "$inc": { "version": 1_i64 } };
+        let result = self.inner
+            .get_database()
+            .collection::<bson::Document>("documents")
+            .update_many(filter, update)
+            .session(session).await?;
+        Ok(result.matched_count)
+    }
 }
 
 #[async_trait]
@@ -56,7 +99,17 @@ impl AsyncRepository<Folder> for FolderMongoRepository {
         folder: Folder,
         page: PageRequest
     ) -> Result<(PageResponse, Vec<Folder>), Error> {
-        match dynamic_mongo_query!(folder, self.collection, "update_time", page, Folder) {
+        match dynamic_mongo_query!(
+            folder,
+            self.collection,
+            Some(current_owner_uid().await?),
+            Some(0),
+            None,
+            "update_time",
+            FOLDER_COLUMNS,
+            page,
+            Folder
+        ) {
             Ok(result) => {
                 tracing::info!("query folders: {:?}", result);
                 Ok((result.0, result.1))
@@ -65,30 +118,188 @@ impl AsyncRepository<Folder> for FolderMongoRepository {
         }
     }
 
+    async fn select_within(
+        &self,
+        ids: Vec<i64>,
+        folder: Folder,
+        page: PageRequest,
+        _summary: bool
+    ) -> Result<(PageResponse, Vec<Folder>), Error> {
+        let mut filter = bean_filter(&folder);
+        filter.insert("id", doc! { "$in": ids });
+        filter.insert("owner_uid", current_owner_uid().await?);
+        filter.extend(del_flag_filter(0));
+        find_page(&self.collection, filter, None, "update_time", FOLDER_COLUMNS, &page).await
+    }
+
     async fn select_by_id(&self, id: i64) -> Result<Folder, Error> {
-        let filter = doc! { "id": id };
+        let mut filter = doc! { "id": id, "owner_uid": current_owner_uid().await? };
+        filter.extend(del_flag_filter(0));
         let folder = self.collection
             .find_one(filter).await?
-            .ok_or_else(|| Error::msg("Folder not found"))?;
+            .ok_or_else(|| BizError::NotFound("folder".to_string()))?;
         Ok(folder)
     }
 
     async fn insert(&self, mut folder: Folder) -> Result<i64, Error> {
+        folder.owner_uid = Some(current_owner_uid().await?);
         dynamic_mongo_insert!(folder, self.collection)
     }
 
     async fn update(&self, mut folder: Folder) -> Result<i64, Error> {
-        dynamic_mongo_update!(folder, self.collection)
+        dynamic_mongo_update!(folder, self.collection, Some(current_owner_uid().await?), Some(0))
     }
 
     async fn delete_all(&self) -> Result<u64, Error> {
-        let result = self.collection.delete_many(doc! {}).await?;
+        let result = self.collection.delete_many(
+            doc! { "owner_uid": current_owner_uid().await? }
+        ).await?;
         Ok(result.deleted_count)
     }
 
+    // Soft delete to the trash.
     async fn delete_by_id(&self, id: i64) -> Result<u64, Error> {
-        let filter = doc! { "id": id };
+        let mut filter = doc! { "id": id, "owner_uid": current_owner_uid().await? };
+        filter.extend(del_flag_filter(0));
+        let result = self.collection.update_one(filter, del_flag_update(1).await).await?;
+        Ok(result.matched_count)
+    }
+
+    async fn select_trash(
+        &self,
+        folder: Folder,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<Folder>), Error> {
+        dynamic_mongo_query!(
+            folder,
+            self.collection,
+            Some(current_owner_uid().await?),
+            Some(1),
+            None,
+            "update_time",
+            FOLDER_COLUMNS,
+            page,
+            Folder
+        )
+    }
+
+    async fn select_trash_before(&self, time: i64, limit: u32) -> Result<Vec<Folder>, Error> {
+        let filter = doc! { "del_flag": 1, "update_time": { "$lt": time } };
+        let cursor = self.collection
+            .find(filter)
+            .sort(doc! { "update_time": 1 })
+            .limit(limit as i64).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn restore_by_id(&self, id: i64) -> Result<u64, Error> {
+        let filter = doc! { "id": id, "owner_uid": current_owner_uid().await?, "del_flag": 1 };
+        let result = self.collection.update_one(filter, del_flag_update(0).await).await?;
+        Ok(result.matched_count)
+    }
+
+    async fn purge_by_id(&self, id: i64) -> Result<u64, Error> {
+        let filter = doc! { "id": id, "owner_uid": current_owner_uid().await?, "del_flag": 1 };
         let result = self.collection.delete_one(filter).await?;
         Ok(result.deleted_count)
     }
+
+    async fn trash_tree(&self, ids: Vec<i64>, keys: Vec<String>) -> Result<u64, Error> {
+        let owner_uid = current_owner_uid().await?;
+        // All are stamped the same trashed time, so that they're restored together, see: FolderHandler::restore
+        let update = del_flag_update(1).await;
+        let mut folders = doc! { "id": { "$in": ids }, "owner_uid": owner_uid };
+        folders.extend(del_flag_filter(0));
+        let mut documents = doc! { "folder_key": { "$in": keys }, "owner_uid": owner_uid };
+        documents.extend(del_flag_filter(0));
+
+        let mut session = self.start_transaction().await?;
+        let mut trashed = self.collection
+            .update_many(folders, update.clone())
+            .session(&mut session).await?.matched_count;
+        trashed += self.inner
+            .get_database()
+            .collection::<bson::Document>("documents")
+            .update_many(documents, update)
+            .session(&mut session).await?.matched_count;
+        session.commit_transaction().await?;
+        Ok(trashed)
+    }
+
+    async fn trash_reparent(&self, folder: Folder, parent_key: Option<String>) -> Result<i64, Error> {
+        let id = folder.base.id.ok_or_else(|| Error::msg("The folder id is required for trash"))?;
+        let owner_uid = current_owner_uid().await?;
+        let mut filter = doc! { "id": id, "owner_uid": owner_uid };
+        filter.extend(del_flag_filter(0));
+        match folder.base.version {
+            Some(0) => filter.insert("version", doc! { "$in": [0_i64, bson::Bson::Null] }),
+            Some(version) => filter.insert("version", version),
+            None => None,
+        };
+
+        let mut session = self.start_transaction().await?;
+        let result = self.collection.update_one(filter, del_flag_update(1).await).session(&mut session).await?;
+        if result.matched_count == 0 {
+            return Ok(-1);
+        }
+        let mut children = doc! { "pid": id, "owner_uid": owner_uid };
+        children.extend(del_flag_filter(0));
+        let mut set = doc! { "pid": folder.pid.unwrap_or_default() };
+        for (key, value) in with_audit_values(vec![]).await {
+            set.insert(key, to_bson_value(value));
+        }
+        let update = doc! { "$set": set, "$inc": { "version": 1_i64 } };
+        self.collection.update_many(children, update).session(&mut session).await?;
+        if let Some(key) = folder.key.filter(|key| !key.is_empty()) {
+            self.move_documents(&mut session, key, parent_key).await?;
+        }
+        session.commit_transaction().await?;
+        Ok(id)
+    }
+
+    async fn update_rekey(&self, mut folder: Folder, from_key: String) -> Result<i64, Error> {
+        folder.base.pre_update(None).await;
+        let id = folder.base.id.ok_or_else(|| Error::msg("The folder id is required for update"))?;
+        let mut filter = doc! { "id": id, "owner_uid": current_owner_uid().await? };
+        filter.extend(del_flag_filter(0));
+        match folder.base.version {
+            Some(0) => filter.insert("version", doc! { "$in": [0_i64, bson::Bson::Null] }),
+            Some(version) => filter.insert("version", version),
+            None => None,
+        };
+        let mut set = bean_filter(&folder);
+        set.remove("version");
+        let update = doc! { "$set": set, "$inc": { "version": 1_i64 } };
+
+        let mut session = self.start_transaction().await?;
+        if self.collection.update_one(filter, update).session(&mut session).await?.matched_count == 0 {
+            return Ok(-1);
+        }
+        self.move_documents(&mut session, from_key, folder.key).await?;
+        session.commit_transaction().await?;
+        Ok(id)
+    }
+
+    async fn select_raw(&self, after_id: i64, limit: u32) -> Result<Vec<Folder>, Error> {
+        let folders = find_after(&self.collection, after_id, limit).await?;
+        Ok(
+            folders
+                .into_iter()
+                .map(|(mut folder, del_flag)| {
+                    folder.base.del_flag = Some(del_flag);
+                    folder
+                })
+                .collect()
+        )
+    }
+
+    async fn insert_raw(&self, folder: Folder) -> Result<i64, Error> {
+        let del_flag = folder.base.del_flag.unwrap_or(0);
+        insert_with(&self.collection, &folder, doc! { "del_flag": del_flag }).await?;
+        Ok(folder.base.id.unwrap_or(-1))
+    }
+
+    async fn delete_raw(&self, id: i64) -> Result<u64, Error> {
+        delete_by_id(&self.collection, id).await
+    }
 }
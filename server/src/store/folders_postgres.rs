@@ -0,0 +1,271 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use anyhow::{ Error, Ok };
+use axum::async_trait;
+use sqlx::PgPool;
+
+use crate::errors::BizError;
+use crate::types::folder::Folder;
+use crate::types::PageRequest;
+use crate::types::PageResponse;
+use crate::utils::types::GenericValue;
+use super::{ AsyncRepository, current_owner_uid, with_audit_values };
+use super::query::{ Dialect, Operator, QueryBuilder };
+use super::sqlite::del_flag_values;
+use super::postgres::{ PostgresRepository, to_arguments };
+use super::documents_sqlite::DOCUMENT_COLUMNS;
+use super::folders_sqlite::FOLDER_COLUMNS;
+
+pub struct FolderPostgresRepository {
+    inner: PostgresRepository<Folder>,
+}
+
+impl FolderPostgresRepository {
+    pub fn new(pool: PgPool) -> Self {
+        FolderPostgresRepository {
+            inner: PostgresRepository::new(pool),
+        }
+    }
+
+    // The query builder scoped to the owner (current user).
+    async fn owned_builder(&self) -> Result<QueryBuilder, Error> {
+        QueryBuilder::new("folders", FOLDER_COLUMNS).and("owner_uid", Operator::Eq, current_owner_uid().await?)
+    }
+
+    // The query builder of the owned and not trashed documents in the folder of the keys.
+    async fn documents_builder(&self, keys: Vec<String>) -> Result<QueryBuilder, Error> {
+        QueryBuilder::new("documents", DOCUMENT_COLUMNS)
+            .and("owner_uid", Operator::Eq, current_owner_uid().await?)?
+            .and_in_array("folder_key", keys.into_iter().map(GenericValue::String).collect())?
+            .and("del_flag", Operator::Eq, 0)
+            .map(|builder| builder.dialect(Dialect::Postgres))
+    }
+}
+
+#[async_trait]
+impl AsyncRepository<Folder> for FolderPostgresRepository {
+    async fn select(
+        &self,
+        folder: Folder,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<Folder>), Error> {
+        let builder = self.owned_builder().await?
+            .and("del_flag", Operator::Eq, 0)?
+            .and_bean(&folder)?
+            .order_by("update_time", true)?;
+        let result = self.inner.select_page(&builder, &page).await?;
+
+        tracing::info!("query folders: {:?}", result);
+        Ok((result.0, result.1))
+    }
+
+    async fn select_within(
+        &self,
+        ids: Vec<i64>,
+        folder: Folder,
+        page: PageRequest,
+        _summary: bool
+    ) -> Result<(PageResponse, Vec<Folder>), Error> {
+        let builder = self.owned_builder().await?
+            .and_in_array("id", ids.into_iter().map(GenericValue::Int64).collect())?
+            .and("del_flag", Operator::Eq, 0)?
+            .and_bean(&folder)?
+            .order_by("update_time", true)?;
+        self.inner.select_page(&builder, &page).await
+    }
+
+    async fn select_by_id(&self, id: i64) -> Result<Folder, Error> {
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?;
+        let folder = self.inner
+            .select_one(&builder).await?
+            .ok_or_else(|| BizError::NotFound("folder".to_string()))?;
+
+        tracing::info!("query folder: {:?}", folder);
+        Ok(folder)
+    }
+
+    async fn insert(&self, mut folder: Folder) -> Result<i64, Error> {
+        folder.owner_uid = Some(current_owner_uid().await?);
+        folder.base.pre_insert(None).await;
+        let builder = QueryBuilder::new("folders", FOLDER_COLUMNS);
+        let inserted_id = self.inner.insert_bean(&builder, &folder).await?;
+        tracing::info!("Inserted folder.id: {:?}", inserted_id);
+        Ok(inserted_id)
+    }
+
+    async fn update(&self, mut folder: Folder) -> Result<i64, Error> {
+        folder.base.pre_update(None).await;
+        let id = folder.base.id.ok_or_else(|| Error::msg("The folder id is required for update"))?;
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?;
+        let updated_id = if self.inner.update_bean(&builder, &folder).await? > 0 { id } else { -1 };
+        tracing::info!("Updated folder.id: {:?}", updated_id);
+        Ok(updated_id)
+    }
+
+    async fn delete_all(&self) -> Result<u64, Error> {
+        let deleted = self.inner.delete(&self.owned_builder().await?).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    // Soft delete to the trash.
+    async fn delete_by_id(&self, id: i64) -> Result<u64, Error> {
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?;
+        let deleted = self.inner.update_values(&builder, del_flag_values(1).await).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    async fn select_trash(
+        &self,
+        folder: Folder,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<Folder>), Error> {
+        let builder = self.owned_builder().await?
+            .and("del_flag", Operator::Eq, 1)?
+            .and_bean(&folder)?
+            .order_by("update_time", true)?;
+        self.inner.select_page(&builder, &page).await
+    }
+
+    async fn select_trash_before(&self, time: i64, limit: u32) -> Result<Vec<Folder>, Error> {
+        let builder = QueryBuilder::new("folders", FOLDER_COLUMNS)
+            .and("del_flag", Operator::Eq, 1)?
+            .and("update_time", Operator::Lt, time)?
+            .order_by("update_time", false)?;
+        let page = PageRequest::new(1, limit);
+        Ok(self.inner.select_page(&builder, &page).await?.1)
+    }
+
+    async fn restore_by_id(&self, id: i64) -> Result<u64, Error> {
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 1)?;
+        let restored = self.inner.update_values(&builder, del_flag_values(0).await).await?;
+        tracing::info!("Restored result: {:?}", restored);
+        Ok(restored)
+    }
+
+    async fn purge_by_id(&self, id: i64) -> Result<u64, Error> {
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 1)?;
+        let purged = self.inner.delete(&builder).await?;
+        tracing::info!("Purged result: {:?}", purged);
+        Ok(purged)
+    }
+
+    async fn trash_tree(&self, ids: Vec<i64>, keys: Vec<String>) -> Result<u64, Error> {
+        // All are stamped the same trashed time, so that they're restored together, see: FolderHandler::restore
+        let values = del_flag_values(1).await;
+        let folders = self.owned_builder().await?
+            .and_in_array("id", ids.into_iter().map(GenericValue::Int64).collect())?
+            .and("del_flag", Operator::Eq, 0)?
+            .dialect(Dialect::Postgres).build_update_values(values.clone())?;
+        let documents = self.documents_builder(keys).await?.build_update_values(values)?;
+
+        let mut tx = self.inner.get_pool().begin().await?;
+        let mut trashed = 0;
+        for (sql, params) in [folders, documents] {
+            trashed += sqlx::query_with(&sql, to_arguments(params)).execute(&mut *tx).await?.rows_affected();
+        }
+        tx.commit().await?;
+        tracing::info!("Trashed result: {:?}", trashed);
+        Ok(trashed)
+    }
+
+    async fn trash_reparent(&self, folder: Folder, parent_key: Option<String>) -> Result<i64, Error> {
+        let id = folder.base.id.ok_or_else(|| Error::msg("The folder id is required for trash"))?;
+        let mut builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?;
+        if let Some(version) = folder.base.version {
+            builder = builder.and("version", Operator::Eq, version)?;
+        }
+        let (sql, params) = builder.dialect(Dialect::Postgres).build_update_values(del_flag_values(1).await)?;
+
+        // The transaction is rolled back when dropped without commit.
+        let mut tx = self.inner.get_pool().begin().await?;
+        if sqlx::query_with(&sql, to_arguments(params)).execute(&mut *tx).await?.rows_affected() == 0 {
+            return Ok(-1);
+        }
+        let pid = vec![("pid".to_string(), GenericValue::Int64(folder.pid.unwrap_or_default()))];
+        let (sql, params) = self.owned_builder().await?
+            .and("pid", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?
+            .dialect(Dialect::Postgres).build_update_values(with_audit_values(pid).await)?;
+        sqlx::query_with(&sql, to_arguments(params)).execute(&mut *tx).await?;
+        if let Some(key) = folder.key.filter(|key| !key.is_empty()) {
+            let folder_key = parent_key.map(GenericValue::String).unwrap_or(GenericValue::Null);
+            let moved = with_audit_values(vec![("folder_key".to_string(), folder_key)]).await;
+            let (sql, params) = self.documents_builder(vec![key]).await?.build_update_values(moved)?;
+            sqlx::query_with(&sql, to_arguments(params)).execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        tracing::info!("Trashed folder.id: {:?}", id);
+        Ok(id)
+    }
+
+    async fn update_rekey(&self, mut folder: Folder, from_key: String) -> Result<i64, Error> {
+        folder.base.pre_update(None).await;
+        let id = folder.base.id.ok_or_else(|| Error::msg("The folder id is required for update"))?;
+        let (sql, params) = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?
+            .dialect(Dialect::Postgres).build_update(&folder)?;
+
+        let mut tx = self.inner.get_pool().begin().await?;
+        if sqlx::query_with(&sql, to_arguments(params)).execute(&mut *tx).await?.rows_affected() == 0 {
+            return Ok(-1);
+        }
+        let folder_key = folder.key.map(GenericValue::String).unwrap_or(GenericValue::Null);
+        let moved = with_audit_values(vec![("folder_key".to_string(), folder_key)]).await;
+        let (sql, params) = self.documents_builder(vec![from_key]).await?.build_update_values(moved)?;
+        sqlx::query_with(&sql, to_arguments(params)).execute(&mut *tx).await?;
+        tx.commit().await?;
+        tracing::info!("Updated folder.id: {:?}", id);
+        Ok(id)
+    }
+
+    async fn select_raw(&self, after_id: i64, limit: u32) -> Result<Vec<Folder>, Error> {
+        let builder = QueryBuilder::new("folders", FOLDER_COLUMNS);
+        self.inner.select_after(&builder, after_id, limit).await
+    }
+
+    async fn insert_raw(&self, folder: Folder) -> Result<i64, Error> {
+        let builder = QueryBuilder::new("folders", FOLDER_COLUMNS);
+        let del_flag = GenericValue::Int32(folder.base.del_flag.unwrap_or(0));
+        self.inner.insert_bean_with(&builder, &folder, vec![("del_flag".to_string(), del_flag)]).await
+    }
+
+    async fn delete_raw(&self, id: i64) -> Result<u64, Error> {
+        let builder = QueryBuilder::new("folders", FOLDER_COLUMNS).and("id", Operator::Eq, id)?;
+        self.inner.delete(&builder).await
+    }
+}
@@ -24,11 +24,21 @@ use anyhow::{ Error, Ok };
 use axum::async_trait;
 
 use crate::config::config_serve::DbProperties;
+use crate::errors::BizError;
 use crate::types::folder::Folder;
 use crate::types::PageRequest;
 use crate::types::PageResponse;
-use super::AsyncRepository;
-use super::sqlite::SQLiteRepository;
+use crate::utils::types::GenericValue;
+use super::{ AsyncRepository, current_owner_uid, with_audit_values };
+use super::documents_sqlite::DOCUMENT_COLUMNS;
+use super::query::{ Operator, QueryBuilder };
+use super::sqlite::{ SQLiteRepository, del_flag_values, to_arguments };
+
+// The whitelist columns of the table 'folders'.
+pub const FOLDER_COLUMNS: &[&str] = &[
+    "id", "status", "create_by", "create_time", "update_by", "update_time", "del_flag", "version",
+    "owner_uid", "pid", "key", "name",
+];
 
 pub struct FolderSQLiteRepository {
     inner: SQLiteRepository<Folder>,
@@ -40,6 +50,19 @@ impl FolderSQLiteRepository {
             inner: SQLiteRepository::new(config).await?,
         })
     }
+
+    // The query builder scoped to the owner (current user).
+    async fn owned_builder(&self) -> Result<QueryBuilder, Error> {
+        QueryBuilder::new("folders", FOLDER_COLUMNS).and("owner_uid", Operator::Eq, current_owner_uid().await?)
+    }
+
+    // The query builder of the owned and not trashed documents in the folder of the keys.
+    async fn documents_builder(&self, keys: Vec<String>) -> Result<QueryBuilder, Error> {
+        QueryBuilder::new("documents", DOCUMENT_COLUMNS)
+            .and("owner_uid", Operator::Eq, current_owner_uid().await?)?
+            .and_in_array("folder_key", keys.into_iter().map(GenericValue::String).collect())?
+            .and("del_flag", Operator::Eq, 0)
+    }
 }
 
 #[async_trait]
@@ -49,60 +72,279 @@ impl AsyncRepository<Folder> for FolderSQLiteRepository {
         folder: Folder,
         page: PageRequest
     ) -> Result<(PageResponse, Vec<Folder>), Error> {
-        let result = dynamic_sqlite_query!(
-            folder,
-            "folders",
-            self.inner.get_pool(),
-            "update_time",
-            page,
-            Folder
-        ).unwrap();
+        let builder = self.owned_builder().await?
+            .and("del_flag", Operator::Eq, 0)?
+            .and_bean(&folder)?
+            .order_by("update_time", true)?;
+        let result = self.inner.select_page(&builder, &page).await?;
 
         tracing::info!("query folders: {:?}", result);
         Ok((result.0, result.1))
     }
 
+    async fn select_within(
+        &self,
+        ids: Vec<i64>,
+        folder: Folder,
+        page: PageRequest,
+        _summary: bool
+    ) -> Result<(PageResponse, Vec<Folder>), Error> {
+        let builder = self.owned_builder().await?
+            .and_in_array("id", ids.into_iter().map(GenericValue::Int64).collect())?
+            .and("del_flag", Operator::Eq, 0)?
+            .and_bean(&folder)?
+            .order_by("update_time", true)?;
+        self.inner.select_page(&builder, &page).await
+    }
+
     async fn select_by_id(&self, id: i64) -> Result<Folder, Error> {
-        let folder = sqlx
-            ::query_as::<_, Folder>("SELECT * FROM folders WHERE id = $1")
-            .bind(id)
-            .fetch_one(self.inner.get_pool()).await
-            .unwrap();
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?;
+        let folder = self.inner
+            .select_one(&builder).await?
+            .ok_or_else(|| BizError::NotFound("folder".to_string()))?;
 
         tracing::info!("query folder: {:?}", folder);
         Ok(folder)
     }
 
     async fn insert(&self, mut folder: Folder) -> Result<i64, Error> {
-        let inserted_id = dynamic_sqlite_insert!(folder, "folders", self.inner.get_pool()).unwrap();
+        folder.owner_uid = Some(current_owner_uid().await?);
+        folder.base.pre_insert(None).await;
+        let builder = QueryBuilder::new("folders", FOLDER_COLUMNS);
+        let inserted_id = self.inner.insert_bean(&builder, &folder).await?;
         tracing::info!("Inserted folder.id: {:?}", inserted_id);
         Ok(inserted_id)
     }
 
     async fn update(&self, mut folder: Folder) -> Result<i64, Error> {
-        let updated_id = dynamic_sqlite_update!(folder, "folders", self.inner.get_pool()).unwrap();
+        folder.base.pre_update(None).await;
+        let id = folder.base.id.ok_or_else(|| Error::msg("The folder id is required for update"))?;
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?;
+        let updated_id = if self.inner.update_bean(&builder, &folder).await? > 0 { id } else { -1 };
         tracing::info!("Updated folder.id: {:?}", updated_id);
         Ok(updated_id)
     }
 
     async fn delete_all(&self) -> Result<u64, Error> {
-        let delete_result = sqlx
-            ::query("DELETE FROM folders")
-            .execute(self.inner.get_pool()).await
-            .unwrap();
-
-        tracing::info!("Deleted result: {:?}", delete_result);
-        Ok(delete_result.rows_affected())
+        let deleted = self.inner.delete(&self.owned_builder().await?).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
     }
 
+    // Soft delete to the trash.
     async fn delete_by_id(&self, id: i64) -> Result<u64, Error> {
-        let delete_result = sqlx
-            ::query("DELETE FROM folders WHERE id = $1")
-            .bind(id)
-            .execute(self.inner.get_pool()).await
-            .unwrap();
-
-        tracing::info!("Deleted result: {:?}", delete_result);
-        Ok(delete_result.rows_affected())
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?;
+        let deleted = self.inner.update_values(&builder, del_flag_values(1).await).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    async fn select_trash(
+        &self,
+        folder: Folder,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<Folder>), Error> {
+        let builder = self.owned_builder().await?
+            .and("del_flag", Operator::Eq, 1)?
+            .and_bean(&folder)?
+            .order_by("update_time", true)?;
+        self.inner.select_page(&builder, &page).await
+    }
+
+    async fn select_trash_before(&self, time: i64, limit: u32) -> Result<Vec<Folder>, Error> {
+        let builder = QueryBuilder::new("folders", FOLDER_COLUMNS)
+            .and("del_flag", Operator::Eq, 1)?
+            .and("update_time", Operator::Lt, time)?
+            .order_by("update_time", false)?;
+        let page = PageRequest::new(1, limit);
+        Ok(self.inner.select_page(&builder, &page).await?.1)
+    }
+
+    async fn restore_by_id(&self, id: i64) -> Result<u64, Error> {
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 1)?;
+        let restored = self.inner.update_values(&builder, del_flag_values(0).await).await?;
+        tracing::info!("Restored result: {:?}", restored);
+        Ok(restored)
+    }
+
+    async fn purge_by_id(&self, id: i64) -> Result<u64, Error> {
+        let builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 1)?;
+        let purged = self.inner.delete(&builder).await?;
+        tracing::info!("Purged result: {:?}", purged);
+        Ok(purged)
+    }
+
+    async fn trash_tree(&self, ids: Vec<i64>, keys: Vec<String>) -> Result<u64, Error> {
+        // All are stamped the same trashed time, so that they're restored together, see: FolderHandler::restore
+        let values = del_flag_values(1).await;
+        let folders = self.owned_builder().await?
+            .and_in_array("id", ids.into_iter().map(GenericValue::Int64).collect())?
+            .and("del_flag", Operator::Eq, 0)?
+            .build_update_values(values.clone())?;
+        let documents = self.documents_builder(keys).await?.build_update_values(values)?;
+
+        let mut tx = self.inner.get_pool().begin().await?;
+        let mut trashed = 0;
+        for (sql, params) in [folders, documents] {
+            trashed += sqlx::query_with(&sql, to_arguments(params)).execute(&mut *tx).await?.rows_affected();
+        }
+        tx.commit().await?;
+        tracing::info!("Trashed result: {:?}", trashed);
+        Ok(trashed)
+    }
+
+    async fn trash_reparent(&self, folder: Folder, parent_key: Option<String>) -> Result<i64, Error> {
+        let id = folder.base.id.ok_or_else(|| Error::msg("The folder id is required for trash"))?;
+        let mut builder = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?;
+        if let Some(version) = folder.base.version {
+            builder = builder.and("version", Operator::Eq, version)?;
+        }
+        let (sql, params) = builder.build_update_values(del_flag_values(1).await)?;
+
+        // The transaction is rolled back when dropped without commit.
+        let mut tx = self.inner.get_pool().begin().await?;
+        if sqlx::query_with(&sql, to_arguments(params)).execute(&mut *tx).await?.rows_affected() == 0 {
+            return Ok(-1);
+        }
+        let pid = vec![("pid".to_string(), GenericValue::Int64(folder.pid.unwrap_or_default()))];
+        let (sql, params) = self.owned_builder().await?
+            .and("pid", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?
+            .build_update_values(with_audit_values(pid).await)?;
+        sqlx::query_with(&sql, to_arguments(params)).execute(&mut *tx).await?;
+        if let Some(key) = folder.key.filter(|key| !key.is_empty()) {
+            let folder_key = parent_key.map(GenericValue::String).unwrap_or(GenericValue::Null);
+            let moved = with_audit_values(vec![("folder_key".to_string(), folder_key)]).await;
+            let (sql, params) = self.documents_builder(vec![key]).await?.build_update_values(moved)?;
+            sqlx::query_with(&sql, to_arguments(params)).execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        tracing::info!("Trashed folder.id: {:?}", id);
+        Ok(id)
+    }
+
+    async fn update_rekey(&self, mut folder: Folder, from_key: String) -> Result<i64, Error> {
+        folder.base.pre_update(None).await;
+        let id = folder.base.id.ok_or_else(|| Error::msg("The folder id is required for update"))?;
+        let (sql, params) = self.owned_builder().await?
+            .and("id", Operator::Eq, id)?
+            .and("del_flag", Operator::Eq, 0)?
+            .build_update(&folder)?;
+
+        let mut tx = self.inner.get_pool().begin().await?;
+        if sqlx::query_with(&sql, to_arguments(params)).execute(&mut *tx).await?.rows_affected() == 0 {
+            return Ok(-1);
+        }
+        let folder_key = folder.key.map(GenericValue::String).unwrap_or(GenericValue::Null);
+        let moved = with_audit_values(vec![("folder_key".to_string(), folder_key)]).await;
+        let (sql, params) = self.documents_builder(vec![from_key]).await?.build_update_values(moved)?;
+        sqlx::query_with(&sql, to_arguments(params)).execute(&mut *tx).await?;
+        tx.commit().await?;
+        tracing::info!("Updated folder.id: {:?}", id);
+        Ok(id)
+    }
+
+    async fn select_raw(&self, after_id: i64, limit: u32) -> Result<Vec<Folder>, Error> {
+        let builder = QueryBuilder::new("folders", FOLDER_COLUMNS);
+        self.inner.select_after(&builder, after_id, limit).await
+    }
+
+    async fn insert_raw(&self, folder: Folder) -> Result<i64, Error> {
+        let builder = QueryBuilder::new("folders", FOLDER_COLUMNS);
+        let del_flag = GenericValue::Int32(folder.base.del_flag.unwrap_or(0));
+        self.inner.insert_bean_with(&builder, &folder, vec![("del_flag".to_string(), del_flag)]).await
+    }
+
+    async fn delete_raw(&self, id: i64) -> Result<u64, Error> {
+        let builder = QueryBuilder::new("folders", FOLDER_COLUMNS).and("id", Operator::Eq, id)?;
+        self.inner.delete(&builder).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::auth::PrincipalType;
+    use crate::store::documents_sqlite::DocumentSQLiteRepository;
+    use crate::types::document::SaveDocumentRequest;
+    use crate::types::folder::SaveFolderRequest;
+    use crate::utils::auths::{ AuthUserClaims, SecurityContext };
+
+    fn create_test_config() -> DbProperties {
+        let dir = std::env::temp_dir().join(format!("mywebnote-{}", uuid::Uuid::new_v4()));
+        let mut config = DbProperties::default();
+        config.sqlite.dir = Some(dir.to_string_lossy().to_string());
+        config
+    }
+
+    fn user(uid: i64) -> Option<AuthUserClaims> {
+        Some(AuthUserClaims {
+            ptype: PrincipalType::Password,
+            uid,
+            uname: format!("user{}", uid),
+            email: format!("user{}@mywebnote.local", uid),
+            exp: 0,
+            ext: None,
+        })
+    }
+
+    fn save_folder(pid: Option<i64>, key: &str) -> Folder {
+        let save = SaveFolderRequest {
+            id: None,
+            pid,
+            key: Some(key.to_string()),
+            name: Some(key.to_string()),
+            version: None,
+        };
+        save.to_folder()
+    }
+
+    #[tokio::test]
+    async fn test_folder_trash_reparent_all_or_nothing() {
+        let config = create_test_config();
+        let repo = FolderSQLiteRepository::new(&config).await.unwrap();
+        let documents = DocumentSQLiteRepository::new(&config).await.unwrap();
+
+        SecurityContext::scope(user(1), async {
+            let root_id = repo.insert(save_folder(None, "root")).await.unwrap();
+            let child_id = repo.insert(save_folder(Some(root_id), "child")).await.unwrap();
+            let save = SaveDocumentRequest {
+                id: None,
+                key: Some("d1".to_string()),
+                name: Some("n1".to_string()),
+                folder_key: Some("root".to_string()),
+                doc_type: None,
+                content: Some("c1".to_string()),
+                version: None,
+            };
+            let document_id = documents.insert(save.to_document()).await.unwrap();
+
+            // The stale version changes nothing.
+            let mut root = repo.select_by_id(root_id).await.unwrap();
+            let version = root.base.version;
+            root.base.version = version.map(|v| v + 1);
+            assert_eq!(repo.trash_reparent(root.clone(), None).await.unwrap(), -1);
+            assert_eq!(repo.select_by_id(child_id).await.unwrap().pid, Some(root_id));
+            let document = documents.select_by_id(document_id).await.unwrap();
+            assert_eq!(document.folder_key, Some("root".to_string()));
+
+            root.base.version = version;
+            assert_eq!(repo.trash_reparent(root, None).await.unwrap(), root_id);
+            assert!(repo.select_by_id(root_id).await.is_err());
+            assert_eq!(repo.select_by_id(child_id).await.unwrap().pid, Some(0));
+            assert_eq!(documents.select_by_id(document_id).await.unwrap().folder_key, None);
+        }).await;
     }
 }
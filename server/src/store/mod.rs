@@ -21,27 +21,69 @@
  */
 
 pub mod mongo;
-#[macro_use]
+pub mod postgres;
+pub mod query;
+pub mod search;
+pub mod data_migration;
+pub mod acls_sqlite;
+pub mod acls_mongo;
+pub mod acls_postgres;
+pub mod blobs;
+pub mod blobs_local;
+pub mod blobs_mongo;
+pub mod blobs_postgres;
+pub mod blobs_sqlite;
 pub mod sqlite;
 pub mod documents_mongo;
+pub mod documents_postgres;
 pub mod documents_sqlite;
+pub mod document_revisions_mongo;
+pub mod document_revisions_postgres;
+pub mod document_revisions_sqlite;
+pub mod document_updates_mongo;
+pub mod document_updates_postgres;
+pub mod document_updates_sqlite;
+pub mod document_blobs_mongo;
+pub mod document_blobs_postgres;
+pub mod document_blobs_sqlite;
+pub mod document_tags_mongo;
+pub mod document_tags_postgres;
+pub mod document_tags_sqlite;
 pub mod folders_mongo;
+pub mod folders_postgres;
 pub mod folders_sqlite;
 pub mod settings_sqlite;
 pub mod settings_mongo;
+pub mod settings_postgres;
+pub mod share_links_sqlite;
+pub mod share_links_mongo;
+pub mod share_links_postgres;
+pub mod tags_sqlite;
+pub mod tags_mongo;
+pub mod tags_postgres;
 pub mod users_sqlite;
 pub mod users_mongo;
+pub mod users_postgres;
+
+use std::future::Future;
+use std::sync::Arc;
 
 use anyhow::Error;
 use axum::async_trait;
+use mongodb::ClientSession;
+use serde::Serialize;
+use sqlx::{ Postgres, Sqlite, Transaction };
+use tokio::sync::Mutex;
 
 use crate::{
     config::config_serve::{ WebServeProperties, DbType },
-    types::{ PageResponse, PageRequest },
+    errors::BizError,
+    types::{ BaseBean, PageCursor, PageResponse, PageRequest, SearchHit, tag::TagMatch },
+    utils::{ auths::SecurityContext, types::GenericValue },
 };
 
 #[async_trait] // solution2: async fn + dyn polymorphism problem.
-pub trait AsyncRepository<T>: Send {
+pub trait AsyncRepository<T>: Send + Sync {
     // solution1: async fn + dyn polymorphism problem.
     // fn select(&self) -> Box<dyn Future<Output = Result<Page<T>, Error>> + Send>;
     async fn select(&self, mut param: T, page: PageRequest) -> Result<(PageResponse, Vec<T>), Error>
@@ -51,21 +93,252 @@ pub trait AsyncRepository<T>: Send {
     async fn update(&self, mut param: T) -> Result<i64, Error> where T: 'static + Send + Sync;
     async fn delete_all(&self) -> Result<u64, Error>;
     async fn delete_by_id(&self, id: i64) -> Result<u64, Error>;
+
+    // Select without the large columns (such as the content of documents) for the fast listing,
+    // which defaults to the full select.
+    async fn select_summary(&self, param: T, page: PageRequest) -> Result<(PageResponse, Vec<T>), Error>
+        where T: 'static + Send + Sync
+    {
+        self.select(param, page).await
+    }
+
+    // Select within the ids, such as the documents filtered by the tags or shared by the ACLs, which is
+    // the same as the 'select' (or the 'select_summary' if summary) except that only the data of the ids
+    // are matched.
+    async fn select_within(
+        &self,
+        _ids: Vec<i64>,
+        _param: T,
+        _page: PageRequest,
+        _summary: bool
+    ) -> Result<(PageResponse, Vec<T>), Error>
+        where T: 'static + Send + Sync
+    {
+        Err(Error::msg("The select within the ids is not supported"))
+    }
+
+    // Select the data tagged with any or all of the tags, which is the same as the 'select_within' except
+    // that the tagged are matched by the database, so that the count of the tagged is unbounded.
+    async fn select_tagged(
+        &self,
+        _tag_ids: Vec<i64>,
+        _tag_match: TagMatch,
+        _param: T,
+        _page: PageRequest,
+        _summary: bool
+    ) -> Result<(PageResponse, Vec<T>), Error>
+        where T: 'static + Send + Sync
+    {
+        Err(Error::msg("The select of the tagged is not supported"))
+    }
+
+    // The trash operations of the soft deleted (del_flag=1) modules data, which the 'delete_by_id'
+    // is soft delete, and the 'purge_by_id' is physically delete only for the trashed.
+    async fn select_trash(
+        &self,
+        mut _param: T,
+        _page: PageRequest
+    ) -> Result<(PageResponse, Vec<T>), Error>
+        where T: 'static + Send + Sync
+    {
+        Err(Error::msg("The trash is not supported"))
+    }
+    // Notice: It's the system wide (not scoped to the owner) for the background purge.
+    async fn select_trash_before(&self, _time: i64, _limit: u32) -> Result<Vec<T>, Error>
+        where T: 'static + Send + Sync
+    {
+        Err(Error::msg("The trash is not supported"))
+    }
+    async fn restore_by_id(&self, _id: i64) -> Result<u64, Error> {
+        Err(Error::msg("The trash is not supported"))
+    }
+    async fn purge_by_id(&self, _id: i64) -> Result<u64, Error> {
+        Err(Error::msg("The trash is not supported"))
+    }
+
+    // Delete the owned revisions of the document out of the retention in bulk, i.e. the older than the
+    // latest 'keep_last' or created before the 'expired_before', and the latest one is always retained.
+    async fn delete_revisions_before(
+        &self,
+        _document_id: i64,
+        _keep_last: Option<u32>,
+        _expired_before: Option<i64>
+    ) -> Result<u64, Error> {
+        Err(Error::msg("The revisions retention is not supported"))
+    }
+
+    // Notice: It's the system wide (not scoped to the owner) for the background tasks, such as
+    // the garbage collection of the unreferenced blobs.
+    async fn select_unscoped(
+        &self,
+        mut _param: T,
+        _page: PageRequest
+    ) -> Result<(PageResponse, Vec<T>), Error>
+        where T: 'static + Send + Sync
+    {
+        Err(Error::msg("The unscoped select is not supported"))
+    }
+
+    // Update the given fields of the owned and not trashed data by id, which could be set to null
+    // (the absent fields are ignored by the 'update'), and the version is increased as well.
+    async fn update_fields(&self, _id: i64, _fields: Vec<(String, GenericValue)>) -> Result<u64, Error> {
+        Err(Error::msg("The fields update is not supported"))
+    }
+    // Increase the counter field of the owned data by id in one statement, so that no concurrent increase is
+    // lost, and the given fields are updated as well, such as the access count of the share links.
+    async fn increase_field(
+        &self,
+        _id: i64,
+        _field: &str,
+        _fields: Vec<(String, GenericValue)>
+    ) -> Result<u64, Error> {
+        Err(Error::msg("The field increase is not supported"))
+    }
+    // Update the given fields of the owned and not trashed data of the ids in one statement, see: update_fields
+    async fn update_fields_within(
+        &self,
+        _ids: Vec<i64>,
+        _fields: Vec<(String, GenericValue)>
+    ) -> Result<u64, Error> {
+        Err(Error::msg("The fields update is not supported"))
+    }
+
+    // The folder tree operations, each of which is done in one transaction together with the documents
+    // in the folders (referred by the folder key), so that nothing is changed if any fails.
+    // Trash the folders of the ids, and the documents in the folders of the keys.
+    async fn trash_tree(&self, _ids: Vec<i64>, _keys: Vec<String>) -> Result<u64, Error> {
+        Err(Error::msg("The folder tree is not supported"))
+    }
+    // Trash the folder matched the version, and move the sub folders and the documents of it to the parent
+    // of it (the key of which is the 'parent_key'), returns -1 if nothing matched, see: update
+    async fn trash_reparent(&self, _param: T, _parent_key: Option<String>) -> Result<i64, Error>
+        where T: 'static + Send + Sync
+    {
+        Err(Error::msg("The folder tree is not supported"))
+    }
+    // Update the folder as 'update', and move the documents in the folder of the 'from_key' to the updated key.
+    async fn update_rekey(&self, _param: T, _from_key: String) -> Result<i64, Error>
+        where T: 'static + Send + Sync
+    {
+        Err(Error::msg("The folder tree is not supported"))
+    }
+
+    // The raw operations of the data migration between the DbTypes and the backup, which are system wide
+    // (include the trashed), and the ids, owners, audit fields and versions of the data are kept as is.
+    // Select the data of the ids greater than the 'after_id' in ascending order of the id.
+    async fn select_raw(&self, _after_id: i64, _limit: u32) -> Result<Vec<T>, Error>
+        where T: 'static + Send + Sync
+    {
+        Err(Error::msg("The raw select is not supported"))
+    }
+    async fn insert_raw(&self, _param: T) -> Result<i64, Error> where T: 'static + Send + Sync {
+        Err(Error::msg("The raw insert is not supported"))
+    }
+    async fn delete_raw(&self, _id: i64) -> Result<u64, Error> {
+        Err(Error::msg("The raw delete is not supported"))
+    }
+    // Begin the transaction of the raw operations, which is shared by all the repositories of the DbType.
+    async fn begin_raw(&self) -> Result<RawTransaction, Error> {
+        Err(Error::msg("The raw transaction is not supported"))
+    }
+
+    // The full text search of the repository, which is none if not searchable.
+    fn searcher(&self) -> Option<&dyn AsyncSearchRepository<T>> {
+        None
+    }
+}
+
+// The full text search of the modules data, the same query should be behaves the same
+// for the all DbTypes (all the terms are required, and the highlights are marked as same).
+#[async_trait]
+pub trait AsyncSearchRepository<T>: Send + Sync {
+    // Search by the query and filter by the present fields of the param, ordered by the
+    // relevance score desc. Notice: Only the owned and not trashed data are matched.
+    async fn search(
+        &self,
+        query: &str,
+        param: T,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<SearchHit<T>>), Error>
+        where T: 'static + Send + Sync;
+}
+
+tokio::task_local! {
+    // The transaction of the raw operations, which bound by the restoring, see: RawTransaction::scope()
+    static RAW_TRANSACTION: RawTransaction;
+}
+
+// The transaction of the raw operations across all the repositories of the DbType, the raw operations
+// of the repositories are executed in it while bound, see: handler/backup.rs#restore()
+#[derive(Clone)]
+pub enum RawTransaction {
+    Sqlite(Arc<Mutex<Transaction<'static, Sqlite>>>),
+    Postgres(Arc<Mutex<Transaction<'static, Postgres>>>),
+    Mongo(Arc<Mutex<ClientSession>>),
+}
+
+impl RawTransaction {
+    // Runs the future in the transaction, which is committed if succeeded, otherwise rolled back.
+    pub async fn scope<F, R>(self, f: F) -> Result<R, Error> where F: Future<Output = Result<R, Error>> {
+        match RAW_TRANSACTION.scope(self.clone(), f).await {
+            Ok(result) => {
+                self.commit().await?;
+                Ok(result)
+            }
+            Err(e) => {
+                if let Err(rollback) = self.rollback().await {
+                    tracing::warn!("Failed to rollback the raw transaction. reason: {}", rollback);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    pub fn current() -> Option<RawTransaction> {
+        RAW_TRANSACTION.try_with(|tx| tx.clone()).ok()
+    }
+
+    async fn commit(self) -> Result<(), Error> {
+        match self {
+            RawTransaction::Sqlite(tx) => Self::unwrap(tx)?.commit().await?,
+            RawTransaction::Postgres(tx) => Self::unwrap(tx)?.commit().await?,
+            RawTransaction::Mongo(session) => session.lock().await.commit_transaction().await?,
+        }
+        Ok(())
+    }
+
+    async fn rollback(self) -> Result<(), Error> {
+        match self {
+            RawTransaction::Sqlite(tx) => Self::unwrap(tx)?.rollback().await?,
+            RawTransaction::Postgres(tx) => Self::unwrap(tx)?.rollback().await?,
+            RawTransaction::Mongo(session) => session.lock().await.abort_transaction().await?,
+        }
+        Ok(())
+    }
+
+    fn unwrap<T>(tx: Arc<Mutex<T>>) -> Result<T, Error> {
+        Arc::try_unwrap(tx)
+            .map(|tx| tx.into_inner())
+            .map_err(|_| Error::msg("The raw transaction is still in use"))
+    }
 }
 
 pub struct RepositoryContainer<T> where T: 'static + Send + Sync {
     sqlite_repo: Box<dyn AsyncRepository<T>>,
     mongo_repo: Box<dyn AsyncRepository<T>>,
+    postgres_repo: Box<dyn AsyncRepository<T>>,
 }
 
 impl<T> RepositoryContainer<T> where T: 'static + Send + Sync {
     pub fn new(
         sqlite_repo: Box<dyn AsyncRepository<T>>,
-        mongo_repo: Box<dyn AsyncRepository<T>>
+        mongo_repo: Box<dyn AsyncRepository<T>>,
+        postgres_repo: Box<dyn AsyncRepository<T>>
     ) -> Self {
         RepositoryContainer {
             sqlite_repo,
             mongo_repo,
+            postgres_repo,
         }
     }
 
@@ -77,10 +350,176 @@ impl<T> RepositoryContainer<T> where T: 'static + Send + Sync {
         &*self.mongo_repo
     }
 
+    fn postgres_repo(&self) -> &dyn AsyncRepository<T> {
+        &*self.postgres_repo
+    }
+
     pub fn get(/*&mut self*/ &self, config: &WebServeProperties) -> &dyn AsyncRepository<T> {
         match config.db.db_type {
             DbType::Sqlite => self.sqlite_repo(),
             DbType::Mongo => self.mongo_repo(),
+            DbType::Postgres => self.postgres_repo(),
+        }
+    }
+}
+
+// Select all the pages of matched data, or the trashed data.
+pub async fn select_all<T>(
+    container: &Mutex<RepositoryContainer<T>>,
+    config: &WebServeProperties,
+    param: T,
+    trash: bool
+) -> Result<Vec<T>, Error>
+    where T: 'static + Send + Sync + Clone
+{
+    const LIMIT: u32 = 1000;
+    let mut result = Vec::new();
+    // Follow the cursors of pages, so that the deep pages are not slowed by the offset.
+    let mut page = PageRequest { with_total: Some(false), ..PageRequest::new(1, LIMIT) };
+    loop {
+        let repo = container.lock().await;
+        let (response, data) = if trash {
+            repo.get(config).select_trash(param.clone(), page.clone()).await?
+        } else {
+            repo.get(config).select(param.clone(), page.clone()).await?
+        };
+        result.extend(data);
+        match response.next_cursor {
+            Some(cursor) => page.cursor = Some(cursor),
+            None => break,
         }
     }
+    Ok(result)
+}
+
+// Select all the pages of matched data without the large columns, see: 'select_summary'
+pub async fn select_all_summary<T>(
+    container: &Mutex<RepositoryContainer<T>>,
+    config: &WebServeProperties,
+    param: T
+) -> Result<Vec<T>, Error>
+    where T: 'static + Send + Sync + Clone
+{
+    const LIMIT: u32 = 1000;
+    let mut result = Vec::new();
+    // Follow the cursors of pages, so that the deep pages are not slowed by the offset.
+    let mut page = PageRequest { with_total: Some(false), ..PageRequest::new(1, LIMIT) };
+    loop {
+        let repo = container.lock().await;
+        let (response, data) = repo.get(config).select_summary(param.clone(), page.clone()).await?;
+        result.extend(data);
+        match response.next_cursor {
+            Some(cursor) => page.cursor = Some(cursor),
+            None => break,
+        }
+    }
+    Ok(result)
+}
+
+// Select all the pages of matched data within the ids without the large columns, see: 'select_within'
+pub async fn select_all_within<T>(
+    container: &Mutex<RepositoryContainer<T>>,
+    config: &WebServeProperties,
+    ids: Vec<i64>,
+    param: T
+) -> Result<Vec<T>, Error>
+    where T: 'static + Send + Sync + Clone
+{
+    const LIMIT: u32 = 1000;
+    let mut result = Vec::new();
+    // Follow the cursors of pages, so that the deep pages are not slowed by the offset.
+    let mut page = PageRequest { with_total: Some(false), ..PageRequest::new(1, LIMIT) };
+    loop {
+        let repo = container.lock().await;
+        let (response, data) = repo
+            .get(config)
+            .select_within(ids.clone(), param.clone(), page.clone(), true).await?;
+        result.extend(data);
+        match response.next_cursor {
+            Some(cursor) => page.cursor = Some(cursor),
+            None => break,
+        }
+    }
+    Ok(result)
+}
+
+// Select all the pages of the system wide matched data, see: 'select_unscoped'
+pub async fn select_all_unscoped<T>(
+    container: &Mutex<RepositoryContainer<T>>,
+    config: &WebServeProperties,
+    param: T
+) -> Result<Vec<T>, Error>
+    where T: 'static + Send + Sync + Clone
+{
+    const LIMIT: u32 = 1000;
+    let mut result = Vec::new();
+    // Follow the cursors of pages, so that the deep pages are not slowed by the offset.
+    let mut page = PageRequest { with_total: Some(false), ..PageRequest::new(1, LIMIT) };
+    loop {
+        let repo = container.lock().await;
+        let (response, data) = repo.get(config).select_unscoped(param.clone(), page.clone()).await?;
+        result.extend(data);
+        match response.next_cursor {
+            Some(cursor) => page.cursor = Some(cursor),
+            None => break,
+        }
+    }
+    Ok(result)
+}
+
+// Resolve the page of the keyset paging from the fetched rows, which are at most the 'limit + 1' for
+// detecting more, and the rows of the backward cursor are reversed back, see: types::PageCursor
+pub(crate) fn to_cursor_page<T: Serialize>(
+    page: &PageRequest,
+    cursor: Option<&PageCursor>,
+    order_columns: &[&str],
+    total: Option<i64>,
+    mut rows: Vec<T>
+) -> Result<(PageResponse, Vec<T>), Error> {
+    let limit = page.get_limit() as usize;
+    let has_more = rows.len() > limit;
+    rows.truncate(limit);
+
+    // Whether there are the rows after (the next page) or before (the previous page) of the page.
+    let (has_next, has_prev) = match cursor {
+        Some(cursor) if cursor.backward => (true, has_more),
+        Some(_) => (has_more, true),
+        None => (has_more, page.get_offset() > 0),
+    };
+    if cursor.is_some_and(|c| c.backward) {
+        rows.reverse();
+    }
+    let to_cursor = |row: Option<&T>, backward: bool| -> Result<Option<PageCursor>, Error> {
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let value = serde_json::to_value(row)?;
+        let keys = order_columns
+            .iter()
+            .map(|column| value.get(column).cloned().unwrap_or_default())
+            .collect();
+        Ok(Some(PageCursor::new(backward, keys)))
+    };
+    let next = if has_next { to_cursor(rows.last(), false)? } else { None };
+    let prev = if has_prev { to_cursor(rows.first(), true)? } else { None };
+
+    // The page number is unknown for the cursor paging.
+    let num = if cursor.is_some() { None } else { Some(page.get_num()) };
+    let response = PageResponse::new(total, num, Some(page.get_limit())).with_cursors(next, prev);
+    Ok((response, rows))
+}
+
+// The fields to update with the audit fields stamped, see: 'update_fields'
+pub(crate) async fn with_audit_values(mut fields: Vec<(String, GenericValue)>) -> Vec<(String, GenericValue)> {
+    let mut base = BaseBean::new_with_id(None);
+    base.pre_update(None).await;
+    fields.push(("update_by".to_string(), GenericValue::String(base.update_by.unwrap_or_default())));
+    fields.push(("update_time".to_string(), GenericValue::Int64(base.update_time.unwrap_or_default())));
+    fields
+}
+
+// Resolve the owner (the current authenticated user) of the user isolation modules data,
+// such as documents, folders and settings.
+pub(crate) async fn current_owner_uid() -> Result<i64, Error> {
+    SecurityContext::get_current_uid().ok_or_else(|| BizError::Unauthenticated.into())
 }
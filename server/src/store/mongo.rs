@@ -22,17 +22,26 @@
 
 use std::any::Any;
 use std::marker::PhantomData;
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::Error;
 use axum::async_trait;
 
 use mongodb::options::{ ReadConcern, WriteConcern };
-use mongodb::{ Client, Database, options::ClientOptions };
+use futures::stream::TryStreamExt;
+use mongodb::bson::{ doc, Bson, Document };
+use mongodb::options::FindOptions;
+use mongodb::{ Client, ClientSession, Collection, Database, options::ClientOptions };
+use serde::{ de::DeserializeOwned, Serialize };
+use tokio::sync::Mutex;
 
-use super::AsyncRepository;
+use super::{ AsyncRepository, RawTransaction, to_cursor_page };
+use super::query::COMMON_COLUMNS;
 use crate::config::config_serve::DbProperties;
-use crate::types::{ PageResponse, PageRequest };
+use crate::errors::BizError;
+use crate::types::{ BaseBean, PageResponse, PageRequest };
+use crate::utils::types::GenericValue;
 
 pub struct MongoRepository<T: Any + Send + Sync> {
     phantom: PhantomData<T>,
@@ -65,6 +74,252 @@ impl<T: Any + Send + Sync> MongoRepository<T> {
     }
 }
 
+// The filter of the trashed (1) or not trashed (0) data, the 'del_flag' field is absent
+// before it's first deleted (the BaseBean skips serializing it).
+pub fn del_flag_filter(del_flag: i32) -> mongodb::bson::Document {
+    if del_flag == 0 {
+        mongodb::bson::doc! { "del_flag": { "$ne": 1 } }
+    } else {
+        mongodb::bson::doc! { "del_flag": del_flag }
+    }
+}
+
+// The equality filter of all the present fields of the bean, the null and empty string are treated as absent.
+pub fn bean_filter<T: Serialize>(bean: &T) -> Document {
+    let serialized = serde_json::to_value(bean).unwrap();
+    let mut filter = Document::new();
+    for (key, value) in serialized.as_object().unwrap() {
+        if let Some(v) = value.as_i64() {
+            filter.insert(key, v);
+        } else if let Some(v) = value.as_bool() {
+            filter.insert(key, v);
+        } else if !value.is_null() {
+            let v = value.as_str().unwrap_or("");
+            if !v.is_empty() {
+                filter.insert(key, v);
+            }
+        }
+    }
+    filter
+}
+
+// Find the page of the documents by the offset, or by seeking the keys of the cursor if present, which
+// fetches one more for detecting the next page, see: store::to_cursor_page
+// The documents are in descending order of the column, unless the client sort of the page presents,
+// and the client sort and fields are whitelisted by the columns.
+pub async fn find_page<T>(
+    collection: &Collection<T>,
+    mut filter: Document,
+    projection: Option<Document>,
+    order_by: &str,
+    columns: &[&str],
+    page: &PageRequest
+) -> Result<(PageResponse, Vec<T>), Error>
+    where T: DeserializeOwned + Serialize + Unpin + Send + Sync
+{
+    let whitelist = |column: &str| -> Result<(), Error> {
+        if columns.contains(&column) {
+            return Ok(());
+        }
+        Err(BizError::BadRequest(format!("Unknown column '{}' of collection", column)).into())
+    };
+    let mut orders = page.get_sorts();
+    for (column, _) in &orders {
+        whitelist(column)?;
+    }
+    if orders.is_empty() {
+        orders.push((order_by, true));
+    }
+    // The 'id' is the tie breaker of the orders, so that the orders are the unique keys of the rows.
+    if !orders.iter().any(|(column, _)| *column == "id") {
+        orders.push(("id", orders[0].1));
+    }
+    let order_columns: Vec<&str> = orders.iter().map(|(column, _)| *column).collect();
+
+    let fields = page.get_fields();
+    for field in &fields {
+        whitelist(field)?;
+    }
+    let projection = if fields.is_empty() {
+        projection
+    } else {
+        // Only the common columns and the fields, but not the excluded of the projection.
+        let excluded = projection.unwrap_or_default();
+        let mut included = Document::new();
+        for column in columns {
+            let selected = COMMON_COLUMNS.contains(column) || fields.contains(column) || order_columns.contains(column);
+            if selected && !excluded.contains_key(column) {
+                included.insert(*column, 1);
+            }
+        }
+        Some(included)
+    };
+    let cursor = page.get_cursor()?;
+
+    // Queries to get total count, which is optional for the cursor paging.
+    let total_count = if page.is_with_total() {
+        Some(collection.count_documents(filter.clone()).await? as i64)
+    } else {
+        None
+    };
+
+    let mut skip = page.get_offset() as u64;
+    if let Some(cursor) = &cursor {
+        if cursor.keys.len() != orders.len() {
+            return Err(BizError::BadRequest("The page cursor mismatches the orders".to_string()).into());
+        }
+        // The backward seeks before the keys in the reversed orders.
+        if cursor.backward {
+            orders.iter_mut().for_each(|(_, desc)| {
+                *desc = !*desc;
+            });
+        }
+        filter.extend(seek_filter(&orders, &cursor.keys)?);
+        skip = 0;
+    }
+    let mut sort = Document::new();
+    for (column, desc) in &orders {
+        sort.insert(*column, if *desc { -1 } else { 1 });
+    }
+    let options = FindOptions::builder()
+        .skip(skip)
+        .limit((page.get_limit() as i64) + 1)
+        .sort(sort)
+        .projection(projection)
+        .build();
+
+    // Queries to get data.
+    let rows: Vec<T> = collection.find(filter).with_options(options).await?.try_collect().await?;
+    to_cursor_page(page, cursor.as_ref(), &order_columns, total_count, rows)
+}
+
+// The keyset condition of the row values, such as '(update_time, id) < (t, i)' is transformed to
+// '{ $or: [{ update_time: { $lt: t } }, { update_time: t, id: { $lt: i } }] }'.
+fn seek_filter(orders: &[(&str, bool)], keys: &[serde_json::Value]) -> Result<Document, Error> {
+    let keys = keys
+        .iter()
+        .map(mongodb::bson::to_bson)
+        .collect::<Result<Vec<_>, _>>()?;
+    let ors: Vec<Document> = orders
+        .iter()
+        .enumerate()
+        .map(|(i, (column, desc))| {
+            let mut condition = Document::new();
+            for ((prev, _), key) in orders.iter().zip(&keys).take(i) {
+                condition.insert(*prev, key.clone());
+            }
+            let operator = if *desc { "$lt" } else { "$gt" };
+            condition.insert(*column, doc! { operator: keys[i].clone() });
+            condition
+        })
+        .collect();
+    Ok(doc! { "$or": ors })
+}
+
+// Find the documents of the ids greater than the 'after_id' in ascending order of the id (include the
+// trashed), which is for scanning all the documents, such as the data migration, and the 'del_flag'
+// skipped by serde is returned along with.
+pub async fn find_after<T>(collection: &Collection<T>, after_id: i64, limit: u32) -> Result<Vec<(T, i32)>, Error>
+    where T: DeserializeOwned + Send + Sync
+{
+    let options = FindOptions::builder().sort(doc! { "id": 1 }).limit(limit as i64).build();
+    let filter = doc! { "id": { "$gt": after_id } };
+    let documents: Vec<Document> = match RawTransaction::current() {
+        Some(RawTransaction::Mongo(session)) => {
+            let mut session = session.lock().await;
+            let mut cursor = session_collection(&session, collection)
+                .find(filter)
+                .with_options(options)
+                .session(&mut *session).await?;
+            cursor.stream(&mut session).try_collect().await?
+        }
+        _ => collection.clone_with_type::<Document>().find(filter).with_options(options).await?.try_collect().await?,
+    };
+    documents
+        .into_iter()
+        .map(|document| {
+            let del_flag = match document.get("del_flag") {
+                Some(Bson::Int32(v)) => *v,
+                Some(Bson::Int64(v)) => *v as i32,
+                _ => 0,
+            };
+            Ok((mongodb::bson::from_document(document)?, del_flag))
+        })
+        .collect()
+}
+
+// Insert the document as is with the given fields, such as the fields skipped by serde.
+pub async fn insert_with<T>(collection: &Collection<T>, bean: &T, fields: Document) -> Result<(), Error>
+    where T: Serialize + Send + Sync
+{
+    let mut document = mongodb::bson::to_document(bean)?;
+    document.extend(fields);
+    match RawTransaction::current() {
+        Some(RawTransaction::Mongo(session)) => {
+            let mut session = session.lock().await;
+            session_collection(&session, collection).insert_one(document).session(&mut *session).await?;
+        }
+        _ => {
+            collection.clone_with_type::<Document>().insert_one(document).await?;
+        }
+    }
+    Ok(())
+}
+
+// Delete the document of the id (include the trashed), see: AsyncRepository::delete_raw
+pub async fn delete_by_id<T>(collection: &Collection<T>, id: i64) -> Result<u64, Error> where T: Send + Sync {
+    let result = match RawTransaction::current() {
+        Some(RawTransaction::Mongo(session)) => {
+            let mut session = session.lock().await;
+            session_collection(&session, collection).delete_one(doc! { "id": id }).session(&mut *session).await?
+        }
+        _ => collection.delete_one(doc! { "id": id }).await?,
+    };
+    Ok(result.deleted_count)
+}
+
+// Begin the raw transaction of the client, which requires the replica set or the sharded cluster.
+pub async fn begin_raw<T>(collection: &Collection<T>) -> Result<RawTransaction, Error> where T: Send + Sync {
+    let mut session = collection.client().start_session().await?;
+    session.start_transaction().await?;
+    Ok(RawTransaction::Mongo(Arc::new(Mutex::new(session))))
+}
+
+// The collection of the client of the session, since the session is only used by its client, and each
+// of the repositories has the own client.
+fn session_collection<T>(session: &ClientSession, collection: &Collection<T>) -> Collection<Document>
+    where T: Send + Sync
+{
+    let namespace = collection.namespace();
+    session.client().database(&namespace.db).collection(&namespace.coll)
+}
+
+// Convert the generic value of updating fields to the BSON value.
+pub fn to_bson_value(value: GenericValue) -> Bson {
+    match value {
+        GenericValue::Null => Bson::Null,
+        GenericValue::Int32(v) => Bson::Int32(v),
+        GenericValue::Int64(v) => Bson::Int64(v),
+        GenericValue::Float64(v) => Bson::Double(v),
+        GenericValue::Bool(v) => Bson::Boolean(v),
+        GenericValue::String(v) => Bson::String(v),
+    }
+}
+
+// The update of mark as trashed (1) or restored (0), also stamps the audit fields.
+pub async fn del_flag_update(del_flag: i32) -> mongodb::bson::Document {
+    let mut base = BaseBean::new_with_id(None);
+    base.pre_update(None).await;
+    mongodb::bson::doc! {
+        "$set": {
+            "del_flag": del_flag,
+            "update_by": base.update_by.unwrap_or_default(),
+            "update_time": base.update_time.unwrap_or_default(),
+        },
+        "$inc": { "version": 1_i64 },
+    }
+}
+
 #[allow(unused)]
 #[async_trait]
 impl<T: Any + Send + Sync> AsyncRepository<T> for MongoRepository<T> {
@@ -107,55 +362,25 @@ impl<T: Any + Send + Sync> AsyncRepository<T> for MongoRepository<T> {
 
 #[macro_export]
 macro_rules! dynamic_mongo_query {
-    ($bean:expr, $collection:expr, $order_by:expr, $page:expr, $($t:ty),+) => {
+    ($bean:expr, $collection:expr, $owner:expr, $del_flag:expr, $projection:expr, $order_by:expr, $columns:expr, $page:expr, $($t:ty),+) => {
         {
-            use mongodb::bson::{doc, Document};
-            use futures::stream::TryStreamExt;
-
-            let serialized = serde_json::to_value(&$bean).unwrap();
-            let obj = serialized.as_object().unwrap();
-
-            let mut filter = Document::new();
-            for (key, value) in obj {
-                if !value.is_null() {
-                    let v = value.as_str().unwrap_or("");
-                    if !v.is_empty() {
-                        filter.insert(key, v);
-                    }
-                }
-            }
+            use mongodb::bson::Document;
+
+            let mut filter: Document = $crate::store::mongo::bean_filter(&$bean);
             if let Some(id) = $bean.base.id {
                 filter.insert("id", id);
             }
-
-            let options = mongodb::options::FindOptions::builder()
-                .skip($page.get_offset() as u64)
-                .limit($page.get_limit() as i64)
-                .sort(doc! { $order_by: -1 })
-                .build();
-
-            // Queries to get total count.
-            let total_count = $collection.count_documents(filter.clone()).await.unwrap();
-
-            // Queries to get data.
-            let cursor = $collection
-                .find(filter)
-                .skip(options.skip.unwrap())
-                .limit(options.limit.unwrap())
-                .sort(options.sort.unwrap()).await?;
-
-            match cursor.try_collect().await {
-                std::result::Result::Ok(result) => {
-                  let page = PageResponse::new(
-                      Some(total_count as i64),
-                      Some($page.get_offset()),
-                      Some($page.get_limit()));
-                    Ok((page, result))
-                },
-                Err(error) => {
-                    Err(error.into())
-                }
+            let owner: Option<i64> = $owner;
+            if let Some(owner_uid) = owner {
+                filter.insert("owner_uid", owner_uid);
+            }
+            let del_flag: Option<i32> = $del_flag;
+            if let Some(del_flag) = del_flag {
+                filter.extend($crate::store::mongo::del_flag_filter(del_flag));
             }
+
+            let projection: Option<Document> = $projection;
+            $crate::store::mongo::find_page(&$collection, filter, projection, $order_by, $columns, &$page).await
         }
     };
 }
@@ -188,7 +413,7 @@ macro_rules! dynamic_mongo_insert {
 
 #[macro_export]
 macro_rules! dynamic_mongo_update {
-    ($bean:expr, $collection:expr) => {
+    ($bean:expr, $collection:expr, $owner:expr, $del_flag:expr) => {
         {
             use mongodb::bson::{doc, to_bson, Bson};
 
@@ -215,16 +440,32 @@ macro_rules! dynamic_mongo_update {
 
             let mut update_doc = mongodb::bson::Document::new();
             for (key, value) in obj.iter() {
-                if !is_empty_value(value) {
+                if !is_empty_value(value) && key != "version" {
                     update_doc.insert(key, value.clone());
                 }
             }
 
-            let filter = doc! { "id": id };
-            let update = doc! { "$set": update_doc };
+            let mut filter = doc! { "id": id };
+            // The optimistic lock, and the absent version of the legacy records is taken as 0.
+            let version: Option<i64> = $bean.base.version;
+            match version {
+                Some(0) => filter.insert("version", doc! { "$in": [0_i64, Bson::Null] }),
+                Some(version) => filter.insert("version", version),
+                None => None,
+            };
+            let owner: Option<i64> = $owner;
+            if let Some(owner_uid) = owner {
+                filter.insert("owner_uid", owner_uid);
+            }
+            let del_flag: Option<i32> = $del_flag;
+            if let Some(del_flag) = del_flag {
+                filter.extend($crate::store::mongo::del_flag_filter(del_flag));
+            }
+            let update = doc! { "$set": update_doc, "$inc": { "version": 1_i64 } };
             let result = $collection.update_one(filter, update).await?;
 
-            if result.modified_count > 0 {
+            // Matched rather than modified, so that an unchanged owned record isn't taken as missing.
+            if result.matched_count > 0 {
                 Ok(id)
             } else {
                 Ok(-1)
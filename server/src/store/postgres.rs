@@ -0,0 +1,387 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use anyhow::Error;
+use serde::Serialize;
+use tracing::debug;
+use sqlx::{
+    migrate::{ Migrate, MigrateError, Migrator },
+    postgres::{ PgArguments, PgPoolOptions, PgQueryResult, PgRow },
+    query::Query,
+    Arguments,
+    FromRow,
+    PgPool,
+    Postgres,
+};
+
+use crate::{
+    config::config_serve::{ DbProperties, DbType },
+    types::{ PageResponse, PageRequest },
+    utils::types::GenericValue,
+};
+use super::query::{ Dialect, Operator, QueryBuilder };
+use super::{ to_cursor_page, RawTransaction };
+use super::sqlite::MigrationStatus;
+
+// The embedded versioned migrations of Postgres, which are equivalent to the SQLite ones of
+// the same versions, see: store/sqlite.rs
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
+
+pub struct PostgresRepository<T: Any + Send + Sync> {
+    phantom: PhantomData<T>,
+    pool: PgPool,
+}
+
+impl<T: Any + Send + Sync> PostgresRepository<T> {
+    // The pool is shared by all the repositories, see: create_pool()
+    pub fn new(pool: PgPool) -> Self {
+        PostgresRepository {
+            phantom: PhantomData,
+            pool,
+        }
+    }
+
+    pub fn get_pool(&self) -> &PgPool {
+        &self.pool
+    }
+}
+
+// Create the pool shared by all the repositories, and run the migrations once.
+pub async fn create_pool(config: &DbProperties) -> Result<PgPool, Error> {
+    if config.db_type == DbType::Postgres {
+        let pool = connect(config).await?;
+        init_migration(&pool).await?;
+        Ok(pool)
+    } else {
+        // Notice: All the repositories of DbTypes are created, so never connect until used.
+        Ok(pool_options(config).connect_lazy(&config.postgres.url)?)
+    }
+}
+
+fn pool_options(config: &DbProperties) -> PgPoolOptions {
+    let postgres = &config.postgres;
+    PgPoolOptions::new()
+        .max_connections(postgres.max_connections.max(1))
+        .min_connections(postgres.min_connections)
+        .acquire_timeout(Duration::from_millis(postgres.acquire_timeout))
+        .idle_timeout(Duration::from_millis(postgres.idle_timeout))
+}
+
+// Connect to the database only without running migrations.
+pub async fn connect(config: &DbProperties) -> Result<PgPool, Error> {
+    match pool_options(config).connect(&config.postgres.url).await {
+        Ok(pool) => {
+            tracing::info!("Successfully connected to the postgres database");
+            Ok(pool)
+        }
+        Err(e) => {
+            tracing::info!("Database postgres connection error: {:?}", e);
+            Err(e.into())
+        }
+    }
+}
+
+async fn init_migration(pool: &PgPool) -> Result<(), Error> {
+    for status in migration_status(pool).await? {
+        debug!("Migration status: {:?}", status);
+    }
+
+    // Notice: The migrator holds the advisory lock of database, so that it's safe to run concurrently.
+    match MIGRATOR.run(pool).await {
+        Ok(_) => {
+            tracing::info!("Migration success");
+            Ok(())
+        }
+        Err(MigrateError::VersionMismatch(version)) => {
+            Err(
+                Error::msg(
+                    format!(
+                        "Error migration: the applied version {} was modified after release, \
+                        please restore its original up script or rollback to the previous version by 'db migrate-rollback'",
+                        version
+                    )
+                )
+            )
+        }
+        Err(MigrateError::Dirty(version)) => {
+            Err(
+                Error::msg(
+                    format!(
+                        "Error migration: the version {} was partially applied, please fix the database \
+                        manually and delete it from the '_sqlx_migrations' table",
+                        version
+                    )
+                )
+            )
+        }
+        Err(error) => Err(Error::msg(format!("Error migration: {}", error))),
+    }
+}
+
+// Compare the embedded migrations with the applied migrations of database.
+pub async fn migration_status(pool: &PgPool) -> Result<Vec<MigrationStatus>, Error> {
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    let applied: HashMap<i64, Vec<u8>> = conn
+        .list_applied_migrations().await?
+        .into_iter()
+        .map(|m| (m.version, m.checksum.into_owned()))
+        .collect();
+
+    Ok(
+        MIGRATOR.iter()
+            .filter(|m| !m.migration_type.is_down_migration())
+            .map(|m| {
+                let checksum = applied.get(&m.version);
+                MigrationStatus {
+                    version: m.version,
+                    description: m.description.to_string(),
+                    applied: checksum.is_some(),
+                    checksum_matched: checksum
+                        .map(|c| c.as_slice() == m.checksum.as_ref())
+                        .unwrap_or(true),
+                }
+            })
+            .collect()
+    )
+}
+
+// Revert all applied versions newer than the target version in descending order.
+pub async fn migration_rollback(pool: &PgPool, target: i64) -> Result<(), Error> {
+    if target != 0 && !MIGRATOR.version_exists(target) {
+        return Err(Error::msg(format!("Unknown the rollback target version {}", target)));
+    }
+    MIGRATOR.undo(pool, target).await.map_err(|e| Error::msg(format!("Error rollback: {}", e)))
+}
+
+// The typed operations shared by all the Postgres repositories, which are same as the SQLite
+// repositories but render the builder with the '$n' placeholders, see: store/query.rs
+impl<T> PostgresRepository<T>
+    where T: Any + Send + Sync + Unpin + Serialize + for<'r> FromRow<'r, PgRow>
+{
+    // Select the page by the offset, or by seeking the keys of the cursor if present, which fetches
+    // one more row for detecting the next page, see: store::to_cursor_page
+    pub async fn select_page(
+        &self,
+        builder: &QueryBuilder,
+        page: &PageRequest
+    ) -> Result<(PageResponse, Vec<T>), Error> {
+        let builder = builder.clone().dialect(Dialect::Postgres);
+        let builder = builder.clone().sort_by(page)?.order_by_keyset()?.select_fields(page)?;
+        let cursor = page.get_cursor()?;
+
+        // Queries to get total count, which is optional for the cursor paging.
+        let total_count = if page.is_with_total() {
+            let (count_sql, params) = builder.build_count();
+            let total_count: i64 = sqlx
+                ::query_scalar_with(&count_sql, to_arguments(params))
+                .fetch_one(self.get_pool()).await?;
+            Some(total_count)
+        } else {
+            None
+        };
+
+        // Queries to get data.
+        let (sql, params) = match &cursor {
+            Some(cursor) => builder.clone().seek(cursor)?.build_select(page.get_limit() + 1, 0),
+            None => builder.build_select(page.get_limit() + 1, page.get_offset()),
+        };
+        let result = sqlx
+            ::query_as_with::<_, T, _>(&sql, to_arguments(params))
+            .fetch_all(self.get_pool()).await?;
+
+        to_cursor_page(page, cursor.as_ref(), &builder.order_columns(), total_count, result)
+    }
+
+    // Select the rows of the ids greater than the 'after_id' in ascending order of the id, which is
+    // for scanning all the rows matched the builder conditions, such as the data migration.
+    pub async fn select_after(&self, builder: &QueryBuilder, after_id: i64, limit: u32) -> Result<Vec<T>, Error> {
+        let builder = builder.clone().dialect(Dialect::Postgres);
+        let builder = builder.and("id", Operator::Gt, after_id)?.order_by("id", false)?;
+        let (sql, params) = builder.build_select(limit, 0);
+        let query = sqlx::query_as_with::<_, T, _>(&sql, to_arguments(params));
+        let result = match RawTransaction::current() {
+            Some(RawTransaction::Postgres(tx)) => query.fetch_all(&mut **tx.lock().await).await?,
+            _ => query.fetch_all(self.get_pool()).await?,
+        };
+        Ok(result)
+    }
+
+    pub async fn select_one(&self, builder: &QueryBuilder) -> Result<Option<T>, Error> {
+        let (sql, params) = builder.clone().dialect(Dialect::Postgres).build_select(1, 0);
+        let result = sqlx
+            ::query_as_with::<_, T, _>(&sql, to_arguments(params))
+            .fetch_optional(self.get_pool()).await?;
+        Ok(result)
+    }
+
+    // Returns the inserted id.
+    pub async fn insert_bean(&self, builder: &QueryBuilder, bean: &T) -> Result<i64, Error> {
+        let (sql, params) = builder.clone().dialect(Dialect::Postgres).build_insert(bean)?;
+        let sql = format!("{} RETURNING id", sql);
+        let query = sqlx::query_scalar_with(&sql, to_arguments(params));
+        let id: i64 = match RawTransaction::current() {
+            Some(RawTransaction::Postgres(tx)) => query.fetch_one(&mut **tx.lock().await).await?,
+            _ => query.fetch_one(self.get_pool()).await?,
+        };
+        Ok(id)
+    }
+
+    // Insert the bean with the given columns, such as the fields skipped by serde, returns the inserted id.
+    pub async fn insert_bean_with(
+        &self,
+        builder: &QueryBuilder,
+        bean: &T,
+        values: Vec<(String, GenericValue)>
+    ) -> Result<i64, Error> {
+        let (sql, params) = builder.clone().dialect(Dialect::Postgres).build_insert_with(bean, values)?;
+        let sql = format!("{} RETURNING id", sql);
+        let query = sqlx::query_scalar_with(&sql, to_arguments(params));
+        let id: i64 = match RawTransaction::current() {
+            Some(RawTransaction::Postgres(tx)) => query.fetch_one(&mut **tx.lock().await).await?,
+            _ => query.fetch_one(self.get_pool()).await?,
+        };
+        Ok(id)
+    }
+
+    // Returns the number of rows affected, which matched the builder conditions.
+    pub async fn update_bean(&self, builder: &QueryBuilder, bean: &T) -> Result<u64, Error> {
+        let (sql, params) = builder.clone().dialect(Dialect::Postgres).build_update(bean)?;
+        let result = sqlx::query_with(&sql, to_arguments(params)).execute(self.get_pool()).await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn update_values(
+        &self,
+        builder: &QueryBuilder,
+        values: Vec<(String, GenericValue)>
+    ) -> Result<u64, Error> {
+        let (sql, params) = builder.clone().dialect(Dialect::Postgres).build_update_values(values)?;
+        let result = sqlx::query_with(&sql, to_arguments(params)).execute(self.get_pool()).await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn update_increase(
+        &self,
+        builder: &QueryBuilder,
+        increases: Vec<(String, i64)>,
+        values: Vec<(String, GenericValue)>
+    ) -> Result<u64, Error> {
+        let (sql, params) = builder.clone().dialect(Dialect::Postgres).build_update_increase(increases, values)?;
+        let result = sqlx::query_with(&sql, to_arguments(params)).execute(self.get_pool()).await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn delete(&self, builder: &QueryBuilder) -> Result<u64, Error> {
+        let (sql, params) = builder.clone().dialect(Dialect::Postgres).build_delete();
+        let result = self.execute(sqlx::query_with(&sql, to_arguments(params))).await?;
+        Ok(result.rows_affected())
+    }
+
+    // The raw operations are executed in the raw transaction if bound, see: RawTransaction::scope()
+    pub async fn execute(&self, query: Query<'_, Postgres, PgArguments>) -> Result<PgQueryResult, Error> {
+        match RawTransaction::current() {
+            Some(RawTransaction::Postgres(tx)) => Ok(query.execute(&mut **tx.lock().await).await?),
+            _ => Ok(query.execute(self.get_pool()).await?),
+        }
+    }
+}
+
+pub fn to_arguments(params: Vec<GenericValue>) -> PgArguments {
+    let mut args = PgArguments::default();
+    for param in params {
+        match param {
+            GenericValue::Null => args.add(Option::<String>::None),
+            GenericValue::Int32(v) => args.add(v),
+            GenericValue::Int64(v) => args.add(v),
+            GenericValue::Float64(v) => args.add(v),
+            GenericValue::Bool(v) => args.add(v),
+            GenericValue::String(v) => args.add(v),
+        }
+    }
+    args
+}
+
+// The test database of the env 'MYWEBNOTE_TEST_POSTGRES_URL', each of which is isolated in a new
+// schema, and the Postgres tests are skipped if it's absent.
+#[cfg(test)]
+pub(crate) async fn create_test_config() -> Option<DbProperties> {
+    let url = std::env::var("MYWEBNOTE_TEST_POSTGRES_URL").ok()?;
+    let schema = format!("test_{}", uuid::Uuid::new_v4().simple());
+    let pool = PgPool::connect(&url).await.unwrap();
+    sqlx::query(&format!("CREATE SCHEMA {}", schema)).execute(&pool).await.unwrap();
+    pool.close().await;
+
+    let mut config = DbProperties::default();
+    config.db_type = DbType::Postgres;
+    let separator = if url.contains('?') { '&' } else { '?' };
+    config.postgres.url = format!("{}{}options[search_path]={}", url, separator, schema);
+    Some(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::Row;
+
+    async fn table_exists(pool: &PgPool, table: &str) -> bool {
+        sqlx::query("SELECT COUNT(1) FROM information_schema.tables WHERE table_schema = current_schema() AND table_name = $1")
+            .bind(table)
+            .fetch_one(pool).await
+            .map(|row| row.get::<i64, _>(0) > 0)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_migration_run_and_rollback() {
+        let config = match create_test_config().await {
+            Some(config) => config,
+            None => return,
+        };
+        let pool = create_pool(&config).await.unwrap();
+        for table in ["users", "documents", "folders", "settings", "document_revisions", "blobs"] {
+            assert!(table_exists(&pool, table).await, "missing table {}", table);
+        }
+        let status = migration_status(&pool).await.unwrap();
+        assert!(status.iter().all(|s| s.applied && s.checksum_matched));
+
+        // Re-open the migrated database should be no-op.
+        assert!(create_pool(&config).await.is_ok());
+
+        migration_rollback(&pool, 0).await.unwrap();
+        let status = migration_status(&pool).await.unwrap();
+        assert!(status.iter().all(|s| !s.applied));
+        assert!(!table_exists(&pool, "users").await);
+    }
+
+    #[tokio::test]
+    async fn test_lazy_connect_if_not_enabled() {
+        let mut config = DbProperties::default();
+        config.postgres.url = "postgres://nobody@127.0.0.1:1/none".to_string();
+        assert!(create_pool(&config).await.is_ok());
+    }
+}
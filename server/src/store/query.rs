@@ -0,0 +1,890 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use anyhow::Error;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::errors::BizError;
+use crate::types::{ PageCursor, PageRequest };
+use crate::utils::types::GenericValue;
+
+// The typed SQL query builder for the repositories of relational databases, which instead
+// of the interpolating the serde keys and values directly. All the column names are checked
+// against the table whitelist, and all the values are bound as the placeholder parameters.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Like,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    In,
+    // The 'IN' of the values bound as one JSON array parameter, see: QueryBuilder::and_in_array
+    InArray,
+    IsNull,
+    IsNotNull,
+}
+
+impl Operator {
+    fn to_sql(&self) -> &'static str {
+        match self {
+            Operator::Eq => "=",
+            Operator::Ne => "<>",
+            Operator::Like => "LIKE",
+            Operator::Gt => ">",
+            Operator::Gte => ">=",
+            Operator::Lt => "<",
+            Operator::Lte => "<=",
+            Operator::In | Operator::InArray => "IN",
+            Operator::IsNull => "IS NULL",
+            Operator::IsNotNull => "IS NOT NULL",
+        }
+    }
+}
+
+// The SQL dialect of the placeholders, such as the '?' of SQLite and the '$1' of Postgres.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dialect {
+    SQLite,
+    Postgres,
+}
+
+impl Dialect {
+    // The placeholder of the parameter at the position (1-based).
+    pub fn placeholder(&self, position: usize) -> String {
+        match self {
+            Dialect::SQLite => "?".to_string(),
+            Dialect::Postgres => format!("${}", position),
+        }
+    }
+
+    // The sub query of the elements of the JSON array parameter, which are cast to the integers if numeric.
+    fn array_elements(&self, holder: &str, numeric: bool) -> String {
+        match self {
+            Dialect::SQLite => format!("SELECT value FROM json_each({})", holder),
+            Dialect::Postgres => {
+                let value = if numeric { "value::bigint" } else { "value" };
+                format!("SELECT {} FROM jsonb_array_elements_text({}::jsonb)", value, holder)
+            }
+        }
+    }
+}
+
+// The optimistic lock column, see: types::BaseBean
+pub const VERSION_COLUMN: &str = "version";
+
+// The columns of the common fields (and the owner), which are always selected by the projection.
+pub const COMMON_COLUMNS: &[&str] = &[
+    "id", "status", "create_by", "create_time", "update_by", "update_time", "del_flag", "version", "owner_uid",
+];
+
+#[derive(Clone, Debug)]
+struct Condition {
+    column: &'static str,
+    operator: Operator,
+    values: Vec<GenericValue>,
+    // The sub query of the 'IN' instead of the values if present.
+    select: Option<Box<SubSelect>>,
+}
+
+// The sub query selecting the column of the matched rows, which are grouped having the count of the
+// distinct values if present, such as the documents tagged with all the tags.
+#[derive(Clone, Debug)]
+struct SubSelect {
+    builder: QueryBuilder,
+    column: &'static str,
+    having: Option<(&'static str, i64)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct QueryBuilder {
+    table: &'static str,
+    columns: &'static [&'static str],
+    conditions: Vec<Condition>,
+    order_by: Vec<(&'static str, bool)>,
+    // The selected columns, all if empty.
+    selects: Vec<&'static str>,
+    // The keys of the cursor paging to seek after along the orders, see: types::PageCursor
+    seek: Option<Vec<GenericValue>>,
+    dialect: Dialect,
+}
+
+impl QueryBuilder {
+    pub fn new(table: &'static str, columns: &'static [&'static str]) -> Self {
+        Self {
+            table,
+            columns,
+            conditions: Vec::new(),
+            order_by: Vec::new(),
+            selects: Vec::new(),
+            seek: None,
+            dialect: Dialect::SQLite,
+        }
+    }
+
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    // The placeholder of the next parameter to push.
+    fn next_placeholder(&self, params: &[GenericValue]) -> String {
+        self.dialect.placeholder(params.len() + 1)
+    }
+
+    // Resolve to the whitelist column, so that the caller input never be interpolated into SQL.
+    fn column(&self, name: &str) -> Result<&'static str, Error> {
+        self.columns
+            .iter()
+            .find(|c| **c == name)
+            .copied()
+            .ok_or_else(|| Error::msg(format!("Unknown column '{}' of table '{}'", name, self.table)))
+    }
+
+    pub fn and(
+        mut self,
+        column: &str,
+        operator: Operator,
+        value: impl Into<GenericValue>
+    ) -> Result<Self, Error> {
+        let column = self.column(column)?;
+        let values = match operator {
+            Operator::IsNull | Operator::IsNotNull => Vec::new(),
+            _ => vec![value.into()],
+        };
+        self.conditions.push(Condition { column, operator, values, select: None });
+        Ok(self)
+    }
+
+    pub fn and_in(mut self, column: &str, values: Vec<GenericValue>) -> Result<Self, Error> {
+        let column = self.column(column)?;
+        self.conditions.push(Condition { column, operator: Operator::In, values, select: None });
+        Ok(self)
+    }
+
+    // The 'IN' condition of the values bound as one JSON array parameter, such as 'id IN (SELECT value FROM
+    // json_each(?))', so that the unbounded values never exceed the limit of the parameters of SQLite.
+    pub fn and_in_array(mut self, column: &str, values: Vec<GenericValue>) -> Result<Self, Error> {
+        let column = self.column(column)?;
+        self.conditions.push(Condition { column, operator: Operator::InArray, values, select: None });
+        Ok(self)
+    }
+
+    // The 'IN' condition of the sub query, such as 'id IN (SELECT document_id FROM document_tags WHERE ...)',
+    // so that the unbounded ids are never bound as the parameters. The sub query is grouped by the selected
+    // column having the count of the distinct values of the other column if present.
+    pub fn and_in_select(
+        mut self,
+        column: &str,
+        builder: QueryBuilder,
+        select: &str,
+        having: Option<(&str, i64)>
+    ) -> Result<Self, Error> {
+        let column = self.column(column)?;
+        let having = match having {
+            Some((distinct, count)) => Some((builder.column(distinct)?, count)),
+            None => None,
+        };
+        let select = SubSelect { column: builder.column(select)?, builder, having };
+        self.conditions.push(Condition {
+            column,
+            operator: Operator::In,
+            values: Vec::new(),
+            select: Some(Box::new(select)),
+        });
+        Ok(self)
+    }
+
+    pub fn and_null(mut self, column: &str, is_null: bool) -> Result<Self, Error> {
+        let column = self.column(column)?;
+        let operator = if is_null { Operator::IsNull } else { Operator::IsNotNull };
+        self.conditions.push(Condition { column, operator, values: Vec::new(), select: None });
+        Ok(self)
+    }
+
+    // Equality conditions of all the present fields of the bean, the null and empty string are
+    // treated as absent.
+    pub fn and_bean<T: Serialize>(mut self, bean: &T) -> Result<Self, Error> {
+        for (key, value) in to_present_values(bean)? {
+            self = self.and(&key, Operator::Eq, value)?;
+        }
+        Ok(self)
+    }
+
+    // Select all the columns but the excluded, such as omit the large content of the listing.
+    pub fn select_without(mut self, excludes: &[&str]) -> Result<Self, Error> {
+        for exclude in excludes {
+            self.column(exclude)?;
+        }
+        self.selects = self.columns
+            .iter()
+            .filter(|c| !excludes.contains(c))
+            .copied()
+            .collect();
+        Ok(self)
+    }
+
+    pub fn order_by(mut self, column: &str, desc: bool) -> Result<Self, Error> {
+        let column = self.column(column)?;
+        self.order_by.push((column, desc));
+        Ok(self)
+    }
+
+    // Replace the orders with the client sort of the page if present, see: types::PageRequest
+    pub fn sort_by(mut self, page: &PageRequest) -> Result<Self, Error> {
+        let sorts = page.get_sorts();
+        if sorts.is_empty() {
+            return Ok(self);
+        }
+        self.order_by.clear();
+        for (column, desc) in sorts {
+            self = self.order_by(column, desc).map_err(to_bad_request)?;
+        }
+        Ok(self)
+    }
+
+    // Select only the common columns and the client fields of the page if present, which is narrowed
+    // within the selected columns, and the ordering columns are also selected for the page cursors.
+    pub fn select_fields(mut self, page: &PageRequest) -> Result<Self, Error> {
+        let fields = page.get_fields();
+        if fields.is_empty() {
+            return Ok(self);
+        }
+        for field in &fields {
+            self.column(field).map_err(to_bad_request)?;
+        }
+        let order_columns = self.order_columns();
+        let selected = if self.selects.is_empty() { self.columns } else { &self.selects[..] };
+        self.selects = selected
+            .iter()
+            .filter(|c| COMMON_COLUMNS.contains(c) || fields.contains(c) || order_columns.contains(c))
+            .copied()
+            .collect();
+        Ok(self)
+    }
+
+    // Append the 'id' as the tie breaker of the orders, so that the orders are the unique keys of
+    // the rows for the cursor paging, see: types::PageCursor
+    pub fn order_by_keyset(self) -> Result<Self, Error> {
+        if self.order_by.iter().any(|(column, _)| *column == "id") {
+            return Ok(self);
+        }
+        let desc = self.order_by.first().map(|(_, desc)| *desc).unwrap_or(true);
+        self.order_by("id", desc)
+    }
+
+    pub fn order_columns(&self) -> Vec<&'static str> {
+        self.order_by.iter().map(|(column, _)| *column).collect()
+    }
+
+    // Seek the rows after the keys of the cursor along the orders, or before the keys for the
+    // backward cursor (the orders are reversed, so the nearest rows are taken first).
+    pub fn seek(mut self, cursor: &PageCursor) -> Result<Self, Error> {
+        if self.order_by.is_empty() || cursor.keys.len() != self.order_by.len() {
+            return Err(BizError::BadRequest("The page cursor mismatches the orders".to_string()).into());
+        }
+        if cursor.backward {
+            self.order_by.iter_mut().for_each(|(_, desc)| {
+                *desc = !*desc;
+            });
+        }
+        let values = cursor.keys.iter().map(to_generic_value).collect::<Result<Vec<_>, _>>();
+        self.seek = Some(values.map_err(to_bad_request)?);
+        Ok(self)
+    }
+
+    fn build_where(&self, params: &mut Vec<GenericValue>) -> String {
+        let mut clause = self.build_conditions(params);
+        if let Some(values) = &self.seek {
+            clause = format!("{} AND {}", clause, self.build_seek(values, params));
+        }
+        clause
+    }
+
+    // The keyset condition, such as '(update_time, id) < (?, ?)' if all the orders are in the same
+    // direction, otherwise expands as '(a > ? OR (a = ? AND b < ?))'.
+    fn build_seek(&self, values: &[GenericValue], params: &mut Vec<GenericValue>) -> String {
+        let operator = |desc: bool| (if desc { Operator::Lt } else { Operator::Gt }).to_sql();
+        let desc = self.order_by[0].1;
+        if self.order_by.iter().all(|(_, d)| *d == desc) {
+            let holders = values
+                .iter()
+                .map(|v| {
+                    let holder = self.next_placeholder(params);
+                    params.push(v.clone());
+                    holder
+                })
+                .collect::<Vec<String>>()
+                .join(", ");
+            let columns = self.order_columns().join(", ");
+            return format!("({}) {} ({})", columns, operator(desc), holders);
+        }
+        let mut ors = Vec::new();
+        for (i, (column, desc)) in self.order_by.iter().enumerate() {
+            let mut ands = Vec::new();
+            for (j, (prev, _)) in self.order_by.iter().enumerate().take(i) {
+                ands.push(format!("{} = {}", prev, self.next_placeholder(params)));
+                params.push(values[j].clone());
+            }
+            ands.push(format!("{} {} {}", column, operator(*desc), self.next_placeholder(params)));
+            params.push(values[i].clone());
+            ors.push(ands.join(" AND "));
+        }
+        format!("(({}))", ors.join(") OR ("))
+    }
+
+    fn build_conditions(&self, params: &mut Vec<GenericValue>) -> String {
+        if self.conditions.is_empty() {
+            return "1=1".to_string();
+        }
+        self.conditions
+            .iter()
+            .map(|c| {
+                if let Some(select) = &c.select {
+                    return format!("{} IN ({})", c.column, self.build_sub_select(select, params));
+                }
+                match c.operator {
+                    Operator::IsNull | Operator::IsNotNull => {
+                        format!("{} {}", c.column, c.operator.to_sql())
+                    }
+                    Operator::In => {
+                        // The empty in-list never matches.
+                        if c.values.is_empty() {
+                            return "1=0".to_string();
+                        }
+                        let holders = c.values
+                            .iter()
+                            .map(|v| {
+                                let holder = self.next_placeholder(params);
+                                params.push(v.clone());
+                                holder
+                            })
+                            .collect::<Vec<String>>()
+                            .join(", ");
+                        format!("{} IN ({})", c.column, holders)
+                    }
+                    Operator::InArray => {
+                        if c.values.is_empty() {
+                            return "1=0".to_string();
+                        }
+                        let holder = self.next_placeholder(params);
+                        params.push(GenericValue::String(to_json_array(&c.values)));
+                        let numeric = matches!(c.values[0], GenericValue::Int32(_) | GenericValue::Int64(_));
+                        format!("{} IN ({})", c.column, self.dialect.array_elements(&holder, numeric))
+                    }
+                    _ => {
+                        let holder = self.next_placeholder(params);
+                        params.extend(c.values.iter().cloned());
+                        format!("{} {} {}", c.column, c.operator.to_sql(), holder)
+                    }
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(" AND ")
+    }
+
+    // The placeholders are numbered along the outer query, so the sub query is built in the same dialect.
+    fn build_sub_select(&self, select: &SubSelect, params: &mut Vec<GenericValue>) -> String {
+        let builder = select.builder.clone().dialect(self.dialect);
+        let mut sql = format!(
+            "SELECT {} FROM {} WHERE {}",
+            select.column,
+            builder.table,
+            builder.build_conditions(params)
+        );
+        if let Some((distinct, count)) = select.having {
+            let holder = self.next_placeholder(params);
+            params.push(GenericValue::Int64(count));
+            sql.push_str(&format!(" GROUP BY {} HAVING COUNT(DISTINCT {}) = {}", select.column, distinct, holder));
+        }
+        sql
+    }
+
+    fn build_order_by(&self) -> String {
+        if self.order_by.is_empty() {
+            return String::new();
+        }
+        let items = self.order_by
+            .iter()
+            .map(|(column, desc)| format!("{} {}", column, if *desc { "DESC" } else { "ASC" }))
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!(" ORDER BY {}", items)
+    }
+
+    pub fn build_count(&self) -> (String, Vec<GenericValue>) {
+        let mut params = Vec::new();
+        let where_clause = self.build_where(&mut params);
+        (format!("SELECT COUNT(1) FROM {} WHERE {}", self.table, where_clause), params)
+    }
+
+    // The ids of matched rows, such as for the sub query.
+    pub fn build_select_ids(&self) -> (String, Vec<GenericValue>) {
+        let mut params = Vec::new();
+        let where_clause = self.build_where(&mut params);
+        (format!("SELECT id FROM {} WHERE {}", self.table, where_clause), params)
+    }
+
+    pub fn build_select(&self, limit: u32, offset: u32) -> (String, Vec<GenericValue>) {
+        let mut params = Vec::new();
+        let where_clause = self.build_where(&mut params);
+        let selects = if self.selects.is_empty() { "*".to_string() } else { self.selects.join(", ") };
+        let sql = format!(
+            "SELECT {} FROM {} WHERE {}{} LIMIT {} OFFSET {}",
+            selects,
+            self.table,
+            where_clause,
+            self.build_order_by(),
+            limit,
+            offset
+        );
+        (sql, params)
+    }
+
+    pub fn build_insert<T: Serialize>(&self, bean: &T) -> Result<(String, Vec<GenericValue>), Error> {
+        self.build_insert_with(bean, Vec::new())
+    }
+
+    // Insert the present fields of the bean with the given columns, such as the fields skipped by serde.
+    pub fn build_insert_with<T: Serialize>(
+        &self,
+        bean: &T,
+        values: Vec<(String, GenericValue)>
+    ) -> Result<(String, Vec<GenericValue>), Error> {
+        let mut columns = Vec::new();
+        let mut holders = Vec::new();
+        let mut params = Vec::new();
+        for (key, value) in to_present_values(bean)?.into_iter().chain(values) {
+            columns.push(self.column(&key)?);
+            holders.push(self.next_placeholder(&params));
+            params.push(value);
+        }
+        if columns.is_empty() {
+            return Err(Error::msg(format!("Nothing to insert into table '{}'", self.table)));
+        }
+        let holders = holders.join(", ");
+        let sql = format!("INSERT INTO {} ({}) VALUES ({})", self.table, columns.join(", "), holders);
+        Ok((sql, params))
+    }
+
+    // Update the present fields of the bean (exclude the id) which matched the conditions, and
+    // if the version of bean presents, it's also matched as the optimistic lock.
+    pub fn build_update<T: Serialize>(&self, bean: &T) -> Result<(String, Vec<GenericValue>), Error> {
+        if self.conditions.is_empty() {
+            return Err(Error::msg(format!("Refusing to update all of table '{}'", self.table)));
+        }
+        let mut builder = self.clone();
+        let mut values = Vec::new();
+        for (key, value) in to_present_values(bean)? {
+            match key.as_str() {
+                "id" => {}
+                VERSION_COLUMN => {
+                    builder = builder.and(VERSION_COLUMN, Operator::Eq, value)?;
+                }
+                _ => values.push((key, value)),
+            }
+        }
+        builder.build_update_values(values)
+    }
+
+    // Update the given columns which matched the conditions, such as the fields skipped by serde,
+    // the version is always increased if the table has.
+    pub fn build_update_values(
+        &self,
+        values: Vec<(String, GenericValue)>
+    ) -> Result<(String, Vec<GenericValue>), Error> {
+        self.build_update_increase(Vec::new(), values)
+    }
+
+    // The update of the values, as well as increase the counter columns in the same statement, such as
+    // 'access_count = access_count + ?', so that no concurrent increase is lost.
+    pub fn build_update_increase(
+        &self,
+        increases: Vec<(String, i64)>,
+        values: Vec<(String, GenericValue)>
+    ) -> Result<(String, Vec<GenericValue>), Error> {
+        let mut sets = Vec::new();
+        let mut params = Vec::new();
+        for (key, delta) in increases {
+            let column = self.column(&key)?;
+            sets.push(format!("{} = COALESCE({}, 0) + {}", column, column, self.next_placeholder(&params)));
+            params.push(GenericValue::Int64(delta));
+        }
+        for (key, value) in values {
+            sets.push(format!("{} = {}", self.column(&key)?, self.next_placeholder(&params)));
+            params.push(value);
+        }
+        if sets.is_empty() {
+            return Err(Error::msg(format!("Nothing to update of table '{}'", self.table)));
+        }
+        if self.conditions.is_empty() {
+            return Err(Error::msg(format!("Refusing to update all of table '{}'", self.table)));
+        }
+        if self.columns.contains(&VERSION_COLUMN) {
+            sets.push(format!("{} = {} + 1", VERSION_COLUMN, VERSION_COLUMN));
+        }
+        let where_clause = self.build_where(&mut params);
+        let sql = format!("UPDATE {} SET {} WHERE {}", self.table, sets.join(", "), where_clause);
+        Ok((sql, params))
+    }
+
+    pub fn build_delete(&self) -> (String, Vec<GenericValue>) {
+        let mut params = Vec::new();
+        let where_clause = self.build_where(&mut params);
+        (format!("DELETE FROM {} WHERE {}", self.table, where_clause), params)
+    }
+}
+
+fn to_bad_request(e: Error) -> Error {
+    BizError::BadRequest(e.to_string()).into()
+}
+
+fn to_generic_value(value: &Value) -> Result<GenericValue, Error> {
+    match value {
+        Value::Null => Ok(GenericValue::Null),
+        Value::Bool(b) => Ok(GenericValue::Bool(*b)),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Ok(GenericValue::Int64(i))
+            } else if let Some(f) = n.as_f64() {
+                Ok(GenericValue::Float64(f))
+            } else {
+                Err(Error::msg(format!("Unsupported number '{}'", n)))
+            }
+        }
+        Value::String(s) => Ok(GenericValue::String(s.to_owned())),
+        _ => Err(Error::msg("Unsupported nested value")),
+    }
+}
+
+fn to_json_array(values: &[GenericValue]) -> String {
+    let values: Vec<Value> = values
+        .iter()
+        .map(|v| match v {
+            GenericValue::Null => Value::Null,
+            GenericValue::Int32(v) => Value::from(*v),
+            GenericValue::Int64(v) => Value::from(*v),
+            GenericValue::Float64(v) => Value::from(*v),
+            GenericValue::Bool(v) => Value::from(*v),
+            GenericValue::String(v) => Value::from(v.to_owned()),
+        })
+        .collect();
+    Value::Array(values).to_string()
+}
+
+// Notice: Because the ORM library is not used for the time being, the fields are dynamically
+// parsed based on serde_json, so the #[serde(rename="xx")] annotation is effective.
+fn to_present_values<T: Serialize>(bean: &T) -> Result<Vec<(String, GenericValue)>, Error> {
+    let serialized = serde_json::to_value(bean)?;
+    let obj = serialized
+        .as_object()
+        .ok_or_else(|| Error::msg("The bean must be serialized as an object"))?;
+
+    let mut values = Vec::new();
+    for (key, value) in obj {
+        let v = match value {
+            Value::Null => None,
+            Value::String(s) if s.is_empty() => None,
+            _ => Some(to_generic_value(value).map_err(|e| Error::msg(format!("{} of field '{}'", e, key)))?),
+        };
+        if let Some(v) = v {
+            values.push((key.to_owned(), v));
+        }
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const COLUMNS: &[&str] = &["id", "pid", "name", "status", "score"];
+
+    #[test]
+    fn test_build_select_with_typed_conditions() {
+        let builder = QueryBuilder::new("folders", COLUMNS)
+            .and_bean(&json!({ "pid": 0, "name": "n1", "status": null, "score": 1.5, "id": "" }))
+            .unwrap()
+            .and("status", Operator::Gte, 1)
+            .unwrap()
+            .and_in("id", vec![1i64.into(), 2i64.into()])
+            .unwrap()
+            .and_null("pid", false)
+            .unwrap()
+            .order_by("id", true)
+            .unwrap();
+
+        let (sql, params) = builder.build_select(10, 20);
+        assert_eq!(
+            sql,
+            "SELECT * FROM folders WHERE pid = ? AND name = ? AND score = ? AND status >= ? AND id IN (?, ?) AND pid IS NOT NULL ORDER BY id DESC LIMIT 10 OFFSET 20"
+        );
+        assert_eq!(
+            params,
+            vec![
+                GenericValue::Int64(0),
+                GenericValue::String("n1".to_string()),
+                GenericValue::Float64(1.5),
+                GenericValue::Int32(1),
+                GenericValue::Int64(1),
+                GenericValue::Int64(2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reject_unknown_columns() {
+        let builder = QueryBuilder::new("folders", COLUMNS);
+        assert!(builder.clone().and("name; drop table folders", Operator::Eq, 1).is_err());
+        assert!(builder.clone().and_bean(&json!({ "unknown": 1 })).is_err());
+        assert!(builder.clone().order_by("1; --", false).is_err());
+        assert!(builder.clone().select_without(&["unknown"]).is_err());
+        assert!(builder.build_insert(&json!({ "id": 1, "bad key": "x" })).is_err());
+    }
+
+    #[test]
+    fn test_build_select_without_columns() {
+        let builder = QueryBuilder::new("folders", COLUMNS).select_without(&["name", "score"]).unwrap();
+        let (sql, _) = builder.build_select(10, 0);
+        assert_eq!(sql, "SELECT id, pid, status FROM folders WHERE 1=1 LIMIT 10 OFFSET 0");
+    }
+
+    #[test]
+    fn test_build_insert_and_update() {
+        let bean = json!({ "id": 1, "name": "n1", "score": 0.5, "status": null });
+        let (sql, params) = QueryBuilder::new("folders", COLUMNS).build_insert(&bean).unwrap();
+        assert_eq!(sql, "INSERT INTO folders (id, name, score) VALUES (?, ?, ?)");
+        assert_eq!(params.len(), 3);
+
+        let builder = QueryBuilder::new("folders", COLUMNS).and("id", Operator::Eq, 1i64).unwrap();
+        let (sql, params) = builder.build_update(&bean).unwrap();
+        assert_eq!(sql, "UPDATE folders SET name = ?, score = ? WHERE id = ?");
+        assert_eq!(params.last(), Some(&GenericValue::Int64(1)));
+
+        // Must not be update the whole table.
+        assert!(QueryBuilder::new("folders", COLUMNS).build_update(&bean).is_err());
+    }
+
+    #[test]
+    fn test_build_update_with_version() {
+        const VERSIONED_COLUMNS: &[&str] = &["id", "name", "version"];
+        let builder = QueryBuilder::new("folders", VERSIONED_COLUMNS).and("id", Operator::Eq, 1i64).unwrap();
+
+        let (sql, params) = builder.build_update(&json!({ "id": 1, "name": "n1", "version": 3 })).unwrap();
+        assert_eq!(sql, "UPDATE folders SET name = ?, version = version + 1 WHERE id = ? AND version = ?");
+        assert_eq!(params.last(), Some(&GenericValue::Int64(3)));
+
+        // Always increased even if the version is absent.
+        let (sql, _) = builder.build_update(&json!({ "name": "n1" })).unwrap();
+        assert_eq!(sql, "UPDATE folders SET name = ?, version = version + 1 WHERE id = ?");
+        let values = vec![("name".to_string(), GenericValue::String("n1".to_string()))];
+        let (sql, _) = builder.build_update_values(values).unwrap();
+        assert_eq!(sql, "UPDATE folders SET name = ?, version = version + 1 WHERE id = ?");
+    }
+
+    #[test]
+    fn test_build_select_with_seek() {
+        const TIMED_COLUMNS: &[&str] = &["id", "name", "update_time"];
+        let builder = QueryBuilder::new("folders", TIMED_COLUMNS)
+            .and("name", Operator::Eq, "n1")
+            .unwrap()
+            .order_by("update_time", true)
+            .unwrap()
+            .order_by_keyset()
+            .unwrap();
+        assert_eq!(builder.order_columns(), vec!["update_time", "id"]);
+
+        let (sql, params) = builder.clone().seek(&PageCursor::new(false, vec![json!(100), json!(1)])).unwrap().build_select(11, 0);
+        assert_eq!(
+            sql,
+            "SELECT * FROM folders WHERE name = ? AND (update_time, id) < (?, ?) ORDER BY update_time DESC, id DESC LIMIT 11 OFFSET 0"
+        );
+        assert_eq!(params.len(), 3);
+
+        // The backward seeks before the keys in the reversed orders.
+        let (sql, _) = builder
+            .clone()
+            .dialect(Dialect::Postgres)
+            .seek(&PageCursor::new(true, vec![json!(100), json!(1)]))
+            .unwrap()
+            .build_select(11, 0);
+        assert_eq!(
+            sql,
+            "SELECT * FROM folders WHERE name = $1 AND (update_time, id) > ($2, $3) ORDER BY update_time ASC, id ASC LIMIT 11 OFFSET 0"
+        );
+
+        // The keys must match the orders.
+        assert!(builder.seek(&PageCursor::new(false, vec![json!(100)])).is_err());
+    }
+
+    #[test]
+    fn test_build_select_with_sort_and_fields() {
+        const TIMED_COLUMNS: &[&str] = &["id", "name", "key", "content", "update_time"];
+        let page = PageRequest {
+            sort: Some("name, -update_time".to_string()),
+            fields: Some("key".to_string()),
+            ..PageRequest::default()
+        };
+        let builder = QueryBuilder::new("folders", TIMED_COLUMNS)
+            .order_by("update_time", true)
+            .unwrap()
+            .sort_by(&page)
+            .unwrap()
+            .select_fields(&page)
+            .unwrap()
+            .order_by_keyset()
+            .unwrap();
+        let (sql, _) = builder.build_select(10, 0);
+        assert_eq!(
+            sql,
+            "SELECT id, name, key, update_time FROM folders WHERE 1=1 ORDER BY name ASC, update_time DESC, id ASC LIMIT 10 OFFSET 0"
+        );
+
+        // The mixed directions of orders are expanded.
+        let cursor = PageCursor::new(false, vec![json!("n1"), json!(100), json!(1)]);
+        let (sql, params) = builder.seek(&cursor).unwrap().build_select(10, 0);
+        assert_eq!(
+            sql,
+            "SELECT id, name, key, update_time FROM folders WHERE 1=1 AND ((name > ?) OR (name = ? AND update_time < ?) OR (name = ? AND update_time = ? AND id > ?)) ORDER BY name ASC, update_time DESC, id ASC LIMIT 10 OFFSET 0"
+        );
+        assert_eq!(params.len(), 6);
+
+        // The unknown columns are rejected as the bad request.
+        let page = PageRequest { sort: Some("-password".to_string()), ..PageRequest::default() };
+        assert!(QueryBuilder::new("folders", TIMED_COLUMNS).sort_by(&page).is_err());
+        let page = PageRequest { fields: Some("key,password".to_string()), ..PageRequest::default() };
+        assert!(QueryBuilder::new("folders", TIMED_COLUMNS).select_fields(&page).is_err());
+    }
+
+    #[test]
+    fn test_build_postgres_placeholders() {
+        let builder = QueryBuilder::new("folders", COLUMNS)
+            .dialect(Dialect::Postgres)
+            .and("pid", Operator::Eq, 0i64)
+            .unwrap()
+            .and_in("id", vec![1i64.into(), 2i64.into()])
+            .unwrap()
+            .and_null("name", true)
+            .unwrap()
+            .and("status", Operator::Lt, 1)
+            .unwrap();
+        let (sql, params) = builder.build_select(10, 0);
+        assert_eq!(
+            sql,
+            "SELECT * FROM folders WHERE pid = $1 AND id IN ($2, $3) AND name IS NULL AND status < $4 LIMIT 10 OFFSET 0"
+        );
+        assert_eq!(params.len(), 4);
+
+        let bean = json!({ "id": 1, "name": "n1", "score": 0.5 });
+        let (sql, _) = builder.build_update(&bean).unwrap();
+        assert_eq!(
+            sql,
+            "UPDATE folders SET name = $1, score = $2 WHERE pid = $3 AND id IN ($4, $5) AND name IS NULL AND status < $6"
+        );
+        let (sql, _) = builder.build_insert(&bean).unwrap();
+        assert_eq!(sql, "INSERT INTO folders (id, name, score) VALUES ($1, $2, $3)");
+    }
+
+    #[test]
+    fn test_build_update_increase() {
+        const COUNTED_COLUMNS: &[&str] = &["id", "version", "access_count", "last_access_time"];
+        let builder = QueryBuilder::new("share_links", COUNTED_COLUMNS)
+            .dialect(Dialect::Postgres)
+            .and("id", Operator::Eq, 1i64)
+            .unwrap();
+        let increases = vec![("access_count".to_string(), 1)];
+        let values = vec![("last_access_time".to_string(), GenericValue::Int64(100))];
+        let (sql, params) = builder.build_update_increase(increases, values).unwrap();
+        assert_eq!(
+            sql,
+            "UPDATE share_links SET access_count = COALESCE(access_count, 0) + $1, last_access_time = $2, version = version + 1 WHERE id = $3"
+        );
+        assert_eq!(params, vec![GenericValue::Int64(1), GenericValue::Int64(100), GenericValue::Int64(1)]);
+        assert!(builder.build_update_increase(vec![("unknown".to_string(), 1)], Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_build_select_in_sub_select() {
+        const TAG_COLUMNS: &[&str] = &["id", "owner_uid", "document_id", "tag_id"];
+        let tagged = QueryBuilder::new("document_tags", TAG_COLUMNS)
+            .and("owner_uid", Operator::Eq, 1i64)
+            .unwrap()
+            .and_in("tag_id", vec![7i64.into(), 8i64.into()])
+            .unwrap();
+        let builder = QueryBuilder::new("folders", COLUMNS)
+            .dialect(Dialect::Postgres)
+            .and("pid", Operator::Eq, 0i64)
+            .unwrap()
+            .and_in_select("id", tagged.clone(), "document_id", Some(("tag_id", 2)))
+            .unwrap()
+            .and("status", Operator::Eq, 1)
+            .unwrap();
+        let (sql, params) = builder.build_select(10, 0);
+        assert_eq!(
+            sql,
+            "SELECT * FROM folders WHERE pid = $1 AND id IN (SELECT document_id FROM document_tags WHERE owner_uid = $2 AND tag_id IN ($3, $4) GROUP BY document_id HAVING COUNT(DISTINCT tag_id) = $5) AND status = $6 LIMIT 10 OFFSET 0"
+        );
+        assert_eq!(params.len(), 6);
+        assert_eq!(params[4], GenericValue::Int64(2));
+
+        let (sql, _) = QueryBuilder::new("folders", COLUMNS)
+            .and_in_select("id", tagged.clone(), "document_id", None)
+            .unwrap()
+            .build_count();
+        assert_eq!(
+            sql,
+            "SELECT COUNT(1) FROM folders WHERE id IN (SELECT document_id FROM document_tags WHERE owner_uid = ? AND tag_id IN (?, ?))"
+        );
+        assert!(QueryBuilder::new("folders", COLUMNS).and_in_select("id", tagged, "unknown", None).is_err());
+    }
+
+    #[test]
+    fn test_build_select_in_array() {
+        let (sql, params) = QueryBuilder::new("folders", COLUMNS)
+            .and_in_array("id", vec![1i64.into(), 2i64.into()])
+            .unwrap()
+            .and("status", Operator::Eq, 1)
+            .unwrap()
+            .build_count();
+        assert_eq!(sql, "SELECT COUNT(1) FROM folders WHERE id IN (SELECT value FROM json_each(?)) AND status = ?");
+        assert_eq!(params[0], GenericValue::String("[1,2]".to_string()));
+
+        let (sql, params) = QueryBuilder::new("folders", COLUMNS)
+            .dialect(Dialect::Postgres)
+            .and_in_array("name", vec!["n1".to_string().into()])
+            .unwrap()
+            .and_in_array("id", vec![3i64.into()])
+            .unwrap()
+            .and_in_array("pid", Vec::new())
+            .unwrap()
+            .build_count();
+        assert_eq!(
+            sql,
+            "SELECT COUNT(1) FROM folders WHERE name IN (SELECT value FROM jsonb_array_elements_text($1::jsonb)) AND id IN (SELECT value::bigint FROM jsonb_array_elements_text($2::jsonb)) AND 1=0"
+        );
+        assert_eq!(params, vec![GenericValue::String("[\"n1\"]".to_string()), GenericValue::String("[3]".to_string())]);
+    }
+}
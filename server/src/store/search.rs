@@ -0,0 +1,206 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use serde_json::Value;
+
+use crate::types::document::DocumentType;
+use crate::utils::notes;
+
+// The highlight markers of the matched terms, which are same for the all DbTypes.
+pub const HIGHLIGHT_START: &str = "<mark>";
+pub const HIGHLIGHT_END: &str = "</mark>";
+pub const HIGHLIGHT_ELLIPSIS: &str = "...";
+// The max count of terms of a search query.
+const MAX_TERMS: usize = 16;
+// The max chars of a highlighted snippet.
+const SNIPPET_CHARS: usize = 96;
+
+// Split the search query to the terms, the quotes are stripped because of each term is
+// matched as a phrase, and all the terms must be matched.
+pub fn to_terms(query: &str) -> Vec<String> {
+    query
+        .split_whitespace()
+        .map(|t| t.replace('"', ""))
+        .filter(|t| !t.is_empty())
+        .take(MAX_TERMS)
+        .collect()
+}
+
+// The SQLite FTS5 match expression, see: https://www.sqlite.org/fts5.html#full_text_query_syntax
+pub fn to_fts5_query(terms: &[String]) -> String {
+    terms
+        .iter()
+        .map(|t| format!("\"{}\"", t))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// The MongoDB $text search expression, the quoted phrases are all required.
+pub fn to_mongo_text_query(terms: &[String]) -> String {
+    to_fts5_query(terms)
+}
+
+// Extract the searchable text of the document content, the board is the JSON snapshot (such as
+// Excalidraw elements or tldraw shapes) and the note is the BlockSuite snapshot, so only the texts
+// of them are searchable rather than the ids and keys.
+pub fn extract_text(doc_type: &Option<DocumentType>, content: &Option<String>) -> String {
+    let content = content.to_owned().unwrap_or_default();
+    match doc_type {
+        Some(DocumentType::Board) =>
+            match serde_json::from_str::<Value>(&content) {
+                Ok(value) => {
+                    let mut texts = Vec::new();
+                    collect_texts(&value, &mut texts);
+                    texts.join(" ")
+                }
+                Err(_) => content,
+            }
+        // The plain text of the legacy notes is searchable as it is.
+        Some(DocumentType::Note) =>
+            match notes::to_root_block(&content) {
+                Ok(root) => notes::texts_of(&root).join(" "),
+                Err(_) => content,
+            }
+        _ => content,
+    }
+}
+
+fn collect_texts(value: &Value, texts: &mut Vec<String>) {
+    match value {
+        Value::Array(items) => items.iter().for_each(|v| collect_texts(v, texts)),
+        Value::Object(map) => {
+            for (key, v) in map {
+                match v {
+                    Value::String(s) if key == "text" && !s.is_empty() => texts.push(s.to_owned()),
+                    _ => collect_texts(v, texts),
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+// Highlight the first matched term of the text with the surrounding snippet, which is
+// equivalent to the FTS5 'snippet()' for the backends without highlighting.
+pub fn highlight(text: &str, terms: &[String]) -> Option<String> {
+    let lower = text.to_lowercase();
+    // The lowercase may change the byte length of some chars, then fallback to not highlight.
+    if lower.len() != text.len() {
+        return None;
+    }
+    let lower_terms: Vec<String> = terms.iter().map(|t| t.to_lowercase()).collect();
+    let first = lower_terms
+        .iter()
+        .filter_map(|t| lower.find(t.as_str()))
+        .min()?;
+
+    // The snippet window around the first matched, which aligned to the char boundaries.
+    let mut start = first.saturating_sub(SNIPPET_CHARS / 3);
+    while !text.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (start + SNIPPET_CHARS).min(text.len());
+    while !text.is_char_boundary(end) {
+        end += 1;
+    }
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push_str(HIGHLIGHT_ELLIPSIS);
+    }
+    let mut i = start;
+    while i < end {
+        let matched = lower_terms
+            .iter()
+            .filter(|t| !t.is_empty() && lower[i..].starts_with(t.as_str()))
+            .map(|t| t.len())
+            .max();
+        match matched {
+            Some(len) => {
+                let to = (i + len).min(text.len());
+                snippet.push_str(HIGHLIGHT_START);
+                snippet.push_str(&text[i..to]);
+                snippet.push_str(HIGHLIGHT_END);
+                i = to;
+            }
+            None => {
+                let ch = text[i..].chars().next().unwrap();
+                snippet.push(ch);
+                i += ch.len_utf8();
+            }
+        }
+    }
+    if i < text.len() {
+        snippet.push_str(HIGHLIGHT_ELLIPSIS);
+    }
+    Some(snippet)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_to_terms_and_queries() {
+        let terms = to_terms(r#"  hello "wor"ld   "#);
+        assert_eq!(terms, vec!["hello", "world"]);
+        assert_eq!(to_fts5_query(&terms), r#""hello" "world""#);
+    }
+
+    #[test]
+    fn test_extract_board_text() {
+        let content = Some(
+            r#"{"elements":[{"type":"text","text":"Hello"},{"type":"rect"},{"props":{"text":"World"}}]}"#.to_string()
+        );
+        assert_eq!(extract_text(&Some(DocumentType::Board), &content), "Hello World");
+        assert_eq!(extract_text(&Some(DocumentType::Note), &content), content.unwrap());
+    }
+
+    #[test]
+    fn test_extract_note_text() {
+        let text = |insert: &str| json!({ "$blocksuite:internal:text$": true, "delta": [{ "insert": insert }] });
+        let paragraph = json!({
+            "type": "block", "id": "b2", "flavour": "affine:paragraph",
+            "props": { "type": "text", "text": text("Hello") }, "children": []
+        });
+        let page = json!({
+            "type": "block", "id": "b1", "flavour": "affine:page",
+            "props": { "title": text("Title") }, "children": [paragraph]
+        });
+        let content = Some(json!({ "type": "page", "meta": { "id": "p1" }, "blocks": page }).to_string());
+        assert_eq!(extract_text(&Some(DocumentType::Note), &content), "Title Hello");
+        assert_eq!(extract_text(&Some(DocumentType::Note), &Some("plain".to_string())), "plain");
+    }
+
+    #[test]
+    fn test_highlight() {
+        let terms = vec!["world".to_string()];
+        assert_eq!(highlight("Hello World", &terms).unwrap(), "Hello <mark>World</mark>");
+        assert_eq!(highlight("Hello", &terms), None);
+        let text = format!("{} world {}", "a".repeat(100), "b".repeat(100));
+        let snippet = highlight(&text, &terms).unwrap();
+        assert!(snippet.starts_with("...") && snippet.ends_with("..."), "{}", snippet);
+        assert!(snippet.contains("<mark>world</mark>"), "{}", snippet);
+    }
+}
@@ -29,10 +29,12 @@ use mongodb::Collection;
 use mongodb::bson::doc;
 
 use crate::config::config_serve::DbProperties;
+use crate::errors::BizError;
 use crate::types::settings::Settings;
 use crate::types::{ PageRequest, PageResponse };
-use super::AsyncRepository;
-use super::mongo::MongoRepository;
+use super::{ AsyncRepository, current_owner_uid };
+use super::mongo::{ MongoRepository, delete_by_id, find_after, insert_with };
+use super::settings_sqlite::SETTINGS_COLUMNS;
 use crate::{ dynamic_mongo_query, dynamic_mongo_insert, dynamic_mongo_update };
 
 pub struct SettingsMongoRepository {
@@ -56,7 +58,17 @@ impl AsyncRepository<Settings> for SettingsMongoRepository {
         settings: Settings,
         page: PageRequest
     ) -> Result<(PageResponse, Vec<Settings>), Error> {
-        match dynamic_mongo_query!(settings, self.collection, "update_time", page, Settings) {
+        match dynamic_mongo_query!(
+            settings,
+            self.collection,
+            Some(current_owner_uid().await?),
+            None,
+            None,
+            "update_time",
+            SETTINGS_COLUMNS,
+            page,
+            Settings
+        ) {
             Ok(result) => {
                 tracing::info!("query settings: {:?}", result);
                 Ok((result.0, result.1))
@@ -66,29 +78,55 @@ impl AsyncRepository<Settings> for SettingsMongoRepository {
     }
 
     async fn select_by_id(&self, id: i64) -> Result<Settings, Error> {
-        let filter = doc! { "id": id };
+        let filter = doc! { "id": id, "owner_uid": current_owner_uid().await? };
         let settings = self.collection
             .find_one(filter).await?
-            .ok_or_else(|| Error::msg("Settings not found"))?;
+            .ok_or_else(|| BizError::NotFound("settings".to_string()))?;
         Ok(settings)
     }
 
     async fn insert(&self, mut settings: Settings) -> Result<i64, Error> {
+        settings.owner_uid = Some(current_owner_uid().await?);
         dynamic_mongo_insert!(settings, self.collection)
     }
 
     async fn update(&self, mut settings: Settings) -> Result<i64, Error> {
-        dynamic_mongo_update!(settings, self.collection)
+        dynamic_mongo_update!(settings, self.collection, Some(current_owner_uid().await?), None)
     }
 
     async fn delete_all(&self) -> Result<u64, Error> {
-        let result = self.collection.delete_many(doc! {}).await?;
+        let result = self.collection.delete_many(
+            doc! { "owner_uid": current_owner_uid().await? }
+        ).await?;
         Ok(result.deleted_count)
     }
 
     async fn delete_by_id(&self, id: i64) -> Result<u64, Error> {
-        let filter = doc! { "id": id };
+        let filter = doc! { "id": id, "owner_uid": current_owner_uid().await? };
         let result = self.collection.delete_one(filter).await?;
         Ok(result.deleted_count)
     }
+
+    async fn select_raw(&self, after_id: i64, limit: u32) -> Result<Vec<Settings>, Error> {
+        let settings = find_after(&self.collection, after_id, limit).await?;
+        Ok(
+            settings
+                .into_iter()
+                .map(|(mut settings, del_flag)| {
+                    settings.base.del_flag = Some(del_flag);
+                    settings
+                })
+                .collect()
+        )
+    }
+
+    async fn insert_raw(&self, settings: Settings) -> Result<i64, Error> {
+        let del_flag = settings.base.del_flag.unwrap_or(0);
+        insert_with(&self.collection, &settings, doc! { "del_flag": del_flag }).await?;
+        Ok(settings.base.id.unwrap_or(-1))
+    }
+
+    async fn delete_raw(&self, id: i64) -> Result<u64, Error> {
+        delete_by_id(&self.collection, id).await
+    }
 }
@@ -24,12 +24,21 @@ use anyhow::{ Error, Ok };
 use axum::async_trait;
 
 use crate::config::config_serve::DbProperties;
+use crate::errors::BizError;
 use crate::types::settings::Settings;
 use crate::types::PageRequest;
 use crate::types::PageResponse;
-use super::AsyncRepository;
+use crate::utils::types::GenericValue;
+use super::{ AsyncRepository, current_owner_uid };
+use super::query::{ Operator, QueryBuilder };
 use super::sqlite::SQLiteRepository;
 
+// The whitelist columns of the table 'settings'.
+pub const SETTINGS_COLUMNS: &[&str] = &[
+    "id", "status", "create_by", "create_time", "update_by", "update_time", "del_flag", "version",
+    "owner_uid", "name",
+];
+
 pub struct SettingsSQLiteRepository {
     inner: SQLiteRepository<Settings>,
 }
@@ -40,6 +49,11 @@ impl SettingsSQLiteRepository {
             inner: SQLiteRepository::new(config).await?,
         })
     }
+
+    // The query builder scoped to the owner (current user).
+    async fn owned_builder(&self) -> Result<QueryBuilder, Error> {
+        QueryBuilder::new("settings", SETTINGS_COLUMNS).and("owner_uid", Operator::Eq, current_owner_uid().await?)
+    }
 }
 
 #[async_trait]
@@ -49,68 +63,67 @@ impl AsyncRepository<Settings> for SettingsSQLiteRepository {
         settings: Settings,
         page: PageRequest
     ) -> Result<(PageResponse, Vec<Settings>), Error> {
-        let result = dynamic_sqlite_query!(
-            settings,
-            "settings",
-            self.inner.get_pool(),
-            "update_time",
-            page,
-            Settings
-        ).unwrap();
+        let builder = self.owned_builder().await?.and_bean(&settings)?.order_by("update_time", true)?;
+        let result = self.inner.select_page(&builder, &page).await?;
 
         tracing::info!("query settings: {:?}", result);
         Ok((result.0, result.1))
     }
 
     async fn select_by_id(&self, id: i64) -> Result<Settings, Error> {
-        let settings = sqlx
-            ::query_as::<_, Settings>("SELECT * FROM settings WHERE id = $1")
-            .bind(id)
-            .fetch_one(self.inner.get_pool()).await
-            .unwrap();
+        let builder = self.owned_builder().await?.and("id", Operator::Eq, id)?;
+        let settings = self.inner
+            .select_one(&builder).await?
+            .ok_or_else(|| BizError::NotFound("settings".to_string()))?;
 
         tracing::info!("query settings: {:?}", settings);
         Ok(settings)
     }
 
     async fn insert(&self, mut settings: Settings) -> Result<i64, Error> {
-        let inserted_id = dynamic_sqlite_insert!(
-            settings,
-            "settings",
-            self.inner.get_pool()
-        ).unwrap();
+        settings.owner_uid = Some(current_owner_uid().await?);
+        settings.base.pre_insert(None).await;
+        let builder = QueryBuilder::new("settings", SETTINGS_COLUMNS);
+        let inserted_id = self.inner.insert_bean(&builder, &settings).await?;
         tracing::info!("Inserted settings.id: {:?}", inserted_id);
         Ok(inserted_id)
     }
 
     async fn update(&self, mut settings: Settings) -> Result<i64, Error> {
-        let updated_id = dynamic_sqlite_update!(
-            settings,
-            "settings",
-            self.inner.get_pool()
-        ).unwrap();
+        settings.base.pre_update(None).await;
+        let id = settings.base.id.ok_or_else(|| Error::msg("The settings id is required for update"))?;
+        let builder = self.owned_builder().await?.and("id", Operator::Eq, id)?;
+        let updated_id = if self.inner.update_bean(&builder, &settings).await? > 0 { id } else { -1 };
         tracing::info!("Updated settings.id: {:?}", updated_id);
         Ok(updated_id)
     }
 
     async fn delete_all(&self) -> Result<u64, Error> {
-        let delete_result = sqlx
-            ::query("DELETE FROM settings")
-            .execute(self.inner.get_pool()).await
-            .unwrap();
-
-        tracing::info!("Deleted result: {:?}", delete_result);
-        Ok(delete_result.rows_affected())
+        let deleted = self.inner.delete(&self.owned_builder().await?).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
     }
 
     async fn delete_by_id(&self, id: i64) -> Result<u64, Error> {
-        let delete_result = sqlx
-            ::query("DELETE FROM settings WHERE id = $1")
-            .bind(id)
-            .execute(self.inner.get_pool()).await
-            .unwrap();
-
-        tracing::info!("Deleted result: {:?}", delete_result);
-        Ok(delete_result.rows_affected())
+        let builder = self.owned_builder().await?.and("id", Operator::Eq, id)?;
+        let deleted = self.inner.delete(&builder).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    async fn select_raw(&self, after_id: i64, limit: u32) -> Result<Vec<Settings>, Error> {
+        let builder = QueryBuilder::new("settings", SETTINGS_COLUMNS);
+        self.inner.select_after(&builder, after_id, limit).await
+    }
+
+    async fn insert_raw(&self, settings: Settings) -> Result<i64, Error> {
+        let builder = QueryBuilder::new("settings", SETTINGS_COLUMNS);
+        let del_flag = GenericValue::Int32(settings.base.del_flag.unwrap_or(0));
+        self.inner.insert_bean_with(&builder, &settings, vec![("del_flag".to_string(), del_flag)]).await
+    }
+
+    async fn delete_raw(&self, id: i64) -> Result<u64, Error> {
+        let builder = QueryBuilder::new("settings", SETTINGS_COLUMNS).and("id", Operator::Eq, id)?;
+        self.inner.delete(&builder).await
     }
 }
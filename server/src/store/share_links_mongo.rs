@@ -0,0 +1,153 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use std::sync::Arc;
+
+use anyhow::Error;
+use axum::async_trait;
+
+use mongodb::Collection;
+use mongodb::bson::{ self, doc };
+
+use crate::config::config_serve::DbProperties;
+use crate::errors::BizError;
+use crate::types::share::ShareLink;
+use crate::types::{ PageRequest, PageResponse };
+use crate::utils::types::GenericValue;
+use super::{ AsyncRepository, current_owner_uid, with_audit_values };
+use super::mongo::{ MongoRepository, delete_by_id, find_after, insert_with, to_bson_value };
+use super::share_links_sqlite::SHARE_LINK_COLUMNS;
+use crate::{ dynamic_mongo_query, dynamic_mongo_insert, dynamic_mongo_update };
+
+pub struct ShareLinkMongoRepository {
+    #[allow(unused)]
+    inner: Arc<MongoRepository<ShareLink>>,
+    collection: Collection<ShareLink>,
+}
+
+impl ShareLinkMongoRepository {
+    pub async fn new(config: &DbProperties) -> Result<Self, Error> {
+        let inner = Arc::new(MongoRepository::new(config).await?);
+        let collection = inner.get_database().collection("share_links");
+        Ok(ShareLinkMongoRepository { inner, collection })
+    }
+}
+
+#[async_trait]
+impl AsyncRepository<ShareLink> for ShareLinkMongoRepository {
+    async fn select(&self, link: ShareLink, page: PageRequest) -> Result<(PageResponse, Vec<ShareLink>), Error> {
+        dynamic_mongo_query!(
+            link,
+            self.collection,
+            Some(current_owner_uid().await?),
+            None,
+            None,
+            "create_time",
+            SHARE_LINK_COLUMNS,
+            page,
+            ShareLink
+        )
+    }
+
+    async fn select_by_id(&self, id: i64) -> Result<ShareLink, Error> {
+        let filter = doc! { "id": id, "owner_uid": current_owner_uid().await? };
+        let link = self.collection
+            .find_one(filter).await?
+            .ok_or_else(|| BizError::NotFound("share link".to_string()))?;
+        Ok(link)
+    }
+
+    async fn insert(&self, mut link: ShareLink) -> Result<i64, Error> {
+        link.owner_uid = Some(current_owner_uid().await?);
+        dynamic_mongo_insert!(link, self.collection)
+    }
+
+    async fn update(&self, mut link: ShareLink) -> Result<i64, Error> {
+        dynamic_mongo_update!(link, self.collection, Some(current_owner_uid().await?), None)
+    }
+
+    async fn increase_field(&self, id: i64, field: &str, fields: Vec<(String, GenericValue)>) -> Result<u64, Error> {
+        let filter = doc! { "id": id, "owner_uid": current_owner_uid().await? };
+        let mut set = bson::Document::new();
+        for (key, value) in with_audit_values(fields).await {
+            set.insert(key, to_bson_value(value));
+        }
+        let update = doc! { "$set": set, "$inc": { field: 1_i64, "version": 1_i64 } };
+        let result = self.collection.update_one(filter, update).await?;
+        Ok(result.matched_count)
+    }
+
+    async fn delete_all(&self) -> Result<u64, Error> {
+        let result = self.collection.delete_many(
+            doc! { "owner_uid": current_owner_uid().await? }
+        ).await?;
+        Ok(result.deleted_count)
+    }
+
+    async fn delete_by_id(&self, id: i64) -> Result<u64, Error> {
+        let filter = doc! { "id": id, "owner_uid": current_owner_uid().await? };
+        let result = self.collection.delete_one(filter).await?;
+        Ok(result.deleted_count)
+    }
+
+    // Notice: It's the system wide for resolving the public links by the token.
+    async fn select_unscoped(
+        &self,
+        link: ShareLink,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<ShareLink>), Error> {
+        dynamic_mongo_query!(
+            link,
+            self.collection,
+            None,
+            None,
+            None,
+            "id",
+            SHARE_LINK_COLUMNS,
+            page,
+            ShareLink
+        )
+    }
+
+    async fn select_raw(&self, after_id: i64, limit: u32) -> Result<Vec<ShareLink>, Error> {
+        let links = find_after(&self.collection, after_id, limit).await?;
+        Ok(
+            links
+                .into_iter()
+                .map(|(mut link, del_flag)| {
+                    link.base.del_flag = Some(del_flag);
+                    link
+                })
+                .collect()
+        )
+    }
+
+    async fn insert_raw(&self, link: ShareLink) -> Result<i64, Error> {
+        let del_flag = link.base.del_flag.unwrap_or(0);
+        insert_with(&self.collection, &link, doc! { "del_flag": del_flag }).await?;
+        Ok(link.base.id.unwrap_or(-1))
+    }
+
+    async fn delete_raw(&self, id: i64) -> Result<u64, Error> {
+        delete_by_id(&self.collection, id).await
+    }
+}
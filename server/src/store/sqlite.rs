@@ -21,6 +21,7 @@
  */
 
 use std::any::Any;
+use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::fs;
 use std::path::Path;
@@ -28,14 +29,40 @@ use std::path::Path;
 use anyhow::Error;
 use axum::async_trait;
 
+use serde::Serialize;
 use tracing::{ info, debug };
-use sqlx::{ migrate::MigrateDatabase, Pool, Sqlite, SqlitePool };
-
-use crate::{ config::config_serve::DbProperties, types::{ PageResponse, PageRequest } };
-use super::AsyncRepository;
-
-//
-// const MIGRATION_INIT_SQL: &str = include_str!("../../migrations/20240710083754_init.sql");
+use sqlx::{
+    migrate::{ Migrate, MigrateDatabase, MigrateError, Migrator },
+    query::Query,
+    sqlite::{ SqliteArguments, SqliteQueryResult, SqliteRow },
+    Arguments,
+    FromRow,
+    Pool,
+    Sqlite,
+    SqlitePool,
+};
+
+use crate::{
+    config::config_serve::DbProperties,
+    types::{ BaseBean, PageResponse, PageRequest },
+    utils::types::GenericValue,
+};
+use super::{ AsyncRepository, RawTransaction, to_cursor_page };
+use super::query::{ Operator, QueryBuilder };
+
+// The embedded versioned migrations, Each version consists of a pair of 'xx.up.sql' and 'xx.down.sql',
+// Notice: Never modify an up script that has been released, because the applied checksum will mismatch,
+// please always add a new version instead.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied: bool,
+    // The applied checksum matches the embedded up script, always true when not applied.
+    pub checksum_matched: bool,
+}
 
 pub struct SQLiteRepository<T: Any + Send + Sync> {
     phantom: PhantomData<T>,
@@ -45,73 +72,50 @@ pub struct SQLiteRepository<T: Any + Send + Sync> {
 impl<T: Any + Send + Sync> SQLiteRepository<T> {
     // see:https://tms-dev-blog.com/rust-sqlx-basics-with-sqlite/#Adding_a_migration_script
     pub async fn new(config: &DbProperties) -> Result<Self, Error> {
-        let dir = config.sqlite.dir.to_owned().expect("SQLite dir missing configured").to_string();
-        let db_dir = Path::new(&dir);
-        if !db_dir.exists() {
-            fs::create_dir_all(db_dir).map_err(|e| {
-                tracing::info!("Failed to sqlite db create directory: {:?}", e);
-                e
-            })?;
-        }
+        let pool = connect(config).await?;
+        let pool = Self::init_migration(pool).await?;
+        Ok(SQLiteRepository {
+            phantom: PhantomData,
+            pool,
+        })
+    }
 
-        let db_url: String = format!("sqlite://{}/sqlite.db", &dir).to_string();
-        if !Sqlite::database_exists(db_url.as_str()).await.unwrap_or(false) {
-            info!("Creating database {}", db_url);
-            match Sqlite::create_database(db_url.as_str()).await {
-                Ok(_) => tracing::info!("Create db success"),
-                Err(error) => panic!("Error to create db: {}", error),
-            }
-        } else {
-            tracing::info!("Database already exists and skip init migration.");
+    async fn init_migration(pool: Pool<Sqlite>) -> Result<Pool<Sqlite>, Error> {
+        for status in migration_status(&pool).await? {
+            debug!("Migration status: {:?}", status);
         }
-        // SQLite in-memory database.
-        // let db_url = format!("sqlite::memory:");
-
-        match SqlitePool::connect(&db_url).await {
-            Ok(pool) => {
-                tracing::info!("Successfully connected to the database");
-                let pool = Self::init_migration(pool).await;
-
-                Ok(SQLiteRepository {
-                    phantom: PhantomData,
-                    pool,
-                })
-            }
-            Err(e) => {
-                tracing::info!("Database sqlite connection error: {:?}", e);
-                tracing::info!("Error details: {}", e);
-                Err(e.into())
-            }
-        }
-    }
 
-    async fn init_migration(pool: Pool<Sqlite>) -> Pool<Sqlite> {
-        // let default_dir = std::env
-        //   ::current_dir()
-        //   .map(|s| s.to_str().unwrap())
-        //   .unwrap();
-        // let migrations_dir = std::env
-        //   ::var("CARGO_MANIFEST_DIR")
-        //   .unwrap_or_else(|_| default_dir.to_string());
-        // let migrations_dir = std::path::Path::new(&current_dir).join("./migrations");
-        // let results = sqlx::migrate::Migrator::new(migrations).await.unwrap().run(&pool).await;
-        // debug!("Migration result: {:?}", results);
-        // match results {
-        //   Ok(_) => tracing::info!("Migration success"),
-        //   Err(error) => {
-        //     panic!("error: {}", error);
-        //   }
-        // }
-
-        let results = sqlx::migrate!("./migrations").run(&pool).await;
+        let results = MIGRATOR.run(&pool).await;
         debug!("Migration result: {:?}", results);
         match results {
-            Ok(_) => tracing::info!("Migration success"),
-            Err(error) => {
-                panic!("Error migration: {}", error);
+            Ok(_) => {
+                tracing::info!("Migration success");
+                Ok(pool)
             }
+            Err(MigrateError::VersionMismatch(version)) => {
+                Err(
+                    Error::msg(
+                        format!(
+                            "Error migration: the applied version {} was modified after release, \
+                            please restore its original up script or rollback to the previous version by 'db migrate-rollback'",
+                            version
+                        )
+                    )
+                )
+            }
+            Err(MigrateError::Dirty(version)) => {
+                Err(
+                    Error::msg(
+                        format!(
+                            "Error migration: the version {} was partially applied, please fix the database \
+                            manually and delete it from the '_sqlx_migrations' table",
+                            version
+                        )
+                    )
+                )
+            }
+            Err(error) => Err(Error::msg(format!("Error migration: {}", error))),
         }
-        pool
     }
 
     pub fn get_pool(&self) -> &SqlitePool {
@@ -119,6 +123,79 @@ impl<T: Any + Send + Sync> SQLiteRepository<T> {
     }
 }
 
+// Connect to the database only (create if not exists) without running migrations.
+pub async fn connect(config: &DbProperties) -> Result<SqlitePool, Error> {
+    let dir = config.sqlite.dir.to_owned().expect("SQLite dir missing configured").to_string();
+    let db_dir = Path::new(&dir);
+    if !db_dir.exists() {
+        fs::create_dir_all(db_dir).map_err(|e| {
+            tracing::info!("Failed to sqlite db create directory: {:?}", e);
+            e
+        })?;
+    }
+
+    let db_url: String = format!("sqlite://{}/sqlite.db", &dir).to_string();
+    if !Sqlite::database_exists(db_url.as_str()).await.unwrap_or(false) {
+        info!("Creating database {}", db_url);
+        match Sqlite::create_database(db_url.as_str()).await {
+            Ok(_) => tracing::info!("Create db success"),
+            Err(error) => panic!("Error to create db: {}", error),
+        }
+    } else {
+        tracing::info!("Database already exists and will be upgrade by migrations.");
+    }
+    // SQLite in-memory database.
+    // let db_url = format!("sqlite::memory:");
+
+    match SqlitePool::connect(&db_url).await {
+        Ok(pool) => {
+            tracing::info!("Successfully connected to the database");
+            Ok(pool)
+        }
+        Err(e) => {
+            tracing::info!("Database sqlite connection error: {:?}", e);
+            tracing::info!("Error details: {}", e);
+            Err(e.into())
+        }
+    }
+}
+
+// Compare the embedded migrations with the applied migrations of database.
+pub async fn migration_status(pool: &SqlitePool) -> Result<Vec<MigrationStatus>, Error> {
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    let applied: HashMap<i64, Vec<u8>> = conn
+        .list_applied_migrations().await?
+        .into_iter()
+        .map(|m| (m.version, m.checksum.into_owned()))
+        .collect();
+
+    Ok(
+        MIGRATOR.iter()
+            .filter(|m| !m.migration_type.is_down_migration())
+            .map(|m| {
+                let checksum = applied.get(&m.version);
+                MigrationStatus {
+                    version: m.version,
+                    description: m.description.to_string(),
+                    applied: checksum.is_some(),
+                    checksum_matched: checksum
+                        .map(|c| c.as_slice() == m.checksum.as_ref())
+                        .unwrap_or(true),
+                }
+            })
+            .collect()
+    )
+}
+
+// Revert all applied versions newer than the target version in descending order.
+pub async fn migration_rollback(pool: &SqlitePool, target: i64) -> Result<(), Error> {
+    if target != 0 && !MIGRATOR.version_exists(target) {
+        return Err(Error::msg(format!("Unknown the rollback target version {}", target)));
+    }
+    MIGRATOR.undo(pool, target).await.map_err(|e| Error::msg(format!("Error rollback: {}", e)))
+}
+
 #[allow(unused)]
 #[async_trait]
 impl<T: Any + Send + Sync> AsyncRepository<T> for SQLiteRepository<T> {
@@ -152,211 +229,207 @@ impl<T: Any + Send + Sync> AsyncRepository<T> for SQLiteRepository<T> {
     }
 }
 
-macro_rules! dynamic_sqlite_query {
-    ($bean:expr, $table:expr, $pool:expr, $order_by:expr, $page:expr, $($t:ty),+) => {
-          {
-              // Notice:
-              // 1. (SQLite) Because the ORM library is not used for the time being, the fields are dynamically
-              // parsed based on serde_json, so the #[serde(rename="xx")] annotation is effective.
-              // 2. (MongoDB) The underlying BSON serialization is also based on serde, so using #[serde(rename="xx")] is also valid
-              // TODO: It is recommended to use an ORM framework, see: https://github.com/diesel-rs/diesel
-              let serialized = serde_json::to_value(&$bean).unwrap();
-              let obj = serialized.as_object().unwrap();
-
-              let mut fields = Vec::new();
-              let mut params = Vec::new();
-              for (key, value) in obj {
-                  if !value.is_null() {
-                    let v = value.as_str().unwrap_or("");
-                    if !v.is_empty() {
-                        fields.push(format!("{} = ?", key));
-                        params.push(v.to_string());
-                    }
-                  }
-              }
-              if let Some(id) = $bean.base.id {
-                  fields.push("id = ?".to_string());
-                  params.push(id.to_string());
-              }
-              let where_clause = if fields.is_empty() {
-                  "1=1".to_string()
-              } else {
-                  fields.join(" AND ")
-              };
-
-              // Queries to get total count.
-              let total_query = format!("SELECT COUNT(1) FROM {} WHERE {}", $table, where_clause);
-              use sqlx::Row;
-              let total_count = sqlx::query(&total_query)
-                .fetch_one($pool)
-                .await
-                .map(|row| row.get::<i64, _>(0) as i64)
-                .unwrap();
-
-              // Queries to get data.
-              let query = format!("SELECT * FROM {} WHERE {} ORDER BY {} LIMIT {} OFFSET {}", 
-                    $table, where_clause, $order_by, $page.get_limit(), $page.get_offset());
-
-              let mut operator = sqlx::query_as::<_, $($t),+>(&query);
-              for param in params.iter() {
-                  operator = operator.bind(param);
-              }
-
-              match operator.fetch_all($pool).await {
-                  std::result::Result::Ok(result) => {
-                    let page = PageResponse::new(
-                        Some(total_count),
-                        Some($page.get_offset()),
-                        Some($page.get_limit()));
-                      Ok((page, result))
-                  },
-                  Err(error) => {
-                      Err(error.into())
-                  }
-              }
-          }
-    };
-}
+// The typed operations shared by all the SQLite repositories, see: store/query.rs
+impl<T> SQLiteRepository<T>
+    where T: Any + Send + Sync + Unpin + Serialize + for<'r> FromRow<'r, SqliteRow>
+{
+    // Select the page by the offset, or by seeking the keys of the cursor if present, which fetches
+    // one more row for detecting the next page, see: store::to_cursor_page
+    pub async fn select_page(
+        &self,
+        builder: &QueryBuilder,
+        page: &PageRequest
+    ) -> Result<(PageResponse, Vec<T>), Error> {
+        let builder = builder.clone().sort_by(page)?.order_by_keyset()?.select_fields(page)?;
+        let cursor = page.get_cursor()?;
+
+        // Queries to get total count, which is optional for the cursor paging.
+        let total_count = if page.is_with_total() {
+            let (count_sql, params) = builder.build_count();
+            let total_count: i64 = sqlx
+                ::query_scalar_with(&count_sql, to_arguments(params))
+                .fetch_one(self.get_pool()).await?;
+            Some(total_count)
+        } else {
+            None
+        };
+
+        // Queries to get data.
+        let (sql, params) = match &cursor {
+            Some(cursor) => builder.clone().seek(cursor)?.build_select(page.get_limit() + 1, 0),
+            None => builder.build_select(page.get_limit() + 1, page.get_offset()),
+        };
+        let result = sqlx
+            ::query_as_with::<_, T, _>(&sql, to_arguments(params))
+            .fetch_all(self.get_pool()).await?;
+
+        to_cursor_page(page, cursor.as_ref(), &builder.order_columns(), total_count, result)
+    }
 
-macro_rules! dynamic_sqlite_insert {
-    ($bean:expr, $table:expr, $pool:expr) => {
-        {
-            use crate::utils::types::GenericValue;
-
-            // Notice:
-            // 1. (SQLite) Because the ORM library is not used for the time being, the fields are dynamically
-            // parsed based on serde_json, so the #[serde(rename="xx")] annotation is effective.
-            // 2. (MongoDB) The underlying BSON serialization is also based on serde, so using #[serde(rename="xx")] is also valid
-            // TODO: It is recommended to use an ORM framework, see: https://github.com/diesel-rs/diesel
-            $bean.base.pre_insert(None).await;
-            let serialized = serde_json::to_value($bean).unwrap();
-            let obj = serialized.as_object().unwrap();
-
-            let mut fields = Vec::new();
-            let mut values = Vec::new();
-            let mut params = Vec::new();
-            for (key, value) in obj {
-                if !value.is_null() {
-                    if value.is_boolean() {
-                        let v = value.as_bool().unwrap();
-                        fields.push(key.as_str());
-                        values.push("?");
-                        params.push(GenericValue::Bool(v));
-                    } else if value.is_number() {
-                        let v = value.as_i64().unwrap();
-                        fields.push(key.as_str());
-                        values.push("?");
-                        params.push(GenericValue::Int64(v));
-                    } else if value.is_string() {
-                        let v = value.as_str().unwrap_or("");
-                        if !v.is_empty() {
-                            fields.push(key.as_str());
-                            values.push("?");
-                            params.push(GenericValue::String(v.to_string()));
-                        }
-                    }
-                }
-            }
-            if fields.is_empty() {
-                return Ok(-1);
-            }
+    // Select the rows of the ids greater than the 'after_id' in ascending order of the id, which is
+    // for scanning all the rows matched the builder conditions, such as the data migration.
+    pub async fn select_after(&self, builder: &QueryBuilder, after_id: i64, limit: u32) -> Result<Vec<T>, Error> {
+        let builder = builder.clone().and("id", Operator::Gt, after_id)?.order_by("id", false)?;
+        let (sql, params) = builder.build_select(limit, 0);
+        let query = sqlx::query_as_with::<_, T, _>(&sql, to_arguments(params));
+        let result = match RawTransaction::current() {
+            Some(RawTransaction::Sqlite(tx)) => query.fetch_all(&mut **tx.lock().await).await?,
+            _ => query.fetch_all(self.get_pool()).await?,
+        };
+        Ok(result)
+    }
 
-            // let fields_str = fields
-            //  .iter()
-            //  .map(|s| s.as_str())
-            //  .collect::<Vec<&str>>()
-            //  .join(",");
-            let query = format!("INSERT INTO {} ({}) VALUES ({})", $table, fields.join(","), values.join(","));
-
-            let mut operator = sqlx::query(&query);
-            for param in params.iter() {
-                if let GenericValue::Bool(v) = param {
-                    operator = operator.bind(v);
-                } else if let GenericValue::Int64(v) = param {
-                    operator = operator.bind(v);
-                } else if let GenericValue::String(v) = param {
-                    operator = operator.bind(v);
-                }
-            }
+    pub async fn select_one(&self, builder: &QueryBuilder) -> Result<Option<T>, Error> {
+        let (sql, params) = builder.build_select(1, 0);
+        let result = sqlx
+            ::query_as_with::<_, T, _>(&sql, to_arguments(params))
+            .fetch_optional(self.get_pool()).await?;
+        Ok(result)
+    }
 
-            match operator.execute($pool).await {
-                std::result::Result::Ok(result) => {
-                    if result.rows_affected() > 0 {
-                        return Ok(result.last_insert_rowid());
-                    } else {
-                        return Ok(-1);
-                    }
-                },
-                Err(e) => Err(Error::from(e)),
-            }
+    // Returns the inserted rowid, which is the bean id when presents.
+    pub async fn insert_bean(&self, builder: &QueryBuilder, bean: &T) -> Result<i64, Error> {
+        let (sql, params) = builder.build_insert(bean)?;
+        let result = sqlx::query_with(&sql, to_arguments(params)).execute(self.get_pool()).await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    // Insert the bean with the given columns, such as the fields skipped by serde, returns the inserted rowid.
+    pub async fn insert_bean_with(
+        &self,
+        builder: &QueryBuilder,
+        bean: &T,
+        values: Vec<(String, GenericValue)>
+    ) -> Result<i64, Error> {
+        let (sql, params) = builder.build_insert_with(bean, values)?;
+        let result = self.execute(sqlx::query_with(&sql, to_arguments(params))).await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    // Returns the number of rows affected, which matched the builder conditions.
+    pub async fn update_bean(&self, builder: &QueryBuilder, bean: &T) -> Result<u64, Error> {
+        let (sql, params) = builder.build_update(bean)?;
+        let result = sqlx::query_with(&sql, to_arguments(params)).execute(self.get_pool()).await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn update_values(
+        &self,
+        builder: &QueryBuilder,
+        values: Vec<(String, GenericValue)>
+    ) -> Result<u64, Error> {
+        let (sql, params) = builder.build_update_values(values)?;
+        let result = sqlx::query_with(&sql, to_arguments(params)).execute(self.get_pool()).await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn update_increase(
+        &self,
+        builder: &QueryBuilder,
+        increases: Vec<(String, i64)>,
+        values: Vec<(String, GenericValue)>
+    ) -> Result<u64, Error> {
+        let (sql, params) = builder.build_update_increase(increases, values)?;
+        let result = sqlx::query_with(&sql, to_arguments(params)).execute(self.get_pool()).await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn delete(&self, builder: &QueryBuilder) -> Result<u64, Error> {
+        let (sql, params) = builder.build_delete();
+        let result = self.execute(sqlx::query_with(&sql, to_arguments(params))).await?;
+        Ok(result.rows_affected())
+    }
+
+    // The raw operations are executed in the raw transaction if bound, see: RawTransaction::scope()
+    pub async fn execute<'q>(
+        &self,
+        query: Query<'q, Sqlite, SqliteArguments<'q>>
+    ) -> Result<SqliteQueryResult, Error> {
+        match RawTransaction::current() {
+            Some(RawTransaction::Sqlite(tx)) => Ok(query.execute(&mut **tx.lock().await).await?),
+            _ => Ok(query.execute(self.get_pool()).await?),
         }
-    };
+    }
 }
 
-macro_rules! dynamic_sqlite_update {
-    ($bean:expr, $table:expr, $pool:expr) => {
-        {
-            use crate::utils::types::GenericValue;
-
-            $bean.base.pre_update(None).await;
-
-            // Notice:
-            // 1. (SQLite) Because the ORM library is not used for the time being, the fields are dynamically
-            // parsed based on serde_json, so the #[serde(rename="xx")] annotation is effective.
-            // 2. (MongoDB) The underlying BSON serialization is also based on serde, so using #[serde(rename="xx")] is also valid
-            // TODO: It is recommended to use an ORM framework, see: https://github.com/diesel-rs/diesel
-            let id = $bean.base.id.unwrap();
-            let serialized = serde_json::to_value($bean).unwrap();
-            let obj = serialized.as_object().unwrap();
-
-            let mut fields = Vec::new();
-            let mut params = Vec::new();
-            for (key, value) in obj {
-                if !value.is_null() {
-                    if value.is_boolean() {
-                        let v = value.as_bool().unwrap();
-                        fields.push(format!("{} = ?", key));
-                        params.push(GenericValue::Bool(v));
-                    } else if value.is_number() {
-                        let v = value.as_i64().unwrap();
-                        fields.push(format!("{} = ?", key));
-                        params.push(GenericValue::Int64(v));
-                    } else if value.is_string() {
-                        let v = value.as_str().unwrap_or("");
-                        if !v.is_empty() {
-                            fields.push(format!("{} = ?", key));
-                            params.push(GenericValue::String(v.to_string()));
-                        }
-                    }
-                }
-            }
-            if fields.is_empty() {
-                return Ok(0);
-            }
+// The columns values of mark as trashed (1) or restored (0), also stamps the audit fields.
+pub async fn del_flag_values(del_flag: i32) -> Vec<(String, GenericValue)> {
+    let mut base = BaseBean::new_with_id(None);
+    base.pre_update(None).await;
+    vec![
+        ("del_flag".to_string(), GenericValue::Int32(del_flag)),
+        ("update_by".to_string(), GenericValue::String(base.update_by.unwrap_or_default())),
+        ("update_time".to_string(), GenericValue::Int64(base.update_time.unwrap_or_default()))
+    ]
+}
 
-            let query = format!("UPDATE {} SET {} WHERE id = ?", $table, fields.join(", "));
-            let mut operator = sqlx::query(&query);
-            for param in params.iter() {
-                if let GenericValue::Bool(v) = param {
-                    operator = operator.bind(v);
-                } else if let GenericValue::Int64(v) = param {
-                    operator = operator.bind(v);
-                } else if let GenericValue::String(v) = param {
-                    operator = operator.bind(v);
-                }
-            }
-            operator = operator.bind(id);
-
-            match operator.execute($pool).await {
-                std::result::Result::Ok(result) => {
-                    if result.rows_affected() > 0 {
-                        return Ok(id);
-                    } else {
-                        return Ok(-1);
-                    }
-                },
-                Err(e) => Err(Error::from(e)),
-            }
+pub fn to_arguments(params: Vec<GenericValue>) -> SqliteArguments<'static> {
+    let mut args = SqliteArguments::default();
+    for param in params {
+        match param {
+            GenericValue::Null => args.add(Option::<String>::None),
+            GenericValue::Int32(v) => args.add(v),
+            GenericValue::Int64(v) => args.add(v),
+            GenericValue::Float64(v) => args.add(v),
+            GenericValue::Bool(v) => args.add(v),
+            GenericValue::String(v) => args.add(v),
+        }
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::Row;
+
+    fn create_test_config() -> DbProperties {
+        let dir = std::env::temp_dir().join(format!("mywebnote-{}", uuid::Uuid::new_v4()));
+        let mut config = DbProperties::default();
+        config.sqlite.dir = Some(dir.to_string_lossy().to_string());
+        config
+    }
+
+    async fn table_exists(pool: &SqlitePool, table: &str) -> bool {
+        sqlx::query("SELECT COUNT(1) FROM sqlite_master WHERE type = 'table' AND name = ?")
+            .bind(table)
+            .fetch_one(pool).await
+            .map(|row| row.get::<i64, _>(0) > 0)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_migration_upgrade_legacy_database() {
+        let config = create_test_config();
+
+        // Simulates the legacy database which only applied the users versions.
+        let pool = connect(&config).await.unwrap();
+        MIGRATOR.run(&pool).await.unwrap();
+        migration_rollback(&pool, 20240723010654).await.unwrap();
+        assert!(table_exists(&pool, "users").await);
+        assert!(!table_exists(&pool, "documents").await);
+
+        let repo = SQLiteRepository::<()>::new(&config).await.unwrap();
+        for table in ["users", "documents", "folders", "settings"] {
+            assert!(table_exists(repo.get_pool(), table).await, "missing table {}", table);
         }
-    };
+        let status = migration_status(repo.get_pool()).await.unwrap();
+        assert!(status.iter().all(|s| s.applied && s.checksum_matched));
+
+        // Re-open the upgraded database should be no-op.
+        assert!(SQLiteRepository::<()>::new(&config).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_migration_rollback_all() {
+        let config = create_test_config();
+        let repo = SQLiteRepository::<()>::new(&config).await.unwrap();
+
+        migration_rollback(repo.get_pool(), 0).await.unwrap();
+        let status = migration_status(repo.get_pool()).await.unwrap();
+        assert!(status.iter().all(|s| !s.applied));
+        assert!(!table_exists(repo.get_pool(), "users").await);
+
+        assert!(migration_rollback(repo.get_pool(), 1).await.is_err());
+    }
 }
@@ -0,0 +1,122 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use std::sync::Arc;
+
+use anyhow::Error;
+use axum::async_trait;
+
+use mongodb::Collection;
+use mongodb::bson::doc;
+
+use crate::config::config_serve::DbProperties;
+use crate::errors::BizError;
+use crate::types::tag::Tag;
+use crate::types::{ PageRequest, PageResponse };
+use super::{ AsyncRepository, current_owner_uid };
+use super::mongo::{ MongoRepository, delete_by_id, find_after, insert_with };
+use super::tags_sqlite::TAG_COLUMNS;
+use crate::{ dynamic_mongo_query, dynamic_mongo_insert, dynamic_mongo_update };
+
+pub struct TagMongoRepository {
+    #[allow(unused)]
+    inner: Arc<MongoRepository<Tag>>,
+    collection: Collection<Tag>,
+}
+
+impl TagMongoRepository {
+    pub async fn new(config: &DbProperties) -> Result<Self, Error> {
+        let inner = Arc::new(MongoRepository::new(config).await?);
+        let collection = inner.get_database().collection("tags");
+        Ok(TagMongoRepository { inner, collection })
+    }
+}
+
+#[async_trait]
+impl AsyncRepository<Tag> for TagMongoRepository {
+    async fn select(&self, tag: Tag, page: PageRequest) -> Result<(PageResponse, Vec<Tag>), Error> {
+        dynamic_mongo_query!(
+            tag,
+            self.collection,
+            Some(current_owner_uid().await?),
+            None,
+            None,
+            "create_time",
+            TAG_COLUMNS,
+            page,
+            Tag
+        )
+    }
+
+    async fn select_by_id(&self, id: i64) -> Result<Tag, Error> {
+        let filter = doc! { "id": id, "owner_uid": current_owner_uid().await? };
+        let tag = self.collection
+            .find_one(filter).await?
+            .ok_or_else(|| BizError::NotFound("tag".to_string()))?;
+        Ok(tag)
+    }
+
+    async fn insert(&self, mut tag: Tag) -> Result<i64, Error> {
+        tag.owner_uid = Some(current_owner_uid().await?);
+        dynamic_mongo_insert!(tag, self.collection)
+    }
+
+    async fn update(&self, mut tag: Tag) -> Result<i64, Error> {
+        dynamic_mongo_update!(tag, self.collection, Some(current_owner_uid().await?), None)
+    }
+
+    async fn delete_all(&self) -> Result<u64, Error> {
+        let result = self.collection.delete_many(
+            doc! { "owner_uid": current_owner_uid().await? }
+        ).await?;
+        Ok(result.deleted_count)
+    }
+
+    async fn delete_by_id(&self, id: i64) -> Result<u64, Error> {
+        let filter = doc! { "id": id, "owner_uid": current_owner_uid().await? };
+        let result = self.collection.delete_one(filter).await?;
+        Ok(result.deleted_count)
+    }
+
+    async fn select_raw(&self, after_id: i64, limit: u32) -> Result<Vec<Tag>, Error> {
+        let tags = find_after(&self.collection, after_id, limit).await?;
+        Ok(
+            tags
+                .into_iter()
+                .map(|(mut tag, del_flag)| {
+                    tag.base.del_flag = Some(del_flag);
+                    tag
+                })
+                .collect()
+        )
+    }
+
+    async fn insert_raw(&self, tag: Tag) -> Result<i64, Error> {
+        let del_flag = tag.base.del_flag.unwrap_or(0);
+        insert_with(&self.collection, &tag, doc! { "del_flag": del_flag }).await?;
+        Ok(tag.base.id.unwrap_or(-1))
+    }
+
+    async fn delete_raw(&self, id: i64) -> Result<u64, Error> {
+        delete_by_id(&self.collection, id).await
+    }
+}
@@ -31,8 +31,9 @@ use mongodb::bson::doc;
 use crate::config::config_serve::DbProperties;
 use crate::types::user::User;
 use crate::types::{ PageRequest, PageResponse };
-use super::AsyncRepository;
-use super::mongo::MongoRepository;
+use super::{ AsyncRepository, RawTransaction };
+use super::mongo::{ MongoRepository, begin_raw, delete_by_id, find_after, insert_with };
+use super::users_sqlite::USER_COLUMNS;
 use crate::{ dynamic_mongo_query, dynamic_mongo_insert, dynamic_mongo_update };
 
 pub struct UserMongoRepository {
@@ -57,7 +58,17 @@ impl AsyncRepository<User> for UserMongoRepository {
         page: PageRequest
     ) -> Result<(PageResponse, Vec<User>), Error> {
         //let result = &self.inner.select(user, page).await;
-        match dynamic_mongo_query!(user, self.collection, "update_time", page, User) {
+        match dynamic_mongo_query!(
+            user,
+            self.collection,
+            None,
+            None,
+            None,
+            "update_time",
+            USER_COLUMNS,
+            page,
+            User
+        ) {
             Ok(result) => {
                 tracing::info!("query users: {:?}", result);
                 Ok((result.0, result.1))
@@ -79,7 +90,7 @@ impl AsyncRepository<User> for UserMongoRepository {
     }
 
     async fn update(&self, mut user: User) -> Result<i64, Error> {
-        dynamic_mongo_update!(user, self.collection)
+        dynamic_mongo_update!(user, self.collection, None, None)
     }
 
     async fn delete_all(&self) -> Result<u64, Error> {
@@ -92,4 +103,31 @@ impl AsyncRepository<User> for UserMongoRepository {
         let result = self.collection.delete_one(filter).await?;
         Ok(result.deleted_count)
     }
+
+    async fn select_raw(&self, after_id: i64, limit: u32) -> Result<Vec<User>, Error> {
+        let users = find_after(&self.collection, after_id, limit).await?;
+        Ok(
+            users
+                .into_iter()
+                .map(|(mut user, del_flag)| {
+                    user.base.del_flag = Some(del_flag);
+                    user
+                })
+                .collect()
+        )
+    }
+
+    async fn insert_raw(&self, user: User) -> Result<i64, Error> {
+        let del_flag = user.base.del_flag.unwrap_or(0);
+        insert_with(&self.collection, &user, doc! { "del_flag": del_flag }).await?;
+        Ok(user.base.id.unwrap_or(-1))
+    }
+
+    async fn delete_raw(&self, id: i64) -> Result<u64, Error> {
+        delete_by_id(&self.collection, id).await
+    }
+
+    async fn begin_raw(&self) -> Result<RawTransaction, Error> {
+        begin_raw(&self.collection).await
+    }
 }
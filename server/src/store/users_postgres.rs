@@ -0,0 +1,127 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use std::sync::Arc;
+
+use anyhow::{ Error, Ok };
+use axum::async_trait;
+use sqlx::PgPool;
+use tokio::sync::Mutex;
+
+use crate::errors::BizError;
+use crate::types::user::User;
+use crate::types::PageRequest;
+use crate::types::PageResponse;
+use crate::utils::types::GenericValue;
+use super::{ AsyncRepository, RawTransaction };
+use super::query::{ Operator, QueryBuilder };
+use super::postgres::PostgresRepository;
+use super::users_sqlite::USER_COLUMNS;
+
+pub struct UserPostgresRepository {
+    inner: PostgresRepository<User>,
+}
+
+impl UserPostgresRepository {
+    pub fn new(pool: PgPool) -> Self {
+        UserPostgresRepository {
+            inner: PostgresRepository::new(pool),
+        }
+    }
+}
+
+#[async_trait]
+impl AsyncRepository<User> for UserPostgresRepository {
+    async fn select(
+        &self,
+        user: User,
+        page: PageRequest
+    ) -> Result<(PageResponse, Vec<User>), Error> {
+        let builder = QueryBuilder::new("users", USER_COLUMNS)
+            .and_bean(&user)?
+            .order_by("update_time", true)?;
+        let result = self.inner.select_page(&builder, &page).await?;
+
+        tracing::info!("query users: {:?}", result);
+        Ok((result.0, result.1))
+    }
+
+    async fn select_by_id(&self, id: i64) -> Result<User, Error> {
+        let builder = QueryBuilder::new("users", USER_COLUMNS).and("id", Operator::Eq, id)?;
+        let user = self.inner
+            .select_one(&builder).await?
+            .ok_or_else(|| BizError::NotFound("user".to_string()))?;
+
+        tracing::info!("query user: {:?}", user);
+        Ok(user)
+    }
+
+    async fn insert(&self, mut user: User) -> Result<i64, Error> {
+        user.base.pre_insert(None).await;
+        let builder = QueryBuilder::new("users", USER_COLUMNS);
+        let inserted_id = self.inner.insert_bean(&builder, &user).await?;
+        tracing::info!("Inserted user.id: {:?}", inserted_id);
+        Ok(inserted_id)
+    }
+
+    async fn update(&self, mut user: User) -> Result<i64, Error> {
+        user.base.pre_update(None).await;
+        let id = user.base.id.ok_or_else(|| Error::msg("The user id is required for update"))?;
+        let builder = QueryBuilder::new("users", USER_COLUMNS).and("id", Operator::Eq, id)?;
+        let updated_id = if self.inner.update_bean(&builder, &user).await? > 0 { id } else { -1 };
+        tracing::info!("Updated user.id: {:?}", updated_id);
+        Ok(updated_id)
+    }
+
+    async fn delete_all(&self) -> Result<u64, Error> {
+        let deleted = self.inner.delete(&QueryBuilder::new("users", USER_COLUMNS)).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    async fn delete_by_id(&self, id: i64) -> Result<u64, Error> {
+        let builder = QueryBuilder::new("users", USER_COLUMNS).and("id", Operator::Eq, id)?;
+        let deleted = self.inner.delete(&builder).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    async fn select_raw(&self, after_id: i64, limit: u32) -> Result<Vec<User>, Error> {
+        let builder = QueryBuilder::new("users", USER_COLUMNS);
+        self.inner.select_after(&builder, after_id, limit).await
+    }
+
+    async fn insert_raw(&self, user: User) -> Result<i64, Error> {
+        let builder = QueryBuilder::new("users", USER_COLUMNS);
+        let del_flag = GenericValue::Int32(user.base.del_flag.unwrap_or(0));
+        self.inner.insert_bean_with(&builder, &user, vec![("del_flag".to_string(), del_flag)]).await
+    }
+
+    async fn delete_raw(&self, id: i64) -> Result<u64, Error> {
+        let builder = QueryBuilder::new("users", USER_COLUMNS).and("id", Operator::Eq, id)?;
+        self.inner.delete(&builder).await
+    }
+
+    async fn begin_raw(&self) -> Result<RawTransaction, Error> {
+        Ok(RawTransaction::Postgres(Arc::new(Mutex::new(self.inner.get_pool().begin().await?))))
+    }
+}
@@ -20,16 +20,31 @@
  * This includes modifications and derived works.
  */
 
+use std::sync::Arc;
+
 use anyhow::{ Error, Ok };
 use axum::async_trait;
+use tokio::sync::Mutex;
 
 use crate::config::config_serve::DbProperties;
+use crate::errors::BizError;
 use crate::types::user::User;
 use crate::types::PageRequest;
 use crate::types::PageResponse;
-use super::AsyncRepository;
+use crate::utils::types::GenericValue;
+use super::{ AsyncRepository, RawTransaction };
+use super::query::{ Operator, QueryBuilder };
 use super::sqlite::SQLiteRepository;
 
+// The whitelist columns of the table 'users'.
+pub const USER_COLUMNS: &[&str] = &[
+    "id", "status", "create_by", "create_time", "update_by", "update_time", "del_flag", "version",
+    "name", "email", "phone", "password", "lang", "ethers_address",
+    "oidc_claims_sub", "oidc_claims_name", "oidc_claims_email",
+    "github_claims_sub", "github_claims_name", "github_claims_email",
+    "google_claims_sub", "google_claims_name", "google_claims_email",
+];
+
 pub struct UserSQLiteRepository {
     inner: SQLiteRepository<User>,
 }
@@ -49,14 +64,10 @@ impl AsyncRepository<User> for UserSQLiteRepository {
         user: User,
         page: PageRequest
     ) -> Result<(PageResponse, Vec<User>), Error> {
-        let result = dynamic_sqlite_query!(
-            user,
-            "users",
-            self.inner.get_pool(),
-            "update_time",
-            page,
-            User
-        ).unwrap();
+        let builder = QueryBuilder::new("users", USER_COLUMNS)
+            .and_bean(&user)?
+            .order_by("update_time", true)?;
+        let result = self.inner.select_page(&builder, &page).await?;
 
         tracing::info!("query users: {:?}", result);
         Ok((result.0, result.1))
@@ -73,18 +84,19 @@ impl AsyncRepository<User> for UserSQLiteRepository {
     }
 
     async fn select_by_id(&self, id: i64) -> Result<User, Error> {
-        let user = sqlx
-            ::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-            .bind(id)
-            .fetch_one(self.inner.get_pool()).await
-            .unwrap();
+        let builder = QueryBuilder::new("users", USER_COLUMNS).and("id", Operator::Eq, id)?;
+        let user = self.inner
+            .select_one(&builder).await?
+            .ok_or_else(|| BizError::NotFound("user".to_string()))?;
 
         tracing::info!("query user: {:?}", user);
         Ok(user)
     }
 
     async fn insert(&self, mut user: User) -> Result<i64, Error> {
-        let inserted_id = dynamic_sqlite_insert!(user, "users", self.inner.get_pool()).unwrap();
+        user.base.pre_insert(None).await;
+        let builder = QueryBuilder::new("users", USER_COLUMNS);
+        let inserted_id = self.inner.insert_bean(&builder, &user).await?;
         tracing::info!("Inserted user.id: {:?}", inserted_id);
         Ok(inserted_id)
 
@@ -113,7 +125,10 @@ impl AsyncRepository<User> for UserSQLiteRepository {
     }
 
     async fn update(&self, mut user: User) -> Result<i64, Error> {
-        let updated_id = dynamic_sqlite_update!(user, "users", self.inner.get_pool()).unwrap();
+        user.base.pre_update(None).await;
+        let id = user.base.id.ok_or_else(|| Error::msg("The user id is required for update"))?;
+        let builder = QueryBuilder::new("users", USER_COLUMNS).and("id", Operator::Eq, id)?;
+        let updated_id = if self.inner.update_bean(&builder, &user).await? > 0 { id } else { -1 };
         tracing::info!("Updated user.id: {:?}", updated_id);
         Ok(updated_id)
 
@@ -130,23 +145,35 @@ impl AsyncRepository<User> for UserSQLiteRepository {
     }
 
     async fn delete_all(&self) -> Result<u64, Error> {
-        let delete_result = sqlx
-            ::query("DELETE FROM users")
-            .execute(self.inner.get_pool()).await
-            .unwrap();
-
-        tracing::info!("Deleted result: {:?}", delete_result);
-        Ok(delete_result.rows_affected())
+        let deleted = self.inner.delete(&QueryBuilder::new("users", USER_COLUMNS)).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
     }
 
     async fn delete_by_id(&self, id: i64) -> Result<u64, Error> {
-        let delete_result = sqlx
-            ::query("DELETE FROM users WHERE id = $1")
-            .bind(id)
-            .execute(self.inner.get_pool()).await
-            .unwrap();
-
-        tracing::info!("Deleted result: {:?}", delete_result);
-        Ok(delete_result.rows_affected())
+        let builder = QueryBuilder::new("users", USER_COLUMNS).and("id", Operator::Eq, id)?;
+        let deleted = self.inner.delete(&builder).await?;
+        tracing::info!("Deleted result: {:?}", deleted);
+        Ok(deleted)
+    }
+
+    async fn select_raw(&self, after_id: i64, limit: u32) -> Result<Vec<User>, Error> {
+        let builder = QueryBuilder::new("users", USER_COLUMNS);
+        self.inner.select_after(&builder, after_id, limit).await
+    }
+
+    async fn insert_raw(&self, user: User) -> Result<i64, Error> {
+        let builder = QueryBuilder::new("users", USER_COLUMNS);
+        let del_flag = GenericValue::Int32(user.base.del_flag.unwrap_or(0));
+        self.inner.insert_bean_with(&builder, &user, vec![("del_flag".to_string(), del_flag)]).await
+    }
+
+    async fn delete_raw(&self, id: i64) -> Result<u64, Error> {
+        let builder = QueryBuilder::new("users", USER_COLUMNS).and("id", Operator::Eq, id)?;
+        self.inner.delete(&builder).await
+    }
+
+    async fn begin_raw(&self) -> Result<RawTransaction, Error> {
+        Ok(RawTransaction::Sqlite(Arc::new(Mutex::new(self.inner.get_pool().begin().await?))))
     }
 }
@@ -0,0 +1,227 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use sqlx::{ FromRow, postgres::PgRow, sqlite::SqliteRow };
+use serde::{ Deserialize, Serialize };
+use validator::Validate;
+
+use super::{ BaseBean, ModuleBean, try_get_selected };
+
+// The entry of granting another user the role on the document or the whole folder subtree, which
+// owned by the owner of the resource.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct Acl {
+    #[serde(flatten)]
+    pub base: BaseBean,
+    // The owner user id, which stamped from the authenticated claims on insert.
+    #[schema(read_only = true)]
+    pub owner_uid: Option<i64>,
+    pub grantee_uid: Option<i64>,
+    pub resource_type: Option<AclResource>,
+    pub resource_id: Option<i64>,
+    pub role: Option<AclRole>,
+}
+
+impl Acl {
+    pub fn new(grantee_uid: i64, resource_type: AclResource, resource_id: i64, role: AclRole) -> Self {
+        Acl {
+            base: BaseBean::new_default(None),
+            owner_uid: None,
+            grantee_uid: Some(grantee_uid),
+            resource_type: Some(resource_type),
+            resource_id: Some(resource_id),
+            role: Some(role),
+        }
+    }
+
+    // The entry only with the present fields, for the query conditions.
+    pub fn with(grantee_uid: Option<i64>, resource_type: Option<AclResource>, resource_id: Option<i64>) -> Self {
+        Acl {
+            base: BaseBean::new_with_id(None),
+            owner_uid: None,
+            grantee_uid,
+            resource_type,
+            resource_id,
+            role: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AclResource {
+    Document,
+    Folder,
+}
+
+impl TryFrom<String> for AclResource {
+    type Error = sqlx::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "document" => Ok(AclResource::Document),
+            "folder" => Ok(AclResource::Folder),
+            _ => Err(sqlx::Error::ColumnNotFound("Invalid acl resource type".into())),
+        }
+    }
+}
+
+// The editor could also do whatever the viewer could, so the roles are ordered.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AclRole {
+    Viewer,
+    Editor,
+}
+
+impl TryFrom<String> for AclRole {
+    type Error = sqlx::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "viewer" => Ok(AclRole::Viewer),
+            "editor" => Ok(AclRole::Editor),
+            _ => Err(sqlx::Error::ColumnNotFound("Invalid acl role".into())),
+        }
+    }
+}
+
+impl ModuleBean for Acl {
+    fn base(&self) -> &BaseBean {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut BaseBean {
+        &mut self.base
+    }
+
+    fn owner_uid(&self) -> Option<i64> {
+        self.owner_uid
+    }
+}
+
+impl<'r> FromRow<'r, SqliteRow> for Acl {
+    fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+        Ok(Acl {
+            base: BaseBean::from_row(row).unwrap(),
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            grantee_uid: try_get_selected(row, "grantee_uid")?,
+            resource_type: try_get_selected::<_, String>(row, "resource_type")?
+                .map(AclResource::try_from)
+                .transpose()?,
+            resource_id: try_get_selected(row, "resource_id")?,
+            role: try_get_selected::<_, String>(row, "role")?.map(AclRole::try_from).transpose()?,
+        })
+    }
+}
+
+impl<'r> FromRow<'r, PgRow> for Acl {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Acl {
+            base: BaseBean::from_pg_row(row)?,
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            grantee_uid: try_get_selected(row, "grantee_uid")?,
+            resource_type: try_get_selected::<_, String>(row, "resource_type")?
+                .map(AclResource::try_from)
+                .transpose()?,
+            resource_id: try_get_selected(row, "resource_id")?,
+            role: try_get_selected::<_, String>(row, "role")?.map(AclRole::try_from).transpose()?,
+        })
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QueryAclRequest {
+    pub resource_type: Option<AclResource>,
+    pub resource_id: Option<i64>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct QueryAclResponse {
+    pub data: Vec<Acl>,
+}
+
+impl QueryAclResponse {
+    pub fn new(data: Vec<Acl>) -> Self {
+        QueryAclResponse { data }
+    }
+}
+
+// Grant the user of the email the role on the resource, which replaces the role if already granted.
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema)]
+pub struct GrantAclRequest {
+    #[serde(rename = "resourceType")]
+    pub resource_type: AclResource,
+    #[serde(rename = "resourceId")]
+    pub resource_id: i64,
+    #[validate(email)]
+    pub email: String,
+    pub role: AclRole,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct GrantAclResponse {
+    pub id: i64,
+}
+
+impl GrantAclResponse {
+    pub fn new(id: i64) -> Self {
+        GrantAclResponse { id }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema)]
+pub struct RevokeAclRequest {
+    pub id: i64,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct RevokeAclResponse {
+    pub count: u64,
+}
+
+impl RevokeAclResponse {
+    pub fn new(count: u64) -> Self {
+        RevokeAclResponse { count }
+    }
+}
+
+// The resource shared with the current user by the others.
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct SharedResource {
+    #[serde(flatten)]
+    pub acl: Acl,
+    pub key: Option<String>,
+    pub name: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct QuerySharedResponse {
+    pub data: Vec<SharedResource>,
+}
+
+impl QuerySharedResponse {
+    pub fn new(data: Vec<SharedResource>) -> Self {
+        QuerySharedResponse { data }
+    }
+}
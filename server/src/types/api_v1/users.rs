@@ -38,7 +38,7 @@ pub struct QueryUserApiV1Request {
 impl QueryUserApiV1Request {
     pub fn to_user(&self) -> User {
         User {
-            base: BaseBean::new(None, None, None),
+            base: BaseBean::new_with_id(None),
             name: Some(self.name.clone().unwrap_or_default()),
             email: Some(self.email.clone().unwrap_or_default()),
             phone: self.phone.clone(),
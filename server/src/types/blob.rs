@@ -0,0 +1,222 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use sqlx::{ FromRow, postgres::PgRow, sqlite::SqliteRow };
+use serde::{ Deserialize, Serialize };
+use validator::Validate;
+
+use super::{ BaseBean, ModuleBean, PageResponse, try_get_selected };
+
+// The metadata of the uploaded attachment (such as the image embedded in the board), the content
+// is stored once in the blob storage addressed by the SHA-256 hash, and owned by each uploader.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct Blob {
+    #[serde(flatten)]
+    pub base: BaseBean,
+    #[schema(read_only = true)]
+    pub owner_uid: Option<i64>,
+    // The hex of SHA-256 of the content.
+    pub hash: Option<String>,
+    pub mime_type: Option<String>,
+    // The content size in bytes.
+    pub size: Option<i64>,
+}
+
+impl Blob {
+    pub fn new(hash: String, mime_type: String, size: i64) -> Self {
+        Blob {
+            base: BaseBean::new_default(None),
+            owner_uid: None,
+            hash: Some(hash),
+            mime_type: Some(mime_type),
+            size: Some(size),
+        }
+    }
+
+    // The blob only with the hash (and owner), for the query conditions.
+    pub fn with_hash(owner_uid: Option<i64>, hash: Option<String>) -> Self {
+        Blob {
+            base: BaseBean::new_with_id(None),
+            owner_uid,
+            hash,
+            mime_type: None,
+            size: None,
+        }
+    }
+}
+
+impl ModuleBean for Blob {
+    fn base(&self) -> &BaseBean {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut BaseBean {
+        &mut self.base
+    }
+
+    fn owner_uid(&self) -> Option<i64> {
+        self.owner_uid
+    }
+}
+
+impl<'r> FromRow<'r, SqliteRow> for Blob {
+    fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+        Ok(Blob {
+            base: BaseBean::from_row(row).unwrap(),
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            hash: try_get_selected(row, "hash")?,
+            mime_type: try_get_selected(row, "mime_type")?,
+            size: try_get_selected(row, "size")?,
+        })
+    }
+}
+
+impl<'r> FromRow<'r, PgRow> for Blob {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Blob {
+            base: BaseBean::from_pg_row(row)?,
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            hash: try_get_selected(row, "hash")?,
+            mime_type: try_get_selected(row, "mime_type")?,
+            size: try_get_selected(row, "size")?,
+        })
+    }
+}
+
+// The reference of the blob by the document, which is synced from the document content on saved.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct DocumentBlob {
+    #[serde(flatten)]
+    pub base: BaseBean,
+    #[schema(read_only = true)]
+    pub owner_uid: Option<i64>,
+    pub document_id: Option<i64>,
+    pub hash: Option<String>,
+}
+
+impl DocumentBlob {
+    pub fn new(document_id: i64, hash: String) -> Self {
+        DocumentBlob {
+            base: BaseBean::new_default(None),
+            owner_uid: None,
+            document_id: Some(document_id),
+            hash: Some(hash),
+        }
+    }
+
+    // The reference only with the present fields, for the query conditions.
+    pub fn with(owner_uid: Option<i64>, document_id: Option<i64>, hash: Option<String>) -> Self {
+        DocumentBlob {
+            base: BaseBean::new_with_id(None),
+            owner_uid,
+            document_id,
+            hash,
+        }
+    }
+}
+
+impl ModuleBean for DocumentBlob {
+    fn base(&self) -> &BaseBean {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut BaseBean {
+        &mut self.base
+    }
+
+    fn owner_uid(&self) -> Option<i64> {
+        self.owner_uid
+    }
+}
+
+impl<'r> FromRow<'r, SqliteRow> for DocumentBlob {
+    fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+        Ok(DocumentBlob {
+            base: BaseBean::from_row(row).unwrap(),
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            document_id: try_get_selected(row, "document_id")?,
+            hash: try_get_selected(row, "hash")?,
+        })
+    }
+}
+
+impl<'r> FromRow<'r, PgRow> for DocumentBlob {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(DocumentBlob {
+            base: BaseBean::from_pg_row(row)?,
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            document_id: try_get_selected(row, "document_id")?,
+            hash: try_get_selected(row, "hash")?,
+        })
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UploadBlobRequest {
+    // The original file name, to guess the MIME type if the 'Content-Type' is absent.
+    #[validate(length(min = 1, max = 255))]
+    pub name: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct UploadBlobResponse {
+    pub hash: String,
+    pub mime_type: String,
+    pub size: i64,
+}
+
+impl UploadBlobResponse {
+    pub fn new(blob: Blob) -> Self {
+        UploadBlobResponse {
+            hash: blob.hash.unwrap_or_default(),
+            mime_type: blob.mime_type.unwrap_or_default(),
+            size: blob.size.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DownloadBlobRequest {
+    #[validate(length(equal = 64))]
+    pub hash: String,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QueryBlobRequest {
+    // The blobs referenced by the document.
+    pub document_id: i64,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct QueryBlobResponse {
+    pub page: Option<PageResponse>,
+    pub data: Option<Vec<Blob>>,
+}
+
+impl QueryBlobResponse {
+    pub fn new(page: PageResponse, data: Vec<Blob>) -> Self {
+        QueryBlobResponse { page: Some(page), data: Some(data) }
+    }
+}
@@ -0,0 +1,104 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use serde::{ Deserialize, Serialize };
+use validator::Validate;
+
+// The formats of the board files, which are both converted to the excalidraw scene as the board content.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BoardFormat {
+    // The '.excalidraw' file, see: https://docs.excalidraw.com/docs/codebase/json-schema
+    Excalidraw,
+    // The '.tldr' file of the tldraw v2 or later.
+    Tldraw,
+}
+
+impl BoardFormat {
+    // The format of the file name extension, i.e: '.excalidraw' or '.tldr'
+    pub fn from_file_name(name: &str) -> Option<BoardFormat> {
+        match name.rsplit_once('.')?.1.to_lowercase().as_str() {
+            "excalidraw" => Some(BoardFormat::Excalidraw),
+            "tldr" => Some(BoardFormat::Tldraw),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportBoardRequest {
+    // The format of the file, which is detected by the content if absent.
+    pub format: Option<BoardFormat>,
+    // The name of the board document. Default: 'Untitled'
+    #[validate(length(min = 1, max = 64))]
+    pub name: Option<String>,
+    // The key of the target folder, which none means the root.
+    #[validate(length(min = 1, max = 64))]
+    pub folder_key: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct ImportBoardResponse {
+    pub id: i64,
+    pub version: i64,
+}
+
+impl ImportBoardResponse {
+    pub fn new(id: i64, version: i64) -> Self {
+        ImportBoardResponse { id, version }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BulkImportBoardRequest {
+    // The key of the target folder, which none means the root.
+    #[validate(length(min = 1, max = 64))]
+    pub folder_key: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct ImportedBoard {
+    // The path of the file in the zip.
+    pub file: String,
+    pub id: i64,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct FailedBoard {
+    pub file: String,
+    pub reason: String,
+}
+
+// The files of the zip are imported each, and the failed are reported rather than aborting the others.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, utoipa::ToSchema)]
+pub struct BulkImportBoardResponse {
+    pub imported: Vec<ImportedBoard>,
+    pub failed: Vec<FailedBoard>,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportBoardRequest {
+    pub id: i64,
+}
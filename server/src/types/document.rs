@@ -20,16 +20,22 @@
  * This includes modifications and derived works.
  */
 
-use sqlx::{ FromRow, sqlite::SqliteRow, Row };
+use sqlx::{ FromRow, postgres::PgRow, sqlite::SqliteRow, Row };
 use serde::{ Deserialize, Serialize };
 use validator::Validate;
 
-use super::{ BaseBean, PageResponse };
+use crate::errors::BizError;
+use crate::utils::compress;
+use super::tag::TagMatch;
+use super::{ BaseBean, ModuleBean, DocumentSearchHit, PageResponse, try_get_selected };
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
 pub struct Document {
     #[serde(flatten)]
     pub base: BaseBean,
+    // The owner user id, which stamped from the authenticated claims on insert.
+    #[schema(read_only = true)]
+    pub owner_uid: Option<i64>,
     pub key: Option<String>,
     pub name: Option<String>,
     pub folder_key: Option<String>,
@@ -40,6 +46,8 @@ pub struct Document {
     // TODO: It is recommended to use an ORM framework, see: https://github.com/diesel-rs/diesel
     #[serde(rename = "type")]
     pub doc_type: Option<DocumentType>,
+    // The content is stored compressed if large, and transparently decompressed on read.
+    #[serde(default, deserialize_with = "compress::deserialize_content")]
     pub content: Option<String>,
 }
 
@@ -62,15 +70,58 @@ impl TryFrom<String> for DocumentType {
     }
 }
 
+impl ModuleBean for Document {
+    fn base(&self) -> &BaseBean {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut BaseBean {
+        &mut self.base
+    }
+
+    fn owner_uid(&self) -> Option<i64> {
+        self.owner_uid
+    }
+}
+
 impl<'r> FromRow<'r, SqliteRow> for Document {
     fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
         Ok(Document {
             base: BaseBean::from_row(row).unwrap(),
-            key: row.try_get("key")?,
-            name: row.try_get("name")?,
-            folder_key: row.try_get("folder_key")?,
-            doc_type: Some(DocumentType::try_from(row.try_get::<String, _>("type")?)?),
-            content: row.try_get("content")?,
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            key: try_get_selected(row, "key")?,
+            name: try_get_selected(row, "name")?,
+            folder_key: try_get_selected(row, "folder_key")?,
+            doc_type: try_get_selected::<_, String>(row, "type")?.map(DocumentType::try_from).transpose()?,
+            // The content column is omitted by the summary queries.
+            content: match row.try_get("content") {
+                Ok(content) => compress::decode_content(content),
+                Err(sqlx::Error::ColumnNotFound(_)) => None,
+                Err(e) => {
+                    return Err(e);
+                }
+            },
+        })
+    }
+}
+
+impl<'r> FromRow<'r, PgRow> for Document {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Document {
+            base: BaseBean::from_pg_row(row)?,
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            key: try_get_selected(row, "key")?,
+            name: try_get_selected(row, "name")?,
+            folder_key: try_get_selected(row, "folder_key")?,
+            doc_type: try_get_selected::<_, String>(row, "type")?.map(DocumentType::try_from).transpose()?,
+            // The content column is omitted by the summary queries.
+            content: match row.try_get("content") {
+                Ok(content) => compress::decode_content(content),
+                Err(sqlx::Error::ColumnNotFound(_)) => None,
+                Err(e) => {
+                    return Err(e);
+                }
+            },
         })
     }
 }
@@ -86,12 +137,33 @@ pub struct QueryDocumentRequest {
     pub folder_key: Option<String>,
     #[serde(rename = "type")]
     pub doc_type: Option<DocumentType>,
+    // Whether to include the content, the listing should be omit it to be fast. Default: true
+    pub with_content: Option<bool>,
+    // The comma separated ids of the tags, which the documents are matched by any (default) or all of them.
+    pub tags: Option<String>,
+    pub tag_match: Option<TagMatch>,
+    // The owner of the documents shared with the current user, defaults to the current user. Notice: The
+    // tags are private to the owner, which are ignored for the shared documents.
+    pub owner_uid: Option<i64>,
 }
 
 impl QueryDocumentRequest {
+    // The ids of the tags to filter, which is empty if absent.
+    pub fn get_tag_ids(&self) -> Result<Vec<i64>, BizError> {
+        self.tags
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(|id| id.trim())
+            .filter(|id| !id.is_empty())
+            .map(|id| id.parse::<i64>().map_err(|_| BizError::BadRequest(format!("Invalid the tag id '{}'", id))))
+            .collect()
+    }
+
     pub fn to_document(&self) -> Document {
         Document {
-            base: BaseBean::new(None, None, None),
+            base: BaseBean::new_with_id(None),
+            owner_uid: None,
             key: Some(self.key.to_owned().unwrap_or_default()),
             name: Some(self.name.to_owned().unwrap_or_default()),
             folder_key: Some(self.folder_key.to_owned().unwrap_or_default()),
@@ -113,6 +185,44 @@ impl QueryDocumentResponse {
     }
 }
 
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchDocumentRequest {
+    // The search terms which separated by whitespace, and all of them are required.
+    #[validate(length(min = 1, max = 256))]
+    pub q: String,
+    #[validate(length(min = 1, max = 64))]
+    pub folder_key: Option<String>,
+    #[serde(rename = "type")]
+    pub doc_type: Option<DocumentType>,
+}
+
+impl SearchDocumentRequest {
+    pub fn to_document(&self) -> Document {
+        Document {
+            base: BaseBean::new_with_id(None),
+            owner_uid: None,
+            key: None,
+            name: None,
+            folder_key: self.folder_key.to_owned(),
+            doc_type: self.doc_type.to_owned(),
+            content: None,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct SearchDocumentResponse {
+    pub page: Option<PageResponse>,
+    pub data: Option<Vec<DocumentSearchHit>>,
+}
+
+impl SearchDocumentResponse {
+    pub fn new(page: PageResponse, data: Vec<DocumentSearchHit>) -> Self {
+        SearchDocumentResponse { page: Some(page), data: Some(data) }
+    }
+}
+
 #[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema)]
 pub struct SaveDocumentRequest {
     pub id: Option<i64>,
@@ -125,14 +235,17 @@ pub struct SaveDocumentRequest {
     pub folder_key: Option<String>,
     #[serde(rename = "type")]
     pub doc_type: Option<DocumentType>,
-    #[validate(length(min = 0, max = 8192))]
+    // Notice: The max size of content is limited by the 'server.max-body-size'.
     pub content: Option<String>,
+    // The version of the document to update, which is required unless the 'If-Match' header presents.
+    pub version: Option<i64>,
 }
 
 impl SaveDocumentRequest {
     pub fn to_document(&self) -> Document {
         Document {
-            base: BaseBean::new_default(self.id),
+            base: BaseBean { version: self.version, ..BaseBean::new_default(self.id) },
+            owner_uid: None,
             key: self.key.to_owned(),
             name: self.name.to_owned(),
             folder_key: self.folder_key.to_owned(),
@@ -145,11 +258,34 @@ impl SaveDocumentRequest {
 #[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
 pub struct SaveDocumentResponse {
     pub id: i64,
+    // The current version after saved, which is also responded as the ETag.
+    pub version: i64,
 }
 
 impl SaveDocumentResponse {
-    pub fn new(id: i64) -> Self {
-        SaveDocumentResponse { id }
+    pub fn new(id: i64, version: i64) -> Self {
+        SaveDocumentResponse { id, version }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema)]
+pub struct MoveDocumentRequest {
+    #[validate(length(min = 1, max = 1000))]
+    pub ids: Vec<i64>,
+    // The key of the target folder, which none means the root.
+    #[serde(rename = "folderKey")]
+    #[validate(length(min = 1, max = 64))]
+    pub folder_key: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct MoveDocumentResponse {
+    pub count: u64,
+}
+
+impl MoveDocumentResponse {
+    pub fn new(count: u64) -> Self {
+        MoveDocumentResponse { count }
     }
 }
 
@@ -168,3 +304,102 @@ impl DeleteDocumentResponse {
         DeleteDocumentResponse { count }
     }
 }
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema)]
+pub struct RestoreDocumentRequest {
+    pub id: i64,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct RestoreDocumentResponse {
+    pub count: u64,
+}
+
+impl RestoreDocumentResponse {
+    pub fn new(count: u64) -> Self {
+        RestoreDocumentResponse { count }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema)]
+pub struct PurgeDocumentRequest {
+    pub id: i64,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct PurgeDocumentResponse {
+    pub count: u64,
+}
+
+impl PurgeDocumentResponse {
+    pub fn new(count: u64) -> Self {
+        PurgeDocumentResponse { count }
+    }
+}
+
+// The formats of exporting the note, which is converted from the BlockSuite snapshot of the content.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteFormat {
+    // The CommonMark, which the images are linked to the blob downloads.
+    Md,
+    // The standalone HTML page, which the images are embedded as the data URLs.
+    Html,
+}
+
+impl NoteFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            NoteFormat::Md => "md",
+            NoteFormat::Html => "html",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            NoteFormat::Md => "text/markdown; charset=utf-8",
+            NoteFormat::Html => "text/html; charset=utf-8",
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportDocumentRequest {
+    pub id: i64,
+    pub format: NoteFormat,
+}
+
+// The formats of the thumbnail of the board, which is rendered from the excalidraw elements.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailFormat {
+    Svg,
+    Png,
+}
+
+impl ThumbnailFormat {
+    pub const ALL: [ThumbnailFormat; 2] = [ThumbnailFormat::Svg, ThumbnailFormat::Png];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ThumbnailFormat::Svg => "svg",
+            ThumbnailFormat::Png => "png",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ThumbnailFormat::Svg => "image/svg+xml",
+            ThumbnailFormat::Png => "image/png",
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GetThumbnailRequest {
+    pub id: i64,
+    // Defaults to the PNG.
+    pub format: Option<ThumbnailFormat>,
+}
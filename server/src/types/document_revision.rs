@@ -0,0 +1,169 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use sqlx::{ FromRow, postgres::PgRow, sqlite::SqliteRow, Row };
+use serde::{ Deserialize, Serialize };
+use validator::Validate;
+
+use crate::utils::compress;
+use super::{ BaseBean, ModuleBean, PageResponse, try_get_selected };
+
+// The immutable snapshot of the document content, the author and timestamp are the audit
+// fields 'create_by' and 'create_time'.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct DocumentRevision {
+    #[serde(flatten)]
+    pub base: BaseBean,
+    #[schema(read_only = true)]
+    pub owner_uid: Option<i64>,
+    pub document_id: Option<i64>,
+    // The content is stored compressed if large, and transparently decompressed on read.
+    #[serde(default, deserialize_with = "compress::deserialize_content")]
+    pub content: Option<String>,
+    // The content size in bytes (uncompressed).
+    pub size: Option<i64>,
+}
+
+impl DocumentRevision {
+    pub fn new(document_id: i64, content: Option<String>) -> Self {
+        DocumentRevision {
+            base: BaseBean::new_default(None),
+            owner_uid: None,
+            document_id: Some(document_id),
+            size: Some(content.as_ref().map(|c| c.len() as i64).unwrap_or(0)),
+            content,
+        }
+    }
+}
+
+impl ModuleBean for DocumentRevision {
+    fn base(&self) -> &BaseBean {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut BaseBean {
+        &mut self.base
+    }
+
+    fn owner_uid(&self) -> Option<i64> {
+        self.owner_uid
+    }
+}
+
+impl<'r> FromRow<'r, SqliteRow> for DocumentRevision {
+    fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+        Ok(DocumentRevision {
+            base: BaseBean::from_row(row).unwrap(),
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            document_id: try_get_selected(row, "document_id")?,
+            content: compress::decode_content(row.try_get("content")?),
+            size: try_get_selected(row, "size")?,
+        })
+    }
+}
+
+impl<'r> FromRow<'r, PgRow> for DocumentRevision {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(DocumentRevision {
+            base: BaseBean::from_pg_row(row)?,
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            document_id: try_get_selected(row, "document_id")?,
+            content: compress::decode_content(row.try_get("content")?),
+            size: try_get_selected(row, "size")?,
+        })
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QueryDocumentRevisionRequest {
+    pub document_id: i64,
+}
+
+impl QueryDocumentRevisionRequest {
+    pub fn to_revision(&self) -> DocumentRevision {
+        DocumentRevision {
+            base: BaseBean::new_with_id(None),
+            owner_uid: None,
+            document_id: Some(self.document_id),
+            content: None,
+            size: None,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct QueryDocumentRevisionResponse {
+    pub page: Option<PageResponse>,
+    pub data: Option<Vec<DocumentRevision>>,
+}
+
+impl QueryDocumentRevisionResponse {
+    pub fn new(page: PageResponse, data: Vec<DocumentRevision>) -> Self {
+        QueryDocumentRevisionResponse { page: Some(page), data: Some(data) }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GetDocumentRevisionRequest {
+    pub id: i64,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DiffDocumentRevisionRequest {
+    pub from_id: i64,
+    // The target revision id, default to the current document content.
+    pub to_id: Option<i64>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct DiffDocumentRevisionResponse {
+    pub from_id: i64,
+    pub to_id: Option<i64>,
+    // The unified diff of the contents.
+    pub diff: String,
+}
+
+impl DiffDocumentRevisionResponse {
+    pub fn new(from_id: i64, to_id: Option<i64>, diff: String) -> Self {
+        DiffDocumentRevisionResponse { from_id, to_id, diff }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema)]
+pub struct RestoreDocumentRevisionRequest {
+    pub id: i64,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct RestoreDocumentRevisionResponse {
+    // The restored document id.
+    pub id: i64,
+}
+
+impl RestoreDocumentRevisionResponse {
+    pub fn new(id: i64) -> Self {
+        RestoreDocumentRevisionResponse { id }
+    }
+}
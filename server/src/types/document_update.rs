@@ -0,0 +1,96 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use sqlx::{ FromRow, postgres::PgRow, sqlite::SqliteRow };
+use serde::{ Deserialize, Serialize };
+
+use super::{ BaseBean, ModuleBean, try_get_selected };
+
+// The entry of the append-only log of the yjs updates of the note, the log is compacted into the one
+// merged update (the snapshot) on exceeded the threshold, see: handler::sync
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct DocumentUpdate {
+    #[serde(flatten)]
+    pub base: BaseBean,
+    #[schema(read_only = true)]
+    pub owner_uid: Option<i64>,
+    pub document_id: Option<i64>,
+    // The base64 of the yjs update (v1 encoding).
+    pub data: Option<String>,
+}
+
+impl DocumentUpdate {
+    pub fn new(document_id: i64, data: Option<String>) -> Self {
+        DocumentUpdate {
+            base: BaseBean::new_default(None),
+            owner_uid: None,
+            document_id: Some(document_id),
+            data,
+        }
+    }
+
+    // The query param of all the updates of the document.
+    pub fn of_document(document_id: i64) -> Self {
+        DocumentUpdate {
+            base: BaseBean::new_with_id(None),
+            owner_uid: None,
+            document_id: Some(document_id),
+            data: None,
+        }
+    }
+}
+
+impl ModuleBean for DocumentUpdate {
+    fn base(&self) -> &BaseBean {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut BaseBean {
+        &mut self.base
+    }
+
+    fn owner_uid(&self) -> Option<i64> {
+        self.owner_uid
+    }
+}
+
+impl<'r> FromRow<'r, SqliteRow> for DocumentUpdate {
+    fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+        Ok(DocumentUpdate {
+            base: BaseBean::from_row(row).unwrap(),
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            document_id: try_get_selected(row, "document_id")?,
+            data: try_get_selected(row, "data")?,
+        })
+    }
+}
+
+impl<'r> FromRow<'r, PgRow> for DocumentUpdate {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(DocumentUpdate {
+            base: BaseBean::from_pg_row(row)?,
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            document_id: try_get_selected(row, "document_id")?,
+            data: try_get_selected(row, "data")?,
+        })
+    }
+}
@@ -0,0 +1,82 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use chrono::Utc;
+use serde::{ Deserialize, Serialize };
+
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeResource {
+    Document,
+    Folder,
+    Settings,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeAction {
+    Created,
+    Updated,
+    Moved,
+    Deleted,
+    Restored,
+    Purged,
+}
+
+// The change of the documents, folders or settings of the owner, which is the data of the server-sent
+// event 'change', and the changed resource should be fetched again if needed, i.e:
+// {"id":1,"resource":"document","action":"updated","resourceId":2,"key":"k1","version":3,"time":1729180800000}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct ChangeEvent {
+    // The increasing id of the event, which is the 'Last-Event-ID' of resuming.
+    pub id: i64,
+    pub resource: ChangeResource,
+    pub action: ChangeAction,
+    #[serde(rename = "resourceId")]
+    pub resource_id: i64,
+    pub key: Option<String>,
+    // The version after changed, only of the saved or moved.
+    pub version: Option<i64>,
+    pub time: i64,
+}
+
+impl ChangeEvent {
+    pub fn new(
+        resource: ChangeResource,
+        action: ChangeAction,
+        resource_id: i64,
+        key: Option<String>,
+        version: Option<i64>
+    ) -> Self {
+        ChangeEvent {
+            // Assigned when publishing, from the sequence of per user.
+            id: 0,
+            resource,
+            action,
+            resource_id,
+            key,
+            version,
+            time: Utc::now().timestamp_millis(),
+        }
+    }
+}
@@ -20,28 +20,59 @@
  * This includes modifications and derived works.
  */
 
-use sqlx::{ FromRow, sqlite::SqliteRow, Row };
+use sqlx::{ FromRow, postgres::PgRow, sqlite::SqliteRow };
 use serde::{ Deserialize, Serialize };
 use validator::Validate;
 
-use super::{ BaseBean, PageResponse };
+use super::{ BaseBean, ModuleBean, PageResponse, try_get_selected };
+use super::document::NoteFormat;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
 pub struct Folder {
     #[serde(flatten)]
     pub base: BaseBean,
+    // The owner user id, which stamped from the authenticated claims on insert.
+    #[schema(read_only = true)]
+    pub owner_uid: Option<i64>,
     pub pid: Option<i64>,
     pub key: Option<String>,
     pub name: Option<String>,
 }
 
+impl ModuleBean for Folder {
+    fn base(&self) -> &BaseBean {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut BaseBean {
+        &mut self.base
+    }
+
+    fn owner_uid(&self) -> Option<i64> {
+        self.owner_uid
+    }
+}
+
 impl<'r> FromRow<'r, SqliteRow> for Folder {
     fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
         Ok(Folder {
             base: BaseBean::from_row(row).unwrap(),
-            pid: row.try_get("pid")?,
-            key: row.try_get("key")?,
-            name: row.try_get("name")?,
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            pid: try_get_selected(row, "pid")?,
+            key: try_get_selected(row, "key")?,
+            name: try_get_selected(row, "name")?,
+        })
+    }
+}
+
+impl<'r> FromRow<'r, PgRow> for Folder {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Folder {
+            base: BaseBean::from_pg_row(row)?,
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            pid: try_get_selected(row, "pid")?,
+            key: try_get_selected(row, "key")?,
+            name: try_get_selected(row, "name")?,
         })
     }
 }
@@ -53,13 +84,16 @@ pub struct QueryFolderRequest {
     #[validate(length(min = 1, max = 64))]
     pub key: Option<String>,
     pub name: Option<String>,
+    // The owner of the folders shared with the current user, defaults to the current user.
+    pub owner_uid: Option<i64>,
 }
 
 impl QueryFolderRequest {
     pub fn to_folder(&self) -> Folder {
         Folder {
-            base: BaseBean::new(None, None, None),
-            pid: Some(self.pid.clone().unwrap_or_default()),
+            base: BaseBean::new_with_id(None),
+            owner_uid: None,
+            pid: self.pid,
             key: Some(self.key.clone().unwrap_or_default()),
             name: Some(self.name.clone().unwrap_or_default()),
         }
@@ -72,14 +106,41 @@ pub struct QueryFolderResponse {
     pub data: Option<Vec<Folder>>,
 }
 
-// pub struct FolderWrapper(Folder); // add field childern and support transform to tree json.
-
 impl QueryFolderResponse {
     pub fn new(page: PageResponse, data: Vec<Folder>) -> Self {
         QueryFolderResponse { page: Some(page), data: Some(data) }
     }
 }
 
+// The folder with the sub folders, which transforms the flat folders to the tree json.
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct FolderNode {
+    #[serde(flatten)]
+    pub folder: Folder,
+    // The count of the documents directly in the folder, exclusive of the sub folders.
+    pub document_count: u64,
+    pub children: Vec<FolderNode>,
+}
+
+impl FolderNode {
+    pub fn new(folder: Folder) -> Self {
+        FolderNode { folder, document_count: 0, children: vec![] }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct FolderTreeResponse {
+    pub data: Vec<FolderNode>,
+    // The count of the documents not in any folder.
+    pub root_document_count: u64,
+}
+
+impl FolderTreeResponse {
+    pub fn new(data: Vec<FolderNode>, root_document_count: u64) -> Self {
+        FolderTreeResponse { data, root_document_count }
+    }
+}
+
 #[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema)]
 pub struct SaveFolderRequest {
     pub id: Option<i64>,
@@ -88,12 +149,15 @@ pub struct SaveFolderRequest {
     pub key: Option<String>,
     #[validate(length(min = 1, max = 64))]
     pub name: Option<String>,
+    // The version of the folder to update, which is required unless the 'If-Match' header presents.
+    pub version: Option<i64>,
 }
 
 impl SaveFolderRequest {
     pub fn to_folder(&self) -> Folder {
         Folder {
-            base: BaseBean::new_default(self.id),
+            base: BaseBean { version: self.version, ..BaseBean::new_default(self.id) },
+            owner_uid: None,
             pid: self.pid,
             key: self.key.clone(),
             name: self.name.clone(),
@@ -104,17 +168,53 @@ impl SaveFolderRequest {
 #[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
 pub struct SaveFolderResponse {
     pub id: i64,
+    // The current version after saved, which is also responded as the ETag.
+    pub version: i64,
 }
 
 impl SaveFolderResponse {
-    pub fn new(id: i64) -> Self {
-        SaveFolderResponse { id }
+    pub fn new(id: i64, version: i64) -> Self {
+        SaveFolderResponse { id, version }
     }
 }
 
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema)]
+pub struct MoveFolderRequest {
+    pub id: i64,
+    // The new parent folder id, which 0 means the root.
+    pub pid: i64,
+    // The version of the folder to move, which is required unless the 'If-Match' header presents.
+    pub version: Option<i64>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct MoveFolderResponse {
+    pub id: i64,
+    pub version: i64,
+}
+
+impl MoveFolderResponse {
+    pub fn new(id: i64, version: i64) -> Self {
+        MoveFolderResponse { id, version }
+    }
+}
+
+// The cascade of deleting the folder for the sub folders and documents of it.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, utoipa::ToSchema)]
+pub enum FolderCascade {
+    // Move the whole subtree (the sub folders and documents) to the trash.
+    #[default]
+    Trash,
+    // Move the sub folders and documents to the parent folder, only the folder is trashed.
+    Reparent,
+    // Refuse to delete the folder unless it's empty.
+    Restrict,
+}
+
 #[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema)]
 pub struct DeleteFolderRequest {
     pub id: i64,
+    pub cascade: Option<FolderCascade>,
 }
 
 #[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
@@ -127,3 +227,42 @@ impl DeleteFolderResponse {
         DeleteFolderResponse { count }
     }
 }
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema)]
+pub struct RestoreFolderRequest {
+    pub id: i64,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct RestoreFolderResponse {
+    pub count: u64,
+}
+
+impl RestoreFolderResponse {
+    pub fn new(count: u64) -> Self {
+        RestoreFolderResponse { count }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema)]
+pub struct PurgeFolderRequest {
+    pub id: i64,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct PurgeFolderResponse {
+    pub count: u64,
+}
+
+impl PurgeFolderResponse {
+    pub fn new(count: u64) -> Self {
+        PurgeFolderResponse { count }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportFolderRequest {
+    pub id: i64,
+    pub format: NoteFormat,
+}
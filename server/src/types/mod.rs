@@ -20,26 +20,48 @@
  * This includes modifications and derived works.
  */
 
+pub mod acl;
 pub mod api_v1;
 pub mod auth;
+pub mod blob;
+pub mod board;
 pub mod user;
 pub mod document;
+pub mod document_revision;
+pub mod document_update;
+pub mod event;
 pub mod folder;
 pub mod settings;
+pub mod room;
+pub mod share;
+pub mod tag;
 pub mod browser_indexeddb;
 
 use anyhow::Error;
+use base64::{ engine::general_purpose::URL_SAFE_NO_PAD, Engine as _ };
 use hyper::StatusCode;
 use serde::{ Deserialize, Serialize };
 use chrono::Utc;
 use sqlx::prelude::FromRow;
 use validator::Validate;
 
+use crate::errors::BizError;
 use crate::utils::{ auths::SecurityContext, snowflake::SnowflakeIdGenerator };
 // use sqlx::{ Decode, FromRow };
 
 pub static DEFAULT_BY: &'static str = "0";
 
+// The matched data of the full text search.
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+#[aliases(DocumentSearchHit = SearchHit<document::Document>)]
+pub struct SearchHit<T> {
+    pub data: T,
+    // The relevance score, the higher the more relevant.
+    pub score: f64,
+    // The snippets of the matched terms which are wrapped with '<mark></mark>'.
+    pub highlights: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, FromRow, utoipa::ToSchema)]
 pub struct BaseBean {
     #[schema(rename = "id")]
@@ -61,6 +83,9 @@ pub struct BaseBean {
     pub update_time: Option<i64>,
     #[serde(skip)]
     pub del_flag: Option<i32>,
+    // The optimistic lock version, which is increased on every update and exposed as the ETag.
+    #[schema(read_only = true)]
+    pub version: Option<i64>,
 }
 
 impl BaseBean {
@@ -69,6 +94,20 @@ impl BaseBean {
         Self::new(id, None, None)
     }
 
+    // The bean only with id, for the query conditions which ignore the other absent fields.
+    pub fn new_with_id(id: Option<i64>) -> Self {
+        Self {
+            id,
+            status: None,
+            create_by: None,
+            create_time: None,
+            update_by: None,
+            update_time: None,
+            del_flag: None,
+            version: None,
+        }
+    }
+
     pub fn new(id: Option<i64>, create_by: Option<String>, update_by: Option<String>) -> Self {
         let now = Utc::now().timestamp_millis();
         Self {
@@ -79,26 +118,43 @@ impl BaseBean {
             update_by,
             update_time: Some(now),
             del_flag: Some(0),
+            version: None,
         }
     }
 
+    // Notice: The status of Postgres is the 'smallint' (there is no 1 byte integer type).
+    pub fn from_pg_row(row: &sqlx::postgres::PgRow) -> Result<Self, sqlx::Error> {
+        use sqlx::Row;
+        Ok(Self {
+            id: row.try_get("id")?,
+            status: row.try_get::<Option<i16>, _>("status")?.map(|s| s as i8),
+            create_by: row.try_get("create_by")?,
+            create_time: row.try_get("create_time")?,
+            update_by: row.try_get("update_by")?,
+            update_time: row.try_get("update_time")?,
+            del_flag: row.try_get("del_flag")?,
+            version: row.try_get("version")?,
+        })
+    }
+
     pub async fn pre_insert(&mut self, create_by: Option<String>) -> i64 {
         let by = create_by
-            .or(SecurityContext::get_instance().get_current_email().await)
-            .or(SecurityContext::get_instance().get_current_uname().await)
+            .or(SecurityContext::get_current_email())
+            .or(SecurityContext::get_current_uname())
             .or(Some(DEFAULT_BY.to_string()));
 
         self.id = Some(SnowflakeIdGenerator::default_next_jssafe());
         self.create_by = by;
         self.create_time = Some(Utc::now().timestamp_millis());
         self.del_flag = Some(0);
+        self.version = Some(1);
         self.id.unwrap()
     }
 
     pub async fn pre_update(&mut self, update_by: Option<String>) {
         let by = update_by
-            .or(SecurityContext::get_instance().get_current_email().await)
-            .or(SecurityContext::get_instance().get_current_uname().await)
+            .or(SecurityContext::get_current_email())
+            .or(SecurityContext::get_current_uname())
             .or(Some(DEFAULT_BY.to_string()));
 
         self.update_by = by;
@@ -107,6 +163,29 @@ impl BaseBean {
     }
 }
 
+// The beans of the modules data, which is for the generic operations of the all modules, such as
+// the data migration and backup.
+pub trait ModuleBean {
+    fn base(&self) -> &BaseBean;
+    fn base_mut(&mut self) -> &mut BaseBean;
+    // The owner user id of the data, which is the id itself of the user.
+    fn owner_uid(&self) -> Option<i64>;
+}
+
+// Get the value of the column which is none if not selected, such as omitted by the projection.
+pub fn try_get_selected<'r, R, T>(row: &'r R, column: &str) -> Result<Option<T>, sqlx::Error>
+    where
+        R: sqlx::Row,
+        for<'c> &'c str: sqlx::ColumnIndex<R>,
+        Option<T>: sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>
+{
+    match row.try_get::<Option<T>, _>(column) {
+        Ok(value) => Ok(value),
+        Err(sqlx::Error::ColumnNotFound(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
 #[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema, utoipa::IntoParams)]
 pub struct PageRequest {
     #[schema(example = "1")]
@@ -115,9 +194,26 @@ pub struct PageRequest {
     #[schema(example = "10")]
     #[validate(range(min = 1, max = 1000))]
     pub limit: Option<u32>, // The per page records count.
-    // For large data of fast-queries cached condition acceleration.
-    // pub cached_forward_last_min_id: Option<i64>,
-    // pub cached_backend_last_max_id: Option<i64>,
+    // The opaque cursor of the 'next_cursor' or 'prev_cursor' of the last page, which seeks the
+    // keys instead of skipping the offset, so that the deep pages are as fast as the first one.
+    // The 'num' is ignored if the cursor is present.
+    #[validate(length(min = 1, max = 512))]
+    pub cursor: Option<String>,
+    // Whether to count the total records, defaults to true for the offset paging and false for the
+    // cursor paging.
+    pub with_total: Option<bool>,
+    /// The comma separated sort columns, and the '-' prefixed is descending, such as '-update_time,name',
+    /// defaults to the latest updated first. The columns are whitelisted per entity.
+    #[schema(example = "-update_time,name")]
+    #[param(example = "-update_time,name")]
+    #[validate(length(min = 1, max = 256))]
+    pub sort: Option<String>,
+    /// The comma separated columns to respond, such as 'key,name', the common columns (such as the id
+    /// and version) are always responded. The columns are whitelisted per entity.
+    #[schema(example = "key,name")]
+    #[param(example = "key,name")]
+    #[validate(length(min = 1, max = 512))]
+    pub fields: Option<String>,
 }
 
 impl PageRequest {
@@ -125,10 +221,21 @@ impl PageRequest {
         PageRequest {
             num: Some(1),
             limit: Some(10),
-            // cached_forward_last_min_id: None,
-            // cached_backend_last_max_id: None,
+            cursor: None,
+            with_total: None,
+            sort: None,
+            fields: None,
         }
     }
+
+    pub fn new(num: u32, limit: u32) -> PageRequest {
+        PageRequest { num: Some(num), limit: Some(limit), ..PageRequest::default() }
+    }
+
+    pub fn get_num(&self) -> u32 {
+        self.num.unwrap_or(1).max(1)
+    }
+
     pub fn get_offset(&self) -> u32 {
         let n = self.num.unwrap_or(1);
         if n < 1 {
@@ -146,6 +253,72 @@ impl PageRequest {
             l
         }
     }
+
+    pub fn get_cursor(&self) -> Result<Option<PageCursor>, Error> {
+        self.cursor.as_deref().map(PageCursor::decode).transpose()
+    }
+
+    pub fn is_with_total(&self) -> bool {
+        self.with_total.unwrap_or(self.cursor.is_none())
+    }
+
+    // The columns and whether descending of the sort, which is empty if absent.
+    pub fn get_sorts(&self) -> Vec<(&str, bool)> {
+        split_columns(&self.sort)
+            .into_iter()
+            .map(|column| {
+                match column.strip_prefix('-') {
+                    Some(column) => (column, true),
+                    None => (column.strip_prefix('+').unwrap_or(column), false),
+                }
+            })
+            .collect()
+    }
+
+    // The columns of the projection, which is empty if absent.
+    pub fn get_fields(&self) -> Vec<&str> {
+        split_columns(&self.fields)
+    }
+}
+
+fn split_columns(columns: &Option<String>) -> Vec<&str> {
+    columns
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(|c| c.trim())
+        .filter(|c| !c.is_empty())
+        .collect()
+}
+
+// The keyset of the boundary row of the page, which is the values of the ordering columns (the last
+// is the 'id' as the tie breaker), and is encoded as the opaque token of the responded cursors.
+// Notice: The rows with the null values of the ordering columns are skipped by the seeking.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PageCursor {
+    // Seek the rows before the boundary (the previous page), otherwise after it (the next page).
+    #[serde(rename = "b")]
+    pub backward: bool,
+    #[serde(rename = "k")]
+    pub keys: Vec<serde_json::Value>,
+}
+
+impl PageCursor {
+    pub fn new(backward: bool, keys: Vec<serde_json::Value>) -> Self {
+        Self { backward, keys }
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(token: &str) -> Result<Self, Error> {
+        URL_SAFE_NO_PAD.decode(token)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<PageCursor>(&bytes).ok())
+            .filter(|cursor| !cursor.keys.is_empty())
+            .ok_or_else(|| BizError::BadRequest("Invalid page cursor".to_string()).into())
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
@@ -153,9 +326,10 @@ pub struct PageResponse {
     pub total: Option<i64>, // The current conditions snapshot data of total records count.
     pub num: Option<u32>, // page number.
     pub limit: Option<u32>, // The per page records count.
-    // For large data of fast-queries cached condition acceleration.
-    // pub cached_forward_last_min_id: Option<i64>,
-    // pub cached_backend_last_max_id: Option<i64>,
+    // The cursor of the next page, which is none if it's the last page.
+    pub next_cursor: Option<String>,
+    // The cursor of the previous page, which is none if it's the first page.
+    pub prev_cursor: Option<String>,
 }
 
 impl PageResponse {
@@ -164,8 +338,16 @@ impl PageResponse {
             total: total,
             num: num,
             limit,
+            next_cursor: None,
+            prev_cursor: None,
         }
     }
+
+    pub fn with_cursors(mut self, next: Option<PageCursor>, prev: Option<PageCursor>) -> Self {
+        self.next_cursor = next.map(|c| c.encode());
+        self.prev_cursor = prev.map(|c| c.encode());
+        self
+    }
 }
 
 #[derive(Serialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema, utoipa::IntoParams)]
@@ -0,0 +1,65 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+use serde::{ Deserialize, Serialize };
+use serde_json::Value;
+
+// The message of the collaboration room of the board, which is the JSON text frame of the websocket,
+// i.e: {"type":"scene_update","elements":[...]}, only the updates and the cursors are accepted from
+// the clients, the others are sent by the server.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RoomMessage {
+    // The merged excalidraw scene of the room, which is sent to the client joined.
+    SceneInit {
+        #[schema(value_type = Object)]
+        scene: Value,
+    },
+    // The changed excalidraw elements, which are merged by the ids and the versions.
+    SceneUpdate {
+        #[schema(value_type = Vec<Object>)]
+        elements: Vec<Value>,
+    },
+    // The pointer of the collaborator, i.e: {"x":10,"y":20,"tool":"pointer"}
+    Cursor {
+        #[schema(value_type = Object)]
+        pointer: Value,
+        button: Option<String>,
+    },
+    Joined,
+    Left,
+}
+
+impl RoomMessage {
+    pub fn is_acceptable(&self) -> bool {
+        matches!(self, RoomMessage::SceneUpdate { .. } | RoomMessage::Cursor { .. })
+    }
+}
+
+// The message relayed to the clients of the room, the sender is the id of the connection, so that the
+// collaborators of the same user are distinguished.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct RoomEvent {
+    pub sender: String,
+    pub uid: i64,
+    #[serde(flatten)]
+    pub message: RoomMessage,
+}
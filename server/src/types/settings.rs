@@ -20,24 +20,52 @@
  * This includes modifications and derived works.
  */
 
-use sqlx::{ FromRow, sqlite::SqliteRow, Row };
+use sqlx::{ FromRow, postgres::PgRow, sqlite::SqliteRow };
 use serde::{ Deserialize, Serialize };
 use validator::Validate;
 
-use super::{ BaseBean, PageResponse };
+use super::{ BaseBean, ModuleBean, PageResponse, try_get_selected };
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
 pub struct Settings {
     #[serde(flatten)]
     pub base: BaseBean,
+    // The owner user id, which stamped from the authenticated claims on insert.
+    #[schema(read_only = true)]
+    pub owner_uid: Option<i64>,
     pub name: Option<String>,
 }
 
+impl ModuleBean for Settings {
+    fn base(&self) -> &BaseBean {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut BaseBean {
+        &mut self.base
+    }
+
+    fn owner_uid(&self) -> Option<i64> {
+        self.owner_uid
+    }
+}
+
 impl<'r> FromRow<'r, SqliteRow> for Settings {
     fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
         Ok(Settings {
             base: BaseBean::from_row(row).unwrap(),
-            name: row.try_get("name")?,
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            name: try_get_selected(row, "name")?,
+        })
+    }
+}
+
+impl<'r> FromRow<'r, PgRow> for Settings {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Settings {
+            base: BaseBean::from_pg_row(row)?,
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            name: try_get_selected(row, "name")?,
         })
     }
 }
@@ -52,7 +80,8 @@ pub struct QuerySettingsRequest {
 impl QuerySettingsRequest {
     pub fn to_settings(&self) -> Settings {
         Settings {
-            base: BaseBean::new(None, None, None),
+            base: BaseBean::new_with_id(None),
+            owner_uid: None,
             name: Some(self.name.clone().unwrap_or_default()),
         }
     }
@@ -81,6 +110,7 @@ impl SaveSettingsRequest {
     pub fn to_settings(&self) -> Settings {
         Settings {
             base: BaseBean::new_default(self.id),
+            owner_uid: None,
             name: self.name.clone(),
         }
     }
@@ -0,0 +1,217 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use sqlx::{ FromRow, postgres::PgRow, sqlite::SqliteRow };
+use serde::{ Deserialize, Serialize };
+use validator::Validate;
+
+use super::document::{ Document, DocumentType };
+use super::{ BaseBean, ModuleBean, try_get_selected };
+
+// The public link of the read-only document access without an account.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct ShareLink {
+    #[serde(flatten)]
+    pub base: BaseBean,
+    // The owner user id, which stamped from the authenticated claims on insert.
+    #[schema(read_only = true)]
+    pub owner_uid: Option<i64>,
+    pub document_id: Option<i64>,
+    // The random token of the public url, which is unique globally.
+    pub token: Option<String>,
+    // The salted hash of the password if protected, formatted as '<salt>$<sha256 hex>'.
+    #[schema(read_only = true)]
+    pub password: Option<String>,
+    // The expiration time (milliseconds) of the link, which never expires if absent.
+    pub expire_time: Option<i64>,
+    #[schema(read_only = true)]
+    pub access_count: Option<i64>,
+    #[schema(read_only = true)]
+    pub last_access_time: Option<i64>,
+}
+
+impl ShareLink {
+    pub fn new(document_id: i64, token: String, password: Option<String>, expire_time: Option<i64>) -> Self {
+        ShareLink {
+            base: BaseBean::new_default(None),
+            owner_uid: None,
+            document_id: Some(document_id),
+            token: Some(token),
+            password,
+            expire_time,
+            access_count: Some(0),
+            last_access_time: None,
+        }
+    }
+
+    // The link only with the present fields, for the query conditions.
+    pub fn with(document_id: Option<i64>, token: Option<String>) -> Self {
+        ShareLink {
+            base: BaseBean::new_with_id(None),
+            owner_uid: None,
+            document_id,
+            token,
+            password: None,
+            expire_time: None,
+            access_count: None,
+            last_access_time: None,
+        }
+    }
+}
+
+impl ModuleBean for ShareLink {
+    fn base(&self) -> &BaseBean {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut BaseBean {
+        &mut self.base
+    }
+
+    fn owner_uid(&self) -> Option<i64> {
+        self.owner_uid
+    }
+}
+
+impl<'r> FromRow<'r, SqliteRow> for ShareLink {
+    fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+        Ok(ShareLink {
+            base: BaseBean::from_row(row).unwrap(),
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            document_id: try_get_selected(row, "document_id")?,
+            token: try_get_selected(row, "token")?,
+            password: try_get_selected(row, "password")?,
+            expire_time: try_get_selected(row, "expire_time")?,
+            access_count: try_get_selected(row, "access_count")?,
+            last_access_time: try_get_selected(row, "last_access_time")?,
+        })
+    }
+}
+
+impl<'r> FromRow<'r, PgRow> for ShareLink {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(ShareLink {
+            base: BaseBean::from_pg_row(row)?,
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            document_id: try_get_selected(row, "document_id")?,
+            token: try_get_selected(row, "token")?,
+            password: try_get_selected(row, "password")?,
+            expire_time: try_get_selected(row, "expire_time")?,
+            access_count: try_get_selected(row, "access_count")?,
+            last_access_time: try_get_selected(row, "last_access_time")?,
+        })
+    }
+}
+
+// The link without the password hash, and whether it's protected by the password.
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct ShareLinkView {
+    #[serde(flatten)]
+    pub link: ShareLink,
+    pub protected: bool,
+}
+
+impl From<ShareLink> for ShareLinkView {
+    fn from(mut link: ShareLink) -> Self {
+        let protected = link.password.take().is_some_and(|p| !p.is_empty());
+        ShareLinkView { link, protected }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QueryShareLinkRequest {
+    // Only the links of the document if present.
+    pub document_id: Option<i64>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct QueryShareLinkResponse {
+    pub data: Vec<ShareLinkView>,
+}
+
+impl QueryShareLinkResponse {
+    pub fn new(data: Vec<ShareLinkView>) -> Self {
+        QueryShareLinkResponse { data }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema)]
+pub struct CreateShareLinkRequest {
+    #[serde(rename = "documentId")]
+    pub document_id: i64,
+    // The expiration time (milliseconds) of the link, which never expires if absent.
+    #[serde(rename = "expireTime")]
+    pub expire_time: Option<i64>,
+    // The password required for accessing if present.
+    #[validate(length(min = 1, max = 128))]
+    pub password: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct CreateShareLinkResponse {
+    pub id: i64,
+    pub token: String,
+}
+
+impl CreateShareLinkResponse {
+    pub fn new(id: i64, token: String) -> Self {
+        CreateShareLinkResponse { id, token }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema)]
+pub struct RevokeShareLinkRequest {
+    pub id: i64,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct RevokeShareLinkResponse {
+    pub count: u64,
+}
+
+impl RevokeShareLinkResponse {
+    pub fn new(count: u64) -> Self {
+        RevokeShareLinkResponse { count }
+    }
+}
+
+// The read-only document of the public link, without the owner and the folder.
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct SharedDocumentResponse {
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub doc_type: Option<DocumentType>,
+    pub content: Option<String>,
+    pub update_time: Option<i64>,
+}
+
+impl From<Document> for SharedDocumentResponse {
+    fn from(document: Document) -> Self {
+        SharedDocumentResponse {
+            name: document.name,
+            doc_type: document.doc_type,
+            content: document.content,
+            update_time: document.base.update_time,
+        }
+    }
+}
@@ -0,0 +1,266 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use sqlx::{ FromRow, postgres::PgRow, sqlite::SqliteRow };
+use serde::{ Deserialize, Serialize };
+use validator::Validate;
+
+use super::{ BaseBean, ModuleBean, try_get_selected };
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct Tag {
+    #[serde(flatten)]
+    pub base: BaseBean,
+    // The owner user id, which stamped from the authenticated claims on insert.
+    #[schema(read_only = true)]
+    pub owner_uid: Option<i64>,
+    // The name is unique of per owner.
+    pub name: Option<String>,
+}
+
+impl Tag {
+    pub fn new(id: Option<i64>, name: Option<String>) -> Self {
+        Tag {
+            base: BaseBean::new_with_id(id),
+            owner_uid: None,
+            name,
+        }
+    }
+}
+
+impl ModuleBean for Tag {
+    fn base(&self) -> &BaseBean {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut BaseBean {
+        &mut self.base
+    }
+
+    fn owner_uid(&self) -> Option<i64> {
+        self.owner_uid
+    }
+}
+
+impl<'r> FromRow<'r, SqliteRow> for Tag {
+    fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+        Ok(Tag {
+            base: BaseBean::from_row(row).unwrap(),
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            name: try_get_selected(row, "name")?,
+        })
+    }
+}
+
+impl<'r> FromRow<'r, PgRow> for Tag {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Tag {
+            base: BaseBean::from_pg_row(row)?,
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            name: try_get_selected(row, "name")?,
+        })
+    }
+}
+
+// The relation of the document and the tag.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct DocumentTag {
+    #[serde(flatten)]
+    pub base: BaseBean,
+    #[schema(read_only = true)]
+    pub owner_uid: Option<i64>,
+    pub document_id: Option<i64>,
+    pub tag_id: Option<i64>,
+}
+
+impl DocumentTag {
+    pub fn new(document_id: i64, tag_id: i64) -> Self {
+        DocumentTag {
+            base: BaseBean::new_default(None),
+            owner_uid: None,
+            document_id: Some(document_id),
+            tag_id: Some(tag_id),
+        }
+    }
+
+    // The relation only with the present fields, for the query conditions.
+    pub fn with(document_id: Option<i64>, tag_id: Option<i64>) -> Self {
+        DocumentTag {
+            base: BaseBean::new_with_id(None),
+            owner_uid: None,
+            document_id,
+            tag_id,
+        }
+    }
+}
+
+impl ModuleBean for DocumentTag {
+    fn base(&self) -> &BaseBean {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut BaseBean {
+        &mut self.base
+    }
+
+    fn owner_uid(&self) -> Option<i64> {
+        self.owner_uid
+    }
+}
+
+impl<'r> FromRow<'r, SqliteRow> for DocumentTag {
+    fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+        Ok(DocumentTag {
+            base: BaseBean::from_row(row).unwrap(),
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            document_id: try_get_selected(row, "document_id")?,
+            tag_id: try_get_selected(row, "tag_id")?,
+        })
+    }
+}
+
+impl<'r> FromRow<'r, PgRow> for DocumentTag {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(DocumentTag {
+            base: BaseBean::from_pg_row(row)?,
+            owner_uid: try_get_selected(row, "owner_uid")?,
+            document_id: try_get_selected(row, "document_id")?,
+            tag_id: try_get_selected(row, "tag_id")?,
+        })
+    }
+}
+
+// Whether the documents are matched by any or all of the tags.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    #[default]
+    Any,
+    All,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QueryTagRequest {
+    #[validate(length(min = 1, max = 64))]
+    pub name: Option<String>,
+    // Only the tags of the document if present.
+    pub document_id: Option<i64>,
+}
+
+// The tag with the count of the tagged documents (exclusive of the trashed), such as for the sidebar.
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct TagCount {
+    #[serde(flatten)]
+    pub tag: Tag,
+    pub document_count: u64,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct QueryTagResponse {
+    pub data: Vec<TagCount>,
+}
+
+impl QueryTagResponse {
+    pub fn new(data: Vec<TagCount>) -> Self {
+        QueryTagResponse { data }
+    }
+}
+
+// Create the tag, or rename it if the id present.
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema)]
+pub struct SaveTagRequest {
+    pub id: Option<i64>,
+    #[validate(length(min = 1, max = 64))]
+    pub name: String,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct SaveTagResponse {
+    pub id: i64,
+}
+
+impl SaveTagResponse {
+    pub fn new(id: i64) -> Self {
+        SaveTagResponse { id }
+    }
+}
+
+// Merge the source tags into the target, the documents of the sources are tagged with the target
+// instead, and then the sources are deleted.
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema)]
+pub struct MergeTagRequest {
+    #[serde(rename = "sourceIds")]
+    #[validate(length(min = 1, max = 100))]
+    pub source_ids: Vec<i64>,
+    #[serde(rename = "targetId")]
+    pub target_id: i64,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct MergeTagResponse {
+    pub count: u64,
+}
+
+impl MergeTagResponse {
+    pub fn new(count: u64) -> Self {
+        MergeTagResponse { count }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema)]
+pub struct DeleteTagRequest {
+    pub id: i64,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct DeleteTagResponse {
+    pub count: u64,
+}
+
+impl DeleteTagResponse {
+    pub fn new(count: u64) -> Self {
+        DeleteTagResponse { count }
+    }
+}
+
+// Attach the tags to or detach them from the documents.
+#[derive(Deserialize, Clone, Debug, PartialEq, Validate, utoipa::ToSchema)]
+pub struct TagDocumentsRequest {
+    #[serde(rename = "documentIds")]
+    #[validate(length(min = 1, max = 1000))]
+    pub document_ids: Vec<i64>,
+    #[serde(rename = "tagIds")]
+    #[validate(length(min = 1, max = 100))]
+    pub tag_ids: Vec<i64>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct TagDocumentsResponse {
+    pub count: u64,
+}
+
+impl TagDocumentsResponse {
+    pub fn new(count: u64) -> Self {
+        TagDocumentsResponse { count }
+    }
+}
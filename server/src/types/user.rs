@@ -22,11 +22,11 @@
 
 use common_makestruct_macro::MakeStructWith;
 // use common_smartcpy_macro::SmartCopy; // TODO: compile error
-use sqlx::{ FromRow, sqlite::SqliteRow, Row };
+use sqlx::{ FromRow, postgres::PgRow, sqlite::SqliteRow };
 use serde::{ Deserialize, Serialize };
 use validator::Validate;
 
-use super::{ BaseBean, PageResponse };
+use super::{ BaseBean, ModuleBean, PageResponse, try_get_selected };
 
 // Manual impl for decode.
 // #[derive(Serialize, Deserialize, Clone, Debug, sqlx::sqlite::FromRow, sqlx::sqlite::Decode)]
@@ -74,25 +74,62 @@ impl Default for User {
     }
 }
 
+impl ModuleBean for User {
+    fn base(&self) -> &BaseBean {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut BaseBean {
+        &mut self.base
+    }
+
+    fn owner_uid(&self) -> Option<i64> {
+        self.base.id
+    }
+}
+
 impl<'r> FromRow<'r, SqliteRow> for User {
     fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
         Ok(User {
             base: BaseBean::from_row(row).unwrap(),
-            name: row.try_get("name")?,
-            email: row.try_get("email")?,
-            phone: row.try_get("phone")?,
-            password: row.try_get("password")?,
-            oidc_claims_sub: row.try_get("oidc_claims_sub")?,
-            oidc_claims_name: row.try_get("oidc_claims_name")?,
-            oidc_claims_email: row.try_get("oidc_claims_email")?,
-            github_claims_sub: row.try_get("github_claims_sub")?,
-            github_claims_name: row.try_get("github_claims_name")?,
-            github_claims_email: row.try_get("github_claims_email")?,
-            google_claims_sub: row.try_get("google_claims_sub")?,
-            google_claims_name: row.try_get("google_claims_name")?,
-            google_claims_email: row.try_get("google_claims_email")?,
-            ethers_address: row.try_get("ethers_address")?,
-            lang: row.try_get("lang")?,
+            name: try_get_selected(row, "name")?,
+            email: try_get_selected(row, "email")?,
+            phone: try_get_selected(row, "phone")?,
+            password: try_get_selected(row, "password")?,
+            oidc_claims_sub: try_get_selected(row, "oidc_claims_sub")?,
+            oidc_claims_name: try_get_selected(row, "oidc_claims_name")?,
+            oidc_claims_email: try_get_selected(row, "oidc_claims_email")?,
+            github_claims_sub: try_get_selected(row, "github_claims_sub")?,
+            github_claims_name: try_get_selected(row, "github_claims_name")?,
+            github_claims_email: try_get_selected(row, "github_claims_email")?,
+            google_claims_sub: try_get_selected(row, "google_claims_sub")?,
+            google_claims_name: try_get_selected(row, "google_claims_name")?,
+            google_claims_email: try_get_selected(row, "google_claims_email")?,
+            ethers_address: try_get_selected(row, "ethers_address")?,
+            lang: try_get_selected(row, "lang")?,
+        })
+    }
+}
+
+impl<'r> FromRow<'r, PgRow> for User {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(User {
+            base: BaseBean::from_pg_row(row)?,
+            name: try_get_selected(row, "name")?,
+            email: try_get_selected(row, "email")?,
+            phone: try_get_selected(row, "phone")?,
+            password: try_get_selected(row, "password")?,
+            oidc_claims_sub: try_get_selected(row, "oidc_claims_sub")?,
+            oidc_claims_name: try_get_selected(row, "oidc_claims_name")?,
+            oidc_claims_email: try_get_selected(row, "oidc_claims_email")?,
+            github_claims_sub: try_get_selected(row, "github_claims_sub")?,
+            github_claims_name: try_get_selected(row, "github_claims_name")?,
+            github_claims_email: try_get_selected(row, "github_claims_email")?,
+            google_claims_sub: try_get_selected(row, "google_claims_sub")?,
+            google_claims_name: try_get_selected(row, "google_claims_name")?,
+            google_claims_email: try_get_selected(row, "google_claims_email")?,
+            ethers_address: try_get_selected(row, "ethers_address")?,
+            lang: try_get_selected(row, "lang")?,
         })
     }
 }
@@ -147,7 +184,7 @@ pub struct QueryUserRequest {
 impl QueryUserRequest {
     pub fn to_user(&self) -> User {
         User {
-            base: BaseBean::new(None, None, None),
+            base: BaseBean::new_with_id(None),
             name: Some(self.name.clone().unwrap_or_default()),
             email: Some(self.email.clone().unwrap_or_default()),
             phone: self.phone.clone(),
@@ -20,8 +20,7 @@
  * This includes modifications and derived works.
  */
 
-use lazy_static::lazy_static;
-use std::{ collections::HashMap, sync::Arc };
+use std::{ collections::HashMap, future::Future, sync::Arc };
 
 use axum::body::Body;
 use chrono::{ Duration, Utc };
@@ -29,7 +28,6 @@ use hyper::{ HeaderMap, Response, StatusCode };
 use jsonwebtoken::{ decode, encode, DecodingKey, EncodingKey, Header, Validation };
 use serde::{ Deserialize, Serialize };
 use tower_cookies::cookie::Cookie;
-use tokio::sync::RwLock;
 
 use crate::{
     config::config_serve::WebServeConfig,
@@ -38,11 +36,6 @@ use crate::{
     utils::webs,
 };
 
-lazy_static! {
-    // singleton instance.
-    static ref SECURITY_CONTEXT: Arc<SecurityContext> = Arc::new(SecurityContext::new());
-}
-
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AuthUserClaims {
     pub ptype: PrincipalType,
@@ -53,6 +46,20 @@ pub struct AuthUserClaims {
     pub ext: Option<HashMap<String, String>>,
 }
 
+impl AuthUserClaims {
+    // The claims of background jobs acting on behalf of the owner user.
+    pub fn system(uid: i64) -> Self {
+        AuthUserClaims {
+            ptype: PrincipalType::System,
+            uid,
+            uname: "system".to_string(),
+            email: "system".to_string(),
+            exp: 0,
+            ext: None,
+        }
+    }
+}
+
 pub fn create_jwt(
     config: &Arc<WebServeConfig>,
     ptype: &PrincipalType,
@@ -184,79 +191,53 @@ pub fn join_context_path(config: &WebServeConfig, path: String) -> String {
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct SecurityContext {
-    pub current_user: Arc<RwLock<Option<AuthUserClaims>>>,
+tokio::task_local! {
+    // The authenticated user of the current request, which bound by the auth middleware per request task.
+    static CURRENT_USER: Option<AuthUserClaims>;
 }
 
-impl SecurityContext {
-    pub fn new() -> Self {
-        SecurityContext {
-            current_user: Arc::new(RwLock::new(None)),
-        }
-    }
+// The request scoped security context, see: routes/auths.rs#auth_middleware()
+// Notice: The task local isn't propagated into the spawned tasks, which should be re-scoped if needs.
+pub struct SecurityContext;
 
-    pub fn get_instance() -> Arc<SecurityContext> {
-        SECURITY_CONTEXT.clone()
-    }
-
-    pub async fn bind(&self, user: Option<AuthUserClaims>) {
+impl SecurityContext {
+    // Runs the future as the given user, such as the authenticated request or the background job.
+    pub async fn scope<F: Future>(user: Option<AuthUserClaims>, f: F) -> F::Output {
         tracing::debug!("Binding from user: {:?}", user);
-        match user {
-            Some(user) => {
-                // Notice: 必须在此函数中执行 write() 获取写锁, 若在外部 routes/auths.rs#auth_middleware() 中获取写锁,
-                // 则当在 routes/users.rs#handle_query_users() 中获取读锁时会产生死锁, 因为 RwLock 的释放机制是超出作用域自动释放,
-                // 在 auth_middleware() 中写锁的生命周期包含了 handle_query_users() 即没有释放.
-                let mut current_user = self.current_user.write().await;
-                *current_user = Some(user);
-            }
-            None => {}
-        }
-        tracing::debug!("Binded from user: {:?}", self.get().await);
+        CURRENT_USER.scope(user, f).await
     }
 
-    pub async fn get(&self) -> Option<AuthUserClaims> {
-        match self.current_user.try_read() {
-            Ok(read_guard) => read_guard.clone(),
-            Err(e) => {
-                tracing::error!("Unable to acquire read lock. reason: {:?}", e);
-                None
-            }
-        }
+    pub fn get() -> Option<AuthUserClaims> {
+        CURRENT_USER.try_with(|user| user.clone()).ok().flatten()
     }
 
-    pub async fn get_current_uid(&self) -> Option<i64> {
-        match self.get().await {
+    pub fn get_current_uid() -> Option<i64> {
+        match Self::get() {
             Some(claims) => Some(claims.uid),
             None => {
-                tracing::error!("No found current user claims sub.");
+                tracing::debug!("No found current user claims sub.");
                 None
             }
         }
     }
 
-    pub async fn get_current_uname(&self) -> Option<String> {
-        match self.get().await {
+    pub fn get_current_uname() -> Option<String> {
+        match Self::get() {
             Some(claims) => Some(claims.uname),
             None => {
-                tracing::error!("No found current user claims uname.");
+                tracing::debug!("No found current user claims uname.");
                 None
             }
         }
     }
 
-    pub async fn get_current_email(&self) -> Option<String> {
-        match self.get().await {
+    pub fn get_current_email() -> Option<String> {
+        match Self::get() {
             Some(claims) => Some(claims.email),
             None => {
-                tracing::error!("No found current user claims email.");
+                tracing::debug!("No found current user claims email.");
                 None
             }
         }
     }
-
-    pub async fn clear(&self) {
-        let mut write_guard = self.current_user.write().await;
-        *write_guard = None;
-    }
 }
@@ -0,0 +1,1014 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use std::collections::{ HashMap, HashSet };
+
+use anyhow::Error;
+use base64::{ engine::general_purpose::STANDARD, Engine as _ };
+use chrono::Utc;
+use serde_json::{ json, Map, Value };
+
+use crate::errors::BizError;
+use crate::types::board::BoardFormat;
+
+// The board content is the excalidraw scene, which is also the format of export.
+pub const EXCALIDRAW_TYPE: &str = "excalidraw";
+pub const EXCALIDRAW_VERSION: i64 = 2;
+pub const EXCALIDRAW_SOURCE: &str = "https://github.com/wl4g/my-webnote";
+pub const EXCALIDRAW_MIME_TYPE: &str = "application/vnd.excalidraw+json";
+
+const ELEMENT_TYPES: [&str; 12] = [
+    "rectangle",
+    "ellipse",
+    "diamond",
+    "text",
+    "arrow",
+    "line",
+    "freedraw",
+    "image",
+    "frame",
+    "magicframe",
+    "embeddable",
+    "iframe",
+];
+const LINEAR_ELEMENT_TYPES: [&str; 3] = ["arrow", "line", "freedraw"];
+// The persisted keys of the app state, the others (such as the selection and collaborators) are transient.
+const APP_STATE_KEYS: [&str; 5] = ["viewBackgroundColor", "gridSize", "gridStep", "gridModeEnabled", "theme"];
+
+// The tldraw pages are stacked vertically with the gap, since the excalidraw scene is a single canvas.
+const TLDRAW_PAGE_GAP: f64 = 200.0;
+const TLDRAW_NOTE_SIZE: f64 = 200.0;
+
+fn invalid(message: impl Into<String>) -> Error {
+    BizError::BadRequest(message.into()).into()
+}
+
+// Detect the format by the content, the tldraw file has the 'tldrawFileFormatVersion'.
+pub fn detect_format(file: &Value) -> Option<BoardFormat> {
+    if file.get("tldrawFileFormatVersion").is_some() {
+        Some(BoardFormat::Tldraw)
+    } else if file.get("type").and_then(Value::as_str) == Some(EXCALIDRAW_TYPE) || file.get("elements").is_some() {
+        Some(BoardFormat::Excalidraw)
+    } else {
+        None
+    }
+}
+
+// Parse the excalidraw or tldraw file to the normalized excalidraw scene, which is detected by the
+// content if the format is absent.
+pub fn to_scene(content: &[u8], format: Option<BoardFormat>) -> Result<Value, Error> {
+    let file: Value = serde_json
+        ::from_slice(content)
+        .map_err(|e| invalid(format!("The board file is not a valid JSON. reason: {}", e)))?;
+    let detected = detect_format(&file);
+    if format.is_some() && detected.is_some() && format != detected {
+        return Err(invalid(format!("The board file isn't the format of the {:?}", format.unwrap())));
+    }
+    match format.or(detected) {
+        Some(BoardFormat::Excalidraw) => normalize_excalidraw(file),
+        Some(BoardFormat::Tldraw) => from_tldraw(&file),
+        None => Err(invalid("Unknown the board file format, which must be the excalidraw or tldraw")),
+    }
+}
+
+// Validate and normalize the excalidraw scene, which the deleted elements, the unreferenced files and
+// the transient app state are dropped.
+pub fn normalize_excalidraw(scene: Value) -> Result<Value, Error> {
+    let Value::Object(mut scene) = scene else {
+        return Err(invalid("The excalidraw scene must be an object"));
+    };
+    if let Some(kind) = scene.get("type") {
+        if kind.as_str() != Some(EXCALIDRAW_TYPE) {
+            return Err(invalid(format!("Unsupported the excalidraw scene type: {}", kind)));
+        }
+    }
+
+    let elements = match scene.remove("elements") {
+        Some(Value::Array(elements)) => elements,
+        None | Some(Value::Null) => Vec::new(),
+        Some(_) => {
+            return Err(invalid("The elements of the excalidraw scene must be an array"));
+        }
+    };
+    let mut ids = HashSet::new();
+    let mut normalized = Vec::with_capacity(elements.len());
+    for (i, element) in elements.into_iter().enumerate() {
+        let element = validate_element(element).map_err(|reason|
+            invalid(format!("Invalid the element at {}, {}", i, reason))
+        )?;
+        if element.get("isDeleted").and_then(Value::as_bool).unwrap_or(false) {
+            continue;
+        }
+        let id = element["id"].as_str().unwrap_or_default().to_string();
+        if !ids.insert(id.to_owned()) {
+            return Err(invalid(format!("Duplicated the element id: {}", id)));
+        }
+        normalized.push(Value::Object(element));
+    }
+
+    // Only the files of the images are kept.
+    let file_ids: HashSet<&str> = normalized
+        .iter()
+        .filter_map(|e| e.get("fileId").and_then(Value::as_str))
+        .collect();
+    let mut files = Map::new();
+    match scene.remove("files") {
+        Some(Value::Object(map)) => {
+            for (id, file) in map {
+                if file_ids.contains(id.as_str()) {
+                    let file = validate_file(&id, file).map_err(|reason|
+                        invalid(format!("Invalid the file {}, {}", id, reason))
+                    )?;
+                    files.insert(id, file);
+                }
+            }
+        }
+        None | Some(Value::Null) => {}
+        Some(_) => {
+            return Err(invalid("The files of the excalidraw scene must be an object"));
+        }
+    }
+
+    let mut app_state = Map::new();
+    if let Some(Value::Object(state)) = scene.remove("appState") {
+        for (key, value) in state {
+            if APP_STATE_KEYS.contains(&key.as_str()) && !value.is_null() {
+                app_state.insert(key, value);
+            }
+        }
+    }
+
+    Ok(
+        json!({
+            "type": EXCALIDRAW_TYPE,
+            "version": EXCALIDRAW_VERSION,
+            "source": EXCALIDRAW_SOURCE,
+            "elements": normalized,
+            "appState": app_state,
+            "files": files,
+        })
+    )
+}
+
+fn is_number(value: Option<&Value>) -> bool {
+    value.and_then(Value::as_f64).map_or(false, f64::is_finite)
+}
+
+fn validate_element(element: Value) -> Result<Map<String, Value>, String> {
+    let Value::Object(element) = element else {
+        return Err("which must be an object".to_string());
+    };
+    if element.get("id").and_then(Value::as_str).map_or(true, str::is_empty) {
+        return Err("the id is required".to_string());
+    }
+    let kind = element.get("type").and_then(Value::as_str).unwrap_or_default();
+    if !ELEMENT_TYPES.contains(&kind) {
+        return Err(format!("unsupported the type '{}'", kind));
+    }
+    if !is_number(element.get("x")) || !is_number(element.get("y")) {
+        return Err("the x and y must be the numbers".to_string());
+    }
+    for key in ["width", "height", "angle"] {
+        if element.get(key).map_or(false, |v| !is_number(Some(v))) {
+            return Err(format!("the {} must be a number", key));
+        }
+    }
+    if LINEAR_ELEMENT_TYPES.contains(&kind) {
+        let valid = element
+            .get("points")
+            .and_then(Value::as_array)
+            .map_or(false, |points| {
+                points.iter().all(|p| {
+                    p.as_array().map_or(false, |p| p.len() == 2 && is_number(p.first()) && is_number(p.get(1)))
+                })
+            });
+        if !valid {
+            return Err("the points must be the array of [x, y]".to_string());
+        }
+    }
+    if kind == "text" && !element.get("text").map_or(false, Value::is_string) {
+        return Err("the text is required".to_string());
+    }
+    Ok(element)
+}
+
+fn validate_file(id: &str, file: Value) -> Result<Value, String> {
+    let Value::Object(mut file) = file else {
+        return Err("which must be an object".to_string());
+    };
+    let data_url = file.get("dataURL").and_then(Value::as_str).unwrap_or_default().to_string();
+    if data_url.is_empty() {
+        return Err("the dataURL is required".to_string());
+    }
+    if !file.get("mimeType").map_or(false, Value::is_string) {
+        let mime_type = data_url
+            .strip_prefix("data:")
+            .and_then(|url| url.split_once(';'))
+            .map(|(mime_type, _)| mime_type.to_string())
+            .ok_or_else(|| "the mimeType is required".to_string())?;
+        file.insert("mimeType".to_string(), Value::String(mime_type));
+    }
+    file.insert("id".to_string(), Value::String(id.to_string()));
+    file.entry("created").or_insert_with(|| Value::from(Utc::now().timestamp_millis()));
+    Ok(Value::Object(file))
+}
+
+// Merge the updated elements into the scene by the ids, as the reconciliation of the excalidraw collaboration,
+// the higher 'version' wins, and the lower 'versionNonce' wins for the same version. The deleted elements are
+// kept as the tombstones, so that the deletions are also propagated. Returns the elements accepted, and the
+// invalid are skipped.
+pub fn reconcile_elements(scene: &mut Value, updates: Vec<Value>) -> Vec<Value> {
+    let Some(elements) = scene.get_mut("elements").and_then(Value::as_array_mut) else {
+        return Vec::new();
+    };
+    let mut indexes: HashMap<String, usize> = elements
+        .iter()
+        .enumerate()
+        .filter_map(|(i, e)| e.get("id").and_then(Value::as_str).map(|id| (id.to_string(), i)))
+        .collect();
+    let mut accepted = Vec::new();
+    for update in updates {
+        let Ok(update) = validate_element(update) else {
+            continue;
+        };
+        let id = update["id"].as_str().unwrap_or_default().to_string();
+        let update = Value::Object(update);
+        match indexes.get(&id) {
+            Some(&i) if !supersedes(&update, &elements[i]) => {
+                continue;
+            }
+            Some(&i) => {
+                elements[i] = update.clone();
+            }
+            None => {
+                indexes.insert(id, elements.len());
+                elements.push(update.clone());
+            }
+        }
+        accepted.push(update);
+    }
+    accepted
+}
+
+fn supersedes(update: &Value, current: &Value) -> bool {
+    let version = |e: &Value| e.get("version").and_then(Value::as_i64).unwrap_or_default();
+    let nonce = |e: &Value| e.get("versionNonce").and_then(Value::as_i64).unwrap_or_default();
+    match version(update).cmp(&version(current)) {
+        std::cmp::Ordering::Greater => true,
+        std::cmp::Ordering::Equal => nonce(update) < nonce(current),
+        std::cmp::Ordering::Less => false,
+    }
+}
+
+// Decode the base64 data URL, returns the mime type and the bytes, i.e: 'data:image/png;base64,<data>'
+pub fn decode_data_url(url: &str) -> Option<(String, Vec<u8>)> {
+    let (meta, data) = url.strip_prefix("data:")?.split_once(',')?;
+    let mime_type = meta.strip_suffix(";base64")?;
+    Some((mime_type.to_string(), STANDARD.decode(data).ok()?))
+}
+
+pub fn encode_data_url(mime_type: &str, data: &[u8]) -> String {
+    format!("data:{};base64,{}", mime_type, STANDARD.encode(data))
+}
+
+// The stable pseudo random seed of the element id, which is used by excalidraw to draw the rough shapes.
+fn seed_of(id: &str) -> i64 {
+    // FNV-1a
+    let hash = id.bytes().fold(0x811c9dc5_u32, |hash, b| (hash ^ (b as u32)).wrapping_mul(0x01000193));
+    ((hash & 0x7fffffff) as i64).max(1)
+}
+
+fn new_element(id: &str, kind: &str, x: f64, y: f64, width: f64, height: f64) -> Map<String, Value> {
+    let element = json!({
+        "id": id,
+        "type": kind,
+        "x": x,
+        "y": y,
+        "width": width,
+        "height": height,
+        "angle": 0,
+        "strokeColor": "#1e1e1e",
+        "backgroundColor": "transparent",
+        "fillStyle": "solid",
+        "strokeWidth": 2,
+        "strokeStyle": "solid",
+        "roughness": 1,
+        "opacity": 100,
+        "groupIds": [],
+        "frameId": null,
+        "roundness": null,
+        "seed": seed_of(id),
+        "version": 1,
+        "versionNonce": seed_of(&format!("{}:nonce", id)),
+        "isDeleted": false,
+        "boundElements": null,
+        "updated": Utc::now().timestamp_millis(),
+        "link": null,
+        "locked": false,
+    });
+    let Value::Object(element) = element else {
+        unreachable!();
+    };
+    element
+}
+
+// The estimated size of the text, since the fonts can't be measured on the server.
+fn new_text(id: &str, x: f64, y: f64, text: &str, font_size: f64, font_family: i64) -> Map<String, Value> {
+    let columns = text
+        .lines()
+        .map(|l| l.chars().count())
+        .max()
+        .unwrap_or_default();
+    let rows = text.lines().count().max(1);
+    let (width, height) = ((columns as f64) * font_size * 0.6, (rows as f64) * font_size * 1.25);
+    let mut element = new_element(id, "text", x, y, width, height);
+    element.insert("text".to_string(), Value::from(text));
+    element.insert("originalText".to_string(), Value::from(text));
+    element.insert("fontSize".to_string(), Value::from(font_size));
+    element.insert("fontFamily".to_string(), Value::from(font_family));
+    element.insert("textAlign".to_string(), Value::from("left"));
+    element.insert("verticalAlign".to_string(), Value::from("top"));
+    element.insert("containerId".to_string(), Value::Null);
+    element.insert("lineHeight".to_string(), Value::from(1.25));
+    element
+}
+
+// The colors of the tldraw default theme, which are the stroke and the fill.
+fn tldraw_color(name: &str) -> (&'static str, &'static str) {
+    match name {
+        "grey" => ("#9fa8b2", "#eceef0"),
+        "light-violet" => ("#e085f4", "#f5eafa"),
+        "violet" => ("#ae3ec9", "#ecdcf2"),
+        "blue" => ("#4465e9", "#dce1f8"),
+        "light-blue" => ("#4ba1f1", "#ddedfa"),
+        "yellow" => ("#f1ac4b", "#f9f0e6"),
+        "orange" => ("#e16919", "#f8e2d4"),
+        "green" => ("#099268", "#d3e9e3"),
+        "light-green" => ("#4cb05e", "#dbf0e0"),
+        "light-red" => ("#f87777", "#f4dadb"),
+        "red" => ("#e03131", "#f4dadb"),
+        "white" => ("#ffffff", "#ffffff"),
+        _ => ("#1d1d1d", "#e8e8e8"),
+    }
+}
+
+fn tldraw_font_size(size: &str) -> f64 {
+    match size {
+        "s" => 18.0,
+        "l" => 36.0,
+        "xl" => 44.0,
+        _ => 24.0,
+    }
+}
+
+fn tldraw_font_family(font: &str) -> i64 {
+    match font {
+        "draw" => 1,
+        "mono" => 3,
+        _ => 2,
+    }
+}
+
+fn tldraw_arrowhead(arrowhead: &str) -> Value {
+    match arrowhead {
+        "none" => Value::Null,
+        "triangle" | "dot" | "bar" => Value::from(arrowhead),
+        _ => Value::from("arrow"),
+    }
+}
+
+// The plain text of the shape, which is the 'text' of the tldraw v2, or the 'richText' (the tiptap
+// document) of the later.
+fn tldraw_text(props: &Value) -> String {
+    fn collect(node: &Value, lines: &mut Vec<String>) {
+        if let Some(text) = node.get("text").and_then(Value::as_str) {
+            if let Some(line) = lines.last_mut() {
+                line.push_str(text);
+            }
+        }
+        if let Some(children) = node.get("content").and_then(Value::as_array) {
+            for child in children {
+                if child.get("type").and_then(Value::as_str) == Some("paragraph") {
+                    lines.push(String::new());
+                }
+                collect(child, lines);
+            }
+        }
+    }
+    if let Some(text) = props.get("text").and_then(Value::as_str) {
+        return text.to_string();
+    }
+    let mut lines = vec![String::new()];
+    if let Some(rich_text) = props.get("richText") {
+        collect(rich_text, &mut lines);
+    }
+    lines
+        .into_iter()
+        .skip_while(|line| line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn short_id(id: &str) -> String {
+    id.split_once(':')
+        .map(|(_, id)| id)
+        .unwrap_or(id)
+        .to_string()
+}
+
+fn num(value: &Value, key: &str) -> f64 {
+    value
+        .get(key)
+        .and_then(Value::as_f64)
+        .filter(|v| v.is_finite())
+        .unwrap_or_default()
+}
+
+fn num_of(element: &Map<String, Value>, key: &str) -> f64 {
+    element.get(key).and_then(Value::as_f64).unwrap_or_default()
+}
+
+fn prop<'a>(props: &'a Value, key: &str, default: &'a str) -> &'a str {
+    props.get(key).and_then(Value::as_str).unwrap_or(default)
+}
+
+struct TldrawConverter<'a> {
+    records: HashMap<&'a str, &'a Value>,
+    // The shapes of the parent (page or shape) id, in order of the index.
+    children: HashMap<&'a str, Vec<&'a Value>>,
+    elements: Vec<Value>,
+    files: Map<String, Value>,
+}
+
+impl<'a> TldrawConverter<'a> {
+    fn new(records: &'a [Value]) -> Self {
+        let mut by_id = HashMap::new();
+        let mut children: HashMap<&str, Vec<&Value>> = HashMap::new();
+        for record in records {
+            if let Some(id) = record.get("id").and_then(Value::as_str) {
+                by_id.insert(id, record);
+            }
+            if record.get("typeName").and_then(Value::as_str) == Some("shape") {
+                let parent_id = record.get("parentId").and_then(Value::as_str).unwrap_or_default();
+                children.entry(parent_id).or_default().push(record);
+            }
+        }
+        for shapes in children.values_mut() {
+            shapes.sort_by_key(|shape| prop(shape, "index", ""));
+        }
+        TldrawConverter { records: by_id, children, elements: Vec::new(), files: Map::new() }
+    }
+
+    // The position of the shape on the page, the position of the shape is relative to the parent.
+    fn page_position(&self, shape: &Value) -> (f64, f64) {
+        let (mut x, mut y) = (num(shape, "x"), num(shape, "y"));
+        let mut parent_id = prop(shape, "parentId", "");
+        // Notice: The depth is limited in case of the cyclic parents of the broken file.
+        for _ in 0..64 {
+            match self.records.get(parent_id) {
+                Some(parent) if prop(parent, "typeName", "") == "shape" => {
+                    x += num(parent, "x");
+                    y += num(parent, "y");
+                    parent_id = prop(parent, "parentId", "");
+                }
+                _ => break,
+            }
+        }
+        (x, y)
+    }
+
+    fn convert_children(&mut self, parent_id: &str, origin: (f64, f64), group_ids: &[String], frame_id: Option<&str>) {
+        let shapes = self.children.get(parent_id).cloned().unwrap_or_default();
+        for shape in shapes {
+            self.convert_shape(shape, origin, group_ids, frame_id);
+        }
+    }
+
+    fn convert_shape(&mut self, shape: &Value, origin: (f64, f64), group_ids: &[String], frame_id: Option<&str>) {
+        let id = short_id(prop(shape, "id", ""));
+        let (x, y) = (origin.0 + num(shape, "x"), origin.1 + num(shape, "y"));
+        let props = shape.get("props").unwrap_or(&Value::Null);
+        let (w, h) = (num(props, "w"), num(props, "h"));
+        let (mut stroke, fill) = tldraw_color(prop(props, "color", "black"));
+
+        let mut label = None;
+        let mut element = match prop(shape, "type", "") {
+            "group" => {
+                // The group ids of excalidraw are in order of the innermost first.
+                let group_ids: Vec<String> = std::iter::once(id).chain(group_ids.iter().cloned()).collect();
+                self.convert_children(prop(shape, "id", ""), (x, y), &group_ids, frame_id);
+                return;
+            }
+            "frame" => {
+                let mut element = new_element(&id, "frame", x, y, w, h);
+                element.insert("name".to_string(), Value::from(prop(props, "name", "")));
+                self.push(element, shape, group_ids, frame_id);
+                self.convert_children(prop(shape, "id", ""), (x, y), group_ids, Some(&id));
+                return;
+            }
+            "geo" => {
+                let kind = match prop(props, "geo", "rectangle") {
+                    kind @ ("ellipse" | "diamond") => kind,
+                    _ => "rectangle",
+                };
+                let h = h + num(props, "growY");
+                let mut element = new_element(&id, kind, x, y, w, h);
+                match prop(props, "fill", "none") {
+                    "none" => {}
+                    fill_style => {
+                        element.insert("backgroundColor".to_string(), Value::from(fill));
+                        let fill_style = if fill_style == "pattern" { "hachure" } else { "solid" };
+                        element.insert("fillStyle".to_string(), Value::from(fill_style));
+                    }
+                }
+                label = Some(tldraw_text(props));
+                element
+            }
+            "note" => {
+                let h = TLDRAW_NOTE_SIZE + num(props, "growY");
+                let mut element = new_element(&id, "rectangle", x, y, TLDRAW_NOTE_SIZE, h);
+                element.insert("backgroundColor".to_string(), Value::from(fill));
+                stroke = "transparent";
+                label = Some(tldraw_text(props));
+                element
+            }
+            "text" => {
+                let font_size = tldraw_font_size(prop(props, "size", "m"));
+                let font_family = tldraw_font_family(prop(props, "font", "draw"));
+                new_text(&id, x, y, &tldraw_text(props), font_size, font_family)
+            }
+            "arrow" => {
+                let start = self.arrow_terminal(props.get("start"), (x, y));
+                let end = self.arrow_terminal(props.get("end"), (x, y));
+                let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+                let mut element = new_element(&id, "arrow", x + start.0, y + start.1, dx.abs(), dy.abs());
+                element.insert("points".to_string(), json!([[0.0, 0.0], [dx, dy]]));
+                element.insert("startArrowhead".to_string(), tldraw_arrowhead(prop(props, "arrowheadStart", "none")));
+                element.insert("endArrowhead".to_string(), tldraw_arrowhead(prop(props, "arrowheadEnd", "arrow")));
+                label = Some(tldraw_text(props));
+                element
+            }
+            "line" => {
+                let points = Self::line_points(props);
+                if points.len() < 2 {
+                    return;
+                }
+                self.new_linear(&id, "line", (x, y), &points)
+            }
+            kind @ ("draw" | "highlight") => {
+                let points: Vec<(f64, f64)> = props
+                    .get("segments")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|segment| segment.get("points").and_then(Value::as_array))
+                    .flatten()
+                    .map(|p| (num(p, "x"), num(p, "y")))
+                    .collect();
+                if points.is_empty() {
+                    return;
+                }
+                let mut element = self.new_linear(&id, "freedraw", (x, y), &points);
+                element.insert("pressures".to_string(), json!([]));
+                element.insert("simulatePressure".to_string(), Value::from(true));
+                if kind == "highlight" {
+                    element.insert("opacity".to_string(), Value::from(50));
+                }
+                element
+            }
+            "image" => {
+                let asset = props
+                    .get("assetId")
+                    .and_then(Value::as_str)
+                    .and_then(|asset_id| self.records.get(asset_id).copied());
+                let Some(asset) = asset else {
+                    return;
+                };
+                let asset_props = asset.get("props").unwrap_or(&Value::Null);
+                let src = prop(asset_props, "src", "");
+                if src.is_empty() {
+                    return;
+                }
+                let file_id = short_id(prop(asset, "id", ""));
+                let mut file = json!({ "id": file_id, "dataURL": src, "created": Utc::now().timestamp_millis() });
+                if let Some(mime_type) = asset_props.get("mimeType").filter(|v| v.is_string()) {
+                    file["mimeType"] = mime_type.clone();
+                }
+                self.files.insert(file_id.to_owned(), file);
+                let mut element = new_element(&id, "image", x, y, w, h);
+                element.insert("fileId".to_string(), Value::from(file_id));
+                element.insert("status".to_string(), Value::from("saved"));
+                element.insert("scale".to_string(), json!([1, 1]));
+                element
+            }
+            "embed" | "bookmark" | "video" => {
+                let mut element = new_element(&id, "rectangle", x, y, w, h);
+                element.insert("link".to_string(), props.get("url").cloned().unwrap_or(Value::Null));
+                element
+            }
+            kind => {
+                tracing::debug!("Skip convert the unsupported tldraw shape: {}", kind);
+                return;
+            }
+        };
+
+        element.insert("strokeColor".to_string(), Value::from(stroke));
+        if let Some(width) = match prop(props, "size", "m") {
+            "s" => Some(1),
+            "l" => Some(4),
+            "xl" => Some(6),
+            _ => None,
+        } {
+            element.insert("strokeWidth".to_string(), Value::from(width));
+        }
+        if let style @ ("dashed" | "dotted") = prop(props, "dash", "draw") {
+            element.insert("strokeStyle".to_string(), Value::from(style));
+        }
+
+        // The label is the text bound to the container, which is centered.
+        let label = label.filter(|label| !label.is_empty());
+        let label_id = format!("{}-label", id);
+        if label.is_some() {
+            element.insert("boundElements".to_string(), json!([{ "type": "text", "id": label_id }]));
+        }
+        let (cx, cy) = (num_of(&element, "x"), num_of(&element, "y"));
+        let (ew, eh) = (num_of(&element, "width"), num_of(&element, "height"));
+        self.push(element, shape, group_ids, frame_id);
+        if let Some(label) = label {
+            let font_size = tldraw_font_size(prop(props, "size", "m"));
+            let font_family = tldraw_font_family(prop(props, "font", "draw"));
+            let mut text = new_text(&label_id, 0.0, 0.0, &label, font_size, font_family);
+            let (tw, th) = (num_of(&text, "width"), num_of(&text, "height"));
+            text.insert("x".to_string(), Value::from(cx + (ew - tw) / 2.0));
+            text.insert("y".to_string(), Value::from(cy + (eh - th) / 2.0));
+            text.insert("textAlign".to_string(), Value::from("center"));
+            text.insert("verticalAlign".to_string(), Value::from("middle"));
+            text.insert("containerId".to_string(), Value::from(id));
+            self.push(text, &json!({}), group_ids, frame_id);
+        }
+    }
+
+    fn push(&mut self, mut element: Map<String, Value>, shape: &Value, group_ids: &[String], frame_id: Option<&str>) {
+        if let Some(rotation) = shape.get("rotation").filter(|v| is_number(Some(v))) {
+            element.insert("angle".to_string(), rotation.clone());
+        }
+        if let Some(opacity) = shape.get("opacity").and_then(Value::as_f64) {
+            let opacity = element["opacity"].as_f64().unwrap_or(100.0) * opacity.clamp(0.0, 1.0);
+            element.insert("opacity".to_string(), Value::from(opacity.round() as i64));
+        }
+        if shape.get("isLocked").and_then(Value::as_bool).unwrap_or(false) {
+            element.insert("locked".to_string(), Value::from(true));
+        }
+        element.insert("groupIds".to_string(), json!(group_ids));
+        element.insert("frameId".to_string(), json!(frame_id));
+        self.elements.push(Value::Object(element));
+    }
+
+    // The point of the arrow terminal relative to the arrow, which is the center of the bound shape if
+    // the point is absent.
+    fn arrow_terminal(&self, terminal: Option<&Value>, origin: (f64, f64)) -> (f64, f64) {
+        let Some(terminal) = terminal else {
+            return (0.0, 0.0);
+        };
+        if is_number(terminal.get("x")) && is_number(terminal.get("y")) {
+            return (num(terminal, "x"), num(terminal, "y"));
+        }
+        let bound = terminal
+            .get("boundShapeId")
+            .and_then(Value::as_str)
+            .and_then(|id| self.records.get(id));
+        match bound {
+            Some(bound) => {
+                let (x, y) = self.page_position(bound);
+                let props = bound.get("props").unwrap_or(&Value::Null);
+                (x + num(props, "w") / 2.0 - origin.0, y + num(props, "h") / 2.0 - origin.1)
+            }
+            None => (0.0, 0.0),
+        }
+    }
+
+    // The points of the line, which are the map of the 'points' (or the 'handles' of the early) in
+    // order of the index, or the array.
+    fn line_points(props: &Value) -> Vec<(f64, f64)> {
+        let points = props.get("points").or_else(|| props.get("handles"));
+        let mut points: Vec<&Value> = match points {
+            Some(Value::Array(points)) => points.iter().collect(),
+            Some(Value::Object(points)) => {
+                let mut points: Vec<&Value> = points.values().collect();
+                points.sort_by_key(|p| prop(p, "index", ""));
+                points
+            }
+            _ => Vec::new(),
+        };
+        points.retain(|p| is_number(p.get("x")) && is_number(p.get("y")));
+        points
+            .into_iter()
+            .map(|p| (num(p, "x"), num(p, "y")))
+            .collect()
+    }
+
+    // The linear element of the points, which are relative to the first one.
+    fn new_linear(&self, id: &str, kind: &str, origin: (f64, f64), points: &[(f64, f64)]) -> Map<String, Value> {
+        let (x0, y0) = points[0];
+        let relative: Vec<Value> = points
+            .iter()
+            .map(|(x, y)| json!([x - x0, y - y0]))
+            .collect();
+        let (min_x, max_x) = points.iter().fold((f64::MAX, f64::MIN), |(min, max), p| (min.min(p.0), max.max(p.0)));
+        let (min_y, max_y) = points.iter().fold((f64::MAX, f64::MIN), |(min, max), p| (min.min(p.1), max.max(p.1)));
+        let mut element = new_element(id, kind, origin.0 + x0, origin.1 + y0, max_x - min_x, max_y - min_y);
+        element.insert("points".to_string(), Value::Array(relative));
+        element
+    }
+}
+
+// Convert the tldraw file (the '.tldr' of the tldraw v2 or later) to the excalidraw scene, and the
+// unsupported shapes are skipped.
+pub fn from_tldraw(file: &Value) -> Result<Value, Error> {
+    if !is_number(file.get("tldrawFileFormatVersion")) {
+        return Err(invalid("Unsupported the tldraw file, which must be the '.tldr' of the tldraw v2 or later"));
+    }
+    let records = file
+        .get("records")
+        .and_then(Value::as_array)
+        .ok_or_else(|| invalid("The records of the tldraw file must be an array"))?;
+
+    let mut pages: Vec<&Value> = records
+        .iter()
+        .filter(|r| prop(r, "typeName", "") == "page")
+        .collect();
+    pages.sort_by_key(|page| prop(page, "index", ""));
+
+    let mut converter = TldrawConverter::new(records);
+    let mut bottom = None;
+    for page in pages {
+        let start = converter.elements.len();
+        converter.convert_children(prop(page, "id", ""), (0.0, 0.0), &[], None);
+        let elements = &mut converter.elements[start..];
+        let top = elements
+            .iter()
+            .map(|e| num(e, "y"))
+            .fold(f64::MAX, f64::min);
+        // The first page is kept as it is, and the others are moved below the previous.
+        let offset = bottom.map_or(0.0, |bottom| bottom + TLDRAW_PAGE_GAP - top);
+        for element in elements.iter_mut() {
+            element["y"] = Value::from(num(element, "y") + offset);
+        }
+        let page_bottom = elements
+            .iter()
+            .map(|e| num(e, "y") + num(e, "height"))
+            .fold(f64::MIN, f64::max);
+        if !elements.is_empty() {
+            bottom = Some(bottom.map_or(page_bottom, |bottom: f64| bottom.max(page_bottom)));
+        }
+    }
+
+    normalize_excalidraw(
+        json!({
+            "type": EXCALIDRAW_TYPE,
+            "elements": converter.elements,
+            "files": converter.files,
+        })
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rectangle(id: &str) -> Value {
+        json!({ "id": id, "type": "rectangle", "x": 10, "y": 20, "width": 100, "height": 50 })
+    }
+
+    fn find<'a>(scene: &'a Value, id: &str) -> &'a Value {
+        scene["elements"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|e| e["id"] == id)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_normalize_excalidraw() {
+        let image = json!({ "id": "i1", "type": "image", "x": 0, "y": 0, "fileId": "f1" });
+        let deleted = json!({ "id": "d1", "type": "ellipse", "x": 0, "y": 0, "isDeleted": true, "fileId": "f2" });
+        let scene = json!({
+            "type": "excalidraw",
+            "version": 2,
+            "elements": [rectangle("r1"), image, deleted],
+            "appState": { "viewBackgroundColor": "#fff", "collaborators": [], "zoom": { "value": 2 } },
+            "files": {
+                "f1": { "dataURL": "data:image/png;base64,AAEC" },
+                "f2": { "mimeType": "image/png", "dataURL": "data:image/png;base64,AAEC" }
+            }
+        });
+        let scene = normalize_excalidraw(scene).unwrap();
+        assert_eq!(scene["source"], EXCALIDRAW_SOURCE);
+        assert_eq!(scene["elements"].as_array().unwrap().len(), 2);
+        assert_eq!(scene["appState"], json!({ "viewBackgroundColor": "#fff" }));
+
+        // Only the file referenced by the image is kept, and the mime type is filled from the data URL.
+        let files = scene["files"].as_object().unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files["f1"]["id"], "f1");
+        assert_eq!(files["f1"]["mimeType"], "image/png");
+        assert!(files["f1"]["created"].is_i64());
+    }
+
+    #[test]
+    fn test_normalize_excalidraw_invalid() {
+        let invalids = [
+            json!([]),
+            json!({ "type": "tldraw" }),
+            json!({ "elements": {} }),
+            json!({ "elements": [{ "type": "rectangle", "x": 0, "y": 0 }] }),
+            json!({ "elements": [{ "id": "a", "type": "star", "x": 0, "y": 0 }] }),
+            json!({ "elements": [{ "id": "a", "type": "rectangle", "x": "0", "y": 0 }] }),
+            json!({ "elements": [{ "id": "a", "type": "arrow", "x": 0, "y": 0, "points": [[0, 0], [1]] }] }),
+            json!({ "elements": [{ "id": "a", "type": "text", "x": 0, "y": 0 }] }),
+            json!({ "elements": [rectangle("a"), rectangle("a")] }),
+            json!({
+                "elements": [{ "id": "a", "type": "image", "x": 0, "y": 0, "fileId": "f" }],
+                "files": { "f": {} }
+            }),
+        ];
+        for scene in invalids {
+            let e = normalize_excalidraw(scene.clone()).unwrap_err();
+            assert!(matches!(e.downcast_ref::<BizError>(), Some(BizError::BadRequest(_))), "{}", scene);
+        }
+    }
+
+    #[test]
+    fn test_to_scene_detect_format() {
+        let excalidraw = json!({ "elements": [rectangle("r1")] }).to_string();
+        assert_eq!(detect_format(&serde_json::from_str(&excalidraw).unwrap()), Some(BoardFormat::Excalidraw));
+        let scene = to_scene(excalidraw.as_bytes(), None).unwrap();
+        assert_eq!(scene["type"], EXCALIDRAW_TYPE);
+
+        let tldraw = json!({ "tldrawFileFormatVersion": 1, "records": [] }).to_string();
+        assert_eq!(detect_format(&serde_json::from_str(&tldraw).unwrap()), Some(BoardFormat::Tldraw));
+        assert_eq!(to_scene(tldraw.as_bytes(), None).unwrap()["elements"], json!([]));
+
+        assert!(to_scene(b"{\"name\":\"unknown\"}", None).is_err());
+        assert!(to_scene(b"not json", Some(BoardFormat::Excalidraw)).is_err());
+        // The format is specified, but the content is the other format.
+        assert!(to_scene(excalidraw.as_bytes(), Some(BoardFormat::Tldraw)).is_err());
+        assert!(to_scene(tldraw.as_bytes(), Some(BoardFormat::Excalidraw)).is_err());
+    }
+
+    #[test]
+    fn test_reconcile_elements() {
+        let versioned = |id: &str, version: i64, nonce: i64, x: i64| {
+            let mut element = rectangle(id);
+            element["version"] = json!(version);
+            element["versionNonce"] = json!(nonce);
+            element["x"] = json!(x);
+            element
+        };
+        let mut scene = normalize_excalidraw(json!({ "elements": [versioned("r1", 2, 5, 0)] })).unwrap();
+
+        let updates = vec![
+            versioned("r1", 1, 1, 1),
+            versioned("r1", 2, 9, 2),
+            versioned("r1", 2, 3, 3),
+            versioned("r2", 1, 1, 4),
+            json!({ "id": "bad", "type": "star", "x": 0, "y": 0 })
+        ];
+        let accepted = reconcile_elements(&mut scene, updates);
+        assert_eq!(accepted.len(), 2);
+        assert_eq!(find(&scene, "r1")["x"], json!(3));
+        assert_eq!(find(&scene, "r2")["x"], json!(4));
+
+        // The deletion is kept as the tombstone.
+        let mut deleted = versioned("r2", 2, 1, 4);
+        deleted["isDeleted"] = json!(true);
+        assert_eq!(reconcile_elements(&mut scene, vec![deleted]).len(), 1);
+        assert_eq!(scene["elements"].as_array().unwrap().len(), 2);
+        assert_eq!(find(&scene, "r2")["isDeleted"], json!(true));
+        assert!(reconcile_elements(&mut json!({}), vec![rectangle("r3")]).is_empty());
+    }
+
+    #[test]
+    fn test_data_url() {
+        let url = encode_data_url("image/png", &[0, 1, 2]);
+        assert_eq!(url, "data:image/png;base64,AAEC");
+        assert_eq!(decode_data_url(&url), Some(("image/png".to_string(), vec![0, 1, 2])));
+        assert_eq!(decode_data_url("data:image/png,AAEC"), None);
+        assert_eq!(decode_data_url("/modules/blob/download?hash=abc"), None);
+    }
+
+    #[test]
+    fn test_from_tldraw() {
+        let file = json!({
+            "tldrawFileFormatVersion": 1,
+            "records": [
+                { "id": "page:p1", "typeName": "page", "name": "Page 1", "index": "a1" },
+                {
+                    "id": "shape:g1", "typeName": "shape", "type": "geo", "parentId": "page:p1", "index": "a1",
+                    "x": 100, "y": 100, "rotation": 0, "opacity": 1,
+                    "props": { "geo": "ellipse", "w": 200, "h": 100, "color": "red", "fill": "solid", "text": "Hello" }
+                },
+                {
+                    "id": "shape:t1", "typeName": "shape", "type": "text", "parentId": "page:p1", "index": "a2",
+                    "x": 0, "y": 0, "props": { "text": "Title", "size": "l" }
+                },
+                {
+                    "id": "shape:a1", "typeName": "shape", "type": "arrow", "parentId": "page:p1", "index": "a3",
+                    "x": 0, "y": 0,
+                    "props": { "start": { "x": 0, "y": 0 }, "end": { "type": "binding", "boundShapeId": "shape:g1" } }
+                },
+                {
+                    "id": "shape:d1", "typeName": "shape", "type": "draw", "parentId": "page:p1", "index": "a4",
+                    "x": 10, "y": 10,
+                    "props": { "segments": [{ "type": "free", "points": [{ "x": 0, "y": 0 }, { "x": 5, "y": 8 }] }] }
+                },
+                {
+                    "id": "shape:u1", "typeName": "shape", "type": "unknown", "parentId": "page:p1", "index": "a5",
+                    "x": 0, "y": 0, "props": {}
+                }
+            ]
+        });
+        let scene = from_tldraw(&file).unwrap();
+        assert_eq!(scene["elements"].as_array().unwrap().len(), 5);
+
+        let geo = find(&scene, "g1");
+        assert_eq!(geo["type"], "ellipse");
+        assert_eq!((geo["x"].as_f64(), geo["width"].as_f64()), (Some(100.0), Some(200.0)));
+        assert_eq!(geo["fillStyle"], "solid");
+        assert_eq!(geo["boundElements"], json!([{ "type": "text", "id": "g1-label" }]));
+        let label = find(&scene, "g1-label");
+        assert_eq!((label["text"].as_str(), label["containerId"].as_str()), (Some("Hello"), Some("g1")));
+
+        assert_eq!(find(&scene, "t1")["text"], "Title");
+        // The end of the arrow is the center of the bound shape.
+        assert_eq!(find(&scene, "a1")["points"], json!([[0.0, 0.0], [200.0, 150.0]]));
+        let draw = find(&scene, "d1");
+        assert_eq!(draw["type"], "freedraw");
+        assert_eq!(draw["points"], json!([[0.0, 0.0], [5.0, 8.0]]));
+    }
+
+    #[test]
+    fn test_from_tldraw_group_frame_image_and_pages() {
+        let file = json!({
+            "tldrawFileFormatVersion": 1,
+            "records": [
+                { "id": "page:p2", "typeName": "page", "index": "a2" },
+                { "id": "page:p1", "typeName": "page", "index": "a1" },
+                {
+                    "id": "asset:img", "typeName": "asset", "type": "image",
+                    "props": { "src": "data:image/png;base64,AAEC", "mimeType": "image/png", "w": 10, "h": 10 }
+                },
+                {
+                    "id": "shape:f1", "typeName": "shape", "type": "frame", "parentId": "page:p1", "index": "a1",
+                    "x": 0, "y": 0, "props": { "w": 300, "h": 300, "name": "Frame" }
+                },
+                {
+                    "id": "shape:gr", "typeName": "shape", "type": "group", "parentId": "shape:f1", "index": "a1",
+                    "x": 50, "y": 50, "props": {}
+                },
+                {
+                    "id": "shape:i1", "typeName": "shape", "type": "image", "parentId": "shape:gr", "index": "a1",
+                    "x": 10, "y": 10, "props": { "assetId": "asset:img", "w": 10, "h": 10 }
+                },
+                {
+                    "id": "shape:r2", "typeName": "shape", "type": "geo", "parentId": "page:p2", "index": "a1",
+                    "x": 0, "y": 0, "props": { "w": 10, "h": 10 }
+                }
+            ]
+        });
+        let scene = from_tldraw(&file).unwrap();
+
+        let image = find(&scene, "i1");
+        assert_eq!((image["x"].as_f64(), image["y"].as_f64()), (Some(60.0), Some(60.0)));
+        assert_eq!(image["groupIds"], json!(["gr"]));
+        assert_eq!(image["frameId"], "f1");
+        assert_eq!(image["fileId"], "img");
+        assert_eq!(scene["files"]["img"]["dataURL"], "data:image/png;base64,AAEC");
+
+        // The second page is moved below the first.
+        assert_eq!(find(&scene, "r2")["y"].as_f64(), Some(300.0 + TLDRAW_PAGE_GAP));
+
+        assert!(from_tldraw(&json!({ "records": [] })).is_err());
+        assert!(from_tldraw(&json!({ "tldrawFileFormatVersion": 1 })).is_err());
+    }
+}
@@ -0,0 +1,109 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use std::io::Cursor;
+
+use anyhow::Error;
+use base64::{ engine::general_purpose::STANDARD, Engine as _ };
+use serde::{ Deserialize, Deserializer };
+
+use crate::config::config_serve::CompressionProperties;
+
+// The prefix of the compressed content, which is stored as text so that compatible with the
+// all DbTypes, i.e: 'zstd:<base64 of the zstd frame>'.
+pub const ZSTD_PREFIX: &str = "zstd:";
+
+// Compress the content if it's large enough and actually smaller after compressed. Notice: The
+// plain content which starts with the prefix is always compressed, so that decoding is unambiguous.
+pub fn encode_content(
+    content: Option<String>,
+    config: &CompressionProperties
+) -> Result<Option<String>, Error> {
+    let content = match content {
+        Some(c) => c,
+        None => return Ok(None),
+    };
+    let must = content.starts_with(ZSTD_PREFIX);
+    if !must && (!config.enabled || content.len() < config.threshold) {
+        return Ok(Some(content));
+    }
+    let compressed = zstd::encode_all(Cursor::new(content.as_bytes()), config.level)?;
+    let encoded = format!("{}{}", ZSTD_PREFIX, STANDARD.encode(compressed));
+    if must || encoded.len() < content.len() {
+        Ok(Some(encoded))
+    } else {
+        Ok(Some(content))
+    }
+}
+
+// Decompress the content if compressed, otherwise as it is.
+pub fn decode_content(content: Option<String>) -> Option<String> {
+    let content = content?;
+    let encoded = match content.strip_prefix(ZSTD_PREFIX) {
+        Some(encoded) => encoded,
+        None => return Some(content),
+    };
+    let decoded = STANDARD.decode(encoded)
+        .map_err(Error::from)
+        .and_then(|bytes| Ok(zstd::decode_all(Cursor::new(bytes))?))
+        .and_then(|bytes| Ok(String::from_utf8(bytes)?));
+    match decoded {
+        Ok(decoded) => Some(decoded),
+        Err(e) => {
+            tracing::warn!("Failed to decompress the content, fallback to raw. reason: {}", e);
+            Some(content)
+        }
+    }
+}
+
+// The serde deserializer of the content field which transparently decompressed.
+pub fn deserialize_content<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+    where D: Deserializer<'de>
+{
+    Ok(decode_content(Option::<String>::deserialize(deserializer)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_and_decode_content() {
+        let config = CompressionProperties { enabled: true, threshold: 16, level: 3 };
+        let large = "{\"type\":\"rectangle\"}".repeat(1000);
+        let encoded = encode_content(Some(large.clone()), &config).unwrap().unwrap();
+        assert!(encoded.starts_with(ZSTD_PREFIX));
+        assert!(encoded.len() < large.len() / 10);
+        assert_eq!(decode_content(Some(encoded)), Some(large));
+
+        // The small content is kept as it is.
+        let small = "hello".to_string();
+        assert_eq!(encode_content(Some(small.clone()), &config).unwrap(), Some(small.clone()));
+        assert_eq!(decode_content(Some(small.clone())), Some(small));
+
+        // The plain content with the prefix is always compressed.
+        let tricky = "zstd:abc".to_string();
+        let encoded = encode_content(Some(tricky.clone()), &config).unwrap().unwrap();
+        assert_ne!(encoded, tricky);
+        assert_eq!(decode_content(Some(encoded)), Some(tricky));
+    }
+}
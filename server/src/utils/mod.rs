@@ -21,7 +21,11 @@
  */
 
 pub mod auths;
+pub mod boards;
+pub mod notes;
+pub mod thumbnails;
 pub mod cgroup;
+pub mod compress;
 pub mod httpclients;
 pub mod mems;
 pub mod inets;
@@ -0,0 +1,861 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use std::collections::{ HashMap, HashSet };
+
+use anyhow::Error;
+use serde_json::{ json, Map, Value };
+use yrs::types::text::YChange;
+use yrs::types::ToJson;
+use yrs::updates::decoder::Decode;
+use yrs::{ Array, Doc, Map as _, MapRef, Out, ReadTxn, Text, Transact, Update };
+
+use crate::errors::BizError;
+
+// The escaped characters of the markdown text, see: https://spec.commonmark.org/0.31.2/#backslash-escapes
+const MARKDOWN_ESCAPES: &str = "\\`*_[]<>~#|";
+const HTML_STYLE: &str =
+    "body { max-width: 800px; margin: 40px auto; padding: 0 16px; line-height: 1.6; color: #1f2328; \
+    font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Helvetica, Arial, sans-serif; }
+pre { background: #f6f8fa; padding: 12px; border-radius: 6px; overflow: auto; }
+code { font-family: SFMono-Regular, Consolas, Menlo, monospace; }
+blockquote { margin: 0; padding: 0 1em; color: #59636e; border-left: 4px solid #d1d9e0; }
+img { max-width: 100%; }
+figcaption { color: #59636e; font-size: 0.9em; }
+li.todo { list-style: none; }";
+
+fn invalid(message: impl Into<String>) -> Error {
+    BizError::BadRequest(message.into()).into()
+}
+
+// Parse the content of the note to the root block, the content is the BlockSuite snapshot of the page,
+// i.e: '{ "type": "page", "meta": {..}, "blocks": { "flavour": "affine:page", .. } }', or the root
+// block itself, and the empty content is the empty page.
+pub fn to_root_block(content: &str) -> Result<Value, Error> {
+    if content.trim().is_empty() {
+        return Ok(json!({ "flavour": "affine:page" }));
+    }
+    let snapshot: Value = serde_json
+        ::from_str(content)
+        .map_err(|e| invalid(format!("The note content is not a valid JSON. reason: {}", e)))?;
+    let root = match snapshot.get("blocks") {
+        Some(blocks) => blocks.clone(),
+        None => snapshot,
+    };
+    if root.get("flavour").and_then(Value::as_str).is_none() {
+        return Err(invalid("The note content is not the BlockSuite snapshot"));
+    }
+    Ok(root)
+}
+
+fn flavour(block: &Value) -> &str {
+    block.get("flavour").and_then(Value::as_str).unwrap_or_default()
+}
+
+fn children(block: &Value) -> &[Value] {
+    block
+        .get("children")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn prop<'a>(block: &'a Value, key: &str) -> &'a str {
+    block
+        .get("props")
+        .and_then(|props| props.get(key))
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+}
+
+// The runs of the text, which is the delta of the BlockSuite text, i.e:
+// '{ "$blocksuite:internal:text$": true, "delta": [{ "insert": "Hi", "attributes": { "bold": true } }] }'
+fn runs(text: Option<&Value>) -> Vec<(&str, Option<&Value>)> {
+    match text {
+        Some(Value::String(text)) => vec![(text.as_str(), None)],
+        Some(text) =>
+            text
+                .get("delta")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|op| op.get("insert").and_then(Value::as_str).map(|insert| (insert, op.get("attributes"))))
+                .collect(),
+        None => Vec::new(),
+    }
+}
+
+fn text_of<'a>(block: &'a Value, key: &str) -> Option<&'a Value> {
+    block.get("props").and_then(|props| props.get(key))
+}
+
+fn plain_text(text: Option<&Value>) -> String {
+    runs(text)
+        .into_iter()
+        .map(|(insert, _)| insert)
+        .collect()
+}
+
+fn has(attrs: Option<&Value>, key: &str) -> bool {
+    attrs.and_then(|attrs| attrs.get(key)).and_then(Value::as_bool).unwrap_or(false)
+}
+
+fn link_of(attrs: Option<&Value>) -> Option<&str> {
+    attrs
+        .and_then(|attrs| attrs.get("link"))
+        .and_then(Value::as_str)
+        .filter(|link| !link.is_empty())
+}
+
+// The title of the page, which is the title of the root block.
+pub fn title_of(root: &Value) -> String {
+    plain_text(text_of(root, "title")).trim().to_string()
+}
+
+// The sources of the blobs which referenced by the images and the attachments, in order of the
+// appearance and without duplicates.
+pub fn blob_sources(root: &Value) -> Vec<String> {
+    fn collect(block: &Value, sources: &mut Vec<String>) {
+        if matches!(flavour(block), "affine:image" | "affine:attachment") {
+            let source = prop(block, "sourceId");
+            if !source.is_empty() && !sources.iter().any(|s| s == source) {
+                sources.push(source.to_string());
+            }
+        }
+        for child in children(block) {
+            collect(child, sources);
+        }
+    }
+    let mut sources = Vec::new();
+    collect(root, &mut sources);
+    sources
+}
+
+// The plain texts of the blocks, such as the title and the paragraphs, in order of the appearance, which
+// are the searchable of the note.
+pub fn texts_of(root: &Value) -> Vec<String> {
+    fn collect(block: &Value, texts: &mut Vec<String>) {
+        let props = block.get("props").and_then(Value::as_object).into_iter().flatten();
+        for (_, value) in props.filter(|(_, value)| value.get("delta").is_some()) {
+            let text = plain_text(Some(value));
+            if !text.trim().is_empty() {
+                texts.push(text);
+            }
+        }
+        for child in children(block) {
+            collect(child, texts);
+        }
+    }
+    let mut texts = Vec::new();
+    collect(root, &mut texts);
+    texts
+}
+
+// The BlockSuite snapshot of the page of the yjs update, the blocks of the page are the map of 'blocks' by id,
+// each of which is the map of the 'sys:flavour', the 'sys:children' ids and the 'prop:*' props, and the texts
+// are the Y.Text, see: https://github.com/toeverything/blocksuite/blob/master/packages/framework/store
+pub fn snapshot_of_update(update: &[u8]) -> Result<Value, Error> {
+    let update = Update::decode_v1(update).map_err(|e| invalid(format!("The yjs update is invalid. reason: {}", e)))?;
+    let doc = Doc::new();
+    let mut txn = doc.transact_mut();
+    txn.apply_update(update).map_err(|e| invalid(format!("The yjs update is invalid. reason: {}", e)))?;
+
+    let root = txn.get_map("blocks").and_then(|blocks| {
+        let root_id = blocks
+            .iter(&txn)
+            .filter_map(|(id, block)| block.cast::<MapRef>().ok().map(|block| (id.to_string(), block)))
+            .find(|(_, block)| block_flavour(&txn, block) == "affine:page")
+            .map(|(id, _)| id)?;
+        block_of(&txn, &blocks, &root_id, &mut HashSet::new())
+    });
+    Ok(json!({ "type": "page", "blocks": root.unwrap_or_else(|| json!({ "flavour": "affine:page" })) }))
+}
+
+fn block_flavour<T: ReadTxn>(txn: &T, block: &MapRef) -> String {
+    match block.get(txn, "sys:flavour") {
+        Some(Out::Any(yrs::Any::String(flavour))) => flavour.to_string(),
+        _ => String::new(),
+    }
+}
+
+// The block of the id and the children of it, the visited are skipped so that the cycled are not walked infinitely.
+fn block_of<T: ReadTxn>(txn: &T, blocks: &MapRef, id: &str, visited: &mut HashSet<String>) -> Option<Value> {
+    let block = blocks.get(txn, id)?.cast::<MapRef>().ok()?;
+    if !visited.insert(id.to_string()) {
+        return None;
+    }
+    let mut props = Map::new();
+    for (key, value) in block.iter(txn) {
+        if let Some(key) = key.strip_prefix("prop:") {
+            props.insert(key.to_string(), value_of(txn, value));
+        }
+    }
+    let children: Vec<Value> = match block.get(txn, "sys:children") {
+        Some(Out::YArray(ids)) => ids
+            .iter(txn)
+            .filter_map(|child| match child {
+                Out::Any(yrs::Any::String(child)) => block_of(txn, blocks, &child, visited),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    Some(
+        json!({
+            "type": "block",
+            "id": id,
+            "flavour": block_flavour(txn, &block),
+            "props": props,
+            "children": children,
+        })
+    )
+}
+
+// The JSON of the prop value, and the Y.Text is the delta of the BlockSuite text, see: runs()
+fn value_of<T: ReadTxn>(txn: &T, value: Out) -> Value {
+    match value {
+        Out::YText(text) => {
+            let delta: Vec<Value> = text
+                .diff(txn, YChange::identity)
+                .into_iter()
+                .map(|diff| {
+                    let mut op = json!({ "insert": value_of(txn, diff.insert) });
+                    if let Some(attributes) = diff.attributes {
+                        op["attributes"] = serde_json::to_value(*attributes).unwrap_or_default();
+                    }
+                    op
+                })
+                .collect();
+            json!({ "$blocksuite:internal:text$": true, "delta": delta })
+        }
+        value => serde_json::to_value(value.to_json(txn)).unwrap_or_default(),
+    }
+}
+
+// Whether the url of the link is safe, the schemes other than the web and mail (such as 'javascript:')
+// are not linked.
+fn is_safe_url(url: &str, image: bool) -> bool {
+    let scheme = url
+        .split_once(':')
+        .map(|(scheme, _)| scheme)
+        .filter(|scheme| !scheme.contains(['/', '?', '#']));
+    match scheme.map(str::to_lowercase).as_deref() {
+        None | Some("http" | "https" | "mailto") => true,
+        Some("data") => image && url[5..].starts_with("image/"),
+        _ => false,
+    }
+}
+
+// Split the leading and trailing whitespaces, so that the emphasis is around the text only.
+fn split_whitespace(text: &str) -> (&str, &str, &str) {
+    let core = text.trim();
+    let start = text.len() - text.trim_start().len();
+    (&text[..start], core, &text[start + core.len()..])
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    text.split(|ch| ch != c)
+        .map(str::len)
+        .max()
+        .unwrap_or_default()
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if MARKDOWN_ESCAPES.contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Escape the start of the line which would be the list marker or the setext heading underline.
+fn escape_line_start(line: &str) -> String {
+    if line.starts_with(['-', '+', '=']) {
+        return format!("\\{}", line);
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 && line[digits..].starts_with(['.', ')']) {
+        return format!("{}\\{}", &line[..digits], &line[digits..]);
+    }
+    line.to_string()
+}
+
+// The lines of the text are joined with the hard line breaks.
+fn markdown_lines(text: &str) -> String {
+    text.split('\n').map(escape_line_start).collect::<Vec<_>>().join("\\\n")
+}
+
+fn indent(text: &str, width: usize) -> String {
+    let padding = " ".repeat(width);
+    text.split('\n')
+        .enumerate()
+        .map(|(i, line)| if i == 0 || line.is_empty() { line.to_string() } else { format!("{}{}", padding, line) })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn markdown_url(url: &str) -> String {
+    url.replace(' ', "%20").replace('(', "%28").replace(')', "%29").replace('<', "%3C").replace('>', "%3E")
+}
+
+fn code_span(text: &str) -> String {
+    let ticks = "`".repeat(longest_run(text, '`') + 1);
+    let padding = if text.starts_with('`') || text.ends_with('`') { " " } else { "" };
+    format!("{}{}{}{}{}", ticks, padding, text, padding, ticks)
+}
+
+fn list_type(block: &Value) -> Option<&str> {
+    if flavour(block) == "affine:list" {
+        Some(match prop(block, "type") {
+            "" => "bulleted",
+            kind => kind,
+        })
+    } else {
+        None
+    }
+}
+
+fn heading_level(kind: &str) -> Option<usize> {
+    kind.strip_prefix('h')
+        .and_then(|level| level.parse::<usize>().ok())
+        .filter(|level| (1..=6).contains(level))
+}
+
+// The renderer of the CommonMark, the blob sources are linked to the urls of the links.
+struct MarkdownRenderer<'a> {
+    links: &'a HashMap<String, String>,
+}
+
+impl<'a> MarkdownRenderer<'a> {
+    fn inline(&self, text: Option<&Value>) -> String {
+        let mut out = String::new();
+        for (insert, attrs) in runs(text) {
+            let (lead, core, trail) = split_whitespace(insert);
+            if core.is_empty() {
+                out.push_str(insert);
+                continue;
+            }
+            let mut core = if has(attrs, "code") { code_span(core) } else { escape_markdown(core) };
+            if has(attrs, "strike") {
+                core = format!("~~{}~~", core);
+            }
+            if has(attrs, "italic") {
+                core = format!("*{}*", core);
+            }
+            if has(attrs, "bold") {
+                core = format!("**{}**", core);
+            }
+            if let Some(link) = link_of(attrs).filter(|link| is_safe_url(link, false)) {
+                core = format!("[{}]({})", core, markdown_url(link));
+            }
+            out.push_str(lead);
+            out.push_str(&core);
+            out.push_str(trail);
+        }
+        out
+    }
+
+    fn blocks(&self, blocks: &[Value]) -> String {
+        let mut out = String::new();
+        let mut previous = None;
+        let mut number = 0;
+        for block in blocks {
+            let kind = list_type(block);
+            number = if kind == Some("numbered") && previous == kind { number + 1 } else { 1 };
+            let rendered = self.block(block, number);
+            if rendered.is_empty() {
+                continue;
+            }
+            // The items of the list are tight.
+            if !out.is_empty() {
+                out.push_str(if previous.is_some() && kind.is_some() { "\n" } else { "\n\n" });
+            }
+            out.push_str(&rendered);
+            previous = kind;
+        }
+        out
+    }
+
+    fn block(&self, block: &Value, number: usize) -> String {
+        let text = || self.inline(text_of(block, "text"));
+        let rendered = match flavour(block) {
+            "affine:paragraph" => {
+                let text = text();
+                match prop(block, "type") {
+                    _ if text.trim().is_empty() => String::new(),
+                    kind if heading_level(kind).is_some() => {
+                        let level = heading_level(kind).unwrap_or(1);
+                        format!("{} {}", "#".repeat(level), text.replace('\n', " "))
+                    }
+                    "quote" =>
+                        markdown_lines(&text)
+                            .split('\n')
+                            .map(|line| format!("> {}", line))
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                    _ => markdown_lines(&text),
+                }
+            }
+            "affine:list" => {
+                let marker = match list_type(block) {
+                    Some("numbered") => format!("{}.", number),
+                    Some("todo") if block["props"]["checked"].as_bool().unwrap_or(false) => "- [x]".to_string(),
+                    Some("todo") => "- [ ]".to_string(),
+                    _ => "-".to_string(),
+                };
+                // The content of the item is aligned to the start of the text after the marker.
+                let width = if marker.starts_with("- [") { 2 } else { marker.len() + 1 };
+                let mut item = format!("{} {}", marker, indent(&markdown_lines(&text()), width));
+                let nested = self.blocks(children(block));
+                if !nested.is_empty() {
+                    let tight = children(block).first().and_then(list_type).is_some();
+                    item.push_str(if tight { "\n" } else { "\n\n" });
+                    item.push_str(&" ".repeat(width));
+                    item.push_str(&indent(&nested, width));
+                }
+                return item;
+            }
+            "affine:code" => {
+                let code = plain_text(text_of(block, "text"));
+                let fence = "`".repeat(longest_run(&code, '`').max(2) + 1);
+                let language = prop(block, "language").to_lowercase();
+                let language = if language.contains(char::is_whitespace) { "" } else { language.as_str() };
+                return format!("{}{}\n{}\n{}", fence, language, code, fence);
+            }
+            "affine:divider" => {
+                return "---".to_string();
+            }
+            "affine:image" => {
+                let caption = escape_markdown(prop(block, "caption"));
+                match self.links.get(prop(block, "sourceId")) {
+                    Some(url) => format!("![{}]({})", caption, markdown_url(url)),
+                    None => caption,
+                }
+            }
+            "affine:attachment" => {
+                let name = escape_markdown(prop(block, "name"));
+                match self.links.get(prop(block, "sourceId")) {
+                    Some(url) => format!("[{}]({})", name, markdown_url(url)),
+                    None => name,
+                }
+            }
+            kind if kind == "affine:bookmark" || kind.starts_with("affine:embed") => {
+                let url = prop(block, "url");
+                if url.is_empty() || !is_safe_url(url, false) {
+                    String::new()
+                } else {
+                    let title = [prop(block, "title"), prop(block, "caption"), url]
+                        .into_iter()
+                        .find(|title| !title.is_empty())
+                        .unwrap_or(url);
+                    format!("[{}]({})", escape_markdown(title), markdown_url(url))
+                }
+            }
+            // The surface is the canvas of the edgeless mode, which isn't a part of the document.
+            "affine:surface" => {
+                return String::new();
+            }
+            _ => {
+                return self.blocks(children(block));
+            }
+        };
+
+        // The children of the text blocks are the nested blocks, which are rendered after the block.
+        let nested = self.blocks(children(block));
+        match (rendered.is_empty(), nested.is_empty()) {
+            (_, true) => rendered,
+            (true, false) => nested,
+            (false, false) => format!("{}\n\n{}", rendered, nested),
+        }
+    }
+}
+
+// Convert the note to the CommonMark, the blob sources of the images and the attachments are linked
+// to the urls of the links, and they are dropped if absent.
+pub fn to_markdown(root: &Value, title: &str, links: &HashMap<String, String>) -> String {
+    let renderer = MarkdownRenderer { links };
+    let mut out = String::new();
+    if !title.trim().is_empty() {
+        out.push_str(&format!("# {}\n\n", escape_markdown(&title.replace('\n', " "))));
+    }
+    out.push_str(&renderer.blocks(children(root)));
+    format!("{}\n", out.trim_end())
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// The renderer of the HTML, the blob sources are linked to the urls of the links.
+struct HtmlRenderer<'a> {
+    links: &'a HashMap<String, String>,
+}
+
+impl<'a> HtmlRenderer<'a> {
+    fn inline(&self, text: Option<&Value>) -> String {
+        let mut out = String::new();
+        for (insert, attrs) in runs(text) {
+            let mut html = escape_html(insert).replace('\n', "<br>");
+            let tags = [("code", "code"), ("strike", "s"), ("underline", "u"), ("italic", "em"), ("bold", "strong")];
+            for (key, tag) in tags {
+                if has(attrs, key) {
+                    html = format!("<{}>{}</{}>", tag, html, tag);
+                }
+            }
+            if let Some(link) = link_of(attrs).filter(|link| is_safe_url(link, false)) {
+                html = format!("<a href=\"{}\">{}</a>", escape_html(link), html);
+            }
+            out.push_str(&html);
+        }
+        out
+    }
+
+    fn blocks(&self, blocks: &[Value]) -> String {
+        let mut out = String::new();
+        let mut open = None;
+        for block in blocks {
+            // The consecutive items of the same kind are in the same list.
+            let tag = list_type(block).map(|kind| if kind == "numbered" { "ol" } else { "ul" });
+            if open != tag {
+                if let Some(open) = open {
+                    out.push_str(&format!("</{}>\n", open));
+                }
+                if let Some(tag) = tag {
+                    out.push_str(&format!("<{}>\n", tag));
+                }
+                open = tag;
+            }
+            let rendered = self.block(block);
+            if !rendered.is_empty() {
+                out.push_str(&rendered);
+                out.push('\n');
+            }
+        }
+        if let Some(open) = open {
+            out.push_str(&format!("</{}>\n", open));
+        }
+        out
+    }
+
+    fn block(&self, block: &Value) -> String {
+        let text = || self.inline(text_of(block, "text"));
+        let rendered = match flavour(block) {
+            "affine:paragraph" => {
+                let text = text();
+                match prop(block, "type") {
+                    _ if text.trim().is_empty() => String::new(),
+                    kind if heading_level(kind).is_some() => format!("<{}>{}</{}>", kind, text, kind),
+                    "quote" => format!("<blockquote><p>{}</p></blockquote>", text),
+                    _ => format!("<p>{}</p>", text),
+                }
+            }
+            "affine:list" => {
+                let nested = self.blocks(children(block));
+                return match list_type(block) {
+                    Some("todo") => {
+                        let checked = block["props"]["checked"].as_bool().unwrap_or(false);
+                        let checkbox = if checked { " checked" } else { "" };
+                        format!(
+                            "<li class=\"todo\"><input type=\"checkbox\" disabled{}> {}{}</li>",
+                            checkbox,
+                            text(),
+                            nested
+                        )
+                    }
+                    _ => format!("<li>{}{}</li>", text(), nested),
+                };
+            }
+            "affine:code" => {
+                let code = escape_html(&plain_text(text_of(block, "text")));
+                let language = prop(block, "language").to_lowercase();
+                if language.is_empty() {
+                    return format!("<pre><code>{}</code></pre>", code);
+                }
+                return format!("<pre><code class=\"language-{}\">{}</code></pre>", escape_html(&language), code);
+            }
+            "affine:divider" => {
+                return "<hr>".to_string();
+            }
+            "affine:image" => {
+                let caption = escape_html(prop(block, "caption"));
+                let url = self.links.get(prop(block, "sourceId")).filter(|url| is_safe_url(url, true));
+                match (url, caption.is_empty()) {
+                    (Some(url), true) => format!("<figure><img src=\"{}\" alt=\"\"></figure>", escape_html(url)),
+                    (Some(url), false) =>
+                        format!(
+                            "<figure><img src=\"{}\" alt=\"{}\"><figcaption>{}</figcaption></figure>",
+                            escape_html(url),
+                            caption,
+                            caption
+                        ),
+                    (None, true) => String::new(),
+                    (None, false) => format!("<p>{}</p>", caption),
+                }
+            }
+            "affine:attachment" => {
+                let name = escape_html(prop(block, "name"));
+                match self.links.get(prop(block, "sourceId")).filter(|url| is_safe_url(url, false)) {
+                    Some(url) => format!("<p><a href=\"{}\" download>{}</a></p>", escape_html(url), name),
+                    None => format!("<p>{}</p>", name),
+                }
+            }
+            kind if kind == "affine:bookmark" || kind.starts_with("affine:embed") => {
+                let url = prop(block, "url");
+                if url.is_empty() || !is_safe_url(url, false) {
+                    String::new()
+                } else {
+                    let title = [prop(block, "title"), prop(block, "caption"), url]
+                        .into_iter()
+                        .find(|title| !title.is_empty())
+                        .unwrap_or(url);
+                    format!("<p><a href=\"{}\">{}</a></p>", escape_html(url), escape_html(title))
+                }
+            }
+            "affine:surface" => {
+                return String::new();
+            }
+            _ => {
+                return self.blocks(children(block)).trim_end().to_string();
+            }
+        };
+
+        let nested = self.blocks(children(block));
+        format!("{}{}", rendered, nested.trim_end())
+    }
+}
+
+// Convert the note to the standalone HTML page, the blob sources of the images and the attachments are
+// linked to the urls of the links (such as the data URLs), and they are dropped if absent.
+pub fn to_html(root: &Value, title: &str, links: &HashMap<String, String>) -> String {
+    let renderer = HtmlRenderer { links };
+    let title = escape_html(title.trim());
+    let heading = if title.is_empty() { String::new() } else { format!("<h1>{}</h1>\n", title) };
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+        <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+        <title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n<article>\n{}{}</article>\n</body>\n</html>\n",
+        title,
+        HTML_STYLE,
+        heading,
+        renderer.blocks(children(root))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::types::Attrs;
+    use yrs::{ ArrayPrelim, MapPrelim, StateVector, TextPrelim };
+
+    fn text(delta: Value) -> Value {
+        json!({ "$blocksuite:internal:text$": true, "delta": delta })
+    }
+
+    fn block(flavour: &str, props: Value, children: Value) -> Value {
+        json!({ "type": "block", "id": flavour, "flavour": flavour, "props": props, "children": children })
+    }
+
+    fn paragraph(kind: &str, insert: &str) -> Value {
+        block("affine:paragraph", json!({ "type": kind, "text": text(json!([{ "insert": insert }])) }), json!([]))
+    }
+
+    fn list(kind: &str, insert: &str, children: Value) -> Value {
+        block("affine:list", json!({ "type": kind, "text": text(json!([{ "insert": insert }])) }), children)
+    }
+
+    fn snapshot(blocks: Value) -> String {
+        let note = block("affine:note", json!({}), blocks);
+        let page = block("affine:page", json!({ "title": text(json!([{ "insert": "My Note" }])) }), json!([note]));
+        json!({ "type": "page", "meta": { "id": "p1", "title": "My Note" }, "blocks": page }).to_string()
+    }
+
+    #[test]
+    fn test_to_root_block() {
+        let root = to_root_block(&snapshot(json!([]))).unwrap();
+        assert_eq!(flavour(&root), "affine:page");
+        assert_eq!(title_of(&root), "My Note");
+        assert_eq!(flavour(&to_root_block("").unwrap()), "affine:page");
+        assert!(to_root_block("plain text").is_err());
+        assert!(to_root_block("{\"elements\":[]}").is_err());
+    }
+
+    #[test]
+    fn test_texts_of() {
+        let item = list("bulleted", "Item", json!([paragraph("text", " ")]));
+        let content = snapshot(json!([paragraph("h1", "Heading"), item]));
+        let root = to_root_block(&content).unwrap();
+        assert_eq!(texts_of(&root), vec!["My Note", "Heading", "Item"]);
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let rich = text(
+            json!([
+                { "insert": "Hello " },
+                { "insert": "bold", "attributes": { "bold": true } },
+                { "insert": " and " },
+                { "insert": "link ", "attributes": { "link": "https://example.com/a b", "italic": true } },
+                { "insert": "x`y", "attributes": { "code": true } },
+                { "insert": " 2*3" }
+            ])
+        );
+        let content = snapshot(
+            json!([
+                paragraph("h2", "Title"),
+                block("affine:paragraph", json!({ "type": "text", "text": rich }), json!([])),
+                paragraph("text", ""),
+                paragraph("quote", "line1\nline2"),
+                list("bulleted", "a", json!([list("numbered", "a1", json!([])), list("numbered", "a2", json!([]))])),
+                list("bulleted", "b", json!([])),
+                block("affine:list", json!({ "type": "todo", "checked": true, "text": "done" }), json!([])),
+                block("affine:code", json!({ "language": "Rust", "text": "let a = 1;" }), json!([])),
+                block("affine:divider", json!({}), json!([])),
+                block("affine:image", json!({ "sourceId": "s1", "caption": "cap" }), json!([])),
+                block("affine:image", json!({ "sourceId": "missing" }), json!([])),
+                paragraph("text", "- not a list"),
+                block("affine:surface", json!({}), json!([paragraph("text", "hidden")]))
+            ])
+        );
+        let root = to_root_block(&content).unwrap();
+        assert_eq!(blob_sources(&root), vec!["s1", "missing"]);
+
+        let links = HashMap::from([("s1".to_string(), "/blob?hash=1".to_string())]);
+        let expected = "\
+# My Note
+
+## Title
+
+Hello **bold** and [*link*](https://example.com/a%20b) ``x`y`` 2\\*3
+
+> line1\\
+> line2
+
+- a
+  1. a1
+  2. a2
+- b
+- [x] done
+
+```rust
+let a = 1;
+```
+
+---
+
+![cap](/blob?hash=1)
+
+\\- not a list
+";
+        assert_eq!(to_markdown(&root, "My Note", &links), expected);
+    }
+
+    #[test]
+    fn test_to_html() {
+        let rich = text(
+            json!([
+                { "insert": "<b>" },
+                { "insert": "bold", "attributes": { "bold": true, "underline": true } },
+                { "insert": "evil", "attributes": { "link": "javascript:alert(1)" } }
+            ])
+        );
+        let content = snapshot(
+            json!([
+                paragraph("h1", "Title"),
+                block("affine:paragraph", json!({ "type": "text", "text": rich }), json!([])),
+                list("numbered", "one", json!([list("bulleted", "nested", json!([]))])),
+                list("numbered", "two", json!([])),
+                block("affine:code", json!({ "text": text(json!([{ "insert": "a < b" }])) }), json!([])),
+                block("affine:image", json!({ "sourceId": "s1", "caption": "cap" }), json!([])),
+                block("affine:bookmark", json!({ "url": "https://example.com", "title": "Example" }), json!([]))
+            ])
+        );
+        let root = to_root_block(&content).unwrap();
+        let links = HashMap::from([("s1".to_string(), "data:image/png;base64,AAEC".to_string())]);
+        let html = to_html(&root, "My <Note>", &links);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>My &lt;Note&gt;</title>"));
+        assert!(html.contains("<h1>My &lt;Note&gt;</h1>\n<h1>Title</h1>"));
+        assert!(html.contains("<p>&lt;b&gt;<strong><u>bold</u></strong>evil</p>"));
+        assert!(html.contains("<ol>\n<li>one<ul>\n<li>nested</li>\n</ul>\n</li>\n<li>two</li>\n</ol>"));
+        assert!(html.contains("<pre><code>a &lt; b</code></pre>"));
+        assert!(html.contains("<img src=\"data:image/png;base64,AAEC\" alt=\"cap\"><figcaption>cap</figcaption>"));
+        assert!(html.contains("<p><a href=\"https://example.com\">Example</a></p>"));
+        assert!(!html.contains("javascript"));
+    }
+
+    #[test]
+    fn test_is_safe_url() {
+        assert!(is_safe_url("https://example.com", false));
+        assert!(is_safe_url("/modules/blob/download?hash=a:b", false));
+        assert!(is_safe_url("assets/a.png", true));
+        assert!(!is_safe_url("JavaScript:alert(1)", false));
+        assert!(is_safe_url("data:image/png;base64,AAEC", true));
+        assert!(!is_safe_url("data:image/png;base64,AAEC", false));
+        assert!(!is_safe_url("data:text/html;base64,AAEC", true));
+    }
+
+    #[test]
+    fn test_snapshot_of_update() {
+        let doc = Doc::with_client_id(1);
+        let blocks = doc.get_or_insert_map("blocks");
+        {
+            let mut txn = doc.transact_mut();
+            let page = blocks.insert(&mut txn, "page", MapPrelim::default());
+            page.insert(&mut txn, "sys:flavour", "affine:page");
+            page.insert(&mut txn, "sys:children", ArrayPrelim::from(["p1", "missing"]));
+            page.insert(&mut txn, "prop:title", TextPrelim::new("Hello"));
+            let paragraph = blocks.insert(&mut txn, "p1", MapPrelim::default());
+            paragraph.insert(&mut txn, "sys:flavour", "affine:paragraph");
+            paragraph.insert(&mut txn, "sys:children", ArrayPrelim::from(["page"]));
+            paragraph.insert(&mut txn, "prop:type", "h2");
+            let text = paragraph.insert(&mut txn, "prop:text", TextPrelim::new("big world"));
+            text.format(&mut txn, 4, 5, Attrs::from([("bold".into(), true.into())]));
+        }
+        let update = doc.transact().encode_state_as_update_v1(&StateVector::default());
+
+        // The missing and the cycled children are skipped.
+        let snapshot = snapshot_of_update(&update).unwrap();
+        let root = to_root_block(&snapshot.to_string()).unwrap();
+        assert_eq!(title_of(&root), "Hello");
+        assert_eq!(texts_of(&root), vec!["Hello", "big world"]);
+        assert_eq!(to_markdown(&root, "Hello", &HashMap::new()), "# Hello\n\n## big **world**\n");
+
+        // The update without the blocks is the empty page.
+        let empty = snapshot_of_update(&Doc::new().transact().encode_state_as_update_v1(&StateVector::default()));
+        assert_eq!(title_of(&to_root_block(&empty.unwrap().to_string()).unwrap()), "");
+        assert!(snapshot_of_update(&[1, 2, 3]).is_err());
+    }
+}
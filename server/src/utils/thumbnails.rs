@@ -0,0 +1,407 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use std::fmt::Write;
+use std::sync::{ Arc, OnceLock };
+
+use anyhow::Error;
+use resvg::{ tiny_skia, usvg };
+use serde_json::Value;
+
+use super::notes::escape_html;
+
+// The padding around the bounds of the elements, so that the strokes aren't clipped.
+const PADDING: f64 = 16.0;
+const DEFAULT_BACKGROUND: &str = "#ffffff";
+const DEFAULT_STROKE: &str = "#1e1e1e";
+const DEFAULT_FONT_SIZE: f64 = 20.0;
+const DEFAULT_LINE_HEIGHT: f64 = 1.25;
+const ARROWHEAD_SIZE: f64 = 12.0;
+
+// The fonts of the system are loaded once, which is expensive.
+static FONT_DB: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+
+fn num(element: &Value, key: &str) -> f64 {
+    element
+        .get(key)
+        .and_then(Value::as_f64)
+        .filter(|n| n.is_finite())
+        .unwrap_or_default()
+}
+
+fn str_of<'a>(element: &'a Value, key: &str, default: &'a str) -> &'a str {
+    element
+        .get(key)
+        .and_then(Value::as_str)
+        .filter(|s| !s.is_empty())
+        .unwrap_or(default)
+}
+
+fn points_of(element: &Value) -> Vec<(f64, f64)> {
+    element
+        .get("points")
+        .and_then(Value::as_array)
+        .map(|points| {
+            points
+                .iter()
+                .filter_map(|p| Some((p.get(0)?.as_f64()?, p.get(1)?.as_f64()?)))
+                .filter(|(x, y)| x.is_finite() && y.is_finite())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// The bounds of the element, which are the minimum and maximum of the x and y.
+fn bounds_of(element: &Value) -> (f64, f64, f64, f64) {
+    let (x, y) = (num(element, "x"), num(element, "y"));
+    let points = points_of(element);
+    if points.is_empty() {
+        let (width, height) = (num(element, "width"), num(element, "height"));
+        return (x.min(x + width), y.min(y + height), x.max(x + width), y.max(y + height));
+    }
+    points.iter().fold((f64::MAX, f64::MAX, f64::MIN, f64::MIN), |(x1, y1, x2, y2), (px, py)| {
+        (x1.min(x + px), y1.min(y + py), x2.max(x + px), y2.max(y + py))
+    })
+}
+
+// The common presentation attributes of the element, such as the stroke, the fill and the rotation.
+fn style_of(element: &Value, fill: bool) -> String {
+    let stroke = escape_html(str_of(element, "strokeColor", DEFAULT_STROKE));
+    let fill = if fill {
+        escape_html(str_of(element, "backgroundColor", "none"))
+    } else {
+        "none".to_string()
+    };
+    let fill = if fill == "transparent" { "none".to_string() } else { fill };
+    let stroke_width = element.get("strokeWidth").and_then(Value::as_f64).unwrap_or(2.0);
+    let mut style = format!(
+        " stroke=\"{}\" fill=\"{}\" stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\"",
+        stroke,
+        fill,
+        stroke_width
+    );
+    match str_of(element, "strokeStyle", "solid") {
+        "dashed" => {
+            let _ = write!(style, " stroke-dasharray=\"{} {}\"", stroke_width * 4.0, stroke_width * 4.0);
+        }
+        "dotted" => {
+            let _ = write!(style, " stroke-dasharray=\"{} {}\"", stroke_width, stroke_width * 3.0);
+        }
+        _ => {}
+    }
+    style.push_str(&transform_of(element));
+    style
+}
+
+// The opacity and the rotation around the center of the element.
+fn transform_of(element: &Value) -> String {
+    let mut attributes = String::new();
+    if let Some(opacity) = element.get("opacity").and_then(Value::as_f64) {
+        if opacity < 100.0 {
+            let _ = write!(attributes, " opacity=\"{}\"", opacity.max(0.0) / 100.0);
+        }
+    }
+    let angle = num(element, "angle");
+    if angle != 0.0 {
+        let (x1, y1, x2, y2) = bounds_of(element);
+        let (cx, cy) = ((x1 + x2) / 2.0, (y1 + y2) / 2.0);
+        let _ = write!(attributes, " transform=\"rotate({} {} {})\"", angle.to_degrees(), cx, cy);
+    }
+    attributes
+}
+
+fn to_path(points: &[(f64, f64)], x: f64, y: f64) -> String {
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, (px, py))| format!("{}{} {}", if i == 0 { "M" } else { "L" }, x + px, y + py))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// The two wings of the arrowhead at the tip, which points from the previous point.
+fn to_arrowhead(tip: (f64, f64), from: (f64, f64)) -> String {
+    let angle = (tip.1 - from.1).atan2(tip.0 - from.0);
+    let wing = |offset: f64| {
+        let a = angle + std::f64::consts::PI - offset;
+        (tip.0 + ARROWHEAD_SIZE * a.cos(), tip.1 + ARROWHEAD_SIZE * a.sin())
+    };
+    let ((x1, y1), (x2, y2)) = (wing(0.45), wing(-0.45));
+    format!("M{} {} L{} {} L{} {}", x1, y1, tip.0, tip.1, x2, y2)
+}
+
+fn write_element(svg: &mut String, element: &Value) {
+    let (x, y) = (num(element, "x"), num(element, "y"));
+    let (width, height) = (num(element, "width"), num(element, "height"));
+    let _ = match element.get("type").and_then(Value::as_str).unwrap_or_default() {
+        "rectangle" | "image" | "frame" | "magicframe" | "embeddable" | "iframe" => {
+            let radius = if element.get("roundness").is_some_and(|r| !r.is_null()) {
+                width.abs().min(height.abs()) * 0.1
+            } else {
+                0.0
+            };
+            writeln!(
+                svg,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{}\"{}/>",
+                x.min(x + width),
+                y.min(y + height),
+                width.abs(),
+                height.abs(),
+                radius,
+                style_of(element, true)
+            )
+        }
+        "ellipse" => {
+            writeln!(
+                svg,
+                "<ellipse cx=\"{}\" cy=\"{}\" rx=\"{}\" ry=\"{}\"{}/>",
+                x + width / 2.0,
+                y + height / 2.0,
+                width.abs() / 2.0,
+                height.abs() / 2.0,
+                style_of(element, true)
+            )
+        }
+        "diamond" => {
+            let (cx, cy) = (x + width / 2.0, y + height / 2.0);
+            writeln!(
+                svg,
+                "<polygon points=\"{} {} {} {} {} {} {} {}\"{}/>",
+                cx,
+                y,
+                x + width,
+                cy,
+                cx,
+                y + height,
+                x,
+                cy,
+                style_of(element, true)
+            )
+        }
+        kind @ ("arrow" | "line" | "freedraw") => {
+            let points = points_of(element);
+            if points.is_empty() {
+                return;
+            }
+            let mut path = to_path(&points, x, y);
+            let absolute = |i: usize| (x + points[i].0, y + points[i].1);
+            if kind == "arrow" && points.len() > 1 {
+                let last = points.len() - 1;
+                if element.get("endArrowhead").map_or(true, |a| !a.is_null()) {
+                    path.push(' ');
+                    path.push_str(&to_arrowhead(absolute(last), absolute(last - 1)));
+                }
+                if element.get("startArrowhead").is_some_and(|a| !a.is_null()) {
+                    path.push(' ');
+                    path.push_str(&to_arrowhead(absolute(0), absolute(1)));
+                }
+            }
+            // The closed line (such as the polygon) is filled, the others are just the strokes.
+            let closed = kind == "line" && points.len() > 2 && points.first() == points.last();
+            writeln!(svg, "<path d=\"{}\"{}/>", path, style_of(element, closed))
+        }
+        "text" => {
+            let font_size = element.get("fontSize").and_then(Value::as_f64).unwrap_or(DEFAULT_FONT_SIZE);
+            let line_height = font_size *
+                element.get("lineHeight").and_then(Value::as_f64).unwrap_or(DEFAULT_LINE_HEIGHT);
+            let (anchor, text_x) = match str_of(element, "textAlign", "left") {
+                "center" => ("middle", x + width / 2.0),
+                "right" => ("end", x + width),
+                _ => ("start", x),
+            };
+            let font_family = match element.get("fontFamily").and_then(Value::as_i64) {
+                Some(3) => "monospace",
+                _ => "sans-serif",
+            };
+            let _ = write!(
+                svg,
+                "<text font-family=\"{}\" font-size=\"{}\" text-anchor=\"{}\" fill=\"{}\"{}>",
+                font_family,
+                font_size,
+                anchor,
+                escape_html(str_of(element, "strokeColor", DEFAULT_STROKE)),
+                transform_of(element)
+            );
+            for (i, line) in str_of(element, "text", "").lines().enumerate() {
+                // The baseline of the line is about the font size below the top of it.
+                let line_y = y + (i as f64) * line_height + (line_height + font_size * 0.6) / 2.0;
+                let _ = write!(svg, "<tspan x=\"{}\" y=\"{}\">{}</tspan>", text_x, line_y, escape_html(line));
+            }
+            writeln!(svg, "</text>")
+        }
+        _ => Ok(()),
+    };
+}
+
+// Render the elements of the excalidraw scene to the SVG, which is fitted to the bounds of the elements.
+pub fn to_svg(scene: &Value) -> String {
+    let elements: Vec<&Value> = scene
+        .get("elements")
+        .and_then(Value::as_array)
+        .map(|elements| {
+            elements
+                .iter()
+                .filter(|e| !e.get("isDeleted").and_then(Value::as_bool).unwrap_or(false))
+                .collect()
+        })
+        .unwrap_or_default();
+    let (x1, y1, x2, y2) = if elements.is_empty() {
+        (0.0, 0.0, 0.0, 0.0)
+    } else {
+        elements
+            .iter()
+            .map(|e| bounds_of(e))
+            .fold((f64::MAX, f64::MAX, f64::MIN, f64::MIN), |(x1, y1, x2, y2), (a1, b1, a2, b2)| {
+                (x1.min(a1), y1.min(b1), x2.max(a2), y2.max(b2))
+            })
+    };
+    let (x, y) = (x1 - PADDING, y1 - PADDING);
+    let (width, height) = (x2 - x1 + PADDING * 2.0, y2 - y1 + PADDING * 2.0);
+    let background = scene
+        .pointer("/appState/viewBackgroundColor")
+        .and_then(Value::as_str)
+        .filter(|c| !c.is_empty())
+        .unwrap_or(DEFAULT_BACKGROUND);
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\">",
+        width,
+        height,
+        x,
+        y,
+        width,
+        height
+    );
+    let _ = writeln!(
+        svg,
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+        x,
+        y,
+        width,
+        height,
+        escape_html(background)
+    );
+    for element in elements {
+        write_element(&mut svg, element);
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// Rasterise the SVG to the PNG, which is scaled down to fit the max size and keeps the aspect ratio.
+pub fn to_png(svg: &str, max_width: u32, max_height: u32) -> Result<Vec<u8>, Error> {
+    let fontdb = FONT_DB.get_or_init(|| {
+        let mut fontdb = usvg::fontdb::Database::new();
+        fontdb.load_system_fonts();
+        Arc::new(fontdb)
+    });
+    let options = usvg::Options { fontdb: fontdb.clone(), ..Default::default() };
+    let tree = usvg::Tree::from_str(svg, &options)?;
+
+    let size = tree.size();
+    let scale = (max_width.max(1) as f32 / size.width())
+        .min(max_height.max(1) as f32 / size.height())
+        .min(1.0);
+    let (width, height) = (
+        ((size.width() * scale).ceil() as u32).max(1),
+        ((size.height() * scale).ceil() as u32).max(1),
+    );
+    let mut pixmap = tiny_skia::Pixmap
+        ::new(width, height)
+        .ok_or_else(|| Error::msg(format!("Invalid the size of the thumbnail: {}x{}", width, height)))?;
+    resvg::render(&tree, tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+    Ok(pixmap.encode_png()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn scene() -> Value {
+        json!({
+            "type": "excalidraw",
+            "elements": [
+                { "id": "r1", "type": "rectangle", "x": 0, "y": 0, "width": 100, "height": 50,
+                  "backgroundColor": "#a5d8ff", "roundness": { "type": 3 } },
+                { "id": "e1", "type": "ellipse", "x": 200, "y": 0, "width": 80, "height": 80, "angle": 0.5 },
+                { "id": "a1", "type": "arrow", "x": 100, "y": 25, "points": [[0, 0], [100, 15]],
+                  "strokeStyle": "dashed", "endArrowhead": "arrow" },
+                { "id": "f1", "type": "freedraw", "x": 0, "y": 100, "points": [[0, 0], [5, 3], [10, 8]] },
+                { "id": "t1", "type": "text", "x": 10, "y": 10, "width": 80, "height": 25,
+                  "text": "a < b\nc", "fontSize": 20, "textAlign": "center" },
+                { "id": "d1", "type": "diamond", "x": 0, "y": 0, "width": 10, "height": 10, "isDeleted": true }
+            ],
+            "appState": { "viewBackgroundColor": "#f8f9fa" }
+        })
+    }
+
+    #[test]
+    fn test_to_svg() {
+        let svg = to_svg(&scene());
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"312\" height=\"140\""));
+        assert!(svg.contains("fill=\"#f8f9fa\""));
+        assert!(svg.contains("<rect x=\"0\" y=\"0\" width=\"100\" height=\"50\" rx=\"5\""));
+        assert!(svg.contains("fill=\"#a5d8ff\""));
+        assert!(svg.contains("<ellipse cx=\"240\" cy=\"40\" rx=\"40\" ry=\"40\""));
+        assert!(svg.contains("rotate("));
+        assert!(svg.contains("<path d=\"M100 25 L200 40 M"));
+        assert!(svg.contains("stroke-dasharray=\"8 8\""));
+        assert!(svg.contains("<path d=\"M0 100 L5 103 L10 108\""));
+        assert!(svg.contains("text-anchor=\"middle\""));
+        assert!(svg.contains(">a &lt; b</tspan>"));
+        assert!(svg.contains(">c</tspan>"));
+        assert!(!svg.contains("<polygon"));
+    }
+
+    #[test]
+    fn test_to_svg_empty() {
+        let svg = to_svg(&json!({}));
+        assert!(svg.contains("width=\"32\" height=\"32\" viewBox=\"-16 -16 32 32\""));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn test_to_svg_escape_attributes() {
+        let scene = json!({
+            "elements": [
+                { "id": "r1", "type": "rectangle", "x": 0, "y": 0, "width": 1, "height": 1,
+                  "strokeColor": "red\" onload=\"alert(1)" }
+            ]
+        });
+        let svg = to_svg(&scene);
+        assert!(!svg.contains("\" onload=\""));
+        assert!(svg.contains("red&quot; onload=&quot;alert(1)"));
+    }
+
+    #[test]
+    fn test_to_png() {
+        let png = to_png(&to_svg(&scene()), 156, 1000).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        // The width of the image header is scaled to half of the svg.
+        assert_eq!(u32::from_be_bytes(png[16..20].try_into().unwrap()), 156);
+        assert_eq!(u32::from_be_bytes(png[20..24].try_into().unwrap()), 70);
+        assert!(to_png("<svg", 10, 10).is_err());
+    }
+}
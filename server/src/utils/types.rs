@@ -20,9 +20,48 @@
  * This includes modifications and derived works.
  */
 
+#[derive(Clone, Debug, PartialEq)]
 pub enum GenericValue {
+    Null,
     Int32(i32),
     Int64(i64),
+    Float64(f64),
     Bool(bool),
     String(String),
 }
+
+impl From<i32> for GenericValue {
+    fn from(value: i32) -> Self {
+        GenericValue::Int32(value)
+    }
+}
+
+impl From<i64> for GenericValue {
+    fn from(value: i64) -> Self {
+        GenericValue::Int64(value)
+    }
+}
+
+impl From<f64> for GenericValue {
+    fn from(value: f64) -> Self {
+        GenericValue::Float64(value)
+    }
+}
+
+impl From<bool> for GenericValue {
+    fn from(value: bool) -> Self {
+        GenericValue::Bool(value)
+    }
+}
+
+impl From<&str> for GenericValue {
+    fn from(value: &str) -> Self {
+        GenericValue::String(value.to_string())
+    }
+}
+
+impl From<String> for GenericValue {
+    fn from(value: String) -> Self {
+        GenericValue::String(value)
+    }
+}
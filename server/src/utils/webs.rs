@@ -92,6 +92,11 @@ pub fn is_browser(headers: &HeaderMap) -> bool {
     user_agent.contains("Mozilla")
 }
 
+// The strong ETag of the version of the optimistic lock.
+pub fn to_etag(version: i64) -> String {
+    format!("\"{}\"", version)
+}
+
 pub fn response_redirect_or_json(
     status: StatusCode,
     headers: &HeaderMap,
@@ -21,3 +21,4 @@
  */
 
 pub mod cache;
+pub mod route;
@@ -0,0 +1,138 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+use axum::{ http::StatusCode, Router };
+use serde_json::{ json, Value };
+
+use super::{ call, create_test_state_with, create_token, create_users, get, post_json };
+
+async fn save(app: &Router, token: &str, uri: &str, body: Value) -> i64 {
+    let (status, resp) = call(app, token, post_json(uri, body)).await;
+    assert_eq!(status, StatusCode::OK);
+    resp["id"].as_i64().unwrap()
+}
+
+async fn grant(app: &Router, token: &str, resource_type: &str, id: i64, uid: i64, role: &str) -> (StatusCode, Value) {
+    let email = format!("user{}@mywebnote.local", uid);
+    let body = json!({ "resourceType": resource_type, "resourceId": id, "email": email, "role": role });
+    call(app, token, post_json("/modules/acl/grant", body)).await
+}
+
+#[tokio::test]
+async fn test_acl_document_viewer() {
+    let (config, state, app) = create_test_state_with(|_| {}).await;
+    create_users(&state, &[1, 2, 3]).await;
+    let owner = create_token(&config, 1);
+    let viewer = create_token(&config, 2);
+    let stranger = create_token(&config, 3);
+
+    let body = json!({ "key": "n1", "name": "note", "type": "Note", "content": "hello" });
+    let doc = save(&app, &owner, "/modules/document/save", body).await;
+    let (status, resp) = grant(&app, &owner, "document", doc, 2, "viewer").await;
+    assert_eq!(status, StatusCode::OK);
+    let acl = resp["id"].as_i64().unwrap();
+
+    // The grantee reads the shared document of the owner, but could not write it.
+    let (status, resp) = call(&app, &viewer, get("/modules/document/query?owner_uid=1")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["data"].as_array().unwrap().len(), 1);
+    assert_eq!(resp["data"][0]["content"], json!("hello"));
+    let body = json!({ "id": doc, "content": "changed", "version": 1 });
+    let (status, _) = call(&app, &viewer, post_json("/modules/document/save", body.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = call(&app, &viewer, post_json("/modules/document/delete", json!({ "id": doc }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // The others are not aware of the document at all.
+    let (_, resp) = call(&app, &stranger, get("/modules/document/query?owner_uid=1")).await;
+    assert!(resp["data"].as_array().unwrap().is_empty());
+    let (status, _) = call(&app, &stranger, post_json("/modules/document/save", body)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (_, resp) = call(&app, &viewer, get("/modules/acl/shared")).await;
+    assert_eq!(resp["data"][0]["resource_type"], json!("document"));
+    assert_eq!(resp["data"][0]["name"], json!("note"));
+    assert_eq!(resp["data"][0]["role"], json!("viewer"));
+
+    // The grant is only manageable by the owner.
+    let (status, _) = call(&app, &viewer, post_json("/modules/acl/revoke", json!({ "id": acl }))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = call(&app, &owner, post_json("/modules/acl/revoke", json!({ "id": acl }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, resp) = call(&app, &viewer, get("/modules/acl/shared")).await;
+    assert!(resp["data"].as_array().unwrap().is_empty());
+    let (status, _) = call(&app, &viewer, post_json("/modules/document/delete", json!({ "id": doc }))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_acl_folder_editor() {
+    let (config, state, app) = create_test_state_with(|_| {}).await;
+    create_users(&state, &[1, 2]).await;
+    let owner = create_token(&config, 1);
+    let editor = create_token(&config, 2);
+
+    let f1 = save(&app, &owner, "/modules/folder/save", json!({ "pid": 0, "key": "f1", "name": "f1" })).await;
+    let f2 = save(&app, &owner, "/modules/folder/save", json!({ "pid": f1, "key": "f2", "name": "f2" })).await;
+    let body = json!({ "key": "d1", "name": "d1", "folderKey": "f2", "content": "v1" });
+    let doc = save(&app, &owner, "/modules/document/save", body).await;
+    let body = json!({ "key": "d2", "name": "d2", "content": "private" });
+    let private = save(&app, &owner, "/modules/document/save", body).await;
+
+    // The unknown users and the owner self are rejected.
+    assert_eq!(grant(&app, &owner, "folder", f1, 9, "editor").await.0, StatusCode::NOT_FOUND);
+    assert_eq!(grant(&app, &owner, "folder", f1, 1, "editor").await.0, StatusCode::BAD_REQUEST);
+    assert_eq!(grant(&app, &editor, "folder", f1, 1, "editor").await.0, StatusCode::NOT_FOUND);
+    // The role of the existing grant is replaced.
+    let (_, resp) = grant(&app, &owner, "folder", f1, 2, "viewer").await;
+    let (_, replaced) = grant(&app, &owner, "folder", f1, 2, "editor").await;
+    assert_eq!(resp["id"], replaced["id"]);
+    let (_, resp) = call(&app, &owner, get(&format!("/modules/acl/query?resource_id={}", f1))).await;
+    assert_eq!(resp["data"].as_array().unwrap().len(), 1);
+    assert_eq!(resp["data"][0]["role"], json!("editor"));
+
+    // The grant of the folder applies to the whole subtree.
+    let (_, resp) = call(&app, &editor, get("/modules/folder/query?owner_uid=1")).await;
+    assert_eq!(resp["data"].as_array().unwrap().len(), 2);
+    let (_, resp) = call(&app, &editor, get("/modules/document/query?owner_uid=1")).await;
+    assert_eq!(resp["data"].as_array().unwrap().len(), 1);
+    assert_eq!(resp["data"][0]["key"], json!("d1"));
+
+    let body = json!({ "id": doc, "content": "v2", "version": 1 });
+    let (status, resp) = call(&app, &editor, post_json("/modules/document/save", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["version"], json!(2));
+    let (_, resp) = call(&app, &owner, get("/modules/document/query?key=d1")).await;
+    assert_eq!(resp["data"][0]["content"], json!("v2"));
+    let body = json!({ "id": f2, "name": "renamed", "version": 1 });
+    let (status, _) = call(&app, &editor, post_json("/modules/folder/save", body)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The documents out of the shared folder are still private.
+    let body = json!({ "id": private, "content": "changed", "version": 1 });
+    let (status, _) = call(&app, &editor, post_json("/modules/document/save", body)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = call(&app, &editor, post_json("/modules/document/delete", json!({ "id": doc }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, resp) = call(&app, &owner, get("/modules/document/query?key=d1")).await;
+    assert!(resp["data"].as_array().unwrap().is_empty());
+}
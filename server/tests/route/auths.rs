@@ -0,0 +1,67 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use axum::{ body::Body, http::{ Request, StatusCode } };
+use serde_json::json;
+
+use super::{ call, create_test_app, create_token };
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_requests_isolated_by_user() {
+    let (config, app) = create_test_app().await;
+    let tokens = [create_token(&config, 1), create_token(&config, 2)];
+
+    // Fires the interleaved save requests as two different users.
+    let mut handles = Vec::new();
+    for i in 0..40 {
+        let (app, uid) = (app.clone(), (i % 2) + 1);
+        let token = tokens[(i % 2) as usize].clone();
+        handles.push(
+            tokio::spawn(async move {
+                let body = json!({ "key": format!("u{}-{}", uid, i), "name": "n", "content": "c" });
+                let req = Request::post("/modules/document/save")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap();
+                let (status, _) = call(&app, &token, req).await;
+                assert_eq!(status, StatusCode::OK);
+            })
+        );
+    }
+    for handle in handles {
+        handle.await.unwrap();
+    }
+
+    for (uid, token) in [1, 2].iter().zip(tokens.iter()) {
+        let req = Request::get("/modules/document/query?limit=100").body(Body::empty()).unwrap();
+        let (status, resp) = call(&app, token, req).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let data = resp["data"].as_array().unwrap();
+        assert_eq!(data.len(), 20);
+        for doc in data {
+            assert_eq!(doc["owner_uid"], json!(uid));
+            assert_eq!(doc["create_by"], json!(format!("user{}@mywebnote.local", uid)));
+            assert!(doc["key"].as_str().unwrap().starts_with(&format!("u{}-", uid)));
+        }
+    }
+}
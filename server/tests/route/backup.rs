@@ -0,0 +1,309 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use std::io::{ Cursor, Read, Write };
+
+use axum::{ body::Body, http::{ Request, StatusCode }, Router };
+use serde_json::{ json, Value };
+use sha2::{ Digest, Sha256 };
+
+use mywebnote::context::state::AppState;
+use mywebnote::handler::backup::{ backup, backup_to_dir, restore, BackupManifest, RestoreMode, RestoreReport };
+
+use super::{ call, create_test_state_with, create_token, create_users, get, post_json };
+
+async fn save(app: &Router, token: &str, uri: &str, body: Value) -> i64 {
+    let (status, resp) = call(app, token, post_json(uri, body)).await;
+    assert_eq!(status, StatusCode::OK);
+    resp["id"].as_i64().unwrap()
+}
+
+async fn query_contents(app: &Router, token: &str, uri: &str) -> Vec<(String, String)> {
+    let (status, resp) = call(app, token, get(uri)).await;
+    assert_eq!(status, StatusCode::OK);
+    let mut contents: Vec<(String, String)> = resp["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| (d["key"].as_str().unwrap().to_string(), d["content"].as_str().unwrap_or_default().to_string()))
+        .collect();
+    contents.sort();
+    contents
+}
+
+async fn backup_archive(state: &AppState, owner_uid: Option<i64>) -> (BackupManifest, Vec<u8>) {
+    let (manifest, output) = backup(state, owner_uid, Cursor::new(Vec::new())).await.unwrap();
+    (manifest, output.into_inner())
+}
+
+// The names and the checksums of all the files of the archive, which the manifest is excluded of the
+// creation time.
+fn archive_files(archive: &[u8]) -> Vec<(String, String)> {
+    let mut reader = zip::ZipArchive::new(Cursor::new(archive)).unwrap();
+    let mut files = Vec::new();
+    for i in 0..reader.len() {
+        let mut file = reader.by_index(i).unwrap();
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).unwrap();
+        if file.name() != "manifest.json" {
+            files.push((file.name().to_string(), hex::encode(Sha256::digest(&data))));
+        }
+    }
+    files.sort();
+    files
+}
+
+fn report_of<'a>(reports: &'a [RestoreReport], name: &str) -> &'a RestoreReport {
+    reports
+        .iter()
+        .find(|r| r.name == name)
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_backup_and_restore_merge_and_replace() {
+    let (config, state, app) = create_test_state_with(|_| {}).await;
+    let (token1, token2) = (create_token(&config, 1), create_token(&config, 2));
+
+    let req = Request::post("/modules/blob/upload")
+        .header("Content-Type", "text/plain")
+        .body(Body::from("attachment"))
+        .unwrap();
+    let (_, resp) = call(&app, &token1, req).await;
+    let hash = resp["hash"].as_str().unwrap().to_string();
+    save(&app, &token1, "/modules/folder/save", json!({ "pid": 0, "key": "f1", "name": "f1" })).await;
+    let content = format!("see /modules/blob/download?hash={}", hash);
+    let body = json!({ "key": "d1", "name": "d1", "folderKey": "f1", "content": content });
+    let d1 = save(&app, &token1, "/modules/document/save", body).await;
+    let d2 = save(&app, &token1, "/modules/document/save", json!({ "key": "d2", "name": "d2", "content": "c2" })).await;
+    call(&app, &token1, post_json("/modules/document/delete", json!({ "id": d2 }))).await;
+    save(&app, &token2, "/modules/document/save", json!({ "key": "d3", "name": "d3", "content": "c3" })).await;
+
+    // The trashed are also included.
+    let (manifest, archive) = backup_archive(&state, None).await;
+    let count_of = |name: &str| manifest.entries.iter().find(|e| e.name == name).unwrap().count;
+    assert_eq!(manifest.owner_uid, None);
+    assert_eq!(count_of("documents"), 3);
+    assert_eq!(count_of("folders"), 1);
+    assert_eq!(count_of("blobs"), 1);
+    assert_eq!(count_of("document_blobs"), 1);
+    let (manifest, _) = backup_archive(&state, Some(2)).await;
+    assert_eq!(manifest.owner_uid, Some(2));
+    assert_eq!(manifest.entries.iter().find(|e| e.name == "documents").unwrap().count, 1);
+    assert_eq!(manifest.entries.iter().find(|e| e.name == "blobs").unwrap().count, 0);
+
+    // Restore to the empty repository.
+    let (config, target, app) = create_test_state_with(|_| {}).await;
+    let (token1, token2) = (create_token(&config, 1), create_token(&config, 2));
+    let reports = restore(&target, Cursor::new(archive.clone()), RestoreMode::Merge, None).await.unwrap();
+    assert_eq!(report_of(&reports, "documents").restored, 3);
+    let expected = vec![("d1".to_string(), content)];
+    assert_eq!(query_contents(&app, &token1, "/modules/document/query").await, expected);
+    assert_eq!(query_contents(&app, &token1, "/modules/document/trash/query").await.len(), 1);
+    assert_eq!(query_contents(&app, &token2, "/modules/document/query").await.len(), 1);
+    let (status, _) = call(&app, &token1, get(&format!("/modules/blob/download?hash={}", hash))).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The existing are skipped of the merge.
+    let body = json!({ "id": d1, "version": 1, "content": "changed" });
+    save(&app, &token1, "/modules/document/save", body).await;
+    save(&app, &token2, "/modules/document/save", json!({ "key": "d4", "name": "d4", "content": "c4" })).await;
+    let reports = restore(&target, Cursor::new(archive.clone()), RestoreMode::Merge, None).await.unwrap();
+    assert_eq!(report_of(&reports, "documents").restored, 0);
+    assert_eq!(report_of(&reports, "documents").skipped, 3);
+    assert_eq!(query_contents(&app, &token1, "/modules/document/query").await[0].1, "changed");
+
+    // The replace of the user only deletes the existing of the user.
+    let reports = restore(&target, Cursor::new(archive.clone()), RestoreMode::Replace, Some(1)).await.unwrap();
+    assert_eq!(report_of(&reports, "documents").count, 2);
+    assert_eq!(report_of(&reports, "documents").deleted, 2);
+    assert_eq!(report_of(&reports, "documents").restored, 2);
+    assert_eq!(query_contents(&app, &token1, "/modules/document/query").await, expected);
+    let keys: Vec<String> = query_contents(&app, &token2, "/modules/document/query").await
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect();
+    assert_eq!(keys, vec!["d3", "d4"]);
+}
+
+#[tokio::test]
+async fn test_restore_rejects_tampered_archive() {
+    let (config, state, app) = create_test_state_with(|_| {}).await;
+    let token = create_token(&config, 1);
+    save(&app, &token, "/modules/document/save", json!({ "key": "d1", "name": "d1", "content": "c1" })).await;
+    let (_, archive) = backup_archive(&state, None).await;
+
+    // Rewrite the documents file of the archive, but keep the manifest as is.
+    let mut reader = zip::ZipArchive::new(Cursor::new(archive)).unwrap();
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for i in 0..reader.len() {
+        let mut file = reader.by_index(i).unwrap();
+        let mut data = String::new();
+        file.read_to_string(&mut data).unwrap();
+        if file.name() == "documents.jsonl" {
+            data = data.replace("c1", "c2");
+        }
+        writer.start_file(file.name(), zip::write::SimpleFileOptions::default()).unwrap();
+        writer.write_all(data.as_bytes()).unwrap();
+    }
+    let tampered = writer.finish().unwrap().into_inner();
+
+    let (_, target, _) = create_test_state_with(|_| {}).await;
+    let err = restore(&target, Cursor::new(tampered), RestoreMode::Merge, None).await.unwrap_err();
+    assert!(err.to_string().contains("documents.jsonl"), "{}", err);
+    let (manifest, _) = backup_archive(&target, None).await;
+    assert!(manifest.entries.iter().all(|e| e.count == 0));
+}
+
+#[tokio::test]
+async fn test_backup_restore_round_trip() {
+    let (config, state, app) = create_test_state_with(|_| {}).await;
+    let (token1, token2) = (create_token(&config, 1), create_token(&config, 2));
+    let req = Request::post("/modules/blob/upload")
+        .header("Content-Type", "text/plain")
+        .body(Body::from("attachment"))
+        .unwrap();
+    let (_, resp) = call(&app, &token1, req).await;
+    let hash = resp["hash"].as_str().unwrap().to_string();
+    save(&app, &token1, "/modules/folder/save", json!({ "pid": 0, "key": "f1", "name": "f1" })).await;
+    let content = format!("see /modules/blob/download?hash={}", hash);
+    let body = json!({ "key": "d1", "name": "d1", "folderKey": "f1", "content": content });
+    let d1 = save(&app, &token1, "/modules/document/save", body).await;
+    save(&app, &token1, "/modules/document/save", json!({ "id": d1, "version": 1, "content": "c1" })).await;
+    let d2 = save(&app, &token1, "/modules/document/save", json!({ "key": "d2", "name": "d2", "content": "c2" })).await;
+    call(&app, &token1, post_json("/modules/document/delete", json!({ "id": d2 }))).await;
+    let tag = save(&app, &token1, "/modules/tag/save", json!({ "name": "work" })).await;
+    let body = json!({ "documentIds": [d1], "tagIds": [tag] });
+    call(&app, &token1, post_json("/modules/tag/attach", body)).await;
+    call(&app, &token1, post_json("/modules/share/create", json!({ "documentId": d1 }))).await;
+    save(&app, &token2, "/modules/document/save", json!({ "key": "d3", "name": "d3", "content": "c3" })).await;
+    create_users(&state, &[1, 2]).await;
+    let email = "user2@mywebnote.local";
+    let body = json!({ "resourceType": "document", "resourceId": d1, "email": email, "role": "viewer" });
+    let (status, _) = call(&app, &token1, post_json("/modules/acl/grant", body)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The backup of the restored is the same as the origin.
+    let (manifest, archive) = backup_archive(&state, None).await;
+    let names = ["users", "folders", "documents", "document_revisions", "tags", "document_tags", "share_links", "acls"];
+    for name in names {
+        assert!(manifest.entries.iter().any(|e| e.name == name && e.count > 0), "{}", name);
+    }
+    let (_, target, _) = create_test_state_with(|_| {}).await;
+    restore(&target, Cursor::new(archive.clone()), RestoreMode::Merge, None).await.unwrap();
+    let (restored_manifest, restored) = backup_archive(&target, None).await;
+    assert_eq!(restored_manifest.entries, manifest.entries);
+    assert_eq!(archive_files(&restored), archive_files(&archive));
+}
+
+// Rewrite the file of the module of the archive, which the checksum of the manifest is rewritten as well.
+fn rewrite_module(archive: Vec<u8>, name: &str, rewrite: impl Fn(&str) -> String) -> Vec<u8> {
+    let mut reader = zip::ZipArchive::new(Cursor::new(archive)).unwrap();
+    let mut files = Vec::new();
+    for i in 0..reader.len() {
+        let mut file = reader.by_index(i).unwrap();
+        let mut data = String::new();
+        file.read_to_string(&mut data).unwrap();
+        files.push((file.name().to_string(), data));
+    }
+    let file = format!("{}.jsonl", name);
+    let rewritten = rewrite(&files.iter().find(|(n, _)| *n == file).unwrap().1);
+    let mut manifest: BackupManifest = serde_json::from_str(
+        &files.iter().find(|(n, _)| n == "manifest.json").unwrap().1
+    ).unwrap();
+    let entry = manifest.entries.iter_mut().find(|e| e.name == name).unwrap();
+    entry.checksum = hex::encode(Sha256::digest(rewritten.as_bytes()));
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for (n, data) in files {
+        let data = match n.as_str() {
+            "manifest.json" => serde_json::to_string(&manifest).unwrap(),
+            _ if n == file => rewritten.clone(),
+            _ => data,
+        };
+        writer.start_file(n, zip::write::SimpleFileOptions::default()).unwrap();
+        writer.write_all(data.as_bytes()).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+async fn query_keys(app: &Router, token: &str) -> Vec<String> {
+    query_contents(app, token, "/modules/document/query").await
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_restore_replace_keeps_existing_of_invalid_archive() {
+    let (config, state, app) = create_test_state_with(|_| {}).await;
+    let token = create_token(&config, 1);
+    save(&app, &token, "/modules/document/save", json!({ "key": "d1", "name": "d1", "content": "c1" })).await;
+    let (_, archive) = backup_archive(&state, None).await;
+    // The invalid row of the documents.
+    let invalid_archive = rewrite_module(archive, "documents", |data| format!("{}{{\"name\":\"no id\"}}\n", data));
+
+    save(&app, &token, "/modules/document/save", json!({ "key": "d2", "name": "d2", "content": "c2" })).await;
+    let err = restore(&state, Cursor::new(invalid_archive), RestoreMode::Replace, None).await.unwrap_err();
+    assert!(err.to_string().contains("line 2 of the archive documents"), "{}", err);
+    assert_eq!(query_keys(&app, &token).await, vec!["d1", "d2"]);
+}
+
+#[tokio::test]
+async fn test_restore_replace_rollback_of_conflict() {
+    let (config, state, app) = create_test_state_with(|_| {}).await;
+    let token = create_token(&config, 1);
+    save(&app, &token, "/modules/document/save", json!({ "key": "d1", "name": "d1", "content": "c1" })).await;
+    let (_, archive) = backup_archive(&state, None).await;
+    // The duplicated row of the documents, which is readable but violates the primary key when inserted.
+    let conflict_archive = rewrite_module(archive, "documents", |data| format!("{}{}", data, data));
+
+    save(&app, &token, "/modules/document/save", json!({ "key": "d2", "name": "d2", "content": "c2" })).await;
+    let err = restore(&state, Cursor::new(conflict_archive), RestoreMode::Replace, None).await.unwrap_err();
+    assert!(err.to_string().contains("Failed to restore the documents"), "{}", err);
+    // The deletes of the documents are rolled back along with the failed inserts.
+    assert_eq!(query_keys(&app, &token).await, vec!["d1", "d2"]);
+}
+
+#[tokio::test]
+async fn test_backup_to_dir_rotation() {
+    let dir = std::env::temp_dir().join(format!("mywebnote-it-backups-{}", uuid::Uuid::new_v4()));
+    let (_, state, _) = create_test_state_with(|p| {
+        p.webnote.backup.dir = dir.to_string_lossy().to_string();
+        p.webnote.backup.keep = 2;
+    }).await;
+
+    let mut paths = Vec::new();
+    for _ in 0..3 {
+        paths.push(backup_to_dir(&state).await.unwrap());
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    let mut names: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    let expected: Vec<String> = paths[1..]
+        .iter()
+        .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(names, expected);
+}
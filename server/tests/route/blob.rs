@@ -0,0 +1,208 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use axum::{ body::{ self, Body, Bytes }, http::{ HeaderMap, Request, StatusCode }, Router };
+use serde_json::json;
+use tower::ServiceExt;
+
+use mywebnote::handler::blob::gc_unreferenced;
+
+use super::{ call, create_test_app_with, create_test_state_with, create_token, create_users, get, post_json };
+
+fn upload(content_type: &str, content: Vec<u8>) -> Request<Body> {
+    Request::post("/modules/blob/upload")
+        .header("Content-Type", content_type)
+        .body(Body::from(content))
+        .unwrap()
+}
+
+async fn download(app: &Router, token: &str, hash: &str, etag: Option<&str>) -> (StatusCode, HeaderMap, Bytes) {
+    let mut req = get(&format!("/modules/blob/download?hash={}", hash));
+    req.headers_mut().insert("Authorization", format!("Bearer {}", token).parse().unwrap());
+    if let Some(etag) = etag {
+        req.headers_mut().insert("If-None-Match", etag.parse().unwrap());
+    }
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let (status, headers) = (resp.status(), resp.headers().clone());
+    (status, headers, body::to_bytes(resp.into_body(), usize::MAX).await.unwrap())
+}
+
+async fn query_hashes(app: &Router, token: &str, document_id: i64) -> Vec<String> {
+    let uri = format!("/modules/blob/query?document_id={}", document_id);
+    let (status, resp) = call(app, token, get(&uri)).await;
+    assert_eq!(status, StatusCode::OK);
+    resp["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|b| b["hash"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_blob_upload_download_and_gc() {
+    let (config, state, app) = create_test_state_with(|p| {
+        p.webnote.blob.gc_grace = 0;
+        p.webnote.revision.keep_last = Some(2);
+    }).await;
+    let token = create_token(&config, 1);
+
+    let image = b"\x89PNG\r\n\x1a\nfake image".to_vec();
+    let (status, resp) = call(&app, &token, upload("image/png", image.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+    let hash = resp["hash"].as_str().unwrap().to_string();
+    assert_eq!(hash.len(), 64);
+    assert_eq!(resp["mime_type"], json!("image/png"));
+    assert_eq!(resp["size"], json!(image.len()));
+
+    // The same content is addressed to the same blob.
+    let (_, resp) = call(&app, &token, upload("image/png", image.clone())).await;
+    assert_eq!(resp["hash"], json!(hash));
+
+    let (status, headers, bytes) = download(&app, &token, &hash, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["content-type"], "image/png");
+    assert_eq!(headers["content-length"], image.len().to_string().as_str());
+    assert_eq!(bytes.to_vec(), image);
+    let etag = headers["etag"].to_str().unwrap().to_string();
+    let (status, _, _) = download(&app, &token, &hash, Some(&etag)).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+
+    // The blob is only visible to the uploader.
+    let other = create_token(&config, 2);
+    let (status, _, _) = download(&app, &other, &hash, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _, _) = download(&app, &token, "not-a-hash", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // The references are synced from the content of the document.
+    let content = json!({ "files": { "f1": { "dataURL": format!("/modules/blob/download?hash={}", hash) } } });
+    let body = json!({ "key": "d1", "name": "d1", "content": content.to_string() });
+    let (status, resp) = call(&app, &token, post_json("/modules/document/save", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    let document_id = resp["id"].as_i64().unwrap();
+    assert_eq!(query_hashes(&app, &token, document_id).await, vec![hash.clone()]);
+
+    // The unreferenced blob is collected, but the referenced is retained.
+    let (_, resp) = call(&app, &token, upload("text/plain", b"unreferenced".to_vec())).await;
+    let unreferenced = resp["hash"].as_str().unwrap().to_string();
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    assert_eq!(gc_unreferenced(&state).await.unwrap(), 1);
+    let (status, _, _) = download(&app, &token, &unreferenced, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _, _) = download(&app, &token, &hash, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Remove the reference from the content, but it's still referenced by the retained revision.
+    let body = json!({ "id": document_id, "version": 1, "content": "{}" });
+    let (status, _) = call(&app, &token, post_json("/modules/document/save", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(query_hashes(&app, &token, document_id).await, vec![hash.clone()]);
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    assert_eq!(gc_unreferenced(&state).await.unwrap(), 0);
+    let (status, _, _) = download(&app, &token, &hash, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The revision is purged out of the retention, and then it's collectable.
+    let body = json!({ "id": document_id, "version": 2, "content": "{ }" });
+    let (status, _) = call(&app, &token, post_json("/modules/document/save", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(query_hashes(&app, &token, document_id).await.is_empty());
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    assert_eq!(gc_unreferenced(&state).await.unwrap(), 1);
+    let (status, _, _) = download(&app, &token, &hash, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_blob_upload_exceeds_max_size() {
+    let (config, app) = create_test_app_with(|p| {
+        p.webnote.blob.max_size = 1024;
+    }).await;
+    let token = create_token(&config, 1);
+
+    let (status, _) = call(&app, &token, upload("application/octet-stream", vec![0u8; 2048])).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    let (status, resp) = call(&app, &token, upload("application/octet-stream", vec![0u8; 1024])).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["mime_type"], json!("application/octet-stream"));
+}
+
+#[tokio::test]
+async fn test_blob_download_not_inline_of_scriptable() {
+    let (config, app) = create_test_app_with(|_| {}).await;
+    let token = create_token(&config, 1);
+
+    // The scriptable (e.g. SVG and HTML) are stored as the default, and downloaded as the attachment.
+    let svg = b"<svg xmlns='http://www.w3.org/2000/svg'><script>alert(1)</script></svg>".to_vec();
+    for content_type in ["image/svg+xml", "text/html; charset=utf-8"] {
+        let (status, resp) = call(&app, &token, upload(content_type, svg.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(resp["mime_type"], json!("application/octet-stream"));
+        let (status, headers, _) = download(&app, &token, resp["hash"].as_str().unwrap(), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers["content-type"], "application/octet-stream");
+        assert_eq!(headers["content-disposition"], "attachment");
+        assert_eq!(headers["x-content-type-options"], "nosniff");
+        assert_eq!(headers["content-security-policy"], "sandbox");
+    }
+
+    let (_, resp) = call(&app, &token, upload("image/png; charset=binary", b"png".to_vec())).await;
+    assert_eq!(resp["mime_type"], json!("image/png"));
+    let (_, headers, _) = download(&app, &token, resp["hash"].as_str().unwrap(), None).await;
+    assert_eq!(headers["content-type"], "image/png");
+    assert_eq!(headers["content-disposition"], "inline");
+    assert_eq!(headers["x-content-type-options"], "nosniff");
+}
+
+#[tokio::test]
+async fn test_blob_uploaded_by_editor() {
+    let (config, state, app) = create_test_state_with(|p| {
+        p.webnote.blob.gc_grace = 0;
+    }).await;
+    create_users(&state, &[1, 2]).await;
+    let owner = create_token(&config, 1);
+    let editor = create_token(&config, 2);
+
+    let body = json!({ "key": "b1", "name": "b1", "type": "Board", "content": "{}" });
+    let (_, resp) = call(&app, &owner, post_json("/modules/document/save", body)).await;
+    let document_id = resp["id"].as_i64().unwrap();
+    let email = "user2@mywebnote.local";
+    let body = json!({ "resourceType": "document", "resourceId": document_id, "email": email, "role": "editor" });
+    let (status, _) = call(&app, &owner, post_json("/modules/acl/grant", body)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The image uploaded by the editor is owned by the owner as well once referenced.
+    let (_, resp) = call(&app, &editor, upload("image/png", b"editor image".to_vec())).await;
+    let hash = resp["hash"].as_str().unwrap().to_string();
+    let content = json!({ "files": { "f1": { "dataURL": format!("/modules/blob/download?hash={}", hash) } } });
+    let body = json!({ "id": document_id, "version": 1, "content": content.to_string() });
+    let (status, _) = call(&app, &editor, post_json("/modules/document/save", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(query_hashes(&app, &owner, document_id).await, vec![hash.clone()]);
+
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    assert_eq!(gc_unreferenced(&state).await.unwrap(), 0);
+    let (status, headers, _) = download(&app, &owner, &hash, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["content-type"], "image/png");
+}
@@ -0,0 +1,228 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use std::io::{ Cursor, Write };
+
+use axum::{ body::{ self, Body }, http::{ HeaderMap, Request, StatusCode }, Router };
+use serde_json::{ json, Value };
+use tower::ServiceExt;
+
+use super::{ call, create_test_app, create_test_app_with, create_token, get, post_json };
+
+const IMAGE_DATA_URL: &str = "data:image/png;base64,iVBORw0KGgo=";
+
+fn import(uri: &str, content: Vec<u8>) -> Request<Body> {
+    Request::post(uri).body(Body::from(content)).unwrap()
+}
+
+async fn export(app: &Router, token: &str, id: i64) -> (StatusCode, HeaderMap, Value) {
+    let mut req = get(&format!("/modules/board/export?id={}", id));
+    req.headers_mut().insert("Authorization", format!("Bearer {}", token).parse().unwrap());
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let (status, headers) = (resp.status(), resp.headers().clone());
+    let bytes = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, headers, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+async fn query_board(app: &Router, token: &str, name: &str) -> Value {
+    let uri = format!("/modules/document/query?name={}", name);
+    let (status, resp) = call(app, token, get(&uri)).await;
+    assert_eq!(status, StatusCode::OK);
+    resp["data"][0].clone()
+}
+
+fn excalidraw_file() -> Value {
+    json!({
+        "type": "excalidraw",
+        "version": 2,
+        "source": "https://excalidraw.com",
+        "elements": [
+            { "id": "r1", "type": "rectangle", "x": 0, "y": 0, "width": 100, "height": 50 },
+            { "id": "i1", "type": "image", "x": 200, "y": 0, "width": 10, "height": 10, "fileId": "f1" },
+            { "id": "d1", "type": "ellipse", "x": 0, "y": 0, "isDeleted": true }
+        ],
+        "appState": { "viewBackgroundColor": "#ffffff", "collaborators": [] },
+        "files": { "f1": { "mimeType": "image/png", "dataURL": IMAGE_DATA_URL } }
+    })
+}
+
+fn tldraw_file() -> Value {
+    json!({
+        "tldrawFileFormatVersion": 1,
+        "schema": { "schemaVersion": 2 },
+        "records": [
+            { "id": "page:p1", "typeName": "page", "name": "Page 1", "index": "a1" },
+            {
+                "id": "shape:g1", "typeName": "shape", "type": "geo", "parentId": "page:p1", "index": "a1",
+                "x": 0, "y": 0, "props": { "geo": "rectangle", "w": 100, "h": 100, "text": "Hi" }
+            }
+        ]
+    })
+}
+
+fn zip_of(files: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, content) in files {
+        writer.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+        writer.write_all(content).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+#[tokio::test]
+async fn test_board_import_and_export() {
+    let (config, app) = create_test_app().await;
+    let token = create_token(&config, 1);
+
+    let content = excalidraw_file().to_string().into_bytes();
+    let (status, resp) = call(&app, &token, import("/modules/board/import?name=b1", content)).await;
+    assert_eq!(status, StatusCode::OK);
+    let id = resp["id"].as_i64().unwrap();
+
+    // The embedded file is stored as the blob, and the deleted element is dropped.
+    let board = query_board(&app, &token, "b1").await;
+    assert_eq!(board["type"], "Board");
+    let scene: Value = serde_json::from_str(board["content"].as_str().unwrap()).unwrap();
+    assert_eq!(scene["elements"].as_array().unwrap().len(), 2);
+    assert_eq!(scene["appState"], json!({ "viewBackgroundColor": "#ffffff" }));
+    let data_url = scene["files"]["f1"]["dataURL"].as_str().unwrap();
+    assert!(data_url.starts_with("/modules/blob/download?hash="), "{}", data_url);
+    let (status, _) = call(&app, &token, get(data_url)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The file is embedded again when export.
+    let (status, headers, exported) = export(&app, &token, id).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["content-type"], "application/vnd.excalidraw+json");
+    let disposition = "attachment; filename=\"b1.excalidraw\"; filename*=UTF-8''b1.excalidraw";
+    assert_eq!(headers["content-disposition"], disposition);
+    assert_eq!(exported["type"], "excalidraw");
+    assert_eq!(exported["files"]["f1"]["dataURL"], IMAGE_DATA_URL);
+    assert_eq!(exported["elements"], scene["elements"]);
+
+    // The board is only visible to the owner.
+    let other = create_token(&config, 2);
+    let (status, _, _) = export(&app, &other, id).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let body = json!({ "key": "n1", "name": "n1", "type": "Note", "content": "note" });
+    let (status, resp) = call(&app, &token, post_json("/modules/document/save", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, _) = export(&app, &token, resp["id"].as_i64().unwrap()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_board_import_tldraw_and_invalid() {
+    let (config, app) = create_test_app().await;
+    let token = create_token(&config, 1);
+
+    let content = tldraw_file().to_string().into_bytes();
+    let (status, _) = call(&app, &token, import("/modules/board/import?name=t1", content.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+    let board = query_board(&app, &token, "t1").await;
+    let scene: Value = serde_json::from_str(board["content"].as_str().unwrap()).unwrap();
+    let elements = scene["elements"].as_array().unwrap();
+    assert_eq!(elements.len(), 2);
+    assert_eq!((elements[0]["type"].as_str(), elements[1]["text"].as_str()), (Some("rectangle"), Some("Hi")));
+
+    // The format is explicitly specified, but the content isn't the format.
+    let uri = "/modules/board/import?format=excalidraw";
+    let (status, _) = call(&app, &token, import(uri, content)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = call(&app, &token, import("/modules/board/import", b"not json".to_vec())).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let invalid = json!({ "type": "excalidraw", "elements": [{ "id": "a", "type": "star", "x": 0, "y": 0 }] });
+    let (status, _) = call(&app, &token, import("/modules/board/import", invalid.to_string().into_bytes())).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let uri = "/modules/board/import?folder_key=missing";
+    let (status, _) = call(&app, &token, import(uri, excalidraw_file().to_string().into_bytes())).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_board_bulk_import() {
+    let (config, app) = create_test_app().await;
+    let token = create_token(&config, 1);
+    let body = json!({ "pid": 0, "key": "f1", "name": "f1" });
+    let (status, _) = call(&app, &token, post_json("/modules/folder/save", body)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let archive = zip_of(
+        &[
+            ("boards/Flow.excalidraw", excalidraw_file().to_string().into_bytes()),
+            ("boards/Sketch.tldr", tldraw_file().to_string().into_bytes()),
+            ("boards/Broken.excalidraw", b"{\"elements\": 1}".to_vec()),
+            ("boards/readme.txt", b"ignored".to_vec()),
+            ("__MACOSX/boards/._Flow.excalidraw", b"ignored".to_vec()),
+        ]
+    );
+    let (status, resp) = call(&app, &token, import("/modules/board/import/bulk?folder_key=f1", archive)).await;
+    assert_eq!(status, StatusCode::OK);
+    let imported: Vec<&str> = resp["imported"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|b| b["file"].as_str().unwrap())
+        .collect();
+    assert_eq!(imported, vec!["boards/Flow.excalidraw", "boards/Sketch.tldr"]);
+    assert_eq!(resp["failed"].as_array().unwrap().len(), 1);
+    assert_eq!(resp["failed"][0]["file"], "boards/Broken.excalidraw");
+
+    let board = query_board(&app, &token, "Sketch").await;
+    assert_eq!((board["folder_key"].as_str(), board["type"].as_str()), (Some("f1"), Some("Board")));
+
+    let (status, _) = call(&app, &token, import("/modules/board/import/bulk", b"not zip".to_vec())).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let uri = "/modules/board/import/bulk?folder_key=missing";
+    let (status, _) = call(&app, &token, import(uri, zip_of(&[]))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_board_bulk_import_limits() {
+    let (config, app) = create_test_app_with(|p| {
+        p.webnote.board.import_max_file_size = 4096;
+        p.webnote.board.import_max_files = 2;
+    }).await;
+    let token = create_token(&config, 1);
+
+    // The highly compressed file is rejected by the uncompressed size.
+    let bomb = vec![b' '; 1024 * 1024];
+    let archive = zip_of(
+        &[
+            ("Flow.excalidraw", excalidraw_file().to_string().into_bytes()),
+            ("Bomb.excalidraw", bomb),
+        ]
+    );
+    assert!(archive.len() < 4096);
+    let (status, resp) = call(&app, &token, import("/modules/board/import/bulk", archive)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["imported"][0]["file"], "Flow.excalidraw");
+    assert_eq!(resp["failed"][0]["file"], "Bomb.excalidraw");
+    assert!(resp["failed"][0]["reason"].as_str().unwrap().contains("at most 4096 bytes"));
+
+    let archive = zip_of(&[("a.txt", vec![]), ("b.txt", vec![]), ("c.txt", vec![])]);
+    let (status, _) = call(&app, &token, import("/modules/board/import/bulk", archive)).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+}
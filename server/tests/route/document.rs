@@ -0,0 +1,293 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use axum::http::{ header, StatusCode };
+use serde_json::json;
+use tower::ServiceExt;
+
+use mywebnote::{ store::sqlite, utils::compress };
+
+use super::{ call, create_test_app_with, create_token, get, post_json };
+
+#[tokio::test]
+async fn test_document_revisions_diff_and_restore() {
+    let (config, app) = create_test_app_with(|p| {
+        p.webnote.revision.keep_last = Some(3);
+    }).await;
+    let token = create_token(&config, 1);
+
+    let body = json!({ "key": "k1", "name": "n1", "content": "line1\nline2" });
+    let (_, resp) = call(&app, &token, post_json("/modules/document/save", body)).await;
+    let id = resp["id"].as_i64().unwrap();
+    let mut version = resp["version"].as_i64().unwrap();
+    for content in ["line1\nline2 changed", "v3", "v4"] {
+        let body = json!({ "id": id, "version": version, "content": content });
+        let (status, resp) = call(&app, &token, post_json("/modules/document/save", body)).await;
+        assert_eq!(status, StatusCode::OK);
+        version = resp["version"].as_i64().unwrap();
+    }
+
+    // Only the latest 3 revisions are retained, newest first.
+    let uri = format!("/modules/document/revision/query?document_id={}", id);
+    let (status, resp) = call(&app, &token, get(&uri)).await;
+    assert_eq!(status, StatusCode::OK);
+    let revisions = resp["data"].as_array().unwrap().clone();
+    let contents: Vec<&str> = revisions
+        .iter()
+        .map(|r| r["content"].as_str().unwrap())
+        .collect();
+    assert_eq!(contents, vec!["v4", "v3", "line1\nline2 changed"]);
+    assert_eq!(revisions[0]["create_by"], json!("user1@mywebnote.local"));
+    assert_eq!(revisions[0]["size"], json!(2));
+
+    let oldest = revisions[2]["id"].as_i64().unwrap();
+    let uri = format!("/modules/document/revision/diff?from_id={}", oldest);
+    let (status, resp) = call(&app, &token, get(&uri)).await;
+    assert_eq!(status, StatusCode::OK);
+    let diff = resp["diff"].as_str().unwrap();
+    assert!(diff.contains("-line2 changed"), "{}", diff);
+    assert!(diff.contains("+v4"), "{}", diff);
+
+    // Restore as the new head.
+    let body = json!({ "id": oldest });
+    let (status, resp) = call(&app, &token, post_json("/modules/document/revision/restore", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["id"], json!(id));
+    let (_, resp) = call(&app, &token, get("/modules/document/query")).await;
+    assert_eq!(resp["data"][0]["content"], json!("line1\nline2 changed"));
+    let uri = format!("/modules/document/revision/query?document_id={}", id);
+    let (_, resp) = call(&app, &token, get(&uri)).await;
+    assert_eq!(resp["data"][0]["content"], json!("line1\nline2 changed"));
+
+    // Not visible to the other user.
+    let other = create_token(&config, 2);
+    let uri = format!("/modules/document/revision/get?id={}", oldest);
+    let (status, _) = call(&app, &other, get(&uri)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_document_large_content_compressed() {
+    let (config, app) = create_test_app_with(|p| {
+        p.server.max_body_size = 8 * 1024 * 1024;
+    }).await;
+    let token = create_token(&config, 1);
+
+    // The board of multi-megabytes, which exceeds the axum default body limit (2MB).
+    let elements: Vec<_> = (0..50000)
+        .map(|i| json!({ "id": format!("e{}", i), "type": "rectangle", "x": i, "y": i * 2 }))
+        .collect();
+    let content = json!({ "elements": elements }).to_string();
+    assert!(content.len() > 2 * 1024 * 1024);
+    let body = json!({ "key": "k1", "name": "board", "type": "Board", "content": content });
+    let (status, resp) = call(&app, &token, post_json("/modules/document/save", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    let id = resp["id"].as_i64().unwrap();
+
+    // Stored compressed, and transparently decompressed on read.
+    let pool = sqlite::connect(&config.db).await.unwrap();
+    let stored: String = sqlx
+        ::query_scalar("SELECT content FROM documents WHERE id = ?")
+        .bind(id)
+        .fetch_one(&pool).await
+        .unwrap();
+    assert!(stored.starts_with(compress::ZSTD_PREFIX));
+    assert!(stored.len() < content.len() / 4);
+    let (_, resp) = call(&app, &token, get("/modules/document/query")).await;
+    assert_eq!(resp["data"][0]["content"], json!(content));
+    let uri = format!("/modules/document/revision/query?document_id={}", id);
+    let (_, resp) = call(&app, &token, get(&uri)).await;
+    assert_eq!(resp["data"][0]["content"], json!(content));
+
+    // The listing could omit the content.
+    let (status, resp) = call(&app, &token, get("/modules/document/query?with_content=false")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["data"][0]["name"], json!("board"));
+    assert_eq!(resp["data"][0]["content"], json!(null));
+
+    // The body exceeds the configured limit is rejected.
+    let content = "x".repeat(9 * 1024 * 1024);
+    let body = json!({ "key": "k2", "name": "huge", "type": "Note", "content": content });
+    let (status, _) = call(&app, &token, post_json("/modules/document/save", body)).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn test_document_save_version_conflict() {
+    let (config, app) = create_test_app_with(|_| {}).await;
+    let token = create_token(&config, 1);
+
+    let body = json!({ "key": "k1", "name": "n1", "content": "c1" });
+    let (_, resp) = call(&app, &token, post_json("/modules/document/save", body)).await;
+    let id = resp["id"].as_i64().unwrap();
+    assert_eq!(resp["version"], json!(1));
+
+    // The version is required to update.
+    let body = json!({ "id": id, "content": "c2" });
+    let (status, _) = call(&app, &token, post_json("/modules/document/save", body)).await;
+    assert_eq!(status, StatusCode::PRECONDITION_REQUIRED);
+
+    // Matched by the If-Match header, and the new version is responded as the ETag.
+    let mut req = post_json("/modules/document/save", json!({ "id": id, "content": "c2" }));
+    req.headers_mut().insert(header::IF_MATCH, "\"1\"".parse().unwrap());
+    req.headers_mut().insert(header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+    let resp = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()[header::ETAG], "\"2\"");
+
+    // The stale write (such as the other tab) is rejected with the current version.
+    let body = json!({ "id": id, "version": 1, "content": "c3" });
+    let (status, resp) = call(&app, &token, post_json("/modules/document/save", body)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(resp["version"], json!(2));
+    let (_, resp) = call(&app, &token, get("/modules/document/query")).await;
+    assert_eq!(resp["data"][0]["content"], json!("c2"));
+    assert_eq!(resp["data"][0]["version"], json!(2));
+
+    // The If-Match must be same as the version field.
+    let mut req = post_json("/modules/document/save", json!({ "id": id, "version": 2, "content": "c3" }));
+    req.headers_mut().insert(header::IF_MATCH, "W/\"1\"".parse().unwrap());
+    let (status, _) = call(&app, &token, req).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // The folder is same.
+    let body = json!({ "pid": 0, "key": "f1", "name": "f1" });
+    let (_, resp) = call(&app, &token, post_json("/modules/folder/save", body)).await;
+    let folder_id = resp["id"].as_i64().unwrap();
+    let body = json!({ "id": folder_id, "version": 1, "name": "f1 renamed" });
+    let (status, resp) = call(&app, &token, post_json("/modules/folder/save", body.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["version"], json!(2));
+    let (status, resp) = call(&app, &token, post_json("/modules/folder/save", body)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(resp["version"], json!(2));
+}
+
+#[tokio::test]
+async fn test_document_cursor_pagination() {
+    let (config, app) = create_test_app_with(|_| {}).await;
+    let token = create_token(&config, 1);
+
+    for i in 0..7 {
+        let body = json!({ "key": format!("k{}", i), "name": format!("n{}", i), "content": "c" });
+        let (status, _) = call(&app, &token, post_json("/modules/document/save", body)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let ids = |resp: &serde_json::Value| -> Vec<i64> {
+        resp["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|d| d["id"].as_i64().unwrap())
+            .collect()
+    };
+    let (_, all) = call(&app, &token, get("/modules/document/query?limit=10")).await;
+    let all = ids(&all);
+    assert_eq!(all.len(), 7);
+
+    // The first page by offset is counted, and responds the cursor of next page.
+    let (status, first) = call(&app, &token, get("/modules/document/query?num=1&limit=3")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(first["page"]["total"], json!(7));
+    assert_eq!(first["page"]["num"], json!(1));
+    assert_eq!(first["page"]["prev_cursor"], json!(null));
+    assert_eq!(ids(&first), all[0..3]);
+
+    // Follow the next cursors, which are not counted by default.
+    let uri = format!("/modules/document/query?limit=3&cursor={}", first["page"]["next_cursor"].as_str().unwrap());
+    let (status, second) = call(&app, &token, get(&uri)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(second["page"]["total"], json!(null));
+    assert_eq!(ids(&second), all[3..6]);
+    let uri = format!(
+        "/modules/document/query?limit=3&with_total=true&cursor={}",
+        second["page"]["next_cursor"].as_str().unwrap()
+    );
+    let (_, last) = call(&app, &token, get(&uri)).await;
+    assert_eq!(last["page"]["total"], json!(7));
+    assert_eq!(ids(&last), all[6..7]);
+    assert_eq!(last["page"]["next_cursor"], json!(null));
+
+    // Back to the previous pages.
+    let uri = format!("/modules/document/query?limit=3&cursor={}", last["page"]["prev_cursor"].as_str().unwrap());
+    let (_, prev) = call(&app, &token, get(&uri)).await;
+    assert_eq!(ids(&prev), all[3..6]);
+    let uri = format!("/modules/document/query?limit=3&cursor={}", prev["page"]["prev_cursor"].as_str().unwrap());
+    let (_, prev) = call(&app, &token, get(&uri)).await;
+    assert_eq!(ids(&prev), all[0..3]);
+    assert_eq!(prev["page"]["prev_cursor"], json!(null));
+
+    let (status, _) = call(&app, &token, get("/modules/document/query?cursor=invalid")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_document_query_sort_and_fields() {
+    let (config, app) = create_test_app_with(|_| {}).await;
+    let token = create_token(&config, 1);
+
+    for name in ["b", "a", "c"] {
+        let body = json!({ "key": format!("k{}", name), "name": name, "content": "c" });
+        let (status, _) = call(&app, &token, post_json("/modules/document/save", body)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let names = |resp: &serde_json::Value| -> Vec<String> {
+        resp["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|d| d["name"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let (status, resp) = call(&app, &token, get("/modules/document/query?sort=name")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(names(&resp), vec!["a", "b", "c"]);
+    let (_, resp) = call(&app, &token, get("/modules/document/query?sort=-name,update_time")).await;
+    assert_eq!(names(&resp), vec!["c", "b", "a"]);
+
+    // Follow the cursor along the client sort.
+    let (_, first) = call(&app, &token, get("/modules/document/query?sort=name&limit=2")).await;
+    assert_eq!(names(&first), vec!["a", "b"]);
+    let uri = format!(
+        "/modules/document/query?sort=name&limit=2&cursor={}",
+        first["page"]["next_cursor"].as_str().unwrap()
+    );
+    let (_, next) = call(&app, &token, get(&uri)).await;
+    assert_eq!(names(&next), vec!["c"]);
+
+    // Only the common columns and the fields are responded.
+    let (status, resp) = call(&app, &token, get("/modules/document/query?fields=name&sort=name")).await;
+    assert_eq!(status, StatusCode::OK);
+    let document = &resp["data"][0];
+    assert_eq!(document["name"], json!("a"));
+    assert_eq!(document["version"], json!(1));
+    assert!(document["id"].is_i64());
+    assert_eq!(document["key"], json!(null));
+    assert_eq!(document["content"], json!(null));
+
+    // The columns are whitelisted.
+    for uri in ["/modules/document/query?sort=-unknown", "/modules/document/query?fields=name,unknown"] {
+        let (status, _) = call(&app, &token, get(uri)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,133 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+use std::time::Duration;
+
+use axum::{ body::BodyDataStream, http::StatusCode, Router };
+use futures::StreamExt;
+use serde_json::{ json, Value };
+use tower::ServiceExt;
+
+use super::{ call, create_test_app, create_token, get, post_json };
+
+// The server-sent events of the streaming response.
+struct EventStream {
+    body: BodyDataStream,
+    buf: String,
+}
+
+impl EventStream {
+    async fn open(app: &Router, token: &str, last_event_id: Option<i64>) -> Self {
+        let mut req = get("/modules/events");
+        req.headers_mut().insert("Authorization", format!("Bearer {}", token).parse().unwrap());
+        if let Some(id) = last_event_id {
+            req.headers_mut().insert("Last-Event-ID", id.to_string().parse().unwrap());
+        }
+        let resp = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()["content-type"], "text/event-stream");
+        EventStream { body: resp.into_body().into_data_stream(), buf: String::new() }
+    }
+
+    // The data of the next 'change' event, and its id is the same as the data.
+    async fn next(&mut self) -> Value {
+        loop {
+            if let Some(end) = self.buf.find("\n\n") {
+                let block: String = self.buf.drain(..end + 2).collect();
+                let data: String = block.lines().filter_map(|line| line.strip_prefix("data: ")).collect();
+                if block.lines().any(|line| line == "event: change") {
+                    let event: Value = serde_json::from_str(&data).unwrap();
+                    assert!(block.lines().any(|line| line == format!("id: {}", event["id"])));
+                    return event;
+                }
+                continue;
+            }
+            let chunk = tokio::time::timeout(Duration::from_secs(5), self.body.next()).await.unwrap();
+            self.buf.push_str(std::str::from_utf8(&chunk.unwrap().unwrap()).unwrap());
+        }
+    }
+}
+
+fn summary(event: &Value) -> (String, String, i64) {
+    (
+        event["resource"].as_str().unwrap().to_string(),
+        event["action"].as_str().unwrap().to_string(),
+        event["resourceId"].as_i64().unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn test_change_events_and_resume() {
+    let (config, app) = create_test_app().await;
+    let token = create_token(&config, 1);
+    let mut events = EventStream::open(&app, &token, None).await;
+
+    let body = json!({ "key": "d1", "name": "doc", "type": "Note", "content": "hello" });
+    let (_, resp) = call(&app, &token, post_json("/modules/document/save", body)).await;
+    let doc = resp["id"].as_i64().unwrap();
+    let created = events.next().await;
+    assert_eq!(summary(&created), ("document".to_string(), "created".to_string(), doc));
+    assert_eq!(created["key"], json!("d1"));
+    assert_eq!(created["version"], json!(1));
+
+    // Renamed, moved and deleted.
+    let body = json!({ "id": doc, "name": "renamed", "version": 1 });
+    let (status, _) = call(&app, &token, post_json("/modules/document/save", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    let body = json!({ "pid": 0, "key": "f1", "name": "f1" });
+    let (_, resp) = call(&app, &token, post_json("/modules/folder/save", body)).await;
+    let folder = resp["id"].as_i64().unwrap();
+    let body = json!({ "ids": [doc], "folderKey": "f1" });
+    let (status, _) = call(&app, &token, post_json("/modules/document/move", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(&app, &token, post_json("/modules/document/delete", json!({ "id": doc }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let expected = vec![
+        ("document".to_string(), "updated".to_string(), doc),
+        ("folder".to_string(), "created".to_string(), folder),
+        ("document".to_string(), "moved".to_string(), doc),
+        ("document".to_string(), "deleted".to_string(), doc),
+    ];
+    // The ids are the sequence of the user, so that the resuming is by the order of the publishing.
+    let mut received = Vec::new();
+    for i in 0..expected.len() {
+        let event = events.next().await;
+        assert_eq!(event["id"].as_i64(), created["id"].as_i64().map(|id| id + (i as i64) + 1));
+        received.push(summary(&event));
+    }
+    assert_eq!(received, expected);
+
+    // The events after the last received are replayed when resuming, and the others are not aware of.
+    let mut resumed = EventStream::open(&app, &token, created["id"].as_i64()).await;
+    let mut replayed = Vec::new();
+    for _ in 0..expected.len() {
+        replayed.push(summary(&resumed.next().await));
+    }
+    assert_eq!(replayed, expected);
+    // The sequence is reset if the last event id is ahead of it, and then all the events are replayed.
+    let mut reset = EventStream::open(&app, &token, Some(1000)).await;
+    assert_eq!(summary(&reset.next().await), summary(&created));
+    let mut others = EventStream::open(&app, &create_token(&config, 2), created["id"].as_i64()).await;
+    let (status, _) = call(&app, &token, post_json("/modules/document/trash/restore", json!({ "id": doc }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(summary(&resumed.next().await), ("document".to_string(), "restored".to_string(), doc));
+    assert!(tokio::time::timeout(Duration::from_millis(300), others.next()).await.is_err());
+}
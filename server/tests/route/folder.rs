@@ -0,0 +1,212 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use axum::{ http::StatusCode, Router };
+use serde_json::{ json, Value };
+
+use super::{ call, create_test_app, create_token, get, post_json };
+
+async fn save(app: &Router, token: &str, uri: &str, body: Value) -> i64 {
+    let (status, resp) = call(app, token, post_json(uri, body)).await;
+    assert_eq!(status, StatusCode::OK);
+    resp["id"].as_i64().unwrap()
+}
+
+async fn query_tree(app: &Router, token: &str) -> Value {
+    let (status, resp) = call(app, token, get("/modules/folder/tree")).await;
+    assert_eq!(status, StatusCode::OK);
+    resp
+}
+
+async fn query_document(app: &Router, token: &str, key: &str) -> Value {
+    let uri = format!("/modules/document/query?key={}", key);
+    let (status, resp) = call(app, token, get(&uri)).await;
+    assert_eq!(status, StatusCode::OK);
+    resp["data"][0].clone()
+}
+
+#[tokio::test]
+async fn test_folder_tree_with_document_counts() {
+    let (config, app) = create_test_app().await;
+    let token = create_token(&config, 1);
+
+    let f1 = save(&app, &token, "/modules/folder/save", json!({ "pid": 0, "key": "f1", "name": "f1" })).await;
+    let f2 = save(&app, &token, "/modules/folder/save", json!({ "pid": f1, "key": "f2", "name": "f2" })).await;
+    save(&app, &token, "/modules/folder/save", json!({ "pid": 0, "key": "f3", "name": "f3" })).await;
+    for (key, folder_key) in [("d1", Some("f1")), ("d2", Some("f2")), ("d3", Some("f2")), ("d4", None)] {
+        let body = json!({ "key": key, "name": key, "folderKey": folder_key, "content": key });
+        save(&app, &token, "/modules/document/save", body).await;
+    }
+
+    let tree = query_tree(&app, &token).await;
+    assert_eq!(tree["root_document_count"], json!(1));
+    let roots = tree["data"].as_array().unwrap();
+    assert_eq!(roots.len(), 2);
+    assert_eq!(roots[0]["key"], json!("f1"));
+    assert_eq!(roots[0]["document_count"], json!(1));
+    assert_eq!(roots[0]["children"][0]["id"], json!(f2));
+    assert_eq!(roots[0]["children"][0]["document_count"], json!(2));
+    assert_eq!(roots[1]["key"], json!("f3"));
+    assert!(roots[1]["children"].as_array().unwrap().is_empty());
+
+    // The tree is scoped to the current user.
+    let other = create_token(&config, 2);
+    let tree = query_tree(&app, &other).await;
+    assert!(tree["data"].as_array().unwrap().is_empty());
+    assert_eq!(tree["root_document_count"], json!(0));
+}
+
+#[tokio::test]
+async fn test_folder_move_with_cycle_detection() {
+    let (config, app) = create_test_app().await;
+    let token = create_token(&config, 1);
+
+    let f1 = save(&app, &token, "/modules/folder/save", json!({ "pid": 0, "key": "f1", "name": "f1" })).await;
+    let f2 = save(&app, &token, "/modules/folder/save", json!({ "pid": f1, "key": "f2", "name": "f2" })).await;
+    let f3 = save(&app, &token, "/modules/folder/save", json!({ "pid": f2, "key": "f3", "name": "f3" })).await;
+
+    // Move the folder under itself or the descendants makes a cycle.
+    for pid in [f1, f3] {
+        let body = json!({ "id": f1, "pid": pid, "version": 1 });
+        let (status, _) = call(&app, &token, post_json("/modules/folder/move", body)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    // Move the sub folder to the root, which requires the version.
+    let body = json!({ "id": f3, "pid": 0 });
+    let (status, _) = call(&app, &token, post_json("/modules/folder/move", body)).await;
+    assert_eq!(status, StatusCode::PRECONDITION_REQUIRED);
+    let body = json!({ "id": f3, "pid": 0, "version": 1 });
+    let (status, resp) = call(&app, &token, post_json("/modules/folder/move", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["version"], json!(2));
+    let tree = query_tree(&app, &token).await;
+    let roots = tree["data"].as_array().unwrap();
+    assert_eq!(roots.len(), 2);
+    assert_eq!(roots[1]["id"], json!(f3));
+
+    // Then the former ancestor could be moved under it, but not with the stale version.
+    let body = json!({ "id": f1, "pid": f3, "version": 0 });
+    let (status, resp) = call(&app, &token, post_json("/modules/folder/move", body)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(resp["version"], json!(1));
+    let body = json!({ "id": f1, "pid": f3, "version": 1 });
+    let (status, _) = call(&app, &token, post_json("/modules/folder/move", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    let tree = query_tree(&app, &token).await;
+    assert_eq!(tree["data"][0]["id"], json!(f3));
+    assert_eq!(tree["data"][0]["children"][0]["id"], json!(f1));
+    assert_eq!(tree["data"][0]["children"][0]["children"][0]["id"], json!(f2));
+
+    // The folder of the other user could not be moved.
+    let other = create_token(&config, 2);
+    let body = json!({ "id": f2, "pid": 0, "version": 1 });
+    let (status, _) = call(&app, &other, post_json("/modules/folder/move", body)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_document_move_and_folder_rename() {
+    let (config, app) = create_test_app().await;
+    let token = create_token(&config, 1);
+
+    let f1 = save(&app, &token, "/modules/folder/save", json!({ "pid": 0, "key": "f1", "name": "f1" })).await;
+    let body = json!({ "key": "d1", "name": "d1", "content": "c1" });
+    let d1 = save(&app, &token, "/modules/document/save", body).await;
+    let body = json!({ "key": "d2", "name": "d2", "content": "c2" });
+    let d2 = save(&app, &token, "/modules/document/save", body).await;
+
+    // None is moved if any of the documents is absent.
+    let body = json!({ "ids": [d1, 999999], "folderKey": "f1" });
+    let (status, _) = call(&app, &token, post_json("/modules/document/move", body)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let body = json!({ "ids": [d1, d2], "folderKey": "absent" });
+    let (status, _) = call(&app, &token, post_json("/modules/document/move", body)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(query_tree(&app, &token).await["root_document_count"], json!(2));
+
+    let body = json!({ "ids": [d1, d2], "folderKey": "f1" });
+    let (status, resp) = call(&app, &token, post_json("/modules/document/move", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["count"], json!(2));
+    let document = query_document(&app, &token, "d1").await;
+    assert_eq!(document["folder_key"], json!("f1"));
+    assert_eq!(document["version"], json!(2));
+
+    // The documents follow the renamed key of the folder.
+    let body = json!({ "id": f1, "key": "f1-renamed", "name": "renamed", "version": 1 });
+    save(&app, &token, "/modules/folder/save", body).await;
+    assert_eq!(query_document(&app, &token, "d2").await["folder_key"], json!("f1-renamed"));
+    let tree = query_tree(&app, &token).await;
+    assert_eq!(tree["data"][0]["document_count"], json!(2));
+    assert_eq!(tree["root_document_count"], json!(0));
+
+    // Move back to the root.
+    let body = json!({ "ids": [d2] });
+    let (status, _) = call(&app, &token, post_json("/modules/document/move", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(query_document(&app, &token, "d2").await["folder_key"], Value::Null);
+    assert_eq!(query_tree(&app, &token).await["root_document_count"], json!(1));
+}
+
+#[tokio::test]
+async fn test_folder_delete_with_cascade() {
+    let (config, app) = create_test_app().await;
+    let token = create_token(&config, 1);
+
+    let f1 = save(&app, &token, "/modules/folder/save", json!({ "pid": 0, "key": "f1", "name": "f1" })).await;
+    let f2 = save(&app, &token, "/modules/folder/save", json!({ "pid": f1, "key": "f2", "name": "f2" })).await;
+    let f3 = save(&app, &token, "/modules/folder/save", json!({ "pid": f2, "key": "f3", "name": "f3" })).await;
+    let body = json!({ "key": "d1", "name": "d1", "folderKey": "f2", "content": "c1" });
+    save(&app, &token, "/modules/document/save", body).await;
+
+    // The non empty folder is refused to delete with restrict.
+    let body = json!({ "id": f2, "cascade": "Restrict" });
+    let (status, _) = call(&app, &token, post_json("/modules/folder/delete", body)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // The sub folders and documents are moved to the parent with reparent.
+    let body = json!({ "id": f2, "cascade": "Reparent" });
+    let (status, resp) = call(&app, &token, post_json("/modules/folder/delete", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["count"], json!(1));
+    let tree = query_tree(&app, &token).await;
+    assert_eq!(tree["data"][0]["id"], json!(f1));
+    assert_eq!(tree["data"][0]["document_count"], json!(1));
+    assert_eq!(tree["data"][0]["children"][0]["id"], json!(f3));
+    assert_eq!(query_document(&app, &token, "d1").await["folder_key"], json!("f1"));
+
+    // The empty folder is allowed to delete with restrict.
+    let body = json!({ "id": f3, "cascade": "Restrict" });
+    let (status, resp) = call(&app, &token, post_json("/modules/folder/delete", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["count"], json!(1));
+
+    // Reparent the top folder moves the contents to the root.
+    let body = json!({ "id": f1, "cascade": "Reparent" });
+    let (status, _) = call(&app, &token, post_json("/modules/folder/delete", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    let tree = query_tree(&app, &token).await;
+    assert!(tree["data"].as_array().unwrap().is_empty());
+    assert_eq!(tree["root_document_count"], json!(1));
+    assert_eq!(query_document(&app, &token, "d1").await["folder_key"], Value::Null);
+}
@@ -0,0 +1,142 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+pub mod acl;
+pub mod auths;
+pub mod backup;
+pub mod blob;
+pub mod board;
+pub mod document;
+pub mod event;
+pub mod folder;
+pub mod note;
+pub mod room;
+pub mod search;
+pub mod share;
+pub mod sync;
+pub mod tag;
+pub mod thumbnail;
+pub mod trash;
+
+use std::sync::Arc;
+
+use axum::{ body::{ self, Body }, extract::DefaultBodyLimit, http::{ Request, StatusCode }, Router };
+use serde_json::Value;
+use tower::ServiceExt;
+
+use mywebnote::{
+    config::config_serve::{ WebServeConfig, WebServeProperties },
+    context::state::AppState,
+    handler::auth::PrincipalType,
+    route::{
+        acl::init as acl_router,
+        auths::auth_middleware,
+        blob::init as blob_router,
+        board::init as board_router,
+        document::init as document_router,
+        event::init as event_router,
+        folder::init as folder_router,
+        room::init as room_router,
+        share::init as share_router,
+        sync::init as sync_router,
+        tag::init as tag_router,
+    },
+    types::{ user::User, BaseBean },
+    utils::auths::create_jwt,
+};
+
+pub async fn create_test_app() -> (Arc<WebServeConfig>, Router) {
+    create_test_app_with(|_| {}).await
+}
+
+pub async fn create_test_app_with(
+    customize: impl FnOnce(&mut WebServeProperties)
+) -> (Arc<WebServeConfig>, Router) {
+    let (config, _, app) = create_test_state_with(customize).await;
+    (config, app)
+}
+
+pub async fn create_test_state_with(
+    customize: impl FnOnce(&mut WebServeProperties)
+) -> (Arc<WebServeConfig>, AppState, Router) {
+    let dir = std::env::temp_dir().join(format!("mywebnote-it-{}", uuid::Uuid::new_v4()));
+    let mut properties = WebServeProperties::default();
+    properties.db.sqlite.dir = Some(dir.to_string_lossy().to_string());
+    properties.webnote.blob.dir = dir.join("blobs").to_string_lossy().to_string();
+    customize(&mut properties);
+    let config = properties.to_config();
+
+    let app_state = AppState::new(&config).await;
+    let app = Router::new()
+        .merge(document_router())
+        .merge(folder_router())
+        .merge(blob_router())
+        .merge(board_router())
+        .merge(tag_router())
+        .merge(share_router())
+        .merge(acl_router())
+        .merge(room_router())
+        .merge(sync_router())
+        .merge(event_router())
+        .with_state(app_state.clone())
+        .layer(axum::middleware::from_fn_with_state(app_state.clone(), auth_middleware))
+        .layer(DefaultBodyLimit::max(config.server.max_body_size));
+    (config, app_state, app)
+}
+
+// The users looked up by the emails, which are the same as the tokens.
+pub async fn create_users(state: &AppState, uids: &[i64]) {
+    for uid in uids {
+        let user = User {
+            base: BaseBean::new_default(Some(*uid)),
+            email: Some(format!("user{}@mywebnote.local", uid)),
+            ..Default::default()
+        };
+        let repo = state.user_repo.lock().await;
+        repo.get(&state.config).insert_raw(user).await.unwrap();
+    }
+}
+
+pub fn create_token(config: &Arc<WebServeConfig>, uid: i64) -> String {
+    let email = format!("user{}@mywebnote.local", uid);
+    create_jwt(config, &PrincipalType::Password, uid, "", &email, false, None)
+}
+
+pub async fn call(app: &Router, token: &str, req: Request<Body>) -> (StatusCode, Value) {
+    let mut req = req;
+    req.headers_mut().insert("Authorization", format!("Bearer {}", token).parse().unwrap());
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+pub fn post_json(uri: &str, body: Value) -> Request<Body> {
+    Request::post(uri)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+pub fn get(uri: &str) -> Request<Body> {
+    Request::get(uri).body(Body::empty()).unwrap()
+}
@@ -0,0 +1,164 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use std::io::{ Cursor, Read };
+
+use axum::{ body::{ self, Body, Bytes }, http::{ HeaderMap, Request, StatusCode }, Router };
+use serde_json::{ json, Value };
+use tower::ServiceExt;
+
+use super::{ call, create_test_app, create_token, get, post_json };
+
+async fn download(app: &Router, token: &str, uri: &str) -> (StatusCode, HeaderMap, Bytes) {
+    let mut req = get(uri);
+    req.headers_mut().insert("Authorization", format!("Bearer {}", token).parse().unwrap());
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let (status, headers) = (resp.status(), resp.headers().clone());
+    (status, headers, body::to_bytes(resp.into_body(), usize::MAX).await.unwrap())
+}
+
+async fn save(app: &Router, token: &str, uri: &str, body: Value) -> i64 {
+    let (status, resp) = call(app, token, post_json(uri, body)).await;
+    assert_eq!(status, StatusCode::OK);
+    resp["id"].as_i64().unwrap()
+}
+
+async fn upload_image(app: &Router, token: &str) -> String {
+    let req = Request::post("/modules/blob/upload")
+        .header("Content-Type", "image/png")
+        .body(Body::from(b"\x89PNG\r\n\x1a\nfake image".to_vec()))
+        .unwrap();
+    let (status, resp) = call(app, token, req).await;
+    assert_eq!(status, StatusCode::OK);
+    resp["hash"].as_str().unwrap().to_string()
+}
+
+fn text(insert: &str) -> Value {
+    json!({ "$blocksuite:internal:text$": true, "delta": [{ "insert": insert }] })
+}
+
+// The BlockSuite snapshot of the page with a heading, a list and the image of the blob.
+fn note_snapshot(title: &str, hash: &str) -> String {
+    let blocks = json!([
+        { "flavour": "affine:paragraph", "props": { "type": "h2", "text": text("Section") }, "children": [] },
+        { "flavour": "affine:list", "props": { "type": "bulleted", "text": text("item") }, "children": [] },
+        { "flavour": "affine:image", "props": { "sourceId": hash, "caption": "photo" }, "children": [] }
+    ]);
+    let page = json!({
+        "flavour": "affine:page",
+        "props": { "title": text(title) },
+        "children": [{ "flavour": "affine:note", "props": {}, "children": blocks }]
+    });
+    json!({ "type": "page", "meta": { "id": "p1", "title": title }, "blocks": page }).to_string()
+}
+
+#[tokio::test]
+async fn test_note_export_markdown_and_html() {
+    let (config, app) = create_test_app().await;
+    let token = create_token(&config, 1);
+    let hash = upload_image(&app, &token).await;
+
+    let body = json!({ "key": "n1", "name": "n1", "type": "Note", "content": note_snapshot("Hello", &hash) });
+    let id = save(&app, &token, "/modules/document/save", body).await;
+
+    let uri = format!("/modules/document/export?id={}&format=md", id);
+    let (status, headers, bytes) = download(&app, &token, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["content-type"], "text/markdown; charset=utf-8");
+    assert_eq!(headers["content-disposition"], "attachment; filename=\"n1.md\"; filename*=UTF-8''n1.md");
+    let expected = format!("# Hello\n\n## Section\n\n- item\n\n![photo](/modules/blob/download?hash={})\n", hash);
+    assert_eq!(String::from_utf8(bytes.to_vec()).unwrap(), expected);
+
+    // The image is embedded, so that the page is standalone.
+    let uri = format!("/modules/document/export?id={}&format=html", id);
+    let (status, headers, bytes) = download(&app, &token, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["content-type"], "text/html; charset=utf-8");
+    let html = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(html.contains("<title>Hello</title>"), "{}", html);
+    assert!(html.contains("<h2>Section</h2>"), "{}", html);
+    assert!(html.contains("<img src=\"data:image/png;base64,"), "{}", html);
+
+    let (status, _, _) = download(&app, &token, &format!("/modules/document/export?id={}&format=pdf", id)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let other = create_token(&config, 2);
+    let (status, _, _) = download(&app, &other, &format!("/modules/document/export?id={}&format=md", id)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // The board and the invalid content of the note aren't exported.
+    let body = json!({ "key": "b1", "name": "b1", "type": "Board", "content": "{}" });
+    let board_id = save(&app, &token, "/modules/document/save", body).await;
+    let uri = format!("/modules/document/export?id={}&format=md", board_id);
+    assert_eq!(download(&app, &token, &uri).await.0, StatusCode::BAD_REQUEST);
+    let body = json!({ "key": "n2", "name": "n2", "type": "Note", "content": "plain" });
+    let invalid_id = save(&app, &token, "/modules/document/save", body).await;
+    let uri = format!("/modules/document/export?id={}&format=html", invalid_id);
+    assert_eq!(download(&app, &token, &uri).await.0, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_folder_export_zip() {
+    let (config, app) = create_test_app().await;
+    let token = create_token(&config, 1);
+    let hash = upload_image(&app, &token).await;
+
+    let f1 = save(&app, &token, "/modules/folder/save", json!({ "pid": 0, "key": "f1", "name": "Work" })).await;
+    save(&app, &token, "/modules/folder/save", json!({ "pid": f1, "key": "f2", "name": "Sub/Dir" })).await;
+    for (key, folder_key, name, doc_type) in [
+        ("n1", "f1", "Plan", "Note"),
+        ("n2", "f1", "Plan", "Note"),
+        ("n3", "f2", "Deep", "Note"),
+        ("b1", "f1", "Board", "Board"),
+    ] {
+        let content = if doc_type == "Note" { note_snapshot(name, &hash) } else { "{}".to_string() };
+        let body = json!({ "key": key, "name": name, "folderKey": folder_key, "type": doc_type, "content": content });
+        save(&app, &token, "/modules/document/save", body).await;
+    }
+
+    let (status, headers, bytes) = download(&app, &token, &format!("/modules/folder/export?id={}&format=md", f1)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["content-type"], "application/zip");
+    assert_eq!(headers["content-disposition"], "attachment; filename=\"Work.zip\"; filename*=UTF-8''Work.zip");
+
+    let mut reader = zip::ZipArchive::new(Cursor::new(bytes.to_vec())).unwrap();
+    let mut names: Vec<String> = reader.file_names().map(|name| name.to_string()).collect();
+    names.sort();
+    let asset = format!("assets/{}.png", hash);
+    assert_eq!(names, vec!["Work/Plan (2).md", "Work/Plan.md", "Work/Sub_Dir/Deep.md", asset.as_str()]);
+
+    // The images are linked to the assets of the zip, which are relative to the note.
+    let mut deep = String::new();
+    reader.by_name("Work/Sub_Dir/Deep.md").unwrap().read_to_string(&mut deep).unwrap();
+    assert!(deep.contains(&format!("![photo](../../{})", asset)), "{}", deep);
+    let mut image = Vec::new();
+    reader.by_name(&asset).unwrap().read_to_end(&mut image).unwrap();
+    assert_eq!(image, b"\x89PNG\r\n\x1a\nfake image".to_vec());
+
+    let (status, _, bytes) = download(&app, &token, &format!("/modules/folder/export?id={}&format=html", f1)).await;
+    assert_eq!(status, StatusCode::OK);
+    let reader = zip::ZipArchive::new(Cursor::new(bytes.to_vec())).unwrap();
+    assert_eq!(reader.len(), 3);
+
+    let other = create_token(&config, 2);
+    let (status, _, _) = download(&app, &other, &format!("/modules/folder/export?id={}&format=md", f1)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
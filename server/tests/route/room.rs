@@ -0,0 +1,172 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+use std::{ net::SocketAddr, time::Duration };
+
+use axum::{ http::StatusCode, Router };
+use futures::{ SinkExt, StreamExt };
+use serde_json::{ json, Value };
+use tokio::net::{ TcpListener, TcpStream };
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{ client::IntoClientRequest, Message },
+    MaybeTlsStream,
+    WebSocketStream,
+};
+
+use super::{ call, create_test_state_with, create_token, create_users, get, post_json };
+
+type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// The websocket is upgraded by the real connection, which isn't supported by the oneshot of the router.
+async fn serve(app: Router) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    addr
+}
+
+async fn connect(addr: SocketAddr, token: &str, id: i64) -> Result<Client, tokio_tungstenite::tungstenite::Error> {
+    let mut req = format!("ws://{}/modules/room/{}", addr, id).into_client_request().unwrap();
+    req.headers_mut().insert("Authorization", format!("Bearer {}", token).parse().unwrap());
+    connect_async(req).await.map(|(client, _)| client)
+}
+
+async fn send(client: &mut Client, message: Value) {
+    client.send(Message::Text(message.to_string())).await.unwrap();
+}
+
+async fn next_event(client: &mut Client) -> Value {
+    loop {
+        let message = tokio::time::timeout(Duration::from_secs(5), client.next()).await.unwrap();
+        if let Message::Text(text) = message.unwrap().unwrap() {
+            return serde_json::from_str(&text).unwrap();
+        }
+    }
+}
+
+fn rectangle(id: &str, version: i64) -> Value {
+    json!({ "id": id, "type": "rectangle", "x": 0, "y": 0, "width": 10, "height": 10, "version": version })
+}
+
+#[tokio::test]
+async fn test_room_relay_and_persist() {
+    let (config, state, app) = create_test_state_with(|_| {}).await;
+    create_users(&state, &[1, 2, 3]).await;
+    let owner = create_token(&config, 1);
+    let viewer = create_token(&config, 2);
+
+    let content = json!({ "type": "excalidraw", "elements": [rectangle("r1", 1)] }).to_string();
+    let body = json!({ "key": "b1", "name": "board", "type": "Board", "content": content });
+    let (_, resp) = call(&app, &owner, post_json("/modules/document/save", body)).await;
+    let board = resp["id"].as_i64().unwrap();
+    let email = "user2@mywebnote.local";
+    let body = json!({ "resourceType": "document", "resourceId": board, "email": email, "role": "viewer" });
+    let (status, _) = call(&app, &owner, post_json("/modules/acl/grant", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    let body = json!({ "key": "n1", "name": "note", "type": "Note", "content": "hello" });
+    let (_, resp) = call(&app, &owner, post_json("/modules/document/save", body)).await;
+    let note = resp["id"].as_i64().unwrap();
+
+    let addr = serve(app.clone()).await;
+    // Only the boards are collaborated, and the others are not aware of the board.
+    assert!(connect(addr, &owner, note).await.is_err());
+    assert!(connect(addr, &create_token(&config, 3), board).await.is_err());
+
+    let mut first = connect(addr, &owner, board).await.unwrap();
+    let init = next_event(&mut first).await;
+    assert_eq!(init["type"], json!("scene_init"));
+    assert_eq!(init["scene"]["elements"][0]["id"], json!("r1"));
+    let mut second = connect(addr, &viewer, board).await.unwrap();
+    assert_eq!(next_event(&mut second).await["scene"]["elements"].as_array().unwrap().len(), 1);
+    let joined = next_event(&mut first).await;
+    assert_eq!(joined["type"], json!("joined"));
+    assert_eq!(joined["uid"], json!(2));
+
+    // Only the newer elements are merged and relayed.
+    send(&mut first, json!({ "type": "scene_update", "elements": [rectangle("r1", 0), rectangle("r2", 1)] })).await;
+    let updated = next_event(&mut second).await;
+    assert_eq!(updated["type"], json!("scene_update"));
+    assert_eq!(updated["elements"], json!([rectangle("r2", 1)]));
+
+    // The updates of the viewer are dropped, but the cursors are relayed.
+    send(&mut second, json!({ "type": "scene_update", "elements": [rectangle("r3", 1)] })).await;
+    send(&mut second, json!({ "type": "cursor", "pointer": { "x": 1, "y": 2 }, "button": "up" })).await;
+    let cursor = next_event(&mut first).await;
+    assert_eq!(cursor["type"], json!("cursor"));
+    assert_eq!(cursor["pointer"], json!({ "x": 1, "y": 2 }));
+    assert_eq!(cursor["uid"], json!(2));
+
+    // The merged scene is persisted when the last client left.
+    second.close(None).await.unwrap();
+    assert_eq!(next_event(&mut first).await["type"], json!("left"));
+    first.close(None).await.unwrap();
+    let mut persisted = Value::Null;
+    for _ in 0..50 {
+        let (_, resp) = call(&app, &owner, get("/modules/document/query?key=b1")).await;
+        persisted = resp["data"][0].clone();
+        if persisted["version"] == json!(2) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(persisted["version"], json!(2));
+    let scene: Value = serde_json::from_str(persisted["content"].as_str().unwrap()).unwrap();
+    let ids: Vec<&str> = scene["elements"].as_array().unwrap().iter().map(|e| e["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, vec!["r1", "r2"]);
+}
+
+#[tokio::test]
+async fn test_room_rejoin_with_latest_scene() {
+    let (config, state, app) = create_test_state_with(|_| {}).await;
+    create_users(&state, &[1]).await;
+    let owner = create_token(&config, 1);
+    let content = json!({ "type": "excalidraw", "elements": [rectangle("r1", 1)] }).to_string();
+    let body = json!({ "key": "b1", "name": "board", "type": "Board", "content": content });
+    let (_, resp) = call(&app, &owner, post_json("/modules/document/save", body)).await;
+    let board = resp["id"].as_i64().unwrap();
+
+    let addr = serve(app.clone()).await;
+    let mut first = connect(addr, &owner, board).await.unwrap();
+    next_event(&mut first).await;
+    let mut second = connect(addr, &owner, board).await.unwrap();
+    next_event(&mut second).await;
+    send(&mut first, json!({ "type": "scene_update", "elements": [rectangle("r2", 1)] })).await;
+    loop {
+        if next_event(&mut second).await["type"] == json!("scene_update") {
+            break;
+        }
+    }
+
+    // The room joined again right after the last client left has the merged scene, whether it's persisted.
+    first.close(None).await.unwrap();
+    second.close(None).await.unwrap();
+    let mut third = connect(addr, &owner, board).await.unwrap();
+    let init = next_event(&mut third).await;
+    assert_eq!(init["type"], json!("scene_init"));
+    let ids: Vec<&str> = init["scene"]["elements"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, vec!["r1", "r2"]);
+}
@@ -0,0 +1,91 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use axum::http::StatusCode;
+use serde_json::json;
+
+use super::{ call, create_test_app, create_token, get, post_json };
+
+#[tokio::test]
+async fn test_search_documents_ranked_with_highlights() {
+    let (config, app) = create_test_app().await;
+    let token = create_token(&config, 1);
+
+    let board = json!({
+        "elements": [
+            { "type": "rectangle", "id": "r1" },
+            { "type": "text", "id": "t1", "text": "Roadmap of the rust server" }
+        ]
+    });
+    let docs = [
+        json!({ "key": "k1", "name": "Weekly", "type": "Note", "content": "notes about rust and axum" }),
+        json!({ "key": "k2", "name": "Rust tips", "type": "Note", "content": "borrow checker" }),
+        json!({ "key": "k3", "name": "Drawing", "type": "Board", "folderKey": "f1", "content": board.to_string() }),
+        json!({ "key": "k4", "name": "Other", "type": "Note", "content": "nothing related" }),
+    ];
+    let mut ids = Vec::new();
+    for doc in docs {
+        let (status, resp) = call(&app, &token, post_json("/modules/document/save", doc)).await;
+        assert_eq!(status, StatusCode::OK);
+        ids.push(resp["id"].as_i64().unwrap());
+    }
+
+    // The name matched is ranked first, and the board is matched by the texts of it.
+    let (status, resp) = call(&app, &token, get("/modules/document/search?q=rust")).await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    assert_eq!(resp["page"]["total"], json!(3), "{}", resp);
+    let hits = resp["data"].as_array().unwrap();
+    assert_eq!(hits[0]["data"]["key"], json!("k2"));
+    assert_eq!(hits[0]["highlights"][0], json!("<mark>Rust</mark> tips"));
+    assert_eq!(hits[0]["data"]["content"], json!(null));
+    let board_hit = hits.iter().find(|h| h["data"]["key"] == json!("k3")).unwrap();
+    assert_eq!(board_hit["highlights"], json!(["Roadmap of the <mark>rust</mark> server"]));
+
+    // All the terms are required, and filtered by the folder.
+    let (_, resp) = call(&app, &token, get("/modules/document/search?q=rust%20axum")).await;
+    assert_eq!(resp["data"].as_array().unwrap().len(), 1);
+    assert_eq!(resp["data"][0]["data"]["key"], json!("k1"));
+    let (_, resp) = call(&app, &token, get("/modules/document/search?q=rust&folder_key=f1")).await;
+    assert_eq!(resp["data"].as_array().unwrap().len(), 1);
+
+    // The updated content is re-indexed, and the trashed is excluded.
+    let body = json!({ "id": ids[3], "version": 1, "content": "now about rust too" });
+    call(&app, &token, post_json("/modules/document/save", body)).await;
+    call(&app, &token, post_json("/modules/document/delete", json!({ "id": ids[0] }))).await;
+    let (_, resp) = call(&app, &token, get("/modules/document/search?q=rust")).await;
+    let mut keys: Vec<&str> = resp["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|h| h["data"]["key"].as_str().unwrap())
+        .collect();
+    keys.sort();
+    assert_eq!(keys, vec!["k2", "k3", "k4"]);
+
+    // Not visible to the other user.
+    let other = create_token(&config, 2);
+    let (_, resp) = call(&app, &other, get("/modules/document/search?q=rust")).await;
+    assert_eq!(resp["data"], json!([]));
+
+    let (status, _) = call(&app, &token, get("/modules/document/search?q=%22%22")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
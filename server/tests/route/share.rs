@@ -0,0 +1,141 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use axum::{ body::{ self, Body }, http::{ Request, StatusCode }, Router };
+use serde_json::{ json, Value };
+use tower::ServiceExt;
+
+use super::{ call, create_test_app, create_test_app_with, create_token, get, post_json };
+
+// Call the public route without the authorization.
+async fn call_anonymous(app: &Router, uri: &str, password: Option<&str>) -> (StatusCode, Value) {
+    let mut req = Request::get(uri);
+    if let Some(password) = password {
+        req = req.header("X-Share-Password", password);
+    }
+    let resp = app.clone().oneshot(req.body(Body::empty()).unwrap()).await.unwrap();
+    let status = resp.status();
+    let bytes = body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+async fn create_link(app: &Router, token: &str, body: Value) -> (i64, String) {
+    let (status, resp) = call(app, token, post_json("/modules/share/create", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    (resp["id"].as_i64().unwrap(), resp["token"].as_str().unwrap().to_string())
+}
+
+#[tokio::test]
+async fn test_share_link_public_access() {
+    let (config, app) = create_test_app().await;
+    let token = create_token(&config, 1);
+
+    let body = json!({ "key": "b1", "name": "board", "type": "Board", "content": "{}" });
+    let (_, resp) = call(&app, &token, post_json("/modules/document/save", body)).await;
+    let doc = resp["id"].as_i64().unwrap();
+
+    let (id, share) = create_link(&app, &token, json!({ "documentId": doc })).await;
+    let uri = format!("/public/share/{}", share);
+    let (status, resp) = call_anonymous(&app, &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["name"], json!("board"));
+    assert_eq!(resp["type"], json!("Board"));
+    assert_eq!(resp["content"], json!("{}"));
+    assert!(resp.get("owner_uid").is_none());
+    call_anonymous(&app, &uri, None).await;
+    // The concurrent accesses are all counted.
+    futures::future::join_all((0..8).map(|_| call_anonymous(&app, &uri, None))).await;
+
+    // The accesses are recorded, and the modules are still not anonymous.
+    let uri_query = format!("/modules/share/query?document_id={}", doc);
+    let (_, resp) = call(&app, &token, get(&uri_query)).await;
+    assert_eq!(resp["data"][0]["access_count"], json!(10));
+    assert_eq!(resp["data"][0]["protected"], json!(false));
+    let (status, _) = call_anonymous(&app, &uri_query, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // The link of the other user is not revocable, and the document of the other is not shareable.
+    let other = create_token(&config, 2);
+    let (status, _) = call(&app, &other, post_json("/modules/share/revoke", json!({ "id": id }))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = call(&app, &other, post_json("/modules/share/create", json!({ "documentId": doc }))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = call(&app, &token, post_json("/modules/share/revoke", json!({ "id": id }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call_anonymous(&app, &uri, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = call_anonymous(&app, "/public/share/unknown", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_share_link_password_and_expiry() {
+    // The cheap rounds, so that the mismatched are counted within the ttl of the memory cache.
+    let (config, app) = create_test_app_with(|p| {
+        p.webnote.share.password_rounds = 1000;
+    }).await;
+    let token = create_token(&config, 1);
+
+    let body = json!({ "key": "n1", "name": "note", "type": "Note", "content": "hello" });
+    let (_, resp) = call(&app, &token, post_json("/modules/document/save", body)).await;
+    let doc = resp["id"].as_i64().unwrap();
+
+    let (_, share) = create_link(&app, &token, json!({ "documentId": doc, "password": "secret" })).await;
+    let uri = format!("/public/share/{}", share);
+    let (status, _) = call_anonymous(&app, &uri, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = call_anonymous(&app, &uri, Some("wrong")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, resp) = call_anonymous(&app, &uri, Some("secret")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["content"], json!("hello"));
+    let (_, resp) = call(&app, &token, get("/modules/share/query")).await;
+    assert_eq!(resp["data"][0]["protected"], json!(true));
+    assert!(resp["data"][0].get("password").map_or(true, |p| p.is_null()));
+
+    // The link is locked after too many mismatched, even for the right password.
+    for _ in 0..9 {
+        assert_eq!(call_anonymous(&app, &uri, Some("wrong")).await.0, StatusCode::UNAUTHORIZED);
+    }
+    assert_eq!(call_anonymous(&app, &uri, Some("secret")).await.0, StatusCode::TOO_MANY_REQUESTS);
+
+    // The passed expire time is rejected, and the expired link is not accessible.
+    let now = chrono::Utc::now().timestamp_millis();
+    let body = json!({ "documentId": doc, "expireTime": now - 1000 });
+    let (status, _) = call(&app, &token, post_json("/modules/share/create", body)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (_, share) = create_link(&app, &token, json!({ "documentId": doc, "expireTime": now + 300 })).await;
+    let uri = format!("/public/share/{}", share);
+    assert_eq!(call_anonymous(&app, &uri, None).await.0, StatusCode::OK);
+    tokio::time::sleep(std::time::Duration::from_millis(400)).await;
+    assert_eq!(call_anonymous(&app, &uri, None).await.0, StatusCode::NOT_FOUND);
+
+    // The trashed document is not accessible, and the links are deleted with the purged.
+    let (_, share) = create_link(&app, &token, json!({ "documentId": doc })).await;
+    let uri = format!("/public/share/{}", share);
+    call(&app, &token, post_json("/modules/document/delete", json!({ "id": doc }))).await;
+    assert_eq!(call_anonymous(&app, &uri, None).await.0, StatusCode::NOT_FOUND);
+    call(&app, &token, post_json("/modules/document/trash/purge", json!({ "id": doc }))).await;
+    let (_, resp) = call(&app, &token, get("/modules/share/query")).await;
+    assert_eq!(resp["data"], json!([]));
+}
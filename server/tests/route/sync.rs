@@ -0,0 +1,230 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+use std::{ collections::HashMap, net::SocketAddr, sync::Arc, time::Duration };
+
+use axum::{ http::StatusCode, Router };
+use futures::{ SinkExt, StreamExt };
+use serde_json::json;
+use tokio::net::{ TcpListener, TcpStream };
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{ client::IntoClientRequest, Message as WsMessage },
+    MaybeTlsStream,
+    WebSocketStream,
+};
+use yrs::{
+    sync::{ awareness::AwarenessUpdateEntry, AwarenessUpdate, Message, SyncMessage },
+    updates::{ decoder::Decode, encoder::Encode },
+    ArrayPrelim,
+    Doc,
+    GetString,
+    Map,
+    MapPrelim,
+    ReadTxn,
+    StateVector,
+    Text,
+    TextPrelim,
+    TextRef,
+    Transact,
+    Update,
+};
+
+use mywebnote::{
+    store,
+    types::document_update::DocumentUpdate,
+    utils::{ auths::{ AuthUserClaims, SecurityContext }, notes },
+};
+
+use super::{ call, create_test_state_with, create_token, create_users, post_json };
+
+type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+async fn serve(app: Router) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    addr
+}
+
+async fn connect(addr: SocketAddr, token: &str, id: i64) -> Result<Client, tokio_tungstenite::tungstenite::Error> {
+    let mut req = format!("ws://{}/modules/sync/{}", addr, id).into_client_request().unwrap();
+    req.headers_mut().insert("Authorization", format!("Bearer {}", token).parse().unwrap());
+    connect_async(req).await.map(|(client, _)| client)
+}
+
+async fn send(client: &mut Client, message: Message) {
+    client.send(WsMessage::Binary(message.encode_v1())).await.unwrap();
+}
+
+async fn next_message(client: &mut Client) -> Message {
+    loop {
+        let message = tokio::time::timeout(Duration::from_secs(5), client.next()).await.unwrap();
+        if let WsMessage::Binary(data) = message.unwrap().unwrap() {
+            return Message::decode_v1(&data).unwrap();
+        }
+    }
+}
+
+// The BlockSuite page of the title with the paragraph of the text, returns the text of the paragraph.
+fn create_page(doc: &Doc, title: &str, text: &str) -> TextRef {
+    let blocks = doc.get_or_insert_map("blocks");
+    let mut txn = doc.transact_mut();
+    let page = blocks.insert(&mut txn, "page", MapPrelim::default());
+    page.insert(&mut txn, "sys:flavour", "affine:page");
+    page.insert(&mut txn, "sys:children", ArrayPrelim::from(["p1"]));
+    page.insert(&mut txn, "prop:title", TextPrelim::new(title));
+    let paragraph = blocks.insert(&mut txn, "p1", MapPrelim::default());
+    paragraph.insert(&mut txn, "sys:flavour", "affine:paragraph");
+    paragraph.insert(&mut txn, "prop:text", TextPrelim::new(text))
+}
+
+// Change the doc, returns the update of the change.
+fn change(doc: &Doc, f: impl FnOnce(&mut yrs::TransactionMut)) -> Vec<u8> {
+    let mut txn = doc.transact_mut();
+    f(&mut txn);
+    txn.encode_update_v1()
+}
+
+fn state_vector(update: &[u8]) -> StateVector {
+    Update::decode_v1(update).unwrap().state_vector()
+}
+
+fn awareness(client: u64, clock: u32, json: &str) -> Message {
+    let entry = AwarenessUpdateEntry { clock, json: Arc::from(json) };
+    Message::Awareness(AwarenessUpdate { clients: HashMap::from([(client, entry)]) })
+}
+
+#[tokio::test]
+async fn test_sync_and_compact() {
+    let (config, state, app) = create_test_state_with(|p| {
+        p.webnote.sync.compact_threshold = 3;
+    }).await;
+    create_users(&state, &[1, 2]).await;
+    let owner = create_token(&config, 1);
+    let viewer = create_token(&config, 2);
+
+    let content = json!({ "type": "page", "meta": { "id": "n1" }, "blocks": { "flavour": "affine:page" } });
+    let body = json!({ "key": "n1", "name": "note", "type": "Note", "content": content.to_string() });
+    let (_, resp) = call(&app, &owner, post_json("/modules/document/save", body)).await;
+    let note = resp["id"].as_i64().unwrap();
+    let email = "user2@mywebnote.local";
+    let body = json!({ "resourceType": "document", "resourceId": note, "email": email, "role": "viewer" });
+    let (status, _) = call(&app, &owner, post_json("/modules/acl/grant", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    let body = json!({ "key": "b1", "name": "board", "type": "Board", "content": "" });
+    let (_, resp) = call(&app, &owner, post_json("/modules/document/save", body)).await;
+    let board = resp["id"].as_i64().unwrap();
+
+    let addr = serve(app.clone()).await;
+    // Only the notes are synced.
+    assert!(connect(addr, &owner, board).await.is_err());
+
+    // The server starts the sync with the state vector, and answers the missing of the client.
+    let doc = Doc::with_client_id(1);
+    let mut first = connect(addr, &owner, note).await.unwrap();
+    assert_eq!(next_message(&mut first).await, Message::Sync(SyncMessage::SyncStep1(StateVector::default())));
+    send(&mut first, Message::Sync(SyncMessage::SyncStep1(StateVector::default()))).await;
+    match next_message(&mut first).await {
+        Message::Sync(SyncMessage::SyncStep2(update)) => assert!(state_vector(&update).is_empty()),
+        message => panic!("unexpected message: {:?}", message),
+    }
+    let text = create_page(&doc, "Hello", "abc");
+    let created = doc.transact().encode_state_as_update_v1(&StateVector::default());
+    let created_state = doc.transact().state_vector();
+    send(&mut first, Message::Sync(SyncMessage::SyncStep2(created.clone()))).await;
+    send(&mut first, Message::Sync(SyncMessage::SyncStep1(StateVector::default()))).await;
+    match next_message(&mut first).await {
+        Message::Sync(SyncMessage::SyncStep2(update)) => assert_eq!(state_vector(&update), created_state),
+        message => panic!("unexpected message: {:?}", message),
+    }
+
+    let mut second = connect(addr, &viewer, note).await.unwrap();
+    assert_eq!(next_message(&mut second).await, Message::Sync(SyncMessage::SyncStep1(created_state.clone())));
+    assert_eq!(next_message(&mut first).await, Message::AwarenessQuery);
+    // The empty step 2 answered by the viewer isn't denied.
+    let empty = Doc::with_client_id(4).transact().encode_state_as_update_v1(&StateVector::default());
+    send(&mut second, Message::Sync(SyncMessage::SyncStep2(empty))).await;
+
+    // The updates are relayed to the others, and the viewer is denied to update.
+    let appended = change(&doc, |txn| text.push(txn, "d"));
+    send(&mut first, Message::Sync(SyncMessage::Update(appended.clone()))).await;
+    assert_eq!(next_message(&mut second).await, Message::Sync(SyncMessage::Update(appended.clone())));
+    let other = Doc::with_client_id(2);
+    let other_text = other.get_or_insert_text("t");
+    let denied = change(&other, |txn| other_text.push(txn, "x"));
+    send(&mut second, Message::Sync(SyncMessage::Update(denied))).await;
+    assert!(matches!(next_message(&mut second).await, Message::Auth(Some(_))));
+    send(&mut second, awareness(7, 1, "{}")).await;
+    assert_eq!(next_message(&mut first).await, awareness(7, 1, "{}"));
+
+    // The log is compacted into the one merged update on reached the threshold.
+    let deleted = change(&doc, |txn| text.remove_range(txn, 1, 1));
+    send(&mut first, Message::Sync(SyncMessage::Update(deleted.clone()))).await;
+    assert_eq!(next_message(&mut second).await, Message::Sync(SyncMessage::Update(deleted)));
+    let param = DocumentUpdate::of_document(note);
+    let mut updates = Vec::new();
+    for _ in 0..50 {
+        let selected = store::select_all(&state.document_update_repo, &state.config, param.clone(), false);
+        updates = SecurityContext::scope(Some(AuthUserClaims::system(1)), selected).await.unwrap();
+        if updates.len() == 1 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(updates.len(), 1);
+
+    // The missing of the replica is answered from the merged.
+    let replica = Doc::with_client_id(3);
+    replica.transact_mut().apply_update(Update::decode_v1(&created).unwrap()).unwrap();
+    send(&mut second, Message::Sync(SyncMessage::SyncStep1(created_state))).await;
+    match next_message(&mut second).await {
+        Message::Sync(SyncMessage::SyncStep2(update)) => {
+            replica.transact_mut().apply_update(Update::decode_v1(&update).unwrap()).unwrap();
+        }
+        message => panic!("unexpected message: {:?}", message),
+    }
+    assert_eq!(replica.transact().state_vector(), doc.transact().state_vector());
+    assert_eq!(text.get_string(&doc.transact()), "acd");
+
+    // The merged is written back to the content of the note, and the meta of the page is kept.
+    let mut content = json!(null);
+    for _ in 0..50 {
+        let selected = async {
+            let repo = state.document_repo.lock().await;
+            repo.get(&state.config).select_by_id(note).await
+        };
+        let document = SecurityContext::scope(Some(AuthUserClaims::system(1)), selected).await.unwrap();
+        content = serde_json::from_str(document.content.as_deref().unwrap()).unwrap();
+        if content["blocks"].get("children").is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(content["meta"], json!({ "id": "n1" }));
+    let root = notes::to_root_block(&content.to_string()).unwrap();
+    assert_eq!(notes::texts_of(&root), vec!["Hello", "acd"]);
+
+    // The awareness states of the client left are removed from the others.
+    second.close(None).await.unwrap();
+    assert_eq!(next_message(&mut first).await, awareness(7, 2, "null"));
+    first.close(None).await.unwrap();
+}
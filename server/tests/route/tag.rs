@@ -0,0 +1,146 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use axum::{ http::StatusCode, Router };
+use serde_json::{ json, Value };
+
+use super::{ call, create_test_app, create_token, get, post_json };
+
+async fn save(app: &Router, token: &str, uri: &str, body: Value) -> i64 {
+    let (status, resp) = call(app, token, post_json(uri, body)).await;
+    assert_eq!(status, StatusCode::OK);
+    resp["id"].as_i64().unwrap()
+}
+
+async fn query_keys(app: &Router, token: &str, uri: &str) -> Vec<String> {
+    let (status, resp) = call(app, token, get(uri)).await;
+    assert_eq!(status, StatusCode::OK);
+    let mut keys: Vec<String> = resp["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| d["key"].as_str().unwrap().to_string())
+        .collect();
+    keys.sort();
+    keys
+}
+
+async fn query_counts(app: &Router, token: &str) -> Vec<(String, u64)> {
+    let (status, resp) = call(app, token, get("/modules/tag/query")).await;
+    assert_eq!(status, StatusCode::OK);
+    resp["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| (t["name"].as_str().unwrap().to_string(), t["document_count"].as_u64().unwrap()))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_tag_save_attach_and_filter() {
+    let (config, app) = create_test_app().await;
+    let token = create_token(&config, 1);
+
+    let work = save(&app, &token, "/modules/tag/save", json!({ "name": "work" })).await;
+    let todo = save(&app, &token, "/modules/tag/save", json!({ "name": "Todo" })).await;
+    let body = json!({ "name": "work" });
+    let (status, _) = call(&app, &token, post_json("/modules/tag/save", body)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let d1 = save(&app, &token, "/modules/document/save", json!({ "key": "d1", "name": "d1" })).await;
+    let d2 = save(&app, &token, "/modules/document/save", json!({ "key": "d2", "name": "d2" })).await;
+    save(&app, &token, "/modules/document/save", json!({ "key": "d3", "name": "d3" })).await;
+
+    let body = json!({ "documentIds": [d1, d2], "tagIds": [work] });
+    let (status, resp) = call(&app, &token, post_json("/modules/tag/attach", body.clone())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["count"], json!(2));
+    // The already attached are skipped.
+    let (_, resp) = call(&app, &token, post_json("/modules/tag/attach", body)).await;
+    assert_eq!(resp["count"], json!(0));
+    let body = json!({ "documentIds": [d2], "tagIds": [todo] });
+    call(&app, &token, post_json("/modules/tag/attach", body)).await;
+
+    let uri = format!("/modules/document/query?tags={},{}", work, todo);
+    assert_eq!(query_keys(&app, &token, &uri).await, vec!["d1", "d2"]);
+    let uri = format!("/modules/document/query?tags={},{}&tag_match=all", work, todo);
+    assert_eq!(query_keys(&app, &token, &uri).await, vec!["d2"]);
+    let uri = format!("/modules/document/query?tags={}&key=d1", work);
+    assert_eq!(query_keys(&app, &token, &uri).await, vec!["d1"]);
+    let (status, _) = call(&app, &token, get("/modules/document/query?tags=x")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // The tags of the document, and the counts exclude the trashed documents.
+    let uri = format!("/modules/tag/query?document_id={}", d1);
+    let (_, resp) = call(&app, &token, get(&uri)).await;
+    assert_eq!(resp["data"][0]["name"], json!("work"));
+    assert_eq!(resp["data"].as_array().unwrap().len(), 1);
+    call(&app, &token, post_json("/modules/document/delete", json!({ "id": d1 }))).await;
+    assert_eq!(query_counts(&app, &token).await, vec![("Todo".to_string(), 1), ("work".to_string(), 1)]);
+
+    // Rename and detach.
+    save(&app, &token, "/modules/tag/save", json!({ "id": todo, "name": "later" })).await;
+    let body = json!({ "documentIds": [d2], "tagIds": [todo] });
+    let (_, resp) = call(&app, &token, post_json("/modules/tag/detach", body)).await;
+    assert_eq!(resp["count"], json!(1));
+    assert_eq!(query_counts(&app, &token).await, vec![("later".to_string(), 0), ("work".to_string(), 1)]);
+
+    // The tags and documents of the other user are not visible.
+    let other = create_token(&config, 2);
+    assert!(query_counts(&app, &other).await.is_empty());
+    let body = json!({ "documentIds": [d2], "tagIds": [work] });
+    let (status, _) = call(&app, &other, post_json("/modules/tag/attach", body)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let uri = format!("/modules/document/query?tags={}", work);
+    assert!(query_keys(&app, &other, &uri).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_tag_merge_and_delete() {
+    let (config, app) = create_test_app().await;
+    let token = create_token(&config, 1);
+
+    let a = save(&app, &token, "/modules/tag/save", json!({ "name": "a" })).await;
+    let b = save(&app, &token, "/modules/tag/save", json!({ "name": "b" })).await;
+    let c = save(&app, &token, "/modules/tag/save", json!({ "name": "c" })).await;
+    let d1 = save(&app, &token, "/modules/document/save", json!({ "key": "d1", "name": "d1" })).await;
+    let d2 = save(&app, &token, "/modules/document/save", json!({ "key": "d2", "name": "d2" })).await;
+    call(&app, &token, post_json("/modules/tag/attach", json!({ "documentIds": [d1], "tagIds": [a, b] }))).await;
+    call(&app, &token, post_json("/modules/tag/attach", json!({ "documentIds": [d2], "tagIds": [b] }))).await;
+
+    // The documents of the sources are tagged with the target once.
+    let body = json!({ "sourceIds": [b, c], "targetId": a });
+    let (status, resp) = call(&app, &token, post_json("/modules/tag/merge", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["count"], json!(2));
+    assert_eq!(query_counts(&app, &token).await, vec![("a".to_string(), 2)]);
+    let uri = format!("/modules/document/query?tags={}", a);
+    assert_eq!(query_keys(&app, &token, &uri).await, vec!["d1", "d2"]);
+
+    let (status, resp) = call(&app, &token, post_json("/modules/tag/delete", json!({ "id": a }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["count"], json!(1));
+    assert!(query_counts(&app, &token).await.is_empty());
+    assert!(query_keys(&app, &token, &uri).await.is_empty());
+    let (status, _) = call(&app, &token, post_json("/modules/tag/delete", json!({ "id": a }))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,119 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use std::time::Duration;
+
+use axum::{ body::{ self, Bytes }, http::{ HeaderMap, StatusCode }, Router };
+use mywebnote::handler::thumbnail::THUMBNAIL_KEY_PREFIX;
+use serde_json::{ json, Value };
+use tower::ServiceExt;
+
+use super::{ call, create_test_state_with, create_token, get, post_json };
+
+async fn fetch(app: &Router, token: &str, uri: &str, etag: Option<&str>) -> (StatusCode, HeaderMap, Bytes) {
+    let mut req = get(uri);
+    req.headers_mut().insert("Authorization", format!("Bearer {}", token).parse().unwrap());
+    if let Some(etag) = etag {
+        req.headers_mut().insert("If-None-Match", etag.parse().unwrap());
+    }
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let (status, headers) = (resp.status(), resp.headers().clone());
+    (status, headers, body::to_bytes(resp.into_body(), usize::MAX).await.unwrap())
+}
+
+fn board_scene(text: &str) -> String {
+    json!({
+        "type": "excalidraw",
+        "elements": [
+            { "id": "r1", "type": "rectangle", "x": 0, "y": 0, "width": 200, "height": 100 },
+            { "id": "e1", "type": "ellipse", "x": 300, "y": 0, "width": 100, "height": 100 },
+            { "id": "a1", "type": "arrow", "x": 200, "y": 50, "points": [[0, 0], [100, 0]] },
+            { "id": "t1", "type": "text", "x": 10, "y": 10, "width": 100, "height": 25, "text": text }
+        ]
+    }).to_string()
+}
+
+async fn save(app: &Router, token: &str, body: Value) -> i64 {
+    let (status, resp) = call(app, token, post_json("/modules/document/save", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    resp["id"].as_i64().unwrap()
+}
+
+#[tokio::test]
+async fn test_board_thumbnail() {
+    let (config, state, app) = create_test_state_with(|_| {}).await;
+    let token = create_token(&config, 1);
+    let body = json!({ "key": "b1", "name": "b1", "type": "Board", "content": board_scene("hello") });
+    let id = save(&app, &token, body).await;
+
+    // The thumbnails are rendered and cached in the background when saved.
+    let cache = state.string_cache.get(&config);
+    let key = format!("{}{}:1:png", THUMBNAIL_KEY_PREFIX, id);
+    let mut cached = None;
+    for _ in 0..50 {
+        cached = cache.get(key.to_owned()).await.unwrap();
+        if cached.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(cached.is_some());
+
+    let uri = format!("/modules/document/thumbnail?id={}&format=svg", id);
+    let (status, headers, bytes) = fetch(&app, &token, &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["content-type"], "image/svg+xml");
+    assert_eq!(headers["etag"], "\"1\"");
+    let svg = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(svg.contains("<rect x=\"0\" y=\"0\" width=\"200\" height=\"100\""), "{}", svg);
+    assert!(svg.contains("<ellipse"), "{}", svg);
+    assert!(svg.contains(">hello</tspan>"), "{}", svg);
+
+    let uri = format!("/modules/document/thumbnail?id={}", id);
+    let (status, headers, bytes) = fetch(&app, &token, &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["content-type"], "image/png");
+    assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+    // The width of the image header is fitted to the max width.
+    assert_eq!(u32::from_be_bytes(bytes[16..20].try_into().unwrap()), 320);
+    let (status, _, bytes) = fetch(&app, &token, &uri, Some("\"1\"")).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    assert!(bytes.is_empty());
+
+    // The thumbnail of the updated board is rendered by the new version.
+    let body = json!({ "id": id, "version": 1, "content": board_scene("world") });
+    save(&app, &token, body).await;
+    let uri = format!("/modules/document/thumbnail?id={}&format=svg", id);
+    let (status, headers, bytes) = fetch(&app, &token, &uri, Some("\"1\"")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["etag"], "\"2\"");
+    assert!(String::from_utf8(bytes.to_vec()).unwrap().contains(">world</tspan>"));
+
+    let other = create_token(&config, 2);
+    assert_eq!(fetch(&app, &other, &uri, None).await.0, StatusCode::NOT_FOUND);
+    let uri = format!("/modules/document/thumbnail?id={}&format=gif", id);
+    assert_eq!(fetch(&app, &token, &uri, None).await.0, StatusCode::BAD_REQUEST);
+    let body = json!({ "key": "n1", "name": "n1", "type": "Note", "content": "" });
+    let note_id = save(&app, &token, body).await;
+    let uri = format!("/modules/document/thumbnail?id={}", note_id);
+    assert_eq!(fetch(&app, &token, &uri, None).await.0, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,124 @@
+/*
+ * SPDX-License-Identifier: GNU GENERAL PUBLIC LICENSE Version 3
+ *
+ * Copyleft (c) 2024 James Wong. This file is part of James Wong.
+ * is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * James Wong is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with James Wong.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * IMPORTANT: Any software that fully or partially contains or uses materials
+ * covered by this license must also be released under the GNU GPL license.
+ * This includes modifications and derived works.
+ */
+
+use axum::{ http::StatusCode, Router };
+use serde_json::{ json, Value };
+
+use mywebnote::handler::trash::purge_expired;
+
+use super::{ call, create_test_state_with, create_token, get, post_json };
+
+async fn save(app: &Router, token: &str, uri: &str, body: Value) -> i64 {
+    let (status, resp) = call(app, token, post_json(uri, body)).await;
+    assert_eq!(status, StatusCode::OK);
+    resp["id"].as_i64().unwrap()
+}
+
+async fn query_keys(app: &Router, token: &str, uri: &str) -> Vec<String> {
+    let (status, resp) = call(app, token, get(uri)).await;
+    assert_eq!(status, StatusCode::OK);
+    let mut keys: Vec<String> = resp["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| d["key"].as_str().unwrap().to_string())
+        .collect();
+    keys.sort();
+    keys
+}
+
+#[tokio::test]
+async fn test_trash_cascade_restore_and_purge() {
+    let (config, _, app) = create_test_state_with(|_| {}).await;
+    let token = create_token(&config, 1);
+
+    let f1 = save(&app, &token, "/modules/folder/save", json!({ "pid": 0, "key": "f1", "name": "f1" })).await;
+    let f2 = save(&app, &token, "/modules/folder/save", json!({ "pid": f1, "key": "f2", "name": "f2" })).await;
+    let body = json!({ "key": "d1", "name": "d1", "folderKey": "f1", "content": "c1" });
+    save(&app, &token, "/modules/document/save", body).await;
+    let body = json!({ "key": "d2", "name": "d2", "folderKey": "f2", "content": "c2" });
+    let d2 = save(&app, &token, "/modules/document/save", body).await;
+    save(&app, &token, "/modules/document/save", json!({ "key": "d3", "name": "d3" })).await;
+
+    // Delete the folder cascades to the sub folders and documents.
+    let (status, resp) = call(&app, &token, post_json("/modules/folder/delete", json!({ "id": f1 }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["count"], json!(4));
+    assert_eq!(query_keys(&app, &token, "/modules/document/query").await, vec!["d3"]);
+    assert!(query_keys(&app, &token, "/modules/folder/query").await.is_empty());
+    assert_eq!(query_keys(&app, &token, "/modules/document/trash/query").await, vec!["d1", "d2"]);
+    assert_eq!(query_keys(&app, &token, "/modules/folder/trash/query").await, vec!["f1", "f2"]);
+
+    // The trashed document is not visible to the other user.
+    let other = create_token(&config, 2);
+    assert!(query_keys(&app, &other, "/modules/document/trash/query").await.is_empty());
+    let body = json!({ "id": d2 });
+    let (status, _) = call(&app, &other, post_json("/modules/document/trash/restore", body)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Restore the document also restores the folder ancestry of it.
+    let body = json!({ "id": d2 });
+    let (status, resp) = call(&app, &token, post_json("/modules/document/trash/restore", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["count"], json!(1));
+    assert_eq!(query_keys(&app, &token, "/modules/folder/query").await, vec!["f1", "f2"]);
+    assert_eq!(query_keys(&app, &token, "/modules/document/query").await, vec!["d2", "d3"]);
+    assert_eq!(query_keys(&app, &token, "/modules/document/trash/query").await, vec!["d1"]);
+
+    // Restore the folder with the contents trashed together with it, the earlier trashed is left.
+    call(&app, &token, post_json("/modules/folder/delete", json!({ "id": f2 }))).await;
+    let body = json!({ "id": f2 });
+    let (status, resp) = call(&app, &token, post_json("/modules/folder/trash/restore", body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["count"], json!(2));
+    assert_eq!(query_keys(&app, &token, "/modules/document/query").await, vec!["d2", "d3"]);
+
+    // Purge the folder permanently deletes the trashed contents, and only the trashed is purgeable.
+    let (status, _) = call(&app, &token, post_json("/modules/folder/trash/purge", json!({ "id": f2 }))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    call(&app, &token, post_json("/modules/folder/delete", json!({ "id": f1 }))).await;
+    let (status, resp) = call(&app, &token, post_json("/modules/folder/trash/purge", json!({ "id": f1 }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(resp["count"], json!(4));
+    assert!(query_keys(&app, &token, "/modules/document/trash/query").await.is_empty());
+    assert!(query_keys(&app, &token, "/modules/folder/trash/query").await.is_empty());
+    let uri = format!("/modules/document/revision/query?document_id={}", d2);
+    let (_, resp) = call(&app, &token, get(&uri)).await;
+    assert_eq!(resp["data"], json!([]));
+}
+
+#[tokio::test]
+async fn test_trash_purge_expired() {
+    let (config, state, app) = create_test_state_with(|p| {
+        p.webnote.trash.retention_days = 0;
+    }).await;
+    let token = create_token(&config, 1);
+
+    let id = save(&app, &token, "/modules/document/save", json!({ "key": "d1", "name": "d1" })).await;
+    save(&app, &token, "/modules/document/save", json!({ "key": "d2", "name": "d2" })).await;
+    call(&app, &token, post_json("/modules/document/delete", json!({ "id": id }))).await;
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+    assert_eq!(purge_expired(&state).await.unwrap(), 1);
+    assert!(query_keys(&app, &token, "/modules/document/trash/query").await.is_empty());
+    assert_eq!(query_keys(&app, &token, "/modules/document/query").await, vec!["d2"]);
+}